@@ -1,6 +1,9 @@
 use ffmpeg_sidecar::{
   command::ffmpeg_is_installed,
-  download::{check_latest_version, download_ffmpeg_package, ffmpeg_download_url, unpack_ffmpeg},
+  download::{
+    download_ffmpeg_package, fetch_manifest_version, install_from_archive, resolve_download_url,
+    BuildVariant,
+  },
   paths::sidecar_dir,
   version::ffmpeg_version,
 };
@@ -19,14 +22,14 @@ fn main() -> anyhow::Result<()> {
 
   // Checking the version number before downloading is actually not necessary,
   // but it's a good way to check that the download URL is correct.
-  match check_latest_version() {
+  match fetch_manifest_version() {
     Ok(version) => println!("Latest available version: {}", version),
     Err(_) => println!("Skipping version check on this platform."),
   }
 
   // These defaults will automatically select the correct download URL for your
   // platform.
-  let download_url = ffmpeg_download_url()?;
+  let download_url = resolve_download_url(BuildVariant::Essentials)?;
   let destination = sidecar_dir()?;
 
   // By default the download will use a `curl` command. You could also write
@@ -35,9 +38,12 @@ fn main() -> anyhow::Result<()> {
   let archive_path = download_ffmpeg_package(download_url, &destination)?;
   println!("Downloaded package: {:?}", archive_path);
 
-  // Extraction uses `tar` on all platforms (available in Windows since version 1803)
+  // The archive could also come from elsewhere, e.g. downloaded by an
+  // installer. Extraction uses `tar` or `unzip` (PowerShell on Windows), and
+  // the binaries are checked to run.
   println!("Extracting...");
-  unpack_ffmpeg(&archive_path, &destination)?;
+  let ffmpeg = install_from_archive(&archive_path, &destination)?;
+  println!("Installed: {:?}", ffmpeg);
 
   // Use the freshly installed FFmpeg to check the version number
   let version = ffmpeg_version()?;
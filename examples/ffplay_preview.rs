@@ -1,14 +1,11 @@
-use std::{
-  io::{Read, Write},
-  process::{Command, Stdio},
-};
+use std::io::{Read, Write};
 
-use ffmpeg_sidecar::command::FfmpegCommand;
+use ffmpeg_sidecar::{command::FfmpegCommand, ffplay::FfplayCommand};
 
 /// Pipe from ffmpeg to ffplay for debugging purposes
 ///
 /// ```console
-/// cargo run --example ffplay_preview
+/// cargo run --example ffplay_preview --features ffplay
 /// ```
 fn main() {
   let mut ffmpeg = FfmpegCommand::new()
@@ -21,14 +18,15 @@ fn main() {
     .spawn()
     .unwrap();
 
-  let mut ffplay = Command::new("ffplay")
-    .args("-i -".split(' '))
-    .stdin(Stdio::piped())
+  let mut ffplay = FfplayCommand::new()
+    .window_title("ffmpeg-sidecar preview")
+    .autoexit()
+    .pipe_stdin()
     .spawn()
     .unwrap();
 
   let mut ffmpeg_stdout = ffmpeg.take_stdout().unwrap();
-  let mut ffplay_stdin = ffplay.stdin.take().unwrap();
+  let mut ffplay_stdin = ffplay.take_stdin().unwrap();
 
   // pipe from ffmpeg stdout to ffplay stdin
   let buf = &mut [0u8; 4096];
@@ -37,6 +35,11 @@ fn main() {
     if n == 0 {
       break;
     }
-    ffplay_stdin.write_all(&buf[..n]).unwrap();
+    if ffplay_stdin.write_all(&buf[..n]).is_err() {
+      break; // ffplay window was closed
+    }
   }
+
+  ffmpeg.kill().ok();
+  ffplay.wait().unwrap();
 }
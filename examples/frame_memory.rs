@@ -0,0 +1,41 @@
+use ffmpeg_sidecar::command::FfmpegCommand;
+use std::time::Duration;
+
+/// Measure the peak memory of reading 4K frames with a slow consumer, for a
+/// given `frame_buffer_count` (2 by default). A 3840x2160 rgb24 frame takes
+/// 24.9 MB, so the peak grows by about that much per buffered frame; compare
+/// e.g. a count of 2 with 100, which ffmpeg can fill up while the consumer
+/// lags behind. Reads the peak from `/proc`, so it only runs on Linux.
+///
+/// ```console
+/// cargo run --release --example frame_memory -- 2
+/// cargo run --release --example frame_memory -- 100
+/// ```
+fn main() {
+  let count = std::env::args()
+    .nth(1)
+    .map_or(2, |count| count.parse().expect("a frame count"));
+
+  let mut child = FfmpegCommand::new()
+    .format("lavfi")
+    .input("testsrc2=size=3840x2160:rate=30:duration=10")
+    .rawvideo()
+    .spawn()
+    .unwrap();
+  child.frame_buffer_count(count);
+  let frames = child
+    .iter()
+    .unwrap()
+    .filter_frames()
+    .inspect(|_| std::thread::sleep(Duration::from_millis(20)))
+    .count();
+  child.wait().unwrap();
+
+  let status = std::fs::read_to_string("/proc/self/status").unwrap();
+  let peak = status
+    .lines()
+    .find_map(|line| line.strip_prefix("VmHWM:"))
+    .unwrap()
+    .trim();
+  println!("{frames} frames with frame_buffer_count({count}), peak RSS {peak}");
+}
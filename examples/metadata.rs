@@ -16,7 +16,7 @@ fn main() {
     .unwrap()
     .for_each(|e| {
       match e {
-        FfmpegEvent::Progress(FfmpegProgress { frame, .. }) =>
+        FfmpegEvent::Progress(FfmpegProgress { frame: Some(frame), .. }) =>
           println!("Current frame: {frame}"),
         FfmpegEvent::Log(_level, msg) =>
           println!("[ffmpeg] {msg}"),
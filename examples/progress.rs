@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use ffmpeg_sidecar::command::FfmpegCommand;
 
 /// Output progress events from a standard ffmpeg command
@@ -21,5 +23,11 @@ fn main() {
     .iter()
     .unwrap()
     .filter_progress()
-    .for_each(|progress| println!("{}%", (progress.frame * 100) / total_frames));
+    .filter_map(|progress| {
+      progress.percent(
+        Some(total_frames),
+        Some(Duration::from_secs(duration as u64).into()),
+      )
+    })
+    .for_each(|percent| println!("{percent:.0}%"));
 }
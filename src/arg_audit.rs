@@ -0,0 +1,390 @@
+//! Catching the options added with [`arg`](crate::command::FfmpegCommand::arg)
+//! or [`args`](crate::command::FfmpegCommand::args) which conflict with
+//! other options of the command, e.g. a `-b:v` copied from a snippet next to
+//! a [`crf`](crate::command::FfmpegCommand::crf). ffmpeg keeps the last of
+//! repeated options and some exclusive ones without saying so, so one of
+//! them is silently ignored.
+//!
+//! Conflicts are reported as hints when spawning, or fail the spawn with
+//! [`FfmpegCommand::strict`](crate::command::FfmpegCommand::strict).
+
+use std::fmt;
+
+/// Options which can be given several times for the same file, each adding
+/// to the previous ones.
+const REPEATABLE_OPTIONS: &[&str] = &[
+  "map",
+  "metadata",
+  "attach",
+  "filter_complex",
+  "program",
+  "stream_group",
+];
+
+/// Options which apply to the whole command rather than to the next file.
+const GLOBAL_OPTIONS: &[&str] = &[
+  "y",
+  "n",
+  "loglevel",
+  "hide_banner",
+  "filter_complex",
+  "filter_complex_threads",
+  "progress",
+  "stats",
+  "nostats",
+  "stats_period",
+  "stdin",
+  "nostdin",
+  "xerror",
+  "report",
+  "benchmark",
+  "init_hw_device",
+  "filter_hw_device",
+  "max_error_rate",
+  "abort_on",
+];
+
+/// Options which don't take a value.
+const FLAG_OPTIONS: &[&str] = &[
+  "y",
+  "n",
+  "an",
+  "vn",
+  "sn",
+  "dn",
+  "shortest",
+  "hide_banner",
+  "xerror",
+  "stdin",
+  "nostdin",
+  "stats",
+  "nostats",
+  "re",
+  "copyts",
+  "start_at_zero",
+  "accurate_seek",
+  "noaccurate_seek",
+  "autorotate",
+  "noautorotate",
+  "autoscale",
+  "noautoscale",
+  "benchmark",
+  "benchmark_all",
+  "ignore_unknown",
+  "copy_unknown",
+  "report",
+  "dump",
+  "hex",
+  "debug_ts",
+  "bitexact",
+  "fix_sub_duration",
+];
+
+/// Short forms of options, and the option with a stream specifier they
+/// stand for.
+const OPTION_ALIASES: &[(&str, &str)] = &[
+  ("codec", "c"),
+  ("vcodec", "c:v"),
+  ("acodec", "c:a"),
+  ("scodec", "c:s"),
+  ("dcodec", "c:d"),
+  ("vf", "filter:v"),
+  ("af", "filter:a"),
+  ("vb", "b:v"),
+  ("ab", "b:a"),
+  ("vframes", "frames:v"),
+  ("aframes", "frames:a"),
+  ("dframes", "frames:d"),
+  ("vtag", "tag:v"),
+  ("atag", "tag:a"),
+  ("vbsf", "bsf:v"),
+  ("absf", "bsf:a"),
+  ("qscale", "q"),
+  ("lavfi", "filter_complex"),
+  ("v", "loglevel"),
+];
+
+/// A pair of options of which ffmpeg, or the encoder, only uses one when
+/// both are set for the same file and streams.
+///
+/// Options are named without their leading `-`. A stream specifier like
+/// `c:a` only matches the option for those streams, while `b` matches `-b`
+/// for any streams, like `-b:v` or `-b:a:0`; both options must apply to the
+/// same streams.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CollisionRule {
+  pub options: &'static [&'static str],
+  pub conflicts_with: &'static [&'static str],
+  /// What happens when both are set, like `ffmpeg uses -t and ignores -to`.
+  pub reason: &'static str,
+}
+
+/// The conflicts checked by [`audit_args`] by default. More are added with
+/// [`FfmpegCommand::collision_rule`](crate::command::FfmpegCommand::collision_rule).
+pub const DEFAULT_COLLISIONS: &[CollisionRule] = &[
+  CollisionRule {
+    options: &["crf"],
+    conflicts_with: &["b", "qp"],
+    reason: "encoders like libx264 use either a constant quality or a bitrate",
+  },
+  CollisionRule {
+    options: &["qp"],
+    conflicts_with: &["b"],
+    reason: "encoders like libx264 use either a constant quantizer or a bitrate",
+  },
+  CollisionRule {
+    options: &["y"],
+    conflicts_with: &["n"],
+    reason: "ffmpeg can't both overwrite and never overwrite the outputs",
+  },
+  CollisionRule {
+    options: &["t"],
+    conflicts_with: &["to"],
+    reason: "ffmpeg uses -t and ignores -to",
+  },
+  CollisionRule {
+    options: &["an"],
+    conflicts_with: &["c:a", "b:a", "filter:a"],
+    reason: "-an drops the audio the other option applies to",
+  },
+  CollisionRule {
+    options: &["vn"],
+    conflicts_with: &["c:v", "b:v", "filter:v", "crf"],
+    reason: "-vn drops the video the other option applies to",
+  },
+];
+
+/// How an option was added to a command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ArgSource {
+  /// By a typed builder method, like `crf` or `seek`.
+  Typed,
+  /// With `arg` or `args`.
+  Raw,
+}
+
+impl fmt::Display for ArgSource {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      ArgSource::Typed => f.write_str("typed builder"),
+      ArgSource::Raw => f.write_str("raw args"),
+    }
+  }
+}
+
+/// An option of a command, as written.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AuditedOption {
+  /// Index of the option in the arguments.
+  pub index: usize,
+  /// The option with its stream specifier, like `-b:v`.
+  pub option: String,
+  pub value: Option<String>,
+  pub source: ArgSource,
+}
+
+impl fmt::Display for AuditedOption {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match &self.value {
+      Some(value) => write!(f, "`{} {value}` ({})", self.option, self.source),
+      None => write!(f, "`{}` ({})", self.option, self.source),
+    }
+  }
+}
+
+/// Two options of a command of which only one takes effect, at least one of
+/// them added with `arg` or `args`.
+///
+/// Returned (through `io::Error`, with the kind `InvalidInput`) by
+/// [`FfmpegCommand::spawn`](crate::command::FfmpegCommand::spawn) with
+/// [`strict`](crate::command::FfmpegCommand::strict).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ArgConflict {
+  pub first: AuditedOption,
+  pub second: AuditedOption,
+  pub reason: String,
+}
+
+impl fmt::Display for ArgConflict {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "{} conflicts with {}: {}",
+      self.first, self.second, self.reason
+    )
+  }
+}
+
+impl std::error::Error for ArgConflict {}
+
+/// An option, with its name and stream specifier after resolving aliases,
+/// and the file it applies to (`None` for global options).
+struct ParsedOption {
+  audited: AuditedOption,
+  name: String,
+  specifier: String,
+  scope: Option<usize>,
+}
+
+/// The conflicts between the options of `args`, where `raw` holds the
+/// indices of the arguments added with `arg` or `args`. Conflicts between
+/// two typed options aren't reported.
+///
+/// An option conflicts with the same option for the same file and streams
+/// (apart from repeatable ones like `-map`), unless both have the same
+/// value, and with the options `rules` pair it with.
+///
+/// ```rust
+/// use ffmpeg_sidecar::arg_audit::{audit_args, DEFAULT_COLLISIONS};
+///
+/// let args = ["-i", "in.mp4", "-crf:v", "23", "-b:v", "5M", "-b:a", "128k", "out.mp4"];
+/// let conflicts = audit_args(&args, &[4, 5], DEFAULT_COLLISIONS);
+/// assert_eq!(
+///   conflicts[0].to_string(),
+///   "`-crf:v 23` (typed builder) conflicts with `-b:v 5M` (raw args): \
+///    encoders like libx264 use either a constant quality or a bitrate"
+/// );
+/// assert_eq!(conflicts.len(), 1);
+/// ```
+pub fn audit_args<S: AsRef<str>>(
+  args: &[S],
+  raw: &[usize],
+  rules: &[CollisionRule],
+) -> Vec<ArgConflict> {
+  let options = parse_options(args, raw);
+  let mut conflicts = Vec::new();
+  for (i, first) in options.iter().enumerate() {
+    for second in &options[i + 1..] {
+      let both_typed =
+        first.audited.source == ArgSource::Typed && second.audited.source == ArgSource::Typed;
+      if both_typed || first.scope != second.scope {
+        continue;
+      }
+      if let Some(reason) = conflict_reason(first, second, rules) {
+        conflicts.push(ArgConflict {
+          first: first.audited.clone(),
+          second: second.audited.clone(),
+          reason,
+        });
+      }
+    }
+  }
+  conflicts
+}
+
+fn conflict_reason(
+  first: &ParsedOption,
+  second: &ParsedOption,
+  rules: &[CollisionRule],
+) -> Option<String> {
+  if first.name == second.name
+    && first.specifier == second.specifier
+    && first.audited.value != second.audited.value
+    && !REPEATABLE_OPTIONS.contains(&first.name.as_str())
+  {
+    return Some(format!(
+      "ffmpeg uses only the last -{}",
+      join_specifier(&first.name, &first.specifier)
+    ));
+  }
+  if !overlap(&first.specifier, &second.specifier) {
+    return None;
+  }
+  rules.iter().find_map(|rule| {
+    let pairs = |a: &ParsedOption, b: &ParsedOption| {
+      rule.options.iter().any(|option| matches(a, option))
+        && rule.conflicts_with.iter().any(|option| matches(b, option))
+    };
+    (pairs(first, second) || pairs(second, first)).then(|| rule.reason.to_string())
+  })
+}
+
+/// Whether `option` is the option of a rule, like `b` or `c:a`.
+fn matches(option: &ParsedOption, rule_option: &str) -> bool {
+  let (name, specifier) = split_specifier(rule_option);
+  option.name == name
+    && (specifier.is_empty() || starts_with_specifier(&option.specifier, specifier))
+}
+
+/// Whether two stream specifiers can select the same stream: either selects
+/// every stream, or one narrows the other, like `v` and `v:0`.
+fn overlap(a: &str, b: &str) -> bool {
+  a.is_empty() || b.is_empty() || starts_with_specifier(a, b) || starts_with_specifier(b, a)
+}
+
+fn starts_with_specifier(specifier: &str, prefix: &str) -> bool {
+  specifier == prefix
+    || specifier
+      .strip_prefix(prefix)
+      .is_some_and(|rest| rest.starts_with(':'))
+}
+
+fn split_specifier(option: &str) -> (&str, &str) {
+  option.split_once(':').unwrap_or((option, ""))
+}
+
+fn join_specifier(name: &str, specifier: &str) -> String {
+  match specifier.is_empty() {
+    true => name.to_string(),
+    false => format!("{name}:{specifier}"),
+  }
+}
+
+/// The options of `args`, each applying to the next input or output.
+fn parse_options<S: AsRef<str>>(args: &[S], raw: &[usize]) -> Vec<ParsedOption> {
+  let mut options = Vec::new();
+  let mut scope = 0;
+  let mut i = 0;
+  while i < args.len() {
+    let arg = args[i].as_ref();
+    let Some(option) = arg.strip_prefix('-').filter(|option| !option.is_empty()) else {
+      // An output
+      scope += 1;
+      i += 1;
+      continue;
+    };
+    if option == "i" {
+      scope += 1;
+      i += 2;
+      continue;
+    }
+
+    let (name, specifier) = split_specifier(option);
+    let (name, specifier) = match OPTION_ALIASES.iter().find(|(alias, _)| *alias == name) {
+      Some((_, canonical)) => {
+        let (canonical, canonical_specifier) = split_specifier(canonical);
+        let specifier = match (canonical_specifier, specifier) {
+          (outer, "") => outer.to_string(),
+          ("", inner) => inner.to_string(),
+          (outer, inner) => format!("{outer}:{inner}"),
+        };
+        (canonical.to_string(), specifier)
+      }
+      None => (name.to_string(), specifier.to_string()),
+    };
+    let value = match FLAG_OPTIONS.contains(&name.as_str()) {
+      true => None,
+      false => args.get(i + 1).map(|value| value.as_ref().to_string()),
+    };
+    let next = i + 1 + usize::from(value.is_some());
+    options.push(ParsedOption {
+      audited: AuditedOption {
+        index: i,
+        option: arg.to_string(),
+        value,
+        source: match raw.contains(&i) {
+          true => ArgSource::Raw,
+          false => ArgSource::Typed,
+        },
+      },
+      scope: match GLOBAL_OPTIONS.contains(&name.as_str()) {
+        true => None,
+        false => Some(scope),
+      },
+      name,
+      specifier,
+    });
+    i = next;
+  }
+  options
+}
@@ -0,0 +1,2419 @@
+//! The arguments of an ffmpeg command, separate from the process running
+//! it. See [`FfmpegArgs`].
+
+#[cfg(feature = "process")]
+use std::ops::Range;
+use std::{
+  ffi::{OsStr, OsString},
+  time::Duration,
+};
+
+use crate::{
+  arg_audit::{audit_args, ArgConflict, CollisionRule, DEFAULT_COLLISIONS},
+  audio::{ChannelLayout, ResampleOptions, SampleFormat},
+  bsf::Bsf,
+  captions::caption_source,
+  color::{hdr_to_sdr_filter, TonemapOptions},
+  compatibility::CompatibilityTarget,
+  container::ContainerFormat,
+  cpu_budget::CpuBudget,
+  drawtext::{drawtext_filter, TextOverlay},
+  event::AVStream,
+  extract::StreamKind,
+  faststart::web_movflags,
+  frame_rate::{CfrStrategy, Rate},
+  gapless::{gapless_args, GaplessWarning},
+  geometry::{crop_filter, fit_filter, FitMode, Rect},
+  grid::{
+    grid_filter, DurationPolicy, GridAudio, GridInput, GridOptions, GRID_AUDIO_LABEL,
+    GRID_OUTPUT_LABEL,
+  },
+  input::InputOptions,
+  language::{Language, LanguageSelection},
+  metadata_policy::MetadataPolicy,
+  mix::{mix_filter, AudioMixInput, MixOptions, TooFewMixInputs, MIX_OUTPUT_LABEL},
+  network::InputNetworkOptions,
+  overlay::{overlay_filter, OverlayOptions, OVERLAY_OUTPUT_LABEL},
+  program::Program,
+  reproducible::ReproducibleOptions,
+  sanitize::PathRole,
+  segment::SegmentOptions,
+  timestamp::TimeArg,
+  visualize::{
+    spectrogram_filter, waveform_filter, SpectrogramOptions, VisualOptions,
+    SPECTROGRAM_OUTPUT_LABEL, WAVEFORM_OUTPUT_LABEL,
+  },
+};
+#[cfg(feature = "process")]
+use crate::{
+  ffprobe::ffprobe_rotation,
+  input::LoopCount,
+  mix::{background_audio_filter, BackgroundAudioOptions, BACKGROUND_OUTPUT_LABEL},
+  probe::probe_cached,
+  rotation::{rotation_filter, RotationPolicy},
+};
+
+/// The arguments of an ffmpeg command, built with the same typed methods as
+/// [`FfmpegCommand`](crate::command::FfmpegCommand) but without a process,
+/// e.g. to run ffmpeg through another process manager, or to inspect and
+/// compare commands.
+///
+/// Along with the arguments, this keeps the settings applying to the outputs
+/// added later, like the [`metadata_policy`](Self::metadata_policy). Unlike
+/// `FfmpegCommand`, it doesn't start with `-loglevel level+info`, which is
+/// only needed to parse the log into events.
+///
+/// ```rust
+/// use ffmpeg_sidecar::args::FfmpegArgs;
+/// use std::process::Command;
+///
+/// let mut args = FfmpegArgs::new();
+/// args
+///   .input("input.mov")
+///   .codec_video("libx264")
+///   .crf(23)
+///   .overwrite()
+///   .output("output.mp4");
+/// let mut unit = Command::new("systemd-run");
+/// unit.args(["--user", "--unit", "transcode", "ffmpeg"]);
+/// unit.args(args.iter_args());
+/// assert_eq!(
+///   args.to_vec(),
+///   ["-i", "input.mov", "-c:v", "libx264", "-crf:v", "23", "-y", "output.mp4"]
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FfmpegArgs {
+  args: Vec<OsString>,
+  /// Index in the arguments and name of each placeholder
+  placeholders: Vec<(usize, String)>,
+  metadata_policy: Option<MetadataPolicy>,
+  web_optimized: bool,
+  /// Stream kind and argument index of each `-bsf` value added for the
+  /// current output, so later filters for the same kind are chained to it
+  bitstream_filters: Vec<(StreamKind, usize)>,
+  /// Argument index and value of each `-pix_fmt` added by
+  /// `ensure_pix_fmt`, removed at spawn if the inputs already have it
+  ensured_pix_fmts: Vec<(usize, String)>,
+  /// Argument index of each path added by `input` or `output`, checked at
+  /// spawn by `sanitize_inputs`
+  paths: Vec<(usize, PathRole)>,
+  /// Argument index of each selection by language, replaced by `-map`
+  /// options at spawn
+  language_selections: Vec<(usize, LanguageSelection)>,
+  /// Argument index of each program selected by name, replaced by a `-map`
+  /// option at spawn
+  program_selections: Vec<(usize, Program)>,
+  /// Warnings about the options of the inputs, reported with the other hints
+  input_warnings: Vec<String>,
+  /// Argument index of each argument added by `arg` or `args`, audited at
+  /// spawn
+  raw_args: Vec<usize>,
+  reproducible: Option<ReproducibleOptions>,
+  audio_gapless: bool,
+  /// The outputs added with `audio_gapless` which can't keep it, reported
+  /// with the other hints
+  gapless_warnings: Vec<GaplessWarning>,
+}
+
+/// A difference between two argument lists, see [`FfmpegArgs::diff`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ArgDiff {
+  /// In both lists.
+  Unchanged(OsString),
+  /// Only in the first list.
+  Removed(OsString),
+  /// Only in the second list.
+  Added(OsString),
+}
+
+impl FfmpegArgs {
+  /// No arguments.
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// The arguments, in order.
+  pub fn iter_args(&self) -> impl DoubleEndedIterator<Item = &OsStr> + ExactSizeIterator {
+    self.args.iter().map(OsString::as_os_str)
+  }
+
+  /// A copy of the arguments, ready for `std::process::Command::args`.
+  pub fn to_vec(&self) -> Vec<OsString> {
+    self.args.clone()
+  }
+
+  /// Append the arguments of `other`, e.g. a set of output options shared
+  /// by several commands. The settings `other` made for the outputs added
+  /// later, like its [`metadata_policy`](Self::metadata_policy), replace the
+  /// ones of `self`.
+  ///
+  /// ```rust
+  /// use ffmpeg_sidecar::args::FfmpegArgs;
+  ///
+  /// let mut h264 = FfmpegArgs::new();
+  /// h264.codec_video("libx264").preset("slow").crf(20);
+  ///
+  /// let mut args = FfmpegArgs::new();
+  /// args.input("in.mov").merge(&h264).output("out.mp4");
+  /// assert_eq!(
+  ///   args.to_vec(),
+  ///   ["-i", "in.mov", "-c:v", "libx264", "-preset:v", "slow", "-crf:v", "20", "out.mp4"]
+  /// );
+  /// ```
+  pub fn merge(&mut self, other: &FfmpegArgs) -> &mut Self {
+    let offset = self.args.len();
+    self.args.extend(other.args.iter().cloned());
+    self.placeholders.extend(
+      other
+        .placeholders
+        .iter()
+        .map(|(index, name)| (index + offset, name.clone())),
+    );
+    for (kind, index) in &other.bitstream_filters {
+      self
+        .bitstream_filters
+        .retain(|(existing, _)| existing != kind);
+      self.bitstream_filters.push((*kind, index + offset));
+    }
+    self.ensured_pix_fmts.extend(
+      other
+        .ensured_pix_fmts
+        .iter()
+        .map(|(index, pix_fmt)| (index + offset, pix_fmt.clone())),
+    );
+    self.paths.extend(
+      other
+        .paths
+        .iter()
+        .map(|(index, role)| (index + offset, *role)),
+    );
+    self.language_selections.extend(
+      other
+        .language_selections
+        .iter()
+        .map(|(index, selection)| (index + offset, selection.clone())),
+    );
+    self.program_selections.extend(
+      other
+        .program_selections
+        .iter()
+        .map(|(index, program)| (index + offset, program.clone())),
+    );
+    self
+      .raw_args
+      .extend(other.raw_args.iter().map(|index| index + offset));
+    self
+      .input_warnings
+      .extend(other.input_warnings.iter().cloned());
+    self
+      .gapless_warnings
+      .extend(other.gapless_warnings.iter().cloned());
+    if other.metadata_policy.is_some() {
+      self.metadata_policy = other.metadata_policy.clone();
+    }
+    if other.reproducible.is_some() {
+      self.reproducible = other.reproducible.clone();
+    }
+    self.web_optimized |= other.web_optimized;
+    self.audio_gapless |= other.audio_gapless;
+    self
+  }
+
+  /// The changes from these arguments to the ones of `other`, as a minimal
+  /// sequence of kept, removed and added arguments, e.g. to show how a
+  /// command differs from the one of a previous run.
+  ///
+  /// ```rust
+  /// use ffmpeg_sidecar::args::{ArgDiff, FfmpegArgs};
+  ///
+  /// let mut before = FfmpegArgs::new();
+  /// before.input("in.mp4").crf(23).output("out.mp4");
+  /// let mut after = FfmpegArgs::new();
+  /// after.input("in.mp4").crf(28).output("out.mp4");
+  ///
+  /// let changes = before
+  ///   .diff(&after)
+  ///   .into_iter()
+  ///   .filter(|change| !matches!(change, ArgDiff::Unchanged(_)))
+  ///   .collect::<Vec<_>>();
+  /// assert_eq!(
+  ///   changes,
+  ///   [ArgDiff::Removed("23".into()), ArgDiff::Added("28".into())]
+  /// );
+  /// ```
+  pub fn diff(&self, other: &FfmpegArgs) -> Vec<ArgDiff> {
+    let (a, b) = (&self.args, &other.args);
+    // Length of the longest common subsequence of the suffixes a[i..], b[j..]
+    let mut lcs = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+      for j in (0..b.len()).rev() {
+        lcs[i][j] = match a[i] == b[j] {
+          true => lcs[i + 1][j + 1] + 1,
+          false => lcs[i + 1][j].max(lcs[i][j + 1]),
+        };
+      }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut diff = Vec::with_capacity(a.len().max(b.len()));
+    while i < a.len() || j < b.len() {
+      if i < a.len() && j < b.len() && a[i] == b[j] {
+        diff.push(ArgDiff::Unchanged(a[i].clone()));
+        (i, j) = (i + 1, j + 1);
+      } else if i < a.len() && (j == b.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+        diff.push(ArgDiff::Removed(a[i].clone()));
+        i += 1;
+      } else {
+        diff.push(ArgDiff::Added(b[j].clone()));
+        j += 1;
+      }
+    }
+    diff
+  }
+
+  /// A copy of these arguments and settings with different arguments and no
+  /// placeholders. The paths stay at the same indices, e.g. the values of
+  /// the placeholders.
+  pub(crate) fn with_args<I, S>(&self, args: I) -> Self
+  where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+  {
+    let mut copy = Self {
+      placeholders: Vec::new(),
+      ensured_pix_fmts: Vec::new(),
+      ..self.clone()
+    };
+    copy.set_args(args);
+    copy
+  }
+
+  /// Replace the arguments, keeping the settings and placeholders.
+  pub(crate) fn set_args<I, S>(&mut self, args: I)
+  where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+  {
+    self.args = args
+      .into_iter()
+      .map(|arg| arg.as_ref().to_os_string())
+      .collect();
+  }
+
+  #[cfg(feature = "process")]
+  pub(crate) fn input_warnings(&self) -> &[String] {
+    &self.input_warnings
+  }
+
+  /// The outputs added since [`audio_gapless`](Self::audio_gapless) was
+  /// enabled which can't keep the gapless playback information.
+  pub fn gapless_warnings(&self) -> &[GaplessWarning] {
+    &self.gapless_warnings
+  }
+
+  //// Generic option aliases ////
+  //// https://ffmpeg.org/ffmpeg.html#Generic-options
+
+  /// alias for `-hide_banner` argument.
+  ///
+  /// Suppress printing banner.
+  ///
+  /// All FFmpeg tools will normally show a copyright notice, build options and
+  /// library versions. This option can be used to suppress printing this
+  /// information.
+  pub fn hide_banner(&mut self) -> &mut Self {
+    self.push_arg("-hide_banner");
+    self
+  }
+
+  //// Main option aliases
+  //// https://ffmpeg.org/ffmpeg.html#Main-options
+
+  /// Alias for `-f` argument, the format name.
+  ///
+  /// Force input or output file format. The format is normally auto detected
+  /// for input files and guessed from the file extension for output files, so
+  /// this option is not needed in most cases.
+  pub fn format<S: AsRef<str>>(&mut self, format: S) -> &mut Self {
+    self.push_arg("-f");
+    self.push_arg(format.as_ref());
+    self
+  }
+
+  /// Alias for `-i` argument, the input file path or URL.
+  ///
+  /// To take input from stdin, use the value `-` or `pipe:0`.
+  pub fn input<S: AsRef<str>>(&mut self, path_or_url: S) -> &mut Self {
+    self.push_arg("-i");
+    self.paths.push((self.args.len(), PathRole::Input));
+    self.push_arg(path_or_url.as_ref());
+    self
+  }
+
+  /// Adds an input along with options scoped to it. Everything configured in
+  /// the closure is emitted immediately before this input's `-i`, so options
+  /// like `-framerate` or `-itsoffset` can't accidentally bind to a different
+  /// input.
+  ///
+  /// ```rust
+  /// use ffmpeg_sidecar::command::FfmpegCommand;
+  /// use std::time::Duration;
+  ///
+  /// let mut command = FfmpegCommand::new();
+  /// command
+  ///   .input_with("frames/%03d.png", |i| {
+  ///     i.framerate(30.0);
+  ///   })
+  ///   .input_with("music.mp3", |i| {
+  ///     i.offset(Duration::from_millis(1500));
+  ///   })
+  ///   .output("output.mp4");
+  /// ```
+  pub fn input_with<S, F>(&mut self, path_or_url: S, configure: F) -> &mut Self
+  where
+    S: AsRef<str>,
+    F: FnOnce(&mut InputOptions),
+  {
+    let mut options = InputOptions::new();
+    configure(&mut options);
+    self.push_args(options.get_args());
+    self.input(path_or_url)
+  }
+
+  /// Add a network input, preceded by the flags of `options` which apply to
+  /// the protocol of `url` (see [`InputNetworkOptions::to_args`]). The
+  /// options which don't apply are reported as hints by the iterator.
+  ///
+  /// ```rust
+  /// use ffmpeg_sidecar::{command::FfmpegCommand, network::InputNetworkOptions};
+  /// use std::time::Duration;
+  ///
+  /// let mut command = FfmpegCommand::new();
+  /// command
+  ///   .network_input(
+  ///     "https://cdn.example.com/live/index.m3u8",
+  ///     &InputNetworkOptions {
+  ///       reconnect: true,
+  ///       reconnect_delay_max: Some(Duration::from_secs(10)),
+  ///       headers: vec![("Authorization".to_string(), "Bearer abc".to_string())],
+  ///       ..Default::default()
+  ///     },
+  ///   )
+  ///   .codec_video("copy")
+  ///   .output("recording.ts");
+  /// ```
+  pub fn network_input<S: AsRef<str>>(
+    &mut self,
+    url: S,
+    options: &InputNetworkOptions,
+  ) -> &mut Self {
+    let url = url.as_ref();
+    self.push_args(options.to_args(url));
+    self.input_warnings.extend(options.warnings(url));
+    self.input(url)
+  }
+
+  /// Adds an input whose path is filled in later by
+  /// [`CommandTemplate::instantiate`](crate::template::CommandTemplate::instantiate).
+  pub fn input_placeholder<S: AsRef<str>>(&mut self, name: S) -> &mut Self {
+    self.push_arg("-i");
+    self.placeholder(name.as_ref(), PathRole::Input)
+  }
+
+  /// Adds an output whose path is filled in later by
+  /// [`CommandTemplate::instantiate`](crate::template::CommandTemplate::instantiate).
+  pub fn output_placeholder<S: AsRef<str>>(&mut self, name: S) -> &mut Self {
+    self.placeholder(name.as_ref(), PathRole::Output)
+  }
+
+  fn placeholder(&mut self, name: &str, role: PathRole) -> &mut Self {
+    let index = self.args.len();
+    self.placeholders.push((index, name.to_string()));
+    self.paths.push((index, role));
+    self.push_arg(format!("{{{name}}}"))
+  }
+
+  pub(crate) fn placeholders(&self) -> &[(usize, String)] {
+    &self.placeholders
+  }
+
+  #[cfg(feature = "process")]
+  pub(crate) fn ensured_pix_fmts(&self) -> &[(usize, String)] {
+    &self.ensured_pix_fmts
+  }
+
+  #[cfg(feature = "process")]
+  pub(crate) fn paths(&self) -> &[(usize, PathRole)] {
+    &self.paths
+  }
+
+  #[cfg(feature = "process")]
+  pub(crate) fn language_selections(&self) -> &[(usize, LanguageSelection)] {
+    &self.language_selections
+  }
+
+  #[cfg(feature = "process")]
+  pub(crate) fn clear_language_selections(&mut self) {
+    self.language_selections.clear();
+  }
+
+  #[cfg(feature = "process")]
+  pub(crate) fn program_selections(&self) -> &[(usize, Program)] {
+    &self.program_selections
+  }
+
+  #[cfg(feature = "process")]
+  pub(crate) fn clear_program_selections(&mut self) {
+    self.program_selections.clear();
+  }
+
+  /// Insert `args` at `index`, e.g. the `-map` options of a selection,
+  /// moving the indices of those from there on.
+  #[cfg(feature = "process")]
+  pub(crate) fn insert_args<I, S>(&mut self, index: usize, args: I)
+  where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+  {
+    let args = args
+      .into_iter()
+      .map(|arg| arg.as_ref().to_os_string())
+      .collect::<Vec<_>>();
+    let count = args.len();
+    self.args.splice(index..index, args);
+    for i in self.tracked_indices() {
+      if *i >= index {
+        *i += count;
+      }
+    }
+  }
+
+  /// The argument indices of the placeholders, bitstream filters, ensured
+  /// pixel formats, paths, language and program selections, and raw
+  /// arguments.
+  fn tracked_indices(&mut self) -> impl Iterator<Item = &mut usize> {
+    let indices = self.placeholders.iter_mut().map(|(i, _)| i);
+    let indices = indices.chain(self.bitstream_filters.iter_mut().map(|(_, i)| i));
+    let indices = indices.chain(self.paths.iter_mut().map(|(i, _)| i));
+    let indices = indices.chain(self.ensured_pix_fmts.iter_mut().map(|(i, _)| i));
+    let indices = indices.chain(self.language_selections.iter_mut().map(|(i, _)| i));
+    let indices = indices.chain(self.program_selections.iter_mut().map(|(i, _)| i));
+    indices.chain(self.raw_args.iter_mut())
+  }
+
+  /// Remove the arguments in `range`, e.g. an option the ffmpeg binary
+  /// doesn't support, moving the indices of those after it.
+  #[cfg(feature = "process")]
+  pub(crate) fn remove_args(&mut self, range: Range<usize>) {
+    let (start, count) = (range.start, range.len());
+    self.args.drain(range);
+    self
+      .placeholders
+      .retain(|(i, _)| *i < start || *i >= start + count);
+    self
+      .bitstream_filters
+      .retain(|(_, i)| *i < start || *i >= start + count);
+    self
+      .ensured_pix_fmts
+      .retain(|(i, _)| *i < start || *i >= start + count);
+    self
+      .paths
+      .retain(|(i, _)| *i < start || *i >= start + count);
+    self.raw_args.retain(|i| *i < start || *i >= start + count);
+    // Kept, since the `-map` options go between the arguments around it
+    for (i, _) in &mut self.language_selections {
+      *i = (*i).min(start + count);
+    }
+    for (i, _) in &mut self.program_selections {
+      *i = (*i).min(start + count);
+    }
+    for i in self.tracked_indices() {
+      if *i >= start + count {
+        *i -= count;
+      }
+    }
+  }
+
+  /// Replace the argument at `index`, e.g. a filtergraph extended by a later
+  /// call.
+  pub(crate) fn replace_arg<S: AsRef<OsStr>>(&mut self, index: usize, value: S) {
+    self.args[index] = value.as_ref().to_os_string();
+  }
+
+  /// Alias for the output file path or URL.
+  ///
+  /// To send output to stdout, use the value `-` or `pipe:1`.
+  ///
+  /// Since this is the last argument in the command and has no `-` flag
+  /// preceding it, it is equivalent to calling `.arg()` directly. However,
+  /// using this command helps label the purpose of the argument, and makes the
+  /// code more readable at a glance.
+  pub fn output<S: AsRef<str>>(&mut self, path_or_url: S) -> &mut Self {
+    let mut args = match &self.metadata_policy {
+      Some(policy) => policy.to_args(path_or_url.as_ref()),
+      None => Vec::new(),
+    };
+    if self.web_optimized && self.is_mp4_output(path_or_url.as_ref()) {
+      // Only the last `-movflags` of an output counts
+      let movflags = web_movflags(path_or_url.as_ref());
+      match args.iter().position(|arg| arg == "-movflags") {
+        Some(index) => args[index + 1].push_str(movflags),
+        None => args.extend(["-movflags".to_string(), movflags.to_string()]),
+      }
+    }
+    if let Some(options) = &self.reproducible {
+      // After the metadata policy, so its `-map_metadata -1` wins
+      args.extend(options.to_args());
+    }
+    if self.audio_gapless {
+      args.extend(self.gapless_output_args(path_or_url.as_ref()));
+    }
+    self.push_args(args);
+    self.bitstream_filters.clear();
+    self.paths.push((self.args.len(), PathRole::Output));
+    self.push_arg(path_or_url.as_ref());
+    self
+  }
+
+  /// The options keeping the gapless playback information of the audio of
+  /// the output being added, going by its audio codec and `-f` or
+  /// extension, or none along with a warning when it can't.
+  fn gapless_output_args(&mut self, path_or_url: &str) -> Vec<String> {
+    let start = self.paths.last().map_or(0, |(index, _)| index + 1);
+    let options = self.args[start.min(self.args.len())..]
+      .iter()
+      .map(|arg| arg.to_string_lossy().into_owned())
+      .collect::<Vec<_>>();
+    if options.iter().any(|arg| arg == "-an") {
+      return Vec::new();
+    }
+    let last_value = |names: &[&str]| {
+      options
+        .windows(2)
+        .rev()
+        .find(|pair| names.contains(&pair[0].as_str()))
+        .map(|pair| pair[1].clone())
+    };
+    let codec = last_value(&["-c:a", "-codec:a", "-acodec", "-c", "-codec"]);
+    let format = last_value(&["-f"]);
+    match gapless_args(codec.as_deref(), format.as_deref(), path_or_url) {
+      Ok(args) => args,
+      Err(limitation) => {
+        self.gapless_warnings.push(GaplessWarning {
+          output: path_or_url.to_string(),
+          limitation,
+        });
+        Vec::new()
+      }
+    }
+  }
+
+  /// Whether the output being added is written by the MP4 muxer, going by
+  /// its `-f` or its extension.
+  fn is_mp4_output(&self, path_or_url: &str) -> bool {
+    let args = self
+      .iter_args()
+      .map(|arg| arg.to_string_lossy())
+      .collect::<Vec<_>>();
+    let last_input = args
+      .iter()
+      .rposition(|arg| arg == "-i")
+      .map_or(0, |i| i + 2);
+    let format = args[last_input.min(args.len())..]
+      .windows(2)
+      .rev()
+      .find(|pair| pair[0] == "-f")
+      .map(|pair| pair[1].to_string());
+    match format {
+      Some(format) => matches!(
+        format.as_str(),
+        "mp4" | "mov" | "ipod" | "ismv" | "3gp" | "3g2" | "psp" | "f4v"
+      ),
+      None => ContainerFormat::from_path(path_or_url) == Some(ContainerFormat::Mp4),
+    }
+  }
+
+  /// Make the MP4 outputs added after this call with
+  /// [`output`](Self::output) playable before they're fully downloaded.
+  ///
+  /// Files get `-movflags +faststart`, which moves the index to the front
+  /// once they're written. That's impossible for stdout, pipes and network
+  /// outputs, which get a fragmented MP4 instead
+  /// (`+frag_keyframe+empty_moov+default_base_moof`). See
+  /// [`faststart`](crate::faststart), and
+  /// [`faststart_in_place`](crate::faststart::faststart_in_place) for
+  /// existing files.
+  ///
+  /// Outputs in other formats are left alone. The flags are combined with the
+  /// ones of the [`metadata_policy`](Self::metadata_policy).
+  ///
+  /// ```rust
+  /// use ffmpeg_sidecar::command::FfmpegCommand;
+  ///
+  /// let mut command = FfmpegCommand::new();
+  /// command
+  ///   .input("recording.mkv")
+  ///   .web_optimized()
+  ///   .output("upload.mp4")
+  ///   .format("mp4")
+  ///   .output("-");
+  /// let args = command.get_args().collect::<Vec<_>>();
+  /// assert_eq!(
+  ///   args[4..],
+  ///   [
+  ///     "-movflags",
+  ///     "+faststart",
+  ///     "upload.mp4",
+  ///     "-f",
+  ///     "mp4",
+  ///     "-movflags",
+  ///     "+frag_keyframe+empty_moov+default_base_moof",
+  ///     "-"
+  ///   ]
+  /// );
+  /// ```
+  pub fn web_optimized(&mut self) -> &mut Self {
+    self.web_optimized = true;
+    self
+  }
+
+  /// Keep the gapless playback information of the audio of the outputs
+  /// added after this call with [`output`](Self::output): the priming
+  /// samples the encoder adds at the start and the padding of the last
+  /// frame, which players drop so the audio lasts exactly as long as the
+  /// source. See [`gapless`](crate::gapless) for what each container
+  /// stores.
+  ///
+  /// Outputs which can't keep it, like raw `.aac` files, are listed by
+  /// [`gapless_warnings`](Self::gapless_warnings) and reported as hints
+  /// when spawning, rather than silently playing with a gap.
+  ///
+  /// ```rust
+  /// use ffmpeg_sidecar::command::FfmpegCommand;
+  ///
+  /// let mut command = FfmpegCommand::new();
+  /// command
+  ///   .input("track.flac")
+  ///   .audio_gapless(true)
+  ///   .codec_audio("aac")
+  ///   .output("track.m4a")
+  ///   .codec_audio("aac")
+  ///   .output("track.aac");
+  /// let args = command.get_args().collect::<Vec<_>>();
+  /// assert_eq!(args[4..8], ["-c:a", "aac", "-use_editlist", "1"]);
+  /// assert_eq!(command.ffmpeg_args().gapless_warnings()[0].output, "track.aac");
+  /// ```
+  pub fn audio_gapless(&mut self, enabled: bool) -> &mut Self {
+    self.audio_gapless = enabled;
+    self
+  }
+
+  /// Encode the next output so it plays on every player of `target`: H.264
+  /// in 4:2:0 with the profile and level the target decodes, and AAC audio.
+  /// Also makes this and the later MP4 outputs
+  /// [`web_optimized`](Self::web_optimized). See [`CompatibilityTarget`] for
+  /// the arguments of each target.
+  ///
+  /// Options added afterwards for the same output override these, e.g.
+  /// [`codec_audio`](Self::codec_audio) for an output without audio.
+  ///
+  /// ```rust
+  /// use ffmpeg_sidecar::{command::FfmpegCommand, compatibility::CompatibilityTarget};
+  ///
+  /// let mut command = FfmpegCommand::new();
+  /// command
+  ///   .input("screen-recording-444.mov")
+  ///   .compatibility_target(CompatibilityTarget::WebSafari)
+  ///   .output("share.mp4");
+  /// let args = command.get_args().collect::<Vec<_>>();
+  /// assert_eq!(
+  ///   args[4..],
+  ///   [
+  ///     "-c:v", "libx264", "-pix_fmt", "yuv420p", "-profile:v", "high", "-level:v", "4.1",
+  ///     "-c:a", "aac", "-movflags", "+faststart", "share.mp4"
+  ///   ]
+  /// );
+  /// ```
+  pub fn compatibility_target(&mut self, target: CompatibilityTarget) -> &mut Self {
+    self.push_args(target.args());
+    self.web_optimized = true;
+    self
+  }
+
+  /// Make the outputs added after this call with [`output`](Self::output)
+  /// byte-for-byte reproducible, by leaving out the metadata which changes
+  /// between runs and ffmpeg builds: tags, versions and the time of the
+  /// encode. See [`reproducible`](crate::reproducible) for the encoders which
+  /// also need [`reproducible_with`](Self::reproducible_with) and
+  /// [`ReproducibleOptions::strict_single_thread`].
+  ///
+  /// ```rust
+  /// use ffmpeg_sidecar::command::FfmpegCommand;
+  ///
+  /// let mut command = FfmpegCommand::new();
+  /// command
+  ///   .input("golden.y4m")
+  ///   .reproducible()
+  ///   .output("encoded.mkv");
+  /// let args = command.get_args().collect::<Vec<_>>();
+  /// assert_eq!(
+  ///   args[4..],
+  ///   [
+  ///     "-map_metadata",
+  ///     "-1",
+  ///     "-fflags",
+  ///     "+bitexact",
+  ///     "-flags:v",
+  ///     "+bitexact",
+  ///     "-flags:a",
+  ///     "+bitexact",
+  ///     "-metadata",
+  ///     "creation_time=1970-01-01T00:00:00.000000Z",
+  ///     "encoded.mkv"
+  ///   ]
+  /// );
+  /// ```
+  pub fn reproducible(&mut self) -> &mut Self {
+    self.reproducible_with(ReproducibleOptions::default())
+  }
+
+  /// Like [`reproducible`](Self::reproducible), with the given options.
+  pub fn reproducible_with(&mut self, options: ReproducibleOptions) -> &mut Self {
+    self.reproducible = Some(options);
+    self
+  }
+
+  /// Alias for `-bsf:<stream>` argument: apply a bitstream filter to the
+  /// streams of the given kind in the next output. Filters added for the same
+  /// kind before the same output are chained in order, as `-bsf:v a,b`.
+  ///
+  /// When spawning an [`FfmpegCommand`](crate::command::FfmpegCommand), the
+  /// arguments are checked for stream copies which need a filter that's
+  /// missing (see
+  /// [`bitstream_filter_warnings`](crate::bsf::bitstream_filter_warnings)),
+  /// reported as [`FfmpegEvent::Hint`](crate::event::FfmpegEvent::Hint) by
+  /// the iterator.
+  ///
+  /// ```rust
+  /// use ffmpeg_sidecar::{bsf::Bsf, command::FfmpegCommand, extract::StreamKind};
+  ///
+  /// let mut command = FfmpegCommand::new();
+  /// command
+  ///   .input("input.mp4")
+  ///   .codec_video("copy")
+  ///   .bitstream_filter(StreamKind::Video, Bsf::H264Mp4ToAnnexB)
+  ///   .bitstream_filter(StreamKind::Video, Bsf::H264Metadata("level=4.1".to_string()))
+  ///   .format("mpegts")
+  ///   .output("output.ts");
+  /// let args = command.get_args().collect::<Vec<_>>();
+  /// assert_eq!(args[6..8], ["-bsf:v", "h264_mp4toannexb,h264_metadata=level=4.1"]);
+  /// ```
+  pub fn bitstream_filter(&mut self, stream: StreamKind, bsf: Bsf) -> &mut Self {
+    let existing = self
+      .bitstream_filters
+      .iter()
+      .find(|(kind, _)| *kind == stream)
+      .map(|(_, index)| *index);
+    match existing.and_then(|index| Some((index, self.iter_args().nth(index)?.to_os_string()))) {
+      Some((index, filters)) => {
+        let filters = format!("{},{bsf}", filters.to_string_lossy());
+        self.replace_arg(index, filters);
+      }
+      None => {
+        self.push_arg(format!("-bsf:{}", stream.specifier()));
+        self.bitstream_filters.push((stream, self.args.len()));
+        self.push_arg(bsf.to_string());
+      }
+    }
+    self
+  }
+
+  /// Keep or strip the tags and chapters of the first input in every output
+  /// added after this call with [`output`](Self::output), which emits the
+  /// arguments of [`MetadataPolicy::to_args`] before the output path.
+  ///
+  /// For MP4 outputs this sets `-movflags +use_metadata_tags`, so other
+  /// movflags like `+faststart` need to be combined into a single
+  /// `-movflags +use_metadata_tags+faststart` added after this, as done by
+  /// [`web_optimized`](Self::web_optimized).
+  ///
+  /// ```rust
+  /// use ffmpeg_sidecar::{command::FfmpegCommand, metadata_policy::MetadataPolicy};
+  ///
+  /// let mut command = FfmpegCommand::new();
+  /// command
+  ///   .input("holiday.mov")
+  ///   .metadata_policy(MetadataPolicy::Strip)
+  ///   .output("shared.mp4");
+  /// let args = command.get_args().collect::<Vec<_>>();
+  /// assert_eq!(
+  ///   args[4..],
+  ///   ["-map_metadata", "-1", "-map_chapters", "-1", "shared.mp4"]
+  /// );
+  /// ```
+  pub fn metadata_policy(&mut self, policy: MetadataPolicy) -> &mut Self {
+    self.metadata_policy = Some(policy);
+    self
+  }
+
+  /// Alias for `-y` argument: overwrite output files without asking.
+  pub fn overwrite(&mut self) -> &mut Self {
+    self.push_arg("-y");
+    self
+  }
+
+  /// Alias for `-n` argument: do not overwrite output files, and exit
+  /// immediately if a specified output file already exists.
+  pub fn no_overwrite(&mut self) -> &mut Self {
+    self.push_arg("-n");
+    self
+  }
+
+  /// Alias for `-xerror` argument: exit with an error on the first decode
+  /// error, instead of skipping the damaged data and exiting successfully
+  /// with a shorter or glitched output.
+  ///
+  /// Without it, the errors are still counted in
+  /// [`FfmpegSummary::decode_errors`](crate::summary::FfmpegSummary::decode_errors),
+  /// and a truncated output can be caught after the fact with
+  /// [`check_output_duration`](crate::integrity::check_output_duration).
+  pub fn fail_on_error(&mut self) -> &mut Self {
+    self.push_arg("-xerror");
+    self
+  }
+
+  /// Alias for `-c:v` argument.
+  ///
+  /// Select an encoder (when used before an output file) or a decoder (when
+  /// used before an input file) for one or more streams. `codec` is the name of
+  /// a decoder/encoder or a special value copy (output only) to indicate that
+  /// the stream is not to be re-encoded.
+  pub fn codec_video<S: AsRef<str>>(&mut self, codec: S) -> &mut Self {
+    self.push_arg("-c:v");
+    self.push_arg(codec.as_ref());
+    self
+  }
+
+  /// Alias for `-c:a` argument.
+  ///
+  /// Select an encoder (when used before an output file) or a decoder (when
+  /// used before an input file) for one or more streams. `codec` is the name of
+  /// a decoder/encoder or a special value `copy` (output only) to indicate that
+  /// the stream is not to be re-encoded.
+  pub fn codec_audio<S: AsRef<str>>(&mut self, codec: S) -> &mut Self {
+    self.push_arg("-c:a");
+    self.push_arg(codec.as_ref());
+    self
+  }
+
+  /// Alias for `-t` argument.
+  ///
+  /// When used as an input option (before `-i`), limit the duration of data
+  /// read from the input file.
+  ///
+  /// When used as an output option (before an output url), stop writing the
+  /// output after its duration reaches duration.
+  ///
+  /// `duration` is a [`TimeArg`]: text in the [Time duration
+  /// syntax](https://ffmpeg.org/ffmpeg-utils.html#time-duration-syntax) of
+  /// ffmpeg, passed verbatim, or seconds, a [`Duration`] or a
+  /// [`Timestamp`](crate::timestamp::Timestamp).
+  ///
+  /// `-to` and `-t` are mutually exclusive and -t has priority.
+  pub fn duration<T: Into<TimeArg>>(&mut self, duration: T) -> &mut Self {
+    self.push_arg("-t");
+    self.push_arg(duration.into().to_string());
+    self
+  }
+
+  /// Alias for `-to` argument.
+  ///
+  /// Stop writing the output or reading the input at `position`, a
+  /// [`TimeArg`] like for [`duration`](Self::duration).
+  ///
+  /// `-to` and `-t` (aka `duration()`) are mutually exclusive and `-t` has
+  /// priority.
+  pub fn to<T: Into<TimeArg>>(&mut self, position: T) -> &mut Self {
+    self.push_arg("-to");
+    self.push_arg(position.into().to_string());
+    self
+  }
+
+  /// Alias for `-shortest` argument.
+  ///
+  /// Finish the output when its shortest stream ends, e.g. with the video
+  /// when its audio is an input
+  /// [looped](crate::input::InputOptions::loop_input) forever. Since ffmpeg
+  /// 6, the other streams can still run on by up to `-shortest_buf_duration`
+  /// (10 seconds by default) of buffered data.
+  pub fn shortest(&mut self) -> &mut Self {
+    self.push_arg("-shortest");
+    self
+  }
+
+  /// Alias for `-fs` argument.
+  ///
+  /// Set the file size limit, expressed in bytes. No further chunk of bytes is
+  /// written after the limit is exceeded. The size of the output file is
+  /// slightly more than the requested file size.
+  pub fn limit_file_size(&mut self, size_in_bytes: u32) -> &mut Self {
+    self.push_arg("-fs");
+    self.push_arg(size_in_bytes.to_string());
+    self
+  }
+
+  /// Alias for `-ss` argument.
+  ///
+  /// When used as an input option (before `-i`), seeks in this input file to
+  /// position. Note that in most formats it is not possible to seek exactly, so
+  /// `ffmpeg` will seek to the closest seek point before `position`. When
+  /// transcoding and `-accurate_seek` is enabled (the default), this extra
+  /// segment between the seek point and `position` will be decoded and
+  /// discarded. When doing stream copy or when `-noaccurate_seek` is used, it
+  /// will be preserved.
+  ///
+  /// When used as an output option (before an output url), decodes but discards
+  /// input until the timestamps reach `position`.
+  ///
+  /// `position` is a [`TimeArg`] like for [`duration`](Self::duration).
+  ///
+  /// ```rust
+  /// use std::time::Duration;
+  /// use ffmpeg_sidecar::command::FfmpegCommand;
+  ///
+  /// let mut command = FfmpegCommand::new();
+  /// command
+  ///   .seek("1:30")
+  ///   .input("talk.mp4")
+  ///   .duration(Duration::from_millis(2500))
+  ///   .to(120.0)
+  ///   .output("clip.mp4");
+  /// let args = command.get_args().collect::<Vec<_>>();
+  /// assert_eq!(args[2..4], ["-ss", "1:30"]);
+  /// assert_eq!(args[6..10], ["-t", "00:00:02.5", "-to", "00:02:00"]);
+  /// ```
+  pub fn seek<T: Into<TimeArg>>(&mut self, position: T) -> &mut Self {
+    self.push_arg("-ss");
+    self.push_arg(position.into().to_string());
+    self
+  }
+
+  /// Alias for `-sseof` argument.
+  ///
+  /// Like the `-ss` option but relative to the "end of file". That is negative
+  /// values are earlier in the file, 0 is at EOF.
+  pub fn seek_eof<T: Into<TimeArg>>(&mut self, position: T) -> &mut Self {
+    self.push_arg("-sseof");
+    self.push_arg(position.into().to_string());
+    self
+  }
+
+  /// Alias for `-filter` argument.
+  ///
+  /// Create the filtergraph specified by `filtergraph` and use it to filter the
+  /// stream.
+  ///
+  /// `filtergraph` is a description of the filtergraph to apply to the stream,
+  /// and must have a single input and a single output of the same type of the
+  /// stream. In the filtergraph, the input is associated to the label `in`, and
+  /// the output to the label `out`. See the ffmpeg-filters manual for more
+  /// information about the filtergraph syntax.
+  ///
+  /// See the [`-filter_complex`
+  /// option](https://ffmpeg.org/ffmpeg.html#filter_005fcomplex_005foption) if
+  /// you want to create filtergraphs with multiple inputs and/or outputs.
+  pub fn filter<S: AsRef<str>>(&mut self, filtergraph: S) -> &mut Self {
+    self.push_arg("-filter");
+    self.push_arg(filtergraph.as_ref());
+    self
+  }
+
+  /// Scale the video to `width`x`height` with a `-vf` filter chain, padding
+  /// with `background_color` or cropping when the aspect ratio differs. See
+  /// [`fit_filter`] for the rounding rules.
+  ///
+  /// Like [`filter`](Self::filter), this can't be combined with other `-vf`
+  /// filters; use `fit_filter` to build a longer chain instead.
+  ///
+  /// ```rust,no_run
+  /// use ffmpeg_sidecar::{command::FfmpegCommand, geometry::FitMode};
+  ///
+  /// FfmpegCommand::new()
+  ///   .input("vertical.mp4")
+  ///   .fit(1920, 1080, FitMode::Contain, "black")
+  ///   .unwrap()
+  ///   .output("letterboxed.mp4");
+  /// ```
+  pub fn fit(
+    &mut self,
+    width: u32,
+    height: u32,
+    mode: FitMode,
+    background_color: &str,
+  ) -> anyhow::Result<&mut Self> {
+    let filter = fit_filter(width, height, mode, background_color)?;
+    Ok(self.push_args(["-vf", &filter]))
+  }
+
+  /// Tag the output with the color properties of `stream`, e.g. an input
+  /// stream from [`FfmpegIterator::collect_metadata`](crate::iter::FfmpegIterator::collect_metadata),
+  /// so HDR video isn't displayed washed out after transcoding. See
+  /// [`ColorMetadata::to_args`](crate::color::ColorMetadata::to_args).
+  ///
+  /// Call this after setting the encoder: for `libx265`, the properties and
+  /// HDR side data are also passed to the encoder with `-x265-params`. Side
+  /// data isn't parsed from ffmpeg's log, so replace `stream.color` with
+  /// [`ffprobe_color_metadata`](crate::ffprobe::ffprobe_color_metadata) to
+  /// carry it over.
+  pub fn preserve_color_metadata(&mut self, stream: &AVStream) -> &mut Self {
+    let args = self.iter_args().collect::<Vec<_>>();
+    let encoder = args
+      .windows(2)
+      .rev()
+      .find(|pair| {
+        ["-c:v", "-codec:v", "-vcodec"]
+          .iter()
+          .any(|flag| pair[0] == *flag)
+      })
+      .map(|pair| pair[1].to_string_lossy().to_string());
+    let color_args = stream.color.to_args(encoder.as_deref());
+    self.push_args(color_args)
+  }
+
+  /// Tone map HDR video to SDR BT.709 with a `-vf` filter chain (see
+  /// [`hdr_to_sdr_filter`]), tagging the output as BT.709. Like
+  /// [`filter`](Self::filter), this can't be combined with other `-vf`
+  /// filters.
+  ///
+  /// ```rust,no_run
+  /// use ffmpeg_sidecar::{color::TonemapOptions, command::FfmpegCommand};
+  ///
+  /// FfmpegCommand::new()
+  ///   .input("hdr10.mkv")
+  ///   .hdr_to_sdr(&TonemapOptions::default())
+  ///   .codec_video("libx264")
+  ///   .output("sdr.mp4");
+  /// ```
+  pub fn hdr_to_sdr(&mut self, options: &TonemapOptions) -> &mut Self {
+    self.push_args(["-vf", &hdr_to_sdr_filter(options)]);
+    self.push_args([
+      "-color_primaries",
+      "bt709",
+      "-color_trc",
+      "bt709",
+      "-colorspace",
+      "bt709",
+    ])
+  }
+
+  /// Overlay an image on top of the video of the first input, e.g. a
+  /// watermark. This adds the image as an input, along with a
+  /// `-filter_complex` graph (see [`overlay_filter`]) whose output is mapped
+  /// as `-map [overlaid]`, followed by `-map 0:a?` to keep the audio.
+  ///
+  /// Calling this again adds the next image to the same graph, on top of the
+  /// previous ones. The graph replaces `-vf`, so further video filters have
+  /// to be added to a filtergraph of their own which reads
+  /// [`OVERLAY_OUTPUT_LABEL`] instead of this mapping. Call this before
+  /// adding the output.
+  ///
+  /// ```rust,no_run
+  /// use ffmpeg_sidecar::{command::FfmpegCommand, overlay::OverlayOptions};
+  ///
+  /// FfmpegCommand::new()
+  ///   .input("video.mp4")
+  ///   .overlay_image(
+  ///     "logo.png",
+  ///     &OverlayOptions {
+  ///       opacity: 0.8,
+  ///       scale_to_fraction_of_width: Some(0.15),
+  ///       ..Default::default()
+  ///     },
+  ///   )
+  ///   .output("watermarked.mp4");
+  /// ```
+  pub fn overlay_image<S: AsRef<str>>(&mut self, path: S, options: &OverlayOptions) -> &mut Self {
+    let args = self
+      .iter_args()
+      .map(|arg| arg.to_string_lossy().into_owned())
+      .collect::<Vec<_>>();
+    let overlay_index = args.iter().filter(|arg| *arg == "-i").count();
+    let output_label = format!("[{OVERLAY_OUTPUT_LABEL}]");
+    let existing_graph = args
+      .windows(2)
+      .position(|pair| pair[0] == "-filter_complex" && pair[1].ends_with(&output_label))
+      .map(|i| i + 1);
+
+    if options.loop_input {
+      self.push_args(["-stream_loop", "-1"]);
+    }
+    self.input(path);
+
+    let overlay = format!("{overlay_index}:v");
+    match existing_graph {
+      Some(graph_index) => {
+        // Relabel the previous result as the main video of the new overlay
+        let main = format!("base{overlay_index}");
+        let previous = &args[graph_index][..args[graph_index].len() - output_label.len()];
+        let graph = format!(
+          "{previous}[{main}];{}",
+          overlay_filter(&main, &overlay, OVERLAY_OUTPUT_LABEL, options)
+        );
+        self.replace_arg(graph_index, graph);
+      }
+      None => {
+        self.filter_complex(overlay_filter(
+          "0:v",
+          &overlay,
+          OVERLAY_OUTPUT_LABEL,
+          options,
+        ));
+        self.push_args(["-map", &output_label, "-map", "0:a?"]);
+      }
+    }
+    self
+  }
+
+  /// Draw text on top of the video, with a `-vf` [`drawtext_filter`]. The
+  /// font is a file found by
+  /// [`default_font_path`](crate::drawtext::default_font_path) unless given,
+  /// since ffmpeg's own lookup through fontconfig fails on Windows and in
+  /// minimal containers. Fails if there's no default font.
+  ///
+  /// ```rust,no_run
+  /// use ffmpeg_sidecar::{
+  ///   command::FfmpegCommand,
+  ///   drawtext::TextOverlay,
+  ///   overlay::{Corner, OverlayPosition},
+  /// };
+  ///
+  /// FfmpegCommand::new()
+  ///   .input("talk.mp4")
+  ///   .drawtext(&TextOverlay {
+  ///     position: OverlayPosition::Corner(Corner::BottomLeft),
+  ///     size: 32,
+  ///     ..TextOverlay::new("Q&A: 10:30 – 11:00")
+  ///   })?
+  ///   .output("captioned.mp4");
+  /// # Ok::<(), anyhow::Error>(())
+  /// ```
+  pub fn drawtext(&mut self, overlay: &TextOverlay) -> anyhow::Result<&mut Self> {
+    let filter = drawtext_filter(overlay)?;
+    Ok(self.push_args(["-vf", &filter]))
+  }
+
+  /// Mix the audio of `inputs` into a single stream, each with its own volume
+  /// and start offset. This adds the inputs, along with a `-filter_complex`
+  /// graph (see [`mix_filter`]) whose output is mapped as `-map [mixed]`,
+  /// followed by `-map 0:v?` to keep the video of the first input of the
+  /// command, if any. Call this before adding the output.
+  ///
+  /// Fails with [`TooFewMixInputs`] for fewer than 2 inputs.
+  ///
+  /// ```rust,no_run
+  /// use ffmpeg_sidecar::{
+  ///   command::FfmpegCommand,
+  ///   mix::{AudioMixInput, MixOptions},
+  /// };
+  /// use std::time::Duration;
+  ///
+  /// FfmpegCommand::new()
+  ///   .input("video.mp4")
+  ///   .mix_audio(
+  ///     &[
+  ///       AudioMixInput {
+  ///         offset: Duration::from_secs(2),
+  ///         ..AudioMixInput::new("voiceover.wav")
+  ///       },
+  ///       AudioMixInput {
+  ///         volume_db: -15.0,
+  ///         ..AudioMixInput::new("music.mp3")
+  ///       },
+  ///     ],
+  ///     &MixOptions {
+  ///       normalize: false,
+  ///       ..Default::default()
+  ///     },
+  ///   )?
+  ///   .output("narrated.mp4");
+  /// # Ok::<(), anyhow::Error>(())
+  /// ```
+  pub fn mix_audio(
+    &mut self,
+    inputs: &[AudioMixInput],
+    options: &MixOptions,
+  ) -> anyhow::Result<&mut Self> {
+    if inputs.len() < 2 {
+      return Err(TooFewMixInputs(inputs.len()).into());
+    }
+    let first_input_index = self.iter_args().filter(|arg| *arg == "-i").count();
+    for input in inputs {
+      self.input(&input.path);
+    }
+    self.filter_complex(mix_filter(
+      first_input_index,
+      inputs,
+      MIX_OUTPUT_LABEL,
+      options,
+    ));
+    Ok(self.push_args(["-map", &format!("[{MIX_OUTPUT_LABEL}]"), "-map", "0:v?"]))
+  }
+
+  /// Compose the video of `inputs` into a grid, e.g. to compare an original
+  /// with its encodes side by side. This adds the inputs, along with a
+  /// `-filter_complex` graph (see [`grid_filter`]) whose output is mapped as
+  /// `-map [grid]`, followed by the audio picked with [`GridOptions::audio`].
+  /// With [`DurationPolicy::Shortest`]
+  /// and audio, `-shortest` ends the audio along with the grid. Call this
+  /// before adding the output.
+  ///
+  /// The size of the grid is given by [`grid_size`](crate::grid::grid_size).
+  ///
+  /// ```rust,no_run
+  /// use ffmpeg_sidecar::{
+  ///   command::FfmpegCommand,
+  ///   grid::{GridAudio, GridInput, GridOptions},
+  /// };
+  ///
+  /// FfmpegCommand::new()
+  ///   .compose_grid(
+  ///     &[
+  ///       GridInput::labeled("original.mp4", "original"),
+  ///       GridInput::labeled("crf28.mp4", "crf 28"),
+  ///     ],
+  ///     &GridOptions {
+  ///       columns: 2,
+  ///       cell_size: (960, 540),
+  ///       audio: GridAudio::Input(0),
+  ///       ..Default::default()
+  ///     },
+  ///   )?
+  ///   .output("comparison.mp4");
+  /// # Ok::<(), anyhow::Error>(())
+  /// ```
+  pub fn compose_grid(
+    &mut self,
+    inputs: &[GridInput],
+    options: &GridOptions,
+  ) -> anyhow::Result<&mut Self> {
+    let first_input_index = self.iter_args().filter(|arg| *arg == "-i").count();
+    let graph = grid_filter(
+      first_input_index,
+      inputs,
+      options,
+      GRID_OUTPUT_LABEL,
+      GRID_AUDIO_LABEL,
+    )?;
+    for input in inputs {
+      self.input(&input.path);
+    }
+    self.filter_complex(graph);
+    self.push_args(["-map", &format!("[{GRID_OUTPUT_LABEL}]")]);
+    match options.audio {
+      GridAudio::None => return Ok(self),
+      GridAudio::Input(index) => {
+        self.push_args(["-map", &format!("{}:a", first_input_index + index)])
+      }
+      GridAudio::Mix => self.push_args(["-map", &format!("[{GRID_AUDIO_LABEL}]")]),
+    };
+    if options.duration_policy == DurationPolicy::Shortest {
+      self.shortest();
+    }
+    Ok(self)
+  }
+
+  /// Add `video_input` and `audio_input` as inputs, and put the audio under
+  /// the video, looped and cut to the duration of the video (see
+  /// [`background_audio_filter`]), which is found with ffprobe. The video is
+  /// mapped as `-map <input>:v`, followed by the audio, with `-shortest`.
+  /// Call this before adding the output.
+  ///
+  /// ```rust,no_run
+  /// use ffmpeg_sidecar::{command::FfmpegCommand, mix::BackgroundAudioOptions};
+  /// use std::time::Duration;
+  ///
+  /// FfmpegCommand::new()
+  ///   .background_audio(
+  ///     "clip.mp4",
+  ///     "music.mp3",
+  ///     &BackgroundAudioOptions {
+  ///       audio_volume_db: -12.0,
+  ///       fade_out: Some(Duration::from_secs(2)),
+  ///     },
+  ///   )?
+  ///   .codec_video("copy")
+  ///   .output("with_music.mp4");
+  /// # Ok::<(), anyhow::Error>(())
+  /// ```
+  #[cfg(feature = "process")]
+  pub fn background_audio<V: AsRef<str>, A: AsRef<str>>(
+    &mut self,
+    video_input: V,
+    audio_input: A,
+    options: &BackgroundAudioOptions,
+  ) -> anyhow::Result<&mut Self> {
+    let video_input = video_input.as_ref();
+    let media = probe_cached(video_input)?;
+    let video = media
+      .streams_of_type("video")
+      .next()
+      .ok_or_else(|| anyhow::anyhow!("{video_input} has no video stream"))?;
+    let video_duration = video
+      .duration
+      .or(media.duration)
+      .filter(|duration| duration.is_finite() && *duration > 0.0)
+      .ok_or_else(|| anyhow::anyhow!("the duration of the video {video_input} is unknown"))?;
+    let video_has_audio = media.streams_of_type("audio").next().is_some();
+
+    let video_input_index = self.iter_args().filter(|arg| *arg == "-i").count();
+    self.input(video_input);
+    self.input_with(audio_input, |input| {
+      input.loop_input(LoopCount::Forever);
+    });
+    self.filter_complex(background_audio_filter(
+      video_input_index,
+      video_has_audio,
+      Duration::from_secs_f64(video_duration),
+      options,
+      BACKGROUND_OUTPUT_LABEL,
+    ));
+    self.push_args([
+      "-map",
+      &format!("{video_input_index}:v"),
+      "-map",
+      &format!("[{BACKGROUND_OUTPUT_LABEL}]"),
+    ]);
+    Ok(self.shortest())
+  }
+
+  /// Add `input_audio` as an input, and render its audio as a waveform video
+  /// (see [`waveform_filter`]). The video is mapped as `-map [waveform]`,
+  /// followed by the audio itself, so progress follows the audio timeline.
+  /// Call this before adding the output.
+  ///
+  /// ```rust,no_run
+  /// use ffmpeg_sidecar::{command::FfmpegCommand, visualize::VisualOptions};
+  ///
+  /// FfmpegCommand::new()
+  ///   .visualize_waveform("episode.mp3", &VisualOptions::default())
+  ///   .codec_video("libx264")
+  ///   .codec_audio("aac")
+  ///   .output("episode.mp4");
+  /// ```
+  pub fn visualize_waveform<S: AsRef<str>>(
+    &mut self,
+    input_audio: S,
+    options: &VisualOptions,
+  ) -> &mut Self {
+    let index = self.iter_args().filter(|arg| *arg == "-i").count();
+    self.input(input_audio);
+    self.filter_complex(waveform_filter(
+      &format!("{index}:a"),
+      WAVEFORM_OUTPUT_LABEL,
+      options,
+    ));
+    self.push_args([
+      "-map",
+      &format!("[{WAVEFORM_OUTPUT_LABEL}]"),
+      "-map",
+      &format!("{index}:a"),
+    ])
+  }
+
+  /// Add `input_audio` as an input, and write a spectrogram of all of it to
+  /// the image at `path` (see [`spectrogram_filter`]), e.g. a PNG.
+  ///
+  /// The picture is only produced once all the audio has been read, so the
+  /// audio is also decoded to a second, `null` output, which makes progress
+  /// follow the audio timeline instead of staying at zero. This adds both
+  /// outputs, so only global options like [`overwrite`](Self::overwrite) can
+  /// follow.
+  ///
+  /// ```rust,no_run
+  /// use ffmpeg_sidecar::{command::FfmpegCommand, visualize::SpectrogramOptions};
+  ///
+  /// FfmpegCommand::new()
+  ///   .spectrogram_image("episode.mp3", "spectrogram.png", &SpectrogramOptions::default())
+  ///   .overwrite()
+  ///   .spawn()
+  ///   .unwrap()
+  ///   .wait()
+  ///   .unwrap();
+  /// ```
+  pub fn spectrogram_image<S: AsRef<str>, P: AsRef<str>>(
+    &mut self,
+    input_audio: S,
+    path: P,
+    options: &SpectrogramOptions,
+  ) -> &mut Self {
+    let index = self.iter_args().filter(|arg| *arg == "-i").count();
+    self.input(input_audio);
+    self.filter_complex(spectrogram_filter(
+      &format!("{index}:a"),
+      SPECTROGRAM_OUTPUT_LABEL,
+      options,
+    ));
+    self
+      .push_args(["-map", &format!("[{SPECTROGRAM_OUTPUT_LABEL}]")])
+      .push_args(["-frames:v", "1", "-update", "1"])
+      .output(path)
+      .push_args(["-map", &format!("{index}:a"), "-f", "null"])
+      .output("-")
+  }
+
+  /// Write the closed captions embedded in the video of the last input, as
+  /// in broadcast TS captures, to `output_srt`. The input is replaced with a
+  /// `lavfi` [`caption_source`], whose caption stream is mapped to the
+  /// output. Options for the input, like a seek, still apply.
+  ///
+  /// Whether an input has captions is shown by
+  /// [`AVStream::has_closed_captions`].
+  ///
+  /// ```rust
+  /// use ffmpeg_sidecar::command::FfmpegCommand;
+  ///
+  /// let mut command = FfmpegCommand::new();
+  /// command.input("news.ts").extract_captions("news.srt").unwrap();
+  /// let args = command.get_args().collect::<Vec<_>>();
+  /// assert_eq!(
+  ///   args[2..],
+  ///   ["-f", "lavfi", "-i", "movie=news.ts[out0+subcc]", "-map", "0:1", "news.srt"]
+  /// );
+  /// ```
+  pub fn extract_captions<S: AsRef<str>>(&mut self, output_srt: S) -> anyhow::Result<&mut Self> {
+    let args = &mut self.args;
+    let input_index = args
+      .iter()
+      .rposition(|arg| *arg == "-i")
+      .filter(|i| i + 1 < args.len())
+      .ok_or_else(|| anyhow::anyhow!("extract_captions must be called after adding an input"))?;
+    let index = args[..input_index]
+      .iter()
+      .filter(|arg| *arg == "-i")
+      .count();
+    let source = caption_source(&args[input_index + 1].to_string_lossy());
+    args.splice(
+      input_index..input_index + 2,
+      ["-f", "lavfi", "-i", &source].map(OsString::from),
+    );
+
+    // Arguments from the input on moved by the two inserted before it
+    for i in self.tracked_indices() {
+      if *i >= input_index {
+        *i += 2;
+      }
+    }
+    Ok(
+      self
+        .push_args(["-map", &format!("{index}:1")])
+        .output(output_srt),
+    )
+  }
+
+  /// Crop the video to `rect` with a `-vf` filter. Only an empty rect is
+  /// rejected here; ffmpeg fails at startup if the rect doesn't fit inside
+  /// the input. See [`crop_filter`].
+  pub fn crop(&mut self, rect: Rect) -> anyhow::Result<&mut Self> {
+    let filter = crop_filter(rect)?;
+    Ok(self.push_args(["-vf", &filter]))
+  }
+
+  //// Video option aliases
+  //// https://ffmpeg.org/ffmpeg.html#Video-Options
+
+  /// Alias for '-crf:v' argument.
+  ///
+  /// Set CRF (Constant Rate Factor) for quality-based VBR (Variable BitRate)
+  ///
+  /// Use this rate control mode if you want to keep the best quality and care
+  /// less about the file size. Lower values means better quality with
+  /// bigger average bitrate (0 usually means lossless).
+  ///
+  /// Possible values depend on codec:
+  ///   * 0-51 for h264 (default is 23), see [ffmpeg encoding guide for h264
+  ///     for more details](https://trac.ffmpeg.org/wiki/Encode/H.264#crf)
+  ///   * 0-51 for h265 (default is 28), see [ffmpeg encoding guide for h265
+  ///     for more details](https://trac.ffmpeg.org/wiki/Encode/H.265#ConstantRateFactorCRF)
+  ///   * 0-63 for vp9  (no default, 31 is recommended for 1080p HD video),
+  ///     see [ffmpeg encoding guide for vp9 for more details](https://trac.ffmpeg.org/wiki/Encode/VP9#constrainedq)
+  ///   * 0-63 for av1(libaom-av1) (no default), see [ffmpeg encoding guide
+  ///     for libaom for more details](https://trac.ffmpeg.org/wiki/Encode/AV1#ConstantQuality)
+  ///   * 0-63 for av1(libsvtav1) (default is 30), see [ffmpeg encoding guide
+  ///     for svt-av1 for mode details](https://trac.ffmpeg.org/wiki/Encode/AV1#CRF)
+  ///
+  pub fn crf(&mut self, crf: u32) -> &mut Self {
+    self.push_arg("-crf:v");
+    self.push_arg(crf.to_string());
+    self
+  }
+
+  /// Alias for `-frames:v` argument.
+  ///
+  /// Stop writing to the stream after `framecount` frames.
+  ///
+  /// See also: `-frames:a` (audio), `-frames:d` (data).
+  pub fn frames(&mut self, framecount: u32) -> &mut Self {
+    self.push_arg("-frames:v");
+    self.push_arg(framecount.to_string());
+    self
+  }
+
+  /// Alias for `-preset:v` argument.
+  ///
+  /// Set preset which is basically trade-off between encoding speed and
+  /// compression ratio.
+  ///
+  /// For h264 and h265 allowed values are:
+  ///   * ultrafast
+  ///   * superfast
+  ///   * veryfast
+  ///   * faster
+  ///   * medium (default preset)
+  ///   * slow
+  ///   * slower
+  ///   * veryslow
+  ///   * placebo
+  ///
+  /// For svt-av1 supported values 0-13 (higher number providing a higher
+  /// encoding speed). Prior to version 0.9.0 valid values was 0-8.
+  ///
+  /// For libaom supported values 0-11 (higher number providing a higher
+  /// encoding speed)
+  ///
+  /// VP9 has no presets
+  pub fn preset<S: AsRef<str>>(&mut self, preset: S) -> &mut Self {
+    self.push_arg("-preset:v");
+    self.push_arg(preset.as_ref());
+    self
+  }
+
+  /// Alias for `-r` argument.
+  ///
+  /// Set frame rate (Hz value, fraction or abbreviation).
+  ///
+  /// As an input option, ignore any timestamps stored in the file and instead
+  /// generate timestamps assuming constant frame rate `fps`. This is not the
+  /// same as the `-framerate` option used for some input formats like image2 or
+  /// v4l2 (it used to be the same in older versions of FFmpeg). If in doubt use
+  /// `-framerate` instead of the input option `-r`.
+  pub fn rate(&mut self, fps: f32) -> &mut Self {
+    self.push_arg("-r");
+    self.push_arg(fps.to_string());
+    self
+  }
+
+  /// Alias for `-r` argument, with an exact [`Rate`] like
+  /// [`Rate::NTSC`] instead of a rounded float. See [`rate`](Self::rate).
+  pub fn frame_rate(&mut self, rate: Rate) -> &mut Self {
+    self.push_arg("-r");
+    self.push_arg(rate.to_string());
+    self
+  }
+
+  /// Convert the output video to the constant frame rate `rate`, e.g. so
+  /// variable frame rate phone footage plays well in editors. See
+  /// [`CfrStrategy`] for the tradeoffs.
+  ///
+  /// ```rust,no_run
+  /// use ffmpeg_sidecar::{
+  ///   command::FfmpegCommand,
+  ///   frame_rate::{CfrStrategy, Rate},
+  /// };
+  ///
+  /// FfmpegCommand::new()
+  ///   .input("phone.mp4")
+  ///   .to_constant_frame_rate(Rate::NTSC, CfrStrategy::FilterFps)
+  ///   .output("edit.mp4");
+  /// ```
+  pub fn to_constant_frame_rate(&mut self, rate: Rate, strategy: CfrStrategy) -> &mut Self {
+    self.push_args(strategy.args(rate))
+  }
+
+  /// Alias for `-s` argument.
+  ///
+  /// Set frame size.
+  ///
+  /// As an input option, this is a shortcut for the `video_size` private
+  /// option, recognized by some demuxers for which the frame size is either not
+  /// stored in the file or is configurable – e.g. raw video or video grabbers.
+  ///
+  /// As an output option, this inserts the `scale` video filter to the end of
+  /// the corresponding filtergraph. Please use the `scale` filter directly to
+  /// insert it at the beginning or some other place.
+  ///
+  /// The format is `'wxh'` (default - same as source).
+  pub fn size(&mut self, width: u32, height: u32) -> &mut Self {
+    self.push_arg("-s");
+    self.push_arg(format!("{}x{}", width, height));
+    self
+  }
+
+  /// Alias for `-vn` argument.
+  ///
+  /// As an input option, blocks all video streams of a file from being filtered
+  /// or being automatically selected or mapped for any output. See `-discard`
+  /// option to disable streams individually.
+  ///
+  /// As an output option, disables video recording i.e. automatic selection or
+  /// mapping of any video stream. For full manual control see the `-map`
+  /// option.
+  pub fn no_video(&mut self) -> &mut Self {
+    self.push_arg("-vn");
+    self
+  }
+
+  //// Advanced video option aliases
+  //// https://ffmpeg.org/ffmpeg.html#Advanced-Video-options
+
+  /// Alias for `-pix_fmt` argument.
+  ///
+  /// Set pixel format. Use `-pix_fmts` to show all the supported pixel formats.
+  /// If the selected pixel format can not be selected, ffmpeg will print a
+  /// warning and select the best pixel format supported by the encoder. If
+  /// pix_fmt is prefixed by a `+`, ffmpeg will exit with an error if the
+  /// requested pixel format can not be selected, and automatic conversions
+  /// inside filtergraphs are disabled. If pix_fmt is a single `+`, ffmpeg
+  /// selects the same pixel format as the input (or graph output) and automatic
+  /// conversions are disabled.
+  pub fn pix_fmt<S: AsRef<str>>(&mut self, format: S) -> &mut Self {
+    self.push_arg("-pix_fmt");
+    self.push_arg(format.as_ref());
+    self
+  }
+
+  /// Convert the video of the next output to the pixel format `format`, like
+  /// [`pix_fmt`](Self::pix_fmt), unless the inputs already have it.
+  ///
+  /// `-pix_fmt` is added right away, so the arguments show the conversion.
+  /// When spawning, the inputs which are local files are probed with
+  /// ffprobe, and it's removed if every one of their video streams already
+  /// has this pixel format, e.g. so `-c:v copy` can be used for them. The
+  /// conversion is kept when an input can't be probed.
+  ///
+  /// ```rust
+  /// use ffmpeg_sidecar::command::FfmpegCommand;
+  ///
+  /// let mut command = FfmpegCommand::new();
+  /// command
+  ///   .input("camera-10bit.mov")
+  ///   .ensure_pix_fmt("yuv420p")
+  ///   .output("out.mp4");
+  /// let args = command.get_args().collect::<Vec<_>>();
+  /// assert_eq!(args[4..], ["-pix_fmt", "yuv420p", "out.mp4"]);
+  /// ```
+  pub fn ensure_pix_fmt<S: AsRef<str>>(&mut self, format: S) -> &mut Self {
+    self
+      .ensured_pix_fmts
+      .push((self.args.len(), format.as_ref().to_string()));
+    self.pix_fmt(format)
+  }
+
+  /// Alias for `-hwaccel` argument.
+  ///
+  /// Use hardware acceleration to decode the matching stream(s). The allowed
+  /// values of hwaccel are:
+  ///
+  /// - `none`: Do not use any hardware acceleration (the default).
+  /// - `auto`: Automatically select the hardware acceleration method.
+  /// - `vdpau`: Use VDPAU (Video Decode and Presentation API for Unix) hardware
+  ///   acceleration.
+  /// - `dxva2`: Use DXVA2 (DirectX Video Acceleration) hardware acceleration.
+  /// - `d3d11va`: Use D3D11VA (DirectX Video Acceleration) hardware
+  ///   acceleration.
+  /// - `vaapi`: Use VAAPI (Video Acceleration API) hardware acceleration.
+  /// - `qsv`: Use the Intel QuickSync Video acceleration for video transcoding.
+  ///   - Unlike most other values, this option does not enable accelerated
+  ///     decoding (that is used automatically whenever a qsv decoder is
+  ///     selected), but accelerated transcoding, without copying the frames
+  ///     into the system memory.
+  ///   - For it to work, both the decoder and the encoder must support QSV
+  ///     acceleration and no filters must be used.
+  ///
+  /// This option has no effect if the selected hwaccel is not available or not
+  /// supported by the chosen decoder.
+  ///
+  /// Note that most acceleration methods are intended for playback and will not
+  /// be faster than software decoding on modern CPUs. Additionally, `ffmpeg`
+  /// will usually need to copy the decoded frames from the GPU memory into the
+  /// system memory, resulting in further performance loss. This option is thus
+  /// mainly useful for testing.
+  pub fn hwaccel<S: AsRef<str>>(&mut self, hwaccel: S) -> &mut Self {
+    self.push_arg("-hwaccel");
+    self.push_arg(hwaccel.as_ref());
+    self
+  }
+
+  //// Audio option aliases
+  //// https://ffmpeg.org/ffmpeg.html#Audio-Options
+
+  /// Alias for `-an` argument.
+  ///
+  /// As an input option, blocks all audio streams of a file from being filtered
+  /// or being automatically selected or mapped for any output. See `-discard`
+  /// option to disable streams individually.
+  ///
+  /// As an output option, disables audio recording i.e. automatic selection or
+  /// mapping of any audio stream. For full manual control see the `-map`
+  /// option.
+  pub fn no_audio(&mut self) -> &mut Self {
+    self.push_arg("-an");
+    self
+  }
+
+  /// Alias for `-ar` argument.
+  ///
+  /// Set the audio sampling frequency. For output streams it is set by default
+  /// to the frequency of the corresponding input stream. For input streams
+  /// this option only makes sense for audio grabbing devices and raw
+  /// demuxers.
+  pub fn sample_rate(&mut self, hz: u32) -> &mut Self {
+    self.push_arg("-ar");
+    self.push_arg(hz.to_string());
+    self
+  }
+
+  /// Alias for `-ac` argument.
+  ///
+  /// Set the number of audio channels. For output streams it is set by
+  /// default to the number of input audio channels.
+  pub fn channels(&mut self, channels: u8) -> &mut Self {
+    self.push_arg("-ac");
+    self.push_arg(channels.to_string());
+    self
+  }
+
+  /// Alias for `-channel_layout` argument.
+  ///
+  /// Set the audio channel layout, e.g. to distinguish 5.1 from 6 arbitrary
+  /// channels. Use `ffmpeg -layouts` for the list of standard layouts.
+  pub fn channel_layout(&mut self, layout: ChannelLayout) -> &mut Self {
+    self.push_arg("-channel_layout");
+    self.push_arg(layout.as_str());
+    self
+  }
+
+  /// Alias for `-sample_fmt` argument.
+  ///
+  /// Set the audio sample format. Use `ffmpeg -sample_fmts` to get a list of
+  /// supported sample formats.
+  pub fn sample_format(&mut self, format: SampleFormat) -> &mut Self {
+    self.push_arg("-sample_fmt");
+    self.push_arg(format.as_str());
+    self
+  }
+
+  /// Applies the `aresample` audio filter (as `-af`) configured with
+  /// `options`, e.g. to correct drift with `async` or to choose a dither
+  /// method.
+  pub fn resample(&mut self, options: ResampleOptions) -> &mut Self {
+    self.push_arg("-af");
+    self.push_arg(options.to_filter());
+    self
+  }
+
+  /// Preset for raw interleaved PCM audio output. Equivalent to `-f <fmt>
+  /// -c:a pcm_<fmt> -ar <sample_rate> -ac <channels>`, where `<fmt>` is e.g.
+  /// `s16le` or `f32le` depending on `format`.
+  ///
+  /// The output path is not included; follow with `.pipe_stdout()` or
+  /// `.output(...)`. Each second of output is then exactly `sample_rate *
+  /// channels * format.bytes_per_sample()` bytes.
+  ///
+  /// Returns an error for planar sample formats (raw PCM is always
+  /// interleaved), or if a conflicting `-sample_fmt` was already set, since
+  /// e.g. `s16` samples can't be written by the `f32le` muxer.
+  ///
+  /// ```rust
+  /// use ffmpeg_sidecar::{audio::SampleFormat, command::FfmpegCommand};
+  ///
+  /// let mut command = FfmpegCommand::new();
+  /// assert!(command.pcm_output(SampleFormat::F32, 48000, 2).is_ok());
+  /// assert!(command.pcm_output(SampleFormat::F32p, 48000, 2).is_err());
+  /// assert!(FfmpegCommand::new()
+  ///   .sample_format(SampleFormat::S16)
+  ///   .pcm_output(SampleFormat::F32, 48000, 2)
+  ///   .is_err());
+  /// ```
+  pub fn pcm_output(
+    &mut self,
+    format: SampleFormat,
+    sample_rate: u32,
+    channels: u8,
+  ) -> anyhow::Result<&mut Self> {
+    let Some(muxer) = format.pcm_muxer() else {
+      anyhow::bail!(
+        "Raw PCM output is interleaved; planar sample format `{format}` is not supported"
+      );
+    };
+
+    let args = self.iter_args().collect::<Vec<_>>();
+    let sample_fmt = args
+      .windows(2)
+      .rev()
+      .find(|pair| pair[0] == "-sample_fmt")
+      .map(|pair| pair[1].to_string_lossy());
+    if let Some(sample_fmt) = sample_fmt {
+      if sample_fmt != format.as_str() {
+        anyhow::bail!(
+          "Sample format `{sample_fmt}` conflicts with the `{muxer}` muxer, which requires `{format}`"
+        );
+      }
+    }
+
+    self.format(muxer);
+    self.codec_audio(format!("pcm_{muxer}"));
+    self.sample_rate(sample_rate);
+    self.channels(channels);
+    Ok(self)
+  }
+
+  //// Advanced option aliases
+  //// https://ffmpeg.org/ffmpeg.html#Advanced-options
+
+  /// Alias for `-map` argument.
+  ///
+  /// Create one or more streams in the output file. This option has two forms
+  /// for specifying the data source(s): the first selects one or more streams
+  /// from some input file (specified with `-i`), the second takes an output
+  /// from some complex filtergraph (specified with `-filter_complex` or
+  /// `-filter_complex_script`).
+  ///
+  /// In the first form, an output stream is created for every stream from the
+  /// input file with the index input_file_id. If stream_specifier is given,
+  /// only those streams that match the specifier are used (see the [Stream
+  /// specifiers](https://ffmpeg.org/ffmpeg.html#Stream-specifiers) section for
+  /// the stream_specifier syntax).
+  ///
+  /// A `-` character before the stream identifier creates a "negative" mapping.
+  /// It disables matching streams from already created mappings.
+  ///
+  /// A trailing `?` after the stream index will allow the map to be optional:
+  /// if the map matches no streams the map will be ignored instead of failing.
+  /// Note the map will still fail if an invalid input file index is used; such
+  /// as if the map refers to a non-existent input.
+  ///
+  /// An alternative `[linklabel]` form will map outputs from complex filter
+  /// graphs (see the `-filter_complex` option) to the output file. `linklabel`
+  /// must correspond to a defined output link label in the graph.
+  ///
+  /// This option may be specified multiple times, each adding more streams to
+  /// the output file. Any given input stream may also be mapped any number of
+  /// times as a source for different output streams, e.g. in order to use
+  /// different encoding options and/or filters. The streams are created in the
+  /// output in the same order in which the `-map` options are given on the
+  /// commandline.
+  ///
+  /// Using this option disables the default mappings for this output file.
+  pub fn map<S: AsRef<str>>(&mut self, map_string: S) -> &mut Self {
+    self.push_arg("-map");
+    self.push_arg(map_string.as_ref());
+    self
+  }
+
+  /// Map the audio streams of the last input in `languages`, going by their
+  /// `language` tag, in that order. When spawning, the input is probed with
+  /// ffprobe and this is replaced by a `-map <input>:a:<n>` for each stream.
+  /// Spawning fails with
+  /// [`MissingLanguages`](crate::language::MissingLanguages) if the input has no stream in
+  /// one of the languages, see
+  /// [`select_by_language`](Self::select_by_language) to skip them instead.
+  ///
+  /// Like any `-map`, this disables the default mappings for the output, so
+  /// the other streams it needs have to be mapped too.
+  ///
+  /// ```rust
+  /// use ffmpeg_sidecar::command::FfmpegCommand;
+  ///
+  /// let mut command = FfmpegCommand::new();
+  /// command
+  ///   .input("master.mkv")
+  ///   .map("0:v")
+  ///   .select_audio_by_language(&["eng", "spa"])
+  ///   .codec_audio("copy")
+  ///   .output("release.mkv");
+  /// // `-map 0:a:2 -map 0:a:0` once spawned, if English is the third track
+  /// let args = command.get_args().collect::<Vec<_>>();
+  /// assert_eq!(args[4..], ["-map", "0:v", "-c:a", "copy", "release.mkv"]);
+  /// ```
+  pub fn select_audio_by_language<L: Into<Language> + Clone>(
+    &mut self,
+    languages: &[L],
+  ) -> &mut Self {
+    self.select_by_language(LanguageSelection::new(StreamKind::Audio, languages))
+  }
+
+  /// Like [`select_audio_by_language`](Self::select_audio_by_language), for
+  /// the video streams.
+  pub fn select_video_by_language<L: Into<Language> + Clone>(
+    &mut self,
+    languages: &[L],
+  ) -> &mut Self {
+    self.select_by_language(LanguageSelection::new(StreamKind::Video, languages))
+  }
+
+  /// Like [`select_audio_by_language`](Self::select_audio_by_language), for
+  /// the subtitle streams.
+  pub fn select_subtitles_by_language<L: Into<Language> + Clone>(
+    &mut self,
+    languages: &[L],
+  ) -> &mut Self {
+    self.select_by_language(LanguageSelection::new(StreamKind::Subtitle, languages))
+  }
+
+  /// Map the streams of the last input selected by `selection`, or of the
+  /// first input if none was added yet, e.g. with `allow_missing`.
+  ///
+  /// ```rust
+  /// use ffmpeg_sidecar::{
+  ///   command::FfmpegCommand,
+  ///   extract::StreamKind,
+  ///   language::{Language, LanguageSelection},
+  /// };
+  ///
+  /// let mut commentary = LanguageSelection::new(StreamKind::Audio, &["eng"]);
+  /// commentary.languages.push(Language::Untagged);
+  /// commentary.allow_missing = true;
+  /// FfmpegCommand::new()
+  ///   .input("master.mkv")
+  ///   .select_by_language(commentary)
+  ///   .output("audio.mka");
+  /// ```
+  pub fn select_by_language(&mut self, selection: LanguageSelection) -> &mut Self {
+    self.language_selections.push((self.args.len(), selection));
+    self
+  }
+
+  /// Map the streams of a program of the last input, or of the first input
+  /// if none was added yet, like a channel of a DVB capture. Any other
+  /// `-map` makes ffmpeg skip the streams it would pick by default.
+  ///
+  /// A program number is mapped right away, as `-map 0:p:<id>`. A name is
+  /// matched with the `service_name` tag of the programs, probing the input
+  /// before spawning, which fails with an
+  /// [`UnknownProgram`](crate::program::UnknownProgram) error if none has it.
+  ///
+  /// ```rust
+  /// use ffmpeg_sidecar::command::FfmpegCommand;
+  ///
+  /// let mut command = FfmpegCommand::new();
+  /// command
+  ///   .input("capture.ts")
+  ///   .select_program(4164)
+  ///   .codec_video("copy")
+  ///   .output("bbc_one.ts");
+  /// let args = command.get_args().collect::<Vec<_>>();
+  /// assert_eq!(args[4..6], ["-map", "0:p:4164"]);
+  ///
+  /// // `-map 0:p:4287` once spawned, if that's the number of BBC TWO HD
+  /// FfmpegCommand::new()
+  ///   .input("capture.ts")
+  ///   .select_program("BBC TWO HD")
+  ///   .output("bbc_two.ts");
+  /// ```
+  pub fn select_program<P: Into<Program>>(&mut self, program: P) -> &mut Self {
+    match program.into() {
+      Program::Id(id) => {
+        let inputs = self.args.iter().filter(|arg| *arg == "-i").count();
+        self.map(format!("{}:p:{id}", inputs.saturating_sub(1)))
+      }
+      program => {
+        self.program_selections.push((self.args.len(), program));
+        self
+      }
+    }
+  }
+
+  /// Alias for `-readrate` argument.
+  ///
+  /// Limit input read speed.
+  ///
+  /// Its value is a floating-point positive number which represents the maximum
+  /// duration of media, in seconds, that should be ingested in one second of
+  /// wallclock time. Default value is zero and represents no imposed limitation
+  /// on speed of ingestion. Value `1` represents real-time speed and is
+  /// equivalent to `-re`.
+  ///
+  /// Mainly used to simulate a capture device or live input stream (e.g. when
+  /// reading from a file). Should not be used with a low value when input is an
+  /// actual capture device or live stream as it may cause packet loss.
+  ///
+  /// It is useful for when flow speed of output packets is important, such as
+  /// live streaming.
+  pub fn readrate(&mut self, speed: f32) -> &mut Self {
+    self.push_arg("-readrate");
+    self.push_arg(speed.to_string());
+    self
+  }
+
+  /// Alias for `-re`.
+  ///
+  /// Read input at native frame rate. This is equivalent to setting `-readrate
+  /// 1`.
+  pub fn realtime(&mut self) -> &mut Self {
+    self.push_arg("-re");
+    self
+  }
+
+  /// Alias for `-fps_mode` argument.
+  ///
+  /// Set video sync method / framerate mode. vsync is applied to all output
+  /// video streams but can be overridden for a stream by setting fps_mode.
+  /// vsync is deprecated and will be removed in the future.
+  ///
+  /// For compatibility reasons some of the values for vsync can be specified as
+  /// numbers (shown in parentheses in the following table).
+  ///
+  /// - `passthrough` (`0`): Each frame is passed with its timestamp from the
+  ///   demuxer to the muxer.
+  /// - `cfr` (`1`): Frames will be duplicated and dropped to achieve exactly
+  ///   the requested constant frame rate.
+  /// - `vfr` (`2`): Frames are passed through with their timestamp or dropped
+  ///   so as to prevent 2 frames from having the same timestamp.
+  /// - `drop`: As passthrough but destroys all timestamps, making the muxer
+  ///   generate fresh timestamps based on frame-rate.
+  /// - `auto` (`-1`): Chooses between cfr and vfr depending on muxer
+  ///   capabilities. This is the default method.
+  ///
+  /// These are also available as [`FpsMode`](crate::frame_rate::FpsMode).
+  pub fn fps_mode<S: AsRef<str>>(&mut self, parameter: S) -> &mut Self {
+    self.push_arg("-fps_mode");
+    self.push_arg(parameter.as_ref());
+    self
+  }
+
+  /// Alias for `-bsf:v` argument.
+  ///
+  /// Set bitstream filters for matching streams. `bitstream_filters` is a
+  /// comma-separated list of bitstream filters. Use the `-bsfs` option to get
+  /// the list of bitstream filters.
+  ///
+  /// See also: `-bsf:s` (subtitles), `-bsf:a` (audio), `-bsf:d` (data)
+  pub fn bitstream_filter_video<S: AsRef<str>>(&mut self, bitstream_filters: S) -> &mut Self {
+    self.push_arg("-bsf:v");
+    self.push_arg(bitstream_filters.as_ref());
+    self
+  }
+
+  /// Alias for `-filter_complex` argument.
+  ///
+  /// Define a complex filtergraph, i.e. one with arbitrary number of inputs
+  /// and/or outputs. For simple graphs – those with one input and one output of
+  /// the same type – see the `-filter` options. `filtergraph` is a description
+  /// of the filtergraph, as described in the "Filtergraph syntax" section of
+  /// the ffmpeg-filters manual.
+  ///
+  /// Input link labels must refer to input streams using the
+  /// `[file_index:stream_specifier]` syntax (i.e. the same as `-map` uses). If
+  /// `stream_specifier` matches multiple streams, the first one will be used.
+  /// An unlabeled input will be connected to the first unused input stream of
+  /// the matching type.
+  ///
+  /// Output link labels are referred to with `-map`. Unlabeled outputs are
+  /// added to the first output file.
+  ///
+  /// Note that with this option it is possible to use only lavfi sources
+  /// without normal input files.
+  pub fn filter_complex<S: AsRef<str>>(&mut self, filtergraph: S) -> &mut Self {
+    self.push_arg("-filter_complex");
+    self.push_arg(filtergraph.as_ref());
+    self
+  }
+
+  /// Alias for `-threads` argument.
+  ///
+  /// The number of threads of the encoders of the next output, or of the
+  /// decoders of the next input when given before it. `0` lets each codec
+  /// pick, which is usually one thread per core.
+  pub fn threads(&mut self, count: usize) -> &mut Self {
+    self.push_arg("-threads");
+    self.push_arg(count.to_string())
+  }
+
+  /// Alias for `-filter_threads` argument.
+  ///
+  /// The number of threads of each simple filtergraph, like the ones of
+  /// `-vf` and `-af`. `0` uses one per core. A global option.
+  pub fn filter_threads(&mut self, count: usize) -> &mut Self {
+    self.push_arg("-filter_threads");
+    self.push_arg(count.to_string())
+  }
+
+  /// Alias for `-filter_complex_threads` argument.
+  ///
+  /// The number of threads of each complex filtergraph, see
+  /// [`filter_complex`](Self::filter_complex). `0` uses one per core. A
+  /// global option.
+  pub fn filter_complex_threads(&mut self, count: usize) -> &mut Self {
+    self.push_arg("-filter_complex_threads");
+    self.push_arg(count.to_string())
+  }
+
+  /// Limit the threads of the filters and of the encoders of the next
+  /// output to `budget`: a fraction of the logical cores like `0.5`, or a
+  /// number of cores like `2usize`. See
+  /// [`JobQueue::cpu_budget`](crate::queue::JobQueue::cpu_budget) to share
+  /// a budget between jobs.
+  ///
+  /// ```rust
+  /// use ffmpeg_sidecar::{args::FfmpegArgs, cpu_budget::CpuBudget};
+  ///
+  /// let mut args = FfmpegArgs::new();
+  /// args.input("in.mov").cpu_budget(CpuBudget::Cores(2)).output("out.mp4");
+  /// assert_eq!(
+  ///   args.to_vec(),
+  ///   [
+  ///     "-i", "in.mov", "-filter_threads", "2", "-filter_complex_threads", "2",
+  ///     "-threads", "2", "out.mp4"
+  ///   ]
+  /// );
+  /// ```
+  pub fn cpu_budget<B: Into<CpuBudget>>(&mut self, budget: B) -> &mut Self {
+    let threads = budget.into().cores();
+    self
+      .filter_threads(threads)
+      .filter_complex_threads(threads)
+      .threads(threads)
+  }
+
+  /// Handle the rotation metadata of the most recently added input (usually
+  /// phone footage), so that the output comes out the right way up regardless
+  /// of the ffmpeg version's defaults. Must be called after `input`.
+  ///
+  /// The input is inspected with ffprobe, and the arguments depend on whether
+  /// ffmpeg's automatic rotation was disabled for it with `-noautorotate`
+  /// (see [`InputOptions::no_autorotate`]):
+  ///
+  /// | Policy | Autorotate on | `-noautorotate` |
+  /// |-|-|-|
+  /// | [`Bake`](RotationPolicy::Bake) | strip the tag | `-vf transpose`, strip the tag |
+  /// | [`Preserve`](RotationPolicy::Preserve) | `-vf` undoing the rotation, keep the tag | keep the tag |
+  ///
+  /// The tag is written as `-metadata:s:v:0 rotate=<degrees>`, which the MP4
+  /// and MOV muxers store as a display matrix. Since this may set `-vf`, it
+  /// can't be combined with other `-vf` filters or stream copy; when copying,
+  /// rotation metadata is always preserved anyway.
+  ///
+  /// ```rust,no_run
+  /// use ffmpeg_sidecar::{command::FfmpegCommand, rotation::RotationPolicy};
+  ///
+  /// FfmpegCommand::new()
+  ///   .input("phone.mov")
+  ///   .auto_rotate(RotationPolicy::Bake)
+  ///   .unwrap()
+  ///   .output("upright.mp4");
+  /// ```
+  #[cfg(feature = "process")]
+  pub fn auto_rotate(&mut self, policy: RotationPolicy) -> anyhow::Result<&mut Self> {
+    let args = self.iter_args().collect::<Vec<_>>();
+    let input_index = args
+      .iter()
+      .rposition(|arg| *arg == "-i")
+      .filter(|i| i + 1 < args.len())
+      .ok_or_else(|| anyhow::anyhow!("auto_rotate must be called after adding an input"))?;
+    let input = args[input_index + 1].to_os_string();
+
+    // Input options are those after the previous input
+    let options_start = args[..input_index]
+      .iter()
+      .rposition(|arg| *arg == "-i")
+      .map_or(0, |i| i + 2);
+    let options = &args[options_start..input_index];
+    let autorotate = !options.iter().any(|arg| *arg == "-noautorotate")
+      && !options
+        .windows(2)
+        .any(|pair| pair[0] == "-autorotate" && pair[1] == "0");
+
+    let degrees = ffprobe_rotation(&input)?;
+    match (policy, autorotate) {
+      (RotationPolicy::Bake, true) => {}
+      (RotationPolicy::Bake, false) => {
+        if let Some(filter) = rotation_filter(degrees) {
+          self.push_args(["-vf", filter]);
+        }
+      }
+      (RotationPolicy::Preserve, true) => {
+        if let Some(filter) = rotation_filter((360 - degrees) % 360) {
+          self.push_args(["-vf", filter]);
+        }
+      }
+      (RotationPolicy::Preserve, false) => {}
+    }
+    let tag = match policy {
+      RotationPolicy::Bake => 0,
+      RotationPolicy::Preserve => degrees,
+    };
+    self.push_args(["-metadata:s:v:0", &format!("rotate={tag}")]);
+    Ok(self)
+  }
+
+  /// Alias for `-progress` argument: write machine readable progress to
+  /// `path` as blocks of `key=value` lines, every
+  /// [`stats_period`](Self::stats_period) (500ms by default). Read it with
+  /// [`ProgressFileReader`](crate::progress_file::ProgressFileReader).
+  pub fn progress_file<S: AsRef<str>>(&mut self, path: S) -> &mut Self {
+    self.push_arg("-progress");
+    self.push_arg(path.as_ref());
+    self
+  }
+
+  /// Alias for `-stats_period` argument: how often progress is logged, and
+  /// written to the [`progress_file`](Self::progress_file), instead of every
+  /// 500ms. Needs ffmpeg 4.4 or later; when spawning an
+  /// [`FfmpegCommand`](crate::command::FfmpegCommand) with an older version,
+  /// the option is left out and a
+  /// [`FfmpegEvent::Hint`](crate::event::FfmpegEvent::Hint) says so.
+  ///
+  /// To get fewer progress events from any version, see
+  /// [`FfmpegIterator::throttle_progress`](crate::iter::FfmpegIterator::throttle_progress).
+  ///
+  /// ```rust
+  /// use ffmpeg_sidecar::args::FfmpegArgs;
+  /// use std::time::Duration;
+  ///
+  /// let mut args = FfmpegArgs::new();
+  /// args.stats_period(Duration::from_millis(100));
+  /// assert_eq!(args.to_vec(), ["-stats_period", "0.1"]);
+  /// ```
+  pub fn stats_period(&mut self, period: Duration) -> &mut Self {
+    self.push_arg("-stats_period");
+    self.push_arg(period.as_secs_f64().to_string());
+    self
+  }
+
+  //// Preset argument sets for common use cases.
+
+  /// Write the output as consecutive files with the `segment` muxer, named
+  /// after `pattern`, e.g. `out%03d.ts`. Equivalent to `-f segment
+  /// -segment_time <duration> ... <pattern>`.
+  ///
+  /// Each finished segment is reported by the iterator as an
+  /// [`FfmpegEvent::SegmentComplete`](crate::event::FfmpegEvent::SegmentComplete),
+  /// once the next one is opened, or when ffmpeg exits for the last one.
+  ///
+  /// ```rust
+  /// use ffmpeg_sidecar::{command::FfmpegCommand, segment::SegmentOptions};
+  /// use std::time::Duration;
+  ///
+  /// let mut command = FfmpegCommand::new();
+  /// command.testsrc().output_segments(
+  ///   "out%03d.ts",
+  ///   SegmentOptions {
+  ///     duration: Duration::from_secs(10),
+  ///     ..Default::default()
+  ///   },
+  /// );
+  /// let args = command.get_args().collect::<Vec<_>>();
+  /// assert_eq!(args[args.len() - 5..], ["-f", "segment", "-segment_time", "10", "out%03d.ts"]);
+  /// ```
+  pub fn output_segments<S: AsRef<str>>(
+    &mut self,
+    pattern: S,
+    options: SegmentOptions,
+  ) -> &mut Self {
+    self.push_args(options.to_args());
+    self.output(pattern)
+  }
+
+  /// Generate a procedural test video. Equivalent to `ffmpeg -f lavfi -i
+  /// testsrc=duration=10`.
+  ///
+  /// [FFmpeg `testsrc` filter
+  /// documentation](https://ffmpeg.org/ffmpeg-filters.html#allrgb_002c-allyuv_002c-color_002c-colorchart_002c-colorspectrum_002c-haldclutsrc_002c-nullsrc_002c-pal75bars_002c-pal100bars_002c-rgbtestsrc_002c-smptebars_002c-smptehdbars_002c-testsrc_002c-testsrc2_002c-yuvtestsrc)
+  pub fn testsrc(&mut self) -> &mut Self {
+    self.push_args(["-f", "lavfi", "-i", "testsrc=duration=10"]);
+    self
+  }
+
+  /// Preset for emitting raw decoded video frames on stdout. Equivalent to `-f
+  /// rawvideo -pix_fmt rgb24 -`.
+  pub fn rawvideo(&mut self) -> &mut Self {
+    self.push_args(["-f", "rawvideo", "-pix_fmt", "rgb24", "-"]);
+    self
+  }
+
+  //// Raw arguments
+
+  /// Adds an argument to pass to the program.
+  ///
+  /// Identical to `arg` in [`std::process::Command`]. The options added this
+  /// way are checked against the other ones at spawn, see
+  /// [`arg_audit`](crate::arg_audit).
+  pub fn arg<S: AsRef<OsStr>>(&mut self, arg: S) -> &mut Self {
+    self.raw_args.push(self.args.len());
+    self.push_arg(arg)
+  }
+
+  /// Adds multiple arguments to pass to the program.
+  ///
+  /// Identical to `args` in [`std::process::Command`].
+  pub fn args<I, S>(&mut self, args: I) -> &mut Self
+  where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+  {
+    for arg in args {
+      self.arg(arg.as_ref());
+    }
+    self
+  }
+
+  /// Adds an argument for a typed option, which isn't audited as raw.
+  pub(crate) fn push_arg<S: AsRef<OsStr>>(&mut self, arg: S) -> &mut Self {
+    self.args.push(arg.as_ref().to_os_string());
+    self
+  }
+
+  pub(crate) fn push_args<I, S>(&mut self, args: I) -> &mut Self
+  where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+  {
+    for arg in args {
+      self.push_arg(arg.as_ref());
+    }
+    self
+  }
+
+  /// The conflicts between the options added with [`arg`](Self::arg) or
+  /// [`args`](Self::args) and the other options, with the
+  /// [`DEFAULT_COLLISIONS`] and `rules`. See [`audit_args`].
+  ///
+  /// ```rust
+  /// use ffmpeg_sidecar::args::FfmpegArgs;
+  ///
+  /// let mut args = FfmpegArgs::new();
+  /// args.input("in.mp4").seek(5.0).arg("-ss").arg("10").output("out.mp4");
+  /// let conflicts = args.audit(&[]);
+  /// assert_eq!(
+  ///   conflicts[0].to_string(),
+  ///   "`-ss 00:00:05` (typed builder) conflicts with `-ss 10` (raw args): \
+  ///    ffmpeg uses only the last -ss"
+  /// );
+  /// ```
+  pub fn audit(&self, rules: &[CollisionRule]) -> Vec<ArgConflict> {
+    let args = self
+      .iter_args()
+      .map(|arg| arg.to_string_lossy())
+      .collect::<Vec<_>>();
+    let rules = DEFAULT_COLLISIONS
+      .iter()
+      .chain(rules)
+      .copied()
+      .collect::<Vec<_>>();
+    audit_args(&args, &self.raw_args, &rules)
+  }
+}
+
+impl<S: AsRef<OsStr>> FromIterator<S> for FfmpegArgs {
+  /// Arguments built elsewhere, without any of the settings for later
+  /// outputs.
+  fn from_iter<I: IntoIterator<Item = S>>(args: I) -> Self {
+    FfmpegArgs::new().with_args(args)
+  }
+}
+
+impl From<FfmpegArgs> for Vec<OsString> {
+  fn from(args: FfmpegArgs) -> Self {
+    args.args
+  }
+}
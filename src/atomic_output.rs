@@ -0,0 +1,145 @@
+//! Writing the outputs of a command to temporary files next to them, renamed
+//! to their paths only once ffmpeg succeeded, with
+//! [`FfmpegCommand::atomic_output`](crate::command::FfmpegCommand::atomic_output).
+//! A job which fails, is killed or crashes never leaves a partial file at the
+//! output path, where something watching the directory could take it for a
+//! finished one.
+//!
+//! The temporary file is in the same directory as the output, so the rename
+//! stays on the same filesystem and replaces the output at once. Its name
+//! keeps the extension of the output last, like `name.tmp1234-0.mp4` for
+//! `name.mp4`, since ffmpeg picks the muxer from it.
+
+use std::{
+  ffi::OsString,
+  fmt, fs, io,
+  path::{Path, PathBuf},
+  sync::atomic::{AtomicU64, Ordering},
+};
+
+use crate::{integrity::VerifyReport, sanitize::protocol_of};
+
+/// The temporary file an output at `path` is written to, in the same
+/// directory and with the same extension, unique in this process.
+///
+/// ```rust
+/// use ffmpeg_sidecar::atomic_output::temp_output_path;
+/// use std::path::Path;
+///
+/// let temp = temp_output_path("videos/name.mp4");
+/// assert_eq!(temp.parent(), Some(Path::new("videos")));
+/// assert_eq!(temp.extension().unwrap(), "mp4");
+/// assert!(temp.file_name().unwrap().to_str().unwrap().starts_with("name.tmp"));
+/// assert_ne!(temp, temp_output_path("videos/name.mp4"));
+/// ```
+pub fn temp_output_path<P: AsRef<Path>>(path: P) -> PathBuf {
+  static COUNTER: AtomicU64 = AtomicU64::new(0);
+  let path = path.as_ref();
+  let token = format!(
+    "tmp{}-{}",
+    std::process::id(),
+    COUNTER.fetch_add(1, Ordering::Relaxed)
+  );
+  let name = match (path.file_stem(), path.extension()) {
+    (Some(stem), Some(extension)) => {
+      let mut name = OsString::from(stem);
+      name.push(format!(".{token}."));
+      name.push(extension);
+      name
+    }
+    _ => {
+      let mut name = path.file_name().map(OsString::from).unwrap_or_default();
+      name.push(format!(".{token}"));
+      name
+    }
+  };
+  path.with_file_name(name)
+}
+
+/// Whether the output `path` is a single local file which can be written to
+/// a temporary file and renamed, unlike stdout (`-`), other protocols like
+/// `pipe:1` or `rtmp://`, the `%d` patterns of the image and segment muxers,
+/// and devices.
+pub(crate) fn is_atomic_candidate(path: &str) -> bool {
+  !path.is_empty()
+    && path != "-"
+    && protocol_of(path).is_none_or(|protocol| protocol == "file")
+    && !path.contains('%')
+    && !path.starts_with("/dev/")
+    && !path.eq_ignore_ascii_case("nul")
+}
+
+/// The path of a `file:` output, without the protocol.
+pub(crate) fn local_path(path: &str) -> &str {
+  path.strip_prefix("file:").unwrap_or(path)
+}
+
+/// An output written to a temporary file, removed when dropped unless
+/// [`finalize`](Self::finalize)d.
+#[derive(Debug)]
+pub(crate) struct PendingOutput {
+  temp: PathBuf,
+  path: PathBuf,
+  finalized: bool,
+}
+
+impl PendingOutput {
+  pub(crate) fn new(temp: PathBuf, path: PathBuf) -> Self {
+    Self {
+      temp,
+      path,
+      finalized: false,
+    }
+  }
+
+  pub(crate) fn temp(&self) -> &Path {
+    &self.temp
+  }
+
+  pub(crate) fn path(&self) -> &Path {
+    &self.path
+  }
+
+  /// Rename the temporary file to the output path, replacing it.
+  pub(crate) fn finalize(mut self) -> io::Result<()> {
+    fs::rename(&self.temp, &self.path)?;
+    self.finalized = true;
+    Ok(())
+  }
+}
+
+impl Drop for PendingOutput {
+  fn drop(&mut self) {
+    if !self.finalized {
+      fs::remove_file(&self.temp).ok();
+    }
+  }
+}
+
+/// Returned (through `io::Error`, with the kind `InvalidData`) by
+/// [`FfmpegChild::wait`](crate::child::FfmpegChild::wait) when an output of
+/// [`verify_atomic_output`](crate::command::FfmpegCommand::verify_atomic_output)
+/// doesn't decode cleanly. The output isn't renamed to `path`, and is
+/// removed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UnverifiedOutput {
+  pub path: PathBuf,
+  pub report: VerifyReport,
+}
+
+impl fmt::Display for UnverifiedOutput {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "{} failed verification with {} decode error(s)",
+      self.path.display(),
+      self.report.errors.len()
+    )?;
+    if let Some(error) = self.report.errors.first() {
+      write!(f, ", first: {}", error.message)?;
+    }
+    Ok(())
+  }
+}
+
+impl std::error::Error for UnverifiedOutput {}
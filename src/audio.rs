@@ -0,0 +1,238 @@
+//! Typed audio parameters used by the audio builder methods on
+//! [`FfmpegCommand`](crate::command::FfmpegCommand).
+
+use std::fmt;
+
+/// Audio sample format, corresponding to the `-sample_fmt` argument.
+///
+/// Obtained from `ffmpeg -sample_fmts`. The `*p` variants are planar (one
+/// buffer per channel) rather than interleaved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SampleFormat {
+  U8,
+  S16,
+  S32,
+  S64,
+  F32,
+  F64,
+  U8p,
+  S16p,
+  S32p,
+  S64p,
+  F32p,
+  F64p,
+}
+
+impl SampleFormat {
+  /// The name used by FFmpeg for this sample format.
+  ///
+  /// ```rust
+  /// use ffmpeg_sidecar::audio::SampleFormat;
+  /// assert_eq!(SampleFormat::F32.as_str(), "flt");
+  /// assert_eq!(SampleFormat::S16p.as_str(), "s16p");
+  /// ```
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      SampleFormat::U8 => "u8",
+      SampleFormat::S16 => "s16",
+      SampleFormat::S32 => "s32",
+      SampleFormat::S64 => "s64",
+      SampleFormat::F32 => "flt",
+      SampleFormat::F64 => "dbl",
+      SampleFormat::U8p => "u8p",
+      SampleFormat::S16p => "s16p",
+      SampleFormat::S32p => "s32p",
+      SampleFormat::S64p => "s64p",
+      SampleFormat::F32p => "fltp",
+      SampleFormat::F64p => "dblp",
+    }
+  }
+
+  /// Parse the name used by FFmpeg (e.g. in `-sample_fmts` or stream
+  /// descriptions in the logs) into a `SampleFormat`.
+  pub fn from_name(name: &str) -> Option<Self> {
+    match name {
+      "u8" => Some(SampleFormat::U8),
+      "s16" => Some(SampleFormat::S16),
+      "s32" => Some(SampleFormat::S32),
+      "s64" => Some(SampleFormat::S64),
+      "flt" => Some(SampleFormat::F32),
+      "dbl" => Some(SampleFormat::F64),
+      "u8p" => Some(SampleFormat::U8p),
+      "s16p" => Some(SampleFormat::S16p),
+      "s32p" => Some(SampleFormat::S32p),
+      "s64p" => Some(SampleFormat::S64p),
+      "fltp" => Some(SampleFormat::F32p),
+      "dblp" => Some(SampleFormat::F64p),
+      _ => None,
+    }
+  }
+
+  /// Size of a single sample of a single channel, in bytes.
+  pub fn bytes_per_sample(&self) -> u32 {
+    match self {
+      SampleFormat::U8 | SampleFormat::U8p => 1,
+      SampleFormat::S16 | SampleFormat::S16p => 2,
+      SampleFormat::S32 | SampleFormat::S32p | SampleFormat::F32 | SampleFormat::F32p => 4,
+      SampleFormat::S64 | SampleFormat::S64p | SampleFormat::F64 | SampleFormat::F64p => 8,
+    }
+  }
+
+  /// Whether samples are stored in one buffer per channel, rather than
+  /// interleaved.
+  pub fn is_planar(&self) -> bool {
+    matches!(
+      self,
+      SampleFormat::U8p
+        | SampleFormat::S16p
+        | SampleFormat::S32p
+        | SampleFormat::S64p
+        | SampleFormat::F32p
+        | SampleFormat::F64p
+    )
+  }
+
+  /// The raw PCM muxer (`-f`) for this sample format, which is also the name
+  /// of the matching `pcm_*` codec without its prefix. Multi-byte formats use
+  /// little-endian byte order.
+  ///
+  /// Raw PCM is always interleaved, so planar formats return `None`.
+  ///
+  /// ```rust
+  /// use ffmpeg_sidecar::audio::SampleFormat;
+  /// assert_eq!(SampleFormat::S16.pcm_muxer(), Some("s16le"));
+  /// assert_eq!(SampleFormat::F32.pcm_muxer(), Some("f32le"));
+  /// assert_eq!(SampleFormat::F32p.pcm_muxer(), None);
+  /// ```
+  pub fn pcm_muxer(&self) -> Option<&'static str> {
+    match self {
+      SampleFormat::U8 => Some("u8"),
+      SampleFormat::S16 => Some("s16le"),
+      SampleFormat::S32 => Some("s32le"),
+      SampleFormat::S64 => Some("s64le"),
+      SampleFormat::F32 => Some("f32le"),
+      SampleFormat::F64 => Some("f64le"),
+      _ => None,
+    }
+  }
+}
+
+impl fmt::Display for SampleFormat {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(self.as_str())
+  }
+}
+
+/// Audio channel layout, corresponding to the `-channel_layout` argument.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ChannelLayout {
+  Mono,
+  Stereo,
+  /// 5.1 surround (`FL+FR+FC+LFE+BL+BR`)
+  Surround51,
+  /// 7.1 surround (`FL+FR+FC+LFE+BL+BR+SL+SR`)
+  Surround71,
+  /// Any other layout name or channel list accepted by FFmpeg; see
+  /// `ffmpeg -layouts`.
+  Custom(String),
+}
+
+impl ChannelLayout {
+  /// The name used by FFmpeg for this channel layout.
+  pub fn as_str(&self) -> &str {
+    match self {
+      ChannelLayout::Mono => "mono",
+      ChannelLayout::Stereo => "stereo",
+      ChannelLayout::Surround51 => "5.1",
+      ChannelLayout::Surround71 => "7.1",
+      ChannelLayout::Custom(layout) => layout,
+    }
+  }
+
+  /// Number of channels in this layout, if known.
+  pub fn channels(&self) -> Option<u8> {
+    match self {
+      ChannelLayout::Mono => Some(1),
+      ChannelLayout::Stereo => Some(2),
+      ChannelLayout::Surround51 => Some(6),
+      ChannelLayout::Surround71 => Some(8),
+      ChannelLayout::Custom(_) => None,
+    }
+  }
+}
+
+impl fmt::Display for ChannelLayout {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(self.as_str())
+  }
+}
+
+/// Dither method used by the `aresample` filter when reducing bit depth.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DitherMethod {
+  Rectangular,
+  Triangular,
+  TriangularHp,
+  Lipshitz,
+  Shibata,
+  LowShibata,
+  HighShibata,
+  FWeighted,
+  EWeighted,
+  ModifiedEWeighted,
+}
+
+impl DitherMethod {
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      DitherMethod::Rectangular => "rectangular",
+      DitherMethod::Triangular => "triangular",
+      DitherMethod::TriangularHp => "triangular_hp",
+      DitherMethod::Lipshitz => "lipshitz",
+      DitherMethod::Shibata => "shibata",
+      DitherMethod::LowShibata => "low_shibata",
+      DitherMethod::HighShibata => "high_shibata",
+      DitherMethod::FWeighted => "f_weighted",
+      DitherMethod::EWeighted => "e_weighted",
+      DitherMethod::ModifiedEWeighted => "modified_e_weighted",
+    }
+  }
+}
+
+/// Options for the `aresample` audio filter.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResampleOptions {
+  /// Stretch/squeeze/fill the audio to keep it in sync with its timestamps,
+  /// compensating at most this many samples per second. `Some(1)` only fills
+  /// and trims at the start, without stretching.
+  pub async_samples: Option<u32>,
+  /// Dither method to use when converting to a lower bit depth.
+  pub dither: Option<DitherMethod>,
+}
+
+impl ResampleOptions {
+  /// The `aresample` filter string for these options.
+  ///
+  /// ```rust
+  /// use ffmpeg_sidecar::audio::{DitherMethod, ResampleOptions};
+  /// let options = ResampleOptions {
+  ///   async_samples: Some(1000),
+  ///   dither: Some(DitherMethod::Triangular),
+  /// };
+  /// assert_eq!(options.to_filter(), "aresample=async=1000:dither_method=triangular");
+  /// assert_eq!(ResampleOptions::default().to_filter(), "aresample");
+  /// ```
+  pub fn to_filter(&self) -> String {
+    let mut params = Vec::new();
+    if let Some(async_samples) = self.async_samples {
+      params.push(format!("async={async_samples}"));
+    }
+    if let Some(dither) = self.dither {
+      params.push(format!("dither_method={}", dither.as_str()));
+    }
+    match params.is_empty() {
+      true => "aresample".to_string(),
+      false => format!("aresample={}", params.join(":")),
+    }
+  }
+}
@@ -0,0 +1,273 @@
+//! Transcoding every media file in a directory with the same settings.
+
+use std::{
+  ffi::OsString,
+  fs, io,
+  path::{Path, PathBuf},
+};
+
+use crate::{command::FfmpegCommand, error::FfmpegErrorKind, queue::JobQueue};
+
+type MapCommand = Box<dyn Fn(&Path, &Path) -> FfmpegCommand>;
+
+/// Transcodes the files of `input_dir` into `output_dir`, skipping any that
+/// are already up to date.
+///
+/// ```rust,no_run
+/// use ffmpeg_sidecar::{batch::BatchTranscode, command::FfmpegCommand};
+///
+/// let reports = BatchTranscode::new("videos", "videos/small")
+///   .filter_extensions(&["mp4", "mov"])
+///   .output_extension("mp4")
+///   .output_suffix("_small")
+///   .max_concurrent(2)
+///   .map_command(|input, output| {
+///     let mut command = FfmpegCommand::new();
+///     command
+///       .input(input.to_string_lossy())
+///       .args(["-vf", "scale=-2:480"])
+///       .overwrite()
+///       .output(output.to_string_lossy());
+///     command
+///   })
+///   .run()
+///   .unwrap();
+///
+/// for report in reports {
+///   println!("{}: {:?}", report.input.display(), report.status);
+/// }
+/// ```
+pub struct BatchTranscode {
+  input_dir: PathBuf,
+  output_dir: PathBuf,
+  extensions: Vec<String>,
+  recursive: bool,
+  output_extension: Option<OsString>,
+  output_suffix: OsString,
+  max_concurrent: usize,
+  map_command: MapCommand,
+}
+
+/// A file found by [`BatchTranscode::plan`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchJob {
+  pub input: PathBuf,
+  pub output: PathBuf,
+  /// Whether the output already exists and is newer than the input, so the
+  /// file won't be transcoded again.
+  pub up_to_date: bool,
+}
+
+/// What happened to a single file of a batch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BatchStatus {
+  /// The output was already up to date.
+  Skipped,
+  Succeeded,
+  Failed {
+    /// The spawn error, or the error messages logged by ffmpeg.
+    message: String,
+    /// Recognized causes among the logged errors.
+    errors: Vec<FfmpegErrorKind>,
+  },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchReport {
+  pub input: PathBuf,
+  pub output: PathBuf,
+  pub status: BatchStatus,
+}
+
+impl BatchTranscode {
+  /// By default, every file directly inside `input_dir` is remuxed or
+  /// transcoded to a file of the same name in `output_dir`, with ffmpeg's
+  /// default settings for the output extension.
+  pub fn new<I: AsRef<Path>, O: AsRef<Path>>(input_dir: I, output_dir: O) -> Self {
+    Self {
+      input_dir: input_dir.as_ref().to_path_buf(),
+      output_dir: output_dir.as_ref().to_path_buf(),
+      extensions: Vec::new(),
+      recursive: false,
+      output_extension: None,
+      output_suffix: OsString::new(),
+      max_concurrent: 1,
+      map_command: Box::new(|input, output| {
+        let mut command = FfmpegCommand::new();
+        command
+          .input(input.to_string_lossy())
+          .overwrite()
+          .output(output.to_string_lossy());
+        command
+      }),
+    }
+  }
+
+  /// Only include files with one of these extensions (case insensitive,
+  /// without the dot). By default all files are included.
+  pub fn filter_extensions(&mut self, extensions: &[&str]) -> &mut Self {
+    self.extensions = extensions.iter().map(|ext| ext.to_lowercase()).collect();
+    self
+  }
+
+  /// Also include files in subdirectories, recreating the same directory
+  /// structure inside the output directory.
+  pub fn recursive(&mut self, recursive: bool) -> &mut Self {
+    self.recursive = recursive;
+    self
+  }
+
+  /// Replace the extension of output files, e.g. `mov` inputs to `mp4`.
+  pub fn output_extension<S: Into<OsString>>(&mut self, extension: S) -> &mut Self {
+    self.output_extension = Some(extension.into());
+    self
+  }
+
+  /// Append a suffix to the name of output files, before the extension.
+  pub fn output_suffix<S: Into<OsString>>(&mut self, suffix: S) -> &mut Self {
+    self.output_suffix = suffix.into();
+    self
+  }
+
+  /// Number of files transcoded at the same time. Defaults to 1.
+  pub fn max_concurrent(&mut self, max_concurrent: usize) -> &mut Self {
+    self.max_concurrent = max_concurrent;
+    self
+  }
+
+  /// Build the ffmpeg command for each file from its input and output path.
+  /// The command should overwrite existing outputs (`-y`), since outdated
+  /// outputs are transcoded again.
+  pub fn map_command<F>(&mut self, map_command: F) -> &mut Self
+  where
+    F: Fn(&Path, &Path) -> FfmpegCommand + 'static,
+  {
+    self.map_command = Box::new(map_command);
+    self
+  }
+
+  /// List the files which would be transcoded, and their outputs, without
+  /// running anything. Files are sorted by path.
+  pub fn plan(&self) -> io::Result<Vec<BatchJob>> {
+    let mut inputs = Vec::new();
+    self.collect_inputs(&self.input_dir, &mut inputs)?;
+    inputs.sort();
+
+    inputs
+      .into_iter()
+      .map(|input| {
+        let output = self.output_path(&input);
+        let up_to_date = is_up_to_date(&input, &output)?;
+        Ok(BatchJob {
+          input,
+          output,
+          up_to_date,
+        })
+      })
+      .collect()
+  }
+
+  /// Transcode every file which isn't up to date, blocking until all are
+  /// done. Returns a report for every planned file, including skipped ones.
+  pub fn run(&self) -> io::Result<Vec<BatchReport>> {
+    let plan = self.plan()?;
+
+    let pending = plan
+      .iter()
+      .filter(|job| !job.up_to_date)
+      .collect::<Vec<_>>();
+    for job in &pending {
+      if let Some(parent) = job.output.parent() {
+        fs::create_dir_all(parent)?;
+      }
+    }
+    let commands = pending
+      .iter()
+      .map(|job| (self.map_command)(&job.input, &job.output))
+      .collect::<Vec<_>>();
+    let mut outcomes = JobQueue::new(self.max_concurrent).run(commands).into_iter();
+
+    Ok(
+      plan
+        .into_iter()
+        .map(|job| {
+          let status = match job.up_to_date {
+            true => BatchStatus::Skipped,
+            false => match outcomes.next() {
+              Some(outcome) if outcome.is_success() => BatchStatus::Succeeded,
+              Some(outcome) => BatchStatus::Failed {
+                message: match outcome.result {
+                  Err(e) => e.to_string(),
+                  Ok(status) if outcome.errors.is_empty() => format!("ffmpeg exited with {status}"),
+                  Ok(_) => outcome.errors.join("\n"),
+                },
+                errors: outcome.summary.errors,
+              },
+              None => BatchStatus::Failed {
+                message: "job did not run".to_string(),
+                errors: Vec::new(),
+              },
+            },
+          };
+          BatchReport {
+            input: job.input,
+            output: job.output,
+            status,
+          }
+        })
+        .collect(),
+    )
+  }
+
+  fn collect_inputs(&self, dir: &Path, inputs: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+      let path = entry?.path();
+      if path.is_dir() {
+        // Don't descend into the output directory if it's nested in the input
+        if self.recursive && path != self.output_dir {
+          self.collect_inputs(&path, inputs)?;
+        }
+      } else if self.matches_extension(&path) {
+        inputs.push(path);
+      }
+    }
+    Ok(())
+  }
+
+  fn matches_extension(&self, path: &Path) -> bool {
+    if self.extensions.is_empty() {
+      return true;
+    }
+    path
+      .extension()
+      .map(|ext| ext.to_string_lossy().to_lowercase())
+      .is_some_and(|ext| self.extensions.contains(&ext))
+  }
+
+  fn output_path(&self, input: &Path) -> PathBuf {
+    let relative = input.strip_prefix(&self.input_dir).unwrap_or(input);
+    let mut file_name = relative.file_stem().unwrap_or_default().to_os_string();
+    file_name.push(&self.output_suffix);
+    let extension = self
+      .output_extension
+      .clone()
+      .or_else(|| relative.extension().map(|ext| ext.to_os_string()));
+    if let Some(extension) = extension {
+      file_name.push(".");
+      file_name.push(extension);
+    }
+    let mut output = self.output_dir.join(relative);
+    output.set_file_name(file_name);
+    output
+  }
+}
+
+/// Whether `output` exists and was modified after `input`.
+fn is_up_to_date(input: &Path, output: &Path) -> io::Result<bool> {
+  let output_modified = match fs::metadata(output) {
+    Ok(metadata) => metadata.modified()?,
+    Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+    Err(e) => return Err(e),
+  };
+  Ok(output_modified > fs::metadata(input)?.modified()?)
+}
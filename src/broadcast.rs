@@ -0,0 +1,246 @@
+//! Several consumers of the events of one ffmpeg, like a progress bar, a log
+//! window and an error monitor. See
+//! [`FfmpegChild::subscribe`](crate::child::FfmpegChild::subscribe).
+
+use std::{
+  collections::VecDeque,
+  fmt,
+  sync::{Arc, Condvar, Mutex, PoisonError, Weak},
+  time::{Duration, Instant},
+};
+
+use crate::{event::FfmpegEvent, iter::FfmpegIterator};
+
+/// How many events a subscription keeps for its receiver, by default.
+pub const SUBSCRIPTION_CAPACITY: usize = 1024;
+
+/// What a subscription receives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SubscribeOptions {
+  /// How many events are kept until they're received. Once it's full, the
+  /// oldest event is dropped for each new one. At least 1.
+  pub capacity: usize,
+  /// Also receive [`FfmpegEvent::OutputFrame`] and
+  /// [`FfmpegEvent::OutputChunk`], which are cloned for each subscription
+  /// that includes them.
+  pub include_frames: bool,
+}
+
+impl Default for SubscribeOptions {
+  fn default() -> Self {
+    Self {
+      capacity: SUBSCRIPTION_CAPACITY,
+      include_frames: false,
+    }
+  }
+}
+
+/// Returned by [`EventSubscription`] in place of the events it dropped since
+/// the last one received, because its buffer was full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Lagged(pub u64);
+
+impl fmt::Display for Lagged {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "subscription lagged behind and dropped {} events",
+      self.0
+    )
+  }
+}
+
+impl std::error::Error for Lagged {}
+
+/// The events of a child received by one subscriber, from when it subscribed
+/// until ffmpeg exits.
+///
+/// The events are read from ffmpeg by a single thread, which never waits for
+/// the subscribers: each subscription has a bounded buffer, and once it's
+/// full the oldest event is dropped to make room for the new one. The next
+/// receive then returns [`Lagged`] with the number of dropped events, before
+/// the events which were kept. A slow or abandoned subscriber therefore never
+/// blocks ffmpeg or the other subscribers, and dropping a subscription just
+/// stops delivering to it.
+///
+/// Iterating yields the events, or `Err(Lagged)`, and ends after the last
+/// event once ffmpeg's output is closed.
+pub struct EventSubscription {
+  queue: Arc<Queue>,
+}
+
+impl EventSubscription {
+  /// Wait for the next event, or `None` once all the events were received.
+  pub fn recv(&self) -> Option<Result<FfmpegEvent, Lagged>> {
+    let mut state = self.queue.lock();
+    loop {
+      if let Some(next) = state.take() {
+        return Some(next);
+      }
+      if state.closed {
+        return None;
+      }
+      state = self
+        .queue
+        .ready
+        .wait(state)
+        .unwrap_or_else(PoisonError::into_inner);
+    }
+  }
+
+  /// Like [`recv`](Self::recv), but giving up after `timeout`, which
+  /// returns `None` as well. Tell the two apart with
+  /// [`is_closed`](Self::is_closed).
+  pub fn recv_timeout(&self, timeout: Duration) -> Option<Result<FfmpegEvent, Lagged>> {
+    let deadline = Instant::now() + timeout;
+    let mut state = self.queue.lock();
+    loop {
+      if let Some(next) = state.take() {
+        return Some(next);
+      }
+      let now = Instant::now();
+      if state.closed || now >= deadline {
+        return None;
+      }
+      state = self
+        .queue
+        .ready
+        .wait_timeout(state, deadline - now)
+        .unwrap_or_else(PoisonError::into_inner)
+        .0;
+    }
+  }
+
+  /// The next event if one is buffered, without waiting.
+  pub fn try_recv(&self) -> Option<Result<FfmpegEvent, Lagged>> {
+    self.queue.lock().take()
+  }
+
+  /// Whether ffmpeg's output is closed and every event was received.
+  pub fn is_closed(&self) -> bool {
+    let state = self.queue.lock();
+    state.closed && state.events.is_empty() && state.lagged == 0
+  }
+}
+
+impl Iterator for EventSubscription {
+  type Item = Result<FfmpegEvent, Lagged>;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    self.recv()
+  }
+}
+
+struct Queue {
+  state: Mutex<QueueState>,
+  ready: Condvar,
+  options: SubscribeOptions,
+}
+
+struct QueueState {
+  events: VecDeque<FfmpegEvent>,
+  /// Dropped since the last receive
+  lagged: u64,
+  closed: bool,
+}
+
+impl Queue {
+  fn lock(&self) -> std::sync::MutexGuard<'_, QueueState> {
+    self.state.lock().unwrap_or_else(PoisonError::into_inner)
+  }
+}
+
+impl QueueState {
+  fn take(&mut self) -> Option<Result<FfmpegEvent, Lagged>> {
+    if self.lagged > 0 {
+      return Some(Err(Lagged(std::mem::take(&mut self.lagged))));
+    }
+    self.events.pop_front().map(Ok)
+  }
+}
+
+/// The subscriptions of a child, fed by its drain thread.
+#[derive(Default)]
+pub(crate) struct Broadcast {
+  subscribers: Mutex<Vec<Weak<Queue>>>,
+  /// Set by the drain thread at the end, so later subscriptions start closed
+  closed: Mutex<bool>,
+}
+
+impl Broadcast {
+  /// Start the thread which reads all the events of `iter` and sends them
+  /// to the subscriptions.
+  pub(crate) fn start(iter: FfmpegIterator) -> Arc<Self> {
+    let broadcast = Arc::new(Self::default());
+    let drain = broadcast.clone();
+    std::thread::spawn(move || {
+      for event in iter {
+        drain.send(event);
+      }
+      drain.close();
+    });
+    broadcast
+  }
+
+  pub(crate) fn subscribe(&self, options: SubscribeOptions) -> EventSubscription {
+    // Locked before reading `closed`, like in `close`
+    let mut subscribers = self
+      .subscribers
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner);
+    let queue = Arc::new(Queue {
+      state: Mutex::new(QueueState {
+        events: VecDeque::new(),
+        lagged: 0,
+        closed: *self.closed.lock().unwrap_or_else(PoisonError::into_inner),
+      }),
+      ready: Condvar::new(),
+      options: SubscribeOptions {
+        capacity: options.capacity.max(1),
+        ..options
+      },
+    });
+    subscribers.push(Arc::downgrade(&queue));
+    EventSubscription { queue }
+  }
+
+  fn send(&self, event: FfmpegEvent) {
+    let is_frame = matches!(
+      event,
+      FfmpegEvent::OutputFrame(_) | FfmpegEvent::OutputChunk(_)
+    );
+    let mut subscribers = self
+      .subscribers
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner);
+    // Forget the dropped subscriptions
+    subscribers.retain(|queue| queue.strong_count() > 0);
+    for queue in subscribers.iter().filter_map(Weak::upgrade) {
+      if is_frame && !queue.options.include_frames {
+        continue;
+      }
+      let mut state = queue.lock();
+      if state.events.len() == queue.options.capacity {
+        state.events.pop_front();
+        state.lagged += 1;
+      }
+      state.events.push_back(event.clone());
+      drop(state);
+      queue.ready.notify_all();
+    }
+  }
+
+  fn close(&self) {
+    // Locked before setting `closed`, so a subscription created meanwhile
+    // either starts closed or is closed here
+    let subscribers = self
+      .subscribers
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner);
+    *self.closed.lock().unwrap_or_else(PoisonError::into_inner) = true;
+    for queue in subscribers.iter().filter_map(Weak::upgrade) {
+      queue.lock().closed = true;
+      queue.ready.notify_all();
+    }
+  }
+}
@@ -0,0 +1,182 @@
+//! Bitstream filters (`-bsf`), which rewrite encoded packets without
+//! decoding them, e.g. to change how H.264 is packaged when stream copying
+//! between containers.
+
+use std::{ffi::OsStr, fmt, path::Path};
+
+use crate::container::ContainerFormat;
+
+/// A bitstream filter, along with its options.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Bsf {
+  /// Convert H.264 from the length-prefixed packaging of MP4, MOV, MKV and FLV
+  /// to the start codes expected by MPEG-TS and raw `.h264` files.
+  H264Mp4ToAnnexB,
+  /// Same as [`H264Mp4ToAnnexB`](Bsf::H264Mp4ToAnnexB), for HEVC.
+  HevcMp4ToAnnexB,
+  /// Convert AAC from ADTS, as in MPEG-TS and raw `.aac` files, to the
+  /// packaging of MP4 and MOV.
+  AacAdtsToAsc,
+  /// Rewrite H.264 headers, with options like `level=4.1`.
+  H264Metadata(String),
+  /// Rewrite HEVC headers, e.g. HDR signaling with
+  /// `colour_primaries=9:transfer_characteristics=16:matrix_coefficients=9`.
+  HevcMetadata(String),
+  /// Any other filter, with its options, like `filter_units=remove_types=6`.
+  Custom(String),
+}
+
+impl fmt::Display for Bsf {
+  /// Formats the filter as it appears in a `-bsf` value.
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let with_options = |f: &mut fmt::Formatter<'_>, name: &str, options: &str| match options {
+      "" => f.write_str(name),
+      options => write!(f, "{name}={options}"),
+    };
+    match self {
+      Bsf::H264Mp4ToAnnexB => f.write_str("h264_mp4toannexb"),
+      Bsf::HevcMp4ToAnnexB => f.write_str("hevc_mp4toannexb"),
+      Bsf::AacAdtsToAsc => f.write_str("aac_adtstoasc"),
+      Bsf::H264Metadata(options) => with_options(f, "h264_metadata", options),
+      Bsf::HevcMetadata(options) => with_options(f, "hevc_metadata", options),
+      Bsf::Custom(filter) => f.write_str(filter),
+    }
+  }
+}
+
+const ANNEXB_OUTPUTS: &[&str] = &["mpegts", "h264", "hevc", "ts", "m2ts", "mts", "264", "h265"];
+const MP4_OUTPUTS: &[&str] = &["mp4", "m4a", "m4v", "mov", "ipod", "3gp"];
+
+/// Check the arguments of a command for stream copies which are known to need
+/// a bitstream filter that isn't set, returning a warning for each.
+///
+/// This is a heuristic based on the file extensions and `-f` formats in the
+/// arguments. Recent versions of ffmpeg insert some of these filters
+/// automatically, but older ones fail or write unplayable files.
+///
+/// ```rust
+/// use ffmpeg_sidecar::bsf::bitstream_filter_warnings;
+///
+/// let args = ["-i", "input.mp4", "-c:v", "copy", "-f", "mpegts", "-"];
+/// assert_eq!(bitstream_filter_warnings(&args).len(), 1);
+///
+/// let args = ["-i", "input.mp4", "-c:v", "copy", "-bsf:v", "h264_mp4toannexb", "out.ts"];
+/// assert!(bitstream_filter_warnings(&args).is_empty());
+/// ```
+pub fn bitstream_filter_warnings<S: AsRef<OsStr>>(args: &[S]) -> Vec<String> {
+  bitstream_filter_warnings_with(args, |input| ContainerFormat::from_path(input))
+}
+
+/// Same as [`bitstream_filter_warnings`], with the format of each input
+/// given by `input_format` instead of its extension, e.g. from
+/// [`detect_format`](crate::container::detect_format).
+pub fn bitstream_filter_warnings_with<S, F>(args: &[S], input_format: F) -> Vec<String>
+where
+  S: AsRef<OsStr>,
+  F: Fn(&str) -> Option<ContainerFormat>,
+{
+  let args = args
+    .iter()
+    .map(|arg| arg.as_ref().to_string_lossy())
+    .collect::<Vec<_>>();
+  let value_of = |names: &[&str]| {
+    args
+      .windows(2)
+      .filter(|pair| names.contains(&pair[0].as_ref()))
+      .map(|pair| pair[1].to_string())
+      .collect::<Vec<_>>()
+  };
+  let extension = |path: &str| {
+    Path::new(path)
+      .extension()
+      .map(|extension| extension.to_string_lossy().to_ascii_lowercase())
+  };
+
+  let inputs = value_of(&["-i"]);
+  let last_input = args
+    .iter()
+    .rposition(|arg| arg == "-i")
+    .map_or(0, |i| i + 2);
+  let output_args = &args[last_input.min(args.len())..];
+  let output_format = output_args
+    .windows(2)
+    .rev()
+    .find(|pair| pair[0] == "-f")
+    .map(|pair| pair[1].to_string())
+    .or_else(|| output_args.last().and_then(|output| extension(output)));
+  let Some(output_format) = output_format else {
+    return Vec::new();
+  };
+
+  let copies = |kind: &str| {
+    value_of(&["-c", "-codec"])
+      .iter()
+      .chain(&value_of(&[
+        &format!("-c:{kind}"),
+        &format!("-codec:{kind}"),
+        &format!("-{kind}codec"),
+      ]))
+      .any(|codec| codec == "copy")
+  };
+  let has_bsf = |kind: &str, names: &[&str]| {
+    value_of(&["-bsf", &format!("-bsf:{kind}"), &format!("-{kind}bsf")])
+      .iter()
+      .any(|filters| names.iter().any(|name| filters.contains(name)))
+  };
+  let input_is = |is_format: fn(&ContainerFormat) -> bool| {
+    inputs
+      .iter()
+      .filter_map(|input| input_format(input))
+      .any(|format| is_format(&format))
+  };
+
+  let mut warnings = Vec::new();
+  if copies("v")
+    && ANNEXB_OUTPUTS.contains(&output_format.as_str())
+    && input_is(ContainerFormat::is_mp4_style)
+    && !has_bsf("v", &["h264_mp4toannexb", "hevc_mp4toannexb"])
+  {
+    warnings.push(format!(
+      "copying video from an MP4-style input to {output_format} needs `-bsf:v h264_mp4toannexb` \
+       (or `hevc_mp4toannexb` for HEVC)"
+    ));
+  }
+  if copies("a")
+    && MP4_OUTPUTS.contains(&output_format.as_str())
+    && input_is(ContainerFormat::is_adts)
+    && !has_bsf("a", &["aac_adtstoasc"])
+  {
+    warnings.push(format!(
+      "copying AAC audio from an ADTS input to {output_format} needs `-bsf:a aac_adtstoasc`"
+    ));
+  }
+  warnings
+}
+
+/// The fix suggested by ffmpeg's errors about a missing bitstream filter,
+/// e.g. `H.264 bitstream malformed, no startcode found`.
+///
+/// ```rust
+/// use ffmpeg_sidecar::bsf::try_parse_bsf_hint;
+///
+/// let line = "[mpegts @ 0x55d8c5a0e1c0] [error] H.264 bitstream malformed, no startcode found, use the video bitstream filter 'h264_mp4toannexb' to fix it ('-bsf:v h264_mp4toannexb' option with ffmpeg)";
+/// assert_eq!(
+///   try_parse_bsf_hint(line).unwrap(),
+///   "the video needs the bitstream filter `-bsf:v h264_mp4toannexb`"
+/// );
+/// ```
+pub fn try_parse_bsf_hint(line: &str) -> Option<String> {
+  let (kind, filter) = if line.contains("H.264 bitstream malformed") {
+    ("v", Bsf::H264Mp4ToAnnexB)
+  } else if line.contains("HEVC bitstream malformed") {
+    ("v", Bsf::HevcMp4ToAnnexB)
+  } else if line.contains("Malformed AAC bitstream detected") {
+    ("a", Bsf::AacAdtsToAsc)
+  } else {
+    return None;
+  };
+  let stream = if kind == "v" { "video" } else { "audio" };
+  Some(format!(
+    "the {stream} needs the bitstream filter `-bsf:{kind} {filter}`"
+  ))
+}
@@ -0,0 +1,28 @@
+//! Closed captions (EIA-608/CEA-708) carried inside video streams, as in
+//! broadcast MPEG-TS captures. See
+//! [`FfmpegCommand::extract_captions`](crate::command::FfmpegCommand::extract_captions).
+
+use crate::sanitize::escape_filter_value;
+
+/// The `lavfi` input which decodes the video of `path` and outputs its
+/// embedded captions as a subtitle stream, the second stream of the input.
+///
+/// ```rust
+/// use ffmpeg_sidecar::captions::caption_source;
+///
+/// assert_eq!(caption_source("news.ts"), "movie=news.ts[out0+subcc]");
+/// assert_eq!(
+///   caption_source(r"C:\rec\news [1].ts"),
+///   r"movie=C\\:\\\\rec\\\\news \[1\].ts[out0+subcc]"
+/// );
+/// ```
+pub fn caption_source(path: &str) -> String {
+  format!("movie={}[out0+subcc]", escape_filter_path(path))
+}
+
+/// Escape a path given as the value of a filter option, like the file of
+/// `movie` or `subtitles`, in a filtergraph. The same as
+/// [`escape_filter_value`].
+pub fn escape_filter_path(path: &str) -> String {
+  escape_filter_value(path)
+}
@@ -1,17 +1,99 @@
 use std::{
-  io::{self, Write},
+  io::{self, Read, Write},
+  ops::{Deref, DerefMut},
   process::{Child, ChildStderr, ChildStdin, ChildStdout, ExitStatus},
+  sync::{
+    mpsc::{channel, Receiver},
+    Arc, Mutex, MutexGuard,
+  },
+  thread::JoinHandle,
+  time::{Duration, Instant},
 };
 
 use anyhow::Context;
 
-use crate::iter::FfmpegIterator;
+#[cfg(feature = "serde")]
+use crate::recording::SessionRecorder;
+use crate::{
+  atomic_output::{PendingOutput, UnverifiedOutput},
+  broadcast::{Broadcast, EventSubscription, Lagged, SubscribeOptions},
+  crash::{describe_exit, exit_signal, format_command_line, CrashReport},
+  error::{loader_failure, ChildExited, GracefulQuitUnavailable, SpawnError},
+  error_policy::ErrorPolicy,
+  event::FfmpegEvent,
+  filter_command::{format_filter_command, FilterCommandError},
+  integrity::{verify_integrity, VerifyOptions},
+  iter::{FfmpegIterator, DEFAULT_FRAME_BUFFER_COUNT, DEFAULT_MAX_FRAME_BYTES},
+  log_group::{LogGrouper, LOG_GROUP_WINDOW},
+  pipe::{is_broken_pipe, OutputPump, StderrTail, StdinFeeder},
+  process_tree::ProcessTree,
+  raw_audio::{RawAudioFormat, StdinAudioWriter},
+  redact::Redactor,
+  resource_usage::{ResourceSampler, ResourceUsage},
+  shared_child::SharedChild,
+  spawner::SpawnedProcess,
+  stderr_policy::{StderrFilter, StderrPolicy},
+  summary::FfmpegSummary,
+  temp::TempFile,
+  timeout::{OutputWatchdog, PromptWatch, DEFAULT_PROMPT_TIMEOUT},
+  version::ffmpeg_version_with_path,
+};
+#[cfg(feature = "serde")]
+use std::path::Path;
 
 /// A wrapper around [`std::process::Child`] containing a spawned FFmpeg command.
 /// Provides interfaces for reading parsed metadata, progress updates, warnings and errors, and
 /// piped output frames if applicable.
 pub struct FfmpegChild {
-  inner: Child,
+  /// Shared with the iterator, which reaps it once the output is drained
+  inner: Arc<SharedChild>,
+  summary: Arc<Mutex<FfmpegSummary>>,
+  output_pump: Option<JoinHandle<io::Result<u64>>>,
+  watchdog: Option<Arc<OutputWatchdog>>,
+  early_events: Option<Receiver<FfmpegEvent>>,
+  stdin_is_input: bool,
+  /// Spawned with `-nostdin`
+  nostdin: bool,
+  /// Raw video frames are output to stdout, for the iterator
+  frames_on_stdout: bool,
+  /// Stdout was handed to `pipe_output_to`
+  stdout_pumped: bool,
+  stderr_tail: StderrTail,
+  /// Found in the arguments at spawn, emitted first by the iterator
+  hints: Vec<String>,
+  /// The program and its arguments, set at spawn
+  command_line: Vec<String>,
+  spawned_at: Instant,
+  crash_diagnostics: bool,
+  crash_report: Option<CrashReport>,
+  stderr_filter: StderrFilter,
+  /// Removed once the process is waited on
+  temp_files: Vec<TempFile>,
+  /// Renamed once the process exits successfully, or else removed
+  pending_outputs: Vec<PendingOutput>,
+  verify_outputs: Option<VerifyOptions>,
+  /// Set with `input_raw_audio`
+  raw_audio_input: Option<RawAudioFormat>,
+  resource_sampler: ResourceSampler,
+  sample_interval: Option<Duration>,
+  frame_buffer_count: usize,
+  max_frame_bytes: u64,
+  prompt_timeout: Duration,
+  error_policy: ErrorPolicy,
+  /// Created along with the iterator, which reports the prompts
+  prompt_watch: Option<Arc<PromptWatch>>,
+  kill_on_drop: bool,
+  group_log_messages: bool,
+  /// Started by the first `subscribe`
+  broadcast: Option<Arc<Broadcast>>,
+  /// The subscription of `poll_events`, started by the first poll
+  poller: Option<EventSubscription>,
+  poll_overflow: u64,
+  /// From the `Exited` event, once polled
+  polled_status: Option<ExitStatus>,
+  /// Set with `record_jsonl`
+  #[cfg(feature = "serde")]
+  recorder: Option<Arc<SessionRecorder>>,
 }
 
 impl FfmpegChild {
@@ -27,32 +109,239 @@ impl FfmpegChild {
     FfmpegIterator::new(self)
   }
 
+  /// Receive the events emitted from now on, along with any other
+  /// subscribers, e.g. a progress bar, a log window and an error monitor.
+  /// Output frames aren't included; see [`subscribe_with`](Self::subscribe_with).
+  ///
+  /// The first subscription starts a thread which reads all the events, like
+  /// [`iter`](Self::iter), so it fails if `iter` was already called, and
+  /// `iter` fails afterwards. Subscribers which don't keep up lose the oldest
+  /// events rather than blocking ffmpeg; see [`EventSubscription`].
+  ///
+  /// ```rust,no_run
+  /// use ffmpeg_sidecar::{command::FfmpegCommand, event::FfmpegEvent};
+  ///
+  /// let mut child = FfmpegCommand::new().testsrc().rawvideo().spawn().unwrap();
+  /// let progress = child.subscribe().unwrap();
+  /// let errors = child.subscribe().unwrap();
+  /// std::thread::spawn(move || {
+  ///   for event in errors.flatten() {
+  ///     if let FfmpegEvent::Error(error) = event {
+  ///       eprintln!("{error}");
+  ///     }
+  ///   }
+  /// });
+  /// for event in progress.flatten() {
+  ///   if let FfmpegEvent::Progress(progress) = event {
+  ///     println!("{}", progress.time);
+  ///   }
+  /// }
+  /// ```
+  pub fn subscribe(&mut self) -> anyhow::Result<EventSubscription> {
+    self.subscribe_with(SubscribeOptions::default())
+  }
+
+  /// Like [`subscribe`](Self::subscribe), with a buffer size or output
+  /// frames.
+  pub fn subscribe_with(&mut self, options: SubscribeOptions) -> anyhow::Result<EventSubscription> {
+    let broadcast = match &self.broadcast {
+      Some(broadcast) => broadcast,
+      None => {
+        let iter = self.iter().context("Failed to start broadcasting events")?;
+        self.broadcast.insert(Broadcast::start(iter))
+      }
+    };
+    Ok(broadcast.subscribe(options))
+  }
+
+  /// Take the events emitted since the last poll, without ever blocking,
+  /// for a loop which polls its systems once per tick and can't wait on
+  /// ffmpeg or own a thread, like a game loop. Polling when nothing happened
+  /// is cheap, and returns an empty `Vec` without allocating.
+  ///
+  /// The first poll starts reading the events on a background thread, like
+  /// [`subscribe`](Self::subscribe), with the default [`SubscribeOptions`]:
+  /// output frames are left out, and up to
+  /// [`SUBSCRIPTION_CAPACITY`](crate::broadcast::SUBSCRIPTION_CAPACITY)
+  /// events are kept between polls, the oldest being dropped beyond that
+  /// and counted by [`poll_overflow`](Self::poll_overflow). Call
+  /// [`start_polling`](Self::start_polling) first for other options.
+  ///
+  /// The last event is [`FfmpegEvent::Exited`], after which
+  /// [`finished`](Self::finished) is true and
+  /// [`exit_status`](Self::exit_status) is known. [`wait`](Self::wait)
+  /// then returns at once, and is still needed for what happens after
+  /// ffmpeg exits, like renaming an
+  /// [`atomic_output`](crate::command::FfmpegCommand::atomic_output).
+  ///
+  /// ```rust,no_run
+  /// use ffmpeg_sidecar::{command::FfmpegCommand, event::FfmpegEvent};
+  ///
+  /// let mut child = FfmpegCommand::new().testsrc().output("out.mp4").spawn().unwrap();
+  /// // Each tick of the game loop
+  /// for event in child.poll_events().unwrap() {
+  ///   if let FfmpegEvent::Progress(progress) = event {
+  ///     println!("{}", progress.time);
+  ///   }
+  /// }
+  /// if child.finished() {
+  ///   println!("done: {:?}", child.exit_status());
+  /// }
+  /// ```
+  pub fn poll_events(&mut self) -> anyhow::Result<Vec<FfmpegEvent>> {
+    let poller = match self.poller.take() {
+      Some(poller) => poller,
+      None => self.subscribe_with(SubscribeOptions::default())?,
+    };
+    let mut events = Vec::new();
+    while let Some(next) = poller.try_recv() {
+      match next {
+        Ok(event) => {
+          if let FfmpegEvent::Exited { status, .. } = &event {
+            self.polled_status = Some(*status);
+          }
+          events.push(event);
+        }
+        Err(Lagged(dropped)) => self.poll_overflow += dropped,
+      }
+    }
+    self.poller = Some(poller);
+    Ok(events)
+  }
+
+  /// Start the reading of [`poll_events`](Self::poll_events) with a buffer
+  /// size, or with output frames. Fails if polling was already started, or
+  /// if [`iter`](Self::iter) was called.
+  pub fn start_polling(&mut self, options: SubscribeOptions) -> anyhow::Result<()> {
+    if self.poller.is_some() {
+      anyhow::bail!("Polling was already started");
+    }
+    self.poller = Some(self.subscribe_with(options)?);
+    Ok(())
+  }
+
+  /// The number of events dropped because
+  /// [`poll_events`](Self::poll_events) wasn't called often enough to keep
+  /// up, in total.
+  pub fn poll_overflow(&self) -> u64 {
+    self.poll_overflow
+  }
+
+  /// Whether the [`FfmpegEvent::Exited`] event was returned by
+  /// [`poll_events`](Self::poll_events), i.e. ffmpeg exited and all its
+  /// events were polled.
+  pub fn finished(&self) -> bool {
+    self.polled_status.is_some()
+  }
+
+  /// The exit status of ffmpeg, once [`finished`](Self::finished).
+  pub fn exit_status(&self) -> Option<ExitStatus> {
+    self.polled_status
+  }
+
   /// Escape hatch to manually control the process' stdout channel.
   /// Calling this method takes ownership of the stdout channel, so
   /// the iterator will no longer include output frames in the stream of events.
   pub fn take_stdout(&mut self) -> Option<ChildStdout> {
-    self.inner.stdout.take()
+    self.inner.lock().take_stdout()
   }
 
   /// Escape hatch to manually control the process' stderr channel.
   /// This method is mutually exclusive with `events_iter`, which relies on
   /// the stderr channel to parse events.
   pub fn take_stderr(&mut self) -> Option<ChildStderr> {
-    self.inner.stderr.take()
+    self.inner.lock().take_stderr()
   }
 
   /// Escape hatch to manually control the process' stdin channel.
   /// This method is mutually exclusive with `send_stdin_command` and `quit`,
   /// which use the stdin channel to send commands to ffmpeg.
   pub fn take_stdin(&mut self) -> Option<ChildStdin> {
-    self.inner.stdin.take()
+    self.inner.lock().take_stdin()
+  }
+
+  /// Copy `reader` into ffmpeg's stdin on a background thread, reading up to
+  /// `buffer_size` bytes at a time. Used along with
+  /// [`FfmpegCommand::input_from_reader`](crate::command::FfmpegCommand::input_from_reader).
+  ///
+  /// Stdin is closed once the reader is exhausted. If ffmpeg stops reading
+  /// first, e.g. because of `-t`, the copy stops without an error.
+  ///
+  /// This takes ownership of stdin, so `quit` and `send_stdin_command` can no
+  /// longer be used.
+  pub fn feed_stdin<R: Read + Send + 'static>(
+    &mut self,
+    reader: R,
+    buffer_size: usize,
+  ) -> anyhow::Result<StdinFeeder> {
+    let stdin = self.take_stdin().context("Missing child stdin")?;
+    Ok(StdinFeeder::spawn(reader, stdin, buffer_size))
+  }
+
+  /// Write PCM audio to ffmpeg's stdin, for a command reading it with
+  /// [`FfmpegCommand::input_raw_audio`](crate::command::FfmpegCommand::input_raw_audio).
+  ///
+  /// Like [`feed_stdin`](Self::feed_stdin), this takes ownership of stdin.
+  /// Create the iterator with [`iter`](Self::iter) before writing, which
+  /// reads stderr in the background, so ffmpeg doesn't stop on a full stderr
+  /// pipe while it waits for the audio.
+  pub fn audio_writer(&mut self) -> anyhow::Result<StdinAudioWriter> {
+    let format = self
+      .raw_audio_input
+      .context("The command has no input_raw_audio")?;
+    let stdin = self.take_stdin().context("Missing child stdin")?;
+    Ok(StdinAudioWriter::new(stdin, format))
+  }
+
+  /// Copy ffmpeg's stdout into `writer` on a background thread, reading up to
+  /// `chunk_size` bytes at a time. Typically used with
+  /// [`FfmpegCommand::pipe_stdout`](crate::command::FfmpegCommand::pipe_stdout)
+  /// and a container format like `mpegts` which can be written to a pipe.
+  ///
+  /// The event iterator can still be used for progress and errors, but won't
+  /// include any output frames or chunks. Since the iterator takes stdout when
+  /// it's created, this method must be called before `iter`. It can't be
+  /// combined with raw frames output to stdout for the iterator, like with
+  /// [`rawvideo`](crate::command::FfmpegCommand::rawvideo): `iter` then
+  /// fails, since there are no frames left for it to read.
+  ///
+  /// [`FfmpegChild::wait`] waits for the copy to finish and the writer to be
+  /// flushed; the returned [`OutputPump`] reports the number of bytes written.
+  ///
+  /// ```rust,no_run
+  /// use ffmpeg_sidecar::command::FfmpegCommand;
+  ///
+  /// let mut child = FfmpegCommand::new()
+  ///   .testsrc()
+  ///   .format("mpegts")
+  ///   .pipe_stdout()
+  ///   .spawn()
+  ///   .unwrap();
+  /// let pump = child.pipe_output_to(Vec::new(), 64 * 1024).unwrap();
+  /// child.iter().unwrap().for_each(|_| {});
+  /// child.wait().unwrap();
+  /// println!("wrote {} bytes", pump.join().unwrap());
+  /// ```
+  pub fn pipe_output_to<W: Write + Send + 'static>(
+    &mut self,
+    writer: W,
+    chunk_size: usize,
+  ) -> anyhow::Result<OutputPump> {
+    let stdout = self.take_stdout().context(
+      "Missing child stdout\n - Was `iter()` called first? The event iterator takes stdout to read output frames\n - Did you call `take_stdout` or `pipe_output_to` elsewhere?",
+    )?;
+    let (pump, handle) = OutputPump::spawn(stdout, writer, chunk_size);
+    self.output_pump = Some(handle);
+    self.stdout_pumped = true;
+    Ok(pump)
   }
 
   /// Send a command to ffmpeg over stdin, used during interactive mode.
   ///
   /// This method does not validate that the command is expected or handled
   /// correctly by ffmpeg. The returned `io::Result` indicates only whether the
-  /// command was successfully sent or not.
+  /// command was successfully sent or not. If ffmpeg has already exited, the
+  /// error is a [`ChildExited`] with the last lines of its log.
   ///
   /// In a typical ffmpeg build, these are the supported commands:
   ///
@@ -68,13 +357,97 @@ impl FfmpegChild {
   /// s      Show QP histogram
   /// ```
   pub fn send_stdin_command(&mut self, command: &[u8]) -> anyhow::Result<()> {
+    // `wait` closes stdin, which isn't the caller's fault
+    if self.inner.lock().stdin_mut().is_none() {
+      if let Ok(Some(status)) = self.inner.try_wait() {
+        return Err(self.child_exited(Some(status)).into());
+      }
+    }
     let mut stdin = self
       .inner
-      .stdin
-      .take()
+      .lock()
+      .take_stdin()
       .context("Missing child stdin")?;
-    stdin.write_all(command)?;
-    self.inner.stdin.replace(stdin);
+    match stdin.write_all(command).and_then(|()| stdin.flush()) {
+      Ok(()) => {
+        self.inner.lock().stdin_mut().replace(stdin);
+        Ok(())
+      }
+      Err(e) if is_broken_pipe(&e) => {
+        let status = self.inner.try_wait().ok().flatten();
+        Err(self.child_exited(status).into())
+      }
+      Err(e) => {
+        self.inner.lock().stdin_mut().replace(stdin);
+        Err(e.into())
+      }
+    }
+  }
+
+  /// Send a command to a running filter, e.g. to change the text of a
+  /// `drawtext` overlay or the level of a `volume` filter without restarting.
+  /// Equivalent to pressing `c` in an interactive ffmpeg session and entering
+  /// `<target> -1 <command> <arg>`.
+  ///
+  /// `target` is a filter name like `drawtext`, an instance name like
+  /// `Parsed_drawtext_0`, or `all`. Which commands a filter supports is listed
+  /// in its documentation.
+  ///
+  /// The command is only sent; ffmpeg's answer arrives later through the
+  /// iterator as an [`FfmpegEvent::FilterCommandReply`], whose `ret` is
+  /// negative if no filter accepted the command. Fails with a
+  /// [`FilterCommandError`] if stdin was taken or is used as an input.
+  ///
+  /// ```rust,no_run
+  /// use ffmpeg_sidecar::command::FfmpegCommand;
+  ///
+  /// let mut child = FfmpegCommand::new()
+  ///   .testsrc()
+  ///   .filter("drawtext=text=before:fontsize=48")
+  ///   .rawvideo()
+  ///   .spawn()
+  ///   .unwrap();
+  /// child.send_filter_command("drawtext", "reinit", "text=after").unwrap();
+  /// ```
+  pub fn send_filter_command(
+    &mut self,
+    target: &str,
+    command: &str,
+    arg: &str,
+  ) -> anyhow::Result<()> {
+    if self.stdin_is_input {
+      return Err(FilterCommandError::StdinIsInput.into());
+    }
+    if self.inner.lock().stdin_mut().is_none() {
+      return Err(FilterCommandError::StdinTaken.into());
+    }
+    let keys = format_filter_command(target, command, arg)?;
+    self.send_stdin_command(keys.as_bytes())
+  }
+
+  /// Answer the question of the last [`FfmpegEvent::Prompt`] returned by the
+  /// iterator, writing `y` or `n` and a line ending to stdin. It must be
+  /// answered within the [`FfmpegChild::prompt_timeout`].
+  ///
+  /// ```rust,no_run
+  /// use ffmpeg_sidecar::{command::FfmpegCommand, event::{FfmpegEvent, PromptKind}};
+  ///
+  /// let mut child = FfmpegCommand::new()
+  ///   .testsrc()
+  ///   .output("existing.mp4")
+  ///   .spawn()
+  ///   .unwrap();
+  /// for event in child.iter().unwrap() {
+  ///   if let FfmpegEvent::Prompt { kind: PromptKind::Overwrite, .. } = event {
+  ///     child.reply_to_prompt(true).unwrap();
+  ///   }
+  /// }
+  /// ```
+  pub fn reply_to_prompt(&mut self, answer: bool) -> anyhow::Result<()> {
+    self.send_stdin_command(if answer { b"y\n" } else { b"n\n" })?;
+    if let Some(watch) = &self.prompt_watch {
+      watch.answered();
+    }
     Ok(())
   }
 
@@ -84,49 +457,680 @@ impl FfmpegChild {
   /// This method returns after the command has been sent; the actual shut down
   /// may take a few more frames as ffmpeg flushes its buffers and writes the
   /// trailer, if applicable.
+  ///
+  /// Fails with [`GracefulQuitUnavailable`] when ffmpeg doesn't read key
+  /// presses from stdin, instead of writing `q` into an input, so the caller
+  /// can [`kill`](FfmpegChild::kill) it instead. See
+  /// [`FfmpegChild::supports_graceful_quit`].
+  ///
+  /// With [`contain_process_tree`](crate::command::FfmpegCommand::contain_process_tree),
+  /// the processes ffmpeg leaves behind are killed by [`FfmpegChild::wait`].
   pub fn quit(&mut self) -> anyhow::Result<()> {
+    match self.graceful_quit_unavailable() {
+      // `wait` closes stdin, which `send_stdin_command` reports as exiting
+      Some(GracefulQuitUnavailable::StdinTaken) if matches!(self.inner.try_wait(), Ok(Some(_))) => {
+      }
+      Some(reason) => return Err(reason.into()),
+      None => {}
+    }
     self.send_stdin_command(b"q")
   }
 
+  /// Whether [`FfmpegChild::quit`] can ask ffmpeg to stop: stdin must still
+  /// be piped to ffmpeg, not taken or used as an input, and ffmpeg must not
+  /// have been spawned with `-nostdin`.
+  ///
+  /// ffmpeg confirms this by logging `Press [q] to stop`, which the iterator
+  /// reports as [`FfmpegEvent::Interactive`] and records in
+  /// [`FfmpegSummary::interactive`].
+  pub fn supports_graceful_quit(&self) -> bool {
+    self.graceful_quit_unavailable().is_none()
+  }
+
+  fn graceful_quit_unavailable(&self) -> Option<GracefulQuitUnavailable> {
+    if self.nostdin {
+      Some(GracefulQuitUnavailable::NoStdin)
+    } else if self.stdin_is_input {
+      Some(GracefulQuitUnavailable::StdinIsInput)
+    } else if self.inner.lock().stdin_mut().is_none() {
+      Some(GracefulQuitUnavailable::StdinTaken)
+    } else {
+      None
+    }
+  }
+
   /// Forcibly terminate the inner child process.
   ///
   /// Alternatively, you may choose to gracefully stop the child process by
   /// sending a command over stdin, using the `quit` method.
   ///
-  /// Identical to `kill` in [`std::process::Child`].
+  /// Identical to `kill` in [`std::process::Child`], except that with
+  /// [`contain_process_tree`](crate::command::FfmpegCommand::contain_process_tree),
+  /// the processes started by ffmpeg are killed too.
   pub fn kill(&mut self) -> io::Result<()> {
     self.inner.kill()
   }
 
   /// Waits for the inner child process to finish execution.
   ///
-  /// Identical to `wait` in [`std::process::Child`].
+  /// Identical to `wait` in [`std::process::Child`], except that if output is
+  /// being copied by [`FfmpegChild::pipe_output_to`], it also waits for the
+  /// copy to be flushed, and returns its error if it failed.
+  ///
+  /// If the process was killed by its
+  /// [`first_output_timeout`](crate::command::FfmpegCommand::first_output_timeout),
+  /// returns an error of kind `TimedOut` wrapping a
+  /// [`NoOutputWithinTimeout`](crate::timeout::NoOutputWithinTimeout).
+  ///
+  /// If ffmpeg couldn't start because the dynamic loader failed to load its
+  /// shared libraries, returns an error wrapping a
+  /// [`SpawnError::MissingDependencies`] with what the loader printed. The
+  /// loader prints to stderr, so this also needs the iterator.
+  ///
+  /// If ffmpeg exited because it rejected an option, like a misspelled one,
+  /// returns an error of kind `InvalidInput` wrapping an
+  /// [`InvalidOption`](crate::error::InvalidOption). Options are reported by
+  /// the iterator, so this needs stderr to be read with
+  /// [`FfmpegChild::iter`] first.
+  ///
+  /// With [`FfmpegChild::enable_crash_diagnostics`], another non-zero exit
+  /// code or a signal returns an error wrapping a [`CrashReport`].
+  ///
+  /// With [`contain_process_tree`](crate::command::FfmpegCommand::contain_process_tree),
+  /// the processes started by ffmpeg which are still running once it exits
+  /// are killed.
+  ///
+  /// With [`atomic_output`](crate::command::FfmpegCommand::atomic_output),
+  /// the outputs are renamed to their paths when ffmpeg exits successfully,
+  /// and removed otherwise, including when this returns another error.
   pub fn wait(&mut self) -> io::Result<ExitStatus> {
-    self.inner.wait()
+    let result = self.wait_for_exit();
+    let pending = std::mem::take(&mut self.pending_outputs);
+    if matches!(&result, Ok(status) if status.success()) {
+      self.finalize_outputs(pending)?;
+    }
+    result
+  }
+
+  /// Verify the outputs written to temporary files, if enabled, then rename
+  /// them all. None is renamed when one fails verification.
+  fn finalize_outputs(&self, pending: Vec<PendingOutput>) -> io::Result<()> {
+    if let Some(options) = self.verify_outputs {
+      for output in &pending {
+        let report = verify_integrity(output.temp(), options).map_err(io::Error::other)?;
+        if !report.ok {
+          let unverified = UnverifiedOutput {
+            path: output.path().to_path_buf(),
+            report,
+          };
+          return Err(io::Error::new(io::ErrorKind::InvalidData, unverified));
+        }
+      }
+    }
+    pending.into_iter().try_for_each(PendingOutput::finalize)
+  }
+
+  fn wait_for_exit(&mut self) -> io::Result<ExitStatus> {
+    let status = self.inner.wait()?;
+    #[cfg(feature = "serde")]
+    if let Some(recorder) = &self.recorder {
+      recorder.record_exit(status);
+    }
+    self.temp_files.clear();
+    let duration_ran = self.spawned_at.elapsed();
+    if let Some(watchdog) = &self.watchdog {
+      watchdog.exited();
+      if let Some(error) = watchdog.error() {
+        return Err(io::Error::new(io::ErrorKind::TimedOut, error));
+      }
+    }
+    if let Some(watch) = &self.prompt_watch {
+      watch.exited();
+      if let Some(error) = watch.error() {
+        return Err(io::Error::new(io::ErrorKind::TimedOut, error));
+      }
+    }
+    if let Some(pump) = self.output_pump.take() {
+      pump
+        .join()
+        .map_err(|_| io::Error::other("output pump thread panicked"))??;
+    }
+    if let Some(too_large) = self.summary().frame_too_large {
+      return Err(io::Error::new(io::ErrorKind::InvalidData, too_large));
+    }
+    if let Some(limit_reached) = self.summary().error_limit_reached {
+      return Err(io::Error::other(limit_reached));
+    }
+    if !status.success() {
+      if let Some(loader_output) = loader_failure(&self.stderr_tail()) {
+        let path = self.command_line.first().cloned().unwrap_or_default();
+        return Err(io::Error::other(SpawnError::MissingDependencies {
+          path: path.into(),
+          loader_output,
+        }));
+      }
+      if let Some(invalid) = self.summary().invalid_option {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, invalid));
+      }
+    }
+    if self.crash_diagnostics && !status.success() {
+      // Waiting again returns the same report
+      let report = match self.crash_report.clone() {
+        Some(report) => report,
+        None => self.build_crash_report(status, duration_ran),
+      };
+      self.crash_report = Some(report.clone());
+      return Err(io::Error::other(report));
+    }
+    Ok(status)
+  }
+
+  /// Keep `file` until ffmpeg exits, removing it once the child is waited on
+  /// or dropped, e.g. a concat list created with a
+  /// [`TempRegistry`](crate::temp::TempRegistry).
+  pub fn own_temp_file(&mut self, file: TempFile) -> &mut Self {
+    self.temp_files.push(file);
+    self
+  }
+
+  /// Collect a [`CrashReport`] when ffmpeg exits with a non-zero exit code
+  /// or is killed by a signal, e.g. a segfault or the OOM killer. The report
+  /// is returned as the error of [`FfmpegChild::wait`], and by
+  /// [`FfmpegChild::crash_report`].
+  ///
+  /// This also keeps the last [`CrashReport::MAX_LOG_LINES`] lines of the log
+  /// instead of the last 20. Log lines are only collected while the iterator
+  /// reads stderr, so call this before [`FfmpegChild::iter`].
+  pub fn enable_crash_diagnostics(&mut self) -> &mut Self {
+    self.crash_diagnostics = true;
+    self.stderr_tail.set_max_lines(CrashReport::MAX_LOG_LINES);
+    self
+  }
+
+  /// The report of an abnormal exit, once [`FfmpegChild::wait`] has returned
+  /// it. Always `None` unless [`FfmpegChild::enable_crash_diagnostics`] was
+  /// called.
+  pub fn crash_report(&self) -> Option<CrashReport> {
+    self.crash_report.clone()
+  }
+
+  fn build_crash_report(&self, status: ExitStatus, duration_ran: Duration) -> CrashReport {
+    // `-version` is run again since the banner is often hidden or out of the
+    // tail by now
+    let ffmpeg_version = self
+      .command_line
+      .first()
+      .and_then(|program| ffmpeg_version_with_path(program).ok())
+      .unwrap_or_default();
+    CrashReport {
+      exit: status,
+      signal: exit_signal(&status),
+      reason: describe_exit(&status),
+      last_log_lines: self.stderr_tail(),
+      command_line: format_command_line(&self.command_line),
+      ffmpeg_version,
+      duration_ran,
+    }
+  }
+
+  /// Statistics about duplicated and dropped frames, timestamp warnings and
+  /// recognized errors, collected from the events read so far by [`FfmpegChild::iter`].
+  ///
+  /// Typically called after exhausting the iterator and calling `wait`, to
+  /// check whether a live capture kept up with its input:
+  ///
+  /// ```rust,no_run
+  /// use ffmpeg_sidecar::command::FfmpegCommand;
+  ///
+  /// let mut child = FfmpegCommand::new()
+  ///   .testsrc()
+  ///   .rawvideo()
+  ///   .spawn()
+  ///   .unwrap();
+  /// child.iter().unwrap().for_each(|_| {});
+  /// child.wait().unwrap();
+  ///
+  /// let summary = child.summary();
+  /// println!("{} dup, {} dropped", summary.dup_frames, summary.total_dropped());
+  /// ```
+  pub fn summary(&self) -> FfmpegSummary {
+    self
+      .summary
+      .lock()
+      .map(|summary| summary.clone())
+      .unwrap_or_default()
+  }
+
+  /// The last lines logged by ffmpeg, as read by the iterator so far. Unlike
+  /// the events, these remain available after the iterator is dropped, e.g.
+  /// to find out why ffmpeg failed.
+  pub fn stderr_tail(&self) -> Vec<String> {
+    self.stderr_tail.lines()
+  }
+
+  fn child_exited(&self, status: Option<ExitStatus>) -> ChildExited {
+    ChildExited {
+      status,
+      stderr_tail: self.stderr_tail(),
+    }
+  }
+
+  /// The CPU and memory used by ffmpeg right now. The CPU percentage is
+  /// averaged since the previous call, or since spawning for the first one.
+  ///
+  /// Fails with `io::ErrorKind::Unsupported` on platforms other than Linux,
+  /// macOS and Windows. Once ffmpeg has exited, the result depends on the
+  /// platform: an error, or the final CPU time with no memory.
+  ///
+  /// ```rust,no_run
+  /// use ffmpeg_sidecar::command::FfmpegCommand;
+  ///
+  /// let mut child = FfmpegCommand::new().testsrc().rawvideo().spawn().unwrap();
+  /// std::thread::sleep(std::time::Duration::from_secs(1));
+  /// let usage = child.resource_usage().unwrap();
+  /// println!("{:.0}% CPU, {} MiB", usage.cpu_percent, usage.rss_bytes >> 20);
+  /// ```
+  pub fn resource_usage(&mut self) -> io::Result<ResourceUsage> {
+    self.resource_sampler.sample()
+  }
+
+  /// Make the iterator emit a [`FfmpegEvent::ResourceSample`] every
+  /// `interval` while ffmpeg runs, between the events parsed from its
+  /// output. The samples are measured independently of
+  /// [`FfmpegChild::resource_usage`], so calling both doesn't skew either.
+  /// Call this before [`FfmpegChild::iter`].
+  ///
+  /// Samples are skipped when they can't be measured, so there are none on
+  /// unsupported platforms.
+  pub fn sample_interval(&mut self, interval: Duration) -> &mut Self {
+    self.sample_interval = Some(interval);
+    self
   }
 
-  /// Wrap a [`std::process::Child`] in a `FfmpegChild`. Should typically only
-  /// be called by `FfmpegCommand::spawn`.
+  /// A sampler for the iterator, with the interval set by
+  /// `sample_interval`.
+  pub(crate) fn interval_sampler(&self) -> Option<(ResourceSampler, Duration)> {
+    let sampler = ResourceSampler::new(self.inner.id(), self.spawned_at);
+    self.sample_interval.map(|interval| (sampler, interval))
+  }
+
+  /// Limit the output frames read from stdout but not yet returned by the
+  /// iterator to `count`, [`DEFAULT_FRAME_BUFFER_COUNT`] by default, e.g. to
+  /// bound the memory used for 8K frames. Call this before
+  /// [`FfmpegChild::iter`].
+  ///
+  /// Once the limit is reached, the iterator stops reading stdout until the
+  /// next frame is taken, so ffmpeg blocks writing to the full pipe and
+  /// waits for the consumer. Along with the frame being processed and the
+  /// pipe's own buffer, that bounds the frames in memory at any time. A
+  /// count of `1` hands each frame over as soon as it's read, and larger
+  /// counts smooth out a consumer with an uneven pace.
+  ///
+  /// The log isn't limited: stderr is read as soon as ffmpeg writes to it,
+  /// so even a `trace` log never blocks ffmpeg while the frames wait for the
+  /// consumer.
+  pub fn frame_buffer_count(&mut self, count: usize) -> &mut Self {
+    self.frame_buffer_count = count.max(1);
+    self
+  }
+
+  /// The largest raw video frame the iterator allocates,
+  /// [`DEFAULT_MAX_FRAME_BYTES`] (1 GiB) by default. Call this before
+  /// [`FfmpegChild::iter`].
+  ///
+  /// The size of the frames is computed from the resolution and pixel
+  /// format of the output stream as parsed from the log. When it's larger,
+  /// e.g. because of an absurd resolution, stdout is closed instead of
+  /// reading a single frame: the iterator emits an
+  /// [`FfmpegEvent::Error`], and [`FfmpegChild::wait`] returns a
+  /// [`FrameTooLarge`](crate::error::FrameTooLarge) error.
+  pub fn max_frame_bytes(&mut self, bytes: u64) -> &mut Self {
+    self.max_frame_bytes = bytes;
+    self
+  }
+
+  /// The `frame_buffer_count` and `max_frame_bytes` for the iterator.
+  pub(crate) fn frame_limits(&self) -> (usize, u64) {
+    (self.frame_buffer_count, self.max_frame_bytes)
+  }
+
+  /// Whether the errors logged by ffmpeg stop it,
+  /// [`ErrorPolicy::CollectAndContinue`] by default. Call this before
+  /// [`FfmpegChild::iter`].
+  ///
+  /// The errors are counted as the iterator reads them, so the events and
+  /// frames always go on until ffmpeg exits. When the policy stops ffmpeg,
+  /// it's killed, the iterator emits an [`FfmpegEvent::Error`], and
+  /// [`FfmpegChild::wait`] returns an
+  /// [`ErrorLimitReached`](crate::error_policy::ErrorLimitReached) error with
+  /// the errors logged, also in the [`summary`](FfmpegChild::summary).
+  ///
+  /// ```rust,no_run
+  /// use ffmpeg_sidecar::{command::FfmpegCommand, error_policy::ErrorPolicy};
+  ///
+  /// let mut child = FfmpegCommand::new()
+  ///   .input("damaged.mp4")
+  ///   .rawvideo()
+  ///   .spawn()
+  ///   .unwrap();
+  /// child.error_policy(ErrorPolicy::AbortAfter(10));
+  /// let frames = child.iter().unwrap().filter_frames().count();
+  /// match child.wait() {
+  ///   Ok(_) => println!("{frames} frames, {} errors", child.summary().error_count),
+  ///   Err(e) => println!("gave up after {frames} frames: {e}"),
+  /// }
+  /// ```
+  pub fn error_policy(&mut self, policy: ErrorPolicy) -> &mut Self {
+    self.error_policy = policy;
+    self
+  }
+
+  /// The `error_policy` for the iterator.
+  pub(crate) fn error_policy_setting(&self) -> ErrorPolicy {
+    self.error_policy
+  }
+
+  /// How long a [`FfmpegEvent::Prompt`] can stay unanswered by
+  /// [`FfmpegChild::reply_to_prompt`] once the iterator returned it,
+  /// [`DEFAULT_PROMPT_TIMEOUT`] (30 seconds) by default. Call this before
+  /// [`FfmpegChild::iter`].
+  ///
+  /// ffmpeg reads nothing else while it waits for the answer, so it's killed
+  /// once the timeout elapses, and [`FfmpegChild::wait`] returns an
+  /// [`UnansweredPrompt`](crate::timeout::UnansweredPrompt) error.
+  pub fn prompt_timeout(&mut self, timeout: Duration) -> &mut Self {
+    self.prompt_timeout = timeout;
+    self
+  }
+
+  /// The timers of the prompts returned by the iterator.
+  pub(crate) fn prompt_watch(&mut self) -> Arc<PromptWatch> {
+    let (killer, timeout) = (self.inner.killer(), self.prompt_timeout);
+    self
+      .prompt_watch
+      .get_or_insert_with(|| PromptWatch::new(killer, timeout))
+      .clone()
+  }
+
+  /// Record the session to a file as JSON lines, e.g. to attach it to a bug
+  /// report: the command line first, then each event returned by the
+  /// iterator with the time it was received, and the exit status once the
+  /// child is waited on. Call this before [`FfmpegChild::iter`].
+  ///
+  /// Each line is written to the file as soon as it's complete, so the
+  /// recording is usable even if the program crashes midway. The data of
+  /// output frames and chunks is left out. Read the file back with
+  /// [`SessionReplay`](crate::recording::SessionReplay).
   ///
-  /// ## Panics
+  /// ```rust,no_run
+  /// use ffmpeg_sidecar::{command::FfmpegCommand, recording::SessionReplay};
   ///
-  /// Panics if the any of the child process's stdio channels were not piped.
-  /// This could be because ffmpeg was spawned with `-nostdin`, or if the
-  /// `Child` instance was not configured with `stdin(Stdio::piped())`.
-  pub(crate) fn from_inner(inner: Child) -> Self {
-    assert!(inner.stdin.is_some(), "stdin was not piped");
-    assert!(inner.stdout.is_some(), "stdout was not piped");
-    assert!(inner.stderr.is_some(), "stderr was not piped");
-    Self { inner }
+  /// let mut child = FfmpegCommand::new().testsrc().rawvideo().spawn().unwrap();
+  /// child.record_jsonl("session.jsonl").unwrap();
+  /// child.iter().unwrap().for_each(|_| {});
+  /// child.wait().unwrap();
+  ///
+  /// let replay = SessionReplay::load("session.jsonl").unwrap();
+  /// println!("{} events", replay.events().count());
+  /// ```
+  #[cfg(feature = "serde")]
+  pub fn record_jsonl<P: AsRef<Path>>(&mut self, path: P) -> io::Result<&mut Self> {
+    let recorder = SessionRecorder::create(path.as_ref(), self.spawned_at, &self.command_line)?;
+    self.recorder = Some(Arc::new(recorder));
+    Ok(self)
+  }
+
+  /// The recorder for the iterator, with `record_jsonl`.
+  #[cfg(feature = "serde")]
+  pub(crate) fn recorder(&self) -> Option<Arc<SessionRecorder>> {
+    self.recorder.clone()
+  }
+
+  /// A grouper for the iterator, with `group_log_messages`.
+  pub(crate) fn log_grouper(&self) -> Option<LogGrouper> {
+    self
+      .group_log_messages
+      .then(|| LogGrouper::new(LOG_GROUP_WINDOW))
+  }
+
+  /// The number of stderr lines dropped so far because of the command's
+  /// [`stderr_policy`](crate::command::FfmpegCommand::stderr_policy). Always
+  /// zero with the default policy, which parses every line.
+  pub fn dropped_stderr_lines(&self) -> u64 {
+    self.stderr_filter.dropped()
+  }
+
+  pub(crate) fn stderr_tail_handle(&self) -> StderrTail {
+    self.stderr_tail.clone()
+  }
+
+  pub(crate) fn stderr_filter_handle(&self) -> StderrFilter {
+    self.stderr_filter.clone()
+  }
+
+  /// Called by `FfmpegCommand::spawn`, before stderr is read.
+  pub(crate) fn set_stderr_policy(&mut self, policy: StderrPolicy) {
+    self.stderr_filter = StderrFilter::new(policy);
+  }
+
+  /// Called by `FfmpegCommand::spawn` after `set_stderr_policy`, before
+  /// stderr is read.
+  pub(crate) fn set_redactor(&mut self, redactor: Redactor) {
+    self.stderr_filter.set_redactor(redactor);
+  }
+
+  /// Shared handle to the summary, updated by the iterator.
+  pub(crate) fn summary_handle(&self) -> Arc<Mutex<FfmpegSummary>> {
+    self.summary.clone()
+  }
+
+  /// Start the timer of [`FfmpegCommand::first_output_timeout`](crate::command::FfmpegCommand::first_output_timeout).
+  /// Takes stderr, whose events are handed over to the iterator once created.
+  pub(crate) fn start_watchdog(&mut self, timeout: Duration) {
+    if let Some(stderr) = self.take_stderr() {
+      let (tx, rx) = channel();
+      let tail = self.stderr_tail.clone();
+      self.watchdog = Some(OutputWatchdog::spawn(
+        self.inner.killer(),
+        timeout,
+        stderr,
+        tx,
+        tail,
+        self.stderr_filter.clone(),
+      ));
+      self.early_events = Some(rx);
+    }
+  }
+
+  /// Called by `FfmpegCommand::spawn` right after spawning, before the
+  /// watchdog is started.
+  pub(crate) fn set_process_tree(&mut self, tree: ProcessTree) {
+    self.inner.set_process_tree(tree);
+  }
+
+  /// Called by `FfmpegCommand::spawn` when an input is read from stdin.
+  pub(crate) fn set_stdin_is_input(&mut self) {
+    self.stdin_is_input = true;
   }
 
-  /// Escape hatch to access the inner `Child`.
-  pub fn as_inner(&mut self) -> &Child {
-    &self.inner
+  /// Called by `FfmpegCommand::spawn` with `atomic_output`.
+  pub(crate) fn set_pending_outputs(
+    &mut self,
+    outputs: Vec<PendingOutput>,
+    verify: Option<VerifyOptions>,
+  ) {
+    self.pending_outputs = outputs;
+    self.verify_outputs = verify;
   }
 
-  /// Escape hatch to mutably access the inner `Child`.
-  pub fn as_inner_mut(&mut self) -> &mut Child {
-    &mut self.inner
+  /// Called by `FfmpegCommand::spawn` with `input_raw_audio`.
+  pub(crate) fn set_raw_audio_input(&mut self, format: RawAudioFormat) {
+    self.raw_audio_input = Some(format);
+  }
+
+  /// Called by `FfmpegCommand::spawn` with `kill_on_drop`.
+  pub(crate) fn set_kill_on_drop(&mut self) {
+    self.kill_on_drop = true;
+  }
+
+  /// Called by `FfmpegCommand::spawn` with `group_log_messages`.
+  pub(crate) fn set_group_log_messages(&mut self) {
+    self.group_log_messages = true;
+  }
+
+  /// Called by `FfmpegCommand::spawn` when `-nostdin` is passed.
+  pub(crate) fn set_nostdin(&mut self) {
+    self.nostdin = true;
+  }
+
+  /// Called by `FfmpegCommand::spawn` when raw video frames are output to
+  /// stdout.
+  pub(crate) fn set_frames_on_stdout(&mut self) {
+    self.frames_on_stdout = true;
+  }
+
+  /// Whether the iterator is expected to read frames from stdout, which was
+  /// handed to `pipe_output_to` instead.
+  pub(crate) fn frames_pumped(&self) -> bool {
+    self.frames_on_stdout && self.stdout_pumped
+  }
+
+  pub(crate) fn set_hints(&mut self, hints: Vec<String>) {
+    self.hints = hints;
+  }
+
+  pub(crate) fn set_command_line(&mut self, command_line: Vec<String>) {
+    self.command_line = command_line;
+  }
+
+  pub(crate) fn take_hints(&mut self) -> Vec<String> {
+    std::mem::take(&mut self.hints)
+  }
+
+  pub(crate) fn watchdog(&self) -> Option<Arc<OutputWatchdog>> {
+    self.watchdog.clone()
+  }
+
+  pub(crate) fn take_early_events(&mut self) -> Option<Receiver<FfmpegEvent>> {
+    self.early_events.take()
+  }
+
+  /// Wrap a [`SpawnedProcess`] in a `FfmpegChild`. Should typically only
+  /// be called by `FfmpegCommand::spawn`, which checks that the stdio channels
+  /// needed by the command are piped (see
+  /// [`FfmpegCommand::stderr_stdio`](crate::command::FfmpegCommand::stderr_stdio)).
+  pub(crate) fn from_inner(inner: SpawnedProcess) -> Self {
+    let (id, spawned_at) = (inner.id(), Instant::now());
+    Self {
+      inner: Arc::new(SharedChild::new(inner)),
+      summary: Arc::new(Mutex::new(FfmpegSummary::new())),
+      output_pump: None,
+      watchdog: None,
+      early_events: None,
+      stdin_is_input: false,
+      nostdin: false,
+      frames_on_stdout: false,
+      stdout_pumped: false,
+      stderr_tail: StderrTail::default(),
+      hints: Vec::new(),
+      command_line: Vec::new(),
+      spawned_at,
+      crash_diagnostics: false,
+      crash_report: None,
+      stderr_filter: StderrFilter::default(),
+      temp_files: Vec::new(),
+      pending_outputs: Vec::new(),
+      verify_outputs: None,
+      raw_audio_input: None,
+      resource_sampler: ResourceSampler::new(id, spawned_at),
+      sample_interval: None,
+      frame_buffer_count: DEFAULT_FRAME_BUFFER_COUNT,
+      max_frame_bytes: DEFAULT_MAX_FRAME_BYTES,
+      prompt_timeout: DEFAULT_PROMPT_TIMEOUT,
+      error_policy: ErrorPolicy::default(),
+      prompt_watch: None,
+      kill_on_drop: false,
+      group_log_messages: false,
+      broadcast: None,
+      poller: None,
+      poll_overflow: 0,
+      polled_status: None,
+      #[cfg(feature = "serde")]
+      recorder: None,
+    }
+  }
+
+  /// Escape hatch to access the inner `Child`, or `None` if ffmpeg was
+  /// started by a [`MockSpawner`](crate::spawner::MockSpawner), see
+  /// [`as_process`](Self::as_process).
+  ///
+  /// **Breaking change:** this returned `&Child` before the event stream
+  /// started reaping the process. The `Child` is now shared with the
+  /// iterator, so this returns a guard which derefs to it instead, e.g.
+  /// `child.as_inner().unwrap().id()`, or `&*guard` where the reference
+  /// itself is needed. Keep the guard short-lived: the iterator can't reap
+  /// the process while it's held, so its final
+  /// [`Exited`](crate::event::FfmpegEvent::Exited) event waits until the
+  /// guard is dropped.
+  pub fn as_inner(&mut self) -> Option<impl Deref<Target = Child> + '_> {
+    InnerChild::new(self.inner.lock())
+  }
+
+  /// Escape hatch to mutably access the inner `Child`, like
+  /// [`as_inner`](Self::as_inner).
+  ///
+  /// **Breaking change:** this returned `&mut Child` before, and returns a
+  /// guard now, taken as `&mut *guard` where the reference is needed.
+  pub fn as_inner_mut(&mut self) -> Option<impl DerefMut<Target = Child> + '_> {
+    InnerChild::new(self.inner.lock())
+  }
+
+  /// Escape hatch to access the inner process, whichever
+  /// [`ProcessSpawner`](crate::spawner::ProcessSpawner) started it.
+  pub fn as_process(&mut self) -> MutexGuard<'_, SpawnedProcess> {
+    self.inner.lock()
+  }
+
+  /// The OS-assigned process identifier, or `0` for a mocked process.
+  pub fn id(&self) -> u32 {
+    self.inner.id()
+  }
+
+  pub(crate) fn shared_child(&self) -> Arc<SharedChild> {
+    self.inner.clone()
+  }
+}
+
+/// The `Child` of an [`FfmpegChild`] which wasn't mocked.
+struct InnerChild<'a>(MutexGuard<'a, SpawnedProcess>);
+
+impl<'a> InnerChild<'a> {
+  fn new(process: MutexGuard<'a, SpawnedProcess>) -> Option<Self> {
+    process.as_child().is_some().then(|| Self(process))
+  }
+}
+
+impl Deref for InnerChild<'_> {
+  type Target = Child;
+
+  fn deref(&self) -> &Child {
+    self.0.as_child().expect("checked by InnerChild::new")
+  }
+}
+
+impl DerefMut for InnerChild<'_> {
+  fn deref_mut(&mut self) -> &mut Child {
+    self.0.as_child_mut().expect("checked by InnerChild::new")
+  }
+}
+
+impl Drop for FfmpegChild {
+  /// With [`kill_on_drop`](crate::command::FfmpegCommand::kill_on_drop), kill
+  /// ffmpeg if it's still running, and reap it.
+  fn drop(&mut self) {
+    if self.kill_on_drop && matches!(self.inner.try_wait(), Ok(None)) {
+      self.kill().ok();
+      self.inner.wait().ok();
+    }
   }
 }
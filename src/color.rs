@@ -0,0 +1,345 @@
+//! Color properties of video streams, and carrying HDR metadata over to the
+//! output or tone mapping it to SDR.
+
+/// Color properties of a video stream, using ffmpeg's names for the values
+/// (as accepted by `-color_primaries`, `-color_trc`, `-colorspace` and
+/// `-color_range`).
+///
+/// Streams parsed from ffmpeg's log only have the first four fields; the
+/// HDR side data is only available from [`ffprobe_color_metadata`](crate::ffprobe::ffprobe_color_metadata).
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ColorMetadata {
+  /// `tv` (limited) or `pc` (full)
+  pub range: Option<String>,
+  /// Matrix coefficients, e.g. `bt709` or `bt2020nc`
+  pub space: Option<String>,
+  /// e.g. `bt709` or `bt2020`
+  pub primaries: Option<String>,
+  /// Transfer characteristics, e.g. `bt709`, `smpte2084` (PQ, used by
+  /// HDR10) or `arib-std-b67` (HLG)
+  pub transfer: Option<String>,
+  pub mastering_display: Option<MasteringDisplay>,
+  pub content_light_level: Option<ContentLightLevel>,
+}
+
+/// The color volume of the display an HDR video was mastered on (SMPTE
+/// ST 2086). Chromaticities are CIE 1931 `(x, y)` coordinates, luminances are
+/// in cd/m².
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MasteringDisplay {
+  pub red: (f64, f64),
+  pub green: (f64, f64),
+  pub blue: (f64, f64),
+  pub white_point: (f64, f64),
+  pub min_luminance: f64,
+  pub max_luminance: f64,
+}
+
+/// Content light levels of an HDR video, in cd/m².
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ContentLightLevel {
+  /// Maximum content light level (MaxCLL)
+  pub max_cll: u32,
+  /// Maximum frame-average light level (MaxFALL)
+  pub max_fall: u32,
+}
+
+impl MasteringDisplay {
+  /// Format as the value of x265's `master-display` parameter, in units of
+  /// 0.00002 for chromaticities and 0.0001 cd/m² for luminance.
+  ///
+  /// ```rust
+  /// use ffmpeg_sidecar::color::MasteringDisplay;
+  ///
+  /// let display = MasteringDisplay {
+  ///   red: (0.68, 0.32),
+  ///   green: (0.265, 0.69),
+  ///   blue: (0.15, 0.06),
+  ///   white_point: (0.3127, 0.329),
+  ///   min_luminance: 0.005,
+  ///   max_luminance: 1000.0,
+  /// };
+  /// assert_eq!(
+  ///   display.x265_param(),
+  ///   "G(13250,34500)B(7500,3000)R(34000,16000)WP(15635,16450)L(10000000,50)"
+  /// );
+  /// ```
+  pub fn x265_param(&self) -> String {
+    let xy = |(x, y): (f64, f64)| {
+      format!(
+        "({},{})",
+        (x * 50000.0).round() as u32,
+        (y * 50000.0).round() as u32
+      )
+    };
+    format!(
+      "G{}B{}R{}WP{}L({},{})",
+      xy(self.green),
+      xy(self.blue),
+      xy(self.red),
+      xy(self.white_point),
+      (self.max_luminance * 10000.0).round() as u64,
+      (self.min_luminance * 10000.0).round() as u64
+    )
+  }
+}
+
+/// Color space names which ffmpeg prints alone when the matrix, primaries
+/// and transfer all have the same name.
+const UNIFORM_NAMES: &[&str] = &["bt709", "bt470bg", "smpte170m", "smpte240m", "unknown"];
+
+impl ColorMetadata {
+  /// Whether the transfer characteristics are those of HDR10 (PQ) or HLG.
+  pub fn is_hdr(&self) -> bool {
+    matches!(self.transfer.as_deref(), Some("smpte2084" | "arib-std-b67"))
+  }
+
+  /// Parse the parenthesized details after the pixel format of a stream in
+  /// ffmpeg's log, e.g. `tv, bt2020nc/bt2020/smpte2084, progressive`. The
+  /// space, primaries and transfer are printed once if they're all the same.
+  ///
+  /// ```rust
+  /// use ffmpeg_sidecar::color::ColorMetadata;
+  ///
+  /// let color = ColorMetadata::parse_pix_fmt_details("tv, bt2020nc/bt2020/smpte2084");
+  /// assert_eq!(color.range.as_deref(), Some("tv"));
+  /// assert_eq!(color.space.as_deref(), Some("bt2020nc"));
+  /// assert_eq!(color.primaries.as_deref(), Some("bt2020"));
+  /// assert_eq!(color.transfer.as_deref(), Some("smpte2084"));
+  /// assert!(color.is_hdr());
+  ///
+  /// let color = ColorMetadata::parse_pix_fmt_details("tv, bt709, progressive");
+  /// assert_eq!(color.primaries.as_deref(), Some("bt709"));
+  /// ```
+  pub fn parse_pix_fmt_details(details: &str) -> Self {
+    let known = |value: &str| Some(value.to_string()).filter(|value| value != "unknown");
+    let mut color = Self::default();
+    for part in details.split(',').map(str::trim) {
+      let values = part.split('/').collect::<Vec<_>>();
+      match values[..] {
+        ["tv" | "pc"] => color.range = Some(part.to_string()),
+        [space, primaries, transfer] => {
+          color.space = known(space);
+          color.primaries = known(primaries);
+          color.transfer = known(transfer);
+        }
+        [name] if UNIFORM_NAMES.contains(&name) => {
+          color.space = known(name);
+          color.primaries = known(name);
+          color.transfer = known(name);
+        }
+        _ => {}
+      }
+    }
+    color
+  }
+
+  /// Parse the output of [`ffprobe_color_metadata`](crate::ffprobe::ffprobe_color_metadata):
+  /// `key=value` lines of the first video stream and the side data of its
+  /// first frame, in ffprobe's default output format.
+  pub fn parse_ffprobe(output: &str) -> Self {
+    let mut color = Self::default();
+    let mut side_data_type = String::new();
+    let mut mastering = MasteringDisplay::default();
+    let mut light_level = ContentLightLevel::default();
+    let (mut has_mastering, mut has_light_level) = (false, false);
+
+    for line in output.lines().map(str::trim) {
+      if line == "[SIDE_DATA]" || line == "[/SIDE_DATA]" {
+        side_data_type.clear();
+        continue;
+      }
+      let Some((key, value)) = line.split_once('=') else {
+        continue;
+      };
+      let known = Some(value.to_string()).filter(|value| value != "unknown");
+      let number = parse_rational(value).unwrap_or_default();
+      match (side_data_type.as_str(), key) {
+        (_, "side_data_type") => side_data_type = value.to_ascii_lowercase(),
+        ("", "color_range") => color.range = known,
+        ("", "color_space") => color.space = known,
+        ("", "color_primaries") => color.primaries = known,
+        ("", "color_transfer") => color.transfer = known,
+        ("mastering display metadata", key) => {
+          has_mastering = true;
+          match key {
+            "red_x" => mastering.red.0 = number,
+            "red_y" => mastering.red.1 = number,
+            "green_x" => mastering.green.0 = number,
+            "green_y" => mastering.green.1 = number,
+            "blue_x" => mastering.blue.0 = number,
+            "blue_y" => mastering.blue.1 = number,
+            "white_point_x" => mastering.white_point.0 = number,
+            "white_point_y" => mastering.white_point.1 = number,
+            "min_luminance" => mastering.min_luminance = number,
+            "max_luminance" => mastering.max_luminance = number,
+            _ => {}
+          }
+        }
+        ("content light level metadata", key) => {
+          has_light_level = true;
+          match key {
+            "max_content" => light_level.max_cll = number as u32,
+            "max_average" => light_level.max_fall = number as u32,
+            _ => {}
+          }
+        }
+        _ => {}
+      }
+    }
+
+    color.mastering_display = has_mastering.then_some(mastering);
+    color.content_light_level = has_light_level.then_some(light_level);
+    color
+  }
+
+  /// The output arguments which tag the output with these properties. For
+  /// `libx265`, they're also passed to the encoder with `-x265-params`, along
+  /// with the HDR side data, which ffmpeg doesn't pass on by itself.
+  ///
+  /// ```rust
+  /// use ffmpeg_sidecar::color::{ColorMetadata, ContentLightLevel};
+  ///
+  /// let mut color = ColorMetadata::parse_pix_fmt_details("tv, bt2020nc/bt2020/smpte2084");
+  /// color.content_light_level = Some(ContentLightLevel { max_cll: 1000, max_fall: 400 });
+  /// assert_eq!(
+  ///   color.to_args(Some("libx265")),
+  ///   [
+  ///     "-color_primaries", "bt2020", "-color_trc", "smpte2084", "-colorspace", "bt2020nc",
+  ///     "-color_range", "tv", "-x265-params",
+  ///     "colorprim=bt2020:transfer=smpte2084:colormatrix=bt2020nc:range=limited:hdr-opt=1:repeat-headers=1:max-cll=1000,400",
+  ///   ]
+  /// );
+  /// ```
+  pub fn to_args(&self, encoder: Option<&str>) -> Vec<String> {
+    let mut args = Vec::new();
+    let properties = [
+      ("-color_primaries", &self.primaries),
+      ("-color_trc", &self.transfer),
+      ("-colorspace", &self.space),
+      ("-color_range", &self.range),
+    ];
+    for (flag, value) in properties {
+      if let Some(value) = value {
+        args.push(flag.to_string());
+        args.push(value.clone());
+      }
+    }
+
+    if encoder == Some("libx265") {
+      let mut params = Vec::new();
+      let x265_params = [
+        ("colorprim", &self.primaries),
+        ("transfer", &self.transfer),
+        ("colormatrix", &self.space),
+      ];
+      for (name, value) in x265_params {
+        if let Some(value) = value {
+          params.push(format!("{name}={value}"));
+        }
+      }
+      match self.range.as_deref() {
+        Some("tv") => params.push("range=limited".to_string()),
+        Some("pc") => params.push("range=full".to_string()),
+        _ => {}
+      }
+      if self.is_hdr() {
+        params.push("hdr-opt=1:repeat-headers=1".to_string());
+      }
+      if let Some(display) = &self.mastering_display {
+        params.push(format!("master-display={}", display.x265_param()));
+      }
+      if let Some(level) = &self.content_light_level {
+        params.push(format!("max-cll={},{}", level.max_cll, level.max_fall));
+      }
+      if !params.is_empty() {
+        args.push("-x265-params".to_string());
+        args.push(params.join(":"));
+      }
+    }
+    args
+  }
+}
+
+/// Parse a number printed by ffprobe, which may be a rational like `34000/50000`.
+fn parse_rational(value: &str) -> Option<f64> {
+  match value.split_once('/') {
+    Some((num, den)) => {
+      let den = den.parse::<f64>().ok().filter(|den| *den != 0.0)?;
+      Some(num.parse::<f64>().ok()? / den)
+    }
+    None => value.parse().ok(),
+  }
+}
+
+/// Tone mapping curves of the `tonemap` filter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Tonemap {
+  /// Keeps detail in highlights and shadows, a good default for film.
+  #[default]
+  Hable,
+  Reinhard,
+  Mobius,
+  /// Clips everything out of range, keeping the rest exact.
+  Clip,
+}
+
+impl Tonemap {
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      Tonemap::Hable => "hable",
+      Tonemap::Reinhard => "reinhard",
+      Tonemap::Mobius => "mobius",
+      Tonemap::Clip => "clip",
+    }
+  }
+}
+
+/// Options for [`hdr_to_sdr_filter`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TonemapOptions {
+  pub tonemap: Tonemap,
+  /// Nominal peak luminance of the SDR output in cd/m² (`npl` of `zscale`).
+  pub peak_luminance: f64,
+  /// Desaturation strength for highlights; `0.0` keeps the colors as they are.
+  pub desaturation: f64,
+  /// Pixel format of the output.
+  pub pix_fmt: String,
+}
+
+impl Default for TonemapOptions {
+  fn default() -> Self {
+    Self {
+      tonemap: Tonemap::Hable,
+      peak_luminance: 100.0,
+      desaturation: 0.0,
+      pix_fmt: "yuv420p".to_string(),
+    }
+  }
+}
+
+/// The filter chain which tone maps HDR video (PQ or HLG) to SDR BT.709,
+/// which requires an ffmpeg build with `zscale` (libzimg).
+///
+/// ```rust
+/// use ffmpeg_sidecar::color::{hdr_to_sdr_filter, TonemapOptions};
+///
+/// assert_eq!(
+///   hdr_to_sdr_filter(&TonemapOptions::default()),
+///   "zscale=t=linear:npl=100,format=gbrpf32le,zscale=p=bt709,\
+///    tonemap=tonemap=hable:desat=0,zscale=t=bt709:m=bt709:r=tv,format=yuv420p"
+/// );
+/// ```
+pub fn hdr_to_sdr_filter(options: &TonemapOptions) -> String {
+  format!(
+    "zscale=t=linear:npl={},format=gbrpf32le,zscale=p=bt709,\
+     tonemap=tonemap={}:desat={},zscale=t=bt709:m=bt709:r=tv,format={}",
+    options.peak_luminance,
+    options.tonemap.as_str(),
+    options.desaturation,
+    options.pix_fmt
+  )
+}
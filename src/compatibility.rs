@@ -0,0 +1,93 @@
+//! Encoding settings which play everywhere a kind of player is found. See
+//! [`FfmpegCommand::compatibility_target`](crate::command::FfmpegCommand::compatibility_target).
+//!
+//! Most decoders in browsers and devices only take 8-bit 4:2:0 H.264, so a
+//! 10-bit or 4:4:4 source encoded as is either fails to play or plays in
+//! some browsers only. Hardware decoders also have a maximum H.264 level,
+//! which caps the resolution, frame rate and bitrate.
+
+use std::fmt;
+
+/// The players an output has to play in.
+///
+/// Each target sets the video encoder, pixel format, H.264 profile and
+/// level, and the audio encoder of the next output, and makes MP4 outputs
+/// start playing before they're fully downloaded (`+faststart`):
+///
+/// | Target      | Arguments |
+/// |-------------|-----------|
+/// | `WebSafari` | `-c:v libx264 -pix_fmt yuv420p -profile:v high -level:v 4.1 -c:a aac` |
+/// | `WebChrome` | `-c:v libx264 -pix_fmt yuv420p -profile:v high -c:a aac` |
+/// | `Broad`     | `-c:v libx264 -pix_fmt yuv420p -profile:v main -level:v 4.0 -c:a aac` |
+///
+/// ```rust
+/// use ffmpeg_sidecar::compatibility::CompatibilityTarget;
+///
+/// assert_eq!(
+///   CompatibilityTarget::WebSafari.args(),
+///   ["-c:v", "libx264", "-pix_fmt", "yuv420p", "-profile:v", "high", "-level:v", "4.1", "-c:a", "aac"]
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CompatibilityTarget {
+  /// Safari on macOS and iOS, whose hardware decoders are the strictest:
+  /// High profile up to level 4.1, which covers 1080p at 30 fps.
+  WebSafari,
+  /// Chrome, Firefox and Edge, which decode any level of the High profile.
+  WebChrome,
+  /// Older phones, TVs and set-top boxes: Main profile up to level 4.0,
+  /// which covers 1080p at 30 fps with a lower bitrate.
+  Broad,
+}
+
+impl CompatibilityTarget {
+  /// The pixel format every player of the target decodes.
+  pub fn pix_fmt(&self) -> &'static str {
+    "yuv420p"
+  }
+
+  /// The H.264 profile, as passed to `-profile:v`.
+  pub fn profile(&self) -> &'static str {
+    match self {
+      CompatibilityTarget::WebSafari | CompatibilityTarget::WebChrome => "high",
+      CompatibilityTarget::Broad => "main",
+    }
+  }
+
+  /// The maximum H.264 level, as passed to `-level:v`, if the target has
+  /// one.
+  pub fn level(&self) -> Option<&'static str> {
+    match self {
+      CompatibilityTarget::WebSafari => Some("4.1"),
+      CompatibilityTarget::WebChrome => None,
+      CompatibilityTarget::Broad => Some("4.0"),
+    }
+  }
+
+  /// The output options of the target, apart from `+faststart`.
+  pub fn args(&self) -> Vec<String> {
+    let mut args = vec![
+      "-c:v".to_string(),
+      "libx264".to_string(),
+      "-pix_fmt".to_string(),
+      self.pix_fmt().to_string(),
+      "-profile:v".to_string(),
+      self.profile().to_string(),
+    ];
+    if let Some(level) = self.level() {
+      args.extend(["-level:v".to_string(), level.to_string()]);
+    }
+    args.extend(["-c:a".to_string(), "aac".to_string()]);
+    args
+  }
+}
+
+impl fmt::Display for CompatibilityTarget {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(match self {
+      CompatibilityTarget::WebSafari => "Safari",
+      CompatibilityTarget::WebChrome => "Chrome",
+      CompatibilityTarget::Broad => "most devices",
+    })
+  }
+}
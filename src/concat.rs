@@ -0,0 +1,354 @@
+//! Joining media files one after the other, with a chapter for each of
+//! them.
+//!
+//! ```rust,no_run
+//! use ffmpeg_sidecar::concat::{concat_with_chapters, ConcatOptions};
+//!
+//! let chapters = concat_with_chapters(
+//!   &[("intro.mp4", "Introduction"), ("part1.mp4", "Part 1"), ("part2.mp4", "Part 2")],
+//!   "lecture.mp4",
+//!   ConcatOptions::default(),
+//! )
+//! .unwrap();
+//! for chapter in chapters {
+//!   println!("{:.1}s - {:.1}s: {}", chapter.start, chapter.end, chapter.title);
+//! }
+//! ```
+
+use std::{fmt, fs, path::Path};
+
+use crate::{
+  command::FfmpegCommand,
+  cut::concat_list,
+  event::FfmpegEvent,
+  geometry::{fit_filter, FitMode, GeometryError},
+  probe::{probe_cached, MediaInfo, StreamInfo},
+  queue::run_job,
+  temp::TempRegistry,
+};
+
+/// A chapter of a media file, with its times in seconds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chapter {
+  pub title: String,
+  pub start: f64,
+  pub end: f64,
+}
+
+/// How [`concat_with_chapters`] joins the inputs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ConcatMethod {
+  /// The demuxer if the inputs are compatible, see [`concat_method`], or
+  /// else the filter.
+  #[default]
+  Auto,
+  /// The `concat` demuxer, copying the streams without re-encoding. Very
+  /// fast, but only plays correctly if every input has the same codecs and
+  /// parameters.
+  Demuxer,
+  /// The `concat` filter, re-encoding everything. The video of every input
+  /// is fitted into the size of the first one, padding the rest, and its
+  /// audio resampled to the same rate and channels.
+  Filter,
+}
+
+/// The settings of [`concat_with_chapters`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ConcatOptions {
+  pub method: ConcatMethod,
+  /// The video encoder when re-encoding, or the default one of the output
+  /// format.
+  pub video_codec: Option<String>,
+  /// The audio encoder when re-encoding, or the default one of the output
+  /// format.
+  pub audio_codec: Option<String>,
+}
+
+/// Returned (through `anyhow::Error`) when a [`concat_with_chapters`] fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConcatError {
+  NoInputs,
+  /// The duration of an input isn't known, so its chapter can't end.
+  UnknownDuration {
+    input: String,
+  },
+  /// ffmpeg failed to join the inputs.
+  Failed {
+    /// The spawn error, or the error messages logged by ffmpeg.
+    message: String,
+  },
+}
+
+impl fmt::Display for ConcatError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      ConcatError::NoInputs => f.write_str("nothing to concatenate"),
+      ConcatError::UnknownDuration { input } => write!(f, "the duration of {input} isn't known"),
+      ConcatError::Failed { message } => write!(f, "concatenation failed: {message}"),
+    }
+  }
+}
+
+impl std::error::Error for ConcatError {}
+
+/// The chapters of inputs joined one after the other, each lasting the
+/// duration of its input, in seconds.
+///
+/// ```rust
+/// use ffmpeg_sidecar::concat::chapters_from_durations;
+///
+/// let chapters = chapters_from_durations(&[("Intro", 12.5), ("Part 1", 600.0)]);
+/// assert_eq!((chapters[1].start, chapters[1].end), (12.5, 612.5));
+/// ```
+pub fn chapters_from_durations<T: AsRef<str>>(parts: &[(T, f64)]) -> Vec<Chapter> {
+  let mut start = 0.0;
+  parts
+    .iter()
+    .map(|(title, duration)| {
+      let chapter = Chapter {
+        title: title.as_ref().to_string(),
+        start,
+        end: start + duration,
+      };
+      start = chapter.end;
+      chapter
+    })
+    .collect()
+}
+
+/// The chapters as an FFMETADATA file, read by ffmpeg as an input with
+/// `-map_chapters`, in milliseconds.
+///
+/// ```rust
+/// use ffmpeg_sidecar::concat::{ffmetadata_chapters, Chapter};
+///
+/// let chapters = [Chapter { title: "Q&A; part=2".to_string(), start: 0.0, end: 61.5 }];
+/// assert_eq!(
+///   ffmetadata_chapters(&chapters),
+///   ";FFMETADATA1\n[CHAPTER]\nTIMEBASE=1/1000\nSTART=0\nEND=61500\ntitle=Q&A\\; part\\=2\n"
+/// );
+/// ```
+pub fn ffmetadata_chapters(chapters: &[Chapter]) -> String {
+  let mut metadata = String::from(";FFMETADATA1\n");
+  for chapter in chapters {
+    metadata.push_str(&format!(
+      "[CHAPTER]\nTIMEBASE=1/1000\nSTART={}\nEND={}\ntitle={}\n",
+      (chapter.start * 1000.0).round() as u64,
+      (chapter.end * 1000.0).round() as u64,
+      escape_ffmetadata(&chapter.title),
+    ));
+  }
+  metadata
+}
+
+/// Escape the characters with a meaning in FFMETADATA with a backslash.
+fn escape_ffmetadata(value: &str) -> String {
+  let mut escaped = String::with_capacity(value.len());
+  for c in value.chars() {
+    if matches!(c, '=' | ';' | '#' | '\\' | '\n') {
+      escaped.push('\\');
+    }
+    escaped.push(c);
+  }
+  escaped
+}
+
+/// How inputs with these streams can be joined: [`ConcatMethod::Demuxer`]
+/// if the first video and audio streams of every input have the same codec
+/// and parameters as those of the first input, or else
+/// [`ConcatMethod::Filter`].
+pub fn concat_method(inputs: &[MediaInfo]) -> ConcatMethod {
+  let params = |info: &MediaInfo| {
+    let video = info.streams_of_type("video").next().map(|video| {
+      (
+        video.codec_name.clone(),
+        video.width,
+        video.height,
+        video.pix_fmt.clone(),
+        video.r_frame_rate,
+      )
+    });
+    let audio = info
+      .streams_of_type("audio")
+      .next()
+      .map(|audio| (audio.codec_name.clone(), audio.sample_rate, audio.channels));
+    (video, audio)
+  };
+  let first = inputs.first().map(params);
+  match inputs.iter().all(|info| Some(params(info)) == first) {
+    true => ConcatMethod::Demuxer,
+    false => ConcatMethod::Filter,
+  }
+}
+
+/// Join `inputs` one after the other into `output`, overwriting it, with a
+/// chapter titled after each input, and return the chapters. See the
+/// [module documentation](self) for an example.
+///
+/// The chapters are computed from the probed durations of the inputs, and
+/// muxed in from an FFMETADATA file, see [`ffmetadata_chapters`].
+pub fn concat_with_chapters<P: AsRef<Path>, T: AsRef<str>, O: AsRef<Path>>(
+  inputs: &[(P, T)],
+  output: O,
+  options: ConcatOptions,
+) -> anyhow::Result<Vec<Chapter>> {
+  if inputs.is_empty() {
+    return Err(ConcatError::NoInputs.into());
+  }
+  let mut infos = Vec::with_capacity(inputs.len());
+  let mut durations = Vec::with_capacity(inputs.len());
+  for (input, _) in inputs {
+    let input = input.as_ref();
+    let info = probe_cached(input)?;
+    let duration = info.duration.ok_or_else(|| ConcatError::UnknownDuration {
+      input: input.to_string_lossy().into_owned(),
+    })?;
+    infos.push(info);
+    durations.push(duration);
+  }
+  let chapters = chapters_from_durations(
+    &inputs
+      .iter()
+      .zip(&durations)
+      .map(|((_, title), duration)| (title.as_ref(), *duration))
+      .collect::<Vec<_>>(),
+  );
+
+  let dir = TempRegistry::global().create_dir("concat")?;
+  let metadata = dir.path().join("chapters.txt");
+  fs::write(&metadata, ffmetadata_chapters(&chapters))?;
+
+  let method = match options.method {
+    ConcatMethod::Auto => concat_method(&infos),
+    method => method,
+  };
+  let mut command = FfmpegCommand::new();
+  command.hide_banner();
+  match method {
+    ConcatMethod::Filter => {
+      for (input, _) in inputs {
+        command.input(input.as_ref().to_string_lossy());
+      }
+      command
+        .input(metadata.to_string_lossy())
+        .filter_complex(concat_filter(&infos, &durations)?);
+      for (codec_type, label) in [("video", "[v]"), ("audio", "[a]")] {
+        if infos
+          .iter()
+          .any(|info| info.streams_of_type(codec_type).next().is_some())
+        {
+          command.args(["-map", label]);
+        }
+      }
+      command.args(["-map_chapters".to_string(), inputs.len().to_string()]);
+      if let Some(codec) = &options.video_codec {
+        command.codec_video(codec);
+      }
+      if let Some(codec) = &options.audio_codec {
+        command.codec_audio(codec);
+      }
+    }
+    ConcatMethod::Demuxer | ConcatMethod::Auto => {
+      let list = dir.path().join("inputs.txt");
+      let paths = inputs
+        .iter()
+        .map(|(input, _)| input.as_ref())
+        .collect::<Vec<_>>();
+      fs::write(&list, concat_list(&paths))?;
+      command
+        .format("concat")
+        .args(["-safe", "0"])
+        .input(list.to_string_lossy())
+        .input(metadata.to_string_lossy())
+        .args(["-map", "0", "-map_chapters", "1", "-c", "copy"]);
+    }
+  }
+  command
+    .overwrite()
+    .output(output.as_ref().to_string_lossy());
+
+  let outcome = run_job(&mut command, |_: &FfmpegEvent| {});
+  if !outcome.is_success() {
+    let message = match outcome.result {
+      Err(e) => e.to_string(),
+      Ok(status) if outcome.errors.is_empty() => format!("ffmpeg exited with {status}"),
+      Ok(_) => outcome.errors.join("\n"),
+    };
+    return Err(ConcatError::Failed { message }.into());
+  }
+  Ok(chapters)
+}
+
+/// The `-filter_complex` of [`ConcatMethod::Filter`], joining the inputs
+/// into `[v]` and `[a]`, harmonized to the first video and audio stream
+/// found among them. Inputs missing a stream get black frames or silence
+/// for their duration.
+fn concat_filter(inputs: &[MediaInfo], durations: &[f64]) -> Result<String, GeometryError> {
+  let first = |codec_type| {
+    inputs
+      .iter()
+      .find_map(|info| info.streams_of_type(codec_type).next())
+  };
+  let video = first("video");
+  let audio = first("audio");
+  let mut filter = String::new();
+  let mut joined = String::new();
+  for (index, (info, duration)) in inputs.iter().zip(durations).enumerate() {
+    if let Some(video) = video {
+      // Even, like the output of `fit_filter`
+      let width = video.width.unwrap_or(1280).div_ceil(2) * 2;
+      let height = video.height.unwrap_or(720).div_ceil(2) * 2;
+      let pix_fmt = video.pix_fmt.as_deref().unwrap_or("yuv420p");
+      let rate = video
+        .r_frame_rate
+        .map_or("25".to_string(), |rate| rate.to_string());
+      match info.streams_of_type("video").next() {
+        Some(_) => filter.push_str(&format!(
+          "[{index}:v:0]{},fps={rate},format={pix_fmt}[v{index}];",
+          fit_filter(width, height, FitMode::Contain, "black")?
+        )),
+        None => filter.push_str(&format!(
+          "color=c=black:s={width}x{height}:r={rate}:d={duration},format={pix_fmt}[v{index}];"
+        )),
+      }
+      joined.push_str(&format!("[v{index}]"));
+    }
+    if let Some(audio) = audio {
+      let sample_rate = audio.sample_rate.unwrap_or(48000);
+      let layout = channel_layout(audio);
+      match info.streams_of_type("audio").next() {
+        Some(_) => filter.push_str(&format!(
+          "[{index}:a:0]aresample={sample_rate},\
+           aformat=sample_rates={sample_rate}:channel_layouts={layout}[a{index}];"
+        )),
+        None => filter.push_str(&format!(
+          "anullsrc=r={sample_rate}:cl={layout},atrim=duration={duration}[a{index}];"
+        )),
+      }
+      joined.push_str(&format!("[a{index}]"));
+    }
+  }
+  let mut outputs = String::new();
+  if video.is_some() {
+    outputs.push_str("[v]");
+  }
+  if audio.is_some() {
+    outputs.push_str("[a]");
+  }
+  filter.push_str(&format!(
+    "{joined}concat=n={}:v={}:a={}{outputs}",
+    inputs.len(),
+    u8::from(video.is_some()),
+    u8::from(audio.is_some()),
+  ));
+  Ok(filter)
+}
+
+/// The channel layout of an audio stream, from its number of channels.
+fn channel_layout(audio: &StreamInfo) -> String {
+  match audio.channels {
+    Some(1) => "mono".to_string(),
+    Some(2) | None => "stereo".to_string(),
+    Some(channels) => format!("{channels}c"),
+  }
+}
@@ -0,0 +1,198 @@
+//! Container formats, as told by the content of a file rather than its
+//! extension, for inputs which are misnamed (an `.mp4` which is actually
+//! Matroska) or have no extension at all.
+
+use std::{fmt, path::Path};
+
+#[cfg(feature = "process")]
+use crate::probe::probe_cached;
+
+/// A container format, canonicalized from the demuxer names reported by
+/// ffprobe or from a file extension.
+///
+/// The demuxers which handle several related formats report all of them, as
+/// in `mov,mp4,m4a,3gp,3g2,mj2` or `matroska,webm`, since they can't be told
+/// apart from the content. They're treated as a single format here, named
+/// after the muxer most commonly used for them.
+///
+/// ```rust
+/// use ffmpeg_sidecar::container::ContainerFormat;
+///
+/// let format = ContainerFormat::from_format_name("mov,mp4,m4a,3gp,3g2,mj2");
+/// assert_eq!(format, ContainerFormat::Mp4);
+/// assert_eq!(ContainerFormat::from_extension("MOV"), Some(ContainerFormat::Mp4));
+/// assert_eq!(ContainerFormat::from_format_name("matroska,webm").to_string(), "matroska");
+/// assert_eq!(ContainerFormat::from_format_name("gif").to_string(), "gif");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ContainerFormat {
+  /// MP4, MOV, M4A, 3GP and the other formats based on the ISO base media
+  /// file format.
+  Mp4,
+  /// Matroska, including WebM.
+  Matroska,
+  MpegTs,
+  Flv,
+  Avi,
+  Ogg,
+  Wav,
+  Mp3,
+  /// Raw AAC, packaged as ADTS.
+  Adts,
+  /// Any other format, by the first name ffprobe reports for it.
+  Other(String),
+}
+
+impl ContainerFormat {
+  /// Canonicalize the `format_name` reported by ffprobe, a comma separated
+  /// list of the names of the demuxer.
+  pub fn from_format_name(format_name: &str) -> Self {
+    let names = format_name.split(',').map(str::trim).collect::<Vec<_>>();
+    let format = names.iter().find_map(|name| {
+      Some(match *name {
+        "mov" | "mp4" | "m4a" | "3gp" | "3g2" | "mj2" => ContainerFormat::Mp4,
+        "matroska" | "webm" => ContainerFormat::Matroska,
+        "mpegts" | "mpegtsraw" => ContainerFormat::MpegTs,
+        "flv" | "live_flv" => ContainerFormat::Flv,
+        "avi" => ContainerFormat::Avi,
+        "ogg" => ContainerFormat::Ogg,
+        "wav" => ContainerFormat::Wav,
+        "mp3" => ContainerFormat::Mp3,
+        "aac" => ContainerFormat::Adts,
+        _ => return None,
+      })
+    });
+    format.unwrap_or_else(|| ContainerFormat::Other(names[0].to_string()))
+  }
+
+  /// The format usually found in files with this extension (case
+  /// insensitive, without the dot), if it's a known one.
+  pub fn from_extension(extension: &str) -> Option<Self> {
+    Some(match extension.to_ascii_lowercase().as_str() {
+      "mp4" | "m4v" | "m4a" | "mov" | "3gp" | "3g2" | "mj2" => ContainerFormat::Mp4,
+      "mkv" | "mka" | "mks" | "webm" => ContainerFormat::Matroska,
+      "ts" | "m2ts" | "mts" => ContainerFormat::MpegTs,
+      "flv" => ContainerFormat::Flv,
+      "avi" => ContainerFormat::Avi,
+      "ogg" | "oga" | "ogv" | "opus" => ContainerFormat::Ogg,
+      "wav" => ContainerFormat::Wav,
+      "mp3" => ContainerFormat::Mp3,
+      "aac" => ContainerFormat::Adts,
+      _ => return None,
+    })
+  }
+
+  /// The format of `path` according to its extension.
+  pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+    Self::from_extension(path.as_ref().extension()?.to_str()?)
+  }
+
+  /// The name of the muxer writing this format, as passed to `-f`.
+  pub fn name(&self) -> &str {
+    match self {
+      ContainerFormat::Mp4 => "mp4",
+      ContainerFormat::Matroska => "matroska",
+      ContainerFormat::MpegTs => "mpegts",
+      ContainerFormat::Flv => "flv",
+      ContainerFormat::Avi => "avi",
+      ContainerFormat::Ogg => "ogg",
+      ContainerFormat::Wav => "wav",
+      ContainerFormat::Mp3 => "mp3",
+      ContainerFormat::Adts => "adts",
+      ContainerFormat::Other(name) => name,
+    }
+  }
+
+  /// Whether H.264 and HEVC are stored with length prefixes, rather than the
+  /// start codes of MPEG-TS.
+  pub fn is_mp4_style(&self) -> bool {
+    matches!(
+      self,
+      ContainerFormat::Mp4 | ContainerFormat::Matroska | ContainerFormat::Flv
+    )
+  }
+
+  /// Whether AAC is stored as ADTS, rather than with the configuration in
+  /// the header as in MP4.
+  pub fn is_adts(&self) -> bool {
+    matches!(self, ContainerFormat::MpegTs | ContainerFormat::Adts)
+  }
+}
+
+impl fmt::Display for ContainerFormat {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(self.name())
+  }
+}
+
+/// The format of an input according to its content, and according to its
+/// extension. See [`detect_format`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DetectedFormat {
+  /// The format found by ffprobe, if it could read the file.
+  pub probed: Option<ContainerFormat>,
+  /// The format usually found in files with the extension of the input.
+  pub by_extension: Option<ContainerFormat>,
+}
+
+impl DetectedFormat {
+  /// The probed format, or the one of the extension as a fallback.
+  pub fn format(&self) -> Option<&ContainerFormat> {
+    self.probed.as_ref().or(self.by_extension.as_ref())
+  }
+
+  /// A warning about the extension of `path` if it doesn't match its
+  /// content, in which case the probed format is used.
+  ///
+  /// ```rust
+  /// use ffmpeg_sidecar::container::{ContainerFormat, DetectedFormat};
+  ///
+  /// let detected = DetectedFormat {
+  ///   probed: Some(ContainerFormat::Matroska),
+  ///   by_extension: ContainerFormat::from_path("clip.mp4"),
+  /// };
+  /// assert_eq!(
+  ///   detected.mismatch_warning("clip.mp4").unwrap(),
+  ///   "`clip.mp4` is named like mp4 but contains matroska, which is used instead"
+  /// );
+  /// ```
+  pub fn mismatch_warning<P: AsRef<Path>>(&self, path: P) -> Option<String> {
+    match (&self.probed, &self.by_extension) {
+      (Some(probed), Some(by_extension)) if probed != by_extension => Some(format!(
+        "`{}` is named like {by_extension} but contains {probed}, which is used instead",
+        path.as_ref().display()
+      )),
+      _ => None,
+    }
+  }
+}
+
+/// Run ffprobe on `path` and canonicalize the format it finds from the
+/// content, regardless of the extension.
+///
+/// ```rust,no_run
+/// use ffmpeg_sidecar::container::{probe_format, ContainerFormat};
+///
+/// // A Matroska file with the wrong extension
+/// assert_eq!(probe_format("download.mp4").unwrap(), ContainerFormat::Matroska);
+/// ```
+#[cfg(feature = "process")]
+pub fn probe_format<P: AsRef<Path>>(path: P) -> anyhow::Result<ContainerFormat> {
+  let info = probe_cached(path.as_ref())?;
+  if info.format_name.is_empty() {
+    anyhow::bail!("ffprobe didn't report a format");
+  }
+  Ok(ContainerFormat::from_format_name(&info.format_name))
+}
+
+/// The format of `path` according to both ffprobe and its extension. A file
+/// which can't be probed, like a URL or a file which doesn't exist yet, only
+/// has the format of its extension.
+#[cfg(feature = "process")]
+pub fn detect_format<P: AsRef<Path>>(path: P) -> DetectedFormat {
+  let path = path.as_ref();
+  DetectedFormat {
+    probed: path.is_file().then(|| probe_format(path).ok()).flatten(),
+    by_extension: ContainerFormat::from_path(path),
+  }
+}
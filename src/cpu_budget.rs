@@ -0,0 +1,67 @@
+//! Sharing the cores of the machine between ffmpeg jobs running at once.
+//! See [`FfmpegCommand::cpu_budget`](crate::command::FfmpegCommand::cpu_budget)
+//! and [`JobQueue::cpu_budget`](crate::queue::JobQueue::cpu_budget).
+//!
+//! Without a limit, each ffmpeg starts as many encoder, decoder and filter
+//! threads as there are cores, so several jobs at once mostly slow each other
+//! down by switching between their threads.
+
+/// How much of the machine's logical cores a job, or a queue of jobs, may
+/// use.
+///
+/// ```rust
+/// use ffmpeg_sidecar::cpu_budget::CpuBudget;
+///
+/// assert_eq!(CpuBudget::Cores(6).threads_per_job(4), 1);
+/// assert_eq!(CpuBudget::Cores(8).threads_per_job(3), 2);
+/// assert_eq!(CpuBudget::from(1.0).cores(), ffmpeg_sidecar::cpu_budget::logical_cores());
+/// // ffmpeg's own choice, for every job
+/// assert_eq!(CpuBudget::Cores(0).threads_per_job(4), 0);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CpuBudget {
+  /// A fraction of the logical cores, like `0.5` for half of them. At least
+  /// one core.
+  Fraction(f64),
+  /// A number of cores. `0` lets ffmpeg pick the number of threads, as
+  /// without a budget.
+  Cores(usize),
+}
+
+impl CpuBudget {
+  /// The number of cores of the budget, or 0 to let ffmpeg pick.
+  pub fn cores(&self) -> usize {
+    match *self {
+      CpuBudget::Fraction(fraction) => {
+        ((logical_cores() as f64 * fraction).round() as usize).max(1)
+      }
+      CpuBudget::Cores(cores) => cores,
+    }
+  }
+
+  /// The number of threads for each of `jobs` jobs running at once, sharing
+  /// the budget equally. At least 1, unless the budget is 0.
+  pub fn threads_per_job(&self, jobs: usize) -> usize {
+    match self.cores() {
+      0 => 0,
+      cores => (cores / jobs.max(1)).max(1),
+    }
+  }
+}
+
+impl From<f64> for CpuBudget {
+  fn from(fraction: f64) -> Self {
+    CpuBudget::Fraction(fraction)
+  }
+}
+
+impl From<usize> for CpuBudget {
+  fn from(cores: usize) -> Self {
+    CpuBudget::Cores(cores)
+  }
+}
+
+/// The number of logical cores, or 4 if it can't be detected.
+pub fn logical_cores() -> usize {
+  std::thread::available_parallelism().map_or(4, |cores| cores.get())
+}
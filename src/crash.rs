@@ -0,0 +1,195 @@
+//! Diagnostics for ffmpeg crashing or exiting with an error. See
+//! [`FfmpegChild::enable_crash_diagnostics`](crate::child::FfmpegChild::enable_crash_diagnostics).
+
+use std::{fmt, process::ExitStatus, time::Duration};
+
+/// What was known about ffmpeg when it exited abnormally, i.e. with a
+/// non-zero exit code or by a signal.
+///
+/// Returned by [`FfmpegChild::wait`](crate::child::FfmpegChild::wait) as the
+/// inner error of an `io::Error` once crash diagnostics are enabled, and kept
+/// by the child for [`FfmpegChild::crash_report`](crate::child::FfmpegChild::crash_report).
+/// With the `serde` feature, it can be serialized, e.g. for telemetry; `exit`
+/// is serialized as its exit code, which is `null` when killed by a signal.
+///
+/// ```rust,no_run
+/// use ffmpeg_sidecar::{command::FfmpegCommand, crash::CrashReport};
+///
+/// let mut child = FfmpegCommand::new().input("input.mp4").output("output.mp4").spawn().unwrap();
+/// child.enable_crash_diagnostics();
+/// child.iter().unwrap().for_each(|_| {});
+///
+/// if let Err(e) = child.wait() {
+///   if let Some(report) = e.get_ref().and_then(|e| e.downcast_ref::<CrashReport>()) {
+///     eprintln!("{report}");
+///   }
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CrashReport {
+  #[cfg_attr(feature = "serde", serde(serialize_with = "serialize_exit_status"))]
+  pub exit: ExitStatus,
+  /// The signal which killed the process, on Unix.
+  pub signal: Option<i32>,
+  /// The exit status in words, e.g. `killed by signal 11 (SIGSEGV)` or
+  /// `exited with code 0xC0000005 (access violation)`.
+  pub reason: String,
+  /// The last lines logged by ffmpeg, as read by the iterator, up to
+  /// [`CrashReport::MAX_LOG_LINES`].
+  pub last_log_lines: Vec<String>,
+  /// The program and its arguments, quoted where needed.
+  pub command_line: String,
+  /// The version reported by `ffmpeg -version`, or empty if it couldn't be
+  /// read.
+  pub ffmpeg_version: String,
+  /// Time from spawning to exiting.
+  pub duration_ran: Duration,
+}
+
+impl CrashReport {
+  /// The number of log lines kept once diagnostics are enabled.
+  pub const MAX_LOG_LINES: usize = 100;
+}
+
+impl fmt::Display for CrashReport {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "ffmpeg {} after {:.1}s\ncommand: {}",
+      self.reason,
+      self.duration_ran.as_secs_f64(),
+      self.command_line
+    )?;
+    if !self.ffmpeg_version.is_empty() {
+      write!(f, "\nversion: {}", self.ffmpeg_version)?;
+    }
+    for line in &self.last_log_lines {
+      write!(f, "\n{line}")?;
+    }
+    Ok(())
+  }
+}
+
+impl std::error::Error for CrashReport {}
+
+#[cfg(feature = "serde")]
+fn serialize_exit_status<S: serde::Serializer>(
+  status: &ExitStatus,
+  serializer: S,
+) -> Result<S::Ok, S::Error> {
+  serde::Serialize::serialize(&status.code(), serializer)
+}
+
+/// The signal which terminated the process, on Unix.
+pub(crate) fn exit_signal(status: &ExitStatus) -> Option<i32> {
+  #[cfg(unix)]
+  {
+    use std::os::unix::process::ExitStatusExt;
+    status.signal()
+  }
+  #[cfg(not(unix))]
+  {
+    let _ = status;
+    None
+  }
+}
+
+/// Describe an exit status in words, decoding signals and Windows crash
+/// codes.
+pub(crate) fn describe_exit(status: &ExitStatus) -> String {
+  if let Some(signal) = exit_signal(status) {
+    return match signal_name(signal) {
+      Some(name) => format!("killed by signal {signal} ({name})"),
+      None => format!("killed by signal {signal}"),
+    };
+  }
+  match status.code() {
+    Some(code) if cfg!(windows) => match ntstatus_description(code as u32) {
+      Some(description) => format!("exited with code {:#010X} ({description})", code as u32),
+      None => format!("exited with code {code}"),
+    },
+    Some(code) => format!("exited with code {code}"),
+    None => format!("exited ({status})"),
+  }
+}
+
+/// The name of a Unix signal number, for the ones which usually end ffmpeg.
+///
+/// ```rust
+/// use ffmpeg_sidecar::crash::signal_name;
+/// assert_eq!(signal_name(11), Some("SIGSEGV"));
+/// assert_eq!(signal_name(9), Some("SIGKILL"));
+/// ```
+pub fn signal_name(signal: i32) -> Option<&'static str> {
+  // SIGBUS is the only one numbered differently on Linux and the BSDs
+  const SIGBUS: i32 = if cfg!(any(target_os = "linux", target_os = "android")) {
+    7
+  } else {
+    10
+  };
+  Some(match signal {
+    1 => "SIGHUP",
+    2 => "SIGINT",
+    3 => "SIGQUIT",
+    4 => "SIGILL",
+    5 => "SIGTRAP",
+    6 => "SIGABRT",
+    8 => "SIGFPE",
+    9 => "SIGKILL",
+    SIGBUS => "SIGBUS",
+    11 => "SIGSEGV",
+    13 => "SIGPIPE",
+    14 => "SIGALRM",
+    15 => "SIGTERM",
+    24 => "SIGXCPU",
+    25 => "SIGXFSZ",
+    _ => return None,
+  })
+}
+
+/// A description of the `NTSTATUS` codes a crashed process exits with on
+/// Windows.
+///
+/// ```rust
+/// use ffmpeg_sidecar::crash::ntstatus_description;
+/// assert_eq!(ntstatus_description(0xC0000005), Some("access violation"));
+/// assert_eq!(ntstatus_description(1), None);
+/// ```
+pub fn ntstatus_description(code: u32) -> Option<&'static str> {
+  Some(match code {
+    0xC0000005 => "access violation",
+    0xC0000017 => "out of memory",
+    0xC000001D => "illegal instruction",
+    0xC0000094 => "integer division by zero",
+    0xC00000FD => "stack overflow",
+    0xC0000135 => "a required DLL was not found",
+    0xC0000139 => "entry point not found in a DLL",
+    0xC0000142 => "a DLL failed to initialize",
+    0xC000013A => "terminated by Ctrl+C",
+    0xC0000409 => "stack buffer overrun or abort",
+    0x40010004 => "terminated by the debugger or task manager",
+    _ => return None,
+  })
+}
+
+/// Format a program and its arguments for display, quoting the ones which
+/// contain whitespace or quotes.
+pub(crate) fn format_command_line<I, S>(parts: I) -> String
+where
+  I: IntoIterator<Item = S>,
+  S: AsRef<str>,
+{
+  parts
+    .into_iter()
+    .map(|part| {
+      let part = part.as_ref();
+      if part.is_empty() || part.contains(|c: char| c.is_whitespace() || c == '"' || c == '\'') {
+        format!("\"{}\"", part.replace('"', "\\\""))
+      } else {
+        part.to_string()
+      }
+    })
+    .collect::<Vec<_>>()
+    .join(" ")
+}
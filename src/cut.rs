@@ -0,0 +1,358 @@
+//! Cutting a clip out of a media file, trading off speed and accuracy.
+//!
+//! ```rust,no_run
+//! use ffmpeg_sidecar::cut::{cut_with_progress, CutMode};
+//! use std::time::Duration;
+//!
+//! cut_with_progress(
+//!   "movie.mp4",
+//!   Duration::from_secs(61),
+//!   Duration::from_secs(95),
+//!   "clip.mp4",
+//!   CutMode::SmartReencodeBoundaries,
+//!   |progress| println!("{:.0}%", progress.fraction * 100.0),
+//! )
+//! .unwrap();
+//! ```
+
+use std::{cell::RefCell, fmt, fs, path::Path, time::Duration};
+
+use crate::{
+  command::FfmpegCommand, event::FfmpegEvent, ffprobe::ffprobe_keyframes, probe::probe_cached,
+  queue::run_job, temp::TempRegistry,
+};
+
+/// Added to a keyframe timestamp when seeking to it, since input seeking
+/// snaps to the last keyframe at or before the position, and the timestamps
+/// printed by ffprobe are rounded.
+const KEYFRAME_SEEK_OFFSET: f64 = 0.0005;
+
+/// How [`cut`] trades off speed for accuracy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum CutMode {
+  /// Copy the streams without re-encoding. Very fast, but the video starts
+  /// at the keyframe before `start`, which usually shows as frozen video for
+  /// up to a few seconds.
+  Copy,
+  /// Re-encode the whole clip with the default encoders for the output.
+  /// Frame-accurate, but slow for long clips.
+  #[default]
+  Accurate,
+  /// Re-encode only the frames between `start` and the next keyframe, copy
+  /// the rest, and concatenate both parts. Frame-accurate and nearly as fast
+  /// as [`Copy`](CutMode::Copy).
+  ///
+  /// Only H.264 and HEVC video with AAC, MP3 or AC-3 audio can be joined
+  /// this way; other inputs are cut as with [`Accurate`](CutMode::Accurate).
+  SmartReencodeBoundaries,
+}
+
+/// Progress of a [`cut_with_progress`] across all of its ffmpeg runs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CutProgress {
+  /// Index of the ffmpeg run in progress, starting at 0.
+  pub step: usize,
+  /// Number of ffmpeg runs needed for the cut (up to 3).
+  pub steps: usize,
+  /// Estimated fraction of the whole cut which is done, from `0.0` to `1.0`,
+  /// weighting each run by the duration it writes.
+  pub fraction: f64,
+}
+
+/// Returned (through `anyhow::Error`) when a cut fails.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CutError {
+  /// `end` is not after `start`.
+  InvalidRange { start: Duration, end: Duration },
+  /// One of the ffmpeg runs failed.
+  Failed {
+    /// `copy`, `encode`, `head`, `tail` or `concat`.
+    step: &'static str,
+    /// The spawn error, or the error messages logged by ffmpeg.
+    message: String,
+  },
+}
+
+impl fmt::Display for CutError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      CutError::InvalidRange { start, end } => {
+        write!(f, "cut end {end:?} must be after its start {start:?}")
+      }
+      CutError::Failed { step, message } => write!(f, "cut failed at step {step}: {message}"),
+    }
+  }
+}
+
+impl std::error::Error for CutError {}
+
+/// Cut the range from `start` to `end` out of `input` into `output`,
+/// overwriting it if it exists. Blocks until every ffmpeg run has finished.
+pub fn cut<I: AsRef<Path>, O: AsRef<Path>>(
+  input: I,
+  start: Duration,
+  end: Duration,
+  output: O,
+  mode: CutMode,
+) -> anyhow::Result<()> {
+  cut_with_progress(input, start, end, output, mode, |_| {})
+}
+
+/// Like [`cut`], additionally reporting the combined progress of the ffmpeg
+/// runs to `on_progress`.
+///
+/// Intermediate files are written to the system temporary directory, and
+/// removed whether or not the cut succeeds.
+pub fn cut_with_progress<I, O, F>(
+  input: I,
+  start: Duration,
+  end: Duration,
+  output: O,
+  mode: CutMode,
+  on_progress: F,
+) -> anyhow::Result<()>
+where
+  I: AsRef<Path>,
+  O: AsRef<Path>,
+  F: FnMut(CutProgress),
+{
+  if end <= start {
+    return Err(CutError::InvalidRange { start, end }.into());
+  }
+  let (input, output) = (input.as_ref(), output.as_ref());
+  let (start, end) = (start.as_secs_f64(), end.as_secs_f64());
+  let on_progress = RefCell::new(on_progress);
+
+  match mode {
+    CutMode::Copy => run_steps(vec![copy_step(input, start, end, output)], &on_progress),
+    CutMode::Accurate => run_steps(vec![encode_step(input, start, end, output)], &on_progress),
+    CutMode::SmartReencodeBoundaries => smart_cut(input, start, end, output, &on_progress),
+  }
+}
+
+/// A single ffmpeg run of a cut.
+struct Step {
+  name: &'static str,
+  /// Duration written by the step, in seconds
+  duration: f64,
+  command: FfmpegCommand,
+}
+
+fn copy_step(input: &Path, start: f64, end: f64, output: &Path) -> Step {
+  let mut command = FfmpegCommand::new();
+  command
+    .seek(start)
+    .input(input.to_string_lossy())
+    .duration(end - start)
+    .codec_video("copy")
+    .codec_audio("copy")
+    .args(["-avoid_negative_ts", "make_zero"])
+    .overwrite()
+    .output(output.to_string_lossy());
+  Step {
+    name: "copy",
+    duration: end - start,
+    command,
+  }
+}
+
+fn encode_step(input: &Path, start: f64, end: f64, output: &Path) -> Step {
+  let mut command = FfmpegCommand::new();
+  command
+    .seek(start)
+    .input(input.to_string_lossy())
+    .duration(end - start)
+    .overwrite()
+    .output(output.to_string_lossy());
+  Step {
+    name: "encode",
+    duration: end - start,
+    command,
+  }
+}
+
+fn smart_cut(
+  input: &Path,
+  start: f64,
+  end: f64,
+  output: &Path,
+  on_progress: &RefCell<impl FnMut(CutProgress)>,
+) -> anyhow::Result<()> {
+  let info = probe_cached(input)?;
+  let video = info.streams_of_type("video").next();
+  let audio = info.streams_of_type("audio").next();
+  let Some(video_encoder) = video.and_then(|video| smart_video_encoder(&video.codec_name)) else {
+    return run_steps(vec![encode_step(input, start, end, output)], on_progress);
+  };
+  let audio_encoder = match audio {
+    Some(audio) => match smart_audio_encoder(&audio.codec_name) {
+      Some(encoder) => Some((encoder, audio)),
+      None => return run_steps(vec![encode_step(input, start, end, output)], on_progress),
+    },
+    None => None,
+  };
+
+  let keyframe = ffprobe_keyframes(input, start, end)?
+    .into_iter()
+    .find(|time| *time + KEYFRAME_SEEK_OFFSET >= start && *time < end);
+  let keyframe = match keyframe {
+    // Starting on a keyframe, so copying is already accurate
+    Some(keyframe) if keyframe <= start + KEYFRAME_SEEK_OFFSET => {
+      let mut step = copy_step(input, keyframe + KEYFRAME_SEEK_OFFSET, end, output);
+      step.duration = end - start;
+      return run_steps(vec![step], on_progress);
+    }
+    Some(keyframe) => keyframe,
+    None => return run_steps(vec![encode_step(input, start, end, output)], on_progress),
+  };
+
+  let parts = TempRegistry::global().create_dir("cut")?;
+  let head = parts.path().join("head.ts");
+  let tail = parts.path().join("tail.ts");
+  let list = parts.path().join("parts.txt");
+
+  // The parts are MPEG-TS, which repeats the codec parameters in-band, so
+  // they can be joined even though the encoder's parameters differ from the
+  // source's.
+  let mut head_command = FfmpegCommand::new();
+  head_command
+    .seek(start)
+    .input(input.to_string_lossy())
+    .duration(keyframe - start)
+    .args(["-map", "0:v:0", "-map", "0:a:0?"])
+    .codec_video(video_encoder);
+  if let Some(pix_fmt) = video.and_then(|video| video.pix_fmt.as_ref()) {
+    head_command.pix_fmt(pix_fmt);
+  }
+  if let Some((encoder, audio)) = audio_encoder {
+    head_command.codec_audio(encoder);
+    if let Some(sample_rate) = audio.sample_rate {
+      head_command.args(["-ar", &sample_rate.to_string()]);
+    }
+    if let Some(channels) = audio.channels {
+      head_command.args(["-ac", &channels.to_string()]);
+    }
+  }
+  head_command
+    .format("mpegts")
+    .overwrite()
+    .output(head.to_string_lossy());
+
+  let mut tail_command = FfmpegCommand::new();
+  tail_command
+    .seek(keyframe + KEYFRAME_SEEK_OFFSET)
+    .input(input.to_string_lossy())
+    .duration(end - keyframe)
+    .args(["-map", "0:v:0", "-map", "0:a:0?"])
+    .codec_video("copy")
+    .codec_audio("copy")
+    .format("mpegts")
+    .overwrite()
+    .output(tail.to_string_lossy());
+
+  fs::write(&list, concat_list(&[&head, &tail]))?;
+  let mut concat_command = FfmpegCommand::new();
+  concat_command
+    .format("concat")
+    .args(["-safe", "0"])
+    .input(list.to_string_lossy())
+    .args(["-map", "0", "-c", "copy"])
+    .overwrite()
+    .output(output.to_string_lossy());
+
+  run_steps(
+    vec![
+      Step {
+        name: "head",
+        duration: keyframe - start,
+        command: head_command,
+      },
+      Step {
+        name: "tail",
+        duration: end - keyframe,
+        command: tail_command,
+      },
+      Step {
+        name: "concat",
+        duration: end - start,
+        command: concat_command,
+      },
+    ],
+    on_progress,
+  )
+}
+
+/// The encoder matching a video codec, for codecs which can be joined with
+/// stream copied parts.
+fn smart_video_encoder(codec_name: &str) -> Option<&'static str> {
+  match codec_name {
+    "h264" => Some("libx264"),
+    "hevc" => Some("libx265"),
+    _ => None,
+  }
+}
+
+fn smart_audio_encoder(codec_name: &str) -> Option<&'static str> {
+  match codec_name {
+    "aac" => Some("aac"),
+    "mp3" => Some("libmp3lame"),
+    "ac3" => Some("ac3"),
+    _ => None,
+  }
+}
+
+/// The input file of the `concat` demuxer. Relative paths in it are resolved
+/// against the directory of the list.
+pub(crate) fn concat_list(parts: &[&Path]) -> String {
+  parts
+    .iter()
+    .map(|part| format!("file '{}'\n", part.to_string_lossy().replace('\'', "'\\''")))
+    .collect()
+}
+
+fn run_steps(
+  mut steps: Vec<Step>,
+  on_progress: &RefCell<impl FnMut(CutProgress)>,
+) -> anyhow::Result<()> {
+  let total = steps
+    .iter()
+    .map(|step| step.duration)
+    .sum::<f64>()
+    .max(f64::EPSILON);
+  let count = steps.len();
+  let mut done = 0.0;
+
+  for (index, step) in steps.iter_mut().enumerate() {
+    let report = |out_time: f64| {
+      if let Ok(mut on_progress) = on_progress.try_borrow_mut() {
+        on_progress(CutProgress {
+          step: index,
+          steps: count,
+          fraction: ((done + out_time.clamp(0.0, step.duration)) / total).min(1.0),
+        });
+      }
+    };
+    let outcome = run_job(&mut step.command, |event| {
+      if let FfmpegEvent::Progress(progress) = event {
+        report(progress.out_time.map_or(0.0, f64::from));
+      }
+    });
+    if !outcome.is_success() {
+      let message = match outcome.result {
+        Err(e) => e.to_string(),
+        Ok(status) if outcome.errors.is_empty() => format!("ffmpeg exited with {status}"),
+        Ok(_) => outcome.errors.join("\n"),
+      };
+      return Err(
+        CutError::Failed {
+          step: step.name,
+          message,
+        }
+        .into(),
+      );
+    }
+    report(step.duration);
+    done += step.duration;
+  }
+  Ok(())
+}
@@ -0,0 +1,96 @@
+//! Settings applied to every new [`FfmpegCommand`](crate::command::FfmpegCommand)
+//! of the process, to avoid repeating them for each command. See
+//! [`FfmpegDefaults::set`].
+
+use std::{
+  path::PathBuf,
+  sync::{PoisonError, RwLock},
+};
+
+use crate::stderr_policy::Verbosity;
+
+static DEFAULTS: RwLock<Option<FfmpegDefaults>> = RwLock::new(None);
+
+/// The settings every [`FfmpegCommand::new`](crate::command::FfmpegCommand::new)
+/// starts with. The defaults of this type are the ones used without
+/// [`FfmpegDefaults::set`].
+///
+/// A command copies the defaults when it's constructed, so changing them
+/// doesn't affect the commands built before. Each command can still override
+/// them, with [`FfmpegCommand::log_level`](crate::command::FfmpegCommand::log_level),
+/// [`FfmpegCommand::kill_on_drop`](crate::command::FfmpegCommand::kill_on_drop),
+/// or other defaults with
+/// [`FfmpegCommand::with_defaults`](crate::command::FfmpegCommand::with_defaults).
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct FfmpegDefaults {
+  /// Add `-hide_banner`.
+  pub hide_banner: bool,
+  /// The `-loglevel`, or `info` by default. The `level` flag expected by the
+  /// log parser is always added. Below `info`, ffmpeg doesn't log the inputs
+  /// and outputs, which the iterator needs for output frames.
+  pub log_level: Option<Verbosity>,
+  /// Kill ffmpeg when its [`FfmpegChild`](crate::child::FfmpegChild) is
+  /// dropped before it exits.
+  pub kill_on_drop: bool,
+  /// Added after the log level, e.g. `-nostdin` or `-nostats`.
+  pub extra_global_args: Vec<String>,
+  /// The ffmpeg binary, instead of the one found by
+  /// [`ffmpeg_path`](crate::paths::ffmpeg_path). Not used by
+  /// [`FfmpegCommand::new_with_path`](crate::command::FfmpegCommand::new_with_path).
+  pub binary_path: Option<PathBuf>,
+  /// The ffprobe binary, instead of the one found by
+  /// [`ffprobe_path`](crate::ffprobe::ffprobe_path), which returns it, so
+  /// every function running ffprobe uses it.
+  pub ffprobe_binary_path: Option<PathBuf>,
+}
+
+impl FfmpegDefaults {
+  /// Replace the defaults of the commands constructed from now on, in any
+  /// thread.
+  ///
+  /// ```rust
+  /// use ffmpeg_sidecar::{command::FfmpegCommand, defaults::FfmpegDefaults};
+  ///
+  /// let before = FfmpegCommand::new_with_path("ffmpeg");
+  /// FfmpegDefaults::set(FfmpegDefaults {
+  ///   hide_banner: true,
+  ///   extra_global_args: vec!["-nostdin".to_string()],
+  ///   ..Default::default()
+  /// });
+  /// let after = FfmpegCommand::new_with_path("ffmpeg");
+  ///
+  /// assert_eq!(before.get_args().collect::<Vec<_>>(), ["-loglevel", "level+info"]);
+  /// assert_eq!(
+  ///   after.get_args().collect::<Vec<_>>(),
+  ///   ["-loglevel", "level+info", "-hide_banner", "-nostdin"]
+  /// );
+  /// ```
+  pub fn set(defaults: FfmpegDefaults) {
+    *DEFAULTS.write().unwrap_or_else(PoisonError::into_inner) = Some(defaults);
+  }
+
+  /// A copy of the current defaults.
+  pub fn get() -> FfmpegDefaults {
+    DEFAULTS
+      .read()
+      .unwrap_or_else(PoisonError::into_inner)
+      .clone()
+      .unwrap_or_default()
+  }
+
+  /// Go back to the defaults of this type.
+  pub fn reset() {
+    *DEFAULTS.write().unwrap_or_else(PoisonError::into_inner) = None;
+  }
+
+  /// The arguments every command starts with.
+  pub(crate) fn global_args(&self) -> Vec<String> {
+    let level = self.log_level.unwrap_or(Verbosity::Info);
+    let mut args = vec!["-loglevel".to_string(), format!("level+{}", level.name())];
+    if self.hide_banner {
+      args.push("-hide_banner".to_string());
+    }
+    args.extend(self.extra_global_args.iter().cloned());
+    args
+  }
+}
@@ -1,49 +1,302 @@
 use std::{
-    fs::{ create_dir_all, read_dir, remove_dir_all, remove_file, rename },
-    io::Read,
+    fmt,
+    fs::{ create_dir_all, read_dir, read_to_string, remove_dir_all, remove_file, rename, write, File },
+    io::{ Read, Seek, SeekFrom },
     path::{ Path, PathBuf },
     process::{ Command, ExitStatus, Stdio },
+    str::FromStr,
 };
 
 use anyhow::Context;
 
-use crate::{ command::ffmpeg_is_installed, paths::sidecar_dir };
+use crate::{
+    command::{ ffmpeg_is_installed, forget_verified_ffmpeg },
+    ffprobe::forget_verified_ffprobe,
+    install_info::InstallInfo,
+    paths::sidecar_dir,
+    spawner::{ self, StdioConfig },
+    stdio_policy::StdioPolicy,
+    version::ffmpeg_version_with_path,
+};
 
 pub const UNPACK_DIRNAME: &str = "ffmpeg_release_temp";
 
+/// Name of the file recording which `BuildVariant` is installed, written to
+/// the same directory as the binaries.
+pub const VARIANT_FILENAME: &str = "ffmpeg_build_variant.txt";
+
+/// Which of the upstream FFmpeg builds to download.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "kebab-case")
+)]
+pub enum BuildVariant {
+    /// GPL build with the most commonly used libraries; the default.
+    #[default]
+    Essentials,
+    /// GPL build with every library the upstream build includes, e.g.
+    /// libaom and libplacebo. Windows (gyan.dev) and Linux (BtbN) only.
+    Full,
+    /// LGPL build with shared libraries, for applications which can't ship
+    /// GPL code. Windows and Linux (BtbN) only.
+    ///
+    /// The libraries are placed next to the binaries. Windows loads them from
+    /// there; on Linux, the sidecar directory may also need to be added to
+    /// `LD_LIBRARY_PATH`.
+    LgplShared,
+}
+
+impl BuildVariant {
+    /// The name recorded in `VARIANT_FILENAME`.
+    ///
+    /// ```rust
+    /// use ffmpeg_sidecar::download::BuildVariant;
+    /// assert_eq!(BuildVariant::LgplShared.as_str(), "lgpl-shared");
+    /// assert_eq!("lgpl-shared".parse::<BuildVariant>().unwrap(), BuildVariant::LgplShared);
+    /// ```
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BuildVariant::Essentials => "essentials",
+            BuildVariant::Full => "full",
+            BuildVariant::LgplShared => "lgpl-shared",
+        }
+    }
+}
+
+impl fmt::Display for BuildVariant {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for BuildVariant {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match s.trim() {
+            "essentials" => Ok(BuildVariant::Essentials),
+            "full" => Ok(BuildVariant::Full),
+            "lgpl-shared" => Ok(BuildVariant::LgplShared),
+            other => anyhow::bail!("Unknown build variant: {}", other),
+        }
+    }
+}
+
+/// Returned (through `anyhow::Error`) by the stages of `auto_download`, when
+/// they fail for a reason other than an I/O error.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InstallError {
+    /// No build of this variant is published for the target platform.
+    UnsupportedPlatform {
+        variant: BuildVariant,
+    },
+    /// The version number couldn't be found in the manifest.
+    UnreadableManifest {
+        url: String,
+    },
+    /// `curl` failed to download the archive.
+    DownloadFailed {
+        status: ExitStatus,
+    },
+    /// The server answered the download with an HTTP error, like `403` for
+    /// an expired link.
+    HttpStatus {
+        code: u16,
+        /// The start of the body, like `NotAnArchive::first_bytes_preview`.
+        first_bytes_preview: String,
+    },
+    /// The downloaded file doesn't start like an archive of the format of
+    /// its extension, typically because it's an HTML or XML error page served
+    /// by the server or a captive portal.
+    NotAnArchive {
+        /// The `Content-Type` of the response when known, or else a guess
+        /// from the first bytes, like `text/html`, or `empty`.
+        content_type_guess: String,
+        /// The first bytes, with the unprintable ones replaced by `.`.
+        first_bytes_preview: String,
+    },
+    /// The archive starts like one, but its end is missing, e.g. because the
+    /// download was interrupted.
+    TruncatedArchive {
+        extension: String,
+    },
+    /// The SHA-256 checksum of the archive isn't the expected one, e.g.
+    /// because the download was truncated or tampered with.
+    ChecksumMismatch {
+        expected: String,
+        actual: String,
+    },
+    /// The archive isn't a ZIP or TAR file, or isn't a ZIP file on Windows.
+    UnsupportedArchive {
+        extension: String,
+    },
+    /// The unpacking command failed.
+    UnpackFailed {
+        status: ExitStatus,
+    },
+    /// A binary is missing from the archive, or from the install directory.
+    BinaryNotFound {
+        path: PathBuf,
+    },
+    /// The installed ffmpeg doesn't run, e.g. because it was built for
+    /// another architecture, or is missing its shared libraries.
+    ValidationFailed {
+        path: PathBuf,
+    },
+}
+
+impl fmt::Display for InstallError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InstallError::UnsupportedPlatform { variant } =>
+                write!(
+                    f,
+                    "No {} build is built in for {}-{} (only for {}); provide your own URL and call download_ffmpeg_package then install_from_archive directly.",
+                    variant,
+                    std::env::consts::ARCH,
+                    std::env::consts::OS,
+                    supported_targets(*variant).join(", ")
+                ),
+            InstallError::UnreadableManifest { url } =>
+                write!(f, "Failed to parse the version number from {}", url),
+            InstallError::DownloadFailed { status } =>
+                write!(f, "Failed to download ffmpeg (curl {})", status),
+            InstallError::HttpStatus { code, first_bytes_preview } =>
+                write!(f, "Failed to download ffmpeg (HTTP {}): {:?}", code, first_bytes_preview),
+            InstallError::NotAnArchive { content_type_guess, first_bytes_preview } =>
+                write!(
+                    f,
+                    "The download is not an archive ({}), it starts with {:?}",
+                    content_type_guess,
+                    first_bytes_preview
+                ),
+            InstallError::TruncatedArchive { extension } =>
+                write!(f, "The {} archive is truncated", extension),
+            InstallError::ChecksumMismatch { expected, actual } =>
+                write!(f, "Archive checksum mismatch: expected {}, got {}", expected, actual),
+            InstallError::UnsupportedArchive { extension } =>
+                write!(f, "Unsupported archive format: {:?}", extension),
+            InstallError::UnpackFailed { status } =>
+                write!(f, "Failed to unpack ffmpeg ({})", status),
+            InstallError::BinaryNotFound { path } => write!(f, "Binary not found: {:?}", path),
+            InstallError::ValidationFailed { path } =>
+                write!(
+                    f,
+                    "FFmpeg failed to install, please install manually ({:?} doesn't run)",
+                    path
+                ),
+        }
+    }
+}
+
+impl std::error::Error for InstallError {}
+
+/// Environment variable with the URL of a native arm64 build for Windows on
+/// ARM, used by `auto_download` instead of the x86_64 build. The x86_64 build
+/// needs Windows 11, which emulates x64 (Windows 10 on ARM only emulates
+/// x86).
+pub const WINDOWS_ARM64_URL_VAR: &str = "FFMPEG_SIDECAR_WINDOWS_ARM64_URL";
+
+/// The target triples with a built-in download URL for `variant`. Windows on
+/// ARM gets the x86_64 build, which runs under emulation unless
+/// `WINDOWS_ARM64_URL_VAR` is set.
+///
+/// ```rust
+/// use ffmpeg_sidecar::download::{supported_targets, BuildVariant};
+/// assert!(supported_targets(BuildVariant::Essentials).contains(&"aarch64-apple-darwin"));
+/// assert!(!supported_targets(BuildVariant::Full).contains(&"aarch64-apple-darwin"));
+/// ```
+pub fn supported_targets(variant: BuildVariant) -> &'static [&'static str] {
+    match variant {
+        BuildVariant::Essentials =>
+            &[
+                "x86_64-pc-windows-msvc",
+                "aarch64-pc-windows-msvc",
+                "x86_64-unknown-linux-gnu",
+                "x86_64-apple-darwin",
+                "aarch64-apple-darwin",
+            ],
+        BuildVariant::Full | BuildVariant::LgplShared =>
+            &["x86_64-pc-windows-msvc", "aarch64-pc-windows-msvc", "x86_64-unknown-linux-gnu"],
+    }
+}
+
 /// URL of a manifest file containing the latest published build of FFmpeg. The
 /// correct URL for the target platform is baked in at compile time.
+///
+/// The version of the x86_64 build applies to the arm64 Macs too, and to
+/// Windows on ARM, which runs the x86_64 build.
 pub fn ffmpeg_manifest_url() -> anyhow::Result<&'static str> {
-    if cfg!(not(target_arch = "x86_64")) {
-        anyhow::bail!("Downloads must be manually provided for non-x86_64 architectures");
-    }
-
-    if cfg!(target_os = "windows") {
+    let arm64_or_x86_64 = cfg!(any(target_arch = "x86_64", target_arch = "aarch64"));
+    if cfg!(target_os = "windows") && arm64_or_x86_64 {
         Ok("https://www.gyan.dev/ffmpeg/builds/release-version")
-    } else if cfg!(target_os = "macos") {
+    } else if cfg!(target_os = "macos") && arm64_or_x86_64 {
         Ok("https://evermeet.cx/ffmpeg/info/ffmpeg/release")
-    } else if cfg!(target_os = "linux") {
+    } else if cfg!(all(target_os = "linux", target_arch = "x86_64")) {
         Ok("https://johnvansickle.com/ffmpeg/release-readme.txt")
     } else {
-        anyhow::bail!("Unsupported platform")
+        Err(InstallError::UnsupportedPlatform { variant: BuildVariant::Essentials }.into())
     }
 }
 
 /// URL for the latest published FFmpeg release. The correct URL for the target
 /// platform is baked in at compile time.
 pub fn ffmpeg_download_url() -> anyhow::Result<&'static str> {
-    if cfg!(all(target_os = "windows", target_arch = "x86_64")) {
-        Ok("https://cap-ffmpeg.s3.amazonaws.com/ffmpeg-7.0.1-essentials_build.zip")
+    ffmpeg_download_url_for(BuildVariant::Essentials)
+}
+
+/// Same as `resolve_download_url`.
+pub fn ffmpeg_download_url_for(variant: BuildVariant) -> anyhow::Result<&'static str> {
+    resolve_download_url(variant)
+}
+
+/// URL for the latest published FFmpeg release of the given variant, or an
+/// `InstallError::UnsupportedPlatform` if that variant isn't published for
+/// the target platform. The first stage of `auto_download`.
+pub fn resolve_download_url(variant: BuildVariant) -> anyhow::Result<&'static str> {
+    let url = match variant {
+        BuildVariant::Essentials => essentials_download_url(),
+        BuildVariant::Full => {
+            if cfg!(all(target_os = "windows", any(target_arch = "x86_64", target_arch = "aarch64"))) {
+                Some("https://www.gyan.dev/ffmpeg/builds/ffmpeg-release-full.zip")
+            } else if cfg!(all(target_os = "linux", target_arch = "x86_64")) {
+                Some(
+                    "https://github.com/BtbN/FFmpeg-Builds/releases/download/latest/ffmpeg-n7.0-latest-linux64-gpl-7.0.tar.xz"
+                )
+            } else {
+                None
+            }
+        }
+        BuildVariant::LgplShared => {
+            if cfg!(all(target_os = "windows", any(target_arch = "x86_64", target_arch = "aarch64"))) {
+                Some(
+                    "https://github.com/BtbN/FFmpeg-Builds/releases/download/latest/ffmpeg-n7.0-latest-win64-lgpl-shared-7.0.zip"
+                )
+            } else if cfg!(all(target_os = "linux", target_arch = "x86_64")) {
+                Some(
+                    "https://github.com/BtbN/FFmpeg-Builds/releases/download/latest/ffmpeg-n7.0-latest-linux64-lgpl-shared-7.0.tar.xz"
+                )
+            } else {
+                None
+            }
+        }
+    };
+    url.ok_or_else(|| InstallError::UnsupportedPlatform { variant }.into())
+}
+
+fn essentials_download_url() -> Option<&'static str> {
+    if cfg!(all(target_os = "windows", any(target_arch = "x86_64", target_arch = "aarch64"))) {
+        Some("https://cap-ffmpeg.s3.amazonaws.com/ffmpeg-7.0.1-essentials_build.zip")
     } else if cfg!(all(target_os = "linux", target_arch = "x86_64")) {
-        Ok("https://cap-ffmpeg.s3.amazonaws.com/ffmpeg-release-amd64-static.tar.xz")
+        Some("https://cap-ffmpeg.s3.amazonaws.com/ffmpeg-release-amd64-static.tar.xz")
     } else if cfg!(all(target_os = "macos", target_arch = "x86_64")) {
-        Ok("https://cap-ffmpeg.s3.amazonaws.com/ffmpeg-7.0.1.zip")
+        Some("https://cap-ffmpeg.s3.amazonaws.com/ffmpeg-7.0.1.zip")
     } else if cfg!(all(target_os = "macos", target_arch = "aarch64")) {
-        Ok("https://cap-ffmpeg.s3.amazonaws.com/ffmpegarm.zip") // Mac M1
+        Some("https://cap-ffmpeg.s3.amazonaws.com/ffmpegarm.zip") // Mac M1
     } else {
-        anyhow::bail!(
-            "Unsupported platform; you can provide your own URL instead and call download_ffmpeg_package directly."
-        )
+        None
     }
 }
 
@@ -52,24 +305,338 @@ pub fn ffmpeg_download_url() -> anyhow::Result<&'static str> {
 /// The binaries will be placed in the same directory as the Rust executable.
 ///
 /// If FFmpeg is already installed, the method exits early without downloading
-/// anything.
+/// anything, unless it was installed by a previous `auto_download` from
+/// another URL than the one now built in for its variant, e.g. after an update
+/// of this crate moved to a newer release. See `installed_info`.
+///
+/// This runs `resolve_download_url`, `download_ffmpeg_package` and
+/// `install_from_archive` in turn, which can also be called separately, e.g.
+/// to download the archive with a custom installer and only install it on
+/// first run.
 pub fn auto_download() -> anyhow::Result<()> {
-    if ffmpeg_is_installed() {
+    if ffmpeg_is_installed() && !installed_info().is_some_and(|info| is_outdated(&info)) {
         return Ok(());
     }
 
-    let download_url = ffmpeg_download_url()?;
+    install_variant(BuildVariant::Essentials)
+}
+
+/// Like `auto_download`, but installs a specific `BuildVariant`. If a
+/// different variant (or an FFmpeg without a recorded variant) is installed,
+/// it's replaced.
+///
+/// ```rust,no_run
+/// use ffmpeg_sidecar::download::{auto_download_variant, BuildVariant};
+///
+/// auto_download_variant(BuildVariant::LgplShared).unwrap();
+/// ```
+pub fn auto_download_variant(variant: BuildVariant) -> anyhow::Result<()> {
+    let up_to_date = match installed_info() {
+        Some(info) => info.variant == variant && !is_outdated(&info),
+        None => installed_variant()? == Some(variant),
+    };
+    if ffmpeg_is_installed() && up_to_date {
+        return Ok(());
+    }
+
+    install_variant(variant)
+}
+
+/// What the last `auto_download` installed into the sidecar directory, from
+/// where and when, or `None` if FFmpeg was installed another way (manually,
+/// or from the system package manager), or the record is corrupt.
+///
+/// ```rust,no_run
+/// use ffmpeg_sidecar::download::installed_info;
+///
+/// match installed_info() {
+///     Some(info) => println!("FFmpeg {} ({}) from {}", info.version, info.variant, info.source_url),
+///     None => println!("FFmpeg wasn't installed by auto_download"),
+/// }
+/// ```
+pub fn installed_info() -> Option<InstallInfo> {
+    let dir = sidecar_dir().ok()?;
+    // Left behind if the binaries were removed since
+    if !dir.join(binary_name("ffmpeg")).is_file() {
+        return None;
+    }
+    InstallInfo::read(&dir)
+}
+
+/// Whether the FFmpeg installed by `auto_download` came from another URL
+/// than the one now built in (or configured) for its variant.
+fn is_outdated(info: &InstallInfo) -> bool {
+    // Installed by `update`, which keeps the evermeet.cx builds up to date
+    if info.source_url.starts_with(EVERMEET_RELEASE_URL) {
+        return false;
+    }
+    download_url_for(info.variant).is_ok_and(|url| url != info.source_url)
+}
+
+/// The variant recorded by the last `auto_download` into the sidecar
+/// directory, if any.
+pub fn installed_variant() -> anyhow::Result<Option<BuildVariant>> {
+    if let Some(info) = installed_info() {
+        return Ok(Some(info.variant));
+    }
+    match read_to_string(sidecar_dir()?.join(VARIANT_FILENAME)) {
+        Ok(contents) => contents.parse().map(Some),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn install_variant(variant: BuildVariant) -> anyhow::Result<()> {
+    let download_url = download_url_for(variant)?;
+    if cfg!(all(target_os = "windows", target_arch = "aarch64")) && windows_arm64_url().is_none() {
+        println!(
+            "Downloading the x86_64 build of FFmpeg, which runs under emulation on Windows on ARM; set {} to the URL of a native build instead",
+            WINDOWS_ARM64_URL_VAR
+        );
+    }
     let destination = sidecar_dir()?;
-    let archive_path = download_ffmpeg_package(download_url, &destination)?;
-    unpack_ffmpeg(&archive_path, &destination)?;
+    let archive_path = download_ffmpeg_package(&download_url, &destination)?;
+    // The archive is removed once unpacked
+    let sha256 = sha256_file(&archive_path).unwrap_or_default();
+    let ffmpeg = install_from_archive(&archive_path, &destination)?;
+    write(destination.join(VARIANT_FILENAME), variant.as_str())?;
+    let version = ffmpeg_version_with_path(&ffmpeg).unwrap_or_default();
+    let mut info = InstallInfo::new(&download_url, &version, &sha256, variant);
+    info.ffprobe_version = binary_version(&destination.join(binary_name("ffprobe")));
+    info.write(&destination)?;
+    Ok(())
+}
+
+/// The URL `auto_download` installs `variant` from: the one built in, or
+/// the one set with `WINDOWS_ARM64_URL_VAR` on Windows on ARM.
+fn download_url_for(variant: BuildVariant) -> anyhow::Result<String> {
+    match windows_arm64_url() {
+        Some(url) => Ok(url),
+        None => Ok(resolve_download_url(variant)?.to_string()),
+    }
+}
 
-    if !ffmpeg_is_installed() {
-        anyhow::bail!("FFmpeg failed to install, please install manually.");
+/// The URL set with `WINDOWS_ARM64_URL_VAR`, on Windows on ARM only.
+fn windows_arm64_url() -> Option<String> {
+    if cfg!(not(all(target_os = "windows", target_arch = "aarch64"))) {
+        return None;
+    }
+    std::env::var(WINDOWS_ARM64_URL_VAR)
+        .ok()
+        .filter(|url| !url.is_empty())
+}
+
+/// Base URL of the per-binary archives published by evermeet.cx for x86_64
+/// Macs, e.g. `ffprobe-7.1.zip`.
+const EVERMEET_RELEASE_URL: &str = "https://evermeet.cx/ffmpeg/";
+
+/// Whether `update` replaced a binary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UpdateStatus {
+    /// A newer build was downloaded and installed.
+    Updated,
+    /// The installed binary is already the latest build, and was kept.
+    Unchanged,
+}
+
+/// What `update` changed, per binary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UpdateReport {
+    pub ffmpeg: UpdateStatus,
+    pub ffprobe: UpdateStatus,
+}
+
+/// Where the latest build of one binary is published.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BinarySource {
+    /// The archive containing the binary, possibly along with the other one.
+    pub url: String,
+    /// The version of the latest build, if the source publishes one. Without
+    /// it, the binary is only updated when the URL changes.
+    pub version: Option<String>,
+}
+
+/// The latest builds of ffmpeg and ffprobe, compared by `update_from` with
+/// the installed ones.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct UpdateSources {
+    pub variant: BuildVariant,
+    pub ffmpeg: BinarySource,
+    pub ffprobe: BinarySource,
+}
+
+/// The sources `update` installs `variant` from. On x86_64 Macs, these are
+/// the separate ffmpeg and ffprobe archives of evermeet.cx, with their
+/// versions read from its manifests. Elsewhere, both binaries come from the
+/// archive `auto_download` installs, with no version.
+pub fn update_sources(variant: BuildVariant) -> anyhow::Result<UpdateSources> {
+    if cfg!(all(target_os = "macos", target_arch = "x86_64")) && variant == BuildVariant::Essentials {
+        let source = |name: &str| -> anyhow::Result<BinarySource> {
+            let manifest_url = format!("https://evermeet.cx/ffmpeg/info/{}/release", name);
+            let version = parse_macos_version(&curl(&manifest_url)?).ok_or(
+                InstallError::UnreadableManifest { url: manifest_url }
+            )?;
+            Ok(BinarySource {
+                url: format!("{}{}-{}.zip", EVERMEET_RELEASE_URL, name, version),
+                version: Some(version),
+            })
+        };
+        return Ok(UpdateSources { variant, ffmpeg: source("ffmpeg")?, ffprobe: source("ffprobe")? });
+    }
+    let source = BinarySource { url: download_url_for(variant)?, version: None };
+    Ok(UpdateSources { variant, ffmpeg: source.clone(), ffprobe: source })
+}
+
+/// Like `auto_download`, but only downloading the binaries which changed,
+/// and reporting which ones were replaced. Updates the installed variant,
+/// or `BuildVariant::Essentials` if there's none. See `update_from`.
+///
+/// ```rust,no_run
+/// use ffmpeg_sidecar::download::{update, UpdateStatus};
+///
+/// let report = update().unwrap();
+/// if report.ffprobe == UpdateStatus::Updated {
+///     println!("ffprobe was updated");
+/// }
+/// ```
+pub fn update() -> anyhow::Result<UpdateReport> {
+    let variant = installed_variant()?.unwrap_or_default();
+    update_from(&update_sources(variant)?, &sidecar_dir()?)
+}
+
+/// Install the binaries of `sources` into `destination` which differ from
+/// the ones recorded in its `InstallInfo`, by URL or by version, or which are
+/// missing. A binary with its own archive is downloaded on its own;
+/// otherwise, the archive with both is downloaded, and only the binaries
+/// which changed are taken from it. Binaries installed another way, with no
+/// record, are replaced.
+pub fn update_from(sources: &UpdateSources, destination: &Path) -> anyhow::Result<UpdateReport> {
+    let previous = InstallInfo::read(destination);
+    let needs_update = |name: &str, source: &BinarySource, installed: Option<(&str, &str)>| {
+        let Some((url, version)) = installed else {
+            return true;
+        };
+        !destination.join(binary_name(name)).is_file() ||
+            url != source.url ||
+            source.version.as_deref().is_some_and(|latest| latest != version)
+    };
+    let ffmpeg = needs_update(
+        "ffmpeg",
+        &sources.ffmpeg,
+        previous.as_ref().map(|info| (info.source_url.as_str(), info.version.as_str()))
+    );
+    let ffprobe = needs_update(
+        "ffprobe",
+        &sources.ffprobe,
+        previous.as_ref().map(|info| (info.ffprobe_source_url.as_str(), info.ffprobe_version.as_str()))
+    );
+
+    // The binaries to take from each archive, downloaded once
+    let mut downloads: Vec<(&str, Vec<&str>)> = Vec::new();
+    for (name, source, needed) in [("ffmpeg", &sources.ffmpeg, ffmpeg), ("ffprobe", &sources.ffprobe, ffprobe)] {
+        if !needed {
+            continue;
+        }
+        match downloads.iter_mut().find(|(url, _)| *url == source.url) {
+            Some((_, names)) => names.push(name),
+            None => downloads.push((&source.url, vec![name])),
+        }
+    }
+    let mut sha256 = previous.as_ref().map(|info| info.sha256.clone()).unwrap_or_default();
+    for (url, names) in &downloads {
+        let archive = download_ffmpeg_package(url, destination)?;
+        if names.contains(&"ffmpeg") {
+            sha256 = sha256_file(&archive).unwrap_or_default();
+        }
+        unpack_binaries(&archive, destination, names)?;
+    }
+    let ffmpeg_path = validate_install(destination)?;
+
+    if !downloads.is_empty() {
+        let mut info = InstallInfo::new("", "", &sha256, sources.variant);
+        // A binary which was kept has a record, see `needs_update`
+        (info.source_url, info.version) = match (&previous, ffmpeg) {
+            (Some(previous), false) => (previous.source_url.clone(), previous.version.clone()),
+            _ => (sources.ffmpeg.url.clone(), ffmpeg_version_with_path(&ffmpeg_path).unwrap_or_default()),
+        };
+        (info.ffprobe_source_url, info.ffprobe_version) = match (&previous, ffprobe) {
+            (Some(previous), false) =>
+                (previous.ffprobe_source_url.clone(), previous.ffprobe_version.clone()),
+            _ => (sources.ffprobe.url.clone(), binary_version(&destination.join(binary_name("ffprobe")))),
+        };
+        write(destination.join(VARIANT_FILENAME), sources.variant.as_str())?;
+        info.write(destination)?;
+    }
+
+    let status = |updated: bool| if updated { UpdateStatus::Updated } else { UpdateStatus::Unchanged };
+    Ok(UpdateReport { ffmpeg: status(ffmpeg), ffprobe: status(ffprobe) })
+}
+
+/// The version number printed by `<binary> -version`, e.g. `7.0.1` from
+/// `ffprobe version 7.0.1 Copyright ...`, or an empty string.
+fn binary_version(path: &Path) -> String {
+    spawner::output(Command::new(path).arg("-version").stderr(Stdio::null()))
+        .ok()
+        .and_then(|output| {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            let version = stdout.lines().next()?.split_whitespace().nth(2)?.to_string();
+            Some(version)
+        })
+        .unwrap_or_default()
+}
+
+/// Like `auto_download`, but also requires an `ffplay` binary to be installed
+/// alongside FFmpeg. Only available with the `ffplay` feature.
+///
+/// The Windows (gyan.dev) and MacOS (evermeet.cx) builds include FFplay, but
+/// the Linux static build (johnvansickle.com) does not, since it has no
+/// SDL dependency. On Linux this will return an error after installing FFmpeg,
+/// and FFplay should be installed from the system package manager instead
+/// (e.g. `apt install ffmpeg`), where `ffplay_path()` will find it in the PATH.
+#[cfg(feature = "ffplay")]
+pub fn auto_download_with_ffplay() -> anyhow::Result<()> {
+    use crate::ffplay::ffplay_is_installed;
+
+    if ffmpeg_is_installed() && ffplay_is_installed() {
+        return Ok(());
+    }
+
+    install_variant(BuildVariant::Essentials)?;
+
+    if !ffplay_is_installed() {
+        anyhow::bail!(
+            "The downloaded FFmpeg build does not include FFplay (the Linux static build never does); please install FFplay manually, e.g. from your system package manager."
+        );
     }
 
     Ok(())
 }
 
+/// File name of a binary on the target platform, e.g. `ffmpeg.exe`.
+fn binary_name(name: &str) -> String {
+    if cfg!(target_os = "windows") { format!("{}.exe", name) } else { name.to_string() }
+}
+
+/// Search `dir` and its subdirectories for a file named `file_name`.
+fn find_file(dir: &Path, file_name: &str) -> Option<PathBuf> {
+    let mut subdirs = Vec::new();
+    for entry in read_dir(dir).ok()?.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            subdirs.push(path);
+        } else if path.file_name().is_some_and(|name| name == file_name) {
+            return Some(path);
+        }
+    }
+    subdirs.iter().find_map(|subdir| find_file(subdir, file_name))
+}
+
+/// Whether `path` is a `.dll`, `.dylib`, or (possibly versioned) `.so` file.
+fn is_shared_library(path: &Path) -> bool {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    name.ends_with(".dll") || name.ends_with(".dylib") || name.ends_with(".so") || name.contains(".so.")
+}
+
 /// Parse the the MacOS version number from a JSON string manifest file.
 ///
 /// Example input: https://evermeet.cx/ffmpeg/info/ffmpeg/release
@@ -111,13 +678,10 @@ pub fn parse_linux_version(version: &str) -> Option<String> {
 
 /// Invoke cURL on the command line to download a file, returning it as a string.
 pub fn curl(url: &str) -> anyhow::Result<String> {
-    let mut child = Command::new("curl")
-        .args(["-L", url])
-        .stderr(Stdio::null())
-        .stdout(Stdio::piped())
-        .spawn()?;
+    let stdio = StdioConfig { stdin: StdioPolicy::Inherit, stderr: StdioPolicy::Null, ..StdioConfig::default() };
+    let mut child = spawner::spawn(Command::new("curl").args(["-L", url]), stdio)?;
 
-    let stdout = child.stdout.take().context("Failed to get stdout")?;
+    let stdout = child.take_stdout().context("Failed to get stdout")?;
 
     let mut string = String::new();
     std::io::BufReader::new(stdout).read_to_string(&mut string)?;
@@ -126,27 +690,57 @@ pub fn curl(url: &str) -> anyhow::Result<String> {
 
 /// Invoke cURL on the command line to download a file, writing to a file.
 pub fn curl_to_file(url: &str, destination: &str) -> anyhow::Result<ExitStatus> {
-    Command::new("curl").args(["-L", url]).args(["-o", destination]).status().map_err(Into::into)
+    spawner::status(Command::new("curl").args(["-L", url]).args(["-o", destination])).map_err(Into::into)
+}
+
+/// Same as `fetch_manifest_version`.
+pub fn check_latest_version() -> anyhow::Result<String> {
+    fetch_manifest_version()
 }
 
 /// Makes an HTTP request to obtain the latest version available online,
 /// automatically choosing the correct URL for the current platform.
-pub fn check_latest_version() -> anyhow::Result<String> {
-    let string = curl(ffmpeg_manifest_url()?)?;
+pub fn fetch_manifest_version() -> anyhow::Result<String> {
+    let url = ffmpeg_manifest_url()?;
+    let string = curl(url)?;
 
-    if cfg!(target_os = "windows") {
-        Ok(string)
+    let version = if cfg!(target_os = "windows") {
+        Some(string.trim().to_string()).filter(|version| !version.is_empty())
     } else if cfg!(target_os = "macos") {
-        parse_macos_version(&string).context("failed to parse version number (macos variant)")
-    } else if cfg!(target_os = "linux") {
-        parse_linux_version(&string).context("failed to parse version number (linux variant)")
+        parse_macos_version(&string)
     } else {
-        Err(anyhow::Error::msg("Unsupported platform"))
-    }
+        parse_linux_version(&string)
+    };
+    version.ok_or_else(|| InstallError::UnreadableManifest { url: url.to_string() }.into())
+}
+
+/// Like `curl_to_file`, also returning the HTTP status code and
+/// `Content-Type` of the response, when there's one.
+fn curl_to_file_with_response(
+    url: &str,
+    destination: &str
+) -> anyhow::Result<(ExitStatus, Option<u16>, Option<String>)> {
+    let output = spawner::output(
+        Command::new("curl")
+            .args(["-L", url])
+            .args(["-o", destination])
+            .args(["-w", "%{http_code} %{content_type}"])
+            .stderr(Stdio::inherit())
+    )?;
+    let response = String::from_utf8_lossy(&output.stdout);
+    let (code, content_type) = response.split_once(' ').unwrap_or((&response, ""));
+    // `000` without an HTTP response, e.g. for a `file://` URL
+    let code = code.parse().ok().filter(|&code| code != 0);
+    let content_type = Some(content_type.trim().to_string()).filter(|content_type| !content_type.is_empty());
+    Ok((output.status, code, content_type))
 }
 
 /// Invoke `curl` to download an archive (ZIP on windows, TAR on linux and mac)
 /// from the latest published release online.
+///
+/// The download is removed if the server answers with an HTTP error
+/// (`InstallError::HttpStatus`), or if it isn't an archive of the format of
+/// its extension (see `check_archive`).
 pub fn download_ffmpeg_package(url: &str, download_dir: &Path) -> anyhow::Result<PathBuf> {
     let filename = Path::new(url).file_name().context("Failed to get filename")?;
 
@@ -154,22 +748,229 @@ pub fn download_ffmpeg_package(url: &str, download_dir: &Path) -> anyhow::Result
 
     let archive_filename = archive_path.to_str().context("invalid download path")?;
 
-    let exit_status = curl_to_file(url, archive_filename)?;
+    let (exit_status, code, content_type) = curl_to_file_with_response(url, archive_filename)?;
 
     if !exit_status.success() {
-        anyhow::bail!("Failed to download ffmpeg");
+        return Err(InstallError::DownloadFailed { status: exit_status }.into());
+    }
+
+    let checked = match code {
+        Some(code) if !(200..300).contains(&code) => {
+            let (head, _) = read_archive_ends(&archive_path).unwrap_or_default();
+            Err(InstallError::HttpStatus { code, first_bytes_preview: bytes_preview(&head) }.into())
+        }
+        _ => check_archive_with_content_type(&archive_path, content_type.as_deref()),
+    };
+    if checked.is_err() {
+        remove_file(&archive_path).ok();
+    }
+    checked.map(|()| archive_path)
+}
+
+/// How many bytes of a file which isn't an archive are shown in errors.
+const PREVIEW_LEN: usize = 64;
+
+/// The end of a ZIP file lies within its last bytes: the 22 bytes of the end
+/// of central directory record, and a comment of up to 65535 bytes.
+const ZIP_TAIL_LEN: u64 = 22 + 65535;
+
+/// Check that the file at `path` looks like an archive of the format of its
+/// extension before unpacking it, from the magic bytes it starts with, and
+/// for ZIP and XZ files, the ones it ends with. Returns an
+/// `InstallError::NotAnArchive` with a preview of the first bytes, or an
+/// `InstallError::TruncatedArchive`. Files with other extensions aren't
+/// checked.
+///
+/// ```rust,no_run
+/// use ffmpeg_sidecar::download::check_archive;
+/// use std::path::Path;
+///
+/// // An HTML error page saved by a browser
+/// let error = check_archive(Path::new("ffmpeg-release-amd64-static.tar.xz")).unwrap_err();
+/// eprintln!("{error}");
+/// ```
+pub fn check_archive(path: &Path) -> anyhow::Result<()> {
+    check_archive_with_content_type(path, None)
+}
+
+fn check_archive_with_content_type(path: &Path, content_type: Option<&str>) -> anyhow::Result<()> {
+    let extension = path.extension().and_then(std::ffi::OsStr::to_str).unwrap_or("");
+    let (magic, offset): (&[u8], usize) = match extension {
+        "zip" => (b"PK\x03\x04", 0),
+        "xz" => (b"\xfd7zXZ\x00", 0),
+        "gz" | "tgz" => (b"\x1f\x8b", 0),
+        "tar" => (b"ustar", 257),
+        _ => {
+            return Ok(());
+        }
+    };
+    let (head, tail) = read_archive_ends(path)?;
+    if head.get(offset..offset + magic.len()) != Some(magic) {
+        let content_type_guess = content_type.map_or_else(|| guess_content_type(&head), str::to_string);
+        let first_bytes_preview = bytes_preview(&head);
+        return Err(InstallError::NotAnArchive { content_type_guess, first_bytes_preview }.into());
+    }
+    // The end of central directory record of a ZIP file, or the footer
+    // magic of an XZ stream
+    let complete = match extension {
+        "zip" => tail.windows(4).any(|window| window == b"PK\x05\x06"),
+        "xz" => tail.ends_with(b"YZ"),
+        _ => true,
+    };
+    if !complete {
+        return Err(InstallError::TruncatedArchive { extension: extension.to_string() }.into());
+    }
+    Ok(())
+}
+
+/// The first 512 bytes of a file, enough for the TAR magic, and its last
+/// `ZIP_TAIL_LEN` bytes.
+fn read_archive_ends(path: &Path) -> anyhow::Result<(Vec<u8>, Vec<u8>)> {
+    let mut file = File::open(path)?;
+    let mut head = Vec::new();
+    file.by_ref().take(512).read_to_end(&mut head)?;
+    let len = file.metadata()?.len();
+    file.seek(SeekFrom::Start(len.saturating_sub(ZIP_TAIL_LEN)))?;
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail)?;
+    Ok((head, tail))
+}
+
+/// What a file which isn't an archive likely is, from its first bytes.
+fn guess_content_type(head: &[u8]) -> String {
+    let text = String::from_utf8_lossy(head).trim_start().to_ascii_lowercase();
+    let content_type = if text.starts_with("<!doctype html") || text.starts_with("<html") {
+        "text/html"
+    } else if text.starts_with("<?xml") || text.starts_with('<') {
+        "application/xml"
+    } else if text.starts_with('{') {
+        "application/json"
+    } else if head.is_empty() {
+        "empty"
+    } else if std::str::from_utf8(head).is_ok() {
+        "text/plain"
+    } else {
+        "application/octet-stream"
+    };
+    content_type.to_string()
+}
+
+/// The first `PREVIEW_LEN` bytes, with line breaks as spaces, and other
+/// unprintable bytes as `.`.
+fn bytes_preview(head: &[u8]) -> String {
+    head.iter()
+        .take(PREVIEW_LEN)
+        .map(|&byte| {
+            match byte {
+                b'\n' | b'\r' | b'\t' => ' ',
+                byte if byte.is_ascii_graphic() || byte == b' ' => byte as char,
+                _ => '.',
+            }
+        })
+        .collect()
+}
+
+/// Compute the SHA-256 checksum of a file as lowercase hex, with the tool
+/// shipped with each platform.
+pub fn sha256_file(path: &Path) -> anyhow::Result<String> {
+    let mut command = if cfg!(target_os = "windows") {
+        let mut command = Command::new("certutil");
+        command.arg("-hashfile").arg(path).arg("SHA256");
+        command
+    } else if cfg!(target_os = "macos") {
+        let mut command = Command::new("shasum");
+        command.args(["-a", "256"]).arg(path);
+        command
+    } else {
+        let mut command = Command::new("sha256sum");
+        command.arg(path);
+        command
+    };
+    let output = spawner::output(command.stderr(Stdio::null()))?;
+    if !output.status.success() {
+        anyhow::bail!("Failed to compute the checksum of {:?}", path);
+    }
+
+    // `certutil` prints the hash on its own line (with spaces in older
+    // versions), the others before the file name
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout
+        .lines()
+        .map(|line| line.split_whitespace().next().unwrap_or_default())
+        .chain(stdout.lines().map(str::trim))
+        .map(|hash| hash.replace(' ', "").to_ascii_lowercase())
+        .find(|hash| hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit()))
+        .with_context(|| format!("Failed to parse the checksum of {:?}", path))
+}
+
+/// Check that the archive at `path` has the SHA-256 `checksum` (hex, in any
+/// case), or return an `InstallError::ChecksumMismatch`. The checksum may be
+/// followed by a file name, as in the `.sha256` files published with builds.
+///
+/// ```rust,no_run
+/// use ffmpeg_sidecar::download::verify_archive;
+/// use std::path::Path;
+///
+/// // Published next to the archive
+/// let checksum = std::fs::read_to_string("ffmpeg-release-amd64-static.tar.xz.sha256").unwrap();
+/// verify_archive(Path::new("ffmpeg-release-amd64-static.tar.xz"), &checksum).unwrap();
+/// ```
+pub fn verify_archive(path: &Path, checksum: &str) -> anyhow::Result<()> {
+    let actual = sha256_file(path)?;
+    let expected = checksum.split_whitespace().next().unwrap_or_default().to_ascii_lowercase();
+    if actual != expected {
+        return Err(InstallError::ChecksumMismatch { expected, actual }.into());
     }
+    Ok(())
+}
 
-    Ok(archive_path)
+/// Unpack an archive downloaded with `download_ffmpeg_package`, or by any
+/// other means, into `destination` with `unpack_ffmpeg`, then check the
+/// result with `validate_install`. Returns the path of the ffmpeg binary.
+///
+/// Like the download, the archive is removed once unpacked.
+///
+/// ```rust,no_run
+/// use ffmpeg_sidecar::{download::install_from_archive, paths::sidecar_dir};
+/// use std::path::Path;
+///
+/// // Downloaded by the installer
+/// let archive = Path::new("ffmpeg-release-amd64-static.tar.xz");
+/// let ffmpeg = install_from_archive(archive, &sidecar_dir().unwrap()).unwrap();
+/// ```
+pub fn install_from_archive(archive: &Path, destination: &Path) -> anyhow::Result<PathBuf> {
+    unpack_ffmpeg(archive, destination)?;
+    validate_install(destination)
+}
+
+/// Check that `destination` contains ffmpeg and ffprobe, and that ffmpeg
+/// runs. Returns the path of the ffmpeg binary.
+///
+/// Ffmpeg is always run, even if a binary at the same path was verified
+/// before, since it may have just been replaced.
+pub fn validate_install(destination: &Path) -> anyhow::Result<PathBuf> {
+    let ffmpeg = destination.join(binary_name("ffmpeg"));
+    let ffprobe = destination.join(binary_name("ffprobe"));
+    for path in [&ffmpeg, &ffprobe] {
+        if !path.is_file() {
+            return Err(InstallError::BinaryNotFound { path: path.clone() }.into());
+        }
+    }
+    if ffmpeg_version_with_path(&ffmpeg).is_err() {
+        return Err(InstallError::ValidationFailed { path: ffmpeg }.into());
+    }
+    Ok(ffmpeg)
 }
 
 /// After downloading, unpacks the archive to a folder, moves the binaries to
 /// their final location, and deletes the archive and temporary folder.
-// After downloading, unpacks the archive to a folder, moves the binaries to
-// their final location, and deletes the archive and temporary folder.
-// ... existing code ...
+pub fn unpack_ffmpeg(from_archive: &Path, binary_folder: &Path) -> anyhow::Result<()> {
+    unpack_binaries(from_archive, binary_folder, &["ffmpeg", "ffprobe"])
+}
 
-pub fn unpack_ffmpeg(from_archive: &PathBuf, binary_folder: &Path) -> anyhow::Result<()> {
+/// Like `unpack_ffmpeg`, but only moving the binaries in `names` (along with
+/// the shared libraries of the build), e.g. ffprobe alone.
+fn unpack_binaries(from_archive: &Path, binary_folder: &Path, names: &[&str]) -> anyhow::Result<()> {
     let temp_dirname = UNPACK_DIRNAME;
     let temp_folder = binary_folder.join(temp_dirname);
 
@@ -180,6 +981,7 @@ pub fn unpack_ffmpeg(from_archive: &PathBuf, binary_folder: &Path) -> anyhow::Re
 
     let extension = from_archive.extension().and_then(std::ffi::OsStr::to_str).unwrap_or("");
     println!("Extension: {:?}", extension);
+    check_archive(from_archive)?;
 
     // Determine the command based on the file extension and OS
     let (mut unpack_command, unpack_args) = if cfg!(target_os = "windows") {
@@ -197,7 +999,7 @@ pub fn unpack_ffmpeg(from_archive: &PathBuf, binary_folder: &Path) -> anyhow::Re
                 ],
             )
         } else {
-            anyhow::bail!("Unsupported archive format for Windows");
+            return Err(InstallError::UnsupportedArchive { extension: extension.to_string() }.into());
         }
     } else {
         match extension {
@@ -209,7 +1011,11 @@ pub fn unpack_ffmpeg(from_archive: &PathBuf, binary_folder: &Path) -> anyhow::Re
                 Command::new("tar"),
                 vec!["-xf", from_archive.to_str().unwrap(), "-C", temp_folder.to_str().unwrap()],
             ),
-            _ => anyhow::bail!("Unsupported archive format"),
+            _ => {
+                return Err(
+                    InstallError::UnsupportedArchive { extension: extension.to_string() }.into()
+                );
+            }
         }
     };
 
@@ -217,9 +1023,9 @@ pub fn unpack_ffmpeg(from_archive: &PathBuf, binary_folder: &Path) -> anyhow::Re
     println!("Unpacking args: {:?}", unpack_args);
 
     // Execute the command
-    let status = unpack_command.args(unpack_args).status()?;
+    let status = spawner::status(unpack_command.args(unpack_args))?;
     if !status.success() {
-        anyhow::bail!("Failed to unpack ffmpeg ({})", extension);
+        return Err(InstallError::UnpackFailed { status }.into());
     }
 
     // List contents of the temp folder for debugging
@@ -237,29 +1043,72 @@ pub fn unpack_ffmpeg(from_archive: &PathBuf, binary_folder: &Path) -> anyhow::Re
                 .with_context(|| format!("Path {} does not have a file_name", path.to_string_lossy()))?,
         );
         if path.exists() {
+            // Replace a previously installed binary (`rename` fails on Windows otherwise)
+            if file_name.exists() {
+                remove_file(&file_name)?;
+            }
             rename(path, &file_name)?;
+            // A binary verified at this path before isn't the one there now
+            forget_verified_ffmpeg(&file_name);
+            forget_verified_ffprobe(&file_name);
         } else {
             println!("Expected binary not found: {:?}", path);
-            return Err(anyhow::anyhow!("Binary not found: {:?}", path));
+            return Err(anyhow::Error::new(InstallError::BinaryNotFound { path: path.to_path_buf() }));
         }
         Ok(())
     };
 
-    // Adjust paths for Windows and Unix-like systems
-    let (ffmpeg_bin, ffprobe_bin) = if cfg!(target_os = "windows") {
-        (
-            Path::new("ffmpeg-7.0.1-essentials_build").join("bin").join("ffmpeg.exe"),
-            Path::new("ffmpeg-7.0.1-essentials_build").join("bin").join("ffprobe.exe")
-        )
-    } else {
-        (PathBuf::from("ffmpeg"), PathBuf::from("ffprobe"))
-    };
+    // Each build nests the binaries differently (e.g.
+    // `ffmpeg-7.0.1-essentials_build/bin/ffmpeg.exe`), so search for them
+    let binary_paths = names
+        .iter()
+        .map(|name| {
+            find_file(&temp_folder, &binary_name(name)).unwrap_or_else(||
+                temp_folder.join(binary_name(name))
+            )
+        })
+        .collect::<Vec<_>>();
+
+    // Shared builds keep their libraries in the same `bin` folder (Windows)
+    // or in a sibling `lib` folder
+    let bin_folder = binary_paths
+        .first()
+        .and_then(|path| path.parent())
+        .map(Path::to_path_buf);
+    let lib_folder = bin_folder
+        .as_ref()
+        .and_then(|bin| bin.parent())
+        .map(|parent| parent.join("lib"));
+    let mut libraries = Vec::new();
+    for folder in [bin_folder, lib_folder].into_iter().flatten() {
+        if let Ok(entries) = read_dir(&folder) {
+            for entry in entries {
+                let path = entry?.path();
+                if path.is_file() && is_shared_library(&path) {
+                    libraries.push(path);
+                }
+            }
+        }
+    }
 
-    let ffmpeg_path = temp_folder.join(ffmpeg_bin);
-    let ffprobe_path = temp_folder.join(ffprobe_bin);
+    for path in &binary_paths {
+        move_bin(path)?;
+    }
+    for library in &libraries {
+        move_bin(library)?;
+    }
 
-    move_bin(&ffmpeg_path)?;
-    move_bin(&ffprobe_path)?;
+    // FFplay is optional, since not every build ships it (see `auto_download_with_ffplay`)
+    #[cfg(feature = "ffplay")]
+    if names.contains(&"ffmpeg") {
+        let ffplay_path = find_file(&temp_folder, &binary_name("ffplay"))
+            .unwrap_or_else(|| temp_folder.join(binary_name("ffplay")));
+        if ffplay_path.exists() {
+            move_bin(&ffplay_path)?;
+        } else {
+            println!("FFplay not included in this archive: {:?}", ffplay_path);
+        }
+    }
 
     // Delete archive and unpacked files
     if temp_folder.exists() && temp_folder.is_dir() {
@@ -277,5 +1126,3 @@ pub fn unpack_ffmpeg(from_archive: &PathBuf, binary_folder: &Path) -> anyhow::Re
     }
     Ok(())
 }
-
-// ... existing code ...
\ No newline at end of file
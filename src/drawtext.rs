@@ -0,0 +1,211 @@
+//! Drawing text on top of the video with the `drawtext` filter, with a font
+//! file found at runtime rather than through fontconfig, which is missing on
+//! Windows and in minimal Linux containers. See
+//! [`FfmpegCommand::drawtext`](crate::command::FfmpegCommand::drawtext).
+
+use std::path::PathBuf;
+
+use crate::{
+  overlay::{Corner, OverlayPosition},
+  sanitize::escape_filter_value,
+};
+
+/// Fonts tried in order by [`default_font_path`] on Windows, relative to the
+/// `Fonts` directory of the Windows installation.
+#[cfg(windows)]
+const SYSTEM_FONTS: &[&str] = &["arial.ttf", "segoeui.ttf", "calibri.ttf", "tahoma.ttf"];
+
+/// Fonts tried in order by [`default_font_path`] on macOS.
+#[cfg(target_os = "macos")]
+const SYSTEM_FONTS: &[&str] = &[
+  "/System/Library/Fonts/Supplemental/Arial.ttf",
+  "/Library/Fonts/Arial.ttf",
+  "/System/Library/Fonts/Helvetica.ttc",
+  "/System/Library/Fonts/SFNS.ttf",
+];
+
+/// Fonts tried in order by [`default_font_path`] on Linux and other Unixes,
+/// where the packages of different distributions install them.
+#[cfg(not(any(windows, target_os = "macos")))]
+const SYSTEM_FONTS: &[&str] = &[
+  "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf",
+  "/usr/share/fonts/dejavu/DejaVuSans.ttf",
+  "/usr/share/fonts/TTF/DejaVuSans.ttf",
+  "/usr/share/fonts/dejavu-sans-fonts/DejaVuSans.ttf",
+  "/usr/share/fonts/truetype/liberation/LiberationSans-Regular.ttf",
+  "/usr/share/fonts/liberation-sans/LiberationSans-Regular.ttf",
+  "/usr/share/fonts/truetype/noto/NotoSans-Regular.ttf",
+  "/usr/share/fonts/noto/NotoSans-Regular.ttf",
+  "/usr/local/share/fonts/dejavu/DejaVuSans.ttf",
+];
+
+/// The font to draw text with.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub enum FontSpec {
+  /// A font found with [`default_font_path`].
+  #[default]
+  Default,
+  /// A TrueType or OpenType font file.
+  File(PathBuf),
+}
+
+/// Options for [`drawtext_filter`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextOverlay {
+  /// The text to draw, as is. `%` doesn't start a `drawtext` expansion like
+  /// `%{pts}`, and line breaks start a new line.
+  pub text: String,
+  /// Where to place the text on the video. Custom expressions can use e.g.
+  /// `w`, `h`, `text_w`, `text_h` and `t`.
+  pub position: OverlayPosition,
+  /// Distance in pixels from the edges, for corner positions.
+  pub margin: u32,
+  /// Font size in pixels.
+  pub size: u32,
+  /// A color name like `white`, or `#RRGGBB[AA]`, optionally followed by
+  /// `@` and an opacity, as in `black@0.5`.
+  pub color: String,
+  pub font: FontSpec,
+}
+
+impl TextOverlay {
+  /// White text of size 24 in the top left corner, with the default font.
+  pub fn new<S: Into<String>>(text: S) -> Self {
+    Self {
+      text: text.into(),
+      position: OverlayPosition::Corner(Corner::TopLeft),
+      margin: 16,
+      size: 24,
+      color: "white".to_string(),
+      font: FontSpec::Default,
+    }
+  }
+}
+
+/// A font which exists on this machine, for [`FontSpec::Default`].
+///
+/// The first font file (`.ttf`, `.otf` or `.ttc`, by name) in a `fonts`
+/// directory next to the sidecar ffmpeg binary is used if there is one, so
+/// an application can bundle its own. Otherwise, a common system font is
+/// looked for: Arial or Segoe UI on Windows, Arial or Helvetica on macOS,
+/// and DejaVu Sans, Liberation Sans or Noto Sans on Linux.
+pub fn default_font_path() -> Option<PathBuf> {
+  if let Some(bundled) = bundled_font() {
+    return Some(bundled);
+  }
+
+  #[cfg(windows)]
+  let fonts_dir =
+    PathBuf::from(std::env::var_os("WINDIR").unwrap_or_else(|| "C:\\Windows".into())).join("Fonts");
+  #[cfg(not(windows))]
+  let fonts_dir = PathBuf::from("/");
+  SYSTEM_FONTS
+    .iter()
+    .map(|font| fonts_dir.join(font))
+    .find(|path| path.is_file())
+}
+
+#[cfg(feature = "process")]
+fn bundled_font() -> Option<PathBuf> {
+  let entries = std::fs::read_dir(crate::paths::sidecar_dir().ok()?.join("fonts")).ok()?;
+  let mut fonts = entries
+    .flatten()
+    .map(|entry| entry.path())
+    .filter(|path| {
+      path.is_file()
+        && path
+          .extension()
+          .and_then(|extension| extension.to_str())
+          .is_some_and(|extension| {
+            matches!(
+              extension.to_ascii_lowercase().as_str(),
+              "ttf" | "otf" | "ttc"
+            )
+          })
+    })
+    .collect::<Vec<_>>();
+  fonts.sort();
+  fonts.into_iter().next()
+}
+
+#[cfg(not(feature = "process"))]
+fn bundled_font() -> Option<PathBuf> {
+  None
+}
+
+/// Escape text for the `text` option of `drawtext` in a filtergraph: once for
+/// its own expansion of `%{...}` sequences, then as any option value.
+///
+/// ```rust
+/// use ffmpeg_sidecar::drawtext::escape_drawtext_text;
+///
+/// assert_eq!(escape_drawtext_text("Hello"), "Hello");
+/// assert_eq!(escape_drawtext_text("100%"), r"100\\\\%");
+/// assert_eq!(escape_drawtext_text("12:30"), r"12\\:30");
+/// assert_eq!(escape_drawtext_text("it's"), r"it\\\'s");
+/// ```
+pub fn escape_drawtext_text(text: &str) -> String {
+  let mut expanded = String::with_capacity(text.len());
+  for c in text.chars() {
+    if matches!(c, '\\' | '%') {
+      expanded.push('\\');
+    }
+    expanded.push(c);
+  }
+  escape_filter_value(&expanded)
+}
+
+/// The `drawtext` filter for `overlay`, resolving [`FontSpec::Default`] to a
+/// font file. Fails if no default font is found.
+///
+/// ```rust
+/// use ffmpeg_sidecar::drawtext::{drawtext_filter, FontSpec, TextOverlay};
+/// use ffmpeg_sidecar::overlay::{Corner, OverlayPosition};
+///
+/// let overlay = TextOverlay {
+///   position: OverlayPosition::Corner(Corner::BottomRight),
+///   color: "yellow".to_string(),
+///   font: FontSpec::File(r"C:\Windows\Fonts\arial.ttf".into()),
+///   ..TextOverlay::new("Live: 50%")
+/// };
+/// assert_eq!(
+///   drawtext_filter(&overlay).unwrap(),
+///   r"drawtext=fontfile=C\\:\\\\Windows\\\\Fonts\\\\arial.ttf:text=Live\\: 50\\\\%:fontsize=24:fontcolor=yellow:x=w-text_w-16:y=h-text_h-16"
+/// );
+/// ```
+pub fn drawtext_filter(overlay: &TextOverlay) -> anyhow::Result<String> {
+  let font = match &overlay.font {
+    FontSpec::File(path) => path.clone(),
+    FontSpec::Default => default_font_path().ok_or_else(|| {
+      anyhow::anyhow!(
+        "no font found for drawtext; install a font like DejaVu Sans, or use FontSpec::File"
+      )
+    })?,
+  };
+
+  let margin = overlay.margin;
+  let (x, y) = match &overlay.position {
+    OverlayPosition::Corner(corner) => {
+      let left = margin.to_string();
+      let right = format!("w-text_w-{margin}");
+      let top = margin.to_string();
+      let bottom = format!("h-text_h-{margin}");
+      match corner {
+        Corner::TopLeft => (left, top),
+        Corner::TopRight => (right, top),
+        Corner::BottomLeft => (left, bottom),
+        Corner::BottomRight => (right, bottom),
+      }
+    }
+    OverlayPosition::Center => ("(w-text_w)/2".to_string(), "(h-text_h)/2".to_string()),
+    OverlayPosition::Custom(x, y) => (escape_filter_value(x), escape_filter_value(y)),
+  };
+
+  Ok(format!(
+    "drawtext=fontfile={}:text={}:fontsize={}:fontcolor={}:x={x}:y={y}",
+    escape_filter_value(&font.to_string_lossy()),
+    escape_drawtext_text(&overlay.text),
+    overlay.size,
+    escape_filter_value(&overlay.color)
+  ))
+}
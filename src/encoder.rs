@@ -0,0 +1,157 @@
+//! Checking which video encoders actually work on this machine.
+//!
+//! `ffmpeg -encoders` lists every encoder compiled into the build, including
+//! hardware encoders like `h264_nvenc` which fail at runtime without the
+//! matching GPU and driver. The only reliable check is a test encode.
+
+use std::{
+  collections::HashMap,
+  ffi::OsStr,
+  path::PathBuf,
+  sync::{Mutex, OnceLock},
+  time::{Duration, Instant},
+};
+
+use crate::{
+  command::FfmpegCommand,
+  error::FfmpegErrorKind,
+  event::{FfmpegEvent, LogLevel},
+  paths::ffmpeg_path,
+};
+
+/// H.264 encoders in order of preference, hardware first.
+pub const H264_ENCODERS: &[&str] = &[
+  "h264_nvenc",
+  "h264_qsv",
+  "h264_amf",
+  "h264_videotoolbox",
+  "libx264",
+];
+
+/// HEVC encoders in order of preference, hardware first.
+pub const HEVC_ENCODERS: &[&str] = &[
+  "hevc_nvenc",
+  "hevc_qsv",
+  "hevc_amf",
+  "hevc_videotoolbox",
+  "libx265",
+];
+
+/// The result of [`probe_encoder`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct EncoderProbe {
+  /// Whether the test encode succeeded.
+  pub available: bool,
+  /// The error messages logged by ffmpeg, if the encode failed.
+  pub error: Option<String>,
+  /// The recognized cause of the failure, e.g.
+  /// [`FfmpegErrorKind::DriverMissing`].
+  pub error_kind: Option<FfmpegErrorKind>,
+  /// How long the test encode took, which is mostly encoder initialization.
+  pub init_time: Duration,
+}
+
+/// Check whether a video encoder works by encoding a single frame of a test
+/// pattern to a null output.
+///
+/// ```rust,no_run
+/// use ffmpeg_sidecar::encoder::probe_encoder;
+///
+/// let probe = probe_encoder("h264_nvenc");
+/// if !probe.available {
+///   println!("nvenc unavailable: {:?}", probe.error_kind);
+/// }
+/// ```
+pub fn probe_encoder(name: &str) -> EncoderProbe {
+  probe_encoder_with_path(ffmpeg_path(), name)
+}
+
+/// Lower level variant of `probe_encoder` that exposes a customized path to
+/// the ffmpeg binary.
+pub fn probe_encoder_with_path<S: AsRef<OsStr>>(
+  path_to_ffmpeg_binary: S,
+  name: &str,
+) -> EncoderProbe {
+  let start = Instant::now();
+  let mut errors = Vec::<String>::new();
+
+  let status = FfmpegCommand::new_with_path(path_to_ffmpeg_binary)
+    .hide_banner()
+    .format("lavfi")
+    // Some hardware encoders have a minimum frame size
+    .input("color=size=256x256:rate=1")
+    .frames(1)
+    .codec_video(name)
+    .format("null")
+    .output("-")
+    .spawn()
+    .map_err(anyhow::Error::from)
+    .and_then(|mut child| {
+      for event in child.iter()? {
+        match event {
+          FfmpegEvent::Error(e) | FfmpegEvent::Log(LogLevel::Error | LogLevel::Fatal, e) => {
+            errors.push(e)
+          }
+          _ => {}
+        }
+      }
+      Ok(child.wait()?)
+    });
+
+  let init_time = start.elapsed();
+  match status {
+    Ok(status) if status.success() => EncoderProbe {
+      available: true,
+      error: None,
+      error_kind: None,
+      init_time,
+    },
+    Ok(_) => EncoderProbe {
+      available: false,
+      error_kind: errors.iter().find_map(|e| FfmpegErrorKind::classify(e)),
+      error: Some(errors.join("\n")),
+      init_time,
+    },
+    Err(e) => EncoderProbe {
+      available: false,
+      error: Some(e.to_string()),
+      error_kind: None,
+      init_time,
+    },
+  }
+}
+
+/// The first working encoder of `candidates`, probing them in order. Unlike
+/// [`probe_encoder`], results are cached for the lifetime of the process, per
+/// ffmpeg binary.
+pub fn best_encoder(candidates: &[&'static str]) -> Option<&'static str> {
+  type Cache = Mutex<HashMap<(PathBuf, Vec<&'static str>), Option<&'static str>>>;
+  static CACHE: OnceLock<Cache> = OnceLock::new();
+
+  let key = (ffmpeg_path(), candidates.to_vec());
+  let cache = CACHE.get_or_init(Default::default);
+  if let Some(best) = cache.lock().ok().and_then(|cache| cache.get(&key).copied()) {
+    return best;
+  }
+
+  let best = candidates
+    .iter()
+    .copied()
+    .find(|name| probe_encoder_with_path(&key.0, name).available);
+  if let Ok(mut cache) = cache.lock() {
+    cache.insert(key, best);
+  }
+  best
+}
+
+/// The best working H.264 encoder, preferring hardware encoders. See
+/// [`H264_ENCODERS`].
+pub fn best_h264_encoder() -> Option<&'static str> {
+  best_encoder(H264_ENCODERS)
+}
+
+/// The best working HEVC encoder, preferring hardware encoders. See
+/// [`HEVC_ENCODERS`].
+pub fn best_hevc_encoder() -> Option<&'static str> {
+  best_encoder(HEVC_ENCODERS)
+}
@@ -0,0 +1,589 @@
+//! Classification of common ffmpeg error messages.
+//!
+//! FFmpeg reports failures as free-form log lines. [`FfmpegErrorKind::classify`]
+//! recognizes the ones with a well known cause, so callers can react to them
+//! (or show a useful hint) without matching on strings themselves.
+
+use std::{fmt, path::PathBuf, process::ExitStatus};
+
+use crate::{
+  host::{host_arch_info, BinaryArch, BinaryFormat},
+  stdio_policy::StdioPolicy,
+  version::option_min_version,
+};
+
+/// A recognized cause of an ffmpeg error message.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum FfmpegErrorKind {
+  /// `moov atom not found`: an MP4/MOV input is truncated, or has its index at
+  /// the end of the file and was read from a non-seekable source like stdin.
+  MoovAtomNotFound,
+  /// `Invalid data found when processing input`: the input isn't a media
+  /// format ffmpeg recognizes, or is corrupt.
+  InvalidData,
+  /// `No such file or directory`
+  NoSuchFile,
+  /// `Unknown encoder 'h264_nvenc'`: this ffmpeg build doesn't include the
+  /// encoder.
+  EncoderNotFound,
+  /// A hardware encoder is compiled in, but the driver or runtime library it
+  /// needs is missing or too old, e.g. `Cannot load libcuda.so.1`.
+  DriverMissing,
+  /// A hardware encoder and its driver are present, but no usable device was
+  /// found, e.g. `No capable devices found`.
+  DeviceNotFound,
+  /// `Broken pipe`: ffmpeg's output was piped, and the reader closed the pipe
+  /// (or dropped the event iterator) before ffmpeg finished writing.
+  BrokenPipe,
+  /// `muxer does not support non seekable output`: the muxer needs to seek
+  /// back, as with MP4 and `-movflags +faststart`, but writes to a pipe or
+  /// a network output.
+  NonSeekableOutput,
+  /// `Connection refused`: nothing listens at the address of a network input
+  /// or output, e.g. a camera which is off, or a server which isn't started.
+  ConnectionRefused,
+  /// `Connection timed out`: the host of a network input or output didn't
+  /// answer, or stopped sending data for longer than `rw_timeout`.
+  ConnectionTimedOut,
+  /// `Failed to resolve hostname`: the host name of a URL doesn't exist.
+  HostNotFound,
+  /// `Immediate exit requested`: ffmpeg was asked to stop (e.g. by a signal,
+  /// or a second `q`) while blocked on I/O, typically a stalled network
+  /// input without a timeout.
+  ExitRequested,
+  /// `Cannot find a valid font` or `Fontconfig error`: `drawtext` was given
+  /// no font file, and fontconfig is missing or has no fonts configured.
+  FontNotFound,
+  /// `Unrecognized option 'crf2'` or `Error splitting the argument list`: an
+  /// option is misspelled, has an invalid value, or needs a newer ffmpeg.
+  /// See [`FfmpegEvent::InvalidOption`](crate::event::FfmpegEvent::InvalidOption).
+  InvalidOption,
+}
+
+/// Substrings identifying each kind, checked in order.
+const PATTERNS: &[(&str, FfmpegErrorKind)] = &[
+  ("moov atom not found", FfmpegErrorKind::MoovAtomNotFound),
+  (
+    "Invalid data found when processing input",
+    FfmpegErrorKind::InvalidData,
+  ),
+  ("No such file or directory", FfmpegErrorKind::NoSuchFile),
+  ("Unknown encoder", FfmpegErrorKind::EncoderNotFound),
+  ("Encoder not found", FfmpegErrorKind::EncoderNotFound),
+  ("Cannot load libcuda", FfmpegErrorKind::DriverMissing),
+  ("Cannot load nvcuda", FfmpegErrorKind::DriverMissing),
+  (
+    "Cannot load libnvidia-encode",
+    FfmpegErrorKind::DriverMissing,
+  ),
+  ("Cannot load nvEncodeAPI", FfmpegErrorKind::DriverMissing),
+  (
+    "minimum required Nvidia driver",
+    FfmpegErrorKind::DriverMissing,
+  ),
+  (
+    "Driver does not support the required nvenc API version",
+    FfmpegErrorKind::DriverMissing,
+  ),
+  ("Failed to load AMF", FfmpegErrorKind::DriverMissing),
+  ("amfrt64.dll failed to open", FfmpegErrorKind::DriverMissing),
+  ("libamfrt64.so", FfmpegErrorKind::DriverMissing),
+  ("Broken pipe", FfmpegErrorKind::BrokenPipe),
+  ("Connection refused", FfmpegErrorKind::ConnectionRefused),
+  ("Connection timed out", FfmpegErrorKind::ConnectionTimedOut),
+  ("Operation timed out", FfmpegErrorKind::ConnectionTimedOut),
+  ("Failed to resolve hostname", FfmpegErrorKind::HostNotFound),
+  ("Name or service not known", FfmpegErrorKind::HostNotFound),
+  ("Immediate exit requested", FfmpegErrorKind::ExitRequested),
+  (
+    "muxer does not support non seekable output",
+    FfmpegErrorKind::NonSeekableOutput,
+  ),
+  ("Cannot find a valid font", FfmpegErrorKind::FontNotFound),
+  ("Fontconfig error", FfmpegErrorKind::FontNotFound),
+  ("Unrecognized option", FfmpegErrorKind::InvalidOption),
+  (
+    "Error splitting the argument list",
+    FfmpegErrorKind::InvalidOption,
+  ),
+  ("No capable devices found", FfmpegErrorKind::DeviceNotFound),
+  (
+    "No NVENC capable devices found",
+    FfmpegErrorKind::DeviceNotFound,
+  ),
+  ("Device creation failed", FfmpegErrorKind::DeviceNotFound),
+  (
+    "No device available for encoder",
+    FfmpegErrorKind::DeviceNotFound,
+  ),
+  (
+    "Error creating a MFX session",
+    FfmpegErrorKind::DeviceNotFound,
+  ),
+  (
+    "Error initializing an internal MFX session",
+    FfmpegErrorKind::DeviceNotFound,
+  ),
+  (
+    "cannot create compression session",
+    FfmpegErrorKind::DeviceNotFound,
+  ),
+];
+
+/// Substrings of the errors logged by decoders for damaged input, which
+/// ffmpeg skips over unless `-xerror` is set.
+const DECODE_ERROR_PATTERNS: &[&str] = &[
+  "corrupt decoded frame",
+  "concealing",
+  "error while decoding",
+  "Error while decoding stream",
+  "Invalid NAL unit size",
+  "decode_slice_header error",
+  "Packet corrupt",
+  "Invalid frame header",
+];
+
+/// Whether a log line is a decoder error about damaged input, which isn't
+/// fatal by default but usually leaves glitches or gaps in the output.
+///
+/// ```rust
+/// use ffmpeg_sidecar::error::is_decode_error;
+///
+/// assert!(is_decode_error("[h264 @ 0x5581c8f0] [error] concealing 1620 DC, 1620 AC, 1620 MV errors in P frame"));
+/// assert!(is_decode_error("[error] Error while decoding stream #0:0: Invalid data found when processing input"));
+/// assert!(!is_decode_error("[info] Press [q] to stop, [?] for help"));
+/// ```
+pub fn is_decode_error(message: &str) -> bool {
+  DECODE_ERROR_PATTERNS
+    .iter()
+    .any(|pattern| message.contains(pattern))
+}
+
+impl FfmpegErrorKind {
+  /// Recognize the cause of an error log line, if it's a known one.
+  ///
+  /// ```rust
+  /// use ffmpeg_sidecar::error::FfmpegErrorKind;
+  ///
+  /// let line = "[mov,mp4,m4a,3gp,3g2,mj2 @ 0x7f8b2c004a00] [error] moov atom not found";
+  /// assert_eq!(FfmpegErrorKind::classify(line), Some(FfmpegErrorKind::MoovAtomNotFound));
+  /// assert_eq!(FfmpegErrorKind::classify("[info] Press [q] to stop"), None);
+  /// ```
+  pub fn classify(message: &str) -> Option<Self> {
+    PATTERNS
+      .iter()
+      .find(|(pattern, _)| message.contains(pattern))
+      .map(|(_, kind)| kind.clone())
+  }
+
+  /// Whether this is a failure of the network, for which reconnecting or
+  /// retrying later may help.
+  pub fn is_network(&self) -> bool {
+    matches!(
+      self,
+      FfmpegErrorKind::ConnectionRefused
+        | FfmpegErrorKind::ConnectionTimedOut
+        | FfmpegErrorKind::HostNotFound
+        | FfmpegErrorKind::ExitRequested
+    )
+  }
+}
+
+impl fmt::Display for FfmpegErrorKind {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      FfmpegErrorKind::MoovAtomNotFound => f.write_str(
+        "moov atom not found; the MP4 index is missing, or at the end of a file read from a \
+         non-seekable input (remux it with `-movflags +faststart`, or read it from a file)",
+      ),
+      FfmpegErrorKind::InvalidData => f.write_str("invalid data found when processing input"),
+      FfmpegErrorKind::NoSuchFile => f.write_str("no such file or directory"),
+      FfmpegErrorKind::EncoderNotFound => f.write_str("encoder not included in this ffmpeg build"),
+      FfmpegErrorKind::DriverMissing => {
+        f.write_str("the driver or runtime library for the hardware encoder is missing or outdated")
+      }
+      FfmpegErrorKind::DeviceNotFound => {
+        f.write_str("no device available for the hardware encoder")
+      }
+      FfmpegErrorKind::BrokenPipe => {
+        f.write_str("the output pipe was closed before ffmpeg finished writing")
+      }
+      FfmpegErrorKind::NonSeekableOutput => f.write_str(
+        "the muxer can't write to a non-seekable output; for MP4, write a fragmented file with \
+           `-movflags +frag_keyframe+empty_moov` instead of `+faststart` (see `web_optimized`)",
+      ),
+      FfmpegErrorKind::ConnectionRefused => f.write_str("the connection was refused"),
+      FfmpegErrorKind::ConnectionTimedOut => f.write_str("the connection timed out"),
+      FfmpegErrorKind::HostNotFound => f.write_str("the host name couldn't be resolved"),
+      FfmpegErrorKind::ExitRequested => f.write_str(
+        "ffmpeg was stopped while waiting on I/O; set a `rw_timeout` on network inputs so \
+         stalled connections fail on their own",
+      ),
+      FfmpegErrorKind::FontNotFound => f.write_str(
+        "drawtext couldn't find a font through fontconfig; pass a font file instead (see \
+         `FontSpec` in the `drawtext` module)",
+      ),
+      FfmpegErrorKind::InvalidOption => {
+        f.write_str("an option is unrecognized or has an invalid value")
+      }
+    }
+  }
+}
+
+/// Returned (through `anyhow::Error`) when writing to ffmpeg's stdin fails
+/// because ffmpeg closed it, typically because it exited.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChildExited {
+  /// The exit status, or `None` if ffmpeg closed stdin but is still running.
+  pub status: Option<ExitStatus>,
+  /// The last lines read from stderr, which usually explain why ffmpeg
+  /// exited. Empty if stderr wasn't being read.
+  pub stderr_tail: Vec<String>,
+}
+
+impl fmt::Display for ChildExited {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self.status {
+      Some(status) => write!(f, "ffmpeg exited ({status}) and closed its stdin")?,
+      None => f.write_str("ffmpeg closed its stdin")?,
+    }
+    for line in &self.stderr_tail {
+      write!(f, "\n{line}")?;
+    }
+    Ok(())
+  }
+}
+
+impl std::error::Error for ChildExited {}
+
+/// Returned (through `anyhow::Error`) by
+/// [`FfmpegChild::quit`](crate::child::FfmpegChild::quit) when ffmpeg doesn't
+/// read key presses from stdin, so the caller can fall back to
+/// [`FfmpegChild::kill`](crate::child::FfmpegChild::kill) right away.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GracefulQuitUnavailable {
+  /// ffmpeg was spawned with `-nostdin`.
+  NoStdin,
+  /// An input is read from stdin (`-i -` or `pipe:`), so a `q` would be
+  /// read as data.
+  StdinIsInput,
+  /// Stdin was taken, e.g. by `take_stdin` or `feed_stdin`, or isn't piped
+  /// (see [`FfmpegCommand::stdin_stdio`](crate::command::FfmpegCommand::stdin_stdio)).
+  StdinTaken,
+}
+
+impl fmt::Display for GracefulQuitUnavailable {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let reason = match self {
+      GracefulQuitUnavailable::NoStdin => "ffmpeg was spawned with -nostdin",
+      GracefulQuitUnavailable::StdinIsInput => "stdin is used as an input",
+      GracefulQuitUnavailable::StdinTaken => "stdin was already taken",
+    };
+    write!(f, "ffmpeg can't be asked to quit: {reason}")
+  }
+}
+
+impl std::error::Error for GracefulQuitUnavailable {}
+
+/// Returned (through `io::Error`, with the kind `InvalidInput`) by
+/// [`FfmpegCommand::spawn`](crate::command::FfmpegCommand::spawn) before
+/// spawning, when a stream which isn't piped is needed by the rest of the
+/// command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StdioConflict {
+  /// `stdin`, `stdout` or `stderr`
+  pub stream: &'static str,
+  pub policy: StdioPolicy,
+  /// What needs the stream, like `first_output_timeout`.
+  pub needed_by: &'static str,
+}
+
+impl fmt::Display for StdioConflict {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "{} is {}, but {} needs it piped",
+      self.stream, self.policy, self.needed_by
+    )
+  }
+}
+
+impl std::error::Error for StdioConflict {}
+
+/// Returned (through `io::Error`, with the kind `InvalidInput`) by
+/// [`FfmpegCommand::spawn`](crate::command::FfmpegCommand::spawn) before
+/// spawning, when raw video frames are output to stdout and something else
+/// writes there too, which would mix its bytes into the frames.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct StdoutConflict {
+  /// The argument writing to stdout, like `-progress pipe:1`, or an output
+  /// like `pipe:1` which isn't raw video.
+  pub arg: String,
+}
+
+impl fmt::Display for StdoutConflict {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "`{}` writes to stdout, where the raw video frames are read from, and would corrupt them",
+      self.arg
+    )
+  }
+}
+
+impl std::error::Error for StdoutConflict {}
+
+/// Why ffmpeg couldn't start, returned (through `io::Error`) by
+/// [`FfmpegCommand::spawn`](crate::command::FfmpegCommand::spawn), or by
+/// [`FfmpegChild::wait`](crate::child::FfmpegChild::wait) for
+/// [`MissingDependencies`](SpawnError::MissingDependencies), which is only
+/// known once the dynamic loader gave up. Each displays the fix.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SpawnError {
+  /// No binary at any of the paths tried, in order: the sidecar next to
+  /// this executable for the default `ffmpeg`, then each directory of the
+  /// `PATH`. Returned with the kind `NotFound`.
+  BinaryNotFound { searched_paths: Vec<PathBuf> },
+  /// The binary exists but the OS can't run it (`Exec format error` or
+  /// `Bad CPU type in executable`), usually because it was built for
+  /// another architecture. Returned with the kind of the original error.
+  IncompatibleBinary {
+    path: PathBuf,
+    /// The format and architectures read from the binary, if recognized.
+    arch: Option<BinaryArch>,
+    /// The error of the OS, along with the architectures.
+    details: String,
+  },
+  /// The binary started, but the dynamic loader couldn't find or load its
+  /// shared libraries, e.g. another version of `libssl` for a shared Linux
+  /// build. Returned with the kind `Other`.
+  MissingDependencies {
+    path: PathBuf,
+    /// What the loader printed to stderr, read by the iterator.
+    loader_output: String,
+  },
+}
+
+impl SpawnError {
+  /// The usual fix for this failure on this machine.
+  pub fn suggestion(&self) -> String {
+    match self {
+      SpawnError::BinaryNotFound { .. } => "install ffmpeg in the PATH, or download it next to \
+        this executable with `auto_download`"
+        .to_string(),
+      SpawnError::IncompatibleBinary { arch, .. } => {
+        let host = host_arch_info();
+        let macos_x86_64 = arch.as_ref().is_some_and(|arch| {
+          matches!(
+            arch.format,
+            BinaryFormat::MachO | BinaryFormat::UniversalMachO
+          ) && arch.supports("x86_64")
+        });
+        if cfg!(target_os = "macos") && host.native_arch == "aarch64" && macos_x86_64 {
+          "install Rosetta 2 with `softwareupdate --install-rosetta`, or use an arm64 build"
+            .to_string()
+        } else {
+          format!(
+            "use a build for {}, e.g. by deleting the binary and running `auto_download`",
+            host.native_arch
+          )
+        }
+      }
+      SpawnError::MissingDependencies { .. } => "use a static build, like the one \
+        `auto_download` installs, or install the libraries it was built against"
+        .to_string(),
+    }
+  }
+}
+
+impl fmt::Display for SpawnError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      SpawnError::BinaryNotFound { searched_paths } => {
+        f.write_str("ffmpeg wasn't found, tried ")?;
+        match searched_paths.as_slice() {
+          [] => f.write_str("no path")?,
+          paths => {
+            let paths = paths
+              .iter()
+              .map(|path| path.display().to_string())
+              .collect::<Vec<_>>();
+            f.write_str(&paths.join(", "))?
+          }
+        }
+      }
+      SpawnError::IncompatibleBinary { path, details, .. } => {
+        write!(f, "{} can't run on this machine: {details}", path.display())?
+      }
+      SpawnError::MissingDependencies {
+        path,
+        loader_output,
+      } => write!(
+        f,
+        "{} is missing shared libraries: {loader_output}",
+        path.display()
+      )?,
+    }
+    write!(f, "; {}", self.suggestion())
+  }
+}
+
+impl std::error::Error for SpawnError {}
+
+/// Substrings of the messages of the dynamic loaders of Linux (glibc and
+/// musl) and macOS when a shared library is missing or too old.
+#[cfg(feature = "process")]
+const LOADER_PATTERNS: &[&str] = &[
+  "error while loading shared libraries",
+  "Error loading shared library",
+  "Error relocating",
+  "not found (required by",
+  "Library not loaded",
+  "Symbol not found",
+];
+
+/// The lines of stderr printed by the dynamic loader failing to load the
+/// shared libraries of the binary, joined with `\n`, if any.
+#[cfg(feature = "process")]
+pub(crate) fn loader_failure<S: AsRef<str>>(lines: &[S]) -> Option<String> {
+  let failures = lines
+    .iter()
+    .map(AsRef::as_ref)
+    .filter(|line| LOADER_PATTERNS.iter().any(|pattern| line.contains(pattern)))
+    .collect::<Vec<_>>();
+  (!failures.is_empty()).then(|| failures.join("\n"))
+}
+
+/// Whether spawning failed because the OS doesn't recognize the binary as
+/// an executable for this machine: `ENOEXEC` on Unix, `EBADARCH` on macOS,
+/// and `ERROR_BAD_EXE_FORMAT` or `ERROR_EXE_MACHINE_TYPE_MISMATCH` on
+/// Windows.
+#[cfg(feature = "process")]
+pub(crate) fn is_exec_format_error(error: &std::io::Error) -> bool {
+  match error.raw_os_error() {
+    Some(8) => cfg!(unix),
+    Some(86) => cfg!(target_os = "macos"),
+    Some(193 | 216) => cfg!(windows),
+    _ => false,
+  }
+}
+
+/// Returned (through `io::Error`, with the kind `InvalidData`) by
+/// [`FfmpegChild::wait`](crate::child::FfmpegChild::wait) when the raw video
+/// frames of an output are larger than
+/// [`max_frame_bytes`](crate::child::FfmpegChild::max_frame_bytes), so the
+/// iterator didn't read them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameTooLarge {
+  pub output_index: u32,
+  pub width: u32,
+  pub height: u32,
+  pub pix_fmt: String,
+  /// The size of a frame, in bytes.
+  pub bytes: u64,
+  pub max_bytes: u64,
+}
+
+impl fmt::Display for FrameTooLarge {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "the {}x{} {} frames of output {} take {} bytes each, more than the maximum of {}",
+      self.width, self.height, self.pix_fmt, self.output_index, self.bytes, self.max_bytes
+    )
+  }
+}
+
+impl std::error::Error for FrameTooLarge {}
+
+/// Returned (through `anyhow::Error`) by
+/// [`check_output_duration`](crate::integrity::check_output_duration) when an
+/// output is shorter than expected by more than the tolerance.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TruncatedOutput {
+  /// The expected duration, in seconds.
+  pub expected: f64,
+  /// The duration of the output, in seconds.
+  pub actual: f64,
+}
+
+impl TruncatedOutput {
+  /// How many seconds are missing.
+  pub fn shortfall(&self) -> f64 {
+    self.expected - self.actual
+  }
+}
+
+impl fmt::Display for TruncatedOutput {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "the output is {:.2}s long instead of {:.2}s, it's missing its last {:.2}s",
+      self.actual,
+      self.expected,
+      self.shortfall()
+    )
+  }
+}
+
+impl std::error::Error for TruncatedOutput {}
+
+/// Returned (through `anyhow::Error`) by
+/// [`verify_integrity`](crate::integrity::verify_integrity) when ffmpeg
+/// fails before decoding anything, e.g. with `Invalid data found when
+/// processing input` for a file which isn't media at all, as opposed to the
+/// errors in the middle of a file listed in its report.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnreadableInput {
+  /// The cause, if it's a known one.
+  pub kind: Option<FfmpegErrorKind>,
+  /// The error messages logged by ffmpeg.
+  pub message: String,
+}
+
+impl fmt::Display for UnreadableInput {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "the input can't be read: {}", self.message)
+  }
+}
+
+impl std::error::Error for UnreadableInput {}
+
+/// Returned (through `io::Error`, with the kind `InvalidInput`) by
+/// [`FfmpegChild::wait`](crate::child::FfmpegChild::wait) when ffmpeg exits
+/// because of an option it rejected, as reported by an
+/// [`FfmpegEvent::InvalidOption`](crate::event::FfmpegEvent::InvalidOption).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct InvalidOption {
+  /// The name of the option, without its leading `-`, like `crf2`.
+  pub option: String,
+  /// The message of ffmpeg's log line, without its prefixes.
+  pub message: String,
+}
+
+impl InvalidOption {
+  /// The first `(major, minor)` version of ffmpeg recognizing the option, if
+  /// it's a known one added in a recent version. See
+  /// [`option_min_version`].
+  pub fn min_version(&self) -> Option<(u32, u32)> {
+    option_min_version(&self.option)
+  }
+}
+
+impl fmt::Display for InvalidOption {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "invalid option -{}: {}", self.option, self.message)?;
+    if let Some((major, minor)) = self.min_version() {
+      write!(
+        f,
+        " (-{} needs ffmpeg {major}.{minor} or later)",
+        self.option
+      )?;
+    }
+    Ok(())
+  }
+}
+
+impl std::error::Error for InvalidOption {}
@@ -0,0 +1,57 @@
+//! What happens when ffmpeg logs errors while it keeps running. See
+//! [`FfmpegChild::error_policy`](crate::child::FfmpegChild::error_policy).
+//!
+//! An error-level line never ends the events or the frames by itself: only
+//! ffmpeg exiting or closing its output does. Decoding a slightly damaged
+//! file, ffmpeg logs lines like `Error while decoding stream #0:0: Invalid
+//! data found when processing input` and skips the damaged frames, so by
+//! default the job carries on and the errors are counted in the
+//! [`FfmpegSummary`](crate::summary::FfmpegSummary).
+
+use std::fmt;
+
+/// Whether the errors logged by ffmpeg stop it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum ErrorPolicy {
+  /// Keep going, collecting the errors in the summary.
+  #[default]
+  CollectAndContinue,
+  /// Kill ffmpeg at the first error.
+  AbortOnFirstError,
+  /// Kill ffmpeg once this many errors were logged, e.g. to tolerate a few
+  /// damaged frames but not a file which is damaged throughout.
+  AbortAfter(u32),
+}
+
+impl ErrorPolicy {
+  /// The number of errors which stops ffmpeg, if any.
+  pub fn limit(&self) -> Option<u32> {
+    match self {
+      ErrorPolicy::CollectAndContinue => None,
+      ErrorPolicy::AbortOnFirstError => Some(1),
+      ErrorPolicy::AbortAfter(errors) => Some((*errors).max(1)),
+    }
+  }
+}
+
+/// Returned (through `std::io::Error`) by
+/// [`FfmpegChild::wait`](crate::child::FfmpegChild::wait) once ffmpeg was
+/// killed because of its [`ErrorPolicy`], and reported in the summary.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ErrorLimitReached {
+  pub policy: ErrorPolicy,
+  /// The errors logged until ffmpeg was killed.
+  pub errors: Vec<String>,
+}
+
+impl fmt::Display for ErrorLimitReached {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str("ffmpeg was stopped because of the errors it logged")?;
+    for error in &self.errors {
+      write!(f, "\n{error}")?;
+    }
+    Ok(())
+  }
+}
+
+impl std::error::Error for ErrorLimitReached {}
@@ -1,4 +1,14 @@
+use std::{process::ExitStatus, time::Duration};
+
+use crate::{
+  color::ColorMetadata, extract::StreamKind, filter_graph::FilterGraphDump,
+  log_parser::try_parse_log_context, progress_view::ProgressView, resource_usage::ResourceUsage,
+  stats::StatsRollup, timestamp::Timestamp,
+};
+
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub enum FfmpegEvent {
   ParsedVersion(FfmpegVersion),
   ParsedConfiguration(FfmpegConfiguration),
@@ -8,7 +18,81 @@ pub enum FfmpegEvent {
   ParsedInputStream(AVStream),
   ParsedOutputStream(AVStream),
   ParsedDuration(FfmpegDuration),
+  /// A program of an input with several, like a channel of a DVB capture.
+  /// Follows the lines of its streams, which are parsed as
+  /// [`ParsedInputStream`](Self::ParsedInputStream) events too, once each
+  /// even when they belong to several programs.
+  ParsedProgram(ParsedProgram),
+  /// A tag of an input or output, or of one of their streams, like `title`
+  /// or `language`. Follows the log lines it was parsed from, since a value
+  /// can continue on the next lines.
+  ParsedTag(FfmpegTag),
+  /// A warning indicating timestamp or frame rate problems, typical of live
+  /// captures which drift over time.
+  SyncWarning(FfmpegSyncWarning),
+  /// ffmpeg's reply to a command sent with
+  /// [`FfmpegChild::send_filter_command`](crate::child::FfmpegChild::send_filter_command).
+  FilterCommandReply(FfmpegFilterCommandReply),
+  /// The filters ffmpeg set up, parsed from the lines logged at the
+  /// `verbose` level (see
+  /// [`FfmpegCommand::dump_filter_graph`](crate::command::FfmpegCommand::dump_filter_graph)).
+  /// Follows those lines, before the first progress update after them.
+  FilterGraph(FilterGraphDump),
+  /// A file written by the `segment` muxer was finalized, see
+  /// [`FfmpegCommand::output_segments`](crate::command::FfmpegCommand::output_segments).
+  SegmentComplete {
+    /// Position of the segment in the output, starting at 0. Unlike the
+    /// number in the filename, this doesn't wrap around.
+    index: u32,
+    path: String,
+  },
+  /// ffmpeg rejected an option of the command, because it's misspelled, has
+  /// an invalid value, or is too recent for this version of ffmpeg. Follows
+  /// the error log line it was parsed from, see
+  /// [`try_parse_invalid_option`](crate::log_parser::try_parse_invalid_option).
+  ///
+  /// ffmpeg exits right after, and
+  /// [`FfmpegChild::wait`](crate::child::FfmpegChild::wait) returns the
+  /// first one as an [`InvalidOption`](crate::error::InvalidOption) error.
+  InvalidOption {
+    /// The name of the option, without its leading `-`, like `crf2`.
+    option: String,
+    /// The message of the log line, without its prefixes.
+    message: String,
+  },
+  /// A suggested fix for a likely problem, either found in the arguments
+  /// when spawning (see [`bitstream_filter_warnings`](crate::bsf::bitstream_filter_warnings)),
+  /// or following a log line which ffmpeg explains with a known cause.
+  Hint(String),
+  /// ffmpeg logged `Press [q] to stop, [?] for help`, confirming that it
+  /// reads key presses from stdin, so
+  /// [`FfmpegChild::quit`](crate::child::FfmpegChild::quit) works. Follows
+  /// the log line itself.
+  Interactive,
+  /// ffmpeg asked a question on stderr and blocks until it's answered on
+  /// stdin with [`FfmpegChild::reply_to_prompt`](crate::child::FfmpegChild::reply_to_prompt),
+  /// e.g. whether to overwrite an existing output when neither
+  /// [`overwrite`](crate::command::FfmpegCommand::overwrite) nor
+  /// [`no_overwrite`](crate::command::FfmpegCommand::no_overwrite) was
+  /// passed. Left unanswered, ffmpeg is killed after the
+  /// [`FfmpegChild::prompt_timeout`](crate::child::FfmpegChild::prompt_timeout).
+  Prompt {
+    kind: PromptKind,
+    /// The question as ffmpeg wrote it, like
+    /// `File 'out.mp4' already exists. Overwrite? [y/N]`.
+    text: String,
+  },
+  /// The sizes of the streams written, logged at the end of a successful
+  /// run. Replaces the log line it was parsed from.
+  EncodeSummary(FfmpegEncodeSummary),
+  /// The frame statistics of a libx264 or libx265 encoder, logged when it's
+  /// closed. Follows the log lines they were parsed from.
+  EncoderStats(EncoderStats),
   Log(LogLevel, String),
+  /// Several log lines of a single message, like a device listing, replacing
+  /// their `Log` events. Only emitted with
+  /// [`FfmpegCommand::group_log_messages`](crate::command::FfmpegCommand::group_log_messages).
+  LogGroup(FfmpegLogGroup),
   LogEOF,
   /// An error that didn't originate from the ffmpeg logs
   Error(String),
@@ -19,10 +103,102 @@ pub enum FfmpegEvent {
   /// These chunks will need to be handled manually, or piped directly to
   /// another FFmpeg instance.
   OutputChunk(Vec<u8>),
+  /// The CPU and memory used by ffmpeg, measured at the interval set with
+  /// [`FfmpegChild::sample_interval`](crate::child::FfmpegChild::sample_interval)
+  /// between the other events.
+  ResourceSample(ResourceUsage),
+  /// The statistics of a bucket of a
+  /// [`StatsAggregator`](crate::stats::StatsAggregator) which ended, from
+  /// the iterator of
+  /// [`aggregate_stats`](crate::iter::FfmpegIterator::aggregate_stats).
+  StatsRollup(StatsRollup),
   Done,
+  /// ffmpeg exited and was reaped, once its output was read to the end.
+  /// Always the last event of an iterator or subscription, so reading them
+  /// to the end is enough to learn the outcome; calling
+  /// [`FfmpegChild::wait`](crate::child::FfmpegChild::wait) too is still
+  /// fine, and returns the same status.
+  Exited {
+    #[cfg_attr(feature = "serde", serde(with = "raw_exit_status"))]
+    status: ExitStatus,
+    /// Whether ffmpeg exited by itself, including after
+    /// [`FfmpegChild::quit`](crate::child::FfmpegChild::quit), rather than
+    /// being killed by [`FfmpegChild::kill`](crate::child::FfmpegChild::kill),
+    /// [`kill_on_drop`](crate::command::FfmpegCommand::kill_on_drop) or a
+    /// timeout.
+    graceful: bool,
+  },
+}
+
+impl FfmpegEvent {
+  /// The line of ffmpeg's stderr this event was parsed from, if any, exactly
+  /// as ffmpeg logged it. For events parsed from several lines, like a tag
+  /// with a multi-line value, the lines are joined with `\n`.
+  pub fn raw_log_message(&self) -> Option<&str> {
+    match self {
+      FfmpegEvent::ParsedVersion(x) => Some(&x.raw_log_message),
+      FfmpegEvent::ParsedConfiguration(x) => Some(&x.raw_log_message),
+      FfmpegEvent::ParsedStreamMapping(x) => Some(x),
+      FfmpegEvent::ParsedOutput(x) => Some(&x.raw_log_message),
+      FfmpegEvent::ParsedInputStream(x) => Some(&x.raw_log_message),
+      FfmpegEvent::ParsedOutputStream(x) => Some(&x.raw_log_message),
+      FfmpegEvent::Log(_, x) => Some(x),
+      FfmpegEvent::LogGroup(group) => Some(&group.raw_log_message),
+      FfmpegEvent::LogEOF => None,
+      FfmpegEvent::Error(_) => None,
+      FfmpegEvent::Progress(x) => Some(&x.raw_log_message),
+      FfmpegEvent::OutputFrame(_) => None,
+      FfmpegEvent::OutputChunk(_) => None,
+      FfmpegEvent::ResourceSample(_) => None,
+      FfmpegEvent::StatsRollup(_) => None,
+      FfmpegEvent::Done => None,
+      FfmpegEvent::Exited { .. } => None,
+      FfmpegEvent::SegmentComplete { .. } => None,
+      FfmpegEvent::InvalidOption { .. } => None,
+      FfmpegEvent::Hint(_) => None,
+      FfmpegEvent::Interactive => None,
+      FfmpegEvent::Prompt { text, .. } => Some(text),
+      FfmpegEvent::EncodeSummary(summary) => Some(&summary.raw_log_message),
+      FfmpegEvent::EncoderStats(stats) => Some(&stats.raw_log_message),
+      FfmpegEvent::ParsedInput(input) => Some(&input.raw_log_message),
+      FfmpegEvent::ParsedDuration(duration) => Some(&duration.raw_log_message),
+      FfmpegEvent::ParsedProgram(program) => Some(&program.raw_log_message),
+      FfmpegEvent::ParsedTag(tag) => Some(&tag.raw_log_message),
+      FfmpegEvent::SyncWarning(warning) => Some(&warning.raw_log_message),
+      FfmpegEvent::FilterCommandReply(reply) => Some(&reply.raw_log_message),
+      FfmpegEvent::FilterGraph(_) => None,
+    }
+  }
+
+  /// The component which logged the line of this event, parsed from its
+  /// prefix when there is one, like `[libx264 @ 0x55d0c1a0]`. See
+  /// [`try_parse_log_context`](crate::log_parser::try_parse_log_context).
+  ///
+  /// ```rust
+  /// use ffmpeg_sidecar::event::{FfmpegEvent, LogLevel};
+  ///
+  /// let line = "[aac @ 0x6000] [warning] Too many bits per frame requested, clamping to max";
+  /// let event = FfmpegEvent::Log(LogLevel::Warning, line.to_string());
+  /// assert_eq!(event.context().unwrap().component, "aac");
+  /// ```
+  pub fn context(&self) -> Option<LogContext<'_>> {
+    try_parse_log_context(self.raw_log_message()?)
+  }
+}
+
+/// The component which logged a line, borrowed from the line itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LogContext<'a> {
+  /// The name of the component, like `libx264`, `mov,mp4,m4a,3gp,3g2,mj2`,
+  /// or `vost#0:0/libx264` for the output streams of ffmpeg 7.
+  pub component: &'a str,
+  /// The address of the component, telling apart several instances of the
+  /// same one, like the decoders of two inputs.
+  pub pointer: Option<&'a str>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum LogLevel {
   Info,
   Warning,
@@ -32,20 +208,81 @@ pub enum LogLevel {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FfmpegInput {
   pub index: u32,
-  pub duration: Option<f64>,
+  pub duration: Option<Timestamp>,
   pub raw_log_message: String,
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FfmpegDuration {
   pub input_index: u32,
-  pub duration: f64,
+  pub duration: Timestamp,
   pub raw_log_message: String,
 }
 
+/// `Program 4164`, in the listing of an input, with the streams under it.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ParsedProgram {
+  pub input_index: u32,
+  /// The program number, as in `-map 0:p:4164`
+  pub id: u32,
+  /// The `service_name` tag of the program, like `BBC ONE HD`
+  pub service_name: Option<String>,
+  /// The indices of its streams in the input, in log order
+  pub stream_indices: Vec<u32>,
+  /// The `Program` line it starts on
+  pub raw_log_message: String,
+}
+
+/// `title           : Holiday`, in a `Metadata:` block of the log.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FfmpegTag {
+  pub scope: TagScope,
+  pub key: String,
+  /// The lines of a value spanning several lines, like an ID3 comment, are
+  /// joined with `\n`.
+  pub value: String,
+  /// The line that this tag starts on
+  pub raw_log_message: String,
+}
+
+/// The question of a [`FfmpegEvent::Prompt`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PromptKind {
+  /// `File 'out.mp4' already exists. Overwrite? [y/N]`: answering no makes
+  /// ffmpeg exit without writing anything.
+  Overwrite,
+}
+
+/// What a [`FfmpegTag`] belongs to, by the indices in the log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TagScope {
+  Input(u32),
+  InputStream {
+    input: u32,
+    stream: u32,
+  },
+  Output(u32),
+  OutputStream {
+    output: u32,
+    stream: u32,
+  },
+  /// A program of an input, by its number
+  Program {
+    input: u32,
+    program: u32,
+  },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FfmpegOutput {
   pub to: String,
   pub index: u32,
@@ -60,56 +297,115 @@ impl FfmpegOutput {
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub struct AVStream {
   /// Typically `video` or `audio`, but might be something else like `data` or `subtitle`.
   pub stream_type: String,
   /// Corresponds to stream `-f` parameter, e.g. `rawvideo`, `h264`, or `mpegts`
   pub format: String,
+  /// The codec tag, e.g. `avc1`, `KLVA`, or `[27][0][0][0]` in MPEG-TS, if
+  /// reported.
+  pub codec_tag: Option<String>,
   /// Corresponds to stream `-pix_fmt` parameter, e.g. `rgb24`
   pub pix_fmt: String,
   /// Width in pixels
   pub width: u32,
   /// Height in pixels
   pub height: u32,
-  /// Framerate in frames per second
+  /// Framerate in frames per second, or 0 if not reported, as for a single
+  /// image or the cover art of an audio file
   pub fps: f32,
+  /// Color range, space, primaries and transfer characteristics, for video
+  /// streams which report them. The HDR side data isn't parsed from the log.
+  pub color: ColorMetadata,
+  /// Whether closed captions (EIA-608/CEA-708) are embedded in this video
+  /// stream, as in broadcast captures. See
+  /// [`FfmpegCommand::extract_captions`](crate::command::FfmpegCommand::extract_captions).
+  pub has_closed_captions: bool,
   /// The index of the input or output that this stream belongs to
   pub parent_index: usize,
+  /// The index of this stream in its input or output
+  pub stream_index: usize,
   /// The stderr line that this stream was parsed from
   pub raw_log_message: String,
 }
 
+impl AVStream {
+  /// The kind of stream, from its `stream_type`, e.g. `Data` for SCTE-35
+  /// cues or KLV metadata in a broadcast capture.
+  pub fn kind(&self) -> Option<StreamKind> {
+    match self.stream_type.as_str() {
+      "Video" => Some(StreamKind::Video),
+      "Audio" => Some(StreamKind::Audio),
+      "Subtitle" => Some(StreamKind::Subtitle),
+      "Data" => Some(StreamKind::Data),
+      "Attachment" => Some(StreamKind::Attachment),
+      _ => None,
+    }
+  }
+}
+
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FfmpegVersion {
   pub version: String,
   pub raw_log_message: String,
 }
 
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FfmpegConfiguration {
   pub configuration: Vec<String>,
   pub raw_log_message: String,
 }
 
+/// A progress update, parsed from lines like
+/// `frame= 1996 fps=1984 q=-1.0 size= 372kB time=00:01:19.72 bitrate= 38.2kbits/s speed=79.2x`.
+///
+/// Fields which ffmpeg doesn't report for every job are `Option`s. In
+/// particular, audio-only outputs have no `frame`, `fps` or `q`, so progress
+/// should be tracked with [`FfmpegProgress::out_time`] and
+/// [`FfmpegProgress::percent`] instead.
 #[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[non_exhaustive]
 pub struct FfmpegProgress {
-  /// index of the current output frame
-  pub frame: u32,
+  /// Index of the current output frame, or `None` for outputs without video.
+  ///
+  /// With several video streams this is a single aggregate figure for the
+  /// whole job (ffmpeg reports the first video output stream), not a
+  /// per-stream count.
+  pub frame: Option<u32>,
 
-  /// frames per second
-  pub fps: f32,
+  /// Frames per second, or `None` for outputs without video. Aggregate in the
+  /// same way as `frame`.
+  pub fps: Option<f32>,
 
-  /// Quality factor (if applicable)
-  pub q: f32,
+  /// Quality factor of the first video stream, if applicable
+  pub q: Option<f32>,
 
-  /// Current total size of the output in kilobytes
-  pub size_kb: u32,
+  /// Current total size of the output in kilobytes, or `None` if not known
+  /// (e.g. `-f null`)
+  pub size_kb: Option<u32>,
 
   /// The raw time string in a format like `00:03:29.04`
   pub time: String,
 
-  /// Bitrate in kilo**bits** per second
-  pub bitrate_kbps: f32,
+  /// The output time, parsed from `time`. `None` before the first timestamp
+  /// is known.
+  pub out_time: Option<Timestamp>,
+
+  /// Bitrate in kilo**bits** per second, or `None` if not yet known
+  pub bitrate_kbps: Option<f32>,
+
+  /// Number of frames duplicated so far to maintain the output frame rate
+  /// (`dup=`), or 0 if not reported
+  pub dup_frames: u32,
+
+  /// Number of frames dropped so far to maintain the output frame rate
+  /// (`drop=`), or 0 if not reported
+  pub drop_frames: u32,
 
   /// Processing speed as a ratio of the input duration
   ///
@@ -121,7 +417,256 @@ pub struct FfmpegProgress {
   pub raw_log_message: String,
 }
 
+impl FfmpegProgress {
+  /// Percentage of the job completed, between 0 and 100.
+  ///
+  /// Uses the frame count when both `total_frames` and [`FfmpegProgress::frame`]
+  /// are known, and otherwise falls back to comparing `out_time` with
+  /// `total_duration`, which also works for audio-only outputs.
+  /// The input duration is typically obtained from
+  /// [`FfmpegMetadata::duration`](crate::metadata::FfmpegMetadata::duration).
+  ///
+  /// ```rust
+  /// use ffmpeg_sidecar::{log_parser::try_parse_progress, timestamp::Timestamp};
+  /// let line = "[info] size=     256kB time=00:00:05.00 bitrate= 419.4kbits/s speed=  50x";
+  /// let progress = try_parse_progress(line).unwrap();
+  /// assert_eq!(progress.frame, None);
+  /// assert_eq!(progress.percent(Some(250), Some(Timestamp::from_secs_f64(10.0))), Some(50.0));
+  /// ```
+  pub fn percent(
+    &self,
+    total_frames: Option<u32>,
+    total_duration: Option<Timestamp>,
+  ) -> Option<f64> {
+    let percent = match (self.frame, total_frames) {
+      (Some(frame), Some(total)) if total > 0 => frame as f64 / total as f64 * 100.0,
+      _ => {
+        let total = total_duration.filter(|d| *d > Timestamp::ZERO)?;
+        self.out_time?.as_secs_f64() / total.as_secs_f64() * 100.0
+      }
+    };
+    Some(percent.clamp(0.0, 100.0))
+  }
+
+  /// Estimated wall clock time remaining, with the same frame and time based
+  /// fallbacks as [`FfmpegProgress::percent`]. Returns `None` until ffmpeg
+  /// reports a non-zero processing rate.
+  pub fn eta(
+    &self,
+    total_frames: Option<u32>,
+    total_duration: Option<Timestamp>,
+  ) -> Option<Duration> {
+    let seconds = match (self.frame, self.fps, total_frames) {
+      (Some(frame), Some(fps), Some(total)) if fps > 0.0 => {
+        total.saturating_sub(frame) as f64 / fps as f64
+      }
+      _ => {
+        let remaining = total_duration?.as_secs_f64() - self.out_time?.as_secs_f64();
+        match self.speed > 0.0 {
+          true => remaining / self.speed as f64,
+          false => return None,
+        }
+      }
+    };
+    Some(Duration::from_secs_f64(seconds.max(0.0)))
+  }
+
+  /// This update as a [`ProgressView`]: bounded if `total_duration` is
+  /// known, and unbounded otherwise, e.g. for a live input, where a
+  /// percentage can't be computed. `elapsed` is the wall clock time since the
+  /// job started. See
+  /// [`ProgressTracker`](crate::progress_view::ProgressTracker) to find out
+  /// the duration from the events.
+  ///
+  /// ```rust
+  /// use ffmpeg_sidecar::{
+  ///   log_parser::try_parse_progress, progress_view::ProgressView, timestamp::Timestamp,
+  /// };
+  /// use std::time::Duration;
+  ///
+  /// let line = "[info] frame=  125 fps= 25 q=28.0 size=     512kB time=00:00:05.00 bitrate= 838.9kbits/s speed=1x";
+  /// let progress = try_parse_progress(line).unwrap();
+  /// let elapsed = Duration::from_secs(5);
+  /// assert!(matches!(
+  ///   progress.view(Some(Timestamp::from_secs_f64(20.0)), elapsed),
+  ///   ProgressView::Bounded { percent, .. } if percent == 25.0
+  /// ));
+  /// assert!(matches!(
+  ///   progress.view(None, elapsed),
+  ///   ProgressView::Unbounded { frames: Some(125), size_kb: Some(512), .. }
+  /// ));
+  /// ```
+  pub fn view(&self, total_duration: Option<Timestamp>, elapsed: Duration) -> ProgressView {
+    match total_duration.filter(|total| *total > Timestamp::ZERO) {
+      Some(total) => ProgressView::Bounded {
+        percent: self.percent(None, Some(total)).unwrap_or(0.0),
+        eta: self.eta(None, Some(total)),
+        out_time: self.out_time,
+        total,
+        elapsed,
+      },
+      None => ProgressView::Unbounded {
+        elapsed,
+        out_time: self.out_time,
+        bitrate_kbps: self.bitrate_kbps,
+        size_kb: self.size_kb,
+        frames: self.frame,
+        fps: self.fps,
+      },
+    }
+  }
+}
+
+/// Consecutive log lines with the same level and component, or a line
+/// followed by its indented continuation lines, coalesced by a
+/// [`LogGrouper`](crate::log_group::LogGrouper).
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FfmpegLogGroup {
+  pub level: LogLevel,
+  /// The component which logged the lines, like `libx264`, if the first
+  /// line has one. See [`LogContext`].
+  pub component: Option<String>,
+  /// The lines, as in the `Log` events they replace.
+  pub lines: Vec<String>,
+  /// The lines joined with `\n`
+  pub raw_log_message: String,
+}
+
+/// `Command reply for stream 0: ret:0 res:`
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FfmpegFilterCommandReply {
+  /// Index of the filtergraph the command was sent to.
+  pub stream: u32,
+  /// `0` if a filter accepted the command, or a negative error code, e.g.
+  /// `AVERROR(ENOSYS)` when no filter matched the target or supports the
+  /// command.
+  pub ret: i32,
+  /// Text returned by the filter, usually empty.
+  pub response: String,
+  /// The line that this reply was parsed from
+  pub raw_log_message: String,
+}
+
+impl FfmpegFilterCommandReply {
+  pub fn is_success(&self) -> bool {
+    self.ret >= 0
+  }
+}
+
+/// `video:10240kB audio:320kB subtitle:0kB other streams:0kB global
+/// headers:0kB muxing overhead: 0.512%`
+///
+/// Sizes are in kibibytes, whether ffmpeg writes `kB` or (since 7.0) `KiB`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FfmpegEncodeSummary {
+  pub video_kb: f64,
+  pub audio_kb: f64,
+  pub subtitle_kb: f64,
+  pub other_kb: f64,
+  pub global_headers_kb: f64,
+  /// How much larger the output is than its streams, in percent, or `None`
+  /// when ffmpeg can't tell (`muxing overhead: unknown`), e.g. when writing
+  /// to a pipe.
+  pub muxing_overhead_pct: Option<f64>,
+  /// The line that this summary was parsed from
+  pub raw_log_message: String,
+}
+
+impl FfmpegEncodeSummary {
+  /// The total size of the streams, without the muxing overhead.
+  pub fn total_kb(&self) -> f64 {
+    self.video_kb + self.audio_kb + self.subtitle_kb + self.other_kb + self.global_headers_kb
+  }
+}
+
+/// The `frame I:12 Avg QP:18.50 size: 45678` lines of libx264, or the
+/// `frame I: 12, Avg QP:18.50 kb/s: 3456.78` lines of libx265.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EncoderStats {
+  /// `libx264` or `libx265`
+  pub encoder: String,
+  /// One entry per frame type, in the order they were logged.
+  pub frames: Vec<FrameTypeStats>,
+  pub raw_log_message: String,
+}
+
+impl EncoderStats {
+  /// Number of frames of the given type, like `'I'`, `'P'` or `'B'`.
+  pub fn count(&self, frame_type: char) -> u32 {
+    self
+      .frames
+      .iter()
+      .filter(|frames| frames.frame_type == frame_type)
+      .map(|frames| frames.count)
+      .sum()
+  }
+
+  pub fn total_frames(&self) -> u32 {
+    self.frames.iter().map(|frames| frames.count).sum()
+  }
+
+  /// Average QP over all frames, weighting each frame type by its count.
+  pub fn avg_qp(&self) -> Option<f64> {
+    let total = self.total_frames();
+    (total > 0).then(|| {
+      self
+        .frames
+        .iter()
+        .map(|frames| frames.avg_qp * f64::from(frames.count))
+        .sum::<f64>()
+        / f64::from(total)
+    })
+  }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FrameTypeStats {
+  pub frame_type: char,
+  pub count: u32,
+  pub avg_qp: f64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FfmpegSyncWarning {
+  pub kind: SyncWarning,
+  /// The line that this warning was parsed from
+  pub raw_log_message: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SyncWarning {
+  /// `Non-monotonous DTS in output stream 0:0; previous: 10, current: 9; ...`
+  ///
+  /// The muxer received a packet with a decode timestamp earlier than the
+  /// previous one, and had to rewrite it.
+  NonMonotonicDts {
+    /// Output stream specifier like `0:1`, if included in the message
+    stream: Option<String>,
+    previous: i64,
+    current: i64,
+  },
+  /// `Past duration 0.999992 too large`
+  ///
+  /// A frame arrived with a timestamp too far behind the expected output
+  /// frame rate, often followed by dropped frames.
+  PastDuration { duration: f64 },
+  /// Frames were dropped by the capture device before reaching ffmpeg, e.g.
+  /// dshow's `real-time buffer [...] too full or near too full (...)! frame dropped!`.
+  ///
+  /// Frames dropped by ffmpeg itself are counted in
+  /// [`FfmpegProgress::drop_frames`] instead.
+  FrameDropped { count: u32 },
+}
+
 #[derive(Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct OutputVideoFrame {
   /// The width of this video frame in pixels
   pub width: u32,
@@ -155,3 +700,36 @@ impl std::fmt::Debug for OutputVideoFrame {
 }
 
 // TODO fix the output for OutputChunk also
+
+/// An [`ExitStatus`] as the raw status of the platform, so it's read back
+/// the same: the wait status on Unix, which includes the signal, or the exit
+/// code on Windows.
+#[cfg(feature = "serde")]
+mod raw_exit_status {
+  use std::process::ExitStatus;
+
+  use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+  pub(super) fn serialize<S: Serializer>(
+    status: &ExitStatus,
+    serializer: S,
+  ) -> Result<S::Ok, S::Error> {
+    #[cfg(unix)]
+    let raw = i64::from(std::os::unix::process::ExitStatusExt::into_raw(*status));
+    #[cfg(windows)]
+    let raw = i64::from(status.code().unwrap_or_default() as u32);
+    raw.serialize(serializer)
+  }
+
+  pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+    deserializer: D,
+  ) -> Result<ExitStatus, D::Error> {
+    let raw = i64::deserialize(deserializer)?;
+    #[cfg(unix)]
+    return Ok(std::os::unix::process::ExitStatusExt::from_raw(raw as i32));
+    #[cfg(windows)]
+    return Ok(std::os::windows::process::ExitStatusExt::from_raw(
+      raw as u32,
+    ));
+  }
+}
@@ -0,0 +1,266 @@
+//! One-liners for extracting a single stream from a media file, like the audio
+//! track of a video.
+//!
+//! Each function probes the input first, so a missing stream is reported as
+//! an [`ExtractError`] before ffmpeg is spawned, and returns an
+//! [`FfmpegCommand`] ready to be spawned.
+//!
+//! ```rust,no_run
+//! use ffmpeg_sidecar::extract::{extract_audio, ExtractOptions};
+//!
+//! extract_audio("movie.mp4", "soundtrack.m4a", ExtractOptions::default())
+//!   .unwrap()
+//!   .spawn()
+//!   .unwrap()
+//!   .wait()
+//!   .unwrap();
+//! ```
+
+use std::fmt;
+#[cfg(feature = "process")]
+use std::path::Path;
+
+#[cfg(feature = "process")]
+use crate::{command::FfmpegCommand, probe::probe_cached};
+
+/// The type of a stream, as used in stream specifiers like `a:0`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StreamKind {
+  Video,
+  Audio,
+  Subtitle,
+  Data,
+  Attachment,
+}
+
+impl StreamKind {
+  /// The `codec_type` reported by ffprobe for this kind of stream.
+  pub fn codec_type(&self) -> &'static str {
+    match self {
+      StreamKind::Video => "video",
+      StreamKind::Audio => "audio",
+      StreamKind::Subtitle => "subtitle",
+      StreamKind::Data => "data",
+      StreamKind::Attachment => "attachment",
+    }
+  }
+
+  /// The letter used in stream specifiers (`v`, `a`, `s`, `d` or `t`).
+  pub fn specifier(&self) -> &'static str {
+    match self {
+      StreamKind::Video => "v",
+      StreamKind::Audio => "a",
+      StreamKind::Subtitle => "s",
+      StreamKind::Data => "d",
+      StreamKind::Attachment => "t",
+    }
+  }
+}
+
+/// Selects the `index`th stream of a given kind, e.g. the second audio track
+/// is `StreamSpec::new(StreamKind::Audio, 1)`, displayed as `a:1`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StreamSpec {
+  pub kind: StreamKind,
+  pub index: usize,
+}
+
+impl StreamSpec {
+  pub fn new(kind: StreamKind, index: usize) -> Self {
+    Self { kind, index }
+  }
+}
+
+impl fmt::Display for StreamSpec {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{}:{}", self.kind.specifier(), self.index)
+  }
+}
+
+/// How to encode the extracted stream.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum ExtractCodec {
+  /// Copy the stream if the output container supports its codec, and
+  /// otherwise transcode it to a sensible codec for the container.
+  #[default]
+  Auto,
+  /// Always copy the stream, failing if the container doesn't support it.
+  Copy,
+  /// Always transcode the stream with the given encoder.
+  Transcode(String),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ExtractOptions {
+  pub codec: ExtractCodec,
+  /// Index of the stream among those of the same kind, e.g. `1` for the
+  /// second audio track.
+  pub stream_index: usize,
+  /// Overwrite the output file if it exists (`-y`).
+  pub overwrite: bool,
+}
+
+/// Returned (through `anyhow::Error`) when the input can't be extracted from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExtractError {
+  /// The input doesn't have the requested stream.
+  StreamNotFound {
+    spec: StreamSpec,
+    /// Number of streams of the requested kind in the input
+    available: usize,
+  },
+}
+
+impl fmt::Display for ExtractError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      ExtractError::StreamNotFound { spec, available } => write!(
+        f,
+        "stream {spec} not found: the input has {available} {} stream(s)",
+        spec.kind.codec_type()
+      ),
+    }
+  }
+}
+
+impl std::error::Error for ExtractError {}
+
+/// Extract a single audio stream, e.g. to `.m4a`, `.mp3`, `.wav` or `.flac`.
+#[cfg(feature = "process")]
+pub fn extract_audio<I: AsRef<Path>, O: AsRef<Path>>(
+  input: I,
+  output: O,
+  options: ExtractOptions,
+) -> anyhow::Result<FfmpegCommand> {
+  let spec = StreamSpec::new(StreamKind::Audio, options.stream_index);
+  extract(input.as_ref(), spec, output.as_ref(), &options)
+}
+
+/// Extract a single video stream without any audio.
+#[cfg(feature = "process")]
+pub fn extract_video<I: AsRef<Path>, O: AsRef<Path>>(
+  input: I,
+  output: O,
+  options: ExtractOptions,
+) -> anyhow::Result<FfmpegCommand> {
+  let spec = StreamSpec::new(StreamKind::Video, options.stream_index);
+  extract(input.as_ref(), spec, output.as_ref(), &options)
+}
+
+/// Extract any single stream, including subtitles and data streams, copying
+/// it where possible.
+#[cfg(feature = "process")]
+pub fn extract_stream<I: AsRef<Path>, O: AsRef<Path>>(
+  input: I,
+  spec: StreamSpec,
+  output: O,
+) -> anyhow::Result<FfmpegCommand> {
+  let options = ExtractOptions {
+    stream_index: spec.index,
+    ..Default::default()
+  };
+  extract(input.as_ref(), spec, output.as_ref(), &options)
+}
+
+#[cfg(feature = "process")]
+fn extract(
+  input: &Path,
+  spec: StreamSpec,
+  output: &Path,
+  options: &ExtractOptions,
+) -> anyhow::Result<FfmpegCommand> {
+  let info = probe_cached(input)?;
+  let streams = info
+    .streams_of_type(spec.kind.codec_type())
+    .collect::<Vec<_>>();
+  let stream = streams
+    .get(spec.index)
+    .ok_or(ExtractError::StreamNotFound {
+      spec,
+      available: streams.len(),
+    })?;
+
+  let extension = output
+    .extension()
+    .map(|ext| ext.to_string_lossy().to_lowercase())
+    .unwrap_or_default();
+  let codec = match &options.codec {
+    ExtractCodec::Copy => "copy".to_string(),
+    ExtractCodec::Transcode(codec) => codec.clone(),
+    ExtractCodec::Auto => match container_supports(&extension, &stream.codec_name) {
+      true => "copy".to_string(),
+      false => default_codec(&extension, spec.kind)
+        .unwrap_or("copy")
+        .to_string(),
+    },
+  };
+
+  let mut command = FfmpegCommand::new();
+  if options.overwrite {
+    command.overwrite();
+  }
+  command
+    .input(input.to_string_lossy())
+    .map(format!("0:{spec}"))
+    .args([format!("-c:{}", spec.kind.specifier()), codec]);
+  match spec.kind {
+    StreamKind::Audio => command.no_video(),
+    StreamKind::Video => command.no_audio(),
+    _ => &mut command,
+  };
+  command.output(output.to_string_lossy());
+  Ok(command)
+}
+
+/// Whether the container for `extension` can hold `codec` without
+/// transcoding. Matroska holds anything; unknown extensions are assumed to
+/// hold nothing, so that they're transcoded to their default codec.
+#[cfg(feature = "process")]
+fn container_supports(extension: &str, codec: &str) -> bool {
+  match extension {
+    "mkv" | "mka" | "mks" | "nut" => true,
+    "m4a" => matches!(codec, "aac" | "alac"),
+    "mp4" | "mov" | "m4v" => matches!(
+      codec,
+      "aac" | "alac" | "mp3" | "ac3" | "eac3" | "h264" | "hevc" | "av1" | "mpeg4" | "mov_text"
+    ),
+    "aac" => codec == "aac",
+    "mp3" => codec == "mp3",
+    "flac" => codec == "flac",
+    "wav" => codec.starts_with("pcm_"),
+    "ogg" | "oga" => matches!(codec, "vorbis" | "opus" | "flac"),
+    "opus" => codec == "opus",
+    "webm" => matches!(codec, "vorbis" | "opus" | "vp8" | "vp9" | "av1" | "webvtt"),
+    "ts" => matches!(
+      codec,
+      "aac" | "mp3" | "ac3" | "h264" | "hevc" | "mpeg2video"
+    ),
+    "srt" => codec == "subrip",
+    "vtt" => codec == "webvtt",
+    "ass" | "ssa" => codec == "ass",
+    _ => false,
+  }
+}
+
+/// A reasonable encoder for a stream of `kind` in the container for
+/// `extension`, when the source codec can't be copied.
+#[cfg(feature = "process")]
+fn default_codec(extension: &str, kind: StreamKind) -> Option<&'static str> {
+  match (kind, extension) {
+    (StreamKind::Audio, "m4a" | "mp4" | "mov" | "m4v" | "aac" | "ts" | "mkv" | "mka") => {
+      Some("aac")
+    }
+    (StreamKind::Audio, "mp3") => Some("libmp3lame"),
+    (StreamKind::Audio, "wav") => Some("pcm_s16le"),
+    (StreamKind::Audio, "flac") => Some("flac"),
+    (StreamKind::Audio, "ogg" | "oga") => Some("libvorbis"),
+    (StreamKind::Audio, "opus" | "webm") => Some("libopus"),
+    (StreamKind::Video, "mp4" | "mov" | "m4v" | "mkv" | "ts") => Some("libx264"),
+    (StreamKind::Video, "webm") => Some("libvpx-vp9"),
+    (StreamKind::Subtitle, "srt") => Some("srt"),
+    (StreamKind::Subtitle, "vtt" | "webm") => Some("webvtt"),
+    (StreamKind::Subtitle, "ass" | "ssa") => Some("ass"),
+    (StreamKind::Subtitle, "mp4" | "mov" | "m4v") => Some("mov_text"),
+    _ => None,
+  }
+}
@@ -0,0 +1,115 @@
+//! MP4 files which can start playing before they're fully downloaded. See
+//! [`FfmpegCommand::web_optimized`](crate::command::FfmpegCommand::web_optimized).
+//!
+//! The MP4 muxer writes the index of the file (the `moov` atom) at the end by
+//! default, once the size of every frame is known, so a player has to load
+//! the whole file before starting. `-movflags +faststart` moves it to the
+//! front in a second pass over the file, which needs a seekable output.
+//! Pipes and network outputs get a fragmented MP4 instead, with a short
+//! index up front and one per fragment.
+
+#[cfg(feature = "process")]
+use std::{ffi::OsString, fs, path::Path};
+
+#[cfg(feature = "process")]
+use crate::{command::FfmpegCommand, queue::run_job};
+
+/// The movflags moving the index to the front of a file.
+pub const FASTSTART_MOVFLAGS: &str = "+faststart";
+
+/// The movflags writing a fragmented MP4 which doesn't need seeking, starting
+/// a fragment at each keyframe.
+pub const STREAMING_MOVFLAGS: &str = "+frag_keyframe+empty_moov+default_base_moof";
+
+/// Whether an output can't be seeked, so `+faststart` is impossible: stdout,
+/// pipes, and URLs of protocols other than `file:`.
+///
+/// ```rust
+/// use ffmpeg_sidecar::faststart::is_streaming_output;
+///
+/// assert!(is_streaming_output("-"));
+/// assert!(is_streaming_output("pipe:1"));
+/// assert!(is_streaming_output("rtmp://live.example.com/app/key"));
+/// assert!(!is_streaming_output("out.mp4"));
+/// assert!(!is_streaming_output("file:out.mp4"));
+/// assert!(!is_streaming_output(r"C:\videos\out.mp4"));
+/// ```
+pub fn is_streaming_output(output: &str) -> bool {
+  if matches!(output, "-" | "/dev/stdout" | "/dev/stderr") {
+    return true;
+  }
+  match output.split_once(':') {
+    // A drive letter rather than a protocol
+    Some((protocol, _)) if protocol.len() == 1 => false,
+    Some((protocol, rest)) => {
+      protocol != "file"
+        && (rest.starts_with("//") || matches!(protocol, "pipe" | "fd"))
+        && protocol
+          .chars()
+          .all(|c| c.is_ascii_alphanumeric() || c == '+' || c == '-' || c == '.')
+    }
+    None => false,
+  }
+}
+
+/// The movflags for an MP4 written to `output`, which can start playing
+/// right away.
+///
+/// ```rust
+/// use ffmpeg_sidecar::faststart::web_movflags;
+///
+/// assert_eq!(web_movflags("out.mp4"), "+faststart");
+/// assert_eq!(web_movflags("-"), "+frag_keyframe+empty_moov+default_base_moof");
+/// ```
+pub fn web_movflags(output: &str) -> &'static str {
+  match is_streaming_output(output) {
+    true => STREAMING_MOVFLAGS,
+    false => FASTSTART_MOVFLAGS,
+  }
+}
+
+/// Move the index of an existing MP4 or MOV file to the front, by remuxing
+/// it without re-encoding. The result is written next to the file, then
+/// renamed over it, so the file is never left half written.
+///
+/// ```rust,no_run
+/// use ffmpeg_sidecar::faststart::faststart_in_place;
+///
+/// faststart_in_place("upload.mp4").unwrap();
+/// ```
+#[cfg(feature = "process")]
+pub fn faststart_in_place<P: AsRef<Path>>(path: P) -> anyhow::Result<()> {
+  let path = path.as_ref();
+  let (Some(stem), Some(extension)) = (path.file_stem(), path.extension()) else {
+    anyhow::bail!("{} needs an extension to pick the muxer", path.display());
+  };
+  // Same directory, so the rename doesn't cross filesystems
+  let mut temp_name = OsString::from(".");
+  temp_name.push(stem);
+  temp_name.push(".faststart.");
+  temp_name.push(extension);
+  let temp = path.with_file_name(temp_name);
+
+  let mut command = FfmpegCommand::new();
+  command
+    .input(path.to_string_lossy())
+    .args(["-map", "0", "-c", "copy", "-map_metadata", "0"])
+    .args(["-movflags", FASTSTART_MOVFLAGS])
+    .overwrite()
+    .output(temp.to_string_lossy());
+  let outcome = run_job(&mut command, |_| {});
+  if !outcome.is_success() {
+    fs::remove_file(&temp).ok();
+    let message = match outcome.result {
+      Err(e) => e.to_string(),
+      Ok(status) if outcome.errors.is_empty() => format!("ffmpeg exited with {status}"),
+      Ok(_) => outcome.errors.join("\n"),
+    };
+    anyhow::bail!("remuxing {} failed: {message}", path.display());
+  }
+  if let Err(e) = fs::rename(&temp, path) {
+    fs::remove_file(&temp).ok();
+    return Err(e.into());
+  }
+  Ok(())
+}
@@ -0,0 +1,255 @@
+//! Optional support for spawning `ffplay`, mostly useful for previewing the
+//! output of a filter chain or an ffmpeg pipe during development.
+//!
+//! Enabled by the `ffplay` cargo feature.
+
+use std::{
+  env::current_exe,
+  ffi::OsStr,
+  fmt, io,
+  path::{Path, PathBuf},
+  process::{Child, ChildStdin, Command, ExitStatus, Stdio},
+};
+
+use anyhow::Context;
+
+use crate::{
+  command::force_nocolor,
+  orphans::{owner_marker, OWNER_ENV},
+  spawner,
+};
+
+/// Returns the path of the downloaded FFplay executable, or falls back to
+/// assuming its installed in the system path. Note that not all FFmpeg
+/// distributions include FFplay; in particular the Linux static builds do not.
+pub fn ffplay_path() -> PathBuf {
+  let default = Path::new("ffplay").to_path_buf();
+  match ffplay_sidecar_path() {
+    Ok(sidecar_path) => match sidecar_path.exists() {
+      true => sidecar_path,
+      false => default,
+    },
+    Err(_) => default,
+  }
+}
+
+/// The (expected) path to an FFplay binary adjacent to the Rust binary.
+///
+/// The extension between platforms, with Windows using `.exe`, while Mac and
+/// Linux have no extension.
+pub fn ffplay_sidecar_path() -> anyhow::Result<PathBuf> {
+  let mut path = current_exe()?
+    .parent()
+    .context("Can't get parent of current_exe")?
+    .join("ffplay");
+  if cfg!(windows) {
+    path.set_extension("exe");
+  }
+  Ok(path)
+}
+
+/// Verify whether ffplay is installed on the system. This will return true if
+/// there is an ffplay binary in the PATH, or in the same directory as the Rust
+/// executable.
+pub fn ffplay_is_installed() -> bool {
+  spawner::output(
+    Command::new(ffplay_path())
+      .arg("-version")
+      .stdin(Stdio::null()),
+  )
+  .map(|output| output.status.success())
+  .unwrap_or_else(|_| false)
+}
+
+/// A minimal wrapper around [`std::process::Command`] for launching `ffplay`.
+///
+/// ```rust,no_run
+/// use ffmpeg_sidecar::ffplay::FfplayCommand;
+///
+/// let mut ffplay = FfplayCommand::new()
+///   .window_title("preview")
+///   .video_filter("hflip")
+///   .autoexit()
+///   .input("input.mp4")
+///   .spawn()
+///   .unwrap();
+/// ffplay.wait().unwrap();
+/// ```
+pub struct FfplayCommand {
+  inner: Command,
+}
+
+impl FfplayCommand {
+  /// Alias for `-i` argument, the input file path or URL.
+  ///
+  /// To take input from stdin, use [`FfplayCommand::pipe_stdin`] instead.
+  pub fn input<S: AsRef<str>>(&mut self, path_or_url: S) -> &mut Self {
+    self.arg("-i");
+    self.arg(path_or_url.as_ref());
+    self
+  }
+
+  /// Alias for `-window_title` argument.
+  ///
+  /// Set the window title (default is the input filename).
+  pub fn window_title<S: AsRef<str>>(&mut self, title: S) -> &mut Self {
+    self.arg("-window_title");
+    self.arg(title.as_ref());
+    self
+  }
+
+  /// Alias for `-autoexit` argument.
+  ///
+  /// Exit when the input is done playing, instead of leaving the window open
+  /// on the last frame.
+  pub fn autoexit(&mut self) -> &mut Self {
+    self.arg("-autoexit");
+    self
+  }
+
+  /// Alias for `-loop` argument.
+  ///
+  /// Loops playback `count` times. `0` means loop forever.
+  pub fn loop_count(&mut self, count: u32) -> &mut Self {
+    self.arg("-loop");
+    self.arg(count.to_string());
+    self
+  }
+
+  /// Alias for `-vf` argument.
+  ///
+  /// Apply `filtergraph` to the video before displaying it, which makes it a
+  /// quick way to preview a filter chain before committing to an encode.
+  pub fn video_filter<S: AsRef<str>>(&mut self, filtergraph: S) -> &mut Self {
+    self.arg("-vf");
+    self.arg(filtergraph.as_ref());
+    self
+  }
+
+  /// Alias for `-af` argument.
+  ///
+  /// Apply `filtergraph` to the audio before playing it.
+  pub fn audio_filter<S: AsRef<str>>(&mut self, filtergraph: S) -> &mut Self {
+    self.arg("-af");
+    self.arg(filtergraph.as_ref());
+    self
+  }
+
+  /// Configure ffplay to read its input from stdin.
+  ///
+  /// Synchronizes two changes:
+  /// 1. Pass `-i -` to the ffplay command ("input from stdin")
+  /// 2. Set the `stdin` field of the inner `Command` to `Stdio::piped()`
+  ///
+  /// The stdin channel can then be obtained with [`FfplayChild::take_stdin`],
+  /// for example to forward the stdout of a running `FfmpegChild`.
+  pub fn pipe_stdin(&mut self) -> &mut Self {
+    self.args(["-i", "-"]);
+    self.inner.stdin(Stdio::piped());
+    self
+  }
+
+  /// Adds an argument to pass to the program.
+  ///
+  /// Identical to `arg` in [`std::process::Command`].
+  pub fn arg<S: AsRef<OsStr>>(&mut self, arg: S) -> &mut Self {
+    self.inner.arg(arg.as_ref());
+    self
+  }
+
+  /// Adds multiple arguments to pass to the program.
+  ///
+  /// Identical to `args` in [`std::process::Command`].
+  pub fn args<I, S>(&mut self, args: I) -> &mut Self
+  where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+  {
+    for arg in args {
+      self.arg(arg.as_ref());
+    }
+    self
+  }
+
+  /// Spawn the ffplay command as a child process.
+  ///
+  /// Identical to `spawn` in [`std::process::Command`].
+  pub fn spawn(&mut self) -> io::Result<FfplayChild> {
+    self.inner.env(OWNER_ENV, owner_marker());
+    force_nocolor(&mut self.inner);
+    self.inner.spawn().map(|inner| FfplayChild { inner })
+  }
+
+  //// Constructors
+  pub fn new() -> Self {
+    Self::new_with_path(ffplay_path())
+  }
+
+  pub fn new_with_path<S: AsRef<OsStr>>(path_to_ffplay_binary: S) -> Self {
+    let mut inner = Command::new(&path_to_ffplay_binary);
+    inner.stdin(Stdio::null());
+    Self { inner }
+  }
+
+  //// Escape hatches
+
+  /// Escape hatch to access the inner `Command`.
+  pub fn as_inner(&mut self) -> &Command {
+    &self.inner
+  }
+
+  /// Escape hatch to mutably access the inner `Command`.
+  pub fn as_inner_mut(&mut self) -> &mut Command {
+    &mut self.inner
+  }
+}
+
+impl Default for FfplayCommand {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl fmt::Debug for FfplayCommand {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    self.inner.fmt(f)
+  }
+}
+
+/// A wrapper around [`std::process::Child`] containing a spawned ffplay
+/// process.
+pub struct FfplayChild {
+  inner: Child,
+}
+
+impl FfplayChild {
+  /// Take ownership of the process' stdin channel, if it was configured with
+  /// [`FfplayCommand::pipe_stdin`].
+  pub fn take_stdin(&mut self) -> Option<ChildStdin> {
+    self.inner.stdin.take()
+  }
+
+  /// Forcibly terminate the inner child process.
+  ///
+  /// Identical to `kill` in [`std::process::Child`].
+  pub fn kill(&mut self) -> io::Result<()> {
+    self.inner.kill()
+  }
+
+  /// Waits for the inner child process to finish execution.
+  ///
+  /// Identical to `wait` in [`std::process::Child`].
+  pub fn wait(&mut self) -> io::Result<ExitStatus> {
+    self.inner.wait()
+  }
+
+  /// Escape hatch to access the inner `Child`.
+  pub fn as_inner(&mut self) -> &Child {
+    &self.inner
+  }
+
+  /// Escape hatch to mutably access the inner `Child`.
+  pub fn as_inner_mut(&mut self) -> &mut Child {
+    &mut self.inner
+  }
+}
@@ -1,26 +1,98 @@
-use std::{env::current_exe, ffi::OsStr, path::PathBuf};
+use std::{collections::HashSet, env::current_exe, ffi::OsStr, path::PathBuf};
 use std::{
   path::Path,
   process::{Command, Stdio},
+  sync::{Mutex, OnceLock},
 };
 
 use anyhow::Context;
 
-/// Returns the path of the downloaded FFprobe executable, or falls back to
-/// assuming its installed in the system path. Note that not all FFmpeg
+use crate::{
+  color::ColorMetadata, defaults::FfmpegDefaults, rotation::normalize_rotation, spawner,
+};
+
+/// Returns the path of the FFprobe executable used by every function of the
+/// crate running it, like [`ffmpeg_path`](crate::paths::ffmpeg_path) for
+/// FFmpeg: the [`FfmpegDefaults::ffprobe_binary_path`] if set, or else the
+/// downloaded binary adjacent to the Rust executable, or else `ffprobe`,
+/// expecting it to be in the system path. Note that not all FFmpeg
 /// distributions include FFprobe.
+///
+/// ```rust
+/// use ffmpeg_sidecar::{defaults::FfmpegDefaults, ffprobe::ffprobe_path};
+/// use std::path::Path;
+///
+/// FfmpegDefaults::set(FfmpegDefaults {
+///   ffprobe_binary_path: Some("/opt/ffmpeg/bin/ffprobe".into()),
+///   ..Default::default()
+/// });
+/// assert_eq!(ffprobe_path(), Path::new("/opt/ffmpeg/bin/ffprobe"));
+/// ```
 pub fn ffprobe_path() -> PathBuf {
-  let default = Path::new("ffprobe").to_path_buf();
+  if let Some(path) = FfmpegDefaults::get().ffprobe_binary_path {
+    return path;
+  }
   match ffprobe_sidecar_path() {
-    Ok(sidecar_path) => match sidecar_path.exists() {
-      true => sidecar_path,
-      false => default,
-    },
-    Err(_) => default,
+    Ok(sidecar_path) => ffprobe_path_with_sidecar(&sidecar_path),
+    Err(_) => Path::new("ffprobe").to_path_buf(),
+  }
+}
+
+/// Lower level variant of `ffprobe_path` that exposes a customized path to
+/// the sidecar binary. As for ffmpeg, the sidecar is only used if it
+/// actually runs (see [`ffprobe_is_installed_at`]), so that a truncated
+/// binary doesn't shadow a working ffprobe in the PATH.
+pub fn ffprobe_path_with_sidecar(sidecar_path: &Path) -> PathBuf {
+  match sidecar_path.exists() && ffprobe_is_installed_at(sidecar_path) {
+    true => sidecar_path.to_path_buf(),
+    false => Path::new("ffprobe").to_path_buf(),
+  }
+}
+
+/// Verify whether ffprobe is installed on the system. This will return true if
+/// there is a working ffprobe binary at the [`ffprobe_path`]: set in the
+/// defaults, in the same directory as the Rust executable, or in the PATH.
+pub fn ffprobe_is_installed() -> bool {
+  ffprobe_is_installed_at(ffprobe_path())
+}
+
+/// The paths of the binaries which ran as ffprobe, see
+/// [`ffprobe_is_installed_at`].
+static VERIFIED_FFPROBE: OnceLock<Mutex<HashSet<PathBuf>>> = OnceLock::new();
+
+/// Verify whether the binary at `path` is a working ffprobe: `-version` must
+/// exit successfully and print a version number.
+///
+/// Successful checks are cached per path for the lifetime of the process, or
+/// until the installer of the `download` feature writes to the path; failed
+/// ones are repeated on the next call.
+pub fn ffprobe_is_installed_at<S: AsRef<OsStr>>(path: S) -> bool {
+  let path = PathBuf::from(path.as_ref());
+  let verified = VERIFIED_FFPROBE.get_or_init(Default::default);
+  if verified
+    .lock()
+    .is_ok_and(|verified| verified.contains(&path))
+  {
+    return true;
+  }
+
+  let installed = ffprobe_version_with_path(&path).is_ok();
+  if let (true, Ok(mut verified)) = (installed, verified.lock()) {
+    verified.insert(path);
   }
+  installed
 }
 
-/// The (expected) path to an FFmpeg binary adjacent to the Rust binary.
+/// Forget the check of the binary at `path`, which was just replaced, so the
+/// new one is run again before being used.
+#[cfg(feature = "download")]
+pub(crate) fn forget_verified_ffprobe(path: &Path) {
+  if let Ok(mut verified) = VERIFIED_FFPROBE.get_or_init(Default::default).lock() {
+    verified.remove(path);
+  }
+}
+
+/// The (expected) path to an FFprobe binary adjacent to the Rust binary.
 ///
 /// The extension between platforms, with Windows using `.exe`, while Mac and
 /// Linux have no extension.
@@ -41,24 +113,117 @@ pub fn ffprobe_version() -> anyhow::Result<String> {
 }
 
 /// Lower level variant of `ffprobe_version` that exposes a customized the path
-/// to the ffmpeg binary.
+/// to the ffprobe binary.
 pub fn ffprobe_version_with_path<S: AsRef<OsStr>>(path: S) -> anyhow::Result<String> {
-  let output = Command::new(&path).arg("-version").output()?;
+  let output = spawner::output(Command::new(&path).arg("-version").stdin(Stdio::null()))?;
+  if !output.status.success() {
+    anyhow::bail!("ffprobe -version exited with non-zero status");
+  }
+  String::from_utf8_lossy(&output.stdout)
+    .lines()
+    .find_map(|line| line.trim().strip_prefix("ffprobe version "))
+    .and_then(|rest| rest.split_whitespace().next())
+    .map(str::to_string)
+    .context("Failed to parse ffprobe version")
+}
+
+/// Read the display rotation of the first video stream of `input`, in
+/// degrees clockwise (`0`, `90`, `180` or `270`).
+///
+/// Checks the display matrix side data first, then the older `rotate` tag.
+pub fn ffprobe_rotation<S: AsRef<OsStr>>(input: S) -> anyhow::Result<u32> {
+  let output = spawner::output(
+    Command::new(ffprobe_path())
+      .args(["-v", "error", "-select_streams", "v:0", "-show_entries"])
+      .arg("stream_side_data=rotation:stream_tags=rotate")
+      .args(["-of", "default=noprint_wrappers=1"])
+      .arg(input.as_ref())
+      .stdin(Stdio::null()),
+  )?;
+  if !output.status.success() {
+    anyhow::bail!(
+      "ffprobe failed: {}",
+      String::from_utf8_lossy(&output.stderr).trim()
+    );
+  }
+  Ok(parse_rotation_entries(&String::from_utf8(output.stdout)?))
+}
 
-  // note:version parsing is not implemented for ffprobe
+/// Timestamps in seconds of the keyframes of the first video stream of
+/// `input`, between `from` and `to` seconds. Only packets are read, so this is
+/// fast even for long inputs, but the range may include an extra keyframe on
+/// either side since seeking snaps to keyframes.
+pub fn ffprobe_keyframes<S: AsRef<OsStr>>(
+  input: S,
+  from: f64,
+  to: f64,
+) -> anyhow::Result<Vec<f64>> {
+  let output = spawner::output(
+    Command::new(ffprobe_path())
+      .args(["-v", "error", "-select_streams", "v:0", "-show_entries"])
+      .arg("packet=pts_time,flags")
+      .args(["-of", "csv=p=0", "-read_intervals"])
+      .arg(format!("{from}%{to}"))
+      .arg(input.as_ref())
+      .stdin(Stdio::null()),
+  )?;
+  if !output.status.success() {
+    anyhow::bail!(
+      "ffprobe failed: {}",
+      String::from_utf8_lossy(&output.stderr).trim()
+    );
+  }
+  Ok(parse_keyframe_entries(&String::from_utf8(output.stdout)?))
+}
 
-  Ok(String::from_utf8(output.stdout)?)
+/// Color properties of the first video stream of `input`, including the HDR
+/// mastering display and content light level side data, which is read from
+/// the stream or its first frame.
+pub fn ffprobe_color_metadata<S: AsRef<OsStr>>(input: S) -> anyhow::Result<ColorMetadata> {
+  let output = spawner::output(
+    Command::new(ffprobe_path())
+      .args(["-v", "error", "-select_streams", "v:0", "-show_streams"])
+      .args(["-show_frames", "-read_intervals", "%+#1"])
+      .arg(input.as_ref())
+      .stdin(Stdio::null()),
+  )?;
+  if !output.status.success() {
+    anyhow::bail!(
+      "ffprobe failed: {}",
+      String::from_utf8_lossy(&output.stderr).trim()
+    );
+  }
+  Ok(ColorMetadata::parse_ffprobe(&String::from_utf8_lossy(
+    &output.stdout,
+  )))
 }
 
-/// Verify whether ffprobe is installed on the system. This will return true if
-/// there is an ffprobe binary in the PATH, or in the same directory as the Rust
-/// executable.
-pub fn ffprobe_is_installed() -> bool {
-  Command::new(ffprobe_path())
-    .arg("-version")
-    .stderr(Stdio::null())
-    .stdout(Stdio::null())
-    .status()
-    .map(|s| s.success())
-    .unwrap_or_else(|_| false)
+/// Parse the `pts_time,flags` lines of [`ffprobe_keyframes`], keeping those
+/// flagged as keyframes (`K`), in ascending order.
+fn parse_keyframe_entries(output: &str) -> Vec<f64> {
+  let mut keyframes = output
+    .lines()
+    .filter_map(|line| line.trim().split_once(','))
+    .filter(|(_, flags)| flags.starts_with('K'))
+    .filter_map(|(time, _)| time.parse::<f64>().ok())
+    .collect::<Vec<_>>();
+  keyframes.sort_by(f64::total_cmp);
+  keyframes.dedup();
+  keyframes
+}
+
+/// Parse the `key=value` output of [`ffprobe_rotation`]. The display matrix
+/// `rotation` is counter-clockwise, while the `rotate` tag is clockwise.
+fn parse_rotation_entries(output: &str) -> u32 {
+  let value = |key: &str| {
+    output
+      .lines()
+      .find_map(|line| line.trim().strip_prefix(key))
+      .and_then(|value| value.parse::<f64>().ok())
+  };
+  match (value("rotation="), value("TAG:rotate=")) {
+    (Some(ccw), _) => normalize_rotation(-ccw),
+    (None, Some(cw)) => normalize_rotation(cw),
+    (None, None) => 0,
+  }
 }
@@ -0,0 +1,69 @@
+//! Changing filter parameters while ffmpeg is running, with the interactive
+//! `c` command. See [`FfmpegChild::send_filter_command`](crate::child::FfmpegChild::send_filter_command).
+
+use std::fmt;
+
+/// Returned (through `anyhow::Error`) when a filter command can't be sent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FilterCommandError {
+  /// Stdin was taken, e.g. by `take_stdin` or `feed_stdin`.
+  StdinTaken,
+  /// An input is read from stdin (`-i -` or `pipe:`), so ffmpeg doesn't
+  /// listen for interactive commands.
+  StdinIsInput,
+  /// The target or command is empty or contains whitespace, or the argument
+  /// contains a line break.
+  InvalidArgument(String),
+}
+
+impl fmt::Display for FilterCommandError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      FilterCommandError::StdinTaken => f.write_str("stdin was already taken"),
+      FilterCommandError::StdinIsInput => {
+        f.write_str("stdin is used as an input, so ffmpeg doesn't accept interactive commands")
+      }
+      FilterCommandError::InvalidArgument(message) => {
+        write!(f, "invalid filter command: {message}")
+      }
+    }
+  }
+}
+
+impl std::error::Error for FilterCommandError {}
+
+/// Format the keys sent to ffmpeg for a `c` command applied immediately
+/// (at time `-1`), e.g. `c\ndrawtext -1 reinit text=hello\n`.
+pub fn format_filter_command(
+  target: &str,
+  command: &str,
+  arg: &str,
+) -> Result<String, FilterCommandError> {
+  // ffmpeg reads the line with `sscanf("%63[^ ] %lf %255[^ ] %255[^\n]")`
+  for (name, value, max_len) in [("target", target, 63), ("command", command, 255)] {
+    if value.is_empty() || value.contains(char::is_whitespace) {
+      return Err(FilterCommandError::InvalidArgument(format!(
+        "{name} must be a single word, got {value:?}"
+      )));
+    }
+    if value.len() > max_len {
+      return Err(FilterCommandError::InvalidArgument(format!(
+        "{name} is longer than {max_len} bytes"
+      )));
+    }
+  }
+  if arg.contains(['\n', '\r']) || arg.len() > 255 {
+    return Err(FilterCommandError::InvalidArgument(
+      "argument must be a single line of at most 255 bytes".to_string(),
+    ));
+  }
+
+  Ok(format!("c\n{target} -1 {command} {arg}\n"))
+}
+
+/// Whether ffmpeg reads from stdin for this input path, which disables its
+/// interactive commands.
+#[cfg(feature = "process")]
+pub(crate) fn is_stdin_input(input: &str) -> bool {
+  input == "-" || input == "fd:" || input == "/dev/stdin" || input.starts_with("pipe:")
+}
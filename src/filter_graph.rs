@@ -0,0 +1,309 @@
+//! The filter graphs ffmpeg actually set up, with the conversions it
+//! inserted and the formats it negotiated, parsed from its `verbose` log.
+//! See [`FfmpegCommand::dump_filter_graph`](crate::command::FfmpegCommand::dump_filter_graph).
+
+use crate::{log_parser::try_parse_log_context, stderr_policy::Verbosity};
+
+/// A filter of a [`FilterGraphDump`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FilterNode {
+  /// The name of the filter instance, like `Parsed_scale_0` for the first
+  /// filter of a graph written as `scale=...`, `auto_scale_0` for a
+  /// conversion inserted by ffmpeg, or `graph 0 input from stream 0:0`.
+  pub name: String,
+  /// The address in the log prefix, telling apart instances with the same
+  /// name in different graphs. `None` for filters which didn't log anything
+  /// themselves, only named by another line.
+  pub pointer: Option<String>,
+  /// The filter, like `scale`, guessed from the name for the filters ffmpeg
+  /// adds itself, like `buffer` for the inputs of a graph. Empty when the
+  /// name doesn't tell.
+  pub filter: String,
+  /// The first line the filter logged when it was set up, which for most
+  /// filters shows their options, like `w:640 h:360 flags:'' interl:0`.
+  pub args: Option<String>,
+  /// The properties of the frames it receives, as logged once negotiated,
+  /// like `w:1920 h:1080 fmt:yuv420p sar:1/1`.
+  pub in_pads: Vec<String>,
+  /// The properties of the frames it sends, like [`in_pads`](Self::in_pads).
+  pub out_pads: Vec<String>,
+  /// The pixel or sample formats found in the lines, in order, like
+  /// `["rgb24", "yuv420p"]` for a conversion.
+  pub formats: Vec<String>,
+  /// Inserted by ffmpeg to convert between filters which have no format in
+  /// common, like `auto_scale_0` or `auto_aresample_0`.
+  pub auto_inserted: bool,
+}
+
+/// A link between two filters of a [`FilterGraphDump`], by their indices in
+/// [`nodes`](FilterGraphDump::nodes).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FilterEdge {
+  pub from: usize,
+  pub to: usize,
+}
+
+/// The filters ffmpeg set up, parsed from the lines it logs at the
+/// `verbose` level while configuring its filter graphs.
+///
+/// ffmpeg only logs the links where it inserts a conversion, so those are
+/// the [`edges`](Self::edges); the other links are those of the filter
+/// graph as written. Filters which log nothing, like `null` or `format`,
+/// only appear when a conversion is inserted next to them.
+///
+/// ```rust
+/// use ffmpeg_sidecar::filter_graph::FilterGraphDump;
+///
+/// let log = "\
+///   [graph 0 input from stream 0:0 @ 0x6000] [verbose] w:64 h:48 pixfmt:rgb24 tb:1/10 fr:10/1 sar:1/1\n\
+///   [auto_scale_0 @ 0x6100] [verbose] w:iw h:ih flags:'' interl:0\n\
+///   [Parsed_format_1 @ 0x6200] [verbose] auto-inserting filter 'auto_scale_0' between the filter 'Parsed_format_0' and the filter 'Parsed_format_1'\n\
+///   [auto_scale_0 @ 0x6100] [verbose] w:64 h:48 fmt:rgb24 sar:1/1 -> w:64 h:48 fmt:yuv420p sar:1/1 flags:0x00000004\n\
+///   [libx264 @ 0x6300] [verbose] using mv_range_thread = 24\n";
+/// let dump = FilterGraphDump::parse(log);
+/// let names = dump.nodes.iter().map(|node| node.name.as_str()).collect::<Vec<_>>();
+/// assert_eq!(
+///   names,
+///   ["graph 0 input from stream 0:0", "auto_scale_0", "Parsed_format_1", "Parsed_format_0"]
+/// );
+/// let scale = &dump.nodes[1];
+/// assert!(scale.auto_inserted);
+/// assert_eq!(scale.filter, "scale");
+/// assert_eq!(scale.formats, ["rgb24", "yuv420p"]);
+/// assert_eq!(dump.nodes[0].filter, "buffer");
+/// assert_eq!(dump.links().collect::<Vec<_>>(), [
+///   ("Parsed_format_0", "auto_scale_0"),
+///   ("auto_scale_0", "Parsed_format_1"),
+/// ]);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FilterGraphDump {
+  pub nodes: Vec<FilterNode>,
+  pub edges: Vec<FilterEdge>,
+}
+
+impl FilterGraphDump {
+  /// Parse the filter lines of a log, ignoring the others.
+  pub fn parse(log: &str) -> Self {
+    let mut dump = Self::default();
+    for line in log.lines() {
+      dump.push_line(line.trim());
+    }
+    dump
+  }
+
+  /// Add a line of the log, if it was logged by a filter at the `verbose`
+  /// level, returning whether it was.
+  pub fn push_line(&mut self, line: &str) -> bool {
+    if Verbosity::of_line(line) != Some(Verbosity::Verbose) {
+      return false;
+    }
+    let Some(context) = try_parse_log_context(line) else {
+      return false;
+    };
+    let Some(message) = message_of(line) else {
+      return false;
+    };
+
+    if let Some((inserted, from, to)) = parse_insertion(message) {
+      let to = self.node(to, context.pointer);
+      let inserted = self.named_node(inserted);
+      let from = self.named_node(from);
+      self.nodes[inserted].auto_inserted = true;
+      self.edges.retain(|edge| (edge.from, edge.to) != (from, to));
+      for (from, to) in [(from, inserted), (inserted, to)] {
+        let edge = FilterEdge { from, to };
+        if !self.edges.contains(&edge) {
+          self.edges.push(edge);
+        }
+      }
+      return true;
+    }
+
+    if filter_of(context.component).is_none()
+      && !self.nodes.iter().any(|node| node.name == context.component)
+    {
+      return false;
+    }
+    let index = self.node(context.component, context.pointer);
+    let node = &mut self.nodes[index];
+    for format in message.split_whitespace().filter_map(|word| {
+      ["fmt:", "pixfmt:", "samplefmt:"]
+        .iter()
+        .find_map(|key| word.strip_prefix(key))
+    }) {
+      if !node.formats.iter().any(|known| known == format) {
+        node.formats.push(format.to_string());
+      }
+    }
+    match message.split_once(" -> ") {
+      Some((input, output)) => {
+        node.in_pads.push(input.trim().to_string());
+        node.out_pads.push(output.trim().to_string());
+      }
+      None if node.args.is_none() => {
+        if node.filter == "buffer" {
+          if message.contains("samplefmt:") {
+            node.filter = "abuffer".to_string();
+          }
+          node.out_pads.push(message.to_string());
+        }
+        node.args = Some(message.to_string());
+      }
+      None => {}
+    }
+    true
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.nodes.is_empty()
+  }
+
+  /// The names of the filters of each edge.
+  pub fn links(&self) -> impl Iterator<Item = (&str, &str)> {
+    self.edges.iter().map(|edge| {
+      (
+        self.nodes[edge.from].name.as_str(),
+        self.nodes[edge.to].name.as_str(),
+      )
+    })
+  }
+
+  /// The graph in the DOT language of Graphviz, with the inserted
+  /// conversions in red, e.g. to render it with `dot -Tsvg`.
+  ///
+  /// ```rust
+  /// use ffmpeg_sidecar::filter_graph::FilterGraphDump;
+  ///
+  /// let dump = FilterGraphDump::parse(
+  ///   "[Parsed_amix_0 @ 0x6000] [verbose] auto-inserting filter 'auto_aresample_0' \
+  ///    between the filter 'graph 0 input from stream 1:0' and the filter 'Parsed_amix_0'",
+  /// );
+  /// assert_eq!(
+  ///   dump.to_dot(),
+  ///   "digraph filters {\n  \
+  ///      rankdir=LR;\n  \
+  ///      n0 [label=\"Parsed_amix_0\\namix\"];\n  \
+  ///      n1 [label=\"auto_aresample_0\\naresample\", color=red, fontcolor=red];\n  \
+  ///      n2 [label=\"graph 0 input from stream 1:0\\nbuffer\"];\n  \
+  ///      n2 -> n1;\n  \
+  ///      n1 -> n0;\n\
+  ///    }\n"
+  /// );
+  /// ```
+  pub fn to_dot(&self) -> String {
+    let mut dot = String::from("digraph filters {\n  rankdir=LR;\n");
+    for (index, node) in self.nodes.iter().enumerate() {
+      let mut label = node.name.clone();
+      if !node.filter.is_empty() {
+        label.push_str(&format!("\n{}", node.filter));
+      }
+      if !node.formats.is_empty() {
+        label.push_str(&format!("\n{}", node.formats.join(" -> ")));
+      }
+      let label = label
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n");
+      let style = match node.auto_inserted {
+        true => ", color=red, fontcolor=red",
+        false => "",
+      };
+      dot.push_str(&format!("  n{index} [label=\"{label}\"{style}];\n"));
+    }
+    for edge in &self.edges {
+      dot.push_str(&format!("  n{} -> n{};\n", edge.from, edge.to));
+    }
+    dot.push_str("}\n");
+    dot
+  }
+
+  /// The index of the node with this name and pointer, added if needed. A
+  /// node only known by name so far takes the pointer.
+  fn node(&mut self, name: &str, pointer: Option<&str>) -> usize {
+    let position = self.nodes.iter().rposition(|node| {
+      node.name == name && (node.pointer.as_deref() == pointer || node.pointer.is_none())
+    });
+    match position {
+      Some(index) => {
+        let node = &mut self.nodes[index];
+        if node.pointer.is_none() {
+          node.pointer = pointer.map(str::to_string);
+        }
+        index
+      }
+      None => self.add(name, pointer),
+    }
+  }
+
+  /// The index of the last node with this name, added if needed.
+  fn named_node(&mut self, name: &str) -> usize {
+    match self.nodes.iter().rposition(|node| node.name == name) {
+      Some(index) => index,
+      None => self.add(name, None),
+    }
+  }
+
+  fn add(&mut self, name: &str, pointer: Option<&str>) -> usize {
+    self.nodes.push(FilterNode {
+      name: name.to_string(),
+      pointer: pointer.map(str::to_string),
+      filter: filter_of(name).unwrap_or_default().to_string(),
+      args: None,
+      in_pads: Vec::new(),
+      out_pads: Vec::new(),
+      formats: Vec::new(),
+      auto_inserted: name.starts_with("auto_"),
+    });
+    self.nodes.len() - 1
+  }
+}
+
+/// The filter of an instance name, for the names given by ffmpeg's filter
+/// graph parser and by the ffmpeg CLI and libavfilter to the filters they
+/// add, across versions.
+fn filter_of(name: &str) -> Option<&str> {
+  if let Some(rest) = name.strip_prefix("Parsed_") {
+    return rest.rsplit_once('_').map(|(filter, _)| filter);
+  }
+  if let Some(rest) = name.strip_prefix("auto_") {
+    return match rest.rsplit_once('_').map_or(rest, |(filter, _)| filter) {
+      "scale" | "scaler" => Some("scale"),
+      "resampler" | "aresample" => Some("aresample"),
+      filter => Some(filter),
+    };
+  }
+  if name.starts_with("graph ") && name.contains(" input from ") {
+    return Some("buffer");
+  }
+  [
+    ("out_", "buffersink"),
+    ("format_out_", "format"),
+    ("scaler_out_", "scale"),
+    ("trim_in_", "trim"),
+    ("trim for ", "trim"),
+  ]
+  .into_iter()
+  .find_map(|(prefix, filter)| name.starts_with(prefix).then_some(filter))
+}
+
+/// The message of a line, after the context and level prefixes.
+fn message_of(line: &str) -> Option<&str> {
+  let (_, rest) = line.split_once("] [")?;
+  let (_, message) = rest.split_once(']')?;
+  Some(message.trim())
+}
+
+/// `auto-inserting filter 'auto_scale_0' between the filter
+/// 'Parsed_format_0' and the filter 'Parsed_format_1'`, as the names of the
+/// inserted filter and of the two it was inserted between.
+fn parse_insertion(message: &str) -> Option<(&str, &str, &str)> {
+  let rest = message.strip_prefix("auto-inserting filter '")?;
+  let (inserted, rest) = rest.split_once("' between the filter '")?;
+  let (from, rest) = rest.split_once("' and the filter '")?;
+  let to = rest.strip_suffix('\'')?;
+  Some((inserted, from, to))
+}
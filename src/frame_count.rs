@@ -0,0 +1,300 @@
+//! Counting the frames of a video, either from the container metadata or by
+//! reading every packet, and the packet statistics of each stream, without
+//! decoding unless asked to.
+
+use std::collections::BTreeMap;
+#[cfg(feature = "process")]
+use std::{
+  ffi::OsStr,
+  process::{Command, Stdio},
+};
+
+use crate::frame_rate::Rate;
+#[cfg(feature = "process")]
+use crate::{
+  command::FfmpegCommand,
+  event::{FfmpegEvent, LogLevel},
+  ffprobe::ffprobe_path,
+  spawner,
+};
+
+/// How [`video_frame_count`] counts the frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Accuracy {
+  /// Read the count from the container, or estimate it from the duration
+  /// and the average frame rate, without reading the file. Instant, but
+  /// only as right as the metadata, which is often wrong for variable frame
+  /// rate videos.
+  #[default]
+  Fast,
+  /// Read every packet of the stream, copying it to a null output. Exact,
+  /// but as slow as reading the whole file.
+  Exact,
+}
+
+/// Where the number of a [`FrameCount`] comes from, from the most to the
+/// least trustworthy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FrameCountMethod {
+  /// The packets were counted by reading the whole stream.
+  PacketCount,
+  /// The `nb_frames` of the stream, written by the muxer in the container,
+  /// e.g. the sample table of an MP4.
+  ContainerMetadata,
+  /// The duration of the stream (or of the file) multiplied by its average
+  /// frame rate, rounded. An estimate, for containers which don't store the
+  /// count, like Matroska.
+  DurationTimesRate,
+}
+
+/// The number of frames of a video stream, returned by
+/// [`video_frame_count`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FrameCount {
+  pub frames: u64,
+  pub method: FrameCountMethod,
+}
+
+/// What [`stream_statistics`] counts besides the packets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct StatisticsOptions {
+  /// Also decode every stream to count its frames (`-count_frames`), which
+  /// is much slower than reading the packets.
+  pub count_frames: bool,
+}
+
+/// The statistics of a stream, returned by [`stream_statistics`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StreamStatistics {
+  /// Absolute index of the stream in the file, as in `-map 0:<index>`
+  pub index: usize,
+  /// `video`, `audio`, `subtitle`, `data` or `attachment`
+  pub codec_type: String,
+  pub packets: u64,
+  /// The size of all the packets, in bytes.
+  pub bytes: u64,
+  /// The frames decoded, with [`StatisticsOptions::count_frames`].
+  pub frames: Option<u64>,
+}
+
+/// Parse the output of `ffprobe -select_streams v:0 -show_entries
+/// stream=nb_frames,avg_frame_rate,duration:format=duration -of compact`
+/// into a [`FrameCount`] made from the metadata.
+///
+/// ```rust
+/// use ffmpeg_sidecar::frame_count::{parse_metadata_frame_count, FrameCountMethod};
+///
+/// let mp4 = "stream|avg_frame_rate=25/1|duration=2.000000|nb_frames=50\nformat|duration=2.000000\n";
+/// let count = parse_metadata_frame_count(mp4).unwrap();
+/// assert_eq!((count.frames, count.method), (50, FrameCountMethod::ContainerMetadata));
+///
+/// let mkv = "stream|avg_frame_rate=30000/1001|duration=N/A|nb_frames=N/A\nformat|duration=10.010000\n";
+/// let count = parse_metadata_frame_count(mkv).unwrap();
+/// assert_eq!((count.frames, count.method), (300, FrameCountMethod::DurationTimesRate));
+/// ```
+pub fn parse_metadata_frame_count(output: &str) -> Option<FrameCount> {
+  let sections = parse_compact(output);
+  let stream = sections.iter().find(|(section, _)| section == "stream")?;
+  let value = |entries: &BTreeMap<String, String>, key: &str| {
+    entries.get(key).filter(|value| *value != "N/A").cloned()
+  };
+  if let Some(frames) = value(&stream.1, "nb_frames").and_then(|frames| frames.parse().ok()) {
+    return Some(FrameCount {
+      frames,
+      method: FrameCountMethod::ContainerMetadata,
+    });
+  }
+  let rate = value(&stream.1, "avg_frame_rate")?.parse::<Rate>().ok()?;
+  let duration = value(&stream.1, "duration")
+    .or_else(|| {
+      let format = sections.iter().find(|(section, _)| section == "format")?;
+      value(&format.1, "duration")
+    })?
+    .parse::<f64>()
+    .ok()?;
+  Some(FrameCount {
+    frames: (duration * rate.as_f64()).round() as u64,
+    method: FrameCountMethod::DurationTimesRate,
+  })
+}
+
+/// Parse the output of `ffprobe -show_entries
+/// stream=index,codec_type,nb_read_frames:packet=stream_index,size -of
+/// compact`, summing the packets of each stream.
+///
+/// ```rust
+/// use ffmpeg_sidecar::frame_count::parse_stream_statistics;
+///
+/// let output = "packet|stream_index=0|size=1200\npacket|stream_index=1|size=300\npacket|stream_index=0|size=800\nstream|index=0|codec_type=video|nb_read_frames=2\nstream|index=1|codec_type=audio|nb_read_frames=1\n";
+/// let statistics = parse_stream_statistics(output);
+/// assert_eq!((statistics[0].packets, statistics[0].bytes), (2, 2000));
+/// assert_eq!(statistics[1].codec_type, "audio");
+/// assert_eq!(statistics[1].frames, Some(1));
+/// ```
+pub fn parse_stream_statistics(output: &str) -> Vec<StreamStatistics> {
+  let mut statistics = BTreeMap::<usize, StreamStatistics>::new();
+  for (section, entries) in parse_compact(output) {
+    let index_key = match section.as_str() {
+      "packet" => "stream_index",
+      "stream" => "index",
+      _ => continue,
+    };
+    let Some(index) = entries.get(index_key).and_then(|index| index.parse().ok()) else {
+      continue;
+    };
+    let stream = statistics.entry(index).or_insert_with(|| StreamStatistics {
+      index,
+      ..Default::default()
+    });
+    if section == "packet" {
+      stream.packets += 1;
+      stream.bytes += entries
+        .get("size")
+        .and_then(|size| size.parse::<u64>().ok())
+        .unwrap_or_default();
+    } else {
+      stream.codec_type = entries.get("codec_type").cloned().unwrap_or_default();
+      stream.frames = entries
+        .get("nb_read_frames")
+        .and_then(|frames| frames.parse().ok());
+    }
+  }
+  statistics.into_values().collect()
+}
+
+/// The sections of ffprobe's `compact` output, each line being the section
+/// name followed by `|key=value` entries.
+fn parse_compact(output: &str) -> Vec<(String, BTreeMap<String, String>)> {
+  output
+    .lines()
+    .filter_map(|line| {
+      let mut fields = line.trim().split('|');
+      let section = fields.next().filter(|section| !section.is_empty())?;
+      let entries = fields
+        .filter_map(|field| field.split_once('='))
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect();
+      Some((section.to_string(), entries))
+    })
+    .collect()
+}
+
+/// Count the frames of the first video stream of `path`, reporting how, see
+/// [`Accuracy`].
+///
+/// With [`Accuracy::Fast`], a file whose container stores neither the count
+/// nor a duration and frame rate fails; [`Accuracy::Exact`] can count it.
+///
+/// ```rust,no_run
+/// use ffmpeg_sidecar::frame_count::{video_frame_count, Accuracy, FrameCountMethod};
+///
+/// let count = video_frame_count("in.mkv", Accuracy::Fast).unwrap();
+/// if count.method == FrameCountMethod::DurationTimesRate {
+///   let exact = video_frame_count("in.mkv", Accuracy::Exact).unwrap();
+///   println!("{} frames, estimated {}", exact.frames, count.frames);
+/// }
+/// ```
+#[cfg(feature = "process")]
+pub fn video_frame_count<S: AsRef<OsStr>>(
+  path: S,
+  accuracy: Accuracy,
+) -> anyhow::Result<FrameCount> {
+  let path = path.as_ref();
+  match accuracy {
+    Accuracy::Fast => {
+      let output = ffprobe(
+        path,
+        &[
+          "-select_streams",
+          "v:0",
+          "-show_entries",
+          "stream=nb_frames,avg_frame_rate,duration:format=duration",
+        ],
+      )?;
+      if !output.contains("stream|") {
+        anyhow::bail!("{} has no video stream", path.to_string_lossy());
+      }
+      parse_metadata_frame_count(&output).ok_or_else(|| {
+        anyhow::anyhow!(
+          "{} stores neither a frame count nor a duration and frame rate",
+          path.to_string_lossy()
+        )
+      })
+    }
+    Accuracy::Exact => {
+      // Stream copy reports the packets written as its frames
+      let mut frames = None;
+      let mut errors = Vec::new();
+      let mut child = FfmpegCommand::new()
+        .input(path.to_string_lossy())
+        .args(["-map", "0:v:0", "-c", "copy", "-f", "null", "-"])
+        .spawn()?;
+      for event in child.iter()? {
+        match event {
+          FfmpegEvent::Progress(progress) => frames = progress.frame.or(frames),
+          FfmpegEvent::Log(LogLevel::Error | LogLevel::Fatal, message) => errors.push(message),
+          _ => {}
+        }
+      }
+      let status = child.wait()?;
+      match (status.success(), frames) {
+        (true, Some(frames)) => Ok(FrameCount {
+          frames: u64::from(frames),
+          method: FrameCountMethod::PacketCount,
+        }),
+        (true, None) => anyhow::bail!(
+          "ffmpeg didn't report the frames of {}",
+          path.to_string_lossy()
+        ),
+        (false, _) if errors.is_empty() => anyhow::bail!("ffmpeg exited with {status}"),
+        (false, _) => anyhow::bail!(errors.join("\n")),
+      }
+    }
+  }
+}
+
+/// The packets and bytes of each stream of `path`, read without decoding
+/// unless [`StatisticsOptions::count_frames`] is set.
+///
+/// ```rust,no_run
+/// use ffmpeg_sidecar::frame_count::{stream_statistics, StatisticsOptions};
+///
+/// for stream in stream_statistics("in.mp4", StatisticsOptions::default()).unwrap() {
+///   println!("{} {}: {} packets, {} bytes", stream.index, stream.codec_type, stream.packets, stream.bytes);
+/// }
+/// ```
+#[cfg(feature = "process")]
+pub fn stream_statistics<S: AsRef<OsStr>>(
+  path: S,
+  options: StatisticsOptions,
+) -> anyhow::Result<Vec<StreamStatistics>> {
+  let mut args = vec!["-count_packets"];
+  let mut entries = "stream=index,codec_type,nb_read_packets";
+  if options.count_frames {
+    args.push("-count_frames");
+    entries = "stream=index,codec_type,nb_read_packets,nb_read_frames";
+  }
+  let entries = format!("{entries}:packet=stream_index,size");
+  args.extend(["-show_entries", &entries]);
+  let output = ffprobe(path.as_ref(), &args)?;
+  Ok(parse_stream_statistics(&output))
+}
+
+#[cfg(feature = "process")]
+fn ffprobe(path: &OsStr, args: &[&str]) -> anyhow::Result<String> {
+  let output = spawner::output(
+    Command::new(ffprobe_path())
+      .args(["-v", "error"])
+      .args(args)
+      .args(["-of", "compact"])
+      .arg(path)
+      .stdin(Stdio::null()),
+  )?;
+  if !output.status.success() {
+    anyhow::bail!(
+      "ffprobe failed: {}",
+      String::from_utf8_lossy(&output.stderr).trim()
+    );
+  }
+  Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
@@ -0,0 +1,380 @@
+//! Hashes of the decoded frames of a video, to find duplicate frames or to
+//! align two versions of the same video.
+//!
+//! ```rust,no_run
+//! use ffmpeg_sidecar::frame_hash::{find_offset, frame_hashes, HashKind};
+//! use std::time::Duration;
+//!
+//! let mut previous = None;
+//! for frame in frame_hashes("movie.mp4", HashKind::Md5PerFrame).unwrap() {
+//!   if previous.as_ref() == Some(&frame.hash) {
+//!     println!("frame {} is a duplicate", frame.index);
+//!   }
+//!   previous = Some(frame.hash);
+//! }
+//!
+//! let offset = find_offset("master.mov", "broadcast.mp4", Duration::from_secs(20)).unwrap();
+//! println!("{} frames ({:.3}s), confidence {:.2}", offset.frames, offset.time, offset.confidence);
+//! ```
+
+use std::{
+  fmt,
+  io::{BufRead, BufReader, Lines},
+  path::{Path, PathBuf},
+  process::ChildStdout,
+  time::Duration,
+};
+
+use crate::{
+  child::FfmpegChild, command::FfmpegCommand, event::FfmpegEvent, iter::FfmpegIterator,
+  stdio_policy::StdioPolicy,
+};
+
+/// Width and height of the thumbnails of [`HashKind::Perceptual`].
+const THUMBNAIL_SIZE: usize = 8;
+
+/// How [`frame_hashes`] hashes each frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum HashKind {
+  /// The MD5 of the decoded pixels, from the `framemd5` muxer. Only equal for
+  /// identical frames, so it finds exact duplicates, but not the same frame
+  /// encoded twice.
+  #[default]
+  Md5PerFrame,
+  /// An 8x8 grayscale thumbnail, compared by the average difference of its
+  /// pixels. Nearly equal for the same frame encoded twice, or scaled.
+  Perceptual,
+}
+
+/// The hash of a frame, see [`HashKind`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum HashValue {
+  /// As printed by `framemd5`, in hexadecimal.
+  Md5(String),
+  /// The luma of the pixels of the thumbnail, row by row.
+  Perceptual([u8; THUMBNAIL_SIZE * THUMBNAIL_SIZE]),
+}
+
+impl HashValue {
+  /// How different the frames are, from `0.0` for equal hashes to `1.0`.
+  /// MD5 hashes are either equal or completely different, as are hashes of
+  /// different kinds.
+  pub fn distance(&self, other: &HashValue) -> f64 {
+    match (self, other) {
+      (HashValue::Md5(a), HashValue::Md5(b)) if a == b => 0.0,
+      (HashValue::Perceptual(a), HashValue::Perceptual(b)) => {
+        let total = a
+          .iter()
+          .zip(b)
+          .map(|(a, b)| u32::from(a.abs_diff(*b)))
+          .sum::<u32>();
+        f64::from(total) / (a.len() as f64 * 255.0)
+      }
+      _ => 1.0,
+    }
+  }
+}
+
+impl fmt::Display for HashValue {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      HashValue::Md5(hash) => write!(f, "{hash}"),
+      HashValue::Perceptual(pixels) => pixels.iter().try_for_each(|p| write!(f, "{p:02x}")),
+    }
+  }
+}
+
+/// The hash of a decoded frame.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrameHash {
+  /// Position of the frame in the video, starting at 0.
+  pub index: u64,
+  /// The timestamp of the frame in its stream's time base, with
+  /// [`HashKind::Md5PerFrame`].
+  pub pts: Option<i64>,
+  /// The timestamp of the frame in seconds.
+  pub time: f64,
+  pub hash: HashValue,
+}
+
+/// The best alignment of two versions of a video, from [`find_offset`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FrameOffset {
+  /// The index of the frame of the reference shown as the first frame of
+  /// the variant, negative when the variant starts before the reference.
+  pub frames: i64,
+  /// The same offset in seconds, from the timestamps of the aligned frames.
+  pub time: f64,
+  /// From `0.0` to `1.0`: how well the frames match at this offset, and how
+  /// much better than at any other offset, so that a still shot or a
+  /// repeating pattern scores low.
+  pub confidence: f64,
+}
+
+/// Returned (through `anyhow::Error`) when a video has no frame to hash.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NoFramesToHash(pub PathBuf);
+
+impl fmt::Display for NoFramesToHash {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "no video frame was decoded from {}", self.0.display())
+  }
+}
+
+impl std::error::Error for NoFramesToHash {}
+
+/// Hash the frames of the first video stream of `input`, as they're
+/// decoded by ffmpeg rather than once it's done. Dropping the iterator
+/// kills ffmpeg.
+pub fn frame_hashes<P: AsRef<Path>>(input: P, kind: HashKind) -> anyhow::Result<FrameHashes> {
+  frame_hashes_within(input.as_ref(), kind, None)
+}
+
+/// The hashes of [`frame_hashes`].
+pub struct FrameHashes {
+  child: FfmpegChild,
+  source: HashSource,
+}
+
+enum HashSource {
+  Md5 {
+    lines: Lines<BufReader<ChildStdout>>,
+    parser: FramehashParser,
+  },
+  Perceptual(Box<FfmpegIterator>),
+}
+
+impl Iterator for FrameHashes {
+  type Item = FrameHash;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    match &mut self.source {
+      HashSource::Md5 { lines, parser } => lines
+        .map_while(Result::ok)
+        .find_map(|line| parser.parse_line(&line)),
+      HashSource::Perceptual(iter) => iter.find_map(|event| match event {
+        FfmpegEvent::OutputFrame(frame) => Some(FrameHash {
+          index: u64::from(frame.frame_num),
+          pts: None,
+          time: f64::from(frame.timestamp),
+          hash: HashValue::Perceptual(frame.data.try_into().ok()?),
+        }),
+        _ => None,
+      }),
+    }
+  }
+}
+
+impl Drop for FrameHashes {
+  fn drop(&mut self) {
+    self.child.kill().ok();
+    self.child.wait().ok();
+  }
+}
+
+/// Parses the output of the `framemd5` and `framehash` muxers, keeping the
+/// frames of a single stream.
+///
+/// ```rust
+/// use ffmpeg_sidecar::frame_hash::{FramehashParser, HashValue};
+///
+/// let output = "#format: frame checksums\n\
+///   #version: 2\n\
+///   #hash: MD5\n\
+///   #tb 0: 1/25\n\
+///   #media_type 0: video\n\
+///   #tb 1: 1/44100\n\
+///   #media_type 1: audio\n\
+///   #stream#, dts,        pts, duration,     size, hash\n\
+///   0,          0,          0,        1,   115200, 0b2e5f7a4e1c3f1d6b4e3a2c1d0e9f8a\n\
+///   1,          0,          0,     1024,     4096, 5c7b1d2e3f4a5b6c7d8e9f0a1b2c3d4e\n\
+///   0,          1,          1,        1,   115200, 9c1e5f7a4e1c3f1d6b4e3a2c1d0e9f8b\n";
+/// let mut parser = FramehashParser::new(0);
+/// let frames = output.lines().filter_map(|line| parser.parse_line(line)).collect::<Vec<_>>();
+/// assert_eq!(frames.len(), 2);
+/// assert_eq!((frames[1].index, frames[1].pts, frames[1].time), (1, Some(1), 0.04));
+/// assert_eq!(frames[1].hash, HashValue::Md5("9c1e5f7a4e1c3f1d6b4e3a2c1d0e9f8b".to_string()));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FramehashParser {
+  stream: u32,
+  /// From the `#tb` header of the stream
+  time_base: Option<(i64, i64)>,
+  next_index: u64,
+}
+
+impl FramehashParser {
+  /// A parser of the frames of the stream with this index in the output.
+  pub fn new(stream: u32) -> Self {
+    Self {
+      stream,
+      time_base: None,
+      next_index: 0,
+    }
+  }
+
+  /// The frame of a line of the output, or `None` for the header lines and
+  /// the frames of other streams.
+  pub fn parse_line(&mut self, line: &str) -> Option<FrameHash> {
+    let line = line.trim();
+    if let Some(header) = line.strip_prefix('#') {
+      // `#tb 0: 1/25`
+      let (stream, time_base) = header.strip_prefix("tb ")?.split_once(':')?;
+      if stream.trim().parse::<u32>().ok()? == self.stream {
+        let (num, den) = time_base.trim().split_once('/')?;
+        self.time_base = Some((num.parse().ok()?, den.parse().ok()?));
+      }
+      return None;
+    }
+
+    // `stream, dts, pts, duration, size, hash`
+    let fields = line.split(',').map(str::trim).collect::<Vec<_>>();
+    let [stream, _, pts, _, _, hash] = fields[..] else {
+      return None;
+    };
+    if stream.parse::<u32>().ok()? != self.stream {
+      return None;
+    }
+    let pts = pts.parse::<i64>().ok()?;
+    let index = self.next_index;
+    self.next_index += 1;
+    let time = match self.time_base {
+      Some((num, den)) if den != 0 => pts as f64 * num as f64 / den as f64,
+      _ => 0.0,
+    };
+    Some(FrameHash {
+      index,
+      pts: Some(pts),
+      time,
+      hash: HashValue::Md5(hash.to_string()),
+    })
+  }
+}
+
+/// Find where `variant`, e.g. a trimmed or re-encoded copy, starts in
+/// `reference`, from the [`HashKind::Perceptual`] hashes of their first
+/// `window` of frames.
+///
+/// Every offset where at least half of the shorter window overlaps is
+/// tried, scoring the average distance of the overlapping frames. The
+/// offset must be within the window, and the window long enough for the
+/// content to change between its frames.
+pub fn find_offset<R: AsRef<Path>, V: AsRef<Path>>(
+  reference: R,
+  variant: V,
+  window: Duration,
+) -> anyhow::Result<FrameOffset> {
+  let hashes = |input: &Path| -> anyhow::Result<Vec<FrameHash>> {
+    let hashes =
+      frame_hashes_within(input, HashKind::Perceptual, Some(window))?.collect::<Vec<_>>();
+    match hashes.is_empty() {
+      true => Err(NoFramesToHash(input.to_path_buf()).into()),
+      false => Ok(hashes),
+    }
+  };
+  let reference = hashes(reference.as_ref())?;
+  let variant = hashes(variant.as_ref())?;
+  Ok(best_offset(&reference, &variant))
+}
+
+/// The offset of `variant` in `reference` where their hashes match best.
+fn best_offset(reference: &[FrameHash], variant: &[FrameHash]) -> FrameOffset {
+  let (reference_len, variant_len) = (reference.len() as i64, variant.len() as i64);
+  let min_overlap = (reference_len.min(variant_len) / 2).max(1);
+
+  // The average distance of the overlapping frames at each offset
+  let scores = (min_overlap - variant_len..=reference_len - min_overlap)
+    .map(|offset| {
+      let pairs = (0..variant_len)
+        .filter(|k| (0..reference_len).contains(&(k + offset)))
+        .map(|k| (&reference[(k + offset) as usize], &variant[k as usize]))
+        .collect::<Vec<_>>();
+      let distance = pairs
+        .iter()
+        .map(|(reference, variant)| reference.hash.distance(&variant.hash))
+        .sum::<f64>()
+        / pairs.len() as f64;
+      let time = pairs[0].0.time - pairs[0].1.time;
+      (offset, distance, time)
+    })
+    .collect::<Vec<_>>();
+
+  let (frames, best, time) = scores
+    .iter()
+    .copied()
+    .min_by(|a, b| a.1.total_cmp(&b.1))
+    .unwrap_or((0, 1.0, 0.0));
+  // The neighbors of the best offset match nearly as well when the content
+  // changes slowly, so they don't count as ambiguous
+  let second = scores
+    .iter()
+    .filter(|(offset, ..)| offset.abs_diff(frames) > 1)
+    .map(|(_, distance, _)| *distance)
+    .min_by(f64::total_cmp);
+  let uniqueness = match second {
+    Some(second) if second > 0.0 => 1.0 - best / second,
+    Some(_) => 0.0,
+    None => 1.0,
+  };
+  FrameOffset {
+    frames,
+    time,
+    confidence: ((1.0 - best) * uniqueness).clamp(0.0, 1.0),
+  }
+}
+
+fn frame_hashes_within(
+  input: &Path,
+  kind: HashKind,
+  window: Option<Duration>,
+) -> anyhow::Result<FrameHashes> {
+  let mut command = FfmpegCommand::new();
+  command.input(input.to_string_lossy()).map("0:v:0");
+  if let Some(window) = window {
+    command.duration(window);
+  }
+  match kind {
+    HashKind::Md5PerFrame => {
+      // The hashes are read from stdout, so the log isn't needed
+      command
+        .format("framemd5")
+        .output("-")
+        .stderr_stdio(StdioPolicy::Null);
+      let mut child = command.spawn()?;
+      let stdout = child.take_stdout();
+      let Some(stdout) = stdout else {
+        child.kill().ok();
+        child.wait().ok();
+        anyhow::bail!("No stdout channel");
+      };
+      Ok(FrameHashes {
+        child,
+        source: HashSource::Md5 {
+          lines: BufReader::new(stdout).lines(),
+          parser: FramehashParser::new(0),
+        },
+      })
+    }
+    HashKind::Perceptual => {
+      command
+        .filter(format!(
+          "scale={THUMBNAIL_SIZE}:{THUMBNAIL_SIZE}:flags=area,format=gray"
+        ))
+        .args(["-f", "rawvideo", "-pix_fmt", "gray"])
+        .output("-");
+      let mut child = command.spawn()?;
+      let iter = match child.iter() {
+        Ok(iter) => iter,
+        Err(e) => {
+          child.kill().ok();
+          child.wait().ok();
+          return Err(e);
+        }
+      };
+      Ok(FrameHashes {
+        child,
+        source: HashSource::Perceptual(Box::new(iter)),
+      })
+    }
+  }
+}
@@ -0,0 +1,244 @@
+//! Exact frame rates and the options for converting video to a constant
+//! frame rate, e.g. variable frame rate phone footage for an editor.
+
+use std::{fmt, str::FromStr};
+
+/// A frame rate as a reduced fraction, so NTSC rates like `30000/1001` are
+/// kept exact instead of being rounded to `29.97`.
+///
+/// Parsed from a fraction, a whole or decimal number, or one of ffmpeg's
+/// abbreviations. Decimals within 0.005 of an NTSC rate parse to it.
+///
+/// ```rust
+/// use ffmpeg_sidecar::frame_rate::Rate;
+///
+/// assert_eq!("30000/1001".parse::<Rate>().unwrap(), Rate::NTSC);
+/// assert_eq!("29.97".parse::<Rate>().unwrap(), Rate::NTSC);
+/// assert_eq!("ntsc-film".parse::<Rate>().unwrap(), Rate::NTSC_FILM);
+/// assert_eq!("12.5".parse::<Rate>().unwrap(), Rate::new(25, 2));
+/// assert_eq!(Rate::new(60, 2).to_string(), "30");
+/// assert_eq!(Rate::NTSC.to_string(), "30000/1001");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rate {
+  numerator: u32,
+  denominator: u32,
+}
+
+impl Rate {
+  /// 24000/1001, the rate of film transferred to NTSC video.
+  pub const NTSC_FILM: Rate = Rate::new(24000, 1001);
+  /// 30000/1001, often written as 29.97.
+  pub const NTSC: Rate = Rate::new(30000, 1001);
+  /// 60000/1001, often written as 59.94.
+  pub const NTSC_60: Rate = Rate::new(60000, 1001);
+
+  /// The rate `numerator / denominator` frames per second, reduced.
+  ///
+  /// Panics if either is zero.
+  pub const fn new(numerator: u32, denominator: u32) -> Self {
+    assert!(
+      numerator > 0 && denominator > 0,
+      "frame rate must be positive"
+    );
+    let (mut a, mut b) = (numerator, denominator);
+    while b != 0 {
+      (a, b) = (b, a % b);
+    }
+    Self {
+      numerator: numerator / a,
+      denominator: denominator / a,
+    }
+  }
+
+  /// A whole number of frames per second.
+  pub const fn fps(fps: u32) -> Self {
+    Self::new(fps, 1)
+  }
+
+  pub fn numerator(&self) -> u32 {
+    self.numerator
+  }
+
+  pub fn denominator(&self) -> u32 {
+    self.denominator
+  }
+
+  /// The rate in frames per second, for display or arithmetic.
+  pub fn as_f64(&self) -> f64 {
+    f64::from(self.numerator) / f64::from(self.denominator)
+  }
+
+  fn from_decimal(s: &str) -> Option<Self> {
+    let (whole, fraction) = s.split_once('.').unwrap_or((s, ""));
+    if whole.is_empty() && fraction.is_empty()
+      || !whole
+        .chars()
+        .chain(fraction.chars())
+        .all(|c| c.is_ascii_digit())
+    {
+      return None;
+    }
+    let fraction = fraction.trim_end_matches('0');
+    let denominator = 10u64.checked_pow(fraction.len() as u32)?;
+    let numerator = format!("{whole}{fraction}").parse::<u64>().ok()?;
+    if denominator == 1 {
+      return Self::from_u64(numerator, 1);
+    }
+
+    // An approximation of an NTSC rate, like 29.97 or 23.976
+    let fps = numerator as f64 / denominator as f64;
+    let ntsc = (fps * 1.001).round();
+    if ntsc >= 1.0 && (fps - ntsc / 1.001).abs() < 0.005 {
+      return Self::from_u64(ntsc as u64 * 1000, 1001);
+    }
+    Self::from_u64(numerator, denominator)
+  }
+
+  fn from_u64(numerator: u64, denominator: u64) -> Option<Self> {
+    let (mut a, mut b) = (numerator, denominator);
+    while b != 0 {
+      (a, b) = (b, a % b);
+    }
+    if a == 0 {
+      return None;
+    }
+    let numerator = u32::try_from(numerator / a).ok()?;
+    let denominator = u32::try_from(denominator / a).ok()?;
+    (numerator > 0 && denominator > 0).then(|| Self::new(numerator, denominator))
+  }
+}
+
+impl fmt::Display for Rate {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    if self.denominator == 1 {
+      write!(f, "{}", self.numerator)
+    } else {
+      write!(f, "{}/{}", self.numerator, self.denominator)
+    }
+  }
+}
+
+impl FromStr for Rate {
+  type Err = InvalidRate;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    let trimmed = s.trim();
+    let rate = match trimmed {
+      "ntsc" => Some(Rate::NTSC),
+      "pal" => Some(Rate::fps(25)),
+      "film" => Some(Rate::fps(24)),
+      "ntsc-film" => Some(Rate::NTSC_FILM),
+      _ => match trimmed.split_once('/') {
+        Some((numerator, denominator)) => {
+          match (numerator.trim().parse(), denominator.trim().parse()) {
+            (Ok(numerator), Ok(denominator)) => Rate::from_u64(numerator, denominator),
+            _ => None,
+          }
+        }
+        None => Rate::from_decimal(trimmed),
+      },
+    };
+    rate.ok_or_else(|| InvalidRate(s.to_string()))
+  }
+}
+
+/// Returned when parsing a [`Rate`] fails, with the text which couldn't be
+/// parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidRate(pub String);
+
+impl fmt::Display for InvalidRate {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "invalid frame rate: {:?}", self.0)
+  }
+}
+
+impl std::error::Error for InvalidRate {}
+
+/// How frames are passed to the muxer, corresponding to `-fps_mode` (or the
+/// deprecated `-vsync`). See
+/// [`FfmpegCommand::fps_mode`](crate::command::FfmpegCommand::fps_mode).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum FpsMode {
+  /// Each frame keeps its timestamp from the demuxer.
+  Passthrough,
+  /// Frames are duplicated and dropped to get exactly the output rate.
+  Cfr,
+  /// Frames keep their timestamp, or are dropped when two share one.
+  Vfr,
+  /// Timestamps are dropped, and regenerated by the muxer from the rate.
+  Drop,
+  /// `Cfr` or `Vfr`, depending on the muxer.
+  #[default]
+  Auto,
+}
+
+impl FpsMode {
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      FpsMode::Passthrough => "passthrough",
+      FpsMode::Cfr => "cfr",
+      FpsMode::Vfr => "vfr",
+      FpsMode::Drop => "drop",
+      FpsMode::Auto => "auto",
+    }
+  }
+}
+
+impl AsRef<str> for FpsMode {
+  fn as_ref(&self) -> &str {
+    self.as_str()
+  }
+}
+
+/// How [`FfmpegCommand::to_constant_frame_rate`](crate::command::FfmpegCommand::to_constant_frame_rate)
+/// gets to the new rate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum CfrStrategy {
+  /// Duplicate and drop frames after filtering, with `-fps_mode cfr -r`.
+  /// The cheapest, and leaves room for other `-vf` filters.
+  #[default]
+  DropDup,
+  /// Duplicate and drop frames with the `fps` filter, which picks the frame
+  /// closest to each output timestamp, for smoother motion than `DropDup`
+  /// with large timestamp jitter. Uses `-vf`.
+  FilterFps,
+  /// Synthesize the frames in between with the `minterpolate` filter, for
+  /// smooth motion when raising the rate. Much slower than the others, and
+  /// prone to artifacts on fast motion. Uses `-vf`.
+  Interpolate,
+}
+
+impl CfrStrategy {
+  /// The arguments which convert the output to `rate` with this strategy.
+  ///
+  /// ```rust
+  /// use ffmpeg_sidecar::frame_rate::{CfrStrategy, Rate};
+  ///
+  /// assert_eq!(
+  ///   CfrStrategy::FilterFps.args(Rate::NTSC),
+  ///   ["-vf", "fps=30000/1001", "-fps_mode", "cfr"]
+  /// );
+  /// ```
+  pub fn args(&self, rate: Rate) -> Vec<String> {
+    let filter = match self {
+      CfrStrategy::DropDup => {
+        return vec![
+          "-fps_mode".to_string(),
+          FpsMode::Cfr.as_str().to_string(),
+          "-r".to_string(),
+          rate.to_string(),
+        ]
+      }
+      CfrStrategy::FilterFps => format!("fps={rate}"),
+      CfrStrategy::Interpolate => format!("minterpolate=fps={rate}:mi_mode=mci"),
+    };
+    vec![
+      "-vf".to_string(),
+      filter,
+      "-fps_mode".to_string(),
+      FpsMode::Cfr.as_str().to_string(),
+    ]
+  }
+}
@@ -0,0 +1,394 @@
+//! Decoded frames of a video from any point in it, e.g. for a timeline
+//! scrubber or a frame-stepping preview, or of an exact range of it with
+//! [`extract_frames`].
+
+use std::{
+  fmt,
+  marker::PhantomData,
+  ops::Range,
+  path::{Path, PathBuf},
+  time::Duration,
+};
+
+use crate::{
+  child::FfmpegChild,
+  command::FfmpegCommand,
+  event::{FfmpegEvent, OutputVideoFrame},
+  frame_rate::Rate,
+  iter::FfmpegIterator,
+  probe::probe_cached,
+};
+
+/// How the frames of a [`FrameSource`] are decoded.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DecodeSettings {
+  /// The pixel format of the frames, as passed to `-pix_fmt`. Defaults to
+  /// `rgb24`.
+  pub pix_fmt: String,
+  /// The size the frames are scaled to, as `(width, height)`, or the size of
+  /// the video.
+  pub scale: Option<(u32, u32)>,
+}
+
+impl Default for DecodeSettings {
+  fn default() -> Self {
+    Self {
+      pix_fmt: "rgb24".to_string(),
+      scale: None,
+    }
+  }
+}
+
+/// What's known about the video of a [`FrameSource`], probed once when it's
+/// opened.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SourceInfo {
+  pub duration: Option<Duration>,
+  /// The size of the video, before the `scale` of the [`DecodeSettings`].
+  pub width: u32,
+  pub height: u32,
+  pub frame_rate: Option<Rate>,
+}
+
+/// Returned (through `anyhow::Error`) when a [`FrameSource`] can't provide
+/// a frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FrameSourceError {
+  /// The input has no video stream.
+  NoVideo(PathBuf),
+  /// ffmpeg decoded no frame from this time on, e.g. since it's past the
+  /// end of the video.
+  NoFrameAt(Duration),
+}
+
+impl fmt::Display for FrameSourceError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      FrameSourceError::NoVideo(input) => write!(f, "{} has no video stream", input.display()),
+      FrameSourceError::NoFrameAt(at) => {
+        write!(f, "no frame was decoded at {:.3}s", at.as_secs_f64())
+      }
+    }
+  }
+}
+
+impl std::error::Error for FrameSourceError {}
+
+/// A video which can be decoded from any point, restarting ffmpeg with the
+/// same [`DecodeSettings`] each time.
+///
+/// Each seek spawns a fresh ffmpeg which seeks the input (`-ss` before
+/// `-i`), so it starts decoding at the keyframe before the requested time
+/// instead of from the start. Only one ffmpeg runs at a time: the previous
+/// one is killed and waited for before the next one is spawned, and when the
+/// source is dropped.
+///
+/// ```rust,no_run
+/// use ffmpeg_sidecar::frame_source::{DecodeSettings, FrameSource};
+/// use std::time::Duration;
+///
+/// let mut source = FrameSource::open("movie.mp4", DecodeSettings::default()).unwrap();
+/// let thumbnail = source.frame_at(Duration::from_secs(60)).unwrap();
+/// for frame in source.frames_from(Duration::from_secs(30)).unwrap().take(10) {
+///   println!("frame at {:.3}s", frame.timestamp);
+/// }
+/// ```
+pub struct FrameSource {
+  input: PathBuf,
+  settings: DecodeSettings,
+  info: SourceInfo,
+  child: Option<FfmpegChild>,
+}
+
+impl FrameSource {
+  /// Probe `input` with ffprobe, which is only done once for all the seeks.
+  pub fn open<P: AsRef<Path>>(input: P, settings: DecodeSettings) -> anyhow::Result<Self> {
+    let input = input.as_ref().to_path_buf();
+    let media = probe_cached(&input)?;
+    let Some(video) = media.streams_of_type("video").next() else {
+      return Err(FrameSourceError::NoVideo(input).into());
+    };
+    let info = SourceInfo {
+      duration: media
+        .duration
+        .or(video.duration)
+        .filter(|duration| duration.is_finite() && *duration >= 0.0)
+        .map(Duration::from_secs_f64),
+      width: video.width.unwrap_or(0),
+      height: video.height.unwrap_or(0),
+      frame_rate: video.r_frame_rate,
+    };
+    Ok(Self {
+      input,
+      settings,
+      info,
+      child: None,
+    })
+  }
+
+  pub fn info(&self) -> &SourceInfo {
+    &self.info
+  }
+
+  pub fn settings(&self) -> &DecodeSettings {
+    &self.settings
+  }
+
+  /// The frames from `timestamp` to the end of the video, with their
+  /// `timestamp` counted from the start of the video rather than from the
+  /// seek. Stops the ffmpeg of the previous seek first.
+  pub fn frames_from(&mut self, timestamp: Duration) -> anyhow::Result<FrameIter<'_>> {
+    self.stop();
+    let mut child = self.command(timestamp).spawn()?;
+    let iter = child.iter();
+    self.child = Some(child);
+    Ok(FrameIter {
+      iter: iter?,
+      offset: timestamp.as_secs_f32(),
+      _source: PhantomData,
+    })
+  }
+
+  /// The first frame at or after `timestamp`, decoding only that frame.
+  pub fn frame_at(&mut self, timestamp: Duration) -> anyhow::Result<OutputVideoFrame> {
+    self.stop();
+    let mut command = self.command(timestamp);
+    command.frames(1);
+    let mut child = command.spawn()?;
+    let frame = child.iter().map(|iter| iter.filter_frames().next());
+    self.child = Some(child);
+    self.stop();
+    let mut frame = frame?.ok_or(FrameSourceError::NoFrameAt(timestamp))?;
+    frame.timestamp += timestamp.as_secs_f32();
+    Ok(frame)
+  }
+
+  /// Kill the ffmpeg of the last seek, if it's still running, and wait for
+  /// it to exit.
+  pub fn stop(&mut self) {
+    if let Some(mut child) = self.child.take() {
+      // It may have exited already, at the end of the video
+      child.kill().ok();
+      child.wait().ok();
+    }
+  }
+
+  /// The process id of the ffmpeg of the last seek, until it's stopped,
+  /// e.g. to monitor its resource usage.
+  pub fn child_id(&mut self) -> Option<u32> {
+    self.child.as_ref().map(FfmpegChild::id)
+  }
+
+  fn command(&self, timestamp: Duration) -> FfmpegCommand {
+    let mut command = FfmpegCommand::new();
+    command
+      .seek(timestamp)
+      .input(self.input.to_string_lossy())
+      .args(["-map", "0:v:0"]);
+    if let Some((width, height)) = self.settings.scale {
+      command.size(width, height);
+    }
+    command
+      .args(["-f", "rawvideo", "-pix_fmt", &self.settings.pix_fmt])
+      .output("-");
+    command
+  }
+}
+
+impl Drop for FrameSource {
+  fn drop(&mut self) {
+    self.stop();
+  }
+}
+
+/// The frames of a seek through a [`FrameSource`], from
+/// [`FrameSource::frames_from`]. Borrows the source, so it has to be dropped
+/// before seeking again.
+pub struct FrameIter<'a> {
+  iter: FfmpegIterator,
+  /// The time of the seek, added to the timestamps of the frames
+  offset: f32,
+  _source: PhantomData<&'a mut FrameSource>,
+}
+
+impl Iterator for FrameIter<'_> {
+  type Item = OutputVideoFrame;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    self.iter.find_map(|event| match event {
+      FfmpegEvent::OutputFrame(mut frame) => {
+        frame.timestamp += self.offset;
+        Some(frame)
+      }
+      _ => None,
+    })
+  }
+}
+
+/// The frames of a video returned by [`extract_frames`], as a half-open
+/// range.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FrameRange {
+  /// The frames with these indices, counting the decoded frames of the video
+  /// from 0.
+  Indices(Range<u64>),
+  /// The frames whose timestamp is at least the start and less than the
+  /// end, counted from the start of the video.
+  Time(Range<Duration>),
+}
+
+/// How the frames of [`extract_frames`] are decoded.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FrameOptions {
+  /// The size the frames are scaled to, as `(width, height)`, or the size of
+  /// the video.
+  pub size: Option<(u32, u32)>,
+  /// The pixel format of the frames, as passed to `-pix_fmt`. Defaults to
+  /// `rgb24`.
+  pub pix_fmt: String,
+  /// Keep only the first frame of the range and every `every_nth` after it,
+  /// `1` (all of them) by default. The others are dropped by ffmpeg before
+  /// being scaled or converted.
+  pub every_nth: u32,
+}
+
+impl Default for FrameOptions {
+  fn default() -> Self {
+    Self {
+      size: None,
+      pix_fmt: "rgb24".to_string(),
+      every_nth: 1,
+    }
+  }
+}
+
+/// Decode exactly the frames of `input` in `range`, e.g. to build a dataset.
+///
+/// The range is cut by a `trim` filter, so no frame is missed or added at
+/// its ends, and `every_nth` subsampling is done by a `select` filter. A
+/// range of indices is decoded from the start of the video, while a time
+/// range seeks the input first (`-ss` before `-i`), decoding from the
+/// keyframe before the start and dropping the frames before it. ffmpeg stops
+/// after the last frame of the range.
+///
+/// The `frame_num` of the frames is their index in the video for
+/// [`FrameRange::Indices`], or counted from the first frame of the range for
+/// [`FrameRange::Time`], and their `timestamp` is counted from the start of
+/// the video, from the frame rate.
+///
+/// ```rust,no_run
+/// use ffmpeg_sidecar::frame_source::{extract_frames, FrameOptions, FrameRange};
+///
+/// let options = FrameOptions {
+///   size: Some((224, 224)),
+///   every_nth: 5,
+///   ..Default::default()
+/// };
+/// for frame in extract_frames("movie.mp4", FrameRange::Indices(100..200), &options).unwrap() {
+///   println!("frame {} at {:.3}s", frame.frame_num, frame.timestamp);
+/// }
+/// ```
+pub fn extract_frames<P: AsRef<Path>>(
+  input: P,
+  range: FrameRange,
+  options: &FrameOptions,
+) -> anyhow::Result<RangeFrames> {
+  let input = input.as_ref();
+  let media = probe_cached(input)?;
+  let Some(video) = media.streams_of_type("video").next() else {
+    return Err(FrameSourceError::NoVideo(input.to_path_buf()).into());
+  };
+  let every_nth = options.every_nth.max(1);
+  let rate = video.r_frame_rate.map_or(0.0, |rate| rate.as_f64());
+
+  let mut command = FfmpegCommand::new();
+  let (trim, first_frame, offset) = match &range {
+    FrameRange::Indices(indices) => {
+      let start = indices.start as f64 / rate;
+      let offset = if start.is_finite() { start } else { 0.0 };
+      let trim = format!(
+        "trim=start_frame={}:end_frame={}",
+        indices.start, indices.end
+      );
+      (trim, indices.start, offset)
+    }
+    FrameRange::Time(times) => {
+      // Timestamps start from the seek, after the frames before it are
+      // dropped
+      let duration = times.end.saturating_sub(times.start);
+      command.seek(times.start);
+      let trim = format!("trim=end={:.6}", duration.as_secs_f64());
+      (trim, 0, times.start.as_secs_f64())
+    }
+  };
+  let mut filters = vec![trim];
+  if every_nth > 1 {
+    filters.push(format!("select=not(mod(n\\,{every_nth}))"));
+  }
+  // One output frame per frame kept, which the constant frame rate of raw
+  // video would otherwise fill the gaps between with duplicates
+  filters.push("setpts=N/FRAME_RATE/TB".to_string());
+  if let Some((width, height)) = options.size {
+    filters.push(format!("scale={width}:{height}"));
+  }
+  command
+    .input(input.to_string_lossy())
+    .args(["-map", "0:v:0"])
+    .filter(filters.join(","))
+    .args(["-f", "rawvideo", "-pix_fmt", &options.pix_fmt])
+    .output("-");
+
+  let is_empty = match &range {
+    FrameRange::Indices(indices) => indices.is_empty(),
+    FrameRange::Time(times) => times.is_empty(),
+  };
+  let (child, iter) = if is_empty {
+    (None, None)
+  } else {
+    let mut child = command.spawn()?;
+    let iter = child.iter()?;
+    (Some(child), Some(iter))
+  };
+  Ok(RangeFrames {
+    child,
+    iter,
+    first_frame,
+    every_nth,
+    offset: offset as f32,
+  })
+}
+
+/// The frames of [`extract_frames`]. Kills ffmpeg when dropped before the
+/// end of the range.
+pub struct RangeFrames {
+  child: Option<FfmpegChild>,
+  iter: Option<FfmpegIterator>,
+  first_frame: u64,
+  every_nth: u32,
+  /// The time of the first frame of the range
+  offset: f32,
+}
+
+impl Iterator for RangeFrames {
+  type Item = OutputVideoFrame;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    let mut frame = self.iter.as_mut()?.find_map(|event| match event {
+      FfmpegEvent::OutputFrame(frame) => Some(frame),
+      _ => None,
+    })?;
+    frame.frame_num =
+      (self.first_frame + u64::from(frame.frame_num) * u64::from(self.every_nth)) as u32;
+    frame.timestamp = self.offset + frame.timestamp * self.every_nth as f32;
+    Some(frame)
+  }
+}
+
+impl Drop for RangeFrames {
+  fn drop(&mut self) {
+    if let Some(mut child) = self.child.take() {
+      child.kill().ok();
+      child.wait().ok();
+    }
+  }
+}
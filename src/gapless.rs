@@ -0,0 +1,184 @@
+//! Keeping the gapless playback information of re-encoded audio. See
+//! [`FfmpegArgs::audio_gapless`](crate::args::FfmpegArgs::audio_gapless).
+//!
+//! Lossy encoders like AAC, MP3 and Opus start with a delay of silent
+//! "priming" samples and pad the last frame, so the decoded audio is longer
+//! than the source unless the container tells the player how many samples
+//! to drop at both ends. Without that, albums meant to play continuously get
+//! a gap between tracks, and loops click.
+//!
+//! | Container | Stored as | Codecs |
+//! |-----------|-----------|--------|
+//! | MP4, M4A, MOV | Edit list (`-use_editlist 1`) | Any |
+//! | MP3 | LAME header (`-write_xing 1`) | MP3 |
+//! | Ogg, Opus | Pre-skip and granule positions | Any |
+//! | Matroska, WebM | `CodecDelay` and `DiscardPadding` | Opus only |
+//! | WAV, FLAC, AIFF | Nothing to store | Lossless only |
+//!
+//! The other containers, like raw ADTS `.aac`, MPEG-TS, FLV and AVI, have
+//! nowhere to store it, and ffmpeg doesn't write the `iTunSMPB` tag of
+//! iTunes, so those outputs get a [`GaplessWarning`] instead.
+
+use std::{fmt, path::Path};
+
+use crate::container::ContainerFormat;
+
+/// Why an output can't keep the gapless playback information.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum GaplessLimitation {
+  /// The container has nowhere to store the encoder delay and padding, like
+  /// ADTS, MPEG-TS, FLV or AVI.
+  Container(ContainerFormat),
+  /// ffmpeg only stores the delay of some codecs in this container, e.g.
+  /// the `CodecDelay` of Matroska is only written for Opus.
+  Codec {
+    codec: String,
+    container: ContainerFormat,
+  },
+  /// The format of the output is neither given with `-f` nor known from its
+  /// extension.
+  UnknownFormat,
+}
+
+impl fmt::Display for GaplessLimitation {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      GaplessLimitation::Container(container) => {
+        write!(f, "{container} can't store the encoder delay and padding")
+      }
+      GaplessLimitation::Codec { codec, container } => write!(
+        f,
+        "ffmpeg doesn't store the encoder delay and padding of {codec} in {container}"
+      ),
+      GaplessLimitation::UnknownFormat => f.write_str("the format of the output isn't known"),
+    }
+  }
+}
+
+/// An output of [`audio_gapless`](crate::args::FfmpegArgs::audio_gapless)
+/// which can't keep the gapless playback information, reported as a hint
+/// when spawning.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GaplessWarning {
+  pub output: String,
+  pub limitation: GaplessLimitation,
+}
+
+impl fmt::Display for GaplessWarning {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "{}: {}, so its audio won't play gaplessly",
+      self.output, self.limitation
+    )
+  }
+}
+
+impl std::error::Error for GaplessWarning {}
+
+/// The output options keeping the gapless playback information of audio
+/// encoded with `codec` (`None` for the default encoder of the format) in
+/// the output `path`, whose muxer is `format` or comes from the extension.
+///
+/// ```rust
+/// use ffmpeg_sidecar::{container::ContainerFormat, gapless::{gapless_args, GaplessLimitation}};
+///
+/// assert_eq!(gapless_args(Some("aac"), None, "song.m4a").unwrap(), ["-use_editlist", "1"]);
+/// assert!(gapless_args(Some("libopus"), None, "song.mka").unwrap().is_empty());
+/// assert_eq!(
+///   gapless_args(Some("aac"), None, "song.aac"),
+///   Err(GaplessLimitation::Container(ContainerFormat::Adts))
+/// );
+/// assert!(matches!(
+///   gapless_args(Some("aac"), None, "song.mka"),
+///   Err(GaplessLimitation::Codec { .. })
+/// ));
+/// ```
+pub fn gapless_args(
+  codec: Option<&str>,
+  format: Option<&str>,
+  path: &str,
+) -> Result<Vec<String>, GaplessLimitation> {
+  let container = match format {
+    Some(format) => ContainerFormat::from_format_name(format),
+    None => ContainerFormat::from_path(path).ok_or(GaplessLimitation::UnknownFormat)?,
+  };
+  let args = |args: [&str; 2]| Ok(args.map(str::to_string).to_vec());
+  match &container {
+    ContainerFormat::Mp4 => args(["-use_editlist", "1"]),
+    ContainerFormat::Mp3 => args(["-write_xing", "1"]),
+    ContainerFormat::Ogg => Ok(Vec::new()),
+    _ if codec.is_some_and(is_lossless) => Ok(Vec::new()),
+    ContainerFormat::Matroska => {
+      let is_webm = format.map_or_else(
+        || {
+          Path::new(path)
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("webm"))
+        },
+        |format| format == "webm",
+      );
+      match codec {
+        Some("libopus" | "opus") => Ok(Vec::new()),
+        // Opus is the default audio encoder of WebM
+        None if is_webm => Ok(Vec::new()),
+        codec => Err(GaplessLimitation::Codec {
+          codec: codec.unwrap_or("the default encoder").to_string(),
+          container,
+        }),
+      }
+    }
+    ContainerFormat::Wav => Ok(Vec::new()),
+    ContainerFormat::Other(name) if matches!(name.as_str(), "flac" | "aiff" | "w64") => {
+      Ok(Vec::new())
+    }
+    _ => Err(GaplessLimitation::Container(container)),
+  }
+}
+
+/// Codecs without an encoder delay, which have nothing to lose.
+fn is_lossless(codec: &str) -> bool {
+  codec.starts_with("pcm_") || matches!(codec, "flac" | "alac" | "wavpack" | "tta")
+}
+
+/// The encoder delay and padding of an input, from its `iTunSMPB` tag. See
+/// [`FfmpegMetadata::gapless_info`](crate::metadata::FfmpegMetadata::gapless_info).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GaplessInfo {
+  /// The priming samples at the start, per channel.
+  pub encoder_delay: u32,
+  /// The samples padding the last frame, per channel.
+  pub padding: u32,
+  /// The samples of the source, per channel, if the tag has them.
+  pub samples: Option<u64>,
+}
+
+impl GaplessInfo {
+  /// Parse the value of an `iTunSMPB` tag, written by iTunes and most AAC
+  /// encoders: space separated hexadecimal fields, the second being the
+  /// delay, the third the padding and the fourth the samples of the source.
+  ///
+  /// ```rust
+  /// use ffmpeg_sidecar::gapless::GaplessInfo;
+  ///
+  /// let info = GaplessInfo::from_itunsmpb(
+  ///   " 00000000 00000840 000001CA 00000000003F31F6 00000000 00000000 00000000",
+  /// )
+  /// .unwrap();
+  /// assert_eq!((info.encoder_delay, info.padding, info.samples), (2112, 458, Some(4141558)));
+  /// ```
+  pub fn from_itunsmpb(value: &str) -> Option<Self> {
+    let mut fields = value.split_whitespace().skip(1);
+    let encoder_delay = u32::from_str_radix(fields.next()?, 16).ok()?;
+    let padding = u32::from_str_radix(fields.next()?, 16).ok()?;
+    let samples = fields
+      .next()
+      .and_then(|samples| u64::from_str_radix(samples, 16).ok())
+      .filter(|samples| *samples > 0);
+    Some(Self {
+      encoder_delay,
+      padding,
+      samples,
+    })
+  }
+}
@@ -0,0 +1,174 @@
+//! Filter chains for fitting video into a target size, and cropping it.
+//!
+//! The chains only use ffmpeg expressions (`iw`, `ih`, `sar`, ...), so they
+//! work without knowing the input dimensions in advance. Target dimensions
+//! are rounded up to even numbers, as required by yuv420p and most encoders.
+
+use std::fmt;
+
+/// How [`fit_filter`] fills the target size when the input has a different
+/// aspect ratio.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum FitMode {
+  /// Scale to fit inside the target, and pad the rest with the background
+  /// color (letterboxing or pillarboxing).
+  #[default]
+  Contain,
+  /// Scale to fill the target, and crop what overflows at the center.
+  Cover,
+  /// Scale to exactly the target, distorting the aspect ratio.
+  Stretch,
+}
+
+/// A rectangle in pixels, with its top left corner at `x`, `y`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Rect {
+  pub x: u32,
+  pub y: u32,
+  pub width: u32,
+  pub height: u32,
+}
+
+impl Rect {
+  pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+    Self {
+      x,
+      y,
+      width,
+      height,
+    }
+  }
+
+  /// Check that the rect is non-empty and lies inside a frame of the given
+  /// size, for inputs whose dimensions are already known.
+  ///
+  /// ```rust
+  /// use ffmpeg_sidecar::geometry::Rect;
+  /// assert!(Rect::new(0, 0, 640, 480).check_within(640, 480).is_ok());
+  /// assert!(Rect::new(1, 0, 640, 480).check_within(640, 480).is_err());
+  /// ```
+  pub fn check_within(&self, width: u32, height: u32) -> Result<(), GeometryError> {
+    self.check_size()?;
+    let fits =
+      |offset: u32, size: u32, max: u32| offset.checked_add(size).is_some_and(|end| end <= max);
+    if !fits(self.x, self.width, width) || !fits(self.y, self.height, height) {
+      return Err(GeometryError::OutOfBounds {
+        rect: *self,
+        width,
+        height,
+      });
+    }
+    Ok(())
+  }
+
+  fn check_size(&self) -> Result<(), GeometryError> {
+    if self.width == 0 || self.height == 0 {
+      return Err(GeometryError::EmptySize(self.width, self.height));
+    }
+    Ok(())
+  }
+}
+
+/// Returned (through `anyhow::Error`) for a target size or crop rect which
+/// can't produce any output.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GeometryError {
+  /// A width or height of zero.
+  EmptySize(u32, u32),
+  /// The rect doesn't fit inside the input frame.
+  OutOfBounds { rect: Rect, width: u32, height: u32 },
+}
+
+impl fmt::Display for GeometryError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      GeometryError::EmptySize(width, height) => {
+        write!(f, "size must be non-zero, got {width}x{height}")
+      }
+      GeometryError::OutOfBounds {
+        rect,
+        width,
+        height,
+      } => write!(
+        f,
+        "crop {}x{} at {},{} doesn't fit inside {width}x{height}",
+        rect.width, rect.height, rect.x, rect.y
+      ),
+    }
+  }
+}
+
+impl std::error::Error for GeometryError {}
+
+/// The filter chain which fits video into `width`x`height`, both rounded up
+/// to even numbers. Non-square pixels are resampled to square ones first
+/// (`setsar=1`), so the aspect ratio is the displayed one.
+///
+/// ```rust
+/// use ffmpeg_sidecar::geometry::{fit_filter, FitMode};
+/// assert_eq!(
+///   fit_filter(1280, 720, FitMode::Stretch, "black").unwrap(),
+///   "scale=1280:720,setsar=1"
+/// );
+/// assert_eq!(
+///   fit_filter(1279, 719, FitMode::Contain, "black").unwrap(),
+///   "scale=trunc(iw*sar/2)*2:ih,setsar=1,\
+///    scale=1280:720:force_original_aspect_ratio=decrease:force_divisible_by=2,\
+///    pad=1280:720:(ow-iw)/2:(oh-ih)/2:color=black"
+/// );
+/// ```
+pub fn fit_filter(
+  width: u32,
+  height: u32,
+  mode: FitMode,
+  background_color: &str,
+) -> Result<String, GeometryError> {
+  if width == 0 || height == 0 {
+    return Err(GeometryError::EmptySize(width, height));
+  }
+  let (width, height) = (round_up_even(width), round_up_even(height));
+  let square_pixels = "scale=trunc(iw*sar/2)*2:ih,setsar=1";
+  Ok(match mode {
+    FitMode::Contain => format!(
+      "{square_pixels},\
+       scale={width}:{height}:force_original_aspect_ratio=decrease:force_divisible_by=2,\
+       pad={width}:{height}:(ow-iw)/2:(oh-ih)/2:color={background_color}"
+    ),
+    FitMode::Cover => format!(
+      "{square_pixels},\
+       scale={width}:{height}:force_original_aspect_ratio=increase:force_divisible_by=2,\
+       crop={width}:{height}"
+    ),
+    FitMode::Stretch => format!("scale={width}:{height},setsar=1"),
+  })
+}
+
+/// The filter which crops video to `rect`. Since the input size may not be
+/// known yet, the bounds are checked by ffmpeg: a rect which doesn't fit
+/// inside the input makes the crop width or height zero, and ffmpeg fails
+/// with an "Invalid too big or non positive size" error.
+///
+/// ```rust
+/// use ffmpeg_sidecar::geometry::{crop_filter, Rect};
+/// assert_eq!(
+///   crop_filter(Rect::new(10, 20, 320, 240)).unwrap(),
+///   "crop=w='if(lte(10+320,iw),320,0)':h='if(lte(20+240,ih),240,0)':x=10:y=20"
+/// );
+/// assert!(crop_filter(Rect::new(0, 0, 0, 240)).is_err());
+/// ```
+pub fn crop_filter(rect: Rect) -> Result<String, GeometryError> {
+  rect.check_size()?;
+  let Rect {
+    x,
+    y,
+    width,
+    height,
+  } = rect;
+  Ok(format!(
+    "crop=w='if(lte({x}+{width},iw),{width},0)':h='if(lte({y}+{height},ih),{height},0)':x={x}:y={y}"
+  ))
+}
+
+pub(crate) fn round_up_even(n: u32) -> u32 {
+  n.saturating_add(n % 2)
+}
@@ -0,0 +1,320 @@
+//! Composing several videos into a grid, e.g. an original next to its
+//! encodes, or a wall of camera feeds. See
+//! [`FfmpegCommand::compose_grid`](crate::command::FfmpegCommand::compose_grid).
+
+use std::fmt;
+
+use crate::{
+  drawtext::{drawtext_filter, FontSpec, TextOverlay},
+  geometry::{fit_filter, round_up_even, FitMode},
+  mix::{mix_filter, AudioMixInput, MixDuration, MixOptions},
+};
+
+/// The label of the grid in the filtergraph added by
+/// [`FfmpegCommand::compose_grid`](crate::command::FfmpegCommand::compose_grid),
+/// which is mapped to the output as `-map [grid]`.
+pub const GRID_OUTPUT_LABEL: &str = "grid";
+
+/// The label of the mixed audio of the grid, with [`GridAudio::Mix`], which
+/// is mapped to the output as `-map [gridaudio]`.
+pub const GRID_AUDIO_LABEL: &str = "gridaudio";
+
+/// A cell of [`FfmpegCommand::compose_grid`](crate::command::FfmpegCommand::compose_grid).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GridInput {
+  pub path: String,
+  /// Text drawn in the top left corner of the cell, like `original` or
+  /// `crf 28`.
+  pub label: Option<String>,
+}
+
+impl GridInput {
+  pub fn new<S: AsRef<str>>(path: S) -> Self {
+    Self {
+      path: path.as_ref().to_string(),
+      label: None,
+    }
+  }
+
+  pub fn labeled<S: AsRef<str>, L: Into<String>>(path: S, label: L) -> Self {
+    Self {
+      label: Some(label.into()),
+      ..Self::new(path)
+    }
+  }
+}
+
+/// When the grid ends, if its inputs have different durations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum DurationPolicy {
+  /// With the input which ends first.
+  #[default]
+  Shortest,
+  /// With the input which ends last, the others freezing on their last
+  /// frame.
+  LongestWithFreeze,
+  /// With the input which ends last, the cells of the others turning to the
+  /// background color. The grid is one frame longer than that input, since
+  /// the cells turn by ending with a frame of the background color.
+  LongestWithBackground,
+}
+
+/// The audio of the grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum GridAudio {
+  /// No audio.
+  #[default]
+  None,
+  /// The audio of the cell at this position among the inputs of the grid.
+  Input(usize),
+  /// The audio of every input, mixed with `amix`. Every input must have
+  /// audio.
+  Mix,
+}
+
+/// Options for [`FfmpegCommand::compose_grid`](crate::command::FfmpegCommand::compose_grid).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GridOptions {
+  /// The number of cells per row. With fewer inputs, a single row of them.
+  pub columns: usize,
+  /// The size of each cell, rounded up to even numbers. Inputs are scaled
+  /// to fit inside, keeping their aspect ratio, and padded.
+  pub cell_size: (u32, u32),
+  /// Pixels between the cells and around them.
+  pub border: u32,
+  /// The color of the borders, the padding, and the empty cells of the last
+  /// row: a name like `black`, or `#RRGGBB`.
+  pub background: String,
+  pub duration_policy: DurationPolicy,
+  pub audio: GridAudio,
+  /// Font size of the labels in pixels.
+  pub label_size: u32,
+  pub label_font: FontSpec,
+}
+
+impl Default for GridOptions {
+  fn default() -> Self {
+    Self {
+      columns: 2,
+      cell_size: (640, 360),
+      border: 0,
+      background: "black".to_string(),
+      duration_policy: DurationPolicy::Shortest,
+      audio: GridAudio::None,
+      label_size: 24,
+      label_font: FontSpec::Default,
+    }
+  }
+}
+
+/// Returned (through `anyhow::Error`) by [`grid_filter`] for a grid which
+/// can't be composed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GridError {
+  NoInputs,
+  NoColumns,
+  /// [`GridAudio::Input`] is past the last input.
+  AudioInputOutOfRange {
+    index: usize,
+    inputs: usize,
+  },
+}
+
+impl fmt::Display for GridError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      GridError::NoInputs => write!(f, "a grid needs at least 1 input"),
+      GridError::NoColumns => write!(f, "a grid needs at least 1 column"),
+      GridError::AudioInputOutOfRange { index, inputs } => {
+        write!(
+          f,
+          "no input {index} to take the audio from, the grid has {inputs}"
+        )
+      }
+    }
+  }
+}
+
+impl std::error::Error for GridError {}
+
+/// The width and height of the grid of `inputs` cells, borders included.
+///
+/// ```rust
+/// use ffmpeg_sidecar::grid::{grid_size, GridOptions};
+///
+/// let options = GridOptions {
+///   columns: 2,
+///   cell_size: (320, 180),
+///   border: 4,
+///   ..Default::default()
+/// };
+/// assert_eq!(grid_size(3, &options), (652, 372));
+/// assert_eq!(grid_size(1, &options), (328, 188));
+/// ```
+pub fn grid_size(inputs: usize, options: &GridOptions) -> (u32, u32) {
+  let (columns, rows) = grid_shape(inputs, options.columns);
+  let (width, height) = cell_size(options);
+  (
+    columns * (width + options.border) + options.border,
+    rows * (height + options.border) + options.border,
+  )
+}
+
+/// The filtergraph which composes the video of `inputs`, read from the
+/// inputs numbered from `first_input_index` on, into a grid labeled `output`,
+/// and with [`GridAudio::Mix`], their audio into a stream labeled
+/// `audio_output`.
+///
+/// Each input is scaled to fit its cell (see [`fit_filter`]) and converted
+/// to `yuv420p`, since the stacking filters need a single pixel format. A
+/// single row is stacked with `hstack`, a single column with `vstack`, and
+/// other grids with `xstack`. Fails with a [`GridError`], or if no font is
+/// found for the labels.
+///
+/// ```rust
+/// use ffmpeg_sidecar::grid::{grid_filter, DurationPolicy, GridInput, GridOptions};
+///
+/// let inputs = [GridInput::new("a.mp4"), GridInput::new("b.mp4"), GridInput::new("c.mp4")];
+/// let options = GridOptions {
+///   cell_size: (320, 180),
+///   duration_policy: DurationPolicy::LongestWithFreeze,
+///   ..Default::default()
+/// };
+/// let fit = "scale=trunc(iw*sar/2)*2:ih,setsar=1,\
+///   scale=320:180:force_original_aspect_ratio=decrease:force_divisible_by=2,\
+///   pad=320:180:(ow-iw)/2:(oh-ih)/2:color=black,format=yuv420p";
+/// assert_eq!(
+///   grid_filter(0, &inputs, &options, "out", "audio").unwrap(),
+///   format!(
+///     "[0:v]{fit}[cell0];[1:v]{fit}[cell1];[2:v]{fit}[cell2];\
+///      [cell0][cell1][cell2]xstack=inputs=3:layout=0_0|320_0|0_180:fill=black[out]"
+///   )
+/// );
+/// ```
+pub fn grid_filter(
+  first_input_index: usize,
+  inputs: &[GridInput],
+  options: &GridOptions,
+  output: &str,
+  audio_output: &str,
+) -> anyhow::Result<String> {
+  if inputs.is_empty() {
+    return Err(GridError::NoInputs.into());
+  }
+  if options.columns == 0 {
+    return Err(GridError::NoColumns.into());
+  }
+  if let GridAudio::Input(index) = options.audio {
+    if index >= inputs.len() {
+      return Err(
+        GridError::AudioInputOutOfRange {
+          index,
+          inputs: inputs.len(),
+        }
+        .into(),
+      );
+    }
+  }
+
+  let (width, height) = cell_size(options);
+  let (border, background) = (options.border, &options.background);
+  let mut graph = String::new();
+  let mut cells = String::new();
+  for (i, input) in inputs.iter().enumerate() {
+    let mut chain = vec![
+      fit_filter(width, height, FitMode::Contain, background)?,
+      "format=yuv420p".to_string(),
+    ];
+    if let Some(label) = &input.label {
+      chain.push(drawtext_filter(&TextOverlay {
+        size: options.label_size,
+        font: options.label_font.clone(),
+        ..TextOverlay::new(label.as_str())
+      })?);
+    }
+    // The border above and to the left of the cell
+    if border > 0 {
+      chain.push(format!(
+        "pad={}:{}:{border}:{border}:color={background}",
+        width + border,
+        height + border
+      ));
+    }
+    // The stacking filters repeat the last frame of an input once it ends
+    if options.duration_policy == DurationPolicy::LongestWithBackground {
+      chain.push(format!("tpad=stop=1:color={background}"));
+    }
+    graph.push_str(&format!(
+      "[{}:v]{}[cell{i}];",
+      first_input_index + i,
+      chain.join(",")
+    ));
+    cells.push_str(&format!("[cell{i}]"));
+  }
+
+  let (columns, rows) = grid_shape(inputs.len(), options.columns);
+  let shortest = options.duration_policy == DurationPolicy::Shortest;
+  let mut stack = match (inputs.len(), columns, rows) {
+    (1, _, _) => "null".to_string(),
+    (count, _, 1) => format!("hstack=inputs={count}"),
+    (count, 1, _) => format!("vstack=inputs={count}"),
+    (count, _, _) => {
+      let layout = (0..count)
+        .map(|i| {
+          let (column, row) = (i as u32 % columns, i as u32 / columns);
+          format!("{}_{}", column * (width + border), row * (height + border))
+        })
+        .collect::<Vec<_>>()
+        .join("|");
+      let mut xstack = format!("xstack=inputs={count}:layout={layout}");
+      if count < (columns * rows) as usize {
+        xstack.push_str(&format!(":fill={background}"));
+      }
+      xstack
+    }
+  };
+  if shortest && inputs.len() > 1 {
+    stack.push_str(":shortest=1");
+  }
+  // The border below and to the right of the last cells
+  if border > 0 {
+    let (grid_width, grid_height) = grid_size(inputs.len(), options);
+    stack.push_str(&format!(
+      ",pad={grid_width}:{grid_height}:0:0:color={background}"
+    ));
+  }
+  graph.push_str(&format!("{cells}{stack}[{output}]"));
+
+  if options.audio == GridAudio::Mix {
+    let audio_inputs = inputs
+      .iter()
+      .map(|input| AudioMixInput::new(&input.path))
+      .collect::<Vec<_>>();
+    let mix_options = MixOptions {
+      duration_policy: match shortest {
+        true => MixDuration::Shortest,
+        false => MixDuration::Longest,
+      },
+      ..Default::default()
+    };
+    graph.push(';');
+    graph.push_str(&mix_filter(
+      first_input_index,
+      &audio_inputs,
+      audio_output,
+      &mix_options,
+    ));
+  }
+  Ok(graph)
+}
+
+/// The number of columns and rows of a grid of `inputs` cells.
+fn grid_shape(inputs: usize, columns: usize) -> (u32, u32) {
+  let columns = columns.clamp(1, inputs.max(1));
+  (columns as u32, inputs.div_ceil(columns) as u32)
+}
+
+fn cell_size(options: &GridOptions) -> (u32, u32) {
+  let (width, height) = options.cell_size;
+  (round_up_even(width), round_up_even(height))
+}
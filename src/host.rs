@@ -0,0 +1,338 @@
+//! The architecture of the machine, and whether this process runs under
+//! emulation, e.g. to tell users why ffmpeg is slower than expected, and the
+//! architecture an executable was built for.
+
+use std::{fmt, fs::File, io, io::Read, path::Path};
+
+#[cfg(feature = "download")]
+use crate::download::WINDOWS_ARM64_URL_VAR;
+
+/// A translation layer running code built for another architecture.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Emulation {
+  /// An x86_64 process on an Apple silicon Mac.
+  Rosetta2,
+  /// An x86_64 or x86 process on Windows on ARM.
+  WindowsOnArm,
+}
+
+impl fmt::Display for Emulation {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(match self {
+      Emulation::Rosetta2 => "Rosetta 2",
+      Emulation::WindowsOnArm => "the x64 emulation of Windows on ARM",
+    })
+  }
+}
+
+/// The architectures of this process and of the machine, from
+/// [`host_arch_info`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct HostArchInfo {
+  /// The architecture this process was built for, as in
+  /// [`std::env::consts::ARCH`], like `x86_64` or `aarch64`.
+  pub process_arch: &'static str,
+  /// The architecture of the CPU, which differs from `process_arch` under
+  /// emulation.
+  pub native_arch: &'static str,
+  /// The translation layer running this process, if any.
+  pub emulation: Option<Emulation>,
+}
+
+impl HostArchInfo {
+  pub fn is_emulated(&self) -> bool {
+    self.emulation.is_some()
+  }
+
+  /// Whether the ffmpeg installed by
+  /// [`auto_download`](crate::download::auto_download) runs under
+  /// emulation, like this process may. On Windows on ARM, the x86_64 build
+  /// is downloaded unless a native one is configured with
+  /// [`WINDOWS_ARM64_URL_VAR`].
+  #[cfg(feature = "download")]
+  pub fn downloaded_ffmpeg_is_emulated(&self) -> bool {
+    let native_download = std::env::var_os(WINDOWS_ARM64_URL_VAR).is_some();
+    self.is_emulated() || (cfg!(windows) && self.native_arch == "aarch64" && !native_download)
+  }
+}
+
+impl fmt::Display for HostArchInfo {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self.emulation {
+      Some(emulation) => write!(
+        f,
+        "{} process on {} through {emulation}, which is slower than a native build",
+        self.process_arch, self.native_arch
+      ),
+      None => write!(f, "native {} process", self.process_arch),
+    }
+  }
+}
+
+/// Detect the architecture of the machine, and whether this process runs
+/// under Rosetta 2 or the x64 emulation of Windows on ARM. Without a way to
+/// tell on the platform, the process is assumed to be native.
+///
+/// ```rust
+/// use ffmpeg_sidecar::host::host_arch_info;
+///
+/// let info = host_arch_info();
+/// if let Some(emulation) = info.emulation {
+///   eprintln!("Running under {emulation}, encoding will be slower");
+/// }
+/// ```
+pub fn host_arch_info() -> HostArchInfo {
+  let process_arch = std::env::consts::ARCH;
+  let native_arch = platform::native_arch().unwrap_or(process_arch);
+  let emulation = match native_arch {
+    "aarch64" if process_arch != native_arch && cfg!(target_os = "macos") => {
+      Some(Emulation::Rosetta2)
+    }
+    "aarch64" if process_arch != native_arch && cfg!(windows) => Some(Emulation::WindowsOnArm),
+    _ => None,
+  };
+  HostArchInfo {
+    process_arch,
+    native_arch,
+    emulation,
+  }
+}
+
+/// The executable formats recognized by [`parse_binary_header`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum BinaryFormat {
+  /// Linux and the BSDs
+  Elf,
+  /// macOS, for a single architecture
+  MachO,
+  /// macOS, for several architectures in one file
+  UniversalMachO,
+  /// Windows
+  Pe,
+}
+
+impl fmt::Display for BinaryFormat {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(match self {
+      BinaryFormat::Elf => "ELF",
+      BinaryFormat::MachO => "Mach-O",
+      BinaryFormat::UniversalMachO => "universal Mach-O",
+      BinaryFormat::Pe => "PE",
+    })
+  }
+}
+
+/// The format of an executable and the architectures it was built for,
+/// named as in [`std::env::consts::ARCH`], or `unknown`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct BinaryArch {
+  pub format: BinaryFormat,
+  pub archs: Vec<&'static str>,
+}
+
+impl BinaryArch {
+  /// Whether the executable has code for `arch`.
+  pub fn supports(&self, arch: &str) -> bool {
+    self.archs.contains(&arch)
+  }
+}
+
+impl fmt::Display for BinaryArch {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "{} {}", self.archs.join("+"), self.format)
+  }
+}
+
+/// Read the format and architectures of an executable from the first bytes
+/// of the file, or `None` if it isn't an ELF, Mach-O or PE executable, like
+/// a script.
+///
+/// ```rust
+/// use ffmpeg_sidecar::host::{parse_binary_header, BinaryFormat};
+///
+/// // An x86_64 Mach-O, as run by Rosetta 2 on Apple silicon
+/// let macho = [0xcf, 0xfa, 0xed, 0xfe, 0x07, 0x00, 0x00, 0x01];
+/// let arch = parse_binary_header(&macho).unwrap();
+/// assert_eq!((arch.format, arch.archs), (BinaryFormat::MachO, vec!["x86_64"]));
+///
+/// let mut elf = vec![0x7f, b'E', b'L', b'F', 2, 1, 1, 0];
+/// elf.resize(18, 0);
+/// elf.extend([0xb7, 0x00]);
+/// assert_eq!(parse_binary_header(&elf).unwrap().archs, ["aarch64"]);
+/// assert_eq!(parse_binary_header(b"#!/bin/sh\n"), None);
+/// ```
+pub fn parse_binary_header(bytes: &[u8]) -> Option<BinaryArch> {
+  let u16_at = |offset: usize, big_endian: bool| {
+    let bytes = bytes.get(offset..offset + 2)?.try_into().ok()?;
+    Some(match big_endian {
+      true => u16::from_be_bytes(bytes),
+      false => u16::from_le_bytes(bytes),
+    })
+  };
+  let u32_at = |offset: usize, big_endian: bool| {
+    let bytes = bytes.get(offset..offset + 4)?.try_into().ok()?;
+    Some(match big_endian {
+      true => u32::from_be_bytes(bytes),
+      false => u32::from_le_bytes(bytes),
+    })
+  };
+  let (format, archs) = match bytes.get(..4)? {
+    [0x7f, b'E', b'L', b'F'] => {
+      let machine = u16_at(18, *bytes.get(5)? == 2)?;
+      let arch = match machine {
+        0x03 => "x86",
+        0x28 => "arm",
+        0x3e => "x86_64",
+        0xb7 => "aarch64",
+        0xf3 => "riscv64",
+        0x15 => "powerpc64",
+        _ => "unknown",
+      };
+      (BinaryFormat::Elf, vec![arch])
+    }
+    [0xce | 0xcf, 0xfa, 0xed, 0xfe] => (BinaryFormat::MachO, vec![macho_arch(u32_at(4, false)?)]),
+    [0xca, 0xfe, 0xba, 0xbe] => {
+      // Java class files share the magic, with a version instead of the
+      // number of architectures
+      let count = u32_at(4, true)?;
+      if count == 0 || count > 16 {
+        return None;
+      }
+      let archs = (0..count as usize)
+        .map(|i| u32_at(8 + 20 * i, true).map(macho_arch))
+        .collect::<Option<Vec<_>>>()?;
+      (BinaryFormat::UniversalMachO, archs)
+    }
+    [b'M', b'Z', ..] => {
+      let header = u32_at(0x3c, false)? as usize;
+      if bytes.get(header..header + 4)? != b"PE\0\0" {
+        return None;
+      }
+      let arch = match u16_at(header + 4, false)? {
+        0x014c => "x86",
+        0x8664 => "x86_64",
+        0xaa64 => "aarch64",
+        _ => "unknown",
+      };
+      (BinaryFormat::Pe, vec![arch])
+    }
+    _ => return None,
+  };
+  Some(BinaryArch { format, archs })
+}
+
+fn macho_arch(cpu_type: u32) -> &'static str {
+  match cpu_type {
+    0x0000_0007 => "x86",
+    0x0100_0007 => "x86_64",
+    0x0000_000c => "arm",
+    0x0100_000c => "aarch64",
+    _ => "unknown",
+  }
+}
+
+/// The format and architectures of the executable at `path`, see
+/// [`parse_binary_header`].
+pub fn binary_arch<P: AsRef<Path>>(path: P) -> io::Result<Option<BinaryArch>> {
+  let mut header = Vec::with_capacity(4096);
+  File::open(path)?.take(4096).read_to_end(&mut header)?;
+  Ok(parse_binary_header(&header))
+}
+
+/// Reads `sysctl.proc_translated`, which is 1 for processes translated by
+/// Rosetta 2 and missing on Intel Macs.
+#[cfg(target_os = "macos")]
+mod platform {
+  use std::{
+    ffi::{c_char, c_int, c_void},
+    mem, ptr,
+  };
+
+  extern "C" {
+    fn sysctlbyname(
+      name: *const c_char,
+      old: *mut c_void,
+      old_size: *mut usize,
+      new: *mut c_void,
+      new_size: usize,
+    ) -> c_int;
+  }
+
+  pub(super) fn native_arch() -> Option<&'static str> {
+    let mut translated: c_int = 0;
+    let mut size = mem::size_of::<c_int>();
+    let result = unsafe {
+      sysctlbyname(
+        b"sysctl.proc_translated\0".as_ptr().cast(),
+        &mut translated as *mut c_int as *mut c_void,
+        &mut size,
+        ptr::null_mut(),
+        0,
+      )
+    };
+    match (result, translated) {
+      (0, 1) => Some("aarch64"),
+      _ => None,
+    }
+  }
+}
+
+/// Uses `IsWow64Process2`, looked up at runtime since it's missing before
+/// Windows 10 1709.
+#[cfg(windows)]
+mod platform {
+  use std::{
+    ffi::{c_char, c_void},
+    mem,
+  };
+
+  type Handle = *mut c_void;
+  type IsWow64Process2 = unsafe extern "system" fn(Handle, *mut u16, *mut u16) -> i32;
+
+  const IMAGE_FILE_MACHINE_I386: u16 = 0x014c;
+  const IMAGE_FILE_MACHINE_AMD64: u16 = 0x8664;
+  const IMAGE_FILE_MACHINE_ARM64: u16 = 0xaa64;
+
+  #[link(name = "kernel32")]
+  extern "system" {
+    fn GetModuleHandleA(name: *const c_char) -> Handle;
+    fn GetProcAddress(module: Handle, name: *const c_char) -> *mut c_void;
+    fn GetCurrentProcess() -> Handle;
+  }
+
+  pub(super) fn native_arch() -> Option<&'static str> {
+    let kernel32 = unsafe { GetModuleHandleA(b"kernel32.dll\0".as_ptr().cast()) };
+    if kernel32.is_null() {
+      return None;
+    }
+    let function = unsafe { GetProcAddress(kernel32, b"IsWow64Process2\0".as_ptr().cast()) };
+    if function.is_null() {
+      return None;
+    }
+    let is_wow64_process2 = unsafe { mem::transmute::<*mut c_void, IsWow64Process2>(function) };
+    let (mut process_machine, mut native_machine) = (0, 0);
+    if unsafe {
+      is_wow64_process2(
+        GetCurrentProcess(),
+        &mut process_machine,
+        &mut native_machine,
+      )
+    } == 0
+    {
+      return None;
+    }
+    match native_machine {
+      IMAGE_FILE_MACHINE_ARM64 => Some("aarch64"),
+      IMAGE_FILE_MACHINE_AMD64 => Some("x86_64"),
+      IMAGE_FILE_MACHINE_I386 => Some("x86"),
+      _ => None,
+    }
+  }
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+mod platform {
+  pub(super) fn native_arch() -> Option<&'static str> {
+    None
+  }
+}
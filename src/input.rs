@@ -0,0 +1,201 @@
+use std::{
+  ffi::{OsStr, OsString},
+  fmt,
+  str::FromStr,
+};
+
+use crate::timestamp::TimeArg;
+
+/// How many times an input is looped, see [`InputOptions::loop_input`].
+///
+/// ```rust
+/// use ffmpeg_sidecar::input::LoopCount;
+///
+/// assert_eq!("-1".parse(), Ok(LoopCount::Forever));
+/// assert_eq!("2".parse(), Ok(LoopCount::Repeat(2)));
+/// assert_eq!(LoopCount::Forever.to_string(), "-1");
+/// assert_eq!(LoopCount::from(-1), LoopCount::Forever);
+/// assert_eq!("-2".parse(), Ok(LoopCount::Forever));
+/// assert!("-".parse::<LoopCount>().is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LoopCount {
+  /// Start over `n` times after the end, so the input plays `n + 1` times.
+  Repeat(u32),
+  /// Start over until the output ends, e.g. with `-shortest` or `-t`.
+  Forever,
+}
+
+impl fmt::Display for LoopCount {
+  /// The value of `-stream_loop`.
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      LoopCount::Repeat(times) => write!(f, "{times}"),
+      LoopCount::Forever => f.write_str("-1"),
+    }
+  }
+}
+
+impl From<i32> for LoopCount {
+  /// The count of `-stream_loop`, where any negative one loops forever like
+  /// FFmpeg does.
+  fn from(times: i32) -> Self {
+    u32::try_from(times).map_or(LoopCount::Forever, LoopCount::Repeat)
+  }
+}
+
+impl FromStr for LoopCount {
+  type Err = InvalidLoopCount;
+
+  /// Parse the value of `-stream_loop`, where any negative count means
+  /// forever, as with `From<i32>`.
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    s.trim()
+      .parse::<i32>()
+      .map(LoopCount::from)
+      .map_err(|_| InvalidLoopCount(s.to_string()))
+  }
+}
+
+/// Returned when parsing a [`LoopCount`] fails, with the text which couldn't
+/// be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidLoopCount(pub String);
+
+impl fmt::Display for InvalidLoopCount {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "invalid loop count: {:?}", self.0)
+  }
+}
+
+impl std::error::Error for InvalidLoopCount {}
+
+/// Options which only apply to a single input, used with
+/// [`FfmpegCommand::input_with`](crate::command::FfmpegCommand::input_with).
+///
+/// FFmpeg input options must appear before the `-i` they apply to, which is
+/// easy to get wrong with several inputs in a flat argument list. Everything
+/// configured here, including raw `arg()`s, is emitted immediately before
+/// its own `-i`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InputOptions {
+  args: Vec<OsString>,
+}
+
+impl InputOptions {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Alias for `-f` argument, forcing the input format (demuxer) instead of
+  /// probing it, e.g. `lavfi`, `rawvideo`, or `image2`.
+  pub fn format<S: AsRef<str>>(&mut self, format: S) -> &mut Self {
+    self.arg("-f");
+    self.arg(format.as_ref());
+    self
+  }
+
+  /// Alias for `-framerate` argument.
+  ///
+  /// Set the frame rate of inputs which don't store one, such as image
+  /// sequences (`image2`) and capture devices.
+  pub fn framerate(&mut self, fps: f32) -> &mut Self {
+    self.arg("-framerate");
+    self.arg(fps.to_string());
+    self
+  }
+
+  /// Alias for `-re` argument.
+  ///
+  /// Read this input at its native frame rate.
+  pub fn realtime(&mut self) -> &mut Self {
+    self.arg("-re");
+    self
+  }
+
+  /// Alias for `-stream_loop` argument.
+  ///
+  /// Set the number of times this input is looped, where `0` means no loop
+  /// and `-1` means infinite loop. Same as [`loop_input`](Self::loop_input)
+  /// with the count converted to a [`LoopCount`].
+  pub fn loop_times<T: Into<LoopCount>>(&mut self, times: T) -> &mut Self {
+    self.loop_input(times.into())
+  }
+
+  /// Alias for `-stream_loop` argument.
+  ///
+  /// ```rust
+  /// use ffmpeg_sidecar::input::{InputOptions, LoopCount};
+  ///
+  /// let mut options = InputOptions::new();
+  /// options.loop_input(LoopCount::Forever);
+  /// assert_eq!(options.get_args(), ["-stream_loop", "-1"]);
+  /// ```
+  pub fn loop_input(&mut self, times: LoopCount) -> &mut Self {
+    self.arg("-stream_loop");
+    self.arg(times.to_string());
+    self
+  }
+
+  /// Alias for `-itsoffset` argument.
+  ///
+  /// Delay the timestamps of this input by `offset`, e.g. to line up an audio
+  /// track that should start partway into a video, or advance them by a
+  /// negative one.
+  ///
+  /// ```rust
+  /// use ffmpeg_sidecar::input::InputOptions;
+  ///
+  /// let mut options = InputOptions::new();
+  /// options.offset(-0.25);
+  /// assert_eq!(options.get_args(), ["-itsoffset", "-00:00:00.25"]);
+  /// ```
+  pub fn offset<T: Into<TimeArg>>(&mut self, offset: T) -> &mut Self {
+    self.arg("-itsoffset");
+    self.arg(offset.into().to_string());
+    self
+  }
+
+  /// Alias for `-thread_queue_size` argument.
+  ///
+  /// Set the maximum number of queued packets from this input. Live sources
+  /// may need a larger value to avoid "Thread message queue blocking"
+  /// warnings.
+  pub fn thread_queue_size(&mut self, size: u32) -> &mut Self {
+    self.arg("-thread_queue_size");
+    self.arg(size.to_string());
+    self
+  }
+
+  /// Alias for `-noautorotate` argument.
+  ///
+  /// Disable ffmpeg's automatic rotation of video with display rotation
+  /// metadata. See also [`FfmpegCommand::auto_rotate`](crate::command::FfmpegCommand::auto_rotate).
+  pub fn no_autorotate(&mut self) -> &mut Self {
+    self.arg("-noautorotate");
+    self
+  }
+
+  /// Adds a raw argument scoped to this input.
+  pub fn arg<S: AsRef<OsStr>>(&mut self, arg: S) -> &mut Self {
+    self.args.push(arg.as_ref().to_os_string());
+    self
+  }
+
+  /// Adds multiple raw arguments scoped to this input.
+  pub fn args<I, S>(&mut self, args: I) -> &mut Self
+  where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+  {
+    for arg in args {
+      self.arg(arg.as_ref());
+    }
+    self
+  }
+
+  /// Returns the arguments that will be emitted before this input's `-i`.
+  pub fn get_args(&self) -> &[OsString] {
+    &self.args
+  }
+}
@@ -0,0 +1,247 @@
+//! The record of an FFmpeg installed by
+//! [`auto_download`](crate::download::auto_download), to tell which build is
+//! installed, e.g. in a support ticket. See
+//! [`installed_info`](crate::download::installed_info).
+
+use std::{
+  collections::HashMap,
+  fs,
+  path::Path,
+  time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::download::BuildVariant;
+
+/// Name of the file written next to the binaries by `auto_download`.
+pub const INSTALL_INFO_FILENAME: &str = "install_info.json";
+
+/// What was installed by `auto_download`, from where, and when.
+///
+/// The file is a flat JSON object, written and read without a JSON library.
+/// With the `serde` feature, this can also be serialized and deserialized,
+/// e.g. to include it in a diagnostics report.
+///
+/// Records written before ffprobe was tracked separately are read with the
+/// ffprobe fields of ffmpeg.
+///
+/// ```rust
+/// use ffmpeg_sidecar::{download::BuildVariant, install_info::InstallInfo};
+///
+/// let info = InstallInfo {
+///   source_url: "https://example.com/ffmpeg-7.0.1.zip".to_string(),
+///   version: "7.0.1".to_string(),
+///   ffprobe_source_url: "https://example.com/ffprobe-7.0.2.zip".to_string(),
+///   ffprobe_version: "7.0.2".to_string(),
+///   sha256: String::new(),
+///   variant: BuildVariant::Essentials,
+///   installed_at: 1_720_000_000,
+/// };
+/// assert_eq!(InstallInfo::from_json(&info.to_json()), Some(info));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct InstallInfo {
+  /// The URL the archive of ffmpeg was downloaded from.
+  pub source_url: String,
+  /// The version reported by `ffmpeg -version` once installed, or empty if
+  /// it couldn't be read.
+  pub version: String,
+  /// The URL the archive of ffprobe was downloaded from, the same as
+  /// `source_url` unless it was updated on its own, see
+  /// [`update`](crate::download::update).
+  pub ffprobe_source_url: String,
+  /// The version reported by `ffprobe -version`, or empty if it couldn't be
+  /// read.
+  pub ffprobe_version: String,
+  /// The SHA-256 checksum of the archive, in lowercase hex, or empty if it
+  /// couldn't be computed.
+  pub sha256: String,
+  pub variant: BuildVariant,
+  /// When the install finished, in seconds since the Unix epoch.
+  pub installed_at: u64,
+}
+
+impl InstallInfo {
+  /// A record of an install of both binaries from the same archive,
+  /// finishing now.
+  pub fn new(source_url: &str, version: &str, sha256: &str, variant: BuildVariant) -> Self {
+    Self {
+      source_url: source_url.to_string(),
+      version: version.to_string(),
+      ffprobe_source_url: source_url.to_string(),
+      ffprobe_version: version.to_string(),
+      sha256: sha256.to_string(),
+      variant,
+      installed_at: SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs(),
+    }
+  }
+
+  pub fn to_json(&self) -> String {
+    format!(
+      "{{\n  \"source_url\": {},\n  \"version\": {},\n  \"ffprobe_source_url\": {},\n  \"ffprobe_version\": {},\n  \"sha256\": {},\n  \"variant\": {},\n  \"installed_at\": {}\n}}\n",
+      json_string(&self.source_url),
+      json_string(&self.version),
+      json_string(&self.ffprobe_source_url),
+      json_string(&self.ffprobe_version),
+      json_string(&self.sha256),
+      json_string(self.variant.as_str()),
+      self.installed_at
+    )
+  }
+
+  /// Parse the contents of an [`INSTALL_INFO_FILENAME`] file, or `None` if
+  /// it isn't valid, e.g. truncated by a crash while it was written.
+  pub fn from_json(json: &str) -> Option<Self> {
+    let mut fields = parse_flat_object(json)?;
+    // Missing from the records written before ffprobe was tracked
+    let ffprobe_source_url = fields.remove("ffprobe_source_url");
+    let ffprobe_version = fields.remove("ffprobe_version");
+    let mut string = |key: &str| match fields.remove(key)? {
+      JsonValue::String(value) => Some(value),
+      JsonValue::Number(_) => None,
+    };
+    let source_url = string("source_url")?;
+    let version = string("version")?;
+    let sha256 = string("sha256")?;
+    let variant = string("variant")?.parse().ok()?;
+    let installed_at = match fields.remove("installed_at")? {
+      JsonValue::Number(number) => number.parse().ok()?,
+      JsonValue::String(_) => return None,
+    };
+    Some(Self {
+      ffprobe_source_url: string_or(ffprobe_source_url, &source_url)?,
+      ffprobe_version: string_or(ffprobe_version, &version)?,
+      source_url,
+      version,
+      sha256,
+      variant,
+      installed_at,
+    })
+  }
+
+  /// Read the record in `dir`, or `None` if there's none or it's corrupt.
+  pub fn read(dir: &Path) -> Option<Self> {
+    Self::from_json(&fs::read_to_string(dir.join(INSTALL_INFO_FILENAME)).ok()?)
+  }
+
+  /// Write the record to `dir`, replacing the previous one.
+  pub fn write(&self, dir: &Path) -> std::io::Result<()> {
+    // Renamed over the previous record, so it's never left half written
+    let temp = dir.join(format!("{INSTALL_INFO_FILENAME}.tmp"));
+    fs::write(&temp, self.to_json())?;
+    fs::rename(&temp, dir.join(INSTALL_INFO_FILENAME))
+  }
+}
+
+fn json_string(value: &str) -> String {
+  let mut escaped = String::with_capacity(value.len() + 2);
+  escaped.push('"');
+  for c in value.chars() {
+    match c {
+      '"' => escaped.push_str("\\\""),
+      '\\' => escaped.push_str("\\\\"),
+      '\n' => escaped.push_str("\\n"),
+      '\r' => escaped.push_str("\\r"),
+      '\t' => escaped.push_str("\\t"),
+      c if c.is_control() => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+      c => escaped.push(c),
+    }
+  }
+  escaped.push('"');
+  escaped
+}
+
+/// The string of an optional field, or `default` if it's missing.
+fn string_or(value: Option<JsonValue>, default: &str) -> Option<String> {
+  match value {
+    Some(JsonValue::String(value)) => Some(value),
+    Some(JsonValue::Number(_)) => None,
+    None => Some(default.to_string()),
+  }
+}
+
+enum JsonValue {
+  String(String),
+  /// Kept as written, to be parsed as the type of the field
+  Number(String),
+}
+
+/// Parse a JSON object whose values are strings or numbers, as written by
+/// [`InstallInfo::to_json`]. Unknown keys are kept, so fields can be added.
+fn parse_flat_object(json: &str) -> Option<HashMap<String, JsonValue>> {
+  let mut chars = json.trim().chars().peekable();
+  let mut fields = HashMap::new();
+  if chars.next()? != '{' {
+    return None;
+  }
+  loop {
+    skip_whitespace(&mut chars);
+    match chars.next()? {
+      '}' if fields.is_empty() => break,
+      '"' => {}
+      _ => return None,
+    }
+    let key = parse_string(&mut chars)?;
+    skip_whitespace(&mut chars);
+    if chars.next()? != ':' {
+      return None;
+    }
+    skip_whitespace(&mut chars);
+    let value = match chars.peek()? {
+      '"' => {
+        chars.next();
+        JsonValue::String(parse_string(&mut chars)?)
+      }
+      c if c.is_ascii_digit() || *c == '-' => {
+        let mut number = String::new();
+        while let Some(c) = chars.next_if(|c| c.is_ascii_digit() || "+-.eE".contains(*c)) {
+          number.push(c);
+        }
+        JsonValue::Number(number)
+      }
+      _ => return None,
+    };
+    fields.insert(key, value);
+    skip_whitespace(&mut chars);
+    match chars.next()? {
+      ',' => {}
+      '}' => break,
+      _ => return None,
+    }
+  }
+  // Nothing may follow the object
+  chars.next().is_none().then_some(fields)
+}
+
+fn skip_whitespace(chars: &mut std::iter::Peekable<std::str::Chars>) {
+  while chars.next_if(|c| c.is_whitespace()).is_some() {}
+}
+
+/// The rest of a string after its opening quote, up to the closing one.
+fn parse_string(chars: &mut std::iter::Peekable<std::str::Chars>) -> Option<String> {
+  let mut value = String::new();
+  loop {
+    match chars.next()? {
+      '"' => return Some(value),
+      '\\' => value.push(match chars.next()? {
+        '"' => '"',
+        '\\' => '\\',
+        '/' => '/',
+        'n' => '\n',
+        'r' => '\r',
+        't' => '\t',
+        'b' => '\u{8}',
+        'f' => '\u{c}',
+        'u' => {
+          let hex = (0..4).map(|_| chars.next()).collect::<Option<String>>()?;
+          char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?
+        }
+        _ => return None,
+      }),
+      c => value.push(c),
+    }
+  }
+}
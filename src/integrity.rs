@@ -0,0 +1,285 @@
+//! Checking that an output isn't missing its end, since ffmpeg skips over
+//! decode errors and still exits successfully unless
+//! [`FfmpegCommand::fail_on_error`](crate::command::FfmpegCommand::fail_on_error)
+//! is set, and checking that a file decodes cleanly with [`verify_integrity`].
+
+use std::ffi::OsStr;
+#[cfg(feature = "process")]
+use std::path::Path;
+
+use crate::log_parser::{parse_time_str, try_parse_log_context};
+#[cfg(feature = "process")]
+use crate::{
+  command::FfmpegCommand,
+  error::{is_decode_error, FfmpegErrorKind, TruncatedOutput, UnreadableInput},
+  event::{FfmpegEvent, LogLevel},
+  probe::probe,
+};
+
+/// How many errors [`verify_integrity`] collects before giving up, by
+/// default.
+pub const VERIFY_MAX_ERRORS: usize = 100;
+
+/// The duration of an output made from an input of `input_duration` seconds,
+/// after the trimming options in `args`: `-ss`, `-sseof`, `-t` and `-to`.
+///
+/// This is meant for commands with a single input, and doesn't tell input
+/// options from output options, which trim the same amount in most cases.
+///
+/// ```rust
+/// use ffmpeg_sidecar::integrity::trimmed_duration;
+///
+/// assert_eq!(trimmed_duration(60.0, &["-i", "in.mp4", "out.mp4"]), 60.0);
+/// assert_eq!(trimmed_duration(60.0, &["-ss", "10", "-i", "in.mp4", "out.mp4"]), 50.0);
+/// assert_eq!(trimmed_duration(60.0, &["-ss", "10", "-to", "00:00:25", "-i", "in.mp4", "out.mp4"]), 15.0);
+/// assert_eq!(trimmed_duration(60.0, &["-i", "in.mp4", "-t", "90", "out.mp4"]), 60.0);
+/// assert_eq!(trimmed_duration(60.0, &["-sseof", "-5", "-i", "in.mp4", "out.mp4"]), 5.0);
+/// ```
+pub fn trimmed_duration<S: AsRef<OsStr>>(input_duration: f64, args: &[S]) -> f64 {
+  let mut start = 0.0;
+  let mut duration = None;
+  let mut end = None;
+  let mut args = args.iter().map(|arg| arg.as_ref().to_string_lossy());
+  while let Some(arg) = args.next() {
+    let option = arg.as_ref();
+    if !matches!(option, "-ss" | "-sseof" | "-t" | "-to") {
+      continue;
+    }
+    let Some(value) = args.next().and_then(|value| parse_time_str(&value)) else {
+      continue;
+    };
+    match option {
+      "-ss" => start = value,
+      "-sseof" => start = input_duration + value.min(0.0),
+      "-t" => duration = Some(value),
+      _ => end = Some(value),
+    }
+  }
+
+  let remaining = (input_duration - start.max(0.0)).max(0.0);
+  // `-t` has priority over `-to`
+  match (duration, end) {
+    (Some(duration), _) => remaining.min(duration),
+    (None, Some(end)) => remaining.min((end - start).max(0.0)),
+    (None, None) => remaining,
+  }
+}
+
+/// Probe the duration of `output`, and return it if it's at most
+/// `tolerance` seconds shorter than `expected`, or a [`TruncatedOutput`]
+/// error otherwise. An output longer than expected is accepted.
+///
+/// ```rust,no_run
+/// use ffmpeg_sidecar::{
+///   command::FfmpegCommand, error::TruncatedOutput, integrity::check_output_duration,
+///   integrity::trimmed_duration, probe::probe,
+/// };
+///
+/// let input_duration = probe("upload.mp4").unwrap().duration.unwrap();
+/// let mut command = FfmpegCommand::new();
+/// command.input("upload.mp4").seek("5").output("clip.mp4");
+/// let args = command.get_args().collect::<Vec<_>>();
+/// let expected = trimmed_duration(input_duration, &args);
+/// command.spawn().unwrap().wait().unwrap();
+///
+/// if let Err(e) = check_output_duration("clip.mp4", expected, 0.5) {
+///   let truncated = e.downcast_ref::<TruncatedOutput>().unwrap();
+///   println!("missing {:.1}s", truncated.shortfall());
+/// }
+/// ```
+#[cfg(feature = "process")]
+pub fn check_output_duration<P: AsRef<Path>>(
+  output: P,
+  expected: f64,
+  tolerance: f64,
+) -> anyhow::Result<f64> {
+  let output = output.as_ref();
+  let Some(actual) = probe(output)?.duration else {
+    anyhow::bail!("ffprobe didn't report a duration for {}", output.display());
+  };
+  if expected - actual > tolerance {
+    return Err(TruncatedOutput { expected, actual }.into());
+  }
+  Ok(actual)
+}
+
+/// What [`verify_integrity`] decodes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VerifyOptions {
+  /// Decode the video streams.
+  pub video: bool,
+  /// Decode the audio streams.
+  pub audio: bool,
+  /// Stop decoding after this many errors, to bound the time spent on a
+  /// badly damaged file. At least 1.
+  pub max_errors: usize,
+}
+
+impl Default for VerifyOptions {
+  fn default() -> Self {
+    Self {
+      video: true,
+      audio: true,
+      max_errors: VERIFY_MAX_ERRORS,
+    }
+  }
+}
+
+/// An error logged while decoding, see [`VerifyReport`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodeError {
+  /// About where in the file the error is, in seconds: the decoded time of
+  /// the last progress update before it, which ffmpeg prints twice a
+  /// second. `None` before the first one.
+  pub timestamp: Option<f64>,
+  /// The stream, like `0:1`, when the message names it, or else the
+  /// component which logged it, like the decoder `h264`.
+  pub stream: Option<String>,
+  /// The message, without the component and level prefixes.
+  pub message: String,
+}
+
+impl DecodeError {
+  /// Parse an error log line, logged at `timestamp`.
+  ///
+  /// ```rust
+  /// use ffmpeg_sidecar::integrity::DecodeError;
+  ///
+  /// let line = "[h264 @ 0x5581c8f0] [error] concealing 1620 DC, 1620 AC, 1620 MV errors in P frame";
+  /// let error = DecodeError::parse(line, Some(1.5));
+  /// assert_eq!(error.stream.as_deref(), Some("h264"));
+  /// assert_eq!(error.message, "concealing 1620 DC, 1620 AC, 1620 MV errors in P frame");
+  ///
+  /// let line = "[error] Error while decoding stream #0:1: Invalid data found when processing input";
+  /// assert_eq!(DecodeError::parse(line, None).stream.as_deref(), Some("0:1"));
+  /// ```
+  pub fn parse(line: &str, timestamp: Option<f64>) -> Self {
+    let mut message = line.trim();
+    while let Some((_, rest)) = message
+      .strip_prefix('[')
+      .and_then(|rest| rest.split_once("] "))
+    {
+      message = rest;
+    }
+    let stream = message
+      .split_once("stream #")
+      .map(|(_, rest)| {
+        let end = rest
+          .find(|c: char| !(c.is_ascii_digit() || c == ':'))
+          .unwrap_or(rest.len());
+        rest[..end].trim_end_matches(':')
+      })
+      .filter(|stream| !stream.is_empty())
+      .or_else(|| try_parse_log_context(line).map(|context| context.component))
+      .map(str::to_string);
+    Self {
+      timestamp,
+      stream,
+      message: message.to_string(),
+    }
+  }
+}
+
+/// The result of [`verify_integrity`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct VerifyReport {
+  /// The whole file decoded without any error.
+  pub ok: bool,
+  pub errors: Vec<DecodeError>,
+  /// How many seconds were decoded, from the last progress update.
+  pub decoded_duration: Option<f64>,
+  /// Decoding stopped after `max_errors` errors, so the rest of the file
+  /// wasn't checked.
+  pub stopped_early: bool,
+}
+
+/// Decode `path` to a null output, e.g. to check an upload before accepting
+/// it, and report every error with about where it is in the file.
+///
+/// Unlike a transcode with `-xerror`, decoding goes on after an error, so
+/// the report lists all the damaged places, up to `max_errors`. Errors are
+/// the lines ffmpeg logs at the error level, and the warnings of damaged
+/// input recognized by [`is_decode_error`].
+///
+/// A file which can't be opened at all, like a text file renamed to `.mp4`,
+/// fails with [`UnreadableInput`] instead of a report.
+///
+/// ```rust,no_run
+/// use ffmpeg_sidecar::integrity::{verify_integrity, VerifyOptions};
+///
+/// let report = verify_integrity("upload.mp4", VerifyOptions::default()).unwrap();
+/// for error in &report.errors {
+///   println!("{:.1}s: {}", error.timestamp.unwrap_or(0.0), error.message);
+/// }
+/// ```
+#[cfg(feature = "process")]
+pub fn verify_integrity<P: AsRef<Path>>(
+  path: P,
+  options: VerifyOptions,
+) -> anyhow::Result<VerifyReport> {
+  if !options.video && !options.audio {
+    anyhow::bail!("verify_integrity needs video or audio to decode");
+  }
+  let mut command = FfmpegCommand::new();
+  command.input(path.as_ref().to_string_lossy());
+  if !options.video {
+    command.no_video();
+  }
+  if !options.audio {
+    command.no_audio();
+  }
+  let mut child = command
+    .args(["-sn", "-dn"])
+    .format("null")
+    .output("-")
+    .spawn()?;
+  drop(child.take_stdin());
+
+  let mut errors = Vec::new();
+  let mut decoded_duration = None;
+  let mut stopped_early = false;
+  for event in child.iter()? {
+    match event {
+      FfmpegEvent::Progress(progress) => {
+        decoded_duration = progress.out_time.map(f64::from).or(decoded_duration);
+      }
+      FfmpegEvent::Log(LogLevel::Error | LogLevel::Fatal, line) | FfmpegEvent::Error(line) => {
+        errors.push(DecodeError::parse(&line, decoded_duration));
+      }
+      FfmpegEvent::Log(LogLevel::Warning, line) if is_decode_error(&line) => {
+        errors.push(DecodeError::parse(&line, decoded_duration));
+      }
+      _ => {}
+    }
+    if errors.len() >= options.max_errors.max(1) {
+      stopped_early = true;
+      break;
+    }
+  }
+  if stopped_early {
+    child.kill()?;
+  }
+  let status = child.wait()?;
+
+  if !stopped_early && !status.success() && decoded_duration.is_none() {
+    return Err(
+      UnreadableInput {
+        kind: errors
+          .iter()
+          .find_map(|error| FfmpegErrorKind::classify(&error.message)),
+        message: errors
+          .iter()
+          .map(|error| error.message.as_str())
+          .collect::<Vec<_>>()
+          .join("\n"),
+      }
+      .into(),
+    );
+  }
+  Ok(VerifyReport {
+    ok: errors.is_empty() && status.success(),
+    errors,
+    decoded_duration,
+    stopped_early,
+  })
+}
@@ -1,33 +1,107 @@
 use std::{
-  io::{BufReader, ErrorKind, Read},
+  collections::VecDeque,
+  io::{self, BufReader, ErrorKind, Read},
   process::{ChildStderr, ChildStdout},
-  sync::mpsc::{sync_channel, Receiver, SyncSender},
+  sync::{
+    mpsc::{channel, sync_channel, Receiver, RecvTimeoutError, Sender, SyncSender},
+    Arc, Mutex,
+  },
   thread::JoinHandle,
+  time::{Duration, Instant},
 };
 
 use anyhow::Context;
 
+#[cfg(feature = "serde")]
+use crate::recording::SessionRecorder;
 use crate::{
   child::FfmpegChild,
+  error::FrameTooLarge,
+  error_policy::{ErrorLimitReached, ErrorPolicy},
   event::{AVStream, FfmpegEvent, FfmpegOutput, FfmpegProgress, LogLevel, OutputVideoFrame},
+  log_group::LogGrouper,
   log_parser::FfmpegLogParser,
   metadata::FfmpegMetadata,
-  pix_fmt::get_bytes_per_frame,
+  pipe::StderrTail,
+  pix_fmt::frame_bytes,
+  resource_usage::ResourceSampler,
+  shared_child::SharedChild,
+  stats::StatsAggregator,
+  stderr_policy::StderrFilter,
+  summary::FfmpegSummary,
+  timeout::{OutputWatchdog, PromptWatch},
 };
 
+/// The default of [`FfmpegChild::frame_buffer_count`].
+pub const DEFAULT_FRAME_BUFFER_COUNT: usize = 2;
+
+/// The default of [`FfmpegChild::max_frame_bytes`], 1 GiB, which fits a
+/// 16K frame with 32 bits per pixel.
+pub const DEFAULT_MAX_FRAME_BYTES: u64 = 1 << 30;
+
 /// An iterator over events from an ffmpeg process, including parsed metadata, progress, and raw video frames.
 pub struct FfmpegIterator {
   rx: Receiver<FfmpegEvent>,
   tx: Option<SyncSender<FfmpegEvent>>,
   stdout: Option<ChildStdout>,
   metadata: FfmpegMetadata,
+  summary: Arc<Mutex<FfmpegSummary>>,
+  watchdog: Option<Arc<OutputWatchdog>>,
+  prompt_watch: Arc<PromptWatch>,
+  /// The hints found when spawning, then the events completed along with a
+  /// log group
+  queued: VecDeque<FfmpegEvent>,
+  /// Set with `FfmpegChild::sample_interval`, with the time of the next
+  /// sample
+  sampler: Option<(ResourceSampler, Duration, Instant)>,
+  /// Set with `FfmpegCommand::group_log_messages`
+  grouper: Option<LogGrouper>,
+  /// Set with `FfmpegChild::max_frame_bytes`
+  max_frame_bytes: u64,
+  /// Set with `FfmpegChild::error_policy`
+  error_policy: ErrorPolicy,
+  /// Set with `FfmpegChild::record_jsonl`
+  #[cfg(feature = "serde")]
+  recorder: Option<Arc<SessionRecorder>>,
+  /// Reaped once the output is drained, then taken, so
+  /// `FfmpegEvent::Exited` is emitted once
+  child: Option<Arc<SharedChild>>,
 }
 
 impl FfmpegIterator {
   pub fn new(child: &mut FfmpegChild) -> anyhow::Result<Self> {
-    let stderr = child.take_stderr().context("No stderr channel\n - Did you call `take_stderr` elsewhere?\n - Did you forget to call `.stderr(Stdio::piped)` on the `ChildProcess`?")?;
-    let (tx, rx) = sync_channel::<FfmpegEvent>(0);
-    spawn_stderr_thread(stderr, tx.clone());
+    if child.frames_pumped() {
+      anyhow::bail!("The raw frames on stdout can't be iterated\n - `pipe_output_to` was called first, which copies stdout to its writer instead");
+    }
+    // The stdout thread holds one more frame while it waits to send it
+    let (frame_buffer_count, max_frame_bytes) = child.frame_limits();
+    let (tx, rx) = sync_channel::<FfmpegEvent>(frame_buffer_count - 1);
+    // Stderr is read into an unbounded queue, and only then into the one
+    // shared with the frames, so ffmpeg never blocks writing its log while
+    // the frames wait for the consumer, whatever the log level
+    let log_events = match child.take_early_events() {
+      // Stderr was already being read since spawn, by the output watchdog
+      Some(early_events) => early_events,
+      None => {
+        let stderr = child.take_stderr().context("No stderr channel\n - Did you call `take_stderr` elsewhere?\n - Did you forget to call `.stderr(Stdio::piped)` on the `ChildProcess`?")?;
+        let (log_tx, log_rx) = channel();
+        spawn_stderr_thread_with_tail(
+          stderr,
+          log_tx,
+          child.stderr_tail_handle(),
+          child.stderr_filter_handle(),
+        );
+        log_rx
+      }
+    };
+    let log_forwarder = tx.clone();
+    std::thread::spawn(move || {
+      for event in log_events {
+        if log_forwarder.send(event).is_err() {
+          break;
+        }
+      }
+    });
     let stdout = child.take_stdout();
 
     Ok(Self {
@@ -35,6 +109,23 @@ impl FfmpegIterator {
       tx: Some(tx),
       stdout,
       metadata: FfmpegMetadata::new(),
+      summary: child.summary_handle(),
+      watchdog: child.watchdog(),
+      prompt_watch: child.prompt_watch(),
+      queued: child
+        .take_hints()
+        .into_iter()
+        .map(FfmpegEvent::Hint)
+        .collect(),
+      sampler: child
+        .interval_sampler()
+        .map(|(sampler, interval)| (sampler, interval, Instant::now() + interval)),
+      grouper: child.log_grouper(),
+      max_frame_bytes,
+      error_policy: child.error_policy_setting(),
+      #[cfg(feature = "serde")]
+      recorder: child.recorder(),
+      child: Some(child.shared_child()),
     })
   }
 
@@ -49,6 +140,14 @@ impl FfmpegIterator {
       anyhow::bail!(err)
     }
 
+    if let Some(too_large) = self.frame_too_large() {
+      // Closing stdout stops ffmpeg, which fails writing to it
+      self.stdout.take();
+      self.tx.take();
+      self.summary.lock().unwrap().frame_too_large = Some(too_large.clone());
+      anyhow::bail!(too_large)
+    }
+
     // Handle stdout
     if let Some(stdout) = self.stdout.take() {
       spawn_stdout_thread(
@@ -62,6 +161,25 @@ impl FfmpegIterator {
     Ok(())
   }
 
+  /// The first raw video output to stdout whose frames are larger than
+  /// `max_frame_bytes`.
+  fn frame_too_large(&self) -> Option<FrameTooLarge> {
+    self.stdout.as_ref()?;
+    stdout_output_streams(&self.metadata.output_streams, &self.metadata.outputs)
+      .filter(|stream| stream.format == "rawvideo")
+      .find_map(|stream| {
+        let bytes = frame_bytes(stream)?;
+        (bytes > self.max_frame_bytes).then(|| FrameTooLarge {
+          output_index: stream.parent_index as u32,
+          width: stream.width,
+          height: stream.height,
+          pix_fmt: stream.pix_fmt.clone(),
+          bytes,
+          max_bytes: self.max_frame_bytes,
+        })
+      })
+  }
+
   /// Advance the iterator until all metadata has been collected, returning it.
   pub fn collect_metadata(&mut self) -> anyhow::Result<FfmpegMetadata> {
     let mut event_queue: Vec<FfmpegEvent> = Vec::new();
@@ -90,6 +208,130 @@ impl FfmpegIterator {
     Ok(self.metadata.clone())
   }
 
+  /// The next event sent by the threads reading ffmpeg's output, or a
+  /// resource sample if one is due first. Times out once `deadline` passes.
+  fn recv(&mut self, deadline: Option<Instant>) -> Result<FfmpegEvent, RecvTimeoutError> {
+    loop {
+      let next_sample = self
+        .sampler
+        .as_ref()
+        .map(|(_, _, next_sample)| *next_sample);
+      let received = match next_sample.into_iter().chain(deadline).min() {
+        Some(wake) => self
+          .rx
+          .recv_timeout(wake.saturating_duration_since(Instant::now())),
+        None => self.rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+      };
+      if !matches!(received, Err(RecvTimeoutError::Timeout)) {
+        return received;
+      }
+      if let Some((sampler, interval, next_sample)) = &mut self.sampler {
+        if Instant::now() >= *next_sample {
+          *next_sample = Instant::now() + *interval;
+          if let Ok(usage) = sampler.sample() {
+            return Ok(FfmpegEvent::ResourceSample(usage));
+          }
+        }
+      }
+      if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+        return received;
+      }
+    }
+  }
+
+  /// The open log group, if any.
+  fn flush_group(&mut self) -> Vec<FfmpegEvent> {
+    self
+      .grouper
+      .as_mut()
+      .and_then(LogGrouper::flush)
+      .into_iter()
+      .collect()
+  }
+
+  /// Update the summary, watchdog and metadata with the next item received,
+  /// returning the event to emit for it.
+  fn handle_item(&mut self, item: Option<FfmpegEvent>) -> Option<FfmpegEvent> {
+    if let Some(FfmpegEvent::LogEOF) = item {
+      self.tx.take(); // drop the tx so that the receiver can close
+    }
+
+    if let (Some(event), Ok(mut summary)) = (&item, self.summary.lock()) {
+      summary.handle_event(event);
+      let limit = self.error_policy.limit();
+      if summary.error_limit_reached.is_none() && limit.is_some_and(|n| summary.error_count >= n) {
+        let limit_reached = ErrorLimitReached {
+          policy: self.error_policy,
+          errors: summary.error_messages.clone(),
+        };
+        // The log and frames already read are still emitted, up to the end
+        // of the output
+        if let Some(child) = &self.child {
+          child.kill().ok();
+        }
+        self
+          .queued
+          .push_back(FfmpegEvent::Error(limit_reached.to_string()));
+        summary.error_limit_reached = Some(limit_reached);
+      }
+    }
+
+    if let (Some(event), Some(watchdog)) = (&item, &self.watchdog) {
+      watchdog.observe(event);
+    }
+
+    if let Some(FfmpegEvent::Prompt { text, .. }) = &item {
+      self.prompt_watch.asked(text);
+    }
+
+    if !self.metadata.is_completed() {
+      match self.metadata.handle_event(&item) {
+        Err(e) => return Some(FfmpegEvent::Error(e.to_string())),
+        // TODO in this case, the preceding `item` is lost;
+        // Probably better to queue it as the next item.
+        Ok(()) if self.metadata.is_completed() => {
+          if let Err(e) = self.start_stdout() {
+            return Some(FfmpegEvent::Error(e.to_string()));
+            // Same problem as above
+          }
+        }
+
+        _ => {}
+      }
+    }
+
+    item
+  }
+
+  /// Once the output is drained, reap ffmpeg and report how it exited. Only
+  /// returned once, before the iterator ends.
+  fn exited(&mut self) -> Option<FfmpegEvent> {
+    let child = self.child.take()?;
+    // Not blocking the child, which can still be killed meanwhile
+    let status = match child.poll_wait() {
+      Ok(status) => status,
+      Err(e) => {
+        return Some(FfmpegEvent::Error(format!(
+          "Failed to wait for ffmpeg: {e}"
+        )))
+      }
+    };
+    // Stop the timers, so the pid is never killed once it could be reused
+    let timed_out = match &self.watchdog {
+      Some(watchdog) => {
+        watchdog.exited();
+        watchdog.error().is_some()
+      }
+      None => false,
+    };
+    self.prompt_watch.exited();
+    let unanswered = self.prompt_watch.error().is_some();
+    Some(FfmpegEvent::Exited {
+      status,
+      graceful: !child.killed() && !timed_out && !unanswered,
+    })
+  }
+
   //// Iterator filters
 
   /// Returns an iterator over error messages (`FfmpegEvent::Error` and `FfmpegEvent::LogError`).
@@ -108,6 +350,93 @@ impl FfmpegIterator {
     })
   }
 
+  /// Pass at most one progress event (`FfmpegEvent::Progress`) per
+  /// `interval`, e.g. to update a UI without redrawing on every one. The
+  /// ones in between are coalesced into the latest, which is emitted once
+  /// the interval has elapsed, or before the end of the log so the final
+  /// progress isn't lost. Other events pass through unchanged.
+  ///
+  /// To make ffmpeg report progress less often in the first place, see
+  /// [`FfmpegCommand::stats_period`](crate::command::FfmpegCommand::stats_period).
+  pub fn throttle_progress(mut self, interval: Duration) -> impl Iterator<Item = FfmpegEvent> {
+    let mut throttle = ProgressThrottle::new(interval);
+    let mut queued = None;
+    std::iter::from_fn(move || loop {
+      if let Some(event) = queued.take() {
+        return Some(event);
+      }
+      match self.next() {
+        Some(FfmpegEvent::Progress(progress)) => {
+          if let Some(progress) = throttle.offer(progress, Instant::now()) {
+            return Some(FfmpegEvent::Progress(progress));
+          }
+        }
+        Some(event @ (FfmpegEvent::LogEOF | FfmpegEvent::Done | FfmpegEvent::Exited { .. })) => {
+          match throttle.flush() {
+            Some(progress) => {
+              queued = Some(event);
+              return Some(FfmpegEvent::Progress(progress));
+            }
+            None => return Some(event),
+          }
+        }
+        Some(event) => return Some(event),
+        None => return throttle.flush().map(FfmpegEvent::Progress),
+      }
+    })
+  }
+
+  /// Count the events with `aggregator`, emitting a
+  /// [`FfmpegEvent::StatsRollup`] before the first event after the end of
+  /// each bucket, and the rollup of the last, partial bucket before
+  /// `FfmpegEvent::Exited`. Other events pass through unchanged.
+  ///
+  /// ```rust,no_run
+  /// use ffmpeg_sidecar::{command::FfmpegCommand, event::FfmpegEvent, stats::StatsAggregator};
+  /// use std::time::Duration;
+  ///
+  /// let hour = Duration::from_secs(3600);
+  /// let stats = StatsAggregator::new(24 * hour, hour);
+  /// let mut child = FfmpegCommand::new()
+  ///   .input("https://example.com/live.m3u8")
+  ///   .codec_video("copy")
+  ///   .format("flv")
+  ///   .output("rtmp://example.com/live/key")
+  ///   .spawn()
+  ///   .unwrap();
+  /// let snapshots = stats.clone();
+  /// for event in child.iter().unwrap().aggregate_stats(&stats) {
+  ///   if let FfmpegEvent::StatsRollup(rollup) = event {
+  ///     println!("{:?} fps, {} bytes out", rollup.average_fps, rollup.bytes_out);
+  ///     println!("{} frames today", snapshots.snapshot().window.frames);
+  ///   }
+  /// }
+  /// ```
+  pub fn aggregate_stats(
+    mut self,
+    aggregator: &StatsAggregator,
+  ) -> impl Iterator<Item = FfmpegEvent> {
+    let aggregator = aggregator.clone();
+    let mut queued = VecDeque::new();
+    let mut flushed = false;
+    std::iter::from_fn(move || loop {
+      if let Some(event) = queued.pop_front() {
+        return Some(event);
+      }
+      let event = self.next();
+      if !flushed && matches!(event, None | Some(FfmpegEvent::Exited { .. })) {
+        flushed = true;
+        queued.push_back(FfmpegEvent::StatsRollup(aggregator.current()));
+        queued.extend(event);
+        continue;
+      }
+      let event = event?;
+      let rollups = aggregator.offer(&event, Instant::now());
+      queued.extend(rollups.into_iter().map(FfmpegEvent::StatsRollup));
+      queued.push_back(event);
+    })
+  }
+
   /// Filter out all events except for output frames (`FfmpegEvent::OutputFrame`).
   pub fn filter_frames(self) -> impl Iterator<Item = OutputVideoFrame> {
     self.filter_map(|event| match event {
@@ -127,23 +456,7 @@ impl FfmpegIterator {
   /// Iterator over every message from ffmpeg's stderr as a raw string.
   /// Conceptually equivalent to `BufReader::new(ffmpeg_stderr).lines()`.
   pub fn into_ffmpeg_stderr(self) -> impl Iterator<Item = String> {
-    self.filter_map(|event| match event {
-      FfmpegEvent::ParsedVersion(x) => Some(x.raw_log_message),
-      FfmpegEvent::ParsedConfiguration(x) => Some(x.raw_log_message),
-      FfmpegEvent::ParsedStreamMapping(x) => Some(x),
-      FfmpegEvent::ParsedOutput(x) => Some(x.raw_log_message),
-      FfmpegEvent::ParsedInputStream(x) => Some(x.raw_log_message),
-      FfmpegEvent::ParsedOutputStream(x) => Some(x.raw_log_message),
-      FfmpegEvent::Log(_, x) => Some(x),
-      FfmpegEvent::LogEOF => None,
-      FfmpegEvent::Error(_) => None,
-      FfmpegEvent::Progress(x) => Some(x.raw_log_message),
-      FfmpegEvent::OutputFrame(_) => None,
-      FfmpegEvent::OutputChunk(_) => None,
-      FfmpegEvent::Done => None,
-      FfmpegEvent::ParsedInput(input) => Some(input.raw_log_message),
-      FfmpegEvent::ParsedDuration(duration) => Some(duration.raw_log_message),
-    })
+    self.filter_map(|event| event.raw_log_message().map(str::to_string))
   }
 }
 
@@ -151,29 +464,90 @@ impl Iterator for FfmpegIterator {
   type Item = FfmpegEvent;
 
   fn next(&mut self) -> Option<Self::Item> {
-    let item = self.rx.recv().ok();
-
-    if let Some(FfmpegEvent::LogEOF) = item {
-      self.tx.take(); // drop the tx so that the receiver can close
+    let event = self.next_event();
+    #[cfg(feature = "serde")]
+    if let (Some(event), Some(recorder)) = (&event, &self.recorder) {
+      recorder.record_event(event);
     }
+    event
+  }
+}
 
-    if !self.metadata.is_completed() {
-      match self.metadata.handle_event(&item) {
-        Err(e) => return Some(FfmpegEvent::Error(e.to_string())),
-        // TODO in this case, the preceding `item` is lost;
-        // Probably better to queue it as the next item.
-        Ok(()) if self.metadata.is_completed() => {
-          if let Err(e) = self.start_stdout() {
-            return Some(FfmpegEvent::Error(e.to_string()));
-            // Same problem as above
+impl FfmpegIterator {
+  fn next_event(&mut self) -> Option<FfmpegEvent> {
+    if let Some(event) = self.queued.pop_front() {
+      return Some(event);
+    }
+    if self.grouper.is_none() {
+      let item = self.recv(None).ok();
+      return self.handle_item(item).or_else(|| self.exited());
+    }
+    // Every event is handled before grouping, so the summary and metadata
+    // see each log line
+    loop {
+      let deadline = self.grouper.as_ref().and_then(LogGrouper::deadline);
+      let ready = match self.recv(deadline) {
+        Err(RecvTimeoutError::Timeout) => self.flush_group(),
+        Ok(event) => match (self.handle_item(Some(event)), &mut self.grouper) {
+          (Some(event), Some(grouper)) => grouper.offer(event, Instant::now()),
+          (event, None) => event.into_iter().collect(),
+          (None, _) => Vec::new(),
+        },
+        Err(RecvTimeoutError::Disconnected) => {
+          let mut ready = self.flush_group();
+          ready.extend(self.handle_item(None));
+          if ready.is_empty() {
+            return self.exited();
           }
+          ready
         }
-
-        _ => {}
+      };
+      self.queued.extend(ready);
+      if let Some(event) = self.queued.pop_front() {
+        return Some(event);
       }
     }
+  }
+}
 
-    item
+/// Coalesces progress updates to at most one per interval, see
+/// [`FfmpegIterator::throttle_progress`].
+#[derive(Debug, Clone)]
+pub(crate) struct ProgressThrottle {
+  interval: Duration,
+  last_emitted: Option<Instant>,
+  pending: Option<FfmpegProgress>,
+}
+
+impl ProgressThrottle {
+  pub(crate) fn new(interval: Duration) -> Self {
+    Self {
+      interval,
+      last_emitted: None,
+      pending: None,
+    }
+  }
+
+  /// The progress to emit at `now`, if any: the first one, then the latest
+  /// one received once `interval` has elapsed since the last emitted.
+  pub(crate) fn offer(&mut self, progress: FfmpegProgress, now: Instant) -> Option<FfmpegProgress> {
+    let due = match self.last_emitted {
+      Some(last) => now.saturating_duration_since(last) >= self.interval,
+      None => true,
+    };
+    if due {
+      self.last_emitted = Some(now);
+      self.pending = None;
+      Some(progress)
+    } else {
+      self.pending = Some(progress);
+      None
+    }
+  }
+
+  /// The latest progress which wasn't emitted yet.
+  pub(crate) fn flush(&mut self) -> Option<FfmpegProgress> {
+    self.pending.take()
   }
 }
 
@@ -185,13 +559,7 @@ pub fn spawn_stdout_thread(
   outputs: Vec<FfmpegOutput>,
 ) -> JoinHandle<()> {
   std::thread::spawn(move || {
-    // Filter streams which are sent to stdout
-    let stdout_output_streams = output_streams.iter().filter(|stream| {
-      outputs
-        .get(stream.parent_index)
-        .map(|o| o.is_stdout())
-        .unwrap_or(false)
-    });
+    let stdout_output_streams = stdout_output_streams(&output_streams, &outputs);
 
     // Error on mixing rawvideo and non-rawvideo streams
     // TODO: Maybe just revert to chunk mode if this happens?
@@ -205,12 +573,14 @@ pub fn spawn_stdout_thread(
       panic!("Cannot mix rawvideo and non-rawvideo streams");
     }
 
-    // Prepare buffers
+    // Prepare buffers. Raw frames are read into a new buffer each, moved
+    // into the event rather than copied, so this thread holds no frame
+    // besides the one it's reading or sending.
     let mut buffers = stdout_output_streams
       .map(|stream| {
-        let bytes_per_frame = get_bytes_per_frame(stream);
+        let bytes_per_frame = frame_bytes(stream).and_then(|bytes| usize::try_from(bytes).ok());
         let buf_size = match stream.format.as_str() {
-          "rawvideo" => bytes_per_frame.expect("Should use a known pix_fmt") as usize,
+          "rawvideo" => bytes_per_frame.expect("Should use a known pix_fmt"),
 
           // Arbitrary default buffer size for receiving indeterminate chunks
           // of any encoder or container output, when frame boundaries are unknown
@@ -223,9 +593,12 @@ pub fn spawn_stdout_thread(
           "Unsupported pixel format with 0 bytes per pixel"
         );
 
-        vec![0u8; buf_size]
+        match stream.format.as_str() {
+          "rawvideo" => (buf_size, Vec::new()),
+          _ => (buf_size, vec![0u8; buf_size]),
+        }
       })
-      .collect::<Vec<Vec<u8>>>();
+      .collect::<Vec<(usize, Vec<u8>)>>();
 
     // No buffers probably indicates that output is being sent to file
     if buffers.is_empty() {
@@ -240,31 +613,39 @@ pub fn spawn_stdout_thread(
     loop {
       let i = buffer_index.next().unwrap();
       let stream = &output_streams[i];
-      let buffer = &mut buffers[i];
+      let (buf_size, buffer) = &mut buffers[i];
       let output_frame_num = frame_num / num_buffers;
       let timestamp = output_frame_num as f32 / stream.fps;
       frame_num += 1;
 
       // Handle two scenarios:
-      match stream.format.as_str() {
+      let sent = match stream.format.as_str() {
         // 1. `rawvideo` with exactly known pixel layout
-        "rawvideo" => match reader.read_exact(buffer.as_mut_slice()) {
-          Ok(_) => tx
-            .send(FfmpegEvent::OutputFrame(OutputVideoFrame {
-              width: stream.width,
-              height: stream.height,
-              pix_fmt: stream.pix_fmt.clone(),
-              output_index: i as u32,
-              data: buffer.clone(),
-              frame_num: output_frame_num as u32,
-              timestamp,
-            }))
-            .ok(),
-          Err(e) => match e.kind() {
-            ErrorKind::UnexpectedEof => break,
-            e => tx.send(FfmpegEvent::Error(e.to_string())).ok(),
-          },
-        },
+        "rawvideo" => {
+          let mut data = vec![0u8; *buf_size];
+          match read_frame(&mut reader, &mut data) {
+            Ok(0) => break,
+            // Stdout can only end between frames, unless something else
+            // wrote to it
+            Ok(read) if read < data.len() => {
+              tx.send(FfmpegEvent::Error(partial_frame_error(read, data.len(), i)))
+                .ok();
+              break;
+            }
+            Ok(_) => tx
+              .send(FfmpegEvent::OutputFrame(OutputVideoFrame {
+                width: stream.width,
+                height: stream.height,
+                pix_fmt: stream.pix_fmt.clone(),
+                output_index: i as u32,
+                data,
+                frame_num: output_frame_num as u32,
+                timestamp,
+              }))
+              .ok(),
+            Err(e) => tx.send(FfmpegEvent::Error(e.to_string())).ok(),
+          }
+        }
 
         // 2. Anything else, with unknown buffer size
         _ => match reader.read(buffer.as_mut_slice()) {
@@ -280,25 +661,113 @@ pub fn spawn_stdout_thread(
           },
         },
       };
+
+      // The iterator was dropped; stop reading and close stdout, so ffmpeg
+      // exits with a broken pipe instead of writing output nobody reads
+      if sent.is_none() {
+        return;
+      }
     }
     tx.send(FfmpegEvent::Done).ok();
   })
 }
 
+/// Fill `frame` from `reader`, returning how many bytes were read, fewer
+/// than its size only at the end of the stream.
+fn read_frame<R: Read>(reader: &mut R, frame: &mut [u8]) -> io::Result<usize> {
+  let mut read = 0;
+  while read < frame.len() {
+    match reader.read(&mut frame[read..]) {
+      Ok(0) => break,
+      Ok(n) => read += n,
+      Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+      Err(e) => return Err(e),
+    }
+  }
+  Ok(read)
+}
+
+/// The error for a raw video frame cut short by the end of stdout.
+fn partial_frame_error(read: usize, frame_bytes: usize, output_index: usize) -> String {
+  format!(
+    "stdout ended {read} bytes into a {frame_bytes} byte frame of output {output_index}; \
+     frames don't line up when something else writes to stdout, like `-progress pipe:1` \
+     or another output to `pipe:1`"
+  )
+}
+
+/// The output streams which are sent to stdout.
+fn stdout_output_streams<'a>(
+  output_streams: &'a [AVStream],
+  outputs: &'a [FfmpegOutput],
+) -> impl Iterator<Item = &'a AVStream> + Clone {
+  output_streams.iter().filter(|stream| {
+    outputs
+      .iter()
+      .find(|o| o.index as usize == stream.parent_index)
+      .map(|o| o.is_stdout())
+      .unwrap_or(false)
+  })
+}
+
 /// Spawn a thread which reads and parses lines from ffmpeg's stderr channel.
 /// The cadence is controlled by the synchronous `tx` channel, which blocks
 /// until a receiver is ready to receive the next event.
 pub fn spawn_stderr_thread(stderr: ChildStderr, tx: SyncSender<FfmpegEvent>) -> JoinHandle<()> {
+  read_stderr(
+    stderr,
+    move |event| {
+      tx.send(event).ok();
+    },
+    StderrTail::default(),
+    StderrFilter::default(),
+  )
+}
+
+/// Like `spawn_stderr_thread`, with an unbounded channel so that reading is
+/// never held up by the consumer, also recording the last lines in `tail`,
+/// and skipping the lines rejected by `filter`. Keeps reading after the
+/// iterator is dropped, so that ffmpeg never blocks on a full stderr pipe.
+pub(crate) fn spawn_stderr_thread_with_tail(
+  stderr: ChildStderr,
+  tx: Sender<FfmpegEvent>,
+  tail: StderrTail,
+  filter: StderrFilter,
+) -> JoinHandle<()> {
+  read_stderr(
+    stderr,
+    move |event| {
+      tx.send(event).ok();
+    },
+    tail,
+    filter,
+  )
+}
+
+/// Parse the lines of `stderr` on a new thread, handing each event to
+/// `send`.
+fn read_stderr(
+  stderr: ChildStderr,
+  send: impl Fn(FfmpegEvent) + Send + 'static,
+  tail: StderrTail,
+  filter: StderrFilter,
+) -> JoinHandle<()> {
   std::thread::spawn(move || {
     let reader = BufReader::new(stderr);
     let mut parser = FfmpegLogParser::new(reader);
+    parser.set_filter(filter);
     loop {
       match parser.parse_next_event() {
         Ok(FfmpegEvent::LogEOF) => {
-          tx.send(FfmpegEvent::LogEOF).ok();
+          send(FfmpegEvent::LogEOF);
           break;
         }
-        Ok(event) => tx.send(event).ok(),
+        Ok(event) => {
+          if let Some(line) = event.raw_log_message() {
+            tail.push(line);
+          }
+          send(event)
+        }
         Err(e) => {
           eprintln!("Error parsing ffmpeg output: {}", e);
           break;
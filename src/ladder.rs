@@ -0,0 +1,447 @@
+//! Encoding a bitrate ladder for adaptive streaming: several renditions of a
+//! source at decreasing resolutions and bitrates, in a single ffmpeg run.
+//!
+//! ```rust,no_run
+//! use ffmpeg_sidecar::ladder::{abr_ladder, LadderOutput, DEFAULT_LADDER};
+//!
+//! let report = abr_ladder("movie.mp4", &DEFAULT_LADDER, &LadderOutput::Hls("hls".into())).unwrap();
+//! for adjustment in &report.adjustments {
+//!   println!("{adjustment}");
+//! }
+//! println!("{}", report.master_playlist.unwrap().display());
+//! ```
+
+use std::{
+  cell::RefCell,
+  fmt, fs,
+  path::{Path, PathBuf},
+};
+
+use crate::{
+  command::FfmpegCommand, event::FfmpegEvent, geometry::round_up_even, probe::probe_cached,
+  queue::run_job,
+};
+
+/// Seconds between the keyframes of every rendition, at the same times in
+/// all of them, so players can switch renditions at any segment boundary.
+const KEYFRAME_INTERVAL: u32 = 2;
+
+/// Seconds of each HLS segment, a multiple of [`KEYFRAME_INTERVAL`].
+const HLS_SEGMENT_DURATION: u32 = 6;
+
+/// The name of the HLS playlist listing the renditions.
+pub const MASTER_PLAYLIST: &str = "master.m3u8";
+
+/// A rendition of the ladder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rung {
+  /// Height in pixels, rounded up to an even number. The width follows the
+  /// aspect ratio of the source.
+  pub height: u32,
+  /// Average video bitrate, in kbit/s.
+  pub video_bitrate: u32,
+  /// Audio bitrate, in kbit/s. Ignored if the source has no audio.
+  pub audio_bitrate: u32,
+}
+
+impl Rung {
+  pub const fn new(height: u32, video_bitrate: u32, audio_bitrate: u32) -> Self {
+    Self {
+      height,
+      video_bitrate,
+      audio_bitrate,
+    }
+  }
+
+  /// The name of the rendition, like `720p`, used for its files.
+  pub fn name(&self) -> String {
+    format!("{}p", round_up_even(self.height))
+  }
+}
+
+/// The classic ladder, from 1080p at 5 Mbit/s down to 360p at 700 kbit/s.
+pub const DEFAULT_LADDER: [Rung; 4] = [
+  Rung::new(1080, 5000, 192),
+  Rung::new(720, 3000, 128),
+  Rung::new(480, 1200, 96),
+  Rung::new(360, 700, 96),
+];
+
+/// Where the renditions of [`abr_ladder`] are written. The directory is
+/// created if needed, and existing files are overwritten.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum LadderOutput {
+  /// An HLS stream: [`MASTER_PLAYLIST`] in the directory, and the playlist
+  /// and segments of each rendition in a subdirectory named after it, like
+  /// `720p/index.m3u8`.
+  Hls(PathBuf),
+  /// A standalone MP4 per rendition, named after it, like `720p.mp4`.
+  Files(PathBuf),
+}
+
+/// A change [`abr_ladder`] made to the requested rungs, to avoid upscaling.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LadderAdjustment {
+  /// The rung is taller than the source, so it wasn't encoded.
+  Skipped { rung: Rung, source_height: u32 },
+  /// Every rung is taller than the source, so the shortest one was encoded
+  /// at the height of the source instead.
+  Lowered { rung: Rung, source_height: u32 },
+}
+
+impl fmt::Display for LadderAdjustment {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      LadderAdjustment::Skipped {
+        rung,
+        source_height,
+      } => write!(
+        f,
+        "skipped {} which is taller than the {source_height}p source",
+        rung.name()
+      ),
+      LadderAdjustment::Lowered {
+        rung,
+        source_height,
+      } => write!(
+        f,
+        "encoded {} at {source_height}p, the height of the source",
+        rung.name()
+      ),
+    }
+  }
+}
+
+/// The rungs of a ladder which fit a source, as encoded by [`abr_ladder`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LadderPlan {
+  /// The rungs to encode, in the requested order.
+  pub rungs: Vec<Rung>,
+  pub adjustments: Vec<LadderAdjustment>,
+}
+
+impl LadderPlan {
+  /// Skip the rungs taller than `source_height`, or if they all are, lower
+  /// the shortest one to it.
+  ///
+  /// ```rust
+  /// use ffmpeg_sidecar::ladder::{LadderAdjustment, LadderPlan, Rung, DEFAULT_LADDER};
+  ///
+  /// let plan = LadderPlan::new(&DEFAULT_LADDER, 720);
+  /// assert_eq!(plan.rungs, DEFAULT_LADDER[1..]);
+  /// assert_eq!(
+  ///   plan.adjustments,
+  ///   [LadderAdjustment::Skipped { rung: DEFAULT_LADDER[0], source_height: 720 }]
+  /// );
+  ///
+  /// let plan = LadderPlan::new(&DEFAULT_LADDER, 240);
+  /// assert_eq!(plan.rungs, [Rung::new(240, 700, 96)]);
+  /// assert_eq!(plan.adjustments.len(), 4);
+  /// ```
+  pub fn new(rungs: &[Rung], source_height: u32) -> Self {
+    let source_height = round_up_even(source_height);
+    let (fitting, taller): (Vec<Rung>, Vec<Rung>) = rungs
+      .iter()
+      .partition(|rung| round_up_even(rung.height) <= source_height);
+    let mut adjustments = taller
+      .iter()
+      .map(|&rung| LadderAdjustment::Skipped {
+        rung,
+        source_height,
+      })
+      .collect::<Vec<_>>();
+    if !fitting.is_empty() {
+      return Self {
+        rungs: fitting,
+        adjustments,
+      };
+    }
+
+    let Some(&shortest) = rungs.iter().min_by_key(|rung| rung.height) else {
+      return Self {
+        rungs: Vec::new(),
+        adjustments,
+      };
+    };
+    adjustments.retain(|adjustment| {
+      !matches!(adjustment, LadderAdjustment::Skipped { rung, .. } if *rung == shortest)
+    });
+    adjustments.push(LadderAdjustment::Lowered {
+      rung: shortest,
+      source_height,
+    });
+    Self {
+      rungs: vec![Rung {
+        height: source_height,
+        ..shortest
+      }],
+      adjustments,
+    }
+  }
+}
+
+/// A rendition written by [`abr_ladder`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Rendition {
+  pub rung: Rung,
+  /// The MP4 file, or the HLS playlist of the rendition.
+  pub path: PathBuf,
+}
+
+/// What [`abr_ladder`] wrote.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LadderReport {
+  pub renditions: Vec<Rendition>,
+  /// The rungs which were skipped or lowered to avoid upscaling.
+  pub adjustments: Vec<LadderAdjustment>,
+  /// The HLS playlist listing the renditions, with [`LadderOutput::Hls`].
+  pub master_playlist: Option<PathBuf>,
+}
+
+/// Returned (through `anyhow::Error`) when a ladder can't be encoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LadderError {
+  NoRungs,
+  /// The source has no video stream to scale.
+  NoVideo,
+  /// The ffmpeg run failed, with the spawn error or the error messages
+  /// logged by ffmpeg.
+  Failed(String),
+}
+
+impl fmt::Display for LadderError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      LadderError::NoRungs => f.write_str("a ladder needs at least 1 rung"),
+      LadderError::NoVideo => f.write_str("the source of the ladder has no video"),
+      LadderError::Failed(message) => write!(f, "encoding the ladder failed: {message}"),
+    }
+  }
+}
+
+impl std::error::Error for LadderError {}
+
+/// Encode `input` into a rendition per rung, in a single ffmpeg run which
+/// decodes the source once. Blocks until done.
+///
+/// Rungs taller than the source are skipped rather than upscaled, and listed
+/// in [`LadderReport::adjustments`]. Video is encoded with `libx264` and
+/// audio, if any, with `aac`, with keyframes at the same times in every
+/// rendition. MP4 renditions are
+/// [`web_optimized`](crate::command::FfmpegCommand::web_optimized).
+pub fn abr_ladder<I: AsRef<Path>>(
+  input: I,
+  rungs: &[Rung],
+  output: &LadderOutput,
+) -> anyhow::Result<LadderReport> {
+  abr_ladder_with_progress(input, rungs, output, |_| {})
+}
+
+/// Like [`abr_ladder`], additionally reporting the fraction of the source
+/// which has been encoded, from `0.0` to `1.0`, to `on_progress`. ffmpeg
+/// reports a single progress for all of its outputs, so this covers every
+/// rendition.
+pub fn abr_ladder_with_progress<I, F>(
+  input: I,
+  rungs: &[Rung],
+  output: &LadderOutput,
+  on_progress: F,
+) -> anyhow::Result<LadderReport>
+where
+  I: AsRef<Path>,
+  F: FnMut(f64),
+{
+  if rungs.is_empty() {
+    return Err(LadderError::NoRungs.into());
+  }
+  let input = input.as_ref();
+  let info = probe_cached(input)?;
+  let source_height = info
+    .streams_of_type("video")
+    .next()
+    .and_then(|video| video.height)
+    .ok_or(LadderError::NoVideo)?;
+  let has_audio = info.streams_of_type("audio").next().is_some();
+  let plan = LadderPlan::new(rungs, source_height);
+
+  let (LadderOutput::Hls(dir) | LadderOutput::Files(dir)) = output;
+  fs::create_dir_all(dir)?;
+  let mut renditions = Vec::new();
+  for rung in &plan.rungs {
+    let path = match output {
+      LadderOutput::Hls(dir) => {
+        let rendition_dir = dir.join(rung.name());
+        fs::create_dir_all(&rendition_dir)?;
+        rendition_dir.join("index.m3u8")
+      }
+      LadderOutput::Files(dir) => dir.join(format!("{}.mp4", rung.name())),
+    };
+    renditions.push(Rendition { rung: *rung, path });
+  }
+
+  let duration = info.duration.unwrap_or(0.0);
+  let on_progress = RefCell::new(on_progress);
+  let mut command = ladder_command(input, &plan.rungs, has_audio, output);
+  let outcome = run_job(&mut command, |event| {
+    if let FfmpegEvent::Progress(progress) = event {
+      let out_time = progress.out_time.map_or(0.0, f64::from);
+      if let (Ok(mut on_progress), true) = (on_progress.try_borrow_mut(), duration > 0.0) {
+        on_progress((out_time / duration).clamp(0.0, 1.0));
+      }
+    }
+  });
+  if !outcome.is_success() {
+    let message = match outcome.result {
+      Err(e) => e.to_string(),
+      Ok(status) if outcome.errors.is_empty() => format!("ffmpeg exited with {status}"),
+      Ok(_) => outcome.errors.join("\n"),
+    };
+    return Err(LadderError::Failed(message).into());
+  }
+  on_progress.borrow_mut()(1.0);
+
+  Ok(LadderReport {
+    renditions,
+    adjustments: plan.adjustments,
+    master_playlist: match output {
+      LadderOutput::Hls(dir) => Some(dir.join(MASTER_PLAYLIST)),
+      LadderOutput::Files(_) => None,
+    },
+  })
+}
+
+/// The command encoding every rung of `rungs` from `input`, without checking
+/// them against the source; see [`LadderPlan`]. `has_audio` tells whether
+/// the first audio stream of the input is encoded with each rendition.
+///
+/// With [`LadderOutput::Hls`], the subdirectories of the renditions must
+/// exist before running it.
+///
+/// ```rust
+/// use ffmpeg_sidecar::ladder::{ladder_command, LadderOutput, Rung};
+///
+/// let rungs = [Rung::new(720, 3000, 128), Rung::new(360, 700, 96)];
+/// let command = ladder_command("in.mp4", &rungs, false, &LadderOutput::Files("out".into()));
+/// let args = command.get_args().map(|arg| arg.to_string_lossy()).collect::<Vec<_>>();
+/// assert_eq!(
+///   args[args.iter().position(|arg| arg == "-filter_complex").unwrap() + 1],
+///   "[0:v]split=2[ladder0][ladder1];[ladder0]scale=-2:720[rung0];[ladder1]scale=-2:360[rung1]"
+/// );
+/// ```
+pub fn ladder_command<I: AsRef<Path>>(
+  input: I,
+  rungs: &[Rung],
+  has_audio: bool,
+  output: &LadderOutput,
+) -> FfmpegCommand {
+  let mut command = FfmpegCommand::new();
+  command
+    .input(input.as_ref().to_string_lossy())
+    .filter_complex(ladder_filter(rungs));
+  let keyframes = [
+    "-force_key_frames".to_string(),
+    format!("expr:gte(t,n_forced*{KEYFRAME_INTERVAL})"),
+    "-sc_threshold".to_string(),
+    "0".to_string(),
+  ];
+
+  match output {
+    LadderOutput::Files(dir) => {
+      command.web_optimized();
+      for (i, rung) in rungs.iter().enumerate() {
+        command.map(format!("[rung{i}]"));
+        if has_audio {
+          command.map("0:a:0");
+        }
+        command
+          .codec_video("libx264")
+          .pix_fmt("yuv420p")
+          .args(&keyframes)
+          .args([
+            "-b:v".to_string(),
+            format!("{}k", rung.video_bitrate),
+            "-maxrate".to_string(),
+            format!("{}k", rung.video_bitrate),
+            "-bufsize".to_string(),
+            format!("{}k", rung.video_bitrate * 2),
+          ]);
+        if has_audio {
+          command
+            .codec_audio("aac")
+            .args(["-b:a".to_string(), format!("{}k", rung.audio_bitrate)]);
+        }
+        command
+          .overwrite()
+          .output(dir.join(format!("{}.mp4", rung.name())).to_string_lossy());
+      }
+    }
+    LadderOutput::Hls(dir) => {
+      let mut var_stream_map = Vec::new();
+      for (i, rung) in rungs.iter().enumerate() {
+        command.map(format!("[rung{i}]"));
+        command.args([
+          format!("-b:v:{i}"),
+          format!("{}k", rung.video_bitrate),
+          format!("-maxrate:v:{i}"),
+          format!("{}k", rung.video_bitrate),
+          format!("-bufsize:v:{i}"),
+          format!("{}k", rung.video_bitrate * 2),
+        ]);
+        match has_audio {
+          true => {
+            command
+              .map("0:a:0")
+              .args([format!("-b:a:{i}"), format!("{}k", rung.audio_bitrate)]);
+            var_stream_map.push(format!("v:{i},a:{i},name:{}", rung.name()));
+          }
+          false => var_stream_map.push(format!("v:{i},name:{}", rung.name())),
+        }
+      }
+      command
+        .codec_video("libx264")
+        .pix_fmt("yuv420p")
+        .args(&keyframes);
+      if has_audio {
+        command.codec_audio("aac");
+      }
+      command
+        .format("hls")
+        .args([
+          "-hls_time".to_string(),
+          HLS_SEGMENT_DURATION.to_string(),
+          "-hls_playlist_type".to_string(),
+          "vod".to_string(),
+          "-hls_segment_filename".to_string(),
+          dir
+            .join("%v")
+            .join("segment%03d.ts")
+            .to_string_lossy()
+            .into_owned(),
+          "-master_pl_name".to_string(),
+          MASTER_PLAYLIST.to_string(),
+          "-var_stream_map".to_string(),
+          var_stream_map.join(" "),
+        ])
+        .overwrite()
+        .output(dir.join("%v").join("index.m3u8").to_string_lossy());
+    }
+  }
+  command
+}
+
+/// Split the video of the first input, and scale it to each rung, labeled
+/// `rung0`, `rung1`, ...
+fn ladder_filter(rungs: &[Rung]) -> String {
+  let scale = |rung: &Rung| format!("scale=-2:{}", round_up_even(rung.height));
+  if let [rung] = rungs {
+    return format!("[0:v]{}[rung0]", scale(rung));
+  }
+  let splits = (0..rungs.len())
+    .map(|i| format!("[ladder{i}]"))
+    .collect::<String>();
+  let mut graph = format!("[0:v]split={}{splits}", rungs.len());
+  for (i, rung) in rungs.iter().enumerate() {
+    graph.push_str(&format!(";[ladder{i}]{}[rung{i}]", scale(rung)));
+  }
+  graph
+}
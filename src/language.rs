@@ -0,0 +1,181 @@
+//! Selecting the streams of an input by their `language` tag, e.g. keeping
+//! only the English and Spanish audio tracks of a master. See
+//! [`FfmpegCommand::select_audio_by_language`](crate::command::FfmpegCommand::select_audio_by_language).
+
+use std::fmt;
+
+use crate::{
+  extract::{StreamKind, StreamSpec},
+  probe::{MediaInfo, StreamInfo},
+};
+
+/// A language to select, as in the `language` tag of a stream.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Language {
+  /// An ISO 639-2 code like `eng` or `spa`, matched in any case.
+  Tag(String),
+  /// The streams with no `language` tag, or with `und` (undetermined),
+  /// which the MP4 muxer writes for streams with no tag.
+  Untagged,
+}
+
+impl Language {
+  /// The language of a stream.
+  pub fn of(stream: &StreamInfo) -> Self {
+    match stream.tags.get("language") {
+      Some(tag) if !tag.is_empty() && !tag.eq_ignore_ascii_case("und") => {
+        Language::Tag(tag.to_ascii_lowercase())
+      }
+      _ => Language::Untagged,
+    }
+  }
+
+  fn matches(&self, stream: &StreamInfo) -> bool {
+    match (self, Language::of(stream)) {
+      (Language::Tag(wanted), Language::Tag(tag)) => wanted.eq_ignore_ascii_case(&tag),
+      (Language::Untagged, Language::Untagged) => true,
+      _ => false,
+    }
+  }
+}
+
+impl From<&str> for Language {
+  fn from(tag: &str) -> Self {
+    Language::Tag(tag.to_string())
+  }
+}
+
+impl From<String> for Language {
+  fn from(tag: String) -> Self {
+    Language::Tag(tag)
+  }
+}
+
+impl fmt::Display for Language {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Language::Tag(tag) => f.write_str(tag),
+      Language::Untagged => f.write_str("untagged"),
+    }
+  }
+}
+
+/// The streams of one kind to keep from an input, by language.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LanguageSelection {
+  pub kind: StreamKind,
+  /// The languages in the order of the output streams. All the streams of
+  /// a language are selected, in the order of the input.
+  pub languages: Vec<Language>,
+  /// Skip the languages the input doesn't have, instead of failing.
+  pub allow_missing: bool,
+}
+
+impl LanguageSelection {
+  pub fn new<L: Into<Language> + Clone>(kind: StreamKind, languages: &[L]) -> Self {
+    Self {
+      kind,
+      languages: languages.iter().cloned().map(Into::into).collect(),
+      allow_missing: false,
+    }
+  }
+
+  /// The streams of `media` to map, in the order of the languages.
+  ///
+  /// ```rust
+  /// use ffmpeg_sidecar::{
+  ///   extract::{StreamKind, StreamSpec},
+  ///   language::{Language, LanguageSelection},
+  ///   probe::MediaInfo,
+  /// };
+  ///
+  /// let media = MediaInfo::parse(
+  ///   "[STREAM]\ncodec_type=video\n[/STREAM]\n\
+  ///    [STREAM]\ncodec_type=audio\nTAG:language=fre\n[/STREAM]\n\
+  ///    [STREAM]\ncodec_type=audio\nTAG:language=spa\n[/STREAM]\n\
+  ///    [STREAM]\ncodec_type=audio\n[/STREAM]\n\
+  ///    [STREAM]\ncodec_type=audio\nTAG:language=eng\n[/STREAM]\n",
+  /// );
+  /// let selection = LanguageSelection::new(StreamKind::Audio, &["ENG", "spa"]);
+  /// assert_eq!(
+  ///   selection.resolve(&media).unwrap(),
+  ///   [StreamSpec::new(StreamKind::Audio, 3), StreamSpec::new(StreamKind::Audio, 1)]
+  /// );
+  ///
+  /// let selection = LanguageSelection::new(StreamKind::Audio, &[Language::Untagged]);
+  /// assert_eq!(selection.resolve(&media).unwrap(), [StreamSpec::new(StreamKind::Audio, 2)]);
+  ///
+  /// let error = LanguageSelection::new(StreamKind::Audio, &["ger"]).resolve(&media).unwrap_err();
+  /// assert_eq!(error.to_string(), "no audio stream in ger; the input has fre, spa, untagged, eng");
+  /// ```
+  pub fn resolve(&self, media: &MediaInfo) -> Result<Vec<StreamSpec>, MissingLanguages> {
+    let streams = media
+      .streams_of_type(self.kind.codec_type())
+      .collect::<Vec<_>>();
+    let mut specs = Vec::new();
+    let mut missing = Vec::new();
+    for language in &self.languages {
+      let before = specs.len();
+      specs.extend(
+        streams
+          .iter()
+          .enumerate()
+          .filter(|(_, stream)| language.matches(stream))
+          .map(|(index, _)| StreamSpec::new(self.kind, index)),
+      );
+      if specs.len() == before {
+        missing.push(language.clone());
+      }
+    }
+    if !missing.is_empty() && !self.allow_missing {
+      let mut available = Vec::new();
+      for language in streams.iter().map(|stream| Language::of(stream)) {
+        if !available.contains(&language) {
+          available.push(language);
+        }
+      }
+      return Err(MissingLanguages {
+        kind: self.kind,
+        missing,
+        available,
+      });
+    }
+    Ok(specs)
+  }
+}
+
+/// Returned (through `io::Error`, with the kind `InvalidInput`) by
+/// [`FfmpegCommand::spawn`](crate::command::FfmpegCommand::spawn) before
+/// spawning, when an input has no stream in a language selected with
+/// [`select_by_language`](crate::command::FfmpegCommand::select_by_language).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MissingLanguages {
+  pub kind: StreamKind,
+  pub missing: Vec<Language>,
+  /// The languages of the streams of this kind in the input, in order.
+  pub available: Vec<Language>,
+}
+
+impl fmt::Display for MissingLanguages {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let join = |languages: &[Language]| {
+      languages
+        .iter()
+        .map(Language::to_string)
+        .collect::<Vec<_>>()
+        .join(", ")
+    };
+    write!(
+      f,
+      "no {} stream in {}; the input has {}",
+      self.kind.codec_type(),
+      join(&self.missing),
+      match self.available.is_empty() {
+        true => "none".to_string(),
+        false => join(&self.available),
+      }
+    )
+  }
+}
+
+impl std::error::Error for MissingLanguages {}
@@ -30,20 +30,139 @@
 //! }
 //! ```
 //!
+//! ## Features
+//!
+//! - `process` (default): spawning ffmpeg and ffprobe, and reading their
+//!   output, e.g. [`FfmpegCommand::spawn`](command::FfmpegCommand::spawn).
+//! - `download` (default): downloading an ffmpeg build with
+//!   `download::auto_download`.
+//! - `parser`: only the log parser, the event types and the argument building
+//!   of `FfmpegCommand`, e.g. for a `wasm32` tool which hands the arguments to
+//!   an ffmpeg running elsewhere. Implied by `process`.
+//! - `ffplay`: spawning and downloading `ffplay`.
+//! - `serde`: `Serialize` for reports like `crash::CrashReport`,
+//!   `Deserialize` for `install_info::InstallInfo`, both for the event types,
+//!   and recording sessions to JSON lines with `recording`.
+//!
 
-#[cfg(test)]
+#[cfg(all(test, feature = "process"))]
 mod test;
 
+pub mod arg_audit;
+pub mod args;
+#[cfg(feature = "process")]
+pub mod atomic_output;
+pub mod audio;
+#[cfg(feature = "process")]
+pub mod batch;
+#[cfg(feature = "process")]
+pub mod broadcast;
+pub mod bsf;
+pub mod captions;
+#[cfg(feature = "process")]
 pub mod child;
+pub mod color;
 pub mod comma_iter;
 pub mod command;
+pub mod compatibility;
+#[cfg(feature = "process")]
+pub mod concat;
+pub mod container;
+pub mod cpu_budget;
+#[cfg(feature = "process")]
+pub mod crash;
+#[cfg(feature = "process")]
+pub mod cut;
+pub mod defaults;
+#[cfg(feature = "download")]
 pub mod download;
+pub mod drawtext;
+#[cfg(feature = "process")]
+pub mod encoder;
+pub mod error;
+pub mod error_policy;
 pub mod event;
+pub mod extract;
+pub mod faststart;
+#[cfg(feature = "ffplay")]
+pub mod ffplay;
+#[cfg(feature = "process")]
 pub mod ffprobe;
+pub mod filter_command;
+pub mod filter_graph;
+pub mod frame_count;
+#[cfg(feature = "process")]
+pub mod frame_hash;
+pub mod frame_rate;
+#[cfg(feature = "process")]
+pub mod frame_source;
+pub mod gapless;
+pub mod geometry;
+pub mod grid;
+pub mod host;
+pub mod input;
+#[cfg(feature = "download")]
+pub mod install_info;
+pub mod integrity;
+#[cfg(feature = "process")]
 pub mod iter;
+#[cfg(feature = "process")]
+pub mod ladder;
+pub mod language;
+pub mod log_group;
 pub mod log_parser;
 pub mod metadata;
+pub mod metadata_policy;
+pub mod mix;
+pub mod network;
+#[cfg(feature = "process")]
+pub mod orphans;
+pub mod overlay;
+#[cfg(feature = "process")]
+pub mod parallel;
+#[cfg(feature = "process")]
 pub mod paths;
+#[cfg(feature = "process")]
+pub mod pipe;
 pub mod pix_fmt;
+#[cfg(feature = "process")]
+pub mod poster;
+pub mod probe;
+#[cfg(feature = "process")]
+mod process_tree;
+pub mod program;
+pub mod progress_file;
+pub mod progress_view;
+pub mod query;
+#[cfg(feature = "process")]
+pub mod queue;
+pub mod raw_audio;
 pub mod read_until_any;
+#[cfg(feature = "serde")]
+pub mod recording;
+pub mod redact;
+pub mod reproducible;
+pub mod resource_usage;
+pub mod rotation;
+#[cfg(feature = "process")]
+pub mod run;
+pub mod sanitize;
+pub mod segment;
+#[cfg(feature = "process")]
+mod shared_child;
+#[cfg(feature = "process")]
+pub mod silence;
+#[cfg(feature = "process")]
+pub mod spawner;
+pub mod stats;
+pub mod stderr_policy;
+pub mod stdio_policy;
+pub mod summary;
+#[cfg(feature = "process")]
+pub mod temp;
+pub mod template;
+#[cfg(feature = "process")]
+pub mod timeout;
+pub mod timestamp;
 pub mod version;
+pub mod visualize;
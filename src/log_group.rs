@@ -0,0 +1,137 @@
+//! Coalescing the lines of a multi-line log message, like the options dump of
+//! libx264 or the device listing of AVFoundation, into a single event. See
+//! [`FfmpegCommand::group_log_messages`](crate::command::FfmpegCommand::group_log_messages).
+
+use std::time::{Duration, Instant};
+
+use crate::{
+  event::{FfmpegEvent, FfmpegLogGroup, LogLevel},
+  log_parser::try_parse_log_context,
+};
+
+/// How long the first line of a group waits for the next ones, by default.
+pub const LOG_GROUP_WINDOW: Duration = Duration::from_millis(100);
+
+/// Groups consecutive [`FfmpegEvent::Log`] events into
+/// [`FfmpegEvent::LogGroup`] events.
+///
+/// A line joins the group of the previous one if it has the same level and
+/// the same component prefix, like `[libx264 @ 0x55d0c1a0]`, or if it has no
+/// component and its message is indented. Any other event, like progress,
+/// closes the group, and so does a line arriving more than the window after
+/// the first line of the group. A group of a single line is emitted as its
+/// original `Log` event.
+///
+/// ```rust
+/// use ffmpeg_sidecar::{
+///   event::{FfmpegEvent, LogLevel},
+///   log_group::{LogGrouper, LOG_GROUP_WINDOW},
+/// };
+/// use std::time::Instant;
+///
+/// let log = |line: &str| FfmpegEvent::Log(LogLevel::Info, line.to_string());
+/// let mut grouper = LogGrouper::new(LOG_GROUP_WINDOW);
+/// let now = Instant::now();
+/// assert!(grouper.offer(log("[libx264 @ 0x6000] [info] profile High, level 3.0"), now).is_empty());
+/// assert!(grouper.offer(log("[libx264 @ 0x6000] [info] 264 - core 164"), now).is_empty());
+/// let ready = grouper.offer(log("[info] Press [q] to stop, [?] for help"), now);
+/// let FfmpegEvent::LogGroup(group) = &ready[0] else { panic!() };
+/// assert_eq!(group.component.as_deref(), Some("libx264"));
+/// assert_eq!(group.lines.len(), 2);
+/// assert!(matches!(grouper.flush(), Some(FfmpegEvent::Log(..))));
+/// ```
+#[derive(Debug, Clone)]
+pub struct LogGrouper {
+  window: Duration,
+  open: Option<OpenGroup>,
+}
+
+#[derive(Debug, Clone)]
+struct OpenGroup {
+  level: LogLevel,
+  /// The component and pointer of the first line
+  context: Option<(String, Option<String>)>,
+  lines: Vec<String>,
+  started: Instant,
+}
+
+impl LogGrouper {
+  pub fn new(window: Duration) -> Self {
+    Self { window, open: None }
+  }
+
+  /// Add the next event, received at `now`, returning the events which are
+  /// complete: the previous group if this event doesn't belong to it, and
+  /// this event itself if it isn't a log line.
+  pub fn offer(&mut self, event: FfmpegEvent, now: Instant) -> Vec<FfmpegEvent> {
+    let mut ready = Vec::new();
+    if self.deadline().is_some_and(|deadline| now >= deadline) {
+      ready.extend(self.flush());
+    }
+    let FfmpegEvent::Log(level, line) = event else {
+      ready.extend(self.flush());
+      ready.push(event);
+      return ready;
+    };
+    let context = try_parse_log_context(&line).map(|context| {
+      (
+        context.component.to_string(),
+        context.pointer.map(str::to_string),
+      )
+    });
+    match &mut self.open {
+      Some(open)
+        if open.level == level
+          && match &context {
+            Some(_) => context == open.context,
+            None => is_continuation(&line),
+          } =>
+      {
+        open.lines.push(line);
+      }
+      _ => {
+        ready.extend(self.flush());
+        self.open = Some(OpenGroup {
+          level,
+          context,
+          lines: vec![line],
+          started: now,
+        });
+      }
+    }
+    ready
+  }
+
+  /// When the open group has to be emitted, even if no other event arrives.
+  pub fn deadline(&self) -> Option<Instant> {
+    self.open.as_ref().map(|open| open.started + self.window)
+  }
+
+  /// The open group, e.g. once its deadline has passed or at the end of the
+  /// log.
+  pub fn flush(&mut self) -> Option<FfmpegEvent> {
+    let mut open = self.open.take()?;
+    if open.lines.len() == 1 {
+      return open
+        .lines
+        .pop()
+        .map(|line| FfmpegEvent::Log(open.level, line));
+    }
+    Some(FfmpegEvent::LogGroup(FfmpegLogGroup {
+      level: open.level,
+      component: open.context.map(|(component, _)| component),
+      raw_log_message: open.lines.join("\n"),
+      lines: open.lines,
+    }))
+  }
+}
+
+/// Whether the message of a line without a component is indented, after
+/// its level prefix, like the lines under `Input #0`.
+fn is_continuation(line: &str) -> bool {
+  let message = match line.strip_prefix('[').and_then(|rest| rest.split_once(']')) {
+    Some((level, message)) if !level.contains(' ') => message,
+    _ => return false,
+  };
+  message.starts_with("  ") || message.starts_with('\t')
+}
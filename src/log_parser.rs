@@ -1,15 +1,25 @@
 use std::{
-  io::{BufReader, Read},
-  str::from_utf8,
+  borrow::Cow,
+  collections::VecDeque,
+  io::{BufRead, BufReader, ErrorKind, Read},
+  mem::take,
 };
 
 use crate::{
+  bsf::try_parse_bsf_hint,
+  color::ColorMetadata,
   comma_iter::CommaIter,
+  error::InvalidOption,
   event::{
-    AVStream, FfmpegConfiguration, FfmpegDuration, FfmpegEvent, FfmpegInput, FfmpegOutput,
-    FfmpegProgress, FfmpegVersion, LogLevel,
+    AVStream, EncoderStats, FfmpegConfiguration, FfmpegDuration, FfmpegEncodeSummary, FfmpegEvent,
+    FfmpegFilterCommandReply, FfmpegInput, FfmpegOutput, FfmpegProgress, FfmpegSyncWarning,
+    FfmpegTag, FfmpegVersion, FrameTypeStats, LogContext, LogLevel, ParsedProgram, PromptKind,
+    SyncWarning, TagScope,
   },
-  read_until_any::read_until_any,
+  filter_graph::FilterGraphDump,
+  stderr_policy::StderrFilter,
+  timestamp::Timestamp,
+  version::option_min_version,
 };
 
 #[derive(Debug, Clone, PartialEq)]
@@ -20,99 +30,452 @@ enum LogSection {
   Other,
 }
 
+/// Parses ffmpeg's stderr from a reader, or the stdout of `ffmpeg -version`,
+/// with a [`StreamingParser`].
 pub struct FfmpegLogParser<R: Read> {
   reader: BufReader<R>,
-  cur_section: LogSection,
+  parser: StreamingParser,
+  /// Parsed from the bytes read so far, returned by the next calls
+  events: VecDeque<FfmpegEvent>,
 }
 
 impl<R: Read> FfmpegLogParser<R> {
-  /// Consume lines from the inner reader until obtaining a completed
+  /// Consume bytes from the inner reader until obtaining a completed
   /// `FfmpegEvent`, returning it.
   ///
   /// Typically this consumes a single line, but in the case of multi-line
   /// input/output stream specifications, nested method calls will consume
   /// additional lines until the entire vector of Inputs/Outputs is parsed.
   ///
-  /// Line endings can be marked by three possible delimiters:
-  /// - `\n` (MacOS),
-  /// - `\r\n` (Windows)
-  /// - `\r` (Windows, progress updates which overwrite the previous line)
+  /// See [`StreamingParser`] for the line endings. Once the reader is
+  /// exhausted, every call returns [`FfmpegEvent::LogEOF`].
   pub fn parse_next_event(&mut self) -> anyhow::Result<FfmpegEvent> {
-    let mut buf = Vec::<u8>::new();
-    let bytes_read = read_until_any(&mut self.reader, &[b'\r', b'\n'], &mut buf);
-    let line = from_utf8(buf.as_slice())?.trim();
+    loop {
+      if let Some(event) = self.events.pop_front() {
+        return Ok(event);
+      }
+      let available = match self.reader.fill_buf() {
+        Ok(available) => available,
+        Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+        Err(e) => return Err(e.into()),
+      };
+      let read = available.len();
+      match read {
+        // The reader keeps returning nothing, so the end is reached again
+        // next time
+        0 => self.events.extend(self.parser.end()),
+        _ => self.events.extend(self.parser.push(available)),
+      }
+      self.reader.consume(read);
+    }
+  }
+
+  pub fn new(inner: R) -> Self {
+    Self {
+      reader: BufReader::new(inner),
+      parser: StreamingParser::new(),
+      events: VecDeque::new(),
+    }
+  }
+
+  /// Skip the lines rejected by `filter` instead of parsing them.
+  #[cfg(feature = "process")]
+  pub(crate) fn set_filter(&mut self, filter: StderrFilter) {
+    self.parser.filter = filter;
+  }
+}
+
+/// Parses ffmpeg's stderr from bytes pushed as they arrive, without doing
+/// any I/O, e.g. to read the log of a process supervised elsewhere.
+/// [`FfmpegLogParser`] reads its events from one.
+///
+/// Line endings can be marked by three possible delimiters:
+/// - `\n` (MacOS),
+/// - `\r\n` (Windows)
+/// - `\r` (Windows, progress updates which overwrite the previous line)
+///
+/// A line split across pushes is parsed once it's complete, so the events
+/// are the same however the bytes are split. A prompt like
+/// `Overwrite? [y/N] ` has no line ending, since ffmpeg waits for the answer
+/// right after it, so it's parsed as soon as it's pushed.
+///
+/// ```rust
+/// use ffmpeg_sidecar::{event::FfmpegEvent, log_parser::StreamingParser};
+///
+/// let mut parser = StreamingParser::new();
+/// assert!(parser.push(b"[info] Input #0, lavfi, fr").is_empty());
+/// let events = parser.push(b"om 'testsrc':\n[info]   Duration: N/A");
+/// assert!(matches!(events[..], [FfmpegEvent::ParsedInput(_)]));
+/// // The last line needs no line ending
+/// let events = parser.finish();
+/// assert!(matches!(events[..], [FfmpegEvent::Log(..), FfmpegEvent::LogEOF]));
+/// ```
+pub struct StreamingParser {
+  /// The bytes of the line being pushed, until its line ending
+  partial: Vec<u8>,
+  /// Whether the last line ended with `\r`, which a `\n` completes
+  after_cr: bool,
+  cur_section: LogSection,
+  /// Index and path of the segment currently being written
+  open_segment: Option<(u32, String)>,
+  segment_count: u32,
+  /// Returned after the event of the line being parsed, when it produces
+  /// several
+  pending: Vec<FfmpegEvent>,
+  /// The frame statistics of an encoder, until its last line
+  encoder_stats: Option<EncoderStats>,
+  /// Index of the last stream listed in the current input or output
+  cur_stream: Option<u32>,
+  /// The program being listed, until the line after its streams
+  program: Option<ParsedProgram>,
+  /// Indices of the streams of the current input already parsed, since
+  /// those of several programs are listed under each
+  listed_streams: Vec<usize>,
+  /// Indentation and scope of the `Metadata:` block being listed
+  metadata_block: Option<(usize, Option<TagScope>)>,
+  /// The last tag, until the line after its value
+  tag: Option<FfmpegTag>,
+  /// Lines it rejects are skipped before being parsed
+  filter: StderrFilter,
+  /// The filter lines of the verbose log, until the next progress update
+  filter_graph: FilterGraphDump,
+}
+
+impl Default for StreamingParser {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl StreamingParser {
+  pub fn new() -> Self {
+    Self {
+      partial: Vec::new(),
+      after_cr: false,
+      cur_section: LogSection::Other,
+      open_segment: None,
+      segment_count: 0,
+      pending: Vec::new(),
+      encoder_stats: None,
+      cur_stream: None,
+      program: None,
+      listed_streams: Vec::new(),
+      metadata_block: None,
+      tag: None,
+      filter: StderrFilter::default(),
+      filter_graph: FilterGraphDump::default(),
+    }
+  }
+
+  /// Parse the lines completed by `bytes`, returning their events in order.
+  /// The bytes after the last line ending are kept until the next push.
+  ///
+  /// Some events are only complete once the next line is something else,
+  /// like a tag whose value can continue on the next lines, so they're
+  /// returned by a later push, or by [`StreamingParser::finish`].
+  pub fn push(&mut self, bytes: &[u8]) -> Vec<FfmpegEvent> {
+    let mut events = Vec::new();
+    for &byte in bytes {
+      // `\r\n` ends a single line, rather than adding an empty one which
+      // would complete multi-line events early
+      if take(&mut self.after_cr) && byte == b'\n' {
+        continue;
+      }
+      self.after_cr = byte == b'\r';
+      self.partial.push(byte);
+      if byte == b'\r' || byte == b'\n' || self.partial.ends_with(PROMPT_END) {
+        let line = take(&mut self.partial);
+        self.parse_bytes(&line, &mut events);
+      }
+    }
+    events
+  }
+
+  /// Parse the bytes left after the last line ending as a final line, and
+  /// return the events which were waiting for another line, ending with
+  /// [`FfmpegEvent::LogEOF`].
+  pub fn finish(mut self) -> Vec<FfmpegEvent> {
+    self.end()
+  }
+
+  /// Like `finish`, without consuming the parser. Only `LogEOF` is returned
+  /// by the next calls.
+  fn end(&mut self) -> Vec<FfmpegEvent> {
+    let mut events = Vec::new();
+    if !self.partial.is_empty() {
+      let line = take(&mut self.partial);
+      self.parse_bytes(&line, &mut events);
+    }
+    events.extend(self.encoder_stats.take().map(FfmpegEvent::EncoderStats));
+    events.extend(self.tag.take().map(FfmpegEvent::ParsedTag));
+    events.extend(self.program.take().map(FfmpegEvent::ParsedProgram));
+    if !self.filter_graph.is_empty() {
+      events.push(FfmpegEvent::FilterGraph(take(&mut self.filter_graph)));
+    }
+    // The last segment is finalized when ffmpeg exits
+    events.extend(
+      self
+        .open_segment
+        .take()
+        .map(|(index, path)| FfmpegEvent::SegmentComplete { index, path }),
+    );
+    events.push(FfmpegEvent::LogEOF);
+    events
+  }
+
+  /// Parse a line, with its line ending, adding its events to `events`.
+  fn parse_bytes(&mut self, bytes: &[u8], events: &mut Vec<FfmpegEvent>) {
+    let line = String::from_utf8_lossy(bytes);
+    // Matched without the colors of builds which think stderr is a terminal
+    let line = strip_ansi(&line);
+    let line = self.filter.redact(line.trim());
+    let line = line.as_ref();
+    if !self.filter.keep(line) {
+      return;
+    }
+
+    // Events which are complete once the next line is something else
+    if let Some(tag) = &mut self.tag {
+      match try_parse_tag_continuation(line) {
+        Some(value) => {
+          tag.value.push('\n');
+          tag.value.push_str(value);
+          tag.raw_log_message.push('\n');
+          tag.raw_log_message.push_str(line);
+          events.push(FfmpegEvent::Log(LogLevel::Info, line.to_string()));
+          return;
+        }
+        None => events.extend(self.tag.take().map(FfmpegEvent::ParsedTag)),
+      }
+    }
+
+    // The statistics of an encoder are complete after its last frame line
+    let frame_stats = try_parse_encoder_frame_stats(line);
+    match (&frame_stats, &self.encoder_stats) {
+      (Some((encoder, frames)), Some(stats))
+        if stats.encoder == *encoder && stats.count(frames.frame_type) == 0 => {}
+      _ => events.extend(self.encoder_stats.take().map(FfmpegEvent::EncoderStats)),
+    }
+    if let Some((encoder, frames)) = frame_stats {
+      let stats = self.encoder_stats.get_or_insert_with(|| EncoderStats {
+        encoder,
+        frames: Vec::new(),
+        raw_log_message: String::new(),
+      });
+      if !stats.raw_log_message.is_empty() {
+        stats.raw_log_message.push('\n');
+      }
+      stats.raw_log_message.push_str(line);
+      stats.frames.push(frames);
+    }
+
+    // A program is complete after its streams, and their own details
+    if let (Some(_), Some((indent, content))) = (&self.program, split_info_indent(line)) {
+      if indent <= 2 && try_parse_stream_specifier(content).is_none() {
+        events.extend(self.program.take().map(FfmpegEvent::ParsedProgram));
+      }
+    }
+    let event = self.parse_line(line);
+    events.push(event.unwrap_or_else(|e| FfmpegEvent::Error(e.to_string())));
+    events.append(&mut self.pending);
+  }
+
+  fn parse_line(&mut self, line: &str) -> anyhow::Result<FfmpegEvent> {
     let raw_log_message = line.to_string();
-    match bytes_read? {
-      0 => Ok(FfmpegEvent::LogEOF),
-      _ => {
-        // Track log section
-        if let Some(input_number) = try_parse_input(line) {
-          self.cur_section = LogSection::Input(input_number);
-          return Ok(FfmpegEvent::ParsedInput(FfmpegInput {
-            index: input_number,
-            duration: None,
-            raw_log_message,
-          }));
-        } else if let Some(output) = try_parse_output(line) {
-          self.cur_section = LogSection::Output(output.index);
-          return Ok(FfmpegEvent::ParsedOutput(output));
-        } else if line.contains("Stream mapping:") {
-          self.cur_section = LogSection::StreamMapping;
+    if let Some(kind) = try_parse_prompt(line) {
+      return Ok(FfmpegEvent::Prompt {
+        kind,
+        text: raw_log_message,
+      });
+    }
+    // Opening a segment means the previous one is complete
+    if let Some(path) = try_parse_segment_opening(line) {
+      let log = FfmpegEvent::Log(LogLevel::Info, line.to_string());
+      let index = self.segment_count;
+      self.segment_count += 1;
+      return match self.open_segment.replace((index, path)) {
+        Some((index, path)) => {
+          self.pending.push(log);
+          Ok(FfmpegEvent::SegmentComplete { index, path })
         }
+        None => Ok(log),
+      };
+    }
 
-        // Parse
-        if let Some(version) = try_parse_version(line) {
-          Ok(FfmpegEvent::ParsedVersion(FfmpegVersion {
-            version,
-            raw_log_message,
-          }))
-        } else if let Some(configuration) = try_parse_configuration(line) {
-          Ok(FfmpegEvent::ParsedConfiguration(FfmpegConfiguration {
-            configuration,
-            raw_log_message,
-          }))
-        } else if let Some(duration) = try_parse_duration(line) {
-          match self.cur_section {
-            LogSection::Input(input_index) => Ok(FfmpegEvent::ParsedDuration(FfmpegDuration {
-              input_index,
-              duration,
-              raw_log_message,
-            })),
-            _ => Ok(FfmpegEvent::Log(LogLevel::Info, line.to_string())),
-          }
-        } else if self.cur_section == LogSection::StreamMapping && line.contains("  Stream #") {
-          Ok(FfmpegEvent::ParsedStreamMapping(line.to_string()))
-        } else if let Some(stream) = try_parse_stream(line) {
-          match self.cur_section {
-            LogSection::Input(_) => Ok(FfmpegEvent::ParsedInputStream(stream)),
-            LogSection::Output(_) => Ok(FfmpegEvent::ParsedOutputStream(stream)),
-            LogSection::Other | LogSection::StreamMapping => Err(anyhow::Error::msg(format!(
-              "Unexpected stream specification: {}",
-              line
-            ))),
+    self.filter_graph.push_line(line);
+
+    // The line itself is still returned first
+    if let Some(hint) = try_parse_bsf_hint(line) {
+      self.pending.push(FfmpegEvent::Hint(hint));
+    } else if line.contains("Press [q] to stop") {
+      self.pending.push(FfmpegEvent::Interactive);
+    } else if let Some(InvalidOption { option, message }) = try_parse_invalid_option(line) {
+      let hint = option_min_version(&option).map(|(major, minor)| {
+        format!("-{option} needs ffmpeg {major}.{minor} or later; update ffmpeg, e.g. with `auto_download`")
+      });
+      self
+        .pending
+        .push(FfmpegEvent::InvalidOption { option, message });
+      self.pending.extend(hint.map(FfmpegEvent::Hint));
+    }
+
+    // Tags, listed under `Metadata:` with more indentation than it
+    if let Some((indent, content)) = split_info_indent(line) {
+      if content == "Metadata:" {
+        self.metadata_block = Some((indent, self.tag_scope(indent)));
+        return Ok(FfmpegEvent::Log(LogLevel::Info, line.to_string()));
+      }
+      match self.metadata_block {
+        Some((block_indent, scope)) if indent > block_indent => {
+          let key_value = content
+            .split_once(':')
+            .map(|(key, value)| (key.trim(), value));
+          if let (Some(scope), Some((key, value))) = (scope, key_value) {
+            if !key.is_empty() {
+              if let (TagScope::Program { .. }, Some(program)) = (scope, &mut self.program) {
+                if key == "service_name" {
+                  program.service_name = Some(value.trim().to_string());
+                }
+              }
+              self.tag = Some(FfmpegTag {
+                scope,
+                key: key.to_string(),
+                value: value.trim().to_string(),
+                raw_log_message,
+              });
+            }
           }
-        } else if let Some(progress) = try_parse_progress(line) {
-          self.cur_section = LogSection::Other;
-          Ok(FfmpegEvent::Progress(progress))
-        } else if line.contains("[info]") {
+          return Ok(FfmpegEvent::Log(LogLevel::Info, line.to_string()));
+        }
+        _ => self.metadata_block = None,
+      }
+      if let Some((_, stream)) = try_parse_stream_specifier(content) {
+        self.cur_stream = Some(stream);
+        if let Some(program) = &mut self.program {
+          program.stream_indices.push(stream);
+        }
+      }
+    }
+
+    // Track log section
+    if let Some(input_number) = try_parse_input(line) {
+      self.cur_section = LogSection::Input(input_number);
+      self.cur_stream = None;
+      self.listed_streams.clear();
+      return Ok(FfmpegEvent::ParsedInput(FfmpegInput {
+        index: input_number,
+        duration: None,
+        raw_log_message,
+      }));
+    } else if let Some(output) = try_parse_output(line) {
+      self.cur_section = LogSection::Output(output.index);
+      self.cur_stream = None;
+      return Ok(FfmpegEvent::ParsedOutput(output));
+    } else if line.contains("Stream mapping:") {
+      self.cur_section = LogSection::StreamMapping;
+    }
+    if let (LogSection::Input(input_index), Some(id)) = (&self.cur_section, try_parse_program(line))
+    {
+      self.cur_stream = None;
+      self.program = Some(ParsedProgram {
+        input_index: *input_index,
+        id,
+        service_name: None,
+        stream_indices: Vec::new(),
+        raw_log_message,
+      });
+      return Ok(FfmpegEvent::Log(LogLevel::Info, line.to_string()));
+    }
+
+    // Parse
+    if let Some(version) = try_parse_version(line) {
+      Ok(FfmpegEvent::ParsedVersion(FfmpegVersion {
+        version,
+        raw_log_message,
+      }))
+    } else if let Some(configuration) = try_parse_configuration(line) {
+      Ok(FfmpegEvent::ParsedConfiguration(FfmpegConfiguration {
+        configuration,
+        raw_log_message,
+      }))
+    } else if let Some(duration) = try_parse_duration(line) {
+      match self.cur_section {
+        LogSection::Input(input_index) => Ok(FfmpegEvent::ParsedDuration(FfmpegDuration {
+          input_index,
+          duration,
+          raw_log_message,
+        })),
+        _ => Ok(FfmpegEvent::Log(LogLevel::Info, line.to_string())),
+      }
+    } else if self.cur_section == LogSection::StreamMapping && line.contains("  Stream #") {
+      Ok(FfmpegEvent::ParsedStreamMapping(line.to_string()))
+    } else if let Some(stream) = try_parse_stream(line) {
+      match self.cur_section {
+        // Already parsed under another program
+        LogSection::Input(_) if self.listed_streams.contains(&stream.stream_index) => {
           Ok(FfmpegEvent::Log(LogLevel::Info, line.to_string()))
-        } else if line.contains("[warning]") {
-          Ok(FfmpegEvent::Log(LogLevel::Warning, line.to_string()))
-        } else if line.contains("[error]") {
-          Ok(FfmpegEvent::Log(LogLevel::Error, line.to_string()))
-        } else if line.contains("[fatal]") {
-          Ok(FfmpegEvent::Log(LogLevel::Fatal, line.to_string()))
-        } else {
-          Ok(FfmpegEvent::Log(LogLevel::Unknown, line.to_string()))
         }
+        LogSection::Input(_) => {
+          self.listed_streams.push(stream.stream_index);
+          Ok(FfmpegEvent::ParsedInputStream(stream))
+        }
+        LogSection::Output(_) => Ok(FfmpegEvent::ParsedOutputStream(stream)),
+        LogSection::Other | LogSection::StreamMapping => Err(anyhow::Error::msg(format!(
+          "Unexpected stream specification: {}",
+          line
+        ))),
       }
+    } else if let Some(progress) = try_parse_progress(line) {
+      self.cur_section = LogSection::Other;
+      if self.filter_graph.is_empty() {
+        return Ok(FfmpegEvent::Progress(progress));
+      }
+      self.pending.push(FfmpegEvent::Progress(progress));
+      Ok(FfmpegEvent::FilterGraph(take(&mut self.filter_graph)))
+    } else if let Some(kind) = try_parse_sync_warning(line) {
+      Ok(FfmpegEvent::SyncWarning(FfmpegSyncWarning {
+        kind,
+        raw_log_message,
+      }))
+    } else if let Some(reply) = try_parse_filter_command_reply(line) {
+      Ok(FfmpegEvent::FilterCommandReply(reply))
+    } else if let Some(summary) = try_parse_encode_summary(line) {
+      Ok(FfmpegEvent::EncodeSummary(summary))
+    } else if line.contains("[info]") {
+      Ok(FfmpegEvent::Log(LogLevel::Info, line.to_string()))
+    } else if line.contains("[warning]") {
+      Ok(FfmpegEvent::Log(LogLevel::Warning, line.to_string()))
+    } else if line.contains("[error]") {
+      Ok(FfmpegEvent::Log(LogLevel::Error, line.to_string()))
+    } else if line.contains("[fatal]") {
+      Ok(FfmpegEvent::Log(LogLevel::Fatal, line.to_string()))
+    } else {
+      Ok(FfmpegEvent::Log(LogLevel::Unknown, line.to_string()))
     }
   }
 
-  pub fn new(inner: R) -> Self {
-    Self {
-      reader: BufReader::new(inner),
-      cur_section: LogSection::Other,
+  /// What the tags of a `Metadata:` block with this indentation belong to:
+  /// the current input or output, its last stream, or the program being
+  /// listed. The blocks of chapters are more indented, and ignored.
+  fn tag_scope(&self, indent: usize) -> Option<TagScope> {
+    match (&self.cur_section, indent, self.cur_stream) {
+      (LogSection::Input(input), 0..=2, _) => Some(TagScope::Input(*input)),
+      (LogSection::Output(output), 0..=2, _) => Some(TagScope::Output(*output)),
+      (LogSection::Input(input), 4, Some(stream)) => Some(TagScope::InputStream {
+        input: *input,
+        stream,
+      }),
+      (LogSection::Output(output), 4, Some(stream)) => Some(TagScope::OutputStream {
+        output: *output,
+        stream,
+      }),
+      (LogSection::Input(input), 4, None) => {
+        self.program.as_ref().map(|program| TagScope::Program {
+          input: *input,
+          program: program.id,
+        })
+      }
+      _ => None,
     }
   }
 }
@@ -140,6 +503,158 @@ pub fn try_parse_version(string: &str) -> Option<String> {
     .map(|s| s.to_string())
 }
 
+/// Remove the ANSI escape sequences, like the colors ffmpeg adds to its log
+/// when stderr looks like a terminal. Every event is parsed from the line
+/// without them, so they're also missing from
+/// [`FfmpegEvent::raw_log_message`].
+///
+/// ```rust
+/// use ffmpeg_sidecar::log_parser::strip_ansi;
+///
+/// let line = "\x1b[0;33m[aac @ 0x6000] \x1b[0m\x1b[1;31m[error] Too many bits\x1b[0m";
+/// assert_eq!(strip_ansi(line), "[aac @ 0x6000] [error] Too many bits");
+/// assert_eq!(strip_ansi("[info] Press [q] to stop"), "[info] Press [q] to stop");
+/// ```
+pub fn strip_ansi(line: &str) -> Cow<'_, str> {
+  if !line.contains('\x1b') {
+    return Cow::Borrowed(line);
+  }
+  let mut stripped = String::with_capacity(line.len());
+  let mut chars = line.chars();
+  while let Some(c) = chars.next() {
+    if c != '\x1b' {
+      stripped.push(c);
+      continue;
+    }
+    // A control sequence is `ESC [`, parameters and intermediate bytes, and
+    // a final byte from `@` to `~`. Other escapes are a single character.
+    if chars.next() == Some('[') {
+      for c in chars.by_ref() {
+        if ('@'..='~').contains(&c) {
+          break;
+        }
+      }
+    }
+  }
+  Cow::Owned(stripped)
+}
+
+/// Parses the component which logged a line, from the prefix ffmpeg adds
+/// before the message, like `[libx264 @ 0x55d0c1a0]`. Log level prefixes
+/// like `[info]` aren't components.
+///
+/// ## Examples
+///
+/// ```rust
+/// use ffmpeg_sidecar::log_parser::try_parse_log_context;
+///
+/// let line = "[libx264 @ 0x55d0c1a04a40] [info] using cpu capabilities: MMX2 SSE2Fast";
+/// let context = try_parse_log_context(line).unwrap();
+/// assert_eq!(context.component, "libx264");
+/// assert_eq!(context.pointer, Some("0x55d0c1a04a40"));
+///
+/// let line = "[vost#0:0/libx264 @ 0x6000] [error] Error while opening encoder";
+/// assert_eq!(try_parse_log_context(line).unwrap().component, "vost#0:0/libx264");
+/// let line = "[graph 0 input from stream 0:0 @ 0x7f8c] [info] tb:1/25 fmt:yuv420p";
+/// assert_eq!(try_parse_log_context(line).unwrap().component, "graph 0 input from stream 0:0");
+/// assert!(try_parse_log_context("[info] Press [q] to stop, [?] for help").is_none());
+/// assert!(try_parse_log_context("frame=  120 fps= 60 q=28.0 size=  256kB").is_none());
+/// ```
+pub fn try_parse_log_context(line: &str) -> Option<LogContext<'_>> {
+  let (prefix, _) = line.strip_prefix('[')?.split_once(']')?;
+  let (component, pointer) = match prefix.split_once(" @ ") {
+    Some((component, pointer)) => (component, Some(pointer)),
+    None => (prefix, None),
+  };
+  let is_level = matches!(
+    component,
+    "quiet" | "panic" | "fatal" | "error" | "warning" | "info" | "verbose" | "debug" | "trace"
+  );
+  // Filter names can contain spaces, like `graph 0 input from stream 0:0`
+  let valid = match pointer {
+    Some(pointer) => pointer.starts_with("0x"),
+    None => !is_level && !component.contains(' '),
+  };
+  if component.is_empty() || !valid {
+    return None;
+  }
+  Some(LogContext { component, pointer })
+}
+
+/// Parses the errors about an option of the command, which ffmpeg logs
+/// before exiting: an unrecognized option, or an invalid value for one.
+/// Returns the name of the option, without its leading `-`, along with the
+/// message.
+///
+/// The shapes of the messages differ between versions, and ffmpeg follows
+/// them with `Error splitting the argument list`, which doesn't name the
+/// option and isn't parsed.
+///
+/// ## Examples
+///
+/// ```rust
+/// use ffmpeg_sidecar::log_parser::try_parse_invalid_option;
+///
+/// let line = "[error] Unrecognized option 'crf2'.";
+/// let invalid = try_parse_invalid_option(line).unwrap();
+/// assert_eq!(invalid.option, "crf2");
+/// assert_eq!(invalid.message, "Unrecognized option 'crf2'.");
+///
+/// let line = "[error] Failed to set value 'fast' for option 'threads': Invalid argument";
+/// assert_eq!(try_parse_invalid_option(line).unwrap().option, "threads");
+/// let line = "[Parsed_scale_0 @ 0x6000] [error] Option 'wdth' not found";
+/// assert_eq!(try_parse_invalid_option(line).unwrap().option, "wdth");
+/// assert!(try_parse_invalid_option("[fatal] Error splitting the argument list: Option not found").is_none());
+/// ```
+pub fn try_parse_invalid_option(line: &str) -> Option<InvalidOption> {
+  // The message follows the component and level prefixes
+  let mut message = line.trim();
+  while let Some((_, rest)) = message
+    .strip_prefix('[')
+    .and_then(|rest| rest.split_once("] "))
+  {
+    message = rest.trim_start();
+  }
+  let quoted = |prefix: &str| {
+    let (_, rest) = message.split_once(prefix)?;
+    let (option, _) = rest.strip_prefix('\'')?.split_once('\'')?;
+    Some(option)
+  };
+  let option = quoted("Unrecognized option ")
+    .or_else(|| quoted("for option "))
+    .or_else(|| quoted("Error parsing option "))
+    .or_else(|| quoted("Error applying option "))
+    .or_else(|| {
+      // `Option 'wdth' not found` from filters, or `Option wdth not found.`
+      let name = message.strip_prefix("Option ")?.split_once(" not found")?.0;
+      Some(name.trim_matches('\''))
+    })
+    .or_else(|| {
+      Some(
+        message
+          .strip_prefix("Expected number for ")?
+          .split_once(" but found")?
+          .0,
+      )
+    })
+    .or_else(|| {
+      // `Invalid duration specification for ss: 1:x` before ffmpeg 7,
+      // `Invalid duration for option ss: 1:x` since
+      let rest = message
+        .strip_prefix("Invalid duration specification for ")
+        .or_else(|| message.strip_prefix("Invalid duration for option "))?;
+      Some(rest.split_once(':')?.0)
+    })?;
+  let option = option.strip_prefix('-').unwrap_or(option);
+  if option.is_empty() || option.contains(char::is_whitespace) {
+    return None;
+  }
+  Some(InvalidOption {
+    option: option.to_string(),
+    message: message.to_string(),
+  })
+}
+
 /// Parses the list of configuration flags ffmpeg was built with.
 /// Typically the second line of log output.
 ///
@@ -191,14 +706,39 @@ pub fn try_parse_input(string: &str) -> Option<u32> {
     .and_then(|s| s.parse::<u32>().ok())
 }
 
+/// Parse the line starting a program of an input, like a channel of a DVB
+/// capture, extracting its number. Its streams are listed after it, until
+/// the next program or `No Program`.
+///
 /// ## Example:
 ///
 /// ```rust
-/// use ffmpeg_sidecar::log_parser::try_parse_duration;
+/// use ffmpeg_sidecar::log_parser::try_parse_program;
+/// assert_eq!(try_parse_program("[info]   Program 4164 "), Some(4164));
+/// assert_eq!(try_parse_program("[info]   No Program"), None);
+/// ```
+pub fn try_parse_program(string: &str) -> Option<u32> {
+  let (indent, content) = split_info_indent(string)?;
+  match indent {
+    // The number can be followed by the `name` tag of the program
+    2 => content
+      .strip_prefix("Program ")?
+      .split_whitespace()
+      .next()?
+      .parse()
+      .ok(),
+    _ => None,
+  }
+}
+
+/// ## Example:
+///
+/// ```rust
+/// use ffmpeg_sidecar::{log_parser::try_parse_duration, timestamp::Timestamp};
 /// let line = "[info]   Duration: 00:00:05.00, start: 0.000000, bitrate: 16 kb/s, start: 0.000000, bitrate: N/A\n";
 /// let duration = try_parse_duration(line);
 /// println!("{:?}", duration);
-/// assert!(duration == Some(5.0));
+/// assert!(duration == Some(Timestamp::from_secs_f64(5.0)));
 /// ```
 ///
 /// ### Unknown duration
@@ -209,16 +749,18 @@ pub fn try_parse_input(string: &str) -> Option<u32> {
 /// let duration = try_parse_duration(line);
 /// assert!(duration == None);
 /// ```
-pub fn try_parse_duration(string: &str) -> Option<f64> {
+pub fn try_parse_duration(string: &str) -> Option<Timestamp> {
   string
     .strip_prefix("[info]")
     .unwrap_or(string)
     .trim()
     .strip_prefix("Duration:")?
     .trim()
-    .split(',')
-    .next()
-    .and_then(parse_time_str)
+    // Not just `,`, which is the decimal separator of some locales
+    .split(", ")
+    .next()?
+    .parse()
+    .ok()
 }
 
 /// Parse an output section like the following, extracting the index of the input:
@@ -305,6 +847,21 @@ pub fn try_parse_output(mut string: &str) -> Option<FfmpegOutput> {
 /// assert!(stream.stream_type == "Audio");
 /// ```
 ///
+/// ### Broadcast capture with captions and a data stream:
+///
+/// ```rust
+/// use ffmpeg_sidecar::{extract::StreamKind, log_parser::try_parse_stream};
+/// let line = "[info]   Stream #0:0[0x100]: Video: mpeg2video (Main) ([2][0][0][0] / 0x0002), yuv420p(tv, top first), 720x480 [SAR 8:9 DAR 4:3], Closed Captions, 29.97 fps, 29.97 tbr, 90k tbn\n";
+/// let stream = try_parse_stream(line).unwrap();
+/// assert!(stream.has_closed_captions);
+/// assert!(stream.codec_tag.as_deref() == Some("[2][0][0][0]"));
+/// assert!(stream.fps == 29.97);
+///
+/// let line = "[info]   Stream #0:2[0x102]: Data: scte_35\n";
+/// let stream = try_parse_stream(line).unwrap();
+/// assert!(stream.kind() == Some(StreamKind::Data));
+/// assert!(stream.format == "scte_35");
+/// ```
 pub fn try_parse_stream(mut string: &str) -> Option<AVStream> {
   let raw_log_message = string.to_string();
 
@@ -314,140 +871,203 @@ pub fn try_parse_stream(mut string: &str) -> Option<AVStream> {
     .trim()
     .strip_prefix("Stream #")?;
 
-  let mut colon_parts = string.split(':');
-  let parent_index = colon_parts.next()?.parse::<usize>().ok()?;
-  let stream_type = colon_parts.nth(1)?.trim().to_string();
+  // The index can be followed by a stream id and a language, as in
+  // `0:1[0x101](eng): Audio: ...`, and the details contain colons of their
+  // own, like `[SAR 1:1 DAR 16:9]`
+  let (specifier, rest) = string.split_once(": ")?;
+  let (parent_index, stream_index) = parse_stream_indices(specifier)?;
+  let (parent_index, stream_index) = (parent_index as usize, stream_index as usize);
+  let (stream_type, details) = rest.split_once(':').unwrap_or((rest, ""));
+  let stream_type = stream_type.trim().to_string();
+  let mut comma_iter = CommaIter::new(details.trim());
+
+  let codec = comma_iter.next().unwrap_or_default().trim();
+  let format = codec
+    .split(&[' ', '(']) // trim trailing junk like " (avc1 / 0x31637661)"
+    .next()
+    .filter(|format| !format.is_empty())
+    .unwrap_or("unknown")
+    .to_string();
+  let codec_tag = try_parse_codec_tag(codec);
+
   if stream_type != "Video" {
     // Audio is not well-supported yet (PRs welcome)
     return Some(AVStream {
       stream_type,
-      format: "unknown".into(),
+      format,
+      codec_tag,
       pix_fmt: "unknown".into(),
       width: 0,
       height: 0,
       fps: 0.0,
+      color: ColorMetadata::default(),
+      has_closed_captions: false,
       parent_index,
+      stream_index,
       raw_log_message,
     });
   }
-  let comma_string = colon_parts.next()?.trim();
-  let mut comma_iter = CommaIter::new(comma_string);
-
-  let format = comma_iter
-    .next()?
-    .trim()
-    .split(&[' ', '(']) // trim trailing junk like " (avc1 / 0x31637661)"
-    .next()?
-    .to_string();
 
-  let pix_fmt = comma_iter
-    .next()?
-    .trim()
-    .split(&[' ', '(']) // trim trailing junk like "(tv, progressive)"
+  let pix_fmt_details = comma_iter.next()?.trim();
+  let pix_fmt = pix_fmt_details
+    .split(&[' ', '(']) // trim trailing details like "(tv, progressive)"
     .next()?
     .to_string();
+  let color = pix_fmt_details
+    .split_once('(')
+    .map(|(_, details)| ColorMetadata::parse_pix_fmt_details(details.trim_end_matches(')')))
+    .unwrap_or_default();
 
   let dims = comma_iter.next()?.split_whitespace().next()?;
   let mut dims_iter = dims.split('x');
   let width = dims_iter.next()?.parse::<u32>().ok()?;
   let height = dims_iter.next()?.parse::<u32>().ok()?;
 
-  let fps = string
-    .split("fps,")
-    .next()?
-    .split_whitespace()
-    .last()?
-    .parse()
-    .ok()?;
+  let mut has_closed_captions = false;
+  let mut fps = None;
+  for part in comma_iter.map(str::trim) {
+    if part == "Closed Captions" {
+      has_closed_captions = true;
+    } else if let Some(value) = part.strip_suffix(" fps") {
+      fps = value.trim().parse().ok();
+    }
+  }
 
   Some(AVStream {
     stream_type,
     parent_index,
+    stream_index,
     format,
+    codec_tag,
     pix_fmt,
     width,
     height,
-    fps,
+    fps: fps.unwrap_or(0.0),
+    color,
+    has_closed_captions,
     raw_log_message,
   })
 }
 
+/// The input or output index and the stream index of a stream listed in the
+/// log, from the start of a line like `Stream #1:0[0x1](und): Video: ...`.
+///
+/// ```rust
+/// use ffmpeg_sidecar::log_parser::try_parse_stream_specifier;
+///
+/// assert_eq!(try_parse_stream_specifier("Stream #1:0[0x1](und): Video: h264"), Some((1, 0)));
+/// assert_eq!(try_parse_stream_specifier("Stream #2:12: Audio: aac"), Some((2, 12)));
+/// ```
+pub fn try_parse_stream_specifier(string: &str) -> Option<(u32, u32)> {
+  parse_stream_indices(string.strip_prefix("Stream #")?)
+}
+
+fn parse_stream_indices(specifier: &str) -> Option<(u32, u32)> {
+  let (parent, rest) = specifier.split_once(':')?;
+  let end = rest
+    .find(|c: char| !c.is_ascii_digit())
+    .unwrap_or(rest.len());
+  Some((parent.parse().ok()?, rest[..end].parse().ok()?))
+}
+
+/// The indentation of a line of the `[info]` listing of the inputs and
+/// outputs, and the rest of the line.
+fn split_info_indent(line: &str) -> Option<(usize, &str)> {
+  let rest = line.strip_prefix("[info]")?;
+  let content = rest.trim_start_matches(' ');
+  // One space separates the prefix from the line
+  Some(((rest.len() - content.len()).saturating_sub(1), content))
+}
+
+/// The next line of a tag value spanning several lines, printed by ffmpeg
+/// with an empty key, as in `                  : second line`.
+fn try_parse_tag_continuation(line: &str) -> Option<&str> {
+  let (_, content) = split_info_indent(line)?;
+  Some(content.strip_prefix(':')?.trim())
+}
+
+/// The codec tag in a stream's codec description, like `avc1` in
+/// `h264 (High) (avc1 / 0x31637661)`, or `[27][0][0][0]` for MPEG-TS.
+fn try_parse_codec_tag(codec: &str) -> Option<String> {
+  let (before, _) = codec.split_once(" / 0x")?;
+  let (_, tag) = before.rsplit_once('(')?;
+  Some(tag.trim().to_string()).filter(|tag| !tag.is_empty())
+}
+
 /// Parse a progress update line from ffmpeg.
 ///
 /// ## Example
 /// ```rust
-/// use ffmpeg_sidecar::log_parser::try_parse_progress;
+/// use ffmpeg_sidecar::{log_parser::try_parse_progress, timestamp::Timestamp};
 /// let line = "[info] frame= 1996 fps=1984 q=-1.0 Lsize=     372kB time=00:01:19.72 bitrate=  38.2kbits/s speed=79.2x\n";
 /// let progress = try_parse_progress(line).unwrap();
-/// assert!(progress.frame == 1996);
-/// assert!(progress.fps == 1984.0);
-/// assert!(progress.q == -1.0);
-/// assert!(progress.size_kb == 372);
+/// assert!(progress.frame == Some(1996));
+/// assert!(progress.fps == Some(1984.0));
+/// assert!(progress.q == Some(-1.0));
+/// assert!(progress.size_kb == Some(372));
 /// assert!(progress.time == "00:01:19.72");
-/// assert!(progress.bitrate_kbps == 38.2);
+/// assert!(progress.out_time == Some(Timestamp::from_secs_f64(79.72)));
+/// assert!(progress.bitrate_kbps == Some(38.2));
 /// assert!(progress.speed == 79.2);
+/// assert!(progress.dup_frames == 0);
+/// ```
+///
+/// Live captures often also report duplicated and dropped frames:
+///
+/// ```rust
+/// use ffmpeg_sidecar::log_parser::try_parse_progress;
+/// let line = "[info] frame=  240 fps= 30 q=23.0 size=     512kB time=00:00:08.00 bitrate= 524.3kbits/s dup=12 drop=3 speed=1.00x\n";
+/// let progress = try_parse_progress(line).unwrap();
+/// assert!(progress.dup_frames == 12);
+/// assert!(progress.drop_frames == 3);
+/// ```
+///
+/// Audio-only outputs omit the video fields entirely:
+///
+/// ```rust
+/// use ffmpeg_sidecar::{log_parser::try_parse_progress, timestamp::Timestamp};
+/// let line = "[info] size=N/A time=00:00:02.50 bitrate=N/A speed=  25x";
+/// let progress = try_parse_progress(line).unwrap();
+/// assert!(progress.frame.is_none());
+/// assert!(progress.size_kb.is_none());
+/// assert!(progress.bitrate_kbps.is_none());
+/// assert!(progress.out_time == Some(Timestamp::from_secs_f64(2.5)));
 /// ```
 pub fn try_parse_progress(mut string: &str) -> Option<FfmpegProgress> {
   let raw_log_message = string.to_string();
 
   string = string.strip_prefix("[info]").unwrap_or(string).trim();
 
-  let frame = string
-    .split("frame=")
-    .nth(1)?
-    .split_whitespace()
-    .next()?
-    .parse::<u32>()
-    .ok()?;
-  let fps = string
-    .split("fps=")
-    .nth(1)?
-    .split_whitespace()
-    .next()?
-    .parse::<f32>()
-    .ok()?;
-  let q = string
-    .split("q=")
-    .nth(1)?
-    .split_whitespace()
-    .next()?
-    .parse::<f32>()
-    .ok()?;
-  let size_kb = string
-    .split("size=") // captures "Lsize=" AND "size="
-    .nth(1)?
-    .split_whitespace()
-    .next()
-    .map(|s| s.trim())
+  // Every progress line has a size (`size=` or `Lsize=`) and a time, even for
+  // audio-only outputs which omit the video fields
+  if !string.contains("size=") {
+    return None;
+  }
+
+  let frame = progress_value(string, "frame=").and_then(|s| s.parse::<u32>().ok());
+  let fps = progress_value(string, "fps=").and_then(|s| s.parse::<f32>().ok());
+  let q = progress_value(string, "q=").and_then(|s| s.parse::<f32>().ok());
+  let size_kb = progress_value(string, "size=") // captures "Lsize=" AND "size="
     .and_then(|s| {
       s.strip_suffix("KiB") // FFmpeg v7.0 and later
         .or_else(|| s.strip_suffix("kB")) // FFmpeg v6.0 and prior
-    })?
-    .parse::<u32>()
-    .ok()?;
-  let time = string
-    .split("time=")
-    .nth(1)?
-    .split_whitespace()
-    .next()?
-    .to_string();
-  let bitrate_kbps = string
-    .split("bitrate=")
-    .nth(1)?
-    .split_whitespace()
-    .next()?
-    .trim()
-    .strip_suffix("kbits/s")?
-    .parse::<f32>()
-    .ok()?;
-  let speed = string
-    .split("speed=")
-    .nth(1)?
-    .split_whitespace()
-    .next()?
-    .strip_suffix('x')
+    })
+    .and_then(|s| s.parse::<u32>().ok());
+  let time = progress_value(string, "time=")?.to_string();
+  let out_time = time.parse::<Timestamp>().ok().filter(|t| !t.is_negative());
+  let bitrate_kbps = progress_value(string, "bitrate=")
+    .and_then(|s| s.strip_suffix("kbits/s"))
+    .and_then(|s| s.parse::<f32>().ok());
+  let speed = progress_value(string, "speed=")
+    .and_then(|s| s.strip_suffix('x'))
     .map(|s| s.parse::<f32>().unwrap_or(0.0))
     .unwrap_or(0.0);
+  let dup_frames = progress_value(string, "dup=")
+    .and_then(|s| s.parse::<u32>().ok())
+    .unwrap_or(0);
+  let drop_frames = progress_value(string, "drop=")
+    .and_then(|s| s.parse::<u32>().ok())
+    .unwrap_or(0);
 
   Some(FfmpegProgress {
     frame,
@@ -455,13 +1075,238 @@ pub fn try_parse_progress(mut string: &str) -> Option<FfmpegProgress> {
     q,
     size_kb,
     time,
+    out_time,
     bitrate_kbps,
+    dup_frames,
+    drop_frames,
     speed,
     raw_log_message,
   })
 }
 
-/// Parse a time string in the format `HOURS:MM:SS.MILLISECONDS` into a number of seconds.
+/// Get the value following `key` in a progress line, allowing for the padding
+/// ffmpeg inserts after the `=` (e.g. `fps= 30`).
+fn progress_value<'a>(string: &'a str, key: &str) -> Option<&'a str> {
+  string.split(key).nth(1)?.split_whitespace().next()
+}
+
+/// Parse a warning about timestamps or dropped frames, as commonly seen when
+/// capturing from live devices.
+///
+/// ## Examples
+///
+/// ```rust
+/// use ffmpeg_sidecar::{event::SyncWarning, log_parser::try_parse_sync_warning};
+///
+/// let line = "[mp4 @ 0x7f9c1c004a00] [warning] Non-monotonous DTS in output stream 0:1; previous: 2123776, current: 2123264; changing to 2123777. This may result in incorrect timestamps in the output file.";
+/// assert_eq!(
+///   try_parse_sync_warning(line),
+///   Some(SyncWarning::NonMonotonicDts {
+///     stream: Some("0:1".to_string()),
+///     previous: 2123776,
+///     current: 2123264,
+///   })
+/// );
+///
+/// let line = "[warning] Past duration 0.999992 too large";
+/// assert_eq!(
+///   try_parse_sync_warning(line),
+///   Some(SyncWarning::PastDuration { duration: 0.999992 })
+/// );
+/// ```
+pub fn try_parse_sync_warning(string: &str) -> Option<SyncWarning> {
+  if let Some((_, rest)) = string
+    .split_once("Non-monotonous DTS")
+    .or_else(|| string.split_once("Non-monotonic DTS"))
+  {
+    let stream = rest
+      .trim_start()
+      .strip_prefix("in output stream ")
+      .and_then(|s| s.split(';').next())
+      .map(|s| s.trim().to_string());
+    let previous = rest
+      .split("previous: ")
+      .nth(1)?
+      .split(',')
+      .next()?
+      .trim()
+      .parse::<i64>()
+      .ok()?;
+    let current = rest
+      .split("current: ")
+      .nth(1)?
+      .split(';')
+      .next()?
+      .trim()
+      .parse::<i64>()
+      .ok()?;
+    Some(SyncWarning::NonMonotonicDts {
+      stream,
+      previous,
+      current,
+    })
+  } else if let Some((_, rest)) = string.split_once("Past duration ") {
+    let duration = rest.split_whitespace().next()?.parse::<f64>().ok()?;
+    Some(SyncWarning::PastDuration { duration })
+  } else if string.ends_with("frame dropped!") {
+    Some(SyncWarning::FrameDropped { count: 1 })
+  } else {
+    None
+  }
+}
+
+/// Parse the path of a new file opened by the `segment` muxer.
+///
+/// ```rust
+/// use ffmpeg_sidecar::log_parser::try_parse_segment_opening;
+///
+/// let line = "[segment @ 0x5581d6d0c940] [info] Opening 'out001.ts' for writing";
+/// assert_eq!(try_parse_segment_opening(line), Some("out001.ts".to_string()));
+/// ```
+pub fn try_parse_segment_opening(string: &str) -> Option<String> {
+  if !string.starts_with("[segment @") {
+    return None;
+  }
+  let path = string.split_once("Opening '")?.1;
+  let path = path.strip_suffix("' for writing")?;
+  Some(path.to_string())
+}
+
+/// How ffmpeg ends a yes/no question, before reading the answer from stdin.
+const PROMPT_END: &[u8] = b"? [y/N] ";
+
+/// Parse a question ffmpeg asks on stderr before waiting for an answer on
+/// stdin, see [`FfmpegEvent::Prompt`]. It's written without a level prefix.
+///
+/// ```rust
+/// use ffmpeg_sidecar::{event::PromptKind, log_parser::try_parse_prompt};
+///
+/// let line = "File 'out.mp4' already exists. Overwrite? [y/N]";
+/// assert_eq!(try_parse_prompt(line), Some(PromptKind::Overwrite));
+/// assert_eq!(try_parse_prompt("[fatal] File 'out.mp4' already exists. Exiting."), None);
+/// ```
+pub fn try_parse_prompt(string: &str) -> Option<PromptKind> {
+  let question = string.trim_end().strip_suffix("? [y/N]")?;
+  question
+    .ends_with("already exists. Overwrite")
+    .then_some(PromptKind::Overwrite)
+}
+
+/// Parse ffmpeg's reply to an interactive filter command.
+///
+/// ```rust
+/// use ffmpeg_sidecar::log_parser::try_parse_filter_command_reply;
+///
+/// let reply = try_parse_filter_command_reply("Command reply for stream 0: ret:-38 res:").unwrap();
+/// assert_eq!(reply.stream, 0);
+/// assert_eq!(reply.ret, -38);
+/// assert!(!reply.is_success());
+/// ```
+pub fn try_parse_filter_command_reply(string: &str) -> Option<FfmpegFilterCommandReply> {
+  let rest = string.split_once("Command reply for stream ")?.1;
+  let (stream, rest) = rest.split_once(':')?;
+  let (ret, response) = rest
+    .trim_start()
+    .strip_prefix("ret:")?
+    .split_once(" res:")?;
+  Some(FfmpegFilterCommandReply {
+    stream: stream.trim().parse().ok()?,
+    ret: ret.trim().parse().ok()?,
+    response: response.trim().to_string(),
+    raw_log_message: string.to_string(),
+  })
+}
+
+/// Parse the sizes of the streams written, logged at the end of a run.
+///
+/// ```rust
+/// use ffmpeg_sidecar::log_parser::try_parse_encode_summary;
+///
+/// let line = "[out#0/mp4 @ 0x5581d6d0c940] [info] video:10240KiB audio:320KiB subtitle:0KiB other streams:0KiB global headers:0KiB muxing overhead: 0.512%";
+/// let summary = try_parse_encode_summary(line).unwrap();
+/// assert_eq!(summary.video_kb, 10240.0);
+/// assert_eq!(summary.audio_kb, 320.0);
+/// assert_eq!(summary.muxing_overhead_pct, Some(0.512));
+///
+/// let line = "video:0kB audio:86kB subtitle:0kB other streams:0kB global headers:0kB muxing overhead: unknown";
+/// assert_eq!(try_parse_encode_summary(line).unwrap().muxing_overhead_pct, None);
+/// ```
+pub fn try_parse_encode_summary(string: &str) -> Option<FfmpegEncodeSummary> {
+  let (sizes, overhead) = string.split_once("muxing overhead:")?;
+  let size = |key: &str| -> Option<f64> {
+    let value = sizes.split_once(key)?.1;
+    let end = value
+      .find(|c: char| !c.is_ascii_digit() && c != '.')
+      .unwrap_or(value.len());
+    value[..end].parse().ok()
+  };
+  Some(FfmpegEncodeSummary {
+    video_kb: size("video:")?,
+    audio_kb: size("audio:")?,
+    subtitle_kb: size("subtitle:")?,
+    other_kb: size("other streams:")?,
+    global_headers_kb: size("global headers:").unwrap_or(0.0),
+    muxing_overhead_pct: overhead.trim().trim_end_matches('%').parse().ok(),
+    raw_log_message: string.to_string(),
+  })
+}
+
+/// Parse a line of the frame statistics logged by libx264 or libx265 when
+/// they're closed, returning the name of the encoder with them.
+///
+/// ```rust
+/// use ffmpeg_sidecar::log_parser::try_parse_encoder_frame_stats;
+///
+/// let line = "[libx264 @ 0x5581d6d0c940] [info] frame P:47    Avg QP:21.09  size:  3427";
+/// let (encoder, frames) = try_parse_encoder_frame_stats(line).unwrap();
+/// assert_eq!(encoder, "libx264");
+/// assert_eq!((frames.frame_type, frames.count, frames.avg_qp), ('P', 47, 21.09));
+///
+/// let line = "x265 [info]: frame B:     30, Avg QP:31.42  kb/s: 141.55";
+/// let (encoder, frames) = try_parse_encoder_frame_stats(line).unwrap();
+/// assert_eq!(encoder, "libx265");
+/// assert_eq!((frames.frame_type, frames.count, frames.avg_qp), ('B', 30, 31.42));
+/// ```
+pub fn try_parse_encoder_frame_stats(string: &str) -> Option<(String, FrameTypeStats)> {
+  // x265 writes to stderr itself, without going through ffmpeg's logging
+  let encoder = if string.starts_with("[libx264 @") {
+    "libx264"
+  } else if string.starts_with("x265 [") {
+    "libx265"
+  } else {
+    return None;
+  };
+  let (frame_type, rest) = string.split_once(" frame ")?.1.split_once(':')?;
+  let mut chars = frame_type.chars();
+  let frame_type = match (chars.next(), chars.next()) {
+    (Some(c), None) if c.is_ascii_uppercase() => c,
+    _ => return None,
+  };
+  let count = rest
+    .trim_start()
+    .split(|c: char| !c.is_ascii_digit())
+    .next()?
+    .parse()
+    .ok()?;
+  let avg_qp = rest
+    .split_once("Avg QP:")?
+    .1
+    .split_whitespace()
+    .next()?
+    .parse()
+    .ok()?;
+  Some((
+    encoder.to_string(),
+    FrameTypeStats {
+      frame_type,
+      count,
+      avg_qp,
+    },
+  ))
+}
+
+/// Parse a time string in the format `HOURS:MM:SS.MILLISECONDS` into a number
+/// of seconds, or any other [`Timestamp`].
 ///
 /// <https://trac.ffmpeg.org/wiki/Seeking#Timeunitsyntax>
 ///
@@ -478,34 +1323,27 @@ pub fn try_parse_progress(mut string: &str) -> Option<FfmpegProgress> {
 /// assert!(parse_time_str("N/A") == None);
 /// ```
 pub fn parse_time_str(str: &str) -> Option<f64> {
-  let mut seconds = 0.0;
-
-  let mut smh = str.split(':').rev();
-  if let Some(sec) = smh.next() {
-    seconds += sec.parse::<f64>().ok()?;
-  }
-
-  if let Some(min) = smh.next() {
-    seconds += min.parse::<f64>().ok()? * 60.0;
-  }
-
-  if let Some(hrs) = smh.next() {
-    seconds += hrs.parse::<f64>().ok()? * 60.0 * 60.0;
-  }
-
-  Some(seconds)
+  str.parse::<Timestamp>().ok().map(f64::from)
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "process"))]
 mod tests {
   use super::*;
-  use crate::paths::ffmpeg_path;
+  use crate::{
+    error::FfmpegErrorKind,
+    extract::StreamKind,
+    log_group::{LogGrouper, LOG_GROUP_WINDOW},
+    metadata::FfmpegMetadata,
+    paths::ffmpeg_path,
+    summary::FfmpegSummary,
+  };
   use std::{
     io::{Cursor, Seek, SeekFrom, Write},
     process::{Command, Stdio},
   };
 
   #[test]
+  #[allow(clippy::zombie_processes)]
   fn test_parse_version() {
     let cmd = Command::new(ffmpeg_path())
       .arg("-version")
@@ -525,6 +1363,7 @@ mod tests {
   }
 
   #[test]
+  #[allow(clippy::zombie_processes)]
   fn test_parse_configuration() {
     let cmd = Command::new(ffmpeg_path())
       .arg("-version")
@@ -572,12 +1411,1137 @@ mod tests {
   fn test_parse_progress_v7() {
     let line = "[info] frame=    5 fps=0.0 q=-1.0 Lsize=      10KiB time=00:00:03.00 bitrate=  27.2kbits/s speed= 283x\n";
     let progress = try_parse_progress(line).unwrap();
-    assert!(progress.frame == 5);
-    assert!(progress.fps == 0.0);
-    assert!(progress.q == -1.0);
-    assert!(progress.size_kb == 10);
+    assert!(progress.frame == Some(5));
+    assert!(progress.fps == Some(0.0));
+    assert!(progress.q == Some(-1.0));
+    assert!(progress.size_kb == Some(10));
     assert!(progress.time == "00:00:03.00");
-    assert!(progress.bitrate_kbps == 27.2);
+    assert!(progress.bitrate_kbps == Some(27.2));
     assert!(progress.speed == 283.0);
   }
+
+  /// Sync warnings as logged during an avfoundation screen capture on macOS
+  /// (FFmpeg 6, `Non-monotonic`) and a dshow webcam capture on Windows
+  /// (FFmpeg 4, `Non-monotonous`).
+  #[test]
+  fn test_parse_sync_warnings() {
+    let stderr_str = "[info] Input #0, avfoundation, from '1:0':\n[info]   Duration: N/A, start: 52171.370000, bitrate: N/A\n[mp4 @ 0x7fb3a8f06440] [warning] Non-monotonic DTS in output stream 0:1; previous: 1201152, current: 1200640; changing to 1201153. This may result in incorrect timestamps in the output file.\n[vost#0:0/libx264 @ 0x600002c04000] [warning] Past duration 0.622932 too large\r[info] frame=  240 fps= 30 q=28.0 size=     512kB time=00:00:08.00 bitrate= 524.3kbits/s dup=4 drop=7 speed=1.00x\r\n[dshow @ 000001d8e3a2f2c0] [error] real-time buffer [Integrated Camera] [video input] too full or near too full (101% of size: 3041280 [rtbufsize parameter])! frame dropped!\r\n[mp4 @ 000001d8e3b41dc0] [warning] Non-monotonous DTS in output stream 0:0; previous: 86016, current: 85504; changing to 86017. This may result in incorrect timestamps in the output file.\r\n";
+
+    let cursor = Cursor::new(stderr_str.as_bytes().to_vec());
+    let mut parser = FfmpegLogParser::new(cursor);
+    let mut warnings = Vec::new();
+    let mut progress = None;
+    while let Ok(event) = parser.parse_next_event() {
+      match event {
+        FfmpegEvent::SyncWarning(warning) => warnings.push(warning.kind),
+        FfmpegEvent::Progress(p) => progress = Some(p),
+        FfmpegEvent::LogEOF => break,
+        _ => {}
+      }
+    }
+
+    assert_eq!(
+      warnings,
+      vec![
+        SyncWarning::NonMonotonicDts {
+          stream: Some("0:1".to_string()),
+          previous: 1201152,
+          current: 1200640,
+        },
+        SyncWarning::PastDuration { duration: 0.622932 },
+        SyncWarning::FrameDropped { count: 1 },
+        SyncWarning::NonMonotonicDts {
+          stream: Some("0:0".to_string()),
+          previous: 86016,
+          current: 85504,
+        },
+      ]
+    );
+    let progress = progress.unwrap();
+    assert_eq!(progress.dup_frames, 4);
+    assert_eq!(progress.drop_frames, 7);
+  }
+
+  /// Segment muxer logs from a `-f segment -segment_time 10` recording of
+  /// three segments, stopped with `q`.
+  #[test]
+  fn test_parse_segment_complete() {
+    let stderr_str = "[segment @ 0x5581d6d0c940] [info] Opening 'out000.ts' for writing\n[info] Output #0, segment, to 'out%03d.ts':\n[info] frame=  120 fps= 25 q=-1.0 size=N/A time=00:00:04.80 bitrate=N/A speed=   1x\r[segment @ 0x5581d6d0c940] [info] Opening 'out001.ts' for writing\n[segment @ 0x5581d6d0c940] [info] Opening 'out002.ts' for writing\n[info] [q] command received. Exiting.\n";
+
+    let cursor = Cursor::new(stderr_str.as_bytes().to_vec());
+    let mut parser = FfmpegLogParser::new(cursor);
+    let mut events = Vec::new();
+    while let Ok(event) = parser.parse_next_event() {
+      match event {
+        FfmpegEvent::LogEOF => break,
+        event => events.push(event),
+      }
+    }
+
+    let segments = events
+      .iter()
+      .filter_map(|event| match event {
+        FfmpegEvent::SegmentComplete { index, path } => Some((*index, path.as_str())),
+        _ => None,
+      })
+      .collect::<Vec<_>>();
+    assert_eq!(
+      segments,
+      [(0, "out000.ts"), (1, "out001.ts"), (2, "out002.ts")]
+    );
+
+    // The opening lines are still logged, after the previous segment completes
+    let opened = events
+      .iter()
+      .position(|event| matches!(event, FfmpegEvent::Log(_, line) if line.contains("'out001.ts'")));
+    assert!(matches!(
+      events[opened.unwrap() - 1],
+      FfmpegEvent::SegmentComplete { index: 0, .. }
+    ));
+  }
+
+  #[test]
+  fn test_parse_bsf_hint() {
+    let stderr_str = "[mpegts @ 0x55d8c5a0e1c0] [error] H.264 bitstream malformed, no startcode found, use the video bitstream filter 'h264_mp4toannexb' to fix it ('-bsf:v h264_mp4toannexb' option with ffmpeg)\n[info] Conversion failed!\n";
+    let cursor = Cursor::new(stderr_str.as_bytes().to_vec());
+    let mut parser = FfmpegLogParser::new(cursor);
+    assert!(matches!(
+      parser.parse_next_event().unwrap(),
+      FfmpegEvent::Log(LogLevel::Error, _)
+    ));
+    match parser.parse_next_event().unwrap() {
+      FfmpegEvent::Hint(hint) => assert!(hint.contains("-bsf:v h264_mp4toannexb")),
+      event => panic!("expected a hint, got {event:?}"),
+    }
+    assert!(matches!(
+      parser.parse_next_event().unwrap(),
+      FfmpegEvent::Log(LogLevel::Info, _)
+    ));
+  }
+
+  #[test]
+  fn test_parse_interactive() {
+    let stderr_str = "[info] Stream mapping:\n[info]   Stream #0:0 -> #0:0 (wrapped_avframe (native) -> rawvideo (native))\n[info] Press [q] to stop, [?] for help\n[info] Output #0, rawvideo, to 'pipe:':\n";
+    let cursor = Cursor::new(stderr_str.as_bytes().to_vec());
+    let mut parser = FfmpegLogParser::new(cursor);
+    assert!(matches!(
+      parser.parse_next_event().unwrap(),
+      FfmpegEvent::Log(LogLevel::Info, _)
+    ));
+    assert!(matches!(
+      parser.parse_next_event().unwrap(),
+      FfmpegEvent::ParsedStreamMapping(_)
+    ));
+    assert!(matches!(
+      parser.parse_next_event().unwrap(),
+      FfmpegEvent::Log(LogLevel::Info, line) if line.contains("Press [q]")
+    ));
+    assert!(matches!(
+      parser.parse_next_event().unwrap(),
+      FfmpegEvent::Interactive
+    ));
+    assert!(matches!(
+      parser.parse_next_event().unwrap(),
+      FfmpegEvent::ParsedOutput(_)
+    ));
+  }
+
+  /// Collect the events of a complete log, with the lines themselves left out.
+  fn parse_all_but_logs(stderr_str: &str) -> Vec<FfmpegEvent> {
+    let mut parser = FfmpegLogParser::new(Cursor::new(stderr_str.as_bytes().to_vec()));
+    let mut events = Vec::new();
+    loop {
+      match parser.parse_next_event().unwrap() {
+        FfmpegEvent::LogEOF => return events,
+        FfmpegEvent::Log(..) => {}
+        event => events.push(event),
+      }
+    }
+  }
+
+  #[test]
+  fn test_parse_filter_graph_video() {
+    // ffmpeg -loglevel level+verbose -f lavfi -i testsrc=size=320x240:rate=25
+    // -vf scale=160:120,format=gray -pix_fmt yuv420p -c:v libx264 -t 1 out.mp4
+    let stderr_str = "\
+      [Parsed_testsrc_0 @ 0x55d4a1c0] [verbose] size:320x240 rate:25/1 duration:-1.000000 sar:1/1\n\
+      [info] Input #0, lavfi, from 'testsrc=size=320x240:rate=25':\n\
+      [info]   Duration: N/A, start: 0.000000, bitrate: N/A\n\
+      [info]   Stream #0:0: Video: wrapped_avframe, rgb24, 320x240 [SAR 1:1 DAR 4:3], 25 fps, 25 tbr, 25 tbn\n\
+      [info] Stream mapping:\n\
+      [info]   Stream #0:0 -> #0:0 (wrapped_avframe (native) -> h264 (libx264))\n\
+      [info] Press [q] to stop, [?] for help\n\
+      [Parsed_scale_0 @ 0x55d4a3c0] [verbose] w:160 h:120 flags:'' interl:0\n\
+      [graph 0 input from stream 0:0 @ 0x55d4a480] [verbose] w:320 h:240 pixfmt:rgb24 tb:1/25 fr:25/1 sar:1/1\n\
+      [auto_scale_0 @ 0x55d4a600] [verbose] w:iw h:ih flags:'' interl:0\n\
+      [format_out_0_0 @ 0x55d4a540] [verbose] auto-inserting filter 'auto_scale_0' between the filter 'Parsed_format_1' and the filter 'format_out_0_0'\n\
+      [Parsed_scale_0 @ 0x55d4a3c0] [verbose] w:320 h:240 fmt:rgb24 sar:1/1 -> w:160 h:120 fmt:gray sar:1/1 flags:0x00000004\n\
+      [auto_scale_0 @ 0x55d4a600] [verbose] w:160 h:120 fmt:gray sar:1/1 -> w:160 h:120 fmt:yuv420p sar:1/1 flags:0x00000004\n\
+      [libx264 @ 0x55d4a700] [info] using SAR=1/1\n\
+      [info] frame=   25 fps=0.0 q=-1.0 Lsize=       4kB time=00:00:00.92 bitrate=  33.4kbits/s speed=4.12x\n";
+    let events = parse_all_but_logs(stderr_str);
+    let position = |predicate: fn(&FfmpegEvent) -> bool| events.iter().position(predicate);
+    let graph_position = position(|event| matches!(event, FfmpegEvent::FilterGraph(_)));
+    let progress_position = position(|event| matches!(event, FfmpegEvent::Progress(_)));
+    assert_eq!(graph_position.unwrap() + 1, progress_position.unwrap());
+    let Some(FfmpegEvent::FilterGraph(graph)) = graph_position.map(|i| &events[i]) else {
+      unreachable!()
+    };
+
+    let names = graph
+      .nodes
+      .iter()
+      .map(|node| node.name.as_str())
+      .collect::<Vec<_>>();
+    assert_eq!(
+      names,
+      [
+        "Parsed_testsrc_0",
+        "Parsed_scale_0",
+        "graph 0 input from stream 0:0",
+        "auto_scale_0",
+        "format_out_0_0",
+        "Parsed_format_1"
+      ]
+    );
+    let node = |name: &str| graph.nodes.iter().find(|node| node.name == name).unwrap();
+    let scale = node("Parsed_scale_0");
+    assert_eq!(scale.filter, "scale");
+    assert!(!scale.auto_inserted);
+    assert_eq!(scale.args.as_deref(), Some("w:160 h:120 flags:'' interl:0"));
+    assert_eq!(scale.in_pads, ["w:320 h:240 fmt:rgb24 sar:1/1"]);
+    assert_eq!(
+      scale.out_pads,
+      ["w:160 h:120 fmt:gray sar:1/1 flags:0x00000004"]
+    );
+    assert_eq!(scale.formats, ["rgb24", "gray"]);
+    let source = node("graph 0 input from stream 0:0");
+    assert_eq!(
+      (source.filter.as_str(), source.formats.as_slice()),
+      ("buffer", &["rgb24".to_string()][..])
+    );
+    let inserted = node("auto_scale_0");
+    assert!(inserted.auto_inserted);
+    assert_eq!(inserted.pointer.as_deref(), Some("0x55d4a600"));
+    assert_eq!(inserted.formats, ["gray", "yuv420p"]);
+    assert_eq!(node("format_out_0_0").filter, "format");
+    assert_eq!(node("Parsed_format_1").pointer, None);
+    assert_eq!(
+      graph.links().collect::<Vec<_>>(),
+      [
+        ("Parsed_format_1", "auto_scale_0"),
+        ("auto_scale_0", "format_out_0_0")
+      ]
+    );
+  }
+
+  #[test]
+  fn test_parse_filter_graph_audio() {
+    // ffmpeg -loglevel level+verbose -nostats -i voice.wav -i music.wav
+    // -filter_complex amix=inputs=2 mixed.wav, with voice.wav at 44100 Hz and
+    // music.wav at 22050 Hz
+    let stderr_str = "\
+      [info] Press [q] to stop, [?] for help\n\
+      [graph 0 input from stream 0:0 @ 0x6000a0] [verbose] tb:1/44100 samplefmt:s16 samplerate:44100 chlayout:mono\n\
+      [graph 0 input from stream 1:0 @ 0x6000b0] [verbose] tb:1/22050 samplefmt:s16 samplerate:22050 chlayout:mono\n\
+      [Parsed_amix_0 @ 0x6000c0] [verbose] auto-inserting filter 'auto_aresample_0' between the filter 'graph 0 input from stream 0:0' and the filter 'Parsed_amix_0'\n\
+      [Parsed_amix_0 @ 0x6000c0] [verbose] auto-inserting filter 'auto_aresample_1' between the filter 'graph 0 input from stream 1:0' and the filter 'Parsed_amix_0'\n\
+      [format_out_0_0 @ 0x6000d0] [verbose] auto-inserting filter 'auto_aresample_2' between the filter 'Parsed_amix_0' and the filter 'format_out_0_0'\n\
+      [auto_aresample_0 @ 0x6000e0] [verbose] ch:1 chl:mono fmt:s16 r:44100Hz -> ch:1 chl:mono fmt:fltp r:44100Hz\n\
+      [auto_aresample_1 @ 0x6000f0] [verbose] ch:1 chl:mono fmt:s16 r:22050Hz -> ch:1 chl:mono fmt:fltp r:44100Hz\n\
+      [auto_aresample_2 @ 0x600100] [verbose] ch:1 chl:mono fmt:fltp r:44100Hz -> ch:1 chl:mono fmt:s16 r:44100Hz\n\
+      [out#0/wav @ 0x600110] [info] video:0kB audio:86kB subtitle:0kB other streams:0kB global headers:0kB muxing overhead: 0.088%\n";
+    let events = parse_all_but_logs(stderr_str);
+    // Without progress updates, the graph is complete at the end
+    let Some(FfmpegEvent::FilterGraph(graph)) = events.last() else {
+      panic!("expected a filter graph last, got {events:?}")
+    };
+
+    let sources = graph
+      .nodes
+      .iter()
+      .filter(|node| node.filter == "abuffer")
+      .count();
+    assert_eq!(sources, 2);
+    let resamplers = graph
+      .nodes
+      .iter()
+      .filter(|node| node.auto_inserted)
+      .map(|node| (node.filter.as_str(), node.pointer.as_deref()))
+      .collect::<Vec<_>>();
+    assert_eq!(
+      resamplers,
+      [
+        ("aresample", Some("0x6000e0")),
+        ("aresample", Some("0x6000f0")),
+        ("aresample", Some("0x600100"))
+      ]
+    );
+    assert_eq!(
+      graph.links().collect::<Vec<_>>(),
+      [
+        ("graph 0 input from stream 0:0", "auto_aresample_0"),
+        ("auto_aresample_0", "Parsed_amix_0"),
+        ("graph 0 input from stream 1:0", "auto_aresample_1"),
+        ("auto_aresample_1", "Parsed_amix_0"),
+        ("Parsed_amix_0", "auto_aresample_2"),
+        ("auto_aresample_2", "format_out_0_0")
+      ]
+    );
+    assert!(graph.to_dot().contains("  n1 -> n4;\n"));
+  }
+
+  #[test]
+  fn test_parse_x264_summary() {
+    let stderr_str = "[info] [out#0/mp4 @ 0x55d6f0b4a7c0] video:1162KiB audio:0KiB subtitle:0KiB other streams:0KiB global headers:0KiB muxing overhead: 0.078417%
+[info] frame=  250 fps=0.0 q=-1.0 Lsize=    1163KiB time=00:00:09.92 bitrate= 960.3kbits/s speed=17.4x
+[libx264 @ 0x55d6f0b4c1c0] [info] frame I:1     Avg QP:18.76  size: 19533
+[libx264 @ 0x55d6f0b4c1c0] [info] frame P:89    Avg QP:21.42  size:  7408
+[libx264 @ 0x55d6f0b4c1c0] [info] frame B:160   Avg QP:24.87  size:  3172
+[libx264 @ 0x55d6f0b4c1c0] [info] consecutive B-frames:  4.4%  6.4%  6.0% 83.2%
+[libx264 @ 0x55d6f0b4c1c0] [info] kb/s:923.47
+";
+    let events = parse_all_but_logs(stderr_str);
+    assert!(matches!(&events[0], FfmpegEvent::EncodeSummary(summary)
+      if summary.video_kb == 1162.0 && summary.muxing_overhead_pct == Some(0.078417)));
+    assert!(matches!(&events[1], FfmpegEvent::Progress(_)));
+    let FfmpegEvent::EncoderStats(stats) = &events[2] else {
+      panic!("expected encoder stats, got {:?}", events[2]);
+    };
+    assert_eq!(stats.encoder, "libx264");
+    assert_eq!(
+      (stats.count('I'), stats.count('P'), stats.count('B')),
+      (1, 89, 160)
+    );
+    assert_eq!(stats.total_frames(), 250);
+    let avg_qp = stats.avg_qp().unwrap();
+    assert!((avg_qp - (18.76 + 89.0 * 21.42 + 160.0 * 24.87) / 250.0).abs() < 1e-9);
+    assert_eq!(events.len(), 3);
+  }
+
+  #[test]
+  fn test_parse_x265_summary() {
+    // x265 writes its statistics itself, after ffmpeg's own summary
+    let stderr_str = "[info] [out#0/mp4 @ 0x5601b1e4d6c0] video:412kB audio:0kB subtitle:0kB other streams:0kB global headers:2kB muxing overhead: 0.512371%
+x265 [info]: frame I:      2, Avg QP:27.43  kb/s: 3821.70
+x265 [info]: frame P:     58, Avg QP:29.80  kb/s: 472.35
+x265 [info]: frame B:    190, Avg QP:34.98  kb/s: 87.12
+x265 [info]: consecutive B-frames: 4.8% 1.6% 3.2% 17.5% 72.9%
+encoded 250 frames in 4.61s (54.21 fps), 231.02 kb/s, Avg QP:33.51
+";
+    let events = parse_all_but_logs(stderr_str);
+    assert_eq!(events.len(), 2);
+    let FfmpegEvent::EncodeSummary(summary) = &events[0] else {
+      panic!("expected encode summary, got {:?}", events[0]);
+    };
+    assert_eq!(summary.global_headers_kb, 2.0);
+    assert_eq!(summary.total_kb(), 414.0);
+    let FfmpegEvent::EncoderStats(stats) = &events[1] else {
+      panic!("expected encoder stats, got {:?}", events[1]);
+    };
+    assert_eq!(stats.encoder, "libx265");
+    assert_eq!(stats.count('B'), 190);
+    assert_eq!(stats.frames[0].avg_qp, 27.43);
+  }
+
+  #[test]
+  fn test_parse_aac_summary() {
+    // Two outputs, the second to a pipe, and no frame statistics
+    let stderr_str = "[info] [out#0/adts @ 0x55c0a8f1e2c0] video:0KiB audio:161KiB subtitle:0KiB other streams:0KiB global headers:0KiB muxing overhead: 0.000000%
+[info] [out#1/adts @ 0x55c0a8f20a00] video:0KiB audio:161KiB subtitle:0KiB other streams:0KiB global headers:0KiB muxing overhead: unknown
+[aac @ 0x55c0a8f1f380] [info] Qavg: 65519.867
+";
+    let events = parse_all_but_logs(stderr_str);
+    assert_eq!(events.len(), 2);
+    assert!(matches!(&events[0], FfmpegEvent::EncodeSummary(summary)
+      if summary.audio_kb == 161.0 && summary.muxing_overhead_pct == Some(0.0)));
+    assert!(matches!(&events[1], FfmpegEvent::EncodeSummary(summary)
+      if summary.muxing_overhead_pct.is_none()));
+
+    let mut summary = FfmpegSummary::new();
+    events.iter().for_each(|event| summary.handle_event(event));
+    assert!(summary.encoder_stats.is_empty());
+    assert_eq!(
+      summary.encode.unwrap().raw_log_message,
+      stderr_str.lines().nth(1).unwrap()
+    );
+  }
+
+  #[test]
+  fn test_raw_and_context() {
+    let lines = [
+      "[libx264 @ 0x55d0c1a04a40] [info] using cpu capabilities: MMX2 SSE2Fast SSSE3",
+      "[info] frame=  120 fps= 60 q=28.0 size=     256kB time=00:00:04.00 bitrate= 524.3kbits/s speed=2.01x",
+      "[h264 @ 0x55d0c1b0] [error] concealing 1620 DC, 1620 AC, 1620 MV errors in P frame",
+      "[aac @ 0x6000] [warning] Too many bits per frame requested, clamping to max",
+      "[libx264 @ 0x55d0c1a04a40] [info] frame I:1     Avg QP:21.40  size:  9645",
+      "[libx264 @ 0x55d0c1a04a40] [info] frame P:119   Avg QP:23.91  size:  1046",
+      "[out#0/mp4 @ 0x55d0c180] [info] video:131kB audio:0kB subtitle:0kB other streams:0kB global headers:0kB muxing overhead: 1.2%",
+    ];
+    let stderr_str = lines.join("\n") + "\n";
+    let mut parser = FfmpegLogParser::new(Cursor::new(stderr_str.as_bytes().to_vec()));
+    let mut events = Vec::new();
+    loop {
+      let event = parser.parse_next_event().unwrap();
+      if event == FfmpegEvent::LogEOF {
+        break;
+      }
+      events.push(event);
+    }
+
+    let raw = |predicate: fn(&FfmpegEvent) -> bool| {
+      let event = events.iter().find(|event| predicate(event)).unwrap();
+      let context = event
+        .context()
+        .map(|context| (context.component, context.pointer));
+      (event.raw_log_message().unwrap(), context)
+    };
+    assert_eq!(
+      raw(|event| matches!(event, FfmpegEvent::Log(LogLevel::Info, _))),
+      (lines[0], Some(("libx264", Some("0x55d0c1a04a40"))))
+    );
+    assert_eq!(
+      raw(|event| matches!(event, FfmpegEvent::Progress(_))),
+      (lines[1], None)
+    );
+    assert_eq!(
+      raw(|event| matches!(event, FfmpegEvent::Log(LogLevel::Error, _))),
+      (lines[2], Some(("h264", Some("0x55d0c1b0"))))
+    );
+    assert_eq!(
+      raw(|event| matches!(event, FfmpegEvent::Log(LogLevel::Warning, _))),
+      (lines[3], Some(("aac", Some("0x6000"))))
+    );
+    let stats = raw(|event| matches!(event, FfmpegEvent::EncoderStats(_)));
+    assert_eq!(stats.0, lines[4..6].join("\n"));
+    assert_eq!(stats.1.unwrap().0, "libx264");
+    assert_eq!(
+      raw(|event| matches!(event, FfmpegEvent::EncodeSummary(_))),
+      (lines[6], Some(("out#0/mp4", Some("0x55d0c180"))))
+    );
+  }
+
+  #[test]
+  fn test_parse_three_inputs() {
+    // A video, a logo and a song with ID3 tags and cover art
+    let stderr_str = "[info] Input #0, mov,mp4,m4a,3gp,3g2,mj2, from 'clip.mp4':
+[info]   Metadata:
+[info]     major_brand     : isom
+[info]     minor_version   : 512
+[info]     compatible_brands: isomiso2avc1mp41
+[info]     encoder         : Lavf60.16.100
+[info]   Duration: 00:00:12.00, start: 0.000000, bitrate: 1054 kb/s
+[info]   Stream #0:0[0x1](und): Video: h264 (High) (avc1 / 0x31637661), yuv420p(progressive), 1280x720 [SAR 1:1 DAR 16:9], 1050 kb/s, 30 fps, 30 tbr, 15360 tbn (default)
+[info]     Metadata:
+[info]       handler_name    : VideoHandler
+[info]       vendor_id       : [0][0][0][0]
+[info] Input #1, png_pipe, from 'logo.png':
+[info]   Duration: N/A, bitrate: N/A
+[info]   Stream #1:0: Video: png, rgba(pc), 300x100, 25 tbr, 25 tbn
+[info] Input #2, mp3, from 'song.mp3':
+[info]   Metadata:
+[info]     title           : Night Drive
+[info]     artist          : The Examples
+[info]     comment         : Recorded live
+[info]                     : at the Roxy
+[info]     encoder         : LAME3.100
+[info]   Duration: 00:03:25.04, start: 0.025057, bitrate: 321 kb/s
+[info]   Stream #2:0: Audio: mp3 (mp3float), 44100 Hz, stereo, fltp, 320 kb/s
+[info]   Stream #2:1: Video: mjpeg (Baseline), yuvj420p(pc, bt470bg/unknown/unknown), 500x500 [SAR 1:1 DAR 1:1], 90k tbr, 90k tbn (attached pic)
+[info]     Metadata:
+[info]       comment         : Cover (front)
+[info] Stream mapping:
+[info]   Stream #0:0 (h264) -> overlay (graph 0)
+";
+    let mut parser = FfmpegLogParser::new(Cursor::new(stderr_str.as_bytes().to_vec()));
+    let mut metadata = FfmpegMetadata::new();
+    let mut events = Vec::new();
+    loop {
+      let event = parser.parse_next_event().unwrap();
+      if event == FfmpegEvent::LogEOF {
+        break;
+      }
+      metadata.handle_event(&Some(event.clone())).unwrap();
+      events.push(event);
+    }
+
+    let inputs = events
+      .iter()
+      .filter_map(|event| match event {
+        FfmpegEvent::ParsedInput(input) => Some(input.index),
+        _ => None,
+      })
+      .collect::<Vec<_>>();
+    assert_eq!(inputs, [0, 1, 2]);
+    let durations = metadata
+      .inputs
+      .iter()
+      .map(|input| input.duration.map(f64::from))
+      .collect::<Vec<_>>();
+    assert_eq!(durations, [Some(12.0), None, Some(205.04)]);
+
+    let streams = metadata
+      .input_streams
+      .iter()
+      .map(|stream| {
+        (
+          stream.parent_index,
+          stream.stream_index,
+          stream.format.as_str(),
+        )
+      })
+      .collect::<Vec<_>>();
+    assert_eq!(
+      streams,
+      [
+        (0, 0, "h264"),
+        (1, 0, "png"),
+        (2, 0, "mp3"),
+        (2, 1, "mjpeg")
+      ]
+    );
+    assert_eq!(metadata.input_streams[1].fps, 0.0);
+    assert_eq!(metadata.input_streams[1].width, 300);
+
+    assert_eq!(
+      metadata.tag(TagScope::Input(0), "compatible_brands"),
+      Some("isomiso2avc1mp41")
+    );
+    assert_eq!(
+      metadata.tag(
+        TagScope::InputStream {
+          input: 0,
+          stream: 0
+        },
+        "handler_name"
+      ),
+      Some("VideoHandler")
+    );
+    assert_eq!(
+      metadata.tag(TagScope::Input(2), "artist"),
+      Some("The Examples")
+    );
+    assert_eq!(
+      metadata.tag(TagScope::Input(2), "comment"),
+      Some("Recorded live\nat the Roxy")
+    );
+    assert_eq!(
+      metadata.tag(TagScope::Input(2), "encoder"),
+      Some("LAME3.100")
+    );
+    assert_eq!(
+      metadata.tag(
+        TagScope::InputStream {
+          input: 2,
+          stream: 1
+        },
+        "comment"
+      ),
+      Some("Cover (front)")
+    );
+    assert!(metadata
+      .tags
+      .iter()
+      .all(|tag| tag.scope != TagScope::Input(1)));
+    assert_eq!(metadata.tags.len(), 11);
+  }
+
+  #[test]
+  fn test_parse_broadcast_streams() {
+    // From an ATSC capture, and an MISB drone feed with KLV metadata
+    let stderr_str = "[info] Input #0, mpegts, from 'wxyz_capture.ts':
+[info]   Duration: 00:00:30.03, start: 1.422289, bitrate: 9342 kb/s
+[info]   Program 3
+[info]     Metadata:
+[info]       service_name    : WXYZ-HD
+[info]       service_provider: WXYZ
+[info]   Stream #0:0[0x31]: Video: h264 (High) ([27][0][0][0] / 0x001B), yuv420p(tv, bt709, top first), 1920x1080 [SAR 1:1 DAR 16:9], Closed Captions, 29.97 fps, 59.94 tbr, 90k tbn
+[info]   Stream #0:1[0x34](eng): Audio: ac3 ([129][0][0][0] / 0x0081), 48000 Hz, 5.1(side), fltp, 384 kb/s
+[info]   Stream #0:2[0x34](spa): Audio: ac3 ([129][0][0][0] / 0x0081), 48000 Hz, stereo, fltp, 192 kb/s (visual impaired) (descriptions)
+[info]   Stream #0:3[0x38]: Data: scte_35
+[info] Input #1, mpegts, from 'uav_feed.ts':
+[info]   Duration: 00:01:00.00, start: 10.000000, bitrate: 4521 kb/s
+[info]   Program 1
+[info]   Stream #1:0[0x1e1]: Video: h264 (Constrained Baseline) ([27][0][0][0] / 0x001B), yuv420p(progressive), 1280x720, 30 fps, 30 tbr, 90k tbn
+[info]   Stream #1:1[0x1f1]: Data: klv (KLVA / 0x41564C4B)
+";
+    let cursor = Cursor::new(stderr_str.as_bytes().to_vec());
+    let mut parser = FfmpegLogParser::new(cursor);
+    let mut streams = Vec::new();
+    loop {
+      match parser.parse_next_event().unwrap() {
+        FfmpegEvent::ParsedInputStream(stream) => streams.push(stream),
+        FfmpegEvent::LogEOF => break,
+        _ => {}
+      }
+    }
+    assert_eq!(streams.len(), 6);
+
+    let summary = streams
+      .iter()
+      .map(|stream| {
+        (
+          stream.parent_index,
+          stream.kind(),
+          stream.format.as_str(),
+          stream.codec_tag.as_deref(),
+          stream.has_closed_captions,
+        )
+      })
+      .collect::<Vec<_>>();
+    assert_eq!(
+      summary,
+      [
+        (
+          0,
+          Some(StreamKind::Video),
+          "h264",
+          Some("[27][0][0][0]"),
+          true
+        ),
+        (
+          0,
+          Some(StreamKind::Audio),
+          "ac3",
+          Some("[129][0][0][0]"),
+          false
+        ),
+        (
+          0,
+          Some(StreamKind::Audio),
+          "ac3",
+          Some("[129][0][0][0]"),
+          false
+        ),
+        (0, Some(StreamKind::Data), "scte_35", None, false),
+        (
+          1,
+          Some(StreamKind::Video),
+          "h264",
+          Some("[27][0][0][0]"),
+          false
+        ),
+        (1, Some(StreamKind::Data), "klv", Some("KLVA"), false),
+      ]
+    );
+    assert_eq!((streams[0].width, streams[0].height), (1920, 1080));
+    assert_eq!(streams[0].fps, 29.97);
+    assert_eq!(streams[4].fps, 30.0);
+
+    // A stream mapping line isn't mistaken for a stream
+    assert!(try_parse_stream("[info]   Stream #0:0 -> #0:0 (copy)").is_none());
+  }
+
+  #[test]
+  fn test_parse_programs() {
+    // From a DVB-T capture of a multiplex, with an audio description track
+    // shared by two channels, and the EPG which is in none
+    let stderr_str = "[info] Input #0, mpegts, from 'mux_capture.ts':
+[info]   Duration: 00:00:20.03, start: 52311.104000, bitrate: 18732 kb/s
+[info]   Program 4164 
+[info]     Metadata:
+[info]       service_name    : BBC ONE HD
+[info]       service_provider: BBC
+[info]   Stream #0:0[0x65]: Video: h264 (High) ([27][0][0][0] / 0x001B), yuv420p(tv, bt709, top first), 1920x1080 [SAR 1:1 DAR 16:9], 25 fps, 50 tbr, 90k tbn
+[info]   Stream #0:1[0x66](eng): Audio: mp2 ([3][0][0][0] / 0x0003), 48000 Hz, stereo, fltp, 256 kb/s
+[info]   Stream #0:2[0x6a](eng): Audio: aac_latm (HE-AACv2) ([17][0][0][0] / 0x0011), 48000 Hz, stereo, fltp (visual impaired) (descriptions)
+[info]   Stream #0:3[0x69](eng): Subtitle: dvb_subtitle ([6][0][0][0] / 0x0006) (hearing impaired)
+[info]   Program 4287 
+[info]     Metadata:
+[info]       service_name    : BBC TWO HD
+[info]       service_provider: BBC
+[info]   Stream #0:4[0x6e]: Video: h264 (High) ([27][0][0][0] / 0x001B), yuv420p(tv, bt709, top first), 1920x1080 [SAR 1:1 DAR 16:9], 25 fps, 50 tbr, 90k tbn
+[info]     Side data:
+[info]       cpb: bitrate max/min/avg: 0/0/0 buffer size: 0 vbv_delay: N/A
+[info]   Stream #0:5[0x6f](eng): Audio: mp2 ([3][0][0][0] / 0x0003), 48000 Hz, stereo, fltp, 256 kb/s
+[info]   Stream #0:2[0x6a](eng): Audio: aac_latm (HE-AACv2) ([17][0][0][0] / 0x0011), 48000 Hz, stereo, fltp (visual impaired) (descriptions)
+[info]   No Program
+[info]   Stream #0:6[0x12]: Data: epg
+[info] Input #1, lavfi, from 'anullsrc':
+[info]   Duration: N/A, start: 0.000000, bitrate: 705 kb/s
+[info]   Stream #1:0: Audio: pcm_u8, 44100 Hz, stereo, u8, 705 kb/s
+[info] Stream mapping:
+[info]   Stream #0:4 -> #0:0 (copy)
+";
+    let cursor = Cursor::new(stderr_str.as_bytes().to_vec());
+    let mut parser = FfmpegLogParser::new(cursor);
+    let mut metadata = FfmpegMetadata::new();
+    let mut events = Vec::new();
+    loop {
+      let event = parser.parse_next_event().unwrap();
+      if event == FfmpegEvent::LogEOF {
+        break;
+      }
+      metadata.handle_event(&Some(event.clone())).unwrap();
+      events.push(event);
+    }
+
+    let programs = metadata
+      .programs
+      .iter()
+      .map(|program| {
+        (
+          program.input_index,
+          program.id,
+          program.service_name.as_deref(),
+          program.stream_indices.as_slice(),
+        )
+      })
+      .collect::<Vec<_>>();
+    assert_eq!(
+      programs,
+      [
+        (0, 4164, Some("BBC ONE HD"), &[0, 1, 2, 3][..]),
+        (0, 4287, Some("BBC TWO HD"), &[4, 5, 2][..]),
+      ]
+    );
+    assert_eq!(
+      metadata.programs[0].raw_log_message,
+      "[info]   Program 4164"
+    );
+    assert_eq!(
+      metadata.tag(
+        TagScope::Program {
+          input: 0,
+          program: 4287
+        },
+        "service_provider"
+      ),
+      Some("BBC")
+    );
+
+    // The shared stream is parsed once, and the program follows its streams
+    let stream_indices = metadata
+      .input_streams
+      .iter()
+      .map(|stream| (stream.parent_index, stream.stream_index))
+      .collect::<Vec<_>>();
+    assert_eq!(
+      stream_indices,
+      [
+        (0, 0),
+        (0, 1),
+        (0, 2),
+        (0, 3),
+        (0, 4),
+        (0, 5),
+        (0, 6),
+        (1, 0)
+      ]
+    );
+    let position = |predicate: &dyn Fn(&FfmpegEvent) -> bool| events.iter().position(predicate);
+    let last_stream_of_first = position(
+      &|event| matches!(event, FfmpegEvent::ParsedInputStream(stream) if stream.stream_index == 3),
+    );
+    let first_program = position(&|event| matches!(event, FfmpegEvent::ParsedProgram(_)));
+    let next_program_line =
+      position(&|event| event.raw_log_message() == Some("[info]   Program 4287"));
+    assert!(last_stream_of_first < first_program);
+    assert!(first_program < next_program_line);
+
+    // Inputs without programs have none
+    let plain = "[info] Input #0, lavfi, from 'testsrc':
+[info]   Duration: N/A, start: 0.000000, bitrate: N/A
+[info]   Stream #0:0: Video: wrapped_avframe, rgb24, 320x240 [SAR 1:1 DAR 4:3], 25 fps, 25 tbr, 25 tbn
+";
+    let mut parser = FfmpegLogParser::new(Cursor::new(plain.as_bytes().to_vec()));
+    let mut metadata = FfmpegMetadata::new();
+    loop {
+      let event = parser.parse_next_event().unwrap();
+      if event == FfmpegEvent::LogEOF {
+        break;
+      }
+      metadata.handle_event(&Some(event)).unwrap();
+    }
+    assert!(metadata.programs.is_empty());
+    assert_eq!(metadata.input_streams.len(), 1);
+  }
+
+  #[test]
+  fn test_streaming_parser_byte_at_a_time() {
+    // A session with every kind of multi-line event, Windows line endings,
+    // progress overwriting itself, and a prompt without a line ending
+    let session = "[info] ffmpeg version 7.0 Copyright (c) 2000-2024 the FFmpeg developers\r\n\
+      [info] Input #0, mpegts, from 'capture.ts':\r\n\
+      [info]   Duration: 00:00:20.03, start: 1.400000, bitrate: 18732 kb/s\r\n\
+      [info]   Program 4164 \r\n\
+      [info]     Metadata:\r\n\
+      [info]       service_name    : BBC ONE HD\r\n\
+      [info]   Stream #0:0[0x65]: Video: h264 (High), yuv420p(tv, bt709), 1920x1080 [SAR 1:1 DAR 16:9], 25 fps, 50 tbr, 90k tbn\r\n\
+      [info]   Stream #0:1[0x66](eng): Audio: mp2, 48000 Hz, stereo, fltp, 256 kb/s\r\n\
+      [info]     Metadata:\r\n\
+      [info]       comment         : first line\r\n\
+      [info]                       : second line\r\n\
+      [info] Stream mapping:\r\n\
+      [info]   Stream #0:0 -> #0:0 (h264 (native) -> h264 (libx264))\r\n\
+      [segment @ 0x5581d6d0c940] [info] Opening 'out000.ts' for writing\r\n\
+      [info] Output #0, segment, to 'out%03d.ts':\r\n\
+      [info]   Stream #0:0: Video: h264, yuv420p(tv, bt709, progressive), 1920x1080 [SAR 1:1 DAR 16:9], q=2-31, 25 fps, 90k tbn\r\n\
+      [info] frame=   60 fps= 25 q=28.0 size=N/A time=00:00:02.40 bitrate=N/A speed=   1x\r\
+      [info] frame=  120 fps= 25 q=28.0 size=N/A time=00:00:04.80 bitrate=N/A speed=   1x\r\
+      [segment @ 0x5581d6d0c940] [info] Opening 'out001.ts' for writing\r\n\
+      [libx264 @ 0x55d0c1a04a40] [info] frame I:1     Avg QP:21.40  size:  9645\r\n\
+      [libx264 @ 0x55d0c1a04a40] [info] frame P:59    Avg QP:24.10  size:  1024\r\n\
+      [info] File 'out.ts' already exists. Overwrite? [y/N] ";
+
+    let mut whole = StreamingParser::new();
+    let mut expected = whole.push(session.as_bytes());
+    expected.extend(whole.finish());
+
+    let mut byte_at_a_time = StreamingParser::new();
+    let mut events = Vec::new();
+    for byte in session.as_bytes() {
+      events.extend(byte_at_a_time.push(std::slice::from_ref(byte)));
+    }
+    events.extend(byte_at_a_time.finish());
+    assert_eq!(events, expected);
+
+    // The reader parses the same events
+    let mut parser = FfmpegLogParser::new(Cursor::new(session.as_bytes().to_vec()));
+    let mut read = Vec::new();
+    loop {
+      let event = parser.parse_next_event().unwrap();
+      read.push(event.clone());
+      if event == FfmpegEvent::LogEOF {
+        break;
+      }
+    }
+    assert_eq!(read, expected);
+    assert_eq!(parser.parse_next_event().unwrap(), FfmpegEvent::LogEOF);
+
+    let count = |matches: fn(&FfmpegEvent) -> bool| expected.iter().filter(|e| matches(e)).count();
+    assert_eq!(count(|e| matches!(e, FfmpegEvent::ParsedVersion(_))), 1);
+    assert_eq!(count(|e| matches!(e, FfmpegEvent::ParsedProgram(_))), 1);
+    assert_eq!(count(|e| matches!(e, FfmpegEvent::ParsedInputStream(_))), 2);
+    assert_eq!(
+      count(|e| matches!(e, FfmpegEvent::ParsedOutputStream(_))),
+      1
+    );
+    assert_eq!(count(|e| matches!(e, FfmpegEvent::Progress(_))), 2);
+    assert_eq!(
+      count(|e| matches!(e, FfmpegEvent::SegmentComplete { .. })),
+      2
+    );
+    assert_eq!(count(|e| matches!(e, FfmpegEvent::EncoderStats(_))), 1);
+    assert!(expected.iter().any(|e| matches!(
+      e,
+      FfmpegEvent::ParsedTag(tag) if tag.key == "comment" && tag.value == "first line\nsecond line"
+    )));
+    assert!(matches!(
+      expected[expected.len() - 3..],
+      [
+        FfmpegEvent::Prompt { .. },
+        FfmpegEvent::SegmentComplete { index: 1, .. },
+        FfmpegEvent::LogEOF
+      ]
+    ));
+  }
+
+  #[test]
+  fn test_parse_colored_log() {
+    let log = "\x1b[0;33m[aac @ 0x6000] \x1b[0m\x1b[0;33m[warning] Too many bits per frame requested\x1b[0m\n\
+      \x1b[1;31m[error] Error opening output files: Invalid argument\x1b[0m\n\
+      [info] frame=   25 fps=0.0 q=-0.0 size=N/A time=00:00:01.00 bitrate=N/A speed=  10x\x1b[K\r\
+      [info] Stream mapping:\n\
+      \x1b[0;36m[info]   Stream #0:0 -> #0:0 (h264 (native) -> h264 (libx264))\x1b[0m\n";
+    let mut parser = FfmpegLogParser::new(Cursor::new(log));
+    let mut events = Vec::new();
+    loop {
+      match parser.parse_next_event().unwrap() {
+        FfmpegEvent::LogEOF => break,
+        event => events.push(event),
+      }
+    }
+
+    let FfmpegEvent::Log(LogLevel::Warning, warning) = &events[0] else {
+      panic!("{:?}", events[0]);
+    };
+    assert_eq!(
+      warning,
+      "[aac @ 0x6000] [warning] Too many bits per frame requested"
+    );
+    assert_eq!(events[0].context().unwrap().component, "aac");
+    assert!(matches!(
+      &events[1],
+      FfmpegEvent::Log(LogLevel::Error, error)
+        if error == "[error] Error opening output files: Invalid argument"
+    ));
+    let FfmpegEvent::Progress(progress) = &events[2] else {
+      panic!("{:?}", events[2]);
+    };
+    assert_eq!(progress.frame, Some(25));
+    assert!(!progress.raw_log_message.contains('\x1b'));
+    assert!(matches!(
+      &events[4],
+      FfmpegEvent::ParsedStreamMapping(line)
+        if line == "[info]   Stream #0:0 -> #0:0 (h264 (native) -> h264 (libx264))"
+    ));
+  }
+
+  #[test]
+  fn test_parse_stream_color() {
+    let hdr10 = "[info]   Stream #0:0: Video: hevc (Main 10), yuv420p10le(tv, bt2020nc/bt2020/smpte2084), 3840x2160 [SAR 1:1 DAR 16:9], 23.98 fps, 23.98 tbr, 1k tbn (default)";
+    let color = try_parse_stream(hdr10).unwrap().color;
+    assert_eq!(color.range.as_deref(), Some("tv"));
+    assert_eq!(color.space.as_deref(), Some("bt2020nc"));
+    assert_eq!(color.primaries.as_deref(), Some("bt2020"));
+    assert_eq!(color.transfer.as_deref(), Some("smpte2084"));
+    assert!(color.is_hdr());
+
+    let hlg = "[info]   Stream #0:0[0x1](und): Video: hevc (Main 10) (hvc1 / 0x31637668), yuv420p10le(tv, bt2020nc/bt2020/arib-std-b67, progressive), 1920x1080, 9862 kb/s, 50 fps, 50 tbr, 90k tbn (default)";
+    let stream = try_parse_stream(hlg).unwrap();
+    assert_eq!(stream.pix_fmt, "yuv420p10le");
+    assert_eq!(stream.color.transfer.as_deref(), Some("arib-std-b67"));
+    assert!(stream.color.is_hdr());
+
+    let jpeg = "[info]   Stream #0:0: Video: mjpeg (Baseline), yuvj420p(pc, bt470bg/unknown/unknown), 640x480, 30 fps, 30 tbr, 30 tbn";
+    let color = try_parse_stream(jpeg).unwrap().color;
+    assert_eq!(color.range.as_deref(), Some("pc"));
+    assert_eq!(color.space.as_deref(), Some("bt470bg"));
+    assert_eq!(color.primaries, None);
+    assert!(!color.is_hdr());
+
+    let untagged = "[info]   Stream #0:0: Video: rawvideo (RGB[24] / 0x18424752), rgb24(progressive), 320x240, 25 fps, 25 tbr, 25 tbn";
+    assert_eq!(
+      try_parse_stream(untagged).unwrap().color,
+      ColorMetadata::default()
+    );
+  }
+
+  #[test]
+  fn test_parse_with_stderr_filter() {
+    use crate::stderr_policy::{StderrFilter, StderrPolicy, Verbosity};
+
+    let log = "[info] Input #0, lavfi, from 'testsrc':\n\
+      [trace] read_frame_internal\n\
+      [h264 @ 0x5581e5c0] [debug] nal_unit_type: 5\n\
+      [verbose] [graph 0 input from stream 0:0 @ 0x1] w:320 h:240\n\
+      [info] frame=   25 fps=0.0 q=-0.0 size=N/A time=00:00:01.00 bitrate=N/A speed=  10x\n\
+      [out#0/null @ 0x1] [error] Error muxing a packet: Broken pipe\n";
+    let parse = |policy: StderrPolicy| {
+      let filter = StderrFilter::new(policy);
+      let mut parser = FfmpegLogParser::new(Cursor::new(log));
+      parser.set_filter(filter.clone());
+      let mut lines = Vec::new();
+      loop {
+        match parser.parse_next_event().unwrap() {
+          FfmpegEvent::LogEOF => break,
+          event => lines.extend(event.raw_log_message().map(str::to_string)),
+        }
+      }
+      (lines, filter.dropped())
+    };
+
+    let (lines, dropped) = parse(StderrPolicy::ParseAll);
+    assert_eq!((lines.len(), dropped), (6, 0));
+
+    let (lines, dropped) = parse(StderrPolicy::ParseDropVerbose {
+      keep_levels: Verbosity::Info,
+    });
+    assert_eq!(dropped, 3);
+    assert!(lines[1].contains("frame=   25"));
+    assert!(lines[2].contains("Broken pipe"));
+
+    let (lines, dropped) = parse(StderrPolicy::RawDiscard);
+    assert_eq!(dropped, 5);
+    assert_eq!(lines.len(), 1);
+    assert!(lines[0].contains("Broken pipe"));
+  }
+
+  #[test]
+  fn test_parse_invalid_option() {
+    let parse = |log: &str| {
+      let mut parser = FfmpegLogParser::new(Cursor::new(log.to_string()));
+      let mut summary = FfmpegSummary::new();
+      let mut events = Vec::new();
+      loop {
+        match parser.parse_next_event().unwrap() {
+          FfmpegEvent::LogEOF => break,
+          event => {
+            summary.handle_event(&event);
+            events.push(event);
+          }
+        }
+      }
+      (events, summary)
+    };
+    let invalid = |events: &[FfmpegEvent]| {
+      events
+        .iter()
+        .filter_map(|event| match event {
+          FfmpegEvent::InvalidOption { option, message } => Some((option.clone(), message.clone())),
+          _ => None,
+        })
+        .collect::<Vec<_>>()
+    };
+
+    // `ffmpeg -i in.mp4 -crf2 23 out.mp4`, logged the same by 5.1, 6.1 and 7.0
+    for version in ["5.1.4", "6.1.1", "7.0.2"] {
+      let log = format!(
+        "[info] ffmpeg version {version} Copyright (c) 2000-2024 the FFmpeg developers\n\
+         [error] Unrecognized option 'crf2'.\n\
+         [fatal] Error splitting the argument list: Option not found\n"
+      );
+      let (events, summary) = parse(&log);
+      assert_eq!(
+        invalid(&events),
+        [(
+          "crf2".to_string(),
+          "Unrecognized option 'crf2'.".to_string()
+        )]
+      );
+      // Right after its line
+      assert!(
+        matches!(&events[1], FfmpegEvent::Log(LogLevel::Error, line) if line.contains("crf2"))
+      );
+      assert!(matches!(events[2], FfmpegEvent::InvalidOption { .. }));
+      assert_eq!(summary.errors, [FfmpegErrorKind::InvalidOption]);
+      assert_eq!(summary.invalid_option.unwrap().option, "crf2");
+    }
+
+    // Invalid values, as logged by 5.1 and 6.1, then by 7.0
+    let fixtures = [
+      (
+        "[error] Invalid duration specification for ss: 1:x\n",
+        "ss",
+      ),
+      ("[error] Invalid duration for option ss: 1:x\n", "ss"),
+      ("[error] Expected number for frames but found: ten\n", "frames"),
+      (
+        "[error] Failed to set value 'ten' for option 'frames:v': Invalid argument\n\
+         [fatal] Error splitting the argument list: Invalid argument\n",
+        "frames:v",
+      ),
+      (
+        "[Parsed_scale_0 @ 0x5581c8f0a2c0] [error] Option 'wdth' not found\n\
+         [AVFilterGraph @ 0x5581c8f09d40] [error] Error initializing filter 'scale' with args 'wdth=640'\n",
+        "wdth",
+      ),
+      (
+        "[AVFilterGraph @ 0x6000] [error] Error applying option 'wdth' to filter 'scale': Option not found\n",
+        "wdth",
+      ),
+    ];
+    for (log, option) in fixtures {
+      let (events, _) = parse(log);
+      let found = invalid(&events);
+      assert_eq!(found.len(), 1, "{log}");
+      assert_eq!(found[0].0, option);
+    }
+
+    // Known options of newer versions get a hint, e.g. `-fps_mode` on 4.4
+    let (events, summary) = parse("[error] Unrecognized option 'fps_mode'.\n");
+    assert!(matches!(
+      events.last(),
+      Some(FfmpegEvent::Hint(hint)) if hint.starts_with("-fps_mode needs ffmpeg 5.1 or later")
+    ));
+    assert_eq!(
+      summary.invalid_option.unwrap().to_string(),
+      "invalid option -fps_mode: Unrecognized option 'fps_mode'. (-fps_mode needs ffmpeg 5.1 or later)"
+    );
+
+    let (events, _) = parse("[info] Press [q] to stop, [?] for help\n[error] Error while decoding stream #0:0: Invalid data found when processing input\n");
+    assert!(invalid(&events).is_empty());
+  }
+
+  /// Parse a complete log and group its lines as if they all arrived at once.
+  fn parse_grouped(stderr_str: &str) -> Vec<FfmpegEvent> {
+    let mut parser = FfmpegLogParser::new(Cursor::new(stderr_str.as_bytes().to_vec()));
+    let mut grouper = LogGrouper::new(LOG_GROUP_WINDOW);
+    let now = std::time::Instant::now();
+    let mut events = Vec::new();
+    loop {
+      let event = parser.parse_next_event().unwrap();
+      let done = event == FfmpegEvent::LogEOF;
+      events.extend(grouper.offer(event, now));
+      if done {
+        return events;
+      }
+    }
+  }
+
+  #[test]
+  fn test_group_x264_banner() {
+    let stderr_str = "[info] Stream mapping:
+[info]   Stream #0:0 -> #0:0 (wrapped_avframe (native) -> h264 (libx264))
+[libx264 @ 0x5581c8f0] [info] using cpu capabilities: MMX2 SSE2Fast SSSE3 SSE4.2 AVX FMA3 BMI2 AVX2
+[libx264 @ 0x5581c8f0] [info] profile High, level 1.3, 4:2:0, 8-bit
+[libx264 @ 0x5581c8f0] [info] 264 - core 164 r3095 baf4ea7 - H.264/MPEG-4 AVC codec - Copyleft 2003-2022 - http://www.videolan.org/x264.html - options: cabac=1 ref=3 deblock=1:0:0
+[libx264 @ 0x55d6f0b4] [warning] non-strictly-monotonic PTS
+[info] Output #0, mp4, to 'out.mp4':
+[info]   Metadata:
+[info]     encoder         : Lavf60.16.100
+[info] frame=   10 fps=0.0 q=-1.0 Lsize=       5KiB time=00:00:00.36 bitrate= 113.6kbits/s speed=11.2x
+";
+    let events = parse_grouped(stderr_str);
+    assert!(
+      matches!(&events[0], FfmpegEvent::Log(LogLevel::Info, line) if line.ends_with("Stream mapping:"))
+    );
+    assert!(matches!(&events[1], FfmpegEvent::ParsedStreamMapping(_)));
+    let FfmpegEvent::LogGroup(banner) = &events[2] else {
+      panic!("expected the banner, got {:?}", events[2]);
+    };
+    assert_eq!(banner.level, LogLevel::Info);
+    assert_eq!(banner.component.as_deref(), Some("libx264"));
+    assert_eq!(banner.lines.len(), 3);
+    assert!(banner.lines[2].contains("options: cabac=1"));
+    assert_eq!(banner.raw_log_message, banner.lines.join("\n"));
+    // Another instance, at another level
+    assert!(matches!(&events[3], FfmpegEvent::Log(LogLevel::Warning, _)));
+    assert!(matches!(&events[4], FfmpegEvent::ParsedOutput(_)));
+    // Indented continuation lines, without a component
+    let FfmpegEvent::LogGroup(metadata) = &events[5] else {
+      panic!("expected the metadata block, got {:?}", events[5]);
+    };
+    assert_eq!(metadata.component, None);
+    assert_eq!(metadata.lines.len(), 2);
+    assert!(matches!(&events[6], FfmpegEvent::ParsedTag(_)));
+    assert!(matches!(&events[7], FfmpegEvent::Progress(_)));
+    assert_eq!(events[8], FfmpegEvent::LogEOF);
+    assert_eq!(events.len(), 9);
+  }
+
+  #[test]
+  fn test_group_device_listing() {
+    let stderr_str = "[AVFoundation indev @ 0x7fa1c4f04280] [info] AVFoundation video devices:
+[AVFoundation indev @ 0x7fa1c4f04280] [info] [0] FaceTime HD Camera
+[AVFoundation indev @ 0x7fa1c4f04280] [info] [1] Capture screen 0
+[AVFoundation indev @ 0x7fa1c4f04280] [info] AVFoundation audio devices:
+[AVFoundation indev @ 0x7fa1c4f04280] [info] [0] MacBook Pro Microphone
+[in#0 @ 0x600002a1c000] [error] Error opening input: Input/output error
+[fatal] Error opening input file .
+";
+    let events = parse_grouped(stderr_str);
+    let FfmpegEvent::LogGroup(devices) = &events[0] else {
+      panic!("expected the device list, got {:?}", events[0]);
+    };
+    assert_eq!(devices.component.as_deref(), Some("AVFoundation indev"));
+    assert_eq!(devices.lines.len(), 5);
+    assert!(devices.lines[4].ends_with("[0] MacBook Pro Microphone"));
+    assert!(matches!(&events[1], FfmpegEvent::Log(LogLevel::Error, _)));
+    assert!(matches!(&events[2], FfmpegEvent::Log(LogLevel::Fatal, _)));
+    assert_eq!(events[3], FfmpegEvent::LogEOF);
+    assert_eq!(events.len(), 4);
+
+    // A line arriving after the window starts a new group
+    let log = |line: &str| FfmpegEvent::Log(LogLevel::Info, line.to_string());
+    let mut grouper = LogGrouper::new(LOG_GROUP_WINDOW);
+    let start = std::time::Instant::now();
+    assert!(grouper
+      .offer(
+        log("[dshow @ 0x6000] [info] \"Integrated Camera\" (video)"),
+        start
+      )
+      .is_empty());
+    assert_eq!(grouper.deadline(), Some(start + LOG_GROUP_WINDOW));
+    let late = start + LOG_GROUP_WINDOW * 2;
+    let ready = grouper.offer(log("[dshow @ 0x6000] [info] \"Microphone\" (audio)"), late);
+    assert!(
+      matches!(&ready[..], [FfmpegEvent::Log(_, line)] if line.contains("Integrated Camera"))
+    );
+    assert!(
+      matches!(grouper.flush(), Some(FfmpegEvent::Log(_, line)) if line.contains("Microphone"))
+    );
+    assert_eq!(grouper.flush(), None);
+  }
 }
@@ -1,4 +1,8 @@
-use crate::event::{AVStream, FfmpegEvent, FfmpegInput, FfmpegOutput};
+use crate::{
+  event::{AVStream, FfmpegEvent, FfmpegInput, FfmpegOutput, FfmpegTag, ParsedProgram, TagScope},
+  gapless::GaplessInfo,
+  timestamp::Timestamp,
+};
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct FfmpegMetadata {
@@ -7,6 +11,11 @@ pub struct FfmpegMetadata {
   pub output_streams: Vec<AVStream>,
   pub inputs: Vec<FfmpegInput>,
   pub input_streams: Vec<AVStream>,
+  /// The programs of the inputs which have several, like the channels of a
+  /// DVB capture. Empty for the usual files without programs.
+  pub programs: Vec<ParsedProgram>,
+  /// Tags of the inputs, the outputs and their streams, in log order.
+  pub tags: Vec<FfmpegTag>,
 
   /// Whether all metadata from the parent process has been gathered into this struct
   completed: bool,
@@ -26,6 +35,8 @@ impl FfmpegMetadata {
       output_streams: Vec::new(),
       inputs: Vec::new(),
       input_streams: Vec::new(),
+      programs: Vec::new(),
+      tags: Vec::new(),
       completed: false,
     }
   }
@@ -34,13 +45,50 @@ impl FfmpegMetadata {
     self.completed
   }
 
-  /// A shortcut to obtain the expected duration (in seconds).
+  /// A shortcut to obtain the expected duration.
   ///
   /// Usually this is the duration of the first input stream. Theoretically
   /// different streams could have different (or conflicting) durations, but
-  /// this handles the common case.
-  pub fn duration(&self) -> Option<f64> {
-    self.inputs[0].duration
+  /// this handles the common case. Inputs without a known duration, like a
+  /// single image, are skipped.
+  pub fn duration(&self) -> Option<Timestamp> {
+    self.inputs.iter().find_map(|input| input.duration)
+  }
+
+  /// The value of a tag, e.g. `tag(TagScope::Input(1), "artist")`.
+  pub fn tag(&self, scope: TagScope, key: &str) -> Option<&str> {
+    self
+      .tags
+      .iter()
+      .find(|tag| tag.scope == scope && tag.key == key)
+      .map(|tag| tag.value.as_str())
+  }
+
+  /// The encoder delay and padding of a stream of an input, from the
+  /// `iTunSMPB` tag of the stream or of the input, e.g. to check that an
+  /// [`audio_gapless`](crate::args::FfmpegArgs::audio_gapless) output
+  /// carries them through. The delay stored in an edit list or a LAME
+  /// header isn't logged by ffmpeg, so it can only be found with ffprobe.
+  ///
+  /// ```rust
+  /// use ffmpeg_sidecar::{event::{FfmpegEvent, FfmpegTag, TagScope}, metadata::FfmpegMetadata};
+  ///
+  /// let mut metadata = FfmpegMetadata::new();
+  /// let tag = FfmpegTag {
+  ///   scope: TagScope::Input(0),
+  ///   key: "iTunSMPB".to_string(),
+  ///   value: " 00000000 00000840 000001CA 00000000003F31F6".to_string(),
+  ///   raw_log_message: String::new(),
+  /// };
+  /// metadata.handle_event(&Some(FfmpegEvent::ParsedTag(tag))).unwrap();
+  /// assert_eq!(metadata.gapless_info(0, 0).unwrap().encoder_delay, 2112);
+  /// assert_eq!(metadata.gapless_info(1, 0), None);
+  /// ```
+  pub fn gapless_info(&self, input: u32, stream: u32) -> Option<GaplessInfo> {
+    self
+      .tag(TagScope::InputStream { input, stream }, "iTunSMPB")
+      .or_else(|| self.tag(TagScope::Input(input), "iTunSMPB"))
+      .and_then(GaplessInfo::from_itunsmpb)
   }
 
   pub fn handle_event(&mut self, item: &Option<FfmpegEvent>) -> anyhow::Result<()> {
@@ -55,8 +103,16 @@ impl FfmpegMetadata {
       Some(FfmpegEvent::ParsedInput(input)) => self.inputs.push(input.clone()),
       Some(FfmpegEvent::ParsedOutput(output)) => self.outputs.push(output.clone()),
       Some(FfmpegEvent::ParsedDuration(duration)) => {
-        self.inputs[duration.input_index as usize].duration = Some(duration.duration)
+        let input = self
+          .inputs
+          .iter_mut()
+          .find(|input| input.index == duration.input_index);
+        if let Some(input) = input {
+          input.duration = Some(duration.duration);
+        }
       }
+      Some(FfmpegEvent::ParsedProgram(program)) => self.programs.push(program.clone()),
+      Some(FfmpegEvent::ParsedTag(tag)) => self.tags.push(tag.clone()),
       Some(FfmpegEvent::ParsedOutputStream(stream)) => self.output_streams.push(stream.clone()),
       Some(FfmpegEvent::ParsedInputStream(stream)) => self.input_streams.push(stream.clone()),
       _ => (),
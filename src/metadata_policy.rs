@@ -0,0 +1,76 @@
+//! Keeping or stripping the tags and chapters of the input when transcoding.
+//! See [`FfmpegCommand::metadata_policy`](crate::command::FfmpegCommand::metadata_policy).
+
+use std::path::Path;
+
+/// What happens to the global metadata (like `title`, `creation_time` or GPS
+/// `location`) and chapters of the first input.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum MetadataPolicy {
+  /// Copy all tags and chapters of the first input (`-map_metadata 0
+  /// -map_chapters 0`).
+  PreserveAll,
+  /// Drop all tags and chapters (`-map_metadata -1 -map_chapters -1`).
+  Strip,
+  /// Like [`PreserveAll`](MetadataPolicy::PreserveAll), then set each key to
+  /// its value, or remove it when the value is `None`.
+  Custom(Vec<(String, Option<String>)>),
+}
+
+impl MetadataPolicy {
+  /// The output arguments for this policy, to be followed by `output`.
+  ///
+  /// The MP4 family of muxers only writes a fixed set of well-known tags
+  /// unless `-movflags use_metadata_tags` is set, so it's added for outputs
+  /// with an `.mp4`, `.m4a`, `.m4v`, `.mov` or `.3gp` extension when tags are
+  /// kept.
+  ///
+  /// ```rust
+  /// use ffmpeg_sidecar::metadata_policy::MetadataPolicy;
+  ///
+  /// assert_eq!(
+  ///   MetadataPolicy::PreserveAll.to_args("out.mp4"),
+  ///   ["-map_metadata", "0", "-map_chapters", "0", "-movflags", "+use_metadata_tags"]
+  /// );
+  /// assert_eq!(
+  ///   MetadataPolicy::Custom(vec![("title".to_string(), None)]).to_args("out.mkv"),
+  ///   ["-map_metadata", "0", "-map_chapters", "0", "-metadata", "title="]
+  /// );
+  /// ```
+  pub fn to_args<P: AsRef<Path>>(&self, output: P) -> Vec<String> {
+    let keep = |value: &str| {
+      vec![
+        "-map_metadata".to_string(),
+        value.to_string(),
+        "-map_chapters".to_string(),
+        value.to_string(),
+      ]
+    };
+    let mut args = match self {
+      MetadataPolicy::Strip => return keep("-1"),
+      MetadataPolicy::PreserveAll | MetadataPolicy::Custom(_) => keep("0"),
+    };
+    if is_mp4_family(output.as_ref()) {
+      args.push("-movflags".to_string());
+      args.push("+use_metadata_tags".to_string());
+    }
+    if let MetadataPolicy::Custom(overrides) = self {
+      for (key, value) in overrides {
+        args.push("-metadata".to_string());
+        args.push(format!("{key}={}", value.as_deref().unwrap_or_default()));
+      }
+    }
+    args
+  }
+}
+
+fn is_mp4_family(output: &Path) -> bool {
+  output
+    .extension()
+    .and_then(|extension| extension.to_str())
+    .is_some_and(|extension| {
+      ["mp4", "m4a", "m4v", "mov", "3gp"]
+        .iter()
+        .any(|known| extension.eq_ignore_ascii_case(known))
+    })
+}
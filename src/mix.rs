@@ -0,0 +1,228 @@
+//! Mixing the audio of several inputs into one stream, e.g. a voiceover on
+//! top of background music.
+
+use std::{fmt, time::Duration};
+
+/// The label of the mixed audio in the filtergraph added by
+/// [`FfmpegCommand::mix_audio`](crate::command::FfmpegCommand::mix_audio),
+/// which is mapped to the output as `-map [mixed]`.
+pub const MIX_OUTPUT_LABEL: &str = "mixed";
+
+/// The label of the audio in the filtergraph added by
+/// [`FfmpegCommand::background_audio`](crate::command::FfmpegCommand::background_audio),
+/// which is mapped to the output as `-map [background]`.
+pub const BACKGROUND_OUTPUT_LABEL: &str = "background";
+
+/// An input of [`FfmpegCommand::mix_audio`](crate::command::FfmpegCommand::mix_audio).
+#[derive(Debug, Clone, PartialEq)]
+pub struct AudioMixInput {
+  pub path: String,
+  /// Gain applied before mixing, e.g. `-12.0` to keep music under a voice.
+  pub volume_db: f64,
+  /// Silence inserted before the input starts.
+  pub offset: Duration,
+}
+
+impl AudioMixInput {
+  pub fn new<S: AsRef<str>>(path: S) -> Self {
+    Self {
+      path: path.as_ref().to_string(),
+      volume_db: 0.0,
+      offset: Duration::ZERO,
+    }
+  }
+}
+
+/// When the mixed stream ends, corresponding to the `duration` option of
+/// `amix`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum MixDuration {
+  /// With the input which ends last.
+  #[default]
+  Longest,
+  /// With the input which ends first.
+  Shortest,
+  /// With the first input.
+  First,
+}
+
+impl MixDuration {
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      MixDuration::Longest => "longest",
+      MixDuration::Shortest => "shortest",
+      MixDuration::First => "first",
+    }
+  }
+}
+
+/// Options for [`FfmpegCommand::mix_audio`](crate::command::FfmpegCommand::mix_audio).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MixOptions {
+  /// Scale each input by the number of active inputs, as `amix` does by
+  /// default, so the mix can't clip. Turn this off to get the `volume_db`
+  /// levels as they are.
+  pub normalize: bool,
+  pub duration_policy: MixDuration,
+  /// All inputs are resampled to this rate before mixing, since `amix`
+  /// needs a single sample rate.
+  pub sample_rate: u32,
+}
+
+impl Default for MixOptions {
+  fn default() -> Self {
+    Self {
+      normalize: true,
+      duration_policy: MixDuration::Longest,
+      sample_rate: 48000,
+    }
+  }
+}
+
+/// Returned (through `anyhow::Error`) by
+/// [`FfmpegCommand::mix_audio`](crate::command::FfmpegCommand::mix_audio)
+/// when there's nothing to mix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TooFewMixInputs(pub usize);
+
+impl fmt::Display for TooFewMixInputs {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "mixing audio needs at least 2 inputs, got {}", self.0)
+  }
+}
+
+impl std::error::Error for TooFewMixInputs {}
+
+/// The filtergraph which mixes `inputs`, whose audio is read from the inputs
+/// numbered from `first_input_index` on, labeling the result `output`.
+///
+/// Offsets are applied with `adelay` rather than `-itsoffset`: `amix` starts
+/// mixing each input at its first sample whatever its timestamp, so only
+/// actual silence keeps an input from starting right away.
+///
+/// ```rust
+/// use ffmpeg_sidecar::mix::{mix_filter, AudioMixInput, MixOptions};
+/// use std::time::Duration;
+///
+/// let voice = AudioMixInput {
+///   offset: Duration::from_millis(1500),
+///   ..AudioMixInput::new("voice.wav")
+/// };
+/// let music = AudioMixInput {
+///   volume_db: -12.0,
+///   ..AudioMixInput::new("music.mp3")
+/// };
+/// assert_eq!(
+///   mix_filter(1, &[voice, music], "out", &MixOptions::default()),
+///   "[1:a]aresample=48000,adelay=delays=1500:all=1[mix0];\
+///    [2:a]aresample=48000,volume=-12dB[mix1];\
+///    [mix0][mix1]amix=inputs=2:duration=longest[out]"
+/// );
+/// ```
+pub fn mix_filter(
+  first_input_index: usize,
+  inputs: &[AudioMixInput],
+  output: &str,
+  options: &MixOptions,
+) -> String {
+  let mut graph = String::new();
+  let mut labels = String::new();
+  for (i, input) in inputs.iter().enumerate() {
+    let mut chain = vec![format!("aresample={}", options.sample_rate)];
+    if !input.offset.is_zero() {
+      chain.push(format!("adelay=delays={}:all=1", input.offset.as_millis()));
+    }
+    if input.volume_db != 0.0 {
+      chain.push(format!("volume={}dB", input.volume_db));
+    }
+    graph.push_str(&format!(
+      "[{}:a]{}[mix{i}];",
+      first_input_index + i,
+      chain.join(",")
+    ));
+    labels.push_str(&format!("[mix{i}]"));
+  }
+
+  graph.push_str(&format!(
+    "{labels}amix=inputs={}:duration={}",
+    inputs.len(),
+    options.duration_policy.as_str()
+  ));
+  // Only set when disabled, since older versions of ffmpeg don't have the
+  // option but always normalize
+  if !options.normalize {
+    graph.push_str(":normalize=0");
+  }
+  graph.push_str(&format!("[{output}]"));
+  graph
+}
+
+/// Options for [`FfmpegCommand::background_audio`](crate::command::FfmpegCommand::background_audio).
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct BackgroundAudioOptions {
+  /// Gain applied to the background audio, e.g. `-18.0` to keep music under
+  /// the sound of the video.
+  pub audio_volume_db: f64,
+  /// Fade the background audio out over the end of the video.
+  pub fade_out: Option<Duration>,
+}
+
+/// The filtergraph which cuts the looped audio of input
+/// `video_input_index + 1` to `video_duration`, fading it out, and mixes it
+/// with the audio of the video if it has any, labeling the result `output`.
+///
+/// The audio is cut with `atrim` rather than left to `-shortest`, which
+/// lets audio run on past the end of the video by up to
+/// `-shortest_buf_duration` on ffmpeg 6 and later. It also places the fade
+/// at the end of the output rather than of the looped input.
+///
+/// ```rust
+/// use ffmpeg_sidecar::mix::{background_audio_filter, BackgroundAudioOptions};
+/// use std::time::Duration;
+///
+/// let options = BackgroundAudioOptions {
+///   audio_volume_db: -18.0,
+///   fade_out: Some(Duration::from_secs(3)),
+/// };
+/// assert_eq!(
+///   background_audio_filter(0, false, Duration::from_secs(180), &options, "out"),
+///   "[1:a]volume=-18dB,atrim=duration=180,afade=t=out:st=177:d=3[out]"
+/// );
+/// assert_eq!(
+///   background_audio_filter(0, true, Duration::from_secs(180), &options, "out"),
+///   "[1:a]volume=-18dB,atrim=duration=180,afade=t=out:st=177:d=3,aresample=48000[music];\
+///    [0:a]aresample=48000[sound];\
+///    [sound][music]amix=inputs=2:duration=longest:normalize=0[out]"
+/// );
+/// ```
+pub fn background_audio_filter(
+  video_input_index: usize,
+  video_has_audio: bool,
+  video_duration: Duration,
+  options: &BackgroundAudioOptions,
+  output: &str,
+) -> String {
+  let duration = video_duration.as_secs_f64();
+  let mut chain = Vec::new();
+  if options.audio_volume_db != 0.0 {
+    chain.push(format!("volume={}dB", options.audio_volume_db));
+  }
+  chain.push(format!("atrim=duration={duration}"));
+  if let Some(fade) = options.fade_out.filter(|fade| !fade.is_zero()) {
+    let fade = fade.as_secs_f64().min(duration);
+    chain.push(format!("afade=t=out:st={}:d={fade}", duration - fade));
+  }
+  let music = format!("[{}:a]{}", video_input_index + 1, chain.join(","));
+  if !video_has_audio {
+    return format!("{music}[{output}]");
+  }
+
+  // amix needs a single sample rate, and would scale down the sound of the
+  // video by default
+  let sample_rate = MixOptions::default().sample_rate;
+  format!(
+    "{music},aresample={sample_rate}[music];\
+     [{video_input_index}:a]aresample={sample_rate}[sound];\
+     [sound][music]amix=inputs=2:duration=longest:normalize=0[{output}]"
+  )
+}
@@ -0,0 +1,264 @@
+//! Options for reading inputs over the network, like HTTP(S) and HLS
+//! streams, RTSP cameras and RTMP servers. See
+//! [`FfmpegCommand::network_input`](crate::command::FfmpegCommand::network_input).
+//!
+//! Each protocol has its own set of options, and ffmpeg fails with `Option
+//! not found` when given an option which the protocol of the input doesn't
+//! use, so only the applicable ones are passed.
+
+use std::{fmt, time::Duration};
+
+/// The protocol of an input, by the scheme of its URL.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum NetworkProtocol {
+  /// `http` and `https`, including HLS playlists and DASH manifests.
+  Http,
+  /// `rtsp` and `rtsps`.
+  Rtsp,
+  /// `rtmp` and its variants, like `rtmps`.
+  Rtmp,
+  /// Other protocols over a socket, like `tcp`, `udp` or `srt`, by scheme.
+  Socket(String),
+  /// A local file, a device, or a pipe.
+  Local,
+}
+
+impl NetworkProtocol {
+  /// ```rust
+  /// use ffmpeg_sidecar::network::NetworkProtocol;
+  ///
+  /// assert_eq!(NetworkProtocol::from_url("https://cdn.example.com/live.m3u8"), NetworkProtocol::Http);
+  /// assert_eq!(NetworkProtocol::from_url("RTSP://10.0.0.5:554/stream1"), NetworkProtocol::Rtsp);
+  /// assert_eq!(NetworkProtocol::from_url("srt://ingest:9000"), NetworkProtocol::Socket("srt".to_string()));
+  /// assert_eq!(NetworkProtocol::from_url(r"C:\videos\in.mp4"), NetworkProtocol::Local);
+  /// assert_eq!(NetworkProtocol::from_url("pipe:0"), NetworkProtocol::Local);
+  /// ```
+  pub fn from_url(url: &str) -> Self {
+    let Some((scheme, rest)) = url.split_once("://") else {
+      return NetworkProtocol::Local;
+    };
+    if rest.is_empty()
+      || !scheme
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || c == '+')
+    {
+      return NetworkProtocol::Local;
+    }
+    match scheme.to_ascii_lowercase().as_str() {
+      "http" | "https" => NetworkProtocol::Http,
+      "rtsp" | "rtsps" => NetworkProtocol::Rtsp,
+      "rtmp" | "rtmps" | "rtmpt" | "rtmpe" | "rtmpte" | "rtmpts" => NetworkProtocol::Rtmp,
+      "file" => NetworkProtocol::Local,
+      scheme => NetworkProtocol::Socket(scheme.to_string()),
+    }
+  }
+}
+
+impl fmt::Display for NetworkProtocol {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      NetworkProtocol::Http => f.write_str("HTTP"),
+      NetworkProtocol::Rtsp => f.write_str("RTSP"),
+      NetworkProtocol::Rtmp => f.write_str("RTMP"),
+      NetworkProtocol::Socket(scheme) => write!(f, "{scheme}"),
+      NetworkProtocol::Local => f.write_str("local input"),
+    }
+  }
+}
+
+/// The lower transport of an RTSP stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RtspTransport {
+  /// Interleaved in the RTSP connection, which gets through firewalls and
+  /// NAT, and doesn't lose packets.
+  Tcp,
+  /// ffmpeg's default, with lower latency, but packets can be lost or
+  /// blocked.
+  Udp,
+  UdpMulticast,
+  /// Tunneled through HTTP.
+  Http,
+}
+
+impl RtspTransport {
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      RtspTransport::Tcp => "tcp",
+      RtspTransport::Udp => "udp",
+      RtspTransport::UdpMulticast => "udp_multicast",
+      RtspTransport::Http => "http",
+    }
+  }
+}
+
+/// Options for reading an input over the network, turned into the flags of
+/// its protocol by [`InputNetworkOptions::to_args`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InputNetworkOptions {
+  /// Reconnect when an HTTP connection drops, including in the middle of
+  /// a live stream (`-reconnect 1 -reconnect_streamed 1
+  /// -reconnect_on_network_error 1`). HTTP only.
+  pub reconnect: bool,
+  /// The longest delay between reconnection attempts, which double each
+  /// time, after which ffmpeg gives up. HTTP only.
+  pub reconnect_delay_max: Option<Duration>,
+  /// How long a read or write can block before failing, e.g. when a
+  /// camera stops sending data. `-rw_timeout`, or `-timeout` for RTSP.
+  pub rw_timeout: Option<Duration>,
+  /// RTSP only.
+  pub rtsp_transport: Option<RtspTransport>,
+  /// The `User-Agent` header. HTTP and RTSP only.
+  pub user_agent: Option<String>,
+  /// Extra request headers, as names and values. HTTP only.
+  pub headers: Vec<(String, String)>,
+  /// Pass the RTSP timeout as `-stimeout`, for ffmpeg 4 and older, which
+  /// use `-timeout` for something else.
+  pub legacy_rtsp_timeout: bool,
+}
+
+impl InputNetworkOptions {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// The input options for reading `url`, leaving out the ones which don't
+  /// apply to its protocol (see [`InputNetworkOptions::warnings`]).
+  ///
+  /// ```rust
+  /// use ffmpeg_sidecar::network::{InputNetworkOptions, RtspTransport};
+  /// use std::time::Duration;
+  ///
+  /// let options = InputNetworkOptions {
+  ///   reconnect: true,
+  ///   rw_timeout: Some(Duration::from_secs(5)),
+  ///   rtsp_transport: Some(RtspTransport::Tcp),
+  ///   ..Default::default()
+  /// };
+  /// assert_eq!(
+  ///   options.to_args("rtsp://10.0.0.5/stream1"),
+  ///   ["-rtsp_transport", "tcp", "-timeout", "5000000"]
+  /// );
+  /// ```
+  pub fn to_args(&self, url: &str) -> Vec<String> {
+    let protocol = NetworkProtocol::from_url(url);
+    let mut args = Vec::new();
+    let mut push = |option: &str, value: String| {
+      args.push(option.to_string());
+      args.push(value);
+    };
+    let micros = |duration: Duration| duration.as_micros().to_string();
+
+    match protocol {
+      NetworkProtocol::Http => {
+        if self.reconnect {
+          push("-reconnect", "1".to_string());
+          push("-reconnect_streamed", "1".to_string());
+          push("-reconnect_on_network_error", "1".to_string());
+        }
+        if let Some(delay) = self.reconnect_delay_max {
+          // Whole seconds only
+          push("-reconnect_delay_max", delay.as_secs().max(1).to_string());
+        }
+        if let Some(timeout) = self.rw_timeout {
+          push("-rw_timeout", micros(timeout));
+        }
+        if let Some(user_agent) = &self.user_agent {
+          push("-user_agent", user_agent.clone());
+        }
+        if !self.headers.is_empty() {
+          push("-headers", headers_value(&self.headers));
+        }
+      }
+      NetworkProtocol::Rtsp => {
+        if let Some(transport) = self.rtsp_transport {
+          push("-rtsp_transport", transport.as_str().to_string());
+        }
+        if let Some(timeout) = self.rw_timeout {
+          let option = match self.legacy_rtsp_timeout {
+            true => "-stimeout",
+            false => "-timeout",
+          };
+          push(option, micros(timeout));
+        }
+        if let Some(user_agent) = &self.user_agent {
+          push("-user_agent", user_agent.clone());
+        }
+      }
+      NetworkProtocol::Rtmp | NetworkProtocol::Socket(_) => {
+        if let Some(timeout) = self.rw_timeout {
+          push("-rw_timeout", micros(timeout));
+        }
+      }
+      NetworkProtocol::Local => {}
+    }
+    args
+  }
+
+  /// A warning for each option which is set but doesn't apply to the
+  /// protocol of `url`, so [`InputNetworkOptions::to_args`] leaves it out.
+  ///
+  /// ```rust
+  /// use ffmpeg_sidecar::network::{InputNetworkOptions, RtspTransport};
+  ///
+  /// let options = InputNetworkOptions {
+  ///   reconnect: true,
+  ///   rtsp_transport: Some(RtspTransport::Tcp),
+  ///   ..Default::default()
+  /// };
+  /// assert_eq!(
+  ///   options.warnings("https://example.com/live.m3u8"),
+  ///   ["`rtsp_transport` doesn't apply to HTTP input `https://example.com/live.m3u8`, so it's ignored"]
+  /// );
+  /// ```
+  pub fn warnings(&self, url: &str) -> Vec<String> {
+    let protocol = NetworkProtocol::from_url(url);
+    let (http, rtsp) = (
+      protocol == NetworkProtocol::Http,
+      protocol == NetworkProtocol::Rtsp,
+    );
+    let set = [
+      ("reconnect", self.reconnect, http),
+      (
+        "reconnect_delay_max",
+        self.reconnect_delay_max.is_some(),
+        http,
+      ),
+      (
+        "rw_timeout",
+        self.rw_timeout.is_some(),
+        protocol != NetworkProtocol::Local,
+      ),
+      ("rtsp_transport", self.rtsp_transport.is_some(), rtsp),
+      ("user_agent", self.user_agent.is_some(), http || rtsp),
+      ("headers", !self.headers.is_empty(), http),
+    ];
+    set
+      .iter()
+      .filter(|(_, is_set, applies)| *is_set && !applies)
+      .map(|(name, _, _)| {
+        format!("`{name}` doesn't apply to {protocol} input `{url}`, so it's ignored")
+      })
+      .collect()
+  }
+}
+
+/// The value of `-headers`: each header on its own line, ended with CRLF as
+/// HTTP requires. Line breaks within names and values are removed, so they
+/// can't add headers of their own.
+///
+/// ```rust
+/// use ffmpeg_sidecar::network::headers_value;
+///
+/// let headers = [
+///   ("Authorization".to_string(), "Bearer abc".to_string()),
+///   ("X-Id".to_string(), "7\r\nX-Evil: 1".to_string()),
+/// ];
+/// assert_eq!(headers_value(&headers), "Authorization: Bearer abc\r\nX-Id: 7X-Evil: 1\r\n");
+/// ```
+pub fn headers_value(headers: &[(String, String)]) -> String {
+  let strip = |s: &str| s.replace(['\r', '\n'], "");
+  headers
+    .iter()
+    .map(|(name, value)| format!("{}: {}\r\n", strip(name), strip(value)))
+    .collect()
+}
@@ -0,0 +1,302 @@
+//! Finding the ffmpeg processes left running by a process which died
+//! without cleaning them up, e.g. after a panic, and killing them.
+//!
+//! Every process spawned by [`FfmpegCommand::spawn`](crate::command::FfmpegCommand::spawn)
+//! and `FfplayCommand::spawn`, with the `ffplay` feature, has the
+//! [`OWNER_ENV`] environment variable, naming the process which spawned it.
+//! Processes without it, like ffmpeg started by hand or by
+//! [`detach`](crate::command::FfmpegCommand::detach), are never listed or
+//! killed.
+//!
+//! Processes are listed from `/proc` on Linux, and with `ps` on macOS. Other
+//! platforms, including Windows, where reading the environment of another
+//! process needs to read its memory, return an `Unsupported` error.
+//!
+//! ```rust,no_run
+//! use ffmpeg_sidecar::orphans::kill_orphans;
+//! use std::time::Duration;
+//!
+//! for process in kill_orphans(Duration::from_secs(60)).unwrap() {
+//!   println!("killed {} `{}`", process.pid, process.command_line);
+//! }
+//! ```
+
+use std::{
+  collections::hash_map::RandomState,
+  hash::{BuildHasher, Hasher},
+  io,
+  sync::OnceLock,
+  time::{Duration, SystemTime},
+};
+
+use crate::timeout::kill_process;
+
+/// The environment variable marking the processes spawned by this crate, set
+/// to `<pid>:<token>`: the pid of the process which spawned it, and a random
+/// token of that process.
+pub const OWNER_ENV: &str = "FFMPEG_SIDECAR_OWNER";
+
+/// A process spawned by this crate, from this or another process.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct OwnedProcess {
+  pub pid: u32,
+  /// The process which spawned it.
+  pub owner_pid: u32,
+  pub started: SystemTime,
+  /// The program and its arguments, separated by spaces. With `ps`, the
+  /// environment follows.
+  pub command_line: String,
+  /// Whether the owner exited. A pid reused by a process started after this
+  /// one doesn't count as the owner.
+  pub orphaned: bool,
+}
+
+impl OwnedProcess {
+  /// Whether it was spawned by the current process.
+  pub fn is_own(&self) -> bool {
+    self.owner_pid == std::process::id()
+  }
+}
+
+/// The value of [`OWNER_ENV`] for the processes spawned by this process.
+pub(crate) fn owner_marker() -> &'static str {
+  static MARKER: OnceLock<String> = OnceLock::new();
+  MARKER.get_or_init(|| {
+    let token = (0..2)
+      .map(|_| {
+        let mut hasher = RandomState::new().build_hasher();
+        hasher.write_u32(std::process::id());
+        hasher.write_u128(
+          SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos(),
+        );
+        format!("{:016x}", hasher.finish())
+      })
+      .collect::<String>();
+    format!("{}:{token}", std::process::id())
+  })
+}
+
+/// The running processes spawned by this crate, from any process of the
+/// current user (and of all users, with the permissions to read their
+/// environment).
+pub fn list_owned_processes() -> io::Result<Vec<OwnedProcess>> {
+  #[cfg(target_os = "linux")]
+  return linux::list_owned_processes();
+
+  #[cfg(target_os = "macos")]
+  return ps::list_owned_processes();
+
+  #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+  Err(io::ErrorKind::Unsupported.into())
+}
+
+/// Kill the processes spawned by this crate whose owner exited, and which
+/// have been running for at least `older_than`, returning them.
+///
+/// The processes which exit before being killed are still returned.
+pub fn kill_orphans(older_than: Duration) -> io::Result<Vec<OwnedProcess>> {
+  let now = SystemTime::now();
+  let orphans = list_owned_processes()?
+    .into_iter()
+    .filter(|process| process.orphaned)
+    .filter(|process| {
+      now
+        .duration_since(process.started)
+        .is_ok_and(|age| age >= older_than)
+    })
+    .collect::<Vec<_>>();
+  for orphan in &orphans {
+    kill_process(orphan.pid, false);
+  }
+  Ok(orphans)
+}
+
+/// The owner pid of a value of [`OWNER_ENV`].
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn parse_marker(marker: &str) -> Option<u32> {
+  let (pid, token) = marker.split_once(':')?;
+  match token.is_empty() {
+    true => None,
+    false => pid.parse().ok(),
+  }
+}
+
+/// Whether `owner_pid` was still running when `started`, the start time of
+/// the process it owns, given its start time if it's running.
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn is_orphaned(owner_started: Option<SystemTime>, started: SystemTime) -> bool {
+  owner_started.is_none_or(|owner_started| owner_started > started)
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+  use std::{
+    fs, io,
+    process::Command,
+    sync::OnceLock,
+    time::{Duration, SystemTime},
+  };
+
+  use super::{is_orphaned, parse_marker, OwnedProcess, OWNER_ENV};
+
+  pub(super) fn list_owned_processes() -> io::Result<Vec<OwnedProcess>> {
+    let mut processes = Vec::new();
+    for entry in fs::read_dir("/proc")? {
+      let Some(pid) = entry?
+        .file_name()
+        .to_str()
+        .and_then(|name| name.parse().ok())
+      else {
+        continue;
+      };
+      // Processes of other users can't be read, and any may have exited
+      let Ok(environ) = fs::read(format!("/proc/{pid}/environ")) else {
+        continue;
+      };
+      let owner_pid = environ
+        .split(|byte| *byte == 0)
+        .filter_map(|variable| std::str::from_utf8(variable).ok())
+        .find_map(|variable| variable.strip_prefix(OWNER_ENV)?.strip_prefix('='))
+        .and_then(parse_marker);
+      let (Some(owner_pid), Some(started)) = (owner_pid, started(pid)) else {
+        continue;
+      };
+      let command_line = fs::read(format!("/proc/{pid}/cmdline")).unwrap_or_default();
+      let command_line = command_line
+        .split(|byte| *byte == 0)
+        .filter(|arg| !arg.is_empty())
+        .map(String::from_utf8_lossy)
+        .collect::<Vec<_>>()
+        .join(" ");
+      processes.push(OwnedProcess {
+        pid,
+        owner_pid,
+        started,
+        command_line,
+        orphaned: is_orphaned(self::started(owner_pid), started),
+      });
+    }
+    Ok(processes)
+  }
+
+  /// The start time of a running process, from the clock ticks since boot in
+  /// its `stat`.
+  fn started(pid: u32) -> Option<SystemTime> {
+    let stat = fs::read_to_string(format!("/proc/{pid}/stat")).ok()?;
+    // After the name, which can contain spaces and parentheses, the start
+    // time is the 20th field
+    let ticks = stat
+      .rsplit_once(')')?
+      .1
+      .split_whitespace()
+      .nth(19)?
+      .parse::<u64>()
+      .ok()?;
+    let since_boot = Duration::from_secs_f64(ticks as f64 / clock_ticks() as f64);
+    Some(boot_time()? + since_boot)
+  }
+
+  fn boot_time() -> Option<SystemTime> {
+    static BOOT_TIME: OnceLock<Option<SystemTime>> = OnceLock::new();
+    *BOOT_TIME.get_or_init(|| {
+      let stat = fs::read_to_string("/proc/stat").ok()?;
+      let seconds = stat
+        .lines()
+        .find_map(|line| line.strip_prefix("btime "))?
+        .trim()
+        .parse()
+        .ok()?;
+      Some(SystemTime::UNIX_EPOCH + Duration::from_secs(seconds))
+    })
+  }
+
+  /// Clock ticks per second, nearly always 100.
+  fn clock_ticks() -> u64 {
+    static CLOCK_TICKS: OnceLock<u64> = OnceLock::new();
+    *CLOCK_TICKS.get_or_init(|| {
+      Command::new("getconf")
+        .arg("CLK_TCK")
+        .output()
+        .ok()
+        .and_then(|output| String::from_utf8_lossy(&output.stdout).trim().parse().ok())
+        .filter(|ticks| *ticks > 0)
+        .unwrap_or(100)
+    })
+  }
+}
+
+#[cfg(target_os = "macos")]
+mod ps {
+  use std::{
+    io,
+    process::{Command, Stdio},
+    time::{Duration, SystemTime},
+  };
+
+  use super::{is_orphaned, parse_marker, OwnedProcess, OWNER_ENV};
+
+  pub(super) fn list_owned_processes() -> io::Result<Vec<OwnedProcess>> {
+    let now = SystemTime::now();
+    let mut processes = Vec::new();
+    // `-E` appends the environment of the processes of the current user
+    for line in ps(&["-A", "-ww", "-E", "-o", "pid=,etime=,command="])?.lines() {
+      let mut fields = line.split_whitespace();
+      let (Some(pid), Some(elapsed)) = (fields.next(), fields.next()) else {
+        continue;
+      };
+      let owner_pid = fields
+        .find_map(|field| field.strip_prefix(OWNER_ENV)?.strip_prefix('='))
+        .and_then(parse_marker);
+      let (Ok(pid), Some(elapsed), Some(owner_pid)) =
+        (pid.parse(), parse_elapsed(elapsed), owner_pid)
+      else {
+        continue;
+      };
+      let started = now - elapsed;
+      let command_line = line
+        .trim_start()
+        .splitn(3, char::is_whitespace)
+        .nth(2)
+        .unwrap_or_default()
+        .trim_start()
+        .to_string();
+      processes.push(OwnedProcess {
+        pid,
+        owner_pid,
+        started,
+        command_line,
+        orphaned: is_orphaned(owner_started(owner_pid, now), started),
+      });
+    }
+    Ok(processes)
+  }
+
+  fn owner_started(pid: u32, now: SystemTime) -> Option<SystemTime> {
+    let elapsed = ps(&["-p", &pid.to_string(), "-o", "etime="]).ok()?;
+    Some(now - parse_elapsed(elapsed.trim())?)
+  }
+
+  fn ps(args: &[&str]) -> io::Result<String> {
+    let output = Command::new("ps")
+      .args(args)
+      .stdin(Stdio::null())
+      .stderr(Stdio::null())
+      .output()?;
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+  }
+
+  /// `[[dd-]hh:]mm:ss`
+  fn parse_elapsed(elapsed: &str) -> Option<Duration> {
+    let (days, clock) = match elapsed.split_once('-') {
+      Some((days, clock)) => (days.parse::<u64>().ok()?, clock),
+      None => (0, elapsed),
+    };
+    let seconds = clock.split(':').try_fold(0, |total, part| {
+      Some(total * 60 + part.parse::<u64>().ok()?)
+    })?;
+    Some(Duration::from_secs(days * 86_400 + seconds))
+  }
+}
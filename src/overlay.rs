@@ -0,0 +1,159 @@
+//! Overlaying an image on top of the video, e.g. a watermark or logo.
+
+use std::time::Duration;
+
+use crate::sanitize::escape_filter_value;
+
+/// The label of the overlaid video in the filtergraph added by
+/// [`FfmpegCommand::overlay_image`](crate::command::FfmpegCommand::overlay_image),
+/// which is mapped to the output as `-map [overlaid]`.
+pub const OVERLAY_OUTPUT_LABEL: &str = "overlaid";
+
+/// A corner of the video.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Corner {
+  TopLeft,
+  TopRight,
+  BottomLeft,
+  BottomRight,
+}
+
+/// Where to place the overlay on the video.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum OverlayPosition {
+  /// In a corner, `margin` pixels away from both edges.
+  Corner(Corner),
+  /// Centered on the video.
+  Center,
+  /// Expressions for the `x` and `y` options of the `overlay` filter, which
+  /// can use e.g. `main_w`, `main_h`, `overlay_w`, `overlay_h` and `t`.
+  Custom(String, String),
+}
+
+/// Options for [`FfmpegCommand::overlay_image`](crate::command::FfmpegCommand::overlay_image).
+#[derive(Debug, Clone, PartialEq)]
+pub struct OverlayOptions {
+  pub position: OverlayPosition,
+  /// Distance in pixels from the edges, for corner positions.
+  pub margin: u32,
+  /// From `0.0` (invisible) to `1.0` (the image's own alpha).
+  pub opacity: f32,
+  /// Scale the image to this fraction of the video width, keeping its
+  /// aspect ratio. By default, the image is used at its own size.
+  pub scale_to_fraction_of_width: Option<f32>,
+  /// Only show the overlay from this time on.
+  pub start: Option<Duration>,
+  /// Only show the overlay until this time.
+  pub end: Option<Duration>,
+  /// Loop the image input forever (`-stream_loop -1`), for animated
+  /// overlays like a GIF or a WebM with alpha. The output still ends with
+  /// the main video.
+  pub loop_input: bool,
+}
+
+impl Default for OverlayOptions {
+  fn default() -> Self {
+    Self {
+      position: OverlayPosition::Corner(Corner::BottomRight),
+      margin: 16,
+      opacity: 1.0,
+      scale_to_fraction_of_width: None,
+      start: None,
+      end: None,
+      loop_input: false,
+    }
+  }
+}
+
+/// The filtergraph which overlays the stream labeled `overlay` on top of the
+/// `main` stream, labeling the result `output`.
+///
+/// The image is converted to `rgba` so opacity applies to images without an
+/// alpha channel too, and scaled relative to the main video with
+/// `scale2ref`.
+///
+/// ```rust
+/// use ffmpeg_sidecar::overlay::{overlay_filter, Corner, OverlayOptions, OverlayPosition};
+/// use std::time::Duration;
+///
+/// let options = OverlayOptions {
+///   position: OverlayPosition::Corner(Corner::TopRight),
+///   margin: 10,
+///   opacity: 0.5,
+///   start: Some(Duration::from_secs(2)),
+///   end: Some(Duration::from_millis(5500)),
+///   ..Default::default()
+/// };
+/// assert_eq!(
+///   overlay_filter("0:v", "1:v", "out", &options),
+///   "[1:v]format=rgba,colorchannelmixer=aa=0.5[ov1];\
+///    [0:v][ov1]overlay=x=main_w-overlay_w-10:y=10:enable='between(t,2,5.5)'[out]"
+/// );
+/// ```
+pub fn overlay_filter(main: &str, overlay: &str, output: &str, options: &OverlayOptions) -> String {
+  // Labels only need to be unique within the graph, which the overlay's input
+  // index ensures
+  let id = overlay.trim_end_matches(":v").replace(':', "_");
+  let mut graph = String::new();
+  let mut main = main.to_string();
+  let mut overlay = overlay.to_string();
+
+  if let Some(fraction) = options.scale_to_fraction_of_width {
+    let (scaled, reference) = (format!("scaled{id}"), format!("ref{id}"));
+    graph.push_str(&format!(
+      "[{overlay}][{main}]scale2ref=w=main_w*{fraction}:h=-1[{scaled}][{reference}];"
+    ));
+    (main, overlay) = (reference, scaled);
+  }
+
+  let mut chain = vec!["format=rgba".to_string()];
+  if options.opacity < 1.0 {
+    chain.push(format!("colorchannelmixer=aa={}", options.opacity.max(0.0)));
+  }
+  graph.push_str(&format!("[{overlay}]{}[ov{id}];", chain.join(",")));
+
+  let margin = options.margin;
+  let (x, y) = match &options.position {
+    OverlayPosition::Corner(corner) => {
+      let left = margin.to_string();
+      let right = format!("main_w-overlay_w-{margin}");
+      let top = margin.to_string();
+      let bottom = format!("main_h-overlay_h-{margin}");
+      match corner {
+        Corner::TopLeft => (left, top),
+        Corner::TopRight => (right, top),
+        Corner::BottomLeft => (left, bottom),
+        Corner::BottomRight => (right, bottom),
+      }
+    }
+    OverlayPosition::Center => (
+      "(main_w-overlay_w)/2".to_string(),
+      "(main_h-overlay_h)/2".to_string(),
+    ),
+    OverlayPosition::Custom(x, y) => (escape_filter_value(x), escape_filter_value(y)),
+  };
+  let mut overlay_options = format!("x={x}:y={y}");
+
+  let enable = match (options.start, options.end) {
+    (Some(start), Some(end)) => Some(format!(
+      "between(t,{},{})",
+      start.as_secs_f64(),
+      end.as_secs_f64()
+    )),
+    (Some(start), None) => Some(format!("gte(t,{})", start.as_secs_f64())),
+    (None, Some(end)) => Some(format!("lte(t,{})", end.as_secs_f64())),
+    (None, None) => None,
+  };
+  if let Some(enable) = enable {
+    overlay_options.push_str(&format!(":enable='{enable}'"));
+  }
+  // A looped input never ends, so end with the main video instead
+  if options.loop_input {
+    overlay_options.push_str(":shortest=1");
+  }
+
+  graph.push_str(&format!(
+    "[{main}][ov{id}]overlay={overlay_options}[{output}]"
+  ));
+  graph
+}
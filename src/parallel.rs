@@ -0,0 +1,388 @@
+//! Encoding the video of a file on several ffmpeg processes at once, for
+//! encoders like libx264 and libx265 which don't use all the cores of a
+//! large machine on their own.
+//!
+//! The video is split at keyframes without re-encoding, the chunks are
+//! encoded concurrently, then joined with the original audio, which is
+//! copied as is.
+//!
+//! ```rust,no_run
+//! use ffmpeg_sidecar::{
+//!   command::FfmpegCommand,
+//!   parallel::{parallel_encode_with_progress, ChunkSplit, ParallelOptions},
+//! };
+//! use std::path::Path;
+//!
+//! fn encode_chunk(input: &Path, output: &Path) -> FfmpegCommand {
+//!   let mut command = FfmpegCommand::new();
+//!   command
+//!     .input(input.to_string_lossy())
+//!     .codec_video("libx265")
+//!     .args(["-crf", "24", "-preset", "slow"])
+//!     .overwrite()
+//!     .output(output.to_string_lossy());
+//!   command
+//! }
+//!
+//! let options = ParallelOptions {
+//!   split: ChunkSplit::Count(8),
+//!   per_chunk_command: encode_chunk,
+//!   max_concurrent: 8,
+//!   ..Default::default()
+//! };
+//! parallel_encode_with_progress("master.mov", "encoded.mkv", &options, |progress| {
+//!   println!("{:?}: {:.0}%", progress.stage, progress.fraction * 100.0)
+//! })
+//! .unwrap();
+//! ```
+
+use std::{
+  fmt, fs,
+  path::{Path, PathBuf},
+  sync::Mutex,
+  time::Duration,
+};
+
+use crate::{
+  command::FfmpegCommand,
+  cut::concat_list,
+  event::FfmpegEvent,
+  ffprobe::ffprobe_keyframes,
+  integrity::check_output_duration,
+  probe::probe_cached,
+  queue::{run_job, JobOutcome, JobQueue},
+  temp::TempRegistry,
+};
+
+/// Subtracted from the keyframe timestamps given to the segment muxer, which
+/// splits at the first keyframe at or after each time, since the timestamps
+/// printed by ffprobe are rounded.
+const SPLIT_OFFSET: f64 = 0.0005;
+
+/// Shares of the whole job given to each stage in [`ParallelProgress`].
+const SPLIT_WEIGHT: f64 = 0.05;
+const JOIN_WEIGHT: f64 = 0.05;
+
+/// How to split the input into chunks. The chunks start at keyframes, so
+/// their durations only approximate the requested ones, and inputs with few
+/// keyframes may be split into fewer chunks.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChunkSplit {
+  /// This many chunks of about the same duration.
+  Count(usize),
+  /// Chunks of about this duration.
+  Duration(Duration),
+}
+
+/// Options for [`parallel_encode`].
+#[derive(Debug, Clone)]
+pub struct ParallelOptions {
+  pub split: ChunkSplit,
+  /// Build the command encoding the video chunk at the first path into the
+  /// second, a Matroska file. Every chunk must be encoded with the same
+  /// settings, so they can be joined. By default, libx264 with its default
+  /// settings.
+  pub per_chunk_command: fn(&Path, &Path) -> FfmpegCommand,
+  /// How many chunks are encoded at once.
+  pub max_concurrent: usize,
+  /// How much shorter than the input the output can be, in seconds, before
+  /// it's considered truncated.
+  pub duration_tolerance: f64,
+}
+
+impl Default for ParallelOptions {
+  /// A chunk for each core, all encoded at once.
+  fn default() -> Self {
+    let cores = std::thread::available_parallelism().map_or(4, |cores| cores.get());
+    Self {
+      split: ChunkSplit::Count(cores),
+      per_chunk_command: default_chunk_command,
+      max_concurrent: cores,
+      duration_tolerance: 0.5,
+    }
+  }
+}
+
+fn default_chunk_command(input: &Path, output: &Path) -> FfmpegCommand {
+  let mut command = FfmpegCommand::new();
+  command
+    .input(input.to_string_lossy())
+    .codec_video("libx264")
+    .overwrite()
+    .output(output.to_string_lossy());
+  command
+}
+
+/// A stage of [`parallel_encode_with_progress`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ParallelStage {
+  /// Splitting the video into chunks, without re-encoding.
+  Split,
+  /// Encoding the chunks.
+  Encode,
+  /// Joining the encoded chunks with the audio of the input.
+  Join,
+}
+
+/// Progress of a [`parallel_encode_with_progress`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParallelProgress {
+  pub stage: ParallelStage,
+  /// Estimated fraction of the whole job which is done, from `0.0` to
+  /// `1.0`. Splitting and joining, which only copy, are counted as a
+  /// twentieth each.
+  pub fraction: f64,
+}
+
+/// The result of a successful [`parallel_encode`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParallelOutcome {
+  /// The start time of each chunk in the input, in seconds.
+  pub chunk_starts: Vec<f64>,
+  /// The duration of the output, in seconds.
+  pub duration: f64,
+}
+
+/// Returned (through `anyhow::Error`) when a parallel encode fails, apart
+/// from a [`TruncatedOutput`](crate::error::TruncatedOutput) when the output
+/// is shorter than the input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParallelEncodeError {
+  /// The input has no video stream.
+  NoVideo,
+  /// The duration of the input is unknown, as for a live stream.
+  UnknownDuration,
+  /// One of the ffmpeg runs failed.
+  Failed {
+    stage: ParallelStage,
+    /// The chunk which failed to encode, for the encode stage.
+    chunk: Option<usize>,
+    /// The spawn error, or the error messages logged by ffmpeg.
+    message: String,
+  },
+}
+
+impl fmt::Display for ParallelEncodeError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      ParallelEncodeError::NoVideo => f.write_str("the input has no video stream"),
+      ParallelEncodeError::UnknownDuration => f.write_str("the duration of the input is unknown"),
+      ParallelEncodeError::Failed {
+        stage,
+        chunk: Some(chunk),
+        message,
+      } => write!(
+        f,
+        "parallel encode failed at {stage:?} of chunk {chunk}: {message}"
+      ),
+      ParallelEncodeError::Failed {
+        stage,
+        chunk: None,
+        message,
+      } => write!(f, "parallel encode failed at {stage:?}: {message}"),
+    }
+  }
+}
+
+impl std::error::Error for ParallelEncodeError {}
+
+/// Encode the video of `input` into `output` in chunks encoded concurrently,
+/// copying the audio of `input`. Blocks until every ffmpeg run has finished.
+///
+/// The output is checked to be as long as the input, within the tolerance
+/// of the options. Intermediate files are written to the system temporary
+/// directory, and removed whether or not the encode succeeds.
+pub fn parallel_encode<I: AsRef<Path>, O: AsRef<Path>>(
+  input: I,
+  output: O,
+  options: &ParallelOptions,
+) -> anyhow::Result<ParallelOutcome> {
+  parallel_encode_with_progress(input, output, options, |_| {})
+}
+
+/// Like [`parallel_encode`], additionally reporting the combined progress of
+/// the ffmpeg runs to `on_progress`, possibly from several threads.
+pub fn parallel_encode_with_progress<I, O, F>(
+  input: I,
+  output: O,
+  options: &ParallelOptions,
+  on_progress: F,
+) -> anyhow::Result<ParallelOutcome>
+where
+  I: AsRef<Path>,
+  O: AsRef<Path>,
+  F: Fn(ParallelProgress) + Sync,
+{
+  let (input, output) = (input.as_ref(), output.as_ref());
+  let info = probe_cached(input)?;
+  if info.streams_of_type("video").next().is_none() {
+    return Err(ParallelEncodeError::NoVideo.into());
+  }
+  let duration = info
+    .duration
+    .filter(|duration| *duration > 0.0)
+    .ok_or(ParallelEncodeError::UnknownDuration)?;
+  let keyframes = ffprobe_keyframes(input, 0.0, duration)?;
+  let split_points = split_points(&keyframes, duration, options.split);
+  let report = |stage, fraction: f64| {
+    on_progress(ParallelProgress {
+      stage,
+      fraction: fraction.clamp(0.0, 1.0),
+    })
+  };
+
+  let parts = TempRegistry::global().create_dir("parallel")?;
+  let chunks_dir = parts.path().join("chunks");
+  fs::create_dir(&chunks_dir)?;
+
+  // Split the first video stream, which is all that's encoded
+  let mut split = FfmpegCommand::new();
+  split
+    .input(input.to_string_lossy())
+    .args(["-map", "0:v:0", "-c", "copy", "-f", "segment"]);
+  if !split_points.is_empty() {
+    let times = split_points
+      .iter()
+      .map(|time| (time - SPLIT_OFFSET).to_string())
+      .collect::<Vec<_>>();
+    split.args(["-segment_times", &times.join(",")]);
+  }
+  split
+    .args(["-reset_timestamps", "1"])
+    .overwrite()
+    .output(chunks_dir.join("%05d.mkv").to_string_lossy());
+  let outcome = run_job(&mut split, |event| {
+    if let FfmpegEvent::Progress(progress) = event {
+      let time = progress.out_time.map_or(0.0, f64::from);
+      report(ParallelStage::Split, SPLIT_WEIGHT * time / duration);
+    }
+  });
+  check(outcome, ParallelStage::Split, None)?;
+
+  let mut chunks = fs::read_dir(&chunks_dir)?
+    .map(|entry| entry.map(|entry| entry.path()))
+    .collect::<Result<Vec<_>, _>>()?;
+  chunks.sort();
+  let encoded = (0..chunks.len())
+    .map(|index| parts.path().join(format!("encoded{index:05}.mkv")))
+    .collect::<Vec<_>>();
+
+  // The chunk boundaries the muxer actually used
+  let mut chunk_starts = vec![0.0];
+  chunk_starts.extend(split_points.iter().take(chunks.len().saturating_sub(1)));
+  let chunk_durations = chunk_starts
+    .iter()
+    .zip(chunk_starts.iter().skip(1).chain([&duration]))
+    .map(|(start, end)| end - start)
+    .collect::<Vec<_>>();
+
+  let encoded_times = Mutex::new(vec![0.0; chunks.len()]);
+  let commands = chunks
+    .iter()
+    .zip(&encoded)
+    .map(|(chunk, encoded)| (options.per_chunk_command)(chunk, encoded));
+  let outcomes = JobQueue::new(options.max_concurrent).run_with_events(commands, |chunk, event| {
+    let FfmpegEvent::Progress(progress) = event else {
+      return;
+    };
+    let Ok(mut times) = encoded_times.lock() else {
+      return;
+    };
+    times[chunk] = progress
+      .out_time
+      .map_or(0.0, f64::from)
+      .min(chunk_durations[chunk]);
+    let done = times.iter().sum::<f64>() / duration;
+    report(
+      ParallelStage::Encode,
+      SPLIT_WEIGHT + (1.0 - SPLIT_WEIGHT - JOIN_WEIGHT) * done,
+    );
+  });
+  for (chunk, outcome) in outcomes.into_iter().enumerate() {
+    check(outcome, ParallelStage::Encode, Some(chunk))?;
+  }
+
+  // Join the chunks, and copy everything but the video from the input
+  let list = parts.path().join("chunks.txt");
+  let encoded_refs = encoded.iter().map(PathBuf::as_path).collect::<Vec<_>>();
+  fs::write(&list, concat_list(&encoded_refs))?;
+  let mut join = FfmpegCommand::new();
+  join
+    .format("concat")
+    .args(["-safe", "0"])
+    .input(list.to_string_lossy())
+    .input(input.to_string_lossy())
+    .args(["-map", "0:v", "-map", "1:a?", "-map", "1:s?"])
+    .args(["-map_metadata", "1", "-map_chapters", "1", "-c", "copy"])
+    .overwrite()
+    .output(output.to_string_lossy());
+  let outcome = run_job(&mut join, |event| {
+    if let FfmpegEvent::Progress(progress) = event {
+      let time = progress.out_time.map_or(0.0, f64::from);
+      report(
+        ParallelStage::Join,
+        1.0 - JOIN_WEIGHT + JOIN_WEIGHT * time / duration,
+      );
+    }
+  });
+  check(outcome, ParallelStage::Join, None)?;
+
+  let output_duration = check_output_duration(output, duration, options.duration_tolerance)?;
+  report(ParallelStage::Join, 1.0);
+  Ok(ParallelOutcome {
+    chunk_starts,
+    duration: output_duration,
+  })
+}
+
+/// The keyframes at which to split an input of `duration` seconds, the
+/// first one at or after each of the evenly spaced targets.
+fn split_points(keyframes: &[f64], duration: f64, split: ChunkSplit) -> Vec<f64> {
+  let targets = match split {
+    ChunkSplit::Count(count) => (1..count.max(1))
+      .map(|i| duration * i as f64 / count as f64)
+      .collect::<Vec<_>>(),
+    ChunkSplit::Duration(chunk) => {
+      let chunk = chunk.as_secs_f64().max(f64::EPSILON);
+      (1..)
+        .map(|i| chunk * i as f64)
+        .take_while(|target| *target < duration)
+        .collect()
+    }
+  };
+  let mut points: Vec<f64> = Vec::new();
+  for target in targets {
+    let keyframe = keyframes
+      .iter()
+      .copied()
+      .find(|keyframe| *keyframe + SPLIT_OFFSET >= target && *keyframe < duration);
+    if let Some(keyframe) = keyframe {
+      if points
+        .last()
+        .map_or(keyframe > SPLIT_OFFSET, |last| keyframe > *last)
+      {
+        points.push(keyframe);
+      }
+    }
+  }
+  points
+}
+
+fn check(outcome: JobOutcome, stage: ParallelStage, chunk: Option<usize>) -> anyhow::Result<()> {
+  if outcome.is_success() {
+    return Ok(());
+  }
+  let message = match outcome.result {
+    Err(e) => e.to_string(),
+    Ok(status) if outcome.errors.is_empty() => format!("ffmpeg exited with {status}"),
+    Ok(_) => outcome.errors.join("\n"),
+  };
+  Err(
+    ParallelEncodeError::Failed {
+      stage,
+      chunk,
+      message,
+    }
+    .into(),
+  )
+}
@@ -5,20 +5,31 @@ use std::{
 
 use anyhow::Context;
 
+use crate::command::ffmpeg_is_installed_at;
+
 /// Returns the default path of the FFmpeg executable, to be used as the
 /// argument to `Command::new`. It should first attempt to locate an FFmpeg
 /// binary adjacent to the Rust executable. If that fails, it should invoke
 /// `ffmpeg` expecting it to be in the system path. If that fails, an
 /// informative error message should be printed (not when this function is
 /// called, but when the command is actually run).
+///
+/// A sidecar binary is only used if it actually runs (see
+/// [`ffmpeg_is_installed_at`]), so that a truncated binary left by an
+/// interrupted download doesn't shadow a working ffmpeg in the PATH.
 pub fn ffmpeg_path() -> PathBuf {
-  let default = Path::new("ffmpeg").to_path_buf();
   match sidecar_path() {
-    Ok(sidecar_path) => match sidecar_path.exists() {
-      true => sidecar_path,
-      false => default,
-    },
-    Err(_) => default,
+    Ok(sidecar_path) => ffmpeg_path_with_sidecar(&sidecar_path),
+    Err(_) => Path::new("ffmpeg").to_path_buf(),
+  }
+}
+
+/// Lower level variant of `ffmpeg_path` that exposes a customized path to the
+/// sidecar binary.
+pub fn ffmpeg_path_with_sidecar(sidecar_path: &Path) -> PathBuf {
+  match sidecar_path.exists() && ffmpeg_is_installed_at(sidecar_path) {
+    true => sidecar_path.to_path_buf(),
+    false => Path::new("ffmpeg").to_path_buf(),
   }
 }
 
@@ -0,0 +1,209 @@
+//! Background threads which move data between ffmpeg's stdio and arbitrary
+//! readers and writers.
+
+use std::{
+  collections::VecDeque,
+  io::{self, ErrorKind, Read, Write},
+  process::{ChildStdin, ChildStdout},
+  sync::{
+    mpsc::{sync_channel, Receiver},
+    Arc, Mutex,
+  },
+  thread::JoinHandle,
+};
+
+/// Default buffer size for [`FfmpegChild::feed_stdin`](crate::child::FfmpegChild::feed_stdin).
+pub const DEFAULT_PIPE_BUFFER_SIZE: usize = 64 * 1024;
+
+/// The result of a finished [`StdinFeeder`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StdinFeedReport {
+  /// Number of bytes written to ffmpeg's stdin.
+  pub bytes_written: u64,
+  /// Whether ffmpeg closed its stdin before the reader was exhausted, which is
+  /// expected when it only needs part of the input (e.g. `-t 5`).
+  pub closed_early: bool,
+}
+
+/// Handle to a thread copying a reader into ffmpeg's stdin, returned by
+/// [`FfmpegChild::feed_stdin`](crate::child::FfmpegChild::feed_stdin).
+///
+/// The thread runs independently of the event iterator, so both can be used
+/// at the same time.
+pub struct StdinFeeder {
+  handle: JoinHandle<io::Result<StdinFeedReport>>,
+}
+
+impl StdinFeeder {
+  pub(crate) fn spawn<R: Read + Send + 'static>(
+    mut reader: R,
+    mut stdin: ChildStdin,
+    buffer_size: usize,
+  ) -> Self {
+    let handle = std::thread::spawn(move || {
+      let mut buf = vec![0u8; buffer_size.max(1)];
+      let mut bytes_written = 0;
+      loop {
+        let n = match reader.read(&mut buf) {
+          Ok(0) => break,
+          Ok(n) => n,
+          Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+          Err(e) => return Err(e),
+        };
+        match stdin.write_all(&buf[..n]) {
+          Ok(()) => bytes_written += n as u64,
+          // ffmpeg exited or stopped reading; not an error for the feeder
+          Err(e) if is_broken_pipe(&e) => {
+            return Ok(StdinFeedReport {
+              bytes_written,
+              closed_early: true,
+            })
+          }
+          Err(e) => return Err(e),
+        }
+      }
+      // `stdin` is dropped here, signaling EOF to ffmpeg
+      Ok(StdinFeedReport {
+        bytes_written,
+        closed_early: false,
+      })
+    });
+    Self { handle }
+  }
+
+  /// Whether the copy has finished, successfully or not.
+  pub fn is_finished(&self) -> bool {
+    self.handle.is_finished()
+  }
+
+  /// Wait for the copy to finish. Returns an error only if reading the source
+  /// failed, or writing failed for a reason other than ffmpeg closing stdin.
+  pub fn join(self) -> io::Result<StdinFeedReport> {
+    self
+      .handle
+      .join()
+      .map_err(|_| io::Error::other("stdin feeder thread panicked"))?
+  }
+}
+
+/// Handle to a thread copying ffmpeg's stdout into a writer, returned by
+/// [`FfmpegChild::pipe_output_to`](crate::child::FfmpegChild::pipe_output_to).
+pub struct OutputPump {
+  rx: Receiver<io::Result<u64>>,
+}
+
+impl OutputPump {
+  /// Spawns the copy thread. The returned `JoinHandle` is kept by the child so
+  /// that `wait` can make sure the output has been flushed.
+  pub(crate) fn spawn<W: Write + Send + 'static>(
+    mut stdout: ChildStdout,
+    mut writer: W,
+    chunk_size: usize,
+  ) -> (Self, JoinHandle<io::Result<u64>>) {
+    let (tx, rx) = sync_channel(1);
+    let handle = std::thread::spawn(move || {
+      let result = copy_chunks(&mut stdout, &mut writer, chunk_size);
+      // `io::Error` isn't `Clone`; the joining side receives an equivalent copy
+      let copy = match &result {
+        Ok(bytes) => Ok(*bytes),
+        Err(e) => Err(io::Error::new(e.kind(), e.to_string())),
+      };
+      tx.send(copy).ok();
+      result
+    });
+    (Self { rx }, handle)
+  }
+
+  /// Wait for all of ffmpeg's output to be written and flushed, returning the
+  /// number of bytes written, or the first IO error which stopped the copy.
+  pub fn join(self) -> io::Result<u64> {
+    self
+      .rx
+      .recv()
+      .map_err(|_| io::Error::other("output pump thread panicked"))?
+  }
+}
+
+fn copy_chunks<W: Write>(
+  stdout: &mut ChildStdout,
+  writer: &mut W,
+  chunk_size: usize,
+) -> io::Result<u64> {
+  let mut buf = vec![0u8; chunk_size.max(1)];
+  let mut bytes_written = 0;
+  loop {
+    let n = match stdout.read(&mut buf) {
+      Ok(0) => break,
+      Ok(n) => n,
+      Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+      Err(e) => return Err(e),
+    };
+    writer.write_all(&buf[..n])?;
+    bytes_written += n as u64;
+  }
+  writer.flush()?;
+  Ok(bytes_written)
+}
+
+/// Whether a write failed because the other end of the pipe was closed, e.g.
+/// because ffmpeg exited. Windows reports this as `ERROR_BROKEN_PIPE` or, for
+/// a pipe which is being closed, `ERROR_NO_DATA`.
+pub(crate) fn is_broken_pipe(error: &io::Error) -> bool {
+  const ERROR_BROKEN_PIPE: i32 = 109;
+  const ERROR_NO_DATA: i32 = 232;
+  error.kind() == ErrorKind::BrokenPipe
+    || (cfg!(windows)
+      && matches!(
+        error.raw_os_error(),
+        Some(ERROR_BROKEN_PIPE | ERROR_NO_DATA)
+      ))
+}
+
+/// The last lines read from ffmpeg's stderr, shared between the child and the
+/// thread reading stderr, so they're still available after the iterator is
+/// dropped.
+#[derive(Debug, Clone)]
+pub(crate) struct StderrTail(Arc<Mutex<TailLines>>);
+
+#[derive(Debug)]
+struct TailLines {
+  lines: VecDeque<String>,
+  max_lines: usize,
+}
+
+impl Default for StderrTail {
+  fn default() -> Self {
+    Self(Arc::new(Mutex::new(TailLines {
+      lines: VecDeque::new(),
+      max_lines: Self::DEFAULT_MAX_LINES,
+    })))
+  }
+}
+
+impl StderrTail {
+  const DEFAULT_MAX_LINES: usize = 20;
+
+  pub(crate) fn push(&self, line: &str) {
+    if let Ok(mut tail) = self.0.lock() {
+      while tail.lines.len() >= tail.max_lines.max(1) {
+        tail.lines.pop_front();
+      }
+      tail.lines.push_back(line.to_string());
+    }
+  }
+
+  pub(crate) fn lines(&self) -> Vec<String> {
+    self
+      .0
+      .lock()
+      .map(|tail| tail.lines.iter().cloned().collect())
+      .unwrap_or_default()
+  }
+
+  /// Keep up to `max_lines` from now on, shared with all the handles.
+  pub(crate) fn set_max_lines(&self, max_lines: usize) {
+    if let Ok(mut tail) = self.0.lock() {
+      tail.max_lines = max_lines;
+    }
+  }
+}
@@ -227,12 +227,19 @@ pub fn get_bits_per_pixel(pix_fmt: &str) -> Option<u32> {
   }
 }
 
+/// The size of a raw video frame of `stream`, or `None` if its pixel format
+/// is unknown, isn't byte-aligned, or the size doesn't fit in a `u32`.
 pub fn get_bytes_per_frame(stream: &AVStream) -> Option<u32> {
+  u32::try_from(frame_bytes(stream)?).ok()
+}
+
+/// Like [`get_bytes_per_frame`], without overflowing for absurd resolutions.
+pub(crate) fn frame_bytes(stream: &AVStream) -> Option<u64> {
   let bits_per_pixel = get_bits_per_pixel(&stream.pix_fmt)?;
   // Enforce byte-alignment, since we don't currently have buffer reads in
   // sub-byte increments.
   match bits_per_pixel % 8 {
-    0 => Some(stream.width * stream.height * bits_per_pixel / 8),
+    0 => Some(u64::from(stream.width) * u64::from(stream.height) * u64::from(bits_per_pixel) / 8),
     _ => None,
   }
 }
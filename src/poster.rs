@@ -0,0 +1,183 @@
+//! Extracting a single frame as a poster image, e.g. for a video thumbnail.
+
+use std::{fmt, fs, path::Path, time::Duration};
+
+use crate::{
+  command::FfmpegCommand,
+  probe::{probe, probe_cached},
+  queue::run_job,
+};
+
+/// How far before the requested time the input is seeked. The rest is
+/// decoded and discarded, so the frame is exact even though input seeking
+/// lands on a keyframe.
+const ACCURATE_SEEK_WINDOW: Duration = Duration::from_secs(3);
+
+/// Frames with at least this percentage of black pixels are skipped by
+/// [`PosterOptions::skip_black`].
+const BLACK_PIXELS_PERCENT: u32 = 95;
+
+/// Options for [`poster_frame`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct PosterOptions {
+  /// Time of the frame. By default, the first keyframe is used, since the
+  /// frames before it can't be decoded cleanly when a stream, like a TS
+  /// capture, starts in the middle of a group of pictures.
+  pub at: Option<Duration>,
+  /// Use the first frame from `at` on which isn't (nearly) black, e.g. to
+  /// skip a fade in. If the rest of the video is black, the frame at `at` is
+  /// used anyway.
+  pub skip_black: bool,
+}
+
+/// Returned (through `anyhow::Error`) when no poster could be written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PosterError {
+  /// An ffmpeg run failed, with its error messages.
+  Failed(String),
+  /// ffmpeg didn't write any frame, e.g. because the input has no video.
+  NoFrame,
+  /// The written image couldn't be read back.
+  InvalidImage(String),
+}
+
+impl fmt::Display for PosterError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      PosterError::Failed(message) => write!(f, "extracting the poster failed: {message}"),
+      PosterError::NoFrame => f.write_str("no frame was extracted for the poster"),
+      PosterError::InvalidImage(reason) => write!(f, "the poster image is invalid: {reason}"),
+    }
+  }
+}
+
+impl std::error::Error for PosterError {}
+
+/// Write a single frame of `input` to the image `output`, e.g. a PNG or JPEG,
+/// overwriting it if it exists. Blocks until done.
+///
+/// When `at` is past the end of the input, the last frame is used instead.
+/// The image is checked to be non-empty and readable by ffprobe before
+/// returning.
+///
+/// ```rust,no_run
+/// use ffmpeg_sidecar::poster::{poster_frame, PosterOptions};
+/// use std::time::Duration;
+///
+/// poster_frame(
+///   "capture.ts",
+///   "poster.jpg",
+///   &PosterOptions {
+///     at: Some(Duration::from_secs(5)),
+///     skip_black: true,
+///   },
+/// )
+/// .unwrap();
+/// ```
+pub fn poster_frame<I: AsRef<Path>, O: AsRef<Path>>(
+  input: I,
+  output: O,
+  options: &PosterOptions,
+) -> anyhow::Result<()> {
+  let (input, output) = (input.as_ref(), output.as_ref());
+  // A stale image would pass for the result of an attempt which wrote nothing
+  if output.exists() {
+    fs::remove_file(output)?;
+  }
+
+  let past_end = match (
+    options.at,
+    probe_cached(input).ok().and_then(|info| info.duration),
+  ) {
+    (Some(at), Some(duration)) => at.as_secs_f64() >= duration,
+    _ => false,
+  };
+  let mut attempts = Vec::new();
+  if !past_end {
+    if options.skip_black {
+      attempts.push(frame_command(input, output, options.at, true));
+    }
+    attempts.push(frame_command(input, output, options.at, false));
+  }
+  attempts.push(last_frame_command(input, output));
+
+  for mut command in attempts {
+    let outcome = run_job(&mut command, |_| {});
+    if !outcome.is_success() {
+      let message = match outcome.result {
+        Err(e) => e.to_string(),
+        Ok(status) if outcome.errors.is_empty() => format!("ffmpeg exited with {status}"),
+        Ok(_) => outcome.errors.join("\n"),
+      };
+      return Err(PosterError::Failed(message).into());
+    }
+    if fs::metadata(output).is_ok_and(|metadata| metadata.len() > 0) {
+      return check_image(output);
+    }
+  }
+  Err(PosterError::NoFrame.into())
+}
+
+/// The frame at `at`, or the first keyframe.
+fn frame_command(
+  input: &Path,
+  output: &Path,
+  at: Option<Duration>,
+  skip_black: bool,
+) -> FfmpegCommand {
+  let mut command = FfmpegCommand::new();
+  match at {
+    Some(at) => {
+      // Fast input seek to a keyframe shortly before, then accurate output
+      // seek to the frame itself, so decoding starts at a clean keyframe
+      let window = at.min(ACCURATE_SEEK_WINDOW);
+      command
+        .seek(at - window)
+        .input(input.to_string_lossy())
+        .seek(window);
+    }
+    None => {
+      command
+        .args(["-skip_frame", "nokey"])
+        .input(input.to_string_lossy());
+    }
+  }
+  if skip_black {
+    // With `amount=0`, every frame gets its percentage of black pixels
+    command.filter(format!(
+      "blackframe=amount=0,\
+       metadata=mode=select:key=lavfi.blackframe.pblack:value={BLACK_PIXELS_PERCENT}:function=less"
+    ));
+  }
+  command
+    .no_audio()
+    .frames(1)
+    .args(["-update", "1"])
+    .overwrite()
+    .output(output.to_string_lossy());
+  command
+}
+
+/// The last frame, written over and over until the end of the input.
+fn last_frame_command(input: &Path, output: &Path) -> FfmpegCommand {
+  let mut command = FfmpegCommand::new();
+  command
+    .args(["-sseof", "-1"])
+    .input(input.to_string_lossy())
+    .no_audio()
+    .args(["-update", "1"])
+    .overwrite()
+    .output(output.to_string_lossy());
+  command
+}
+
+fn check_image(output: &Path) -> anyhow::Result<()> {
+  let info = probe(output).map_err(|e| PosterError::InvalidImage(e.to_string()))?;
+  let decodable = info
+    .streams_of_type("video")
+    .any(|stream| stream.width.is_some_and(|width| width > 0));
+  if !decodable {
+    return Err(PosterError::InvalidImage("no picture found".to_string()).into());
+  }
+  Ok(())
+}
@@ -0,0 +1,365 @@
+//! Inspecting media files with ffprobe before running ffmpeg on them.
+
+use std::collections::HashMap;
+#[cfg(feature = "process")]
+use std::{
+  collections::VecDeque,
+  ffi::OsStr,
+  fs,
+  path::{Path, PathBuf},
+  process::{Command, Stdio},
+  sync::Mutex,
+  time::SystemTime,
+};
+
+use crate::frame_rate::Rate;
+#[cfg(feature = "process")]
+use crate::{ffprobe::ffprobe_path, spawner};
+
+/// Container and stream information about a media file, obtained from
+/// [`probe`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MediaInfo {
+  /// Comma separated list of demuxer names, like `mov,mp4,m4a,3gp,3g2,mj2`
+  pub format_name: String,
+  /// How confident ffprobe is about the format, from 1 to 100. A score of
+  /// 50 or less means the content was inconclusive, and the format was
+  /// picked by the extension.
+  pub probe_score: Option<u32>,
+  /// Duration in seconds, if known
+  pub duration: Option<f64>,
+  /// Container tags such as `title` or `creation_time`
+  pub tags: HashMap<String, String>,
+  pub streams: Vec<StreamInfo>,
+  /// The programs of an input with several, like the channels of a DVB
+  /// capture. Empty for the usual files without programs.
+  pub programs: Vec<ProgramInfo>,
+}
+
+/// A program of a [`MediaInfo`], grouping some of its streams.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ProgramInfo {
+  /// The program number, as in `-map 0:p:<id>`
+  pub id: u32,
+  /// Program tags such as `service_name` and `service_provider`
+  pub tags: HashMap<String, String>,
+  /// The [`StreamInfo::index`] of its streams
+  pub stream_indices: Vec<usize>,
+}
+
+impl ProgramInfo {
+  /// The name of the channel, like `BBC ONE HD`, if tagged.
+  pub fn service_name(&self) -> Option<&str> {
+    self.tags.get("service_name").map(String::as_str)
+  }
+}
+
+/// A single stream of a [`MediaInfo`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct StreamInfo {
+  /// Absolute index of the stream in the file, as in `-map 0:<index>`
+  pub index: usize,
+  /// `video`, `audio`, `subtitle`, `data` or `attachment`
+  pub codec_type: String,
+  /// Short codec name, like `h264` or `aac`
+  pub codec_name: String,
+  pub width: Option<u32>,
+  pub height: Option<u32>,
+  /// Pixel format of a video stream, like `yuv420p`
+  pub pix_fmt: Option<String>,
+  /// The base frame rate of a video stream, the lowest one all timestamps
+  /// can be represented in
+  pub r_frame_rate: Option<Rate>,
+  pub sample_rate: Option<u32>,
+  pub channels: Option<u32>,
+  /// Duration in seconds, if known
+  pub duration: Option<f64>,
+  /// Stream tags such as `language`
+  pub tags: HashMap<String, String>,
+}
+
+impl MediaInfo {
+  /// Streams of the given type (`video`, `audio`, ...), in file order.
+  pub fn streams_of_type<'a>(
+    &'a self,
+    codec_type: &'a str,
+  ) -> impl Iterator<Item = &'a StreamInfo> {
+    self
+      .streams
+      .iter()
+      .filter(move |stream| stream.codec_type == codec_type)
+  }
+
+  /// Parse the output of `ffprobe -show_format -show_streams -show_programs`
+  /// in its default output format, which consists of `key=value` lines
+  /// grouped into `[STREAM]`, `[FORMAT]` and `[PROGRAM]` sections. The
+  /// streams listed in a program are only counted once, in [`Self::streams`].
+  ///
+  /// ```rust
+  /// use ffmpeg_sidecar::probe::MediaInfo;
+  ///
+  /// let output = "[STREAM]\nindex=0\ncodec_name=aac\ncodec_type=audio\nsample_rate=48000\nchannels=2\nTAG:language=eng\n[/STREAM]\n[FORMAT]\nformat_name=mov,mp4,m4a,3gp,3g2,mj2\nduration=10.000000\nTAG:title=Holiday\n[/FORMAT]\n";
+  /// let info = MediaInfo::parse(output);
+  /// assert_eq!(info.duration, Some(10.0));
+  /// assert_eq!(info.tags["title"], "Holiday");
+  /// assert_eq!(info.streams[0].codec_name, "aac");
+  /// assert_eq!(info.streams[0].tags["language"], "eng");
+  /// assert!(info.programs.is_empty());
+  /// ```
+  pub fn parse(output: &str) -> Self {
+    let mut info = MediaInfo::default();
+    let mut in_format = false;
+    let mut in_program = false;
+    let mut in_program_stream = false;
+    for line in output.lines().map(str::trim) {
+      match line {
+        "[PROGRAM]" => {
+          in_program = true;
+          info.programs.push(ProgramInfo::default());
+        }
+        "[/PROGRAM]" => in_program = false,
+        // Only the index of the streams nested in a program is kept
+        "[STREAM]" if in_program => in_program_stream = true,
+        "[/STREAM]" => in_program_stream = false,
+        "[STREAM]" => info.streams.push(StreamInfo::default()),
+        "[FORMAT]" => in_format = true,
+        "[/FORMAT]" => in_format = false,
+        _ => {
+          let Some((key, value)) = line.split_once('=') else {
+            continue;
+          };
+          if in_format {
+            match key {
+              "format_name" => info.format_name = value.to_string(),
+              "probe_score" => info.probe_score = value.parse().ok(),
+              "duration" => info.duration = value.parse().ok(),
+              _ => {
+                if let Some(tag) = key.strip_prefix("TAG:") {
+                  info.tags.insert(tag.to_string(), value.to_string());
+                }
+              }
+            }
+          } else if in_program {
+            let Some(program) = info.programs.last_mut() else {
+              continue;
+            };
+            match (in_program_stream, key) {
+              (true, "index") => program.stream_indices.extend(value.parse::<usize>().ok()),
+              (true, _) => {}
+              (false, _) => program.set(key, value),
+            }
+          } else if let Some(stream) = info.streams.last_mut() {
+            stream.set(key, value);
+          }
+        }
+      }
+    }
+    info
+  }
+}
+
+impl StreamInfo {
+  fn set(&mut self, key: &str, value: &str) {
+    match key {
+      "index" => self.index = value.parse().unwrap_or_default(),
+      "codec_type" => self.codec_type = value.to_string(),
+      "codec_name" => self.codec_name = value.to_string(),
+      "width" => self.width = value.parse().ok(),
+      "height" => self.height = value.parse().ok(),
+      "pix_fmt" => self.pix_fmt = Some(value.to_string()).filter(|v| v != "unknown"),
+      "r_frame_rate" => self.r_frame_rate = value.parse().ok(),
+      "sample_rate" => self.sample_rate = value.parse().ok(),
+      "channels" => self.channels = value.parse().ok(),
+      "duration" => self.duration = value.parse().ok(),
+      _ => {
+        if let Some(tag) = key.strip_prefix("TAG:") {
+          self.tags.insert(tag.to_string(), value.to_string());
+        }
+      }
+    }
+  }
+}
+
+impl ProgramInfo {
+  fn set(&mut self, key: &str, value: &str) {
+    match key {
+      "program_id" => self.id = value.parse().unwrap_or_default(),
+      _ => {
+        if let Some(tag) = key.strip_prefix("TAG:") {
+          self.tags.insert(tag.to_string(), value.to_string());
+        }
+      }
+    }
+  }
+}
+
+/// The number of files remembered by [`probe_cached`], unless changed with
+/// [`set_probe_cache_capacity`].
+#[cfg(feature = "process")]
+pub const DEFAULT_PROBE_CACHE_CAPACITY: usize = 64;
+
+#[cfg(feature = "process")]
+static PROBE_CACHE: Mutex<ProbeCache> = Mutex::new(ProbeCache {
+  enabled: true,
+  capacity: DEFAULT_PROBE_CACHE_CAPACITY,
+  entries: VecDeque::new(),
+});
+
+#[cfg(feature = "process")]
+struct ProbeCache {
+  enabled: bool,
+  capacity: usize,
+  /// The least recently used first
+  entries: VecDeque<CachedProbe>,
+}
+
+#[cfg(feature = "process")]
+struct CachedProbe {
+  ffprobe: PathBuf,
+  /// The canonical path of the file
+  path: PathBuf,
+  size: u64,
+  modified: SystemTime,
+  info: MediaInfo,
+}
+
+/// Run ffprobe on `input` and parse its container and stream information.
+///
+/// Every call spawns ffprobe; see [`probe_cached`] to probe the same file
+/// several times.
+#[cfg(feature = "process")]
+pub fn probe<S: AsRef<OsStr>>(input: S) -> anyhow::Result<MediaInfo> {
+  probe_with_path(ffprobe_path(), input)
+}
+
+/// Like [`probe`], with a specific ffprobe binary.
+#[cfg(feature = "process")]
+pub fn probe_with_path<P: AsRef<Path>, S: AsRef<OsStr>>(
+  path_to_ffprobe_binary: P,
+  input: S,
+) -> anyhow::Result<MediaInfo> {
+  let output = spawner::output(
+    Command::new(path_to_ffprobe_binary.as_ref())
+      .args([
+        "-v",
+        "error",
+        "-show_format",
+        "-show_streams",
+        "-show_programs",
+      ])
+      .arg(input.as_ref())
+      .stdin(Stdio::null()),
+  )?;
+  if !output.status.success() {
+    anyhow::bail!(
+      "ffprobe failed: {}",
+      String::from_utf8_lossy(&output.stderr).trim()
+    );
+  }
+  Ok(MediaInfo::parse(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Like [`probe`], reusing the result of a previous call for the same file,
+/// as long as its size and modification time didn't change. The helpers of
+/// the crate probe their inputs this way, so an operation doesn't spawn
+/// ffprobe several times for the same file, which is slow on network shares.
+///
+/// Only regular files are cached: URLs, devices and pipes are probed every
+/// time. The cache is process-wide, keeps the
+/// [`DEFAULT_PROBE_CACHE_CAPACITY`] most recently used files, and can be
+/// turned off with [`probe_cache_disable`].
+#[cfg(feature = "process")]
+pub fn probe_cached<S: AsRef<OsStr>>(input: S) -> anyhow::Result<MediaInfo> {
+  probe_cached_with_path(ffprobe_path(), input)
+}
+
+/// Like [`probe_cached`], with a specific ffprobe binary. Files probed with
+/// different binaries are cached separately.
+#[cfg(feature = "process")]
+pub fn probe_cached_with_path<P: AsRef<Path>, S: AsRef<OsStr>>(
+  path_to_ffprobe_binary: P,
+  input: S,
+) -> anyhow::Result<MediaInfo> {
+  let ffprobe = path_to_ffprobe_binary.as_ref();
+  let input = input.as_ref();
+  let enabled = PROBE_CACHE.lock().is_ok_and(|cache| cache.enabled);
+  let Some((path, size, modified)) = enabled.then(|| cache_key(input)).flatten() else {
+    return probe_with_path(ffprobe, input);
+  };
+
+  if let Ok(mut cache) = PROBE_CACHE.lock() {
+    let position = cache
+      .entries
+      .iter()
+      .position(|entry| entry.ffprobe == ffprobe && entry.path == path);
+    if let Some(entry) = position.and_then(|position| cache.entries.remove(position)) {
+      if entry.size == size && entry.modified == modified {
+        let info = entry.info.clone();
+        cache.entries.push_back(entry);
+        return Ok(info);
+      }
+    }
+  }
+
+  let info = probe_with_path(ffprobe, input)?;
+  if let Ok(mut cache) = PROBE_CACHE.lock() {
+    if cache.enabled && cache.capacity > 0 {
+      // Probed by another thread meanwhile
+      cache
+        .entries
+        .retain(|entry| entry.ffprobe != ffprobe || entry.path != path);
+      while cache.entries.len() >= cache.capacity {
+        cache.entries.pop_front();
+      }
+      cache.entries.push_back(CachedProbe {
+        ffprobe: ffprobe.to_path_buf(),
+        path,
+        size,
+        modified,
+        info: info.clone(),
+      });
+    }
+  }
+  Ok(info)
+}
+
+/// The canonical path, size and modification time of a regular file, or
+/// `None` for anything else, like a URL or a pipe.
+#[cfg(feature = "process")]
+fn cache_key(input: &OsStr) -> Option<(PathBuf, u64, SystemTime)> {
+  let metadata = fs::metadata(input)
+    .ok()
+    .filter(|metadata| metadata.is_file())?;
+  let path = fs::canonicalize(input).ok()?;
+  Some((path, metadata.len(), metadata.modified().ok()?))
+}
+
+/// Stop [`probe_cached`] from caching, forgetting what it cached, e.g. when
+/// files may be replaced without changing their size or modification time.
+#[cfg(feature = "process")]
+pub fn probe_cache_disable() {
+  if let Ok(mut cache) = PROBE_CACHE.lock() {
+    cache.enabled = false;
+    cache.entries.clear();
+  }
+}
+
+/// Turn the cache of [`probe_cached`] back on after [`probe_cache_disable`].
+#[cfg(feature = "process")]
+pub fn probe_cache_enable() {
+  if let Ok(mut cache) = PROBE_CACHE.lock() {
+    cache.enabled = true;
+  }
+}
+
+/// Change how many files [`probe_cached`] remembers, forgetting the least
+/// recently used ones beyond `capacity`.
+#[cfg(feature = "process")]
+pub fn set_probe_cache_capacity(capacity: usize) {
+  if let Ok(mut cache) = PROBE_CACHE.lock() {
+    cache.capacity = capacity;
+    while cache.entries.len() > capacity {
+      cache.entries.pop_front();
+    }
+  }
+}
@@ -0,0 +1,171 @@
+//! Killing ffmpeg together with the processes it starts, like the helpers of
+//! some protocols and filters. See
+//! [`FfmpegCommand::contain_process_tree`](crate::command::FfmpegCommand::contain_process_tree).
+
+use std::{io, process::Child};
+
+/// The processes of a contained ffmpeg child: its process group on Unix, or
+/// a Job Object on Windows.
+pub(crate) struct ProcessTree {
+  #[cfg(unix)]
+  pgid: u32,
+  #[cfg(windows)]
+  job: windows::Job,
+}
+
+impl ProcessTree {
+  /// Contain `child`, which was spawned as the leader of a new process group
+  /// on Unix. On Windows, it's assigned to a new Job Object which kills all
+  /// its processes once closed, i.e. when this is dropped.
+  pub(crate) fn new(child: &Child) -> io::Result<Self> {
+    #[cfg(unix)]
+    return Ok(Self { pgid: child.id() });
+
+    #[cfg(windows)]
+    {
+      let job = windows::Job::new()?;
+      job.assign(child)?;
+      Ok(Self { job })
+    }
+
+    #[cfg(not(any(unix, windows)))]
+    {
+      let _ = child;
+      Err(io::ErrorKind::Unsupported.into())
+    }
+  }
+
+  /// Kill every process left in the tree.
+  pub(crate) fn kill(&self) -> io::Result<()> {
+    #[cfg(unix)]
+    return kill_process_group(self.pgid);
+
+    #[cfg(windows)]
+    return self.job.terminate();
+
+    #[cfg(not(any(unix, windows)))]
+    unreachable!("process trees can't be created on this platform")
+  }
+}
+
+/// Signal a whole process group with the `kill` command, like the timer of
+/// `first_output_timeout` does for a single process. An error is only
+/// returned if the command can't be run, since the group may be gone already.
+#[cfg(unix)]
+pub(crate) fn kill_process_group(pgid: u32) -> io::Result<()> {
+  std::process::Command::new("kill")
+    .args(["-KILL", "--", &format!("-{pgid}")])
+    .stdin(std::process::Stdio::null())
+    .stdout(std::process::Stdio::null())
+    .stderr(std::process::Stdio::null())
+    .status()
+    .map(|_| ())
+}
+
+/// The few Job Object functions needed, declared by hand to avoid a
+/// dependency on the Windows API crates.
+#[cfg(windows)]
+mod windows {
+  use std::{ffi::c_void, io, mem, os::windows::io::AsRawHandle, process::Child, ptr};
+
+  type Handle = *mut c_void;
+
+  const JOB_OBJECT_EXTENDED_LIMIT_INFORMATION: i32 = 9;
+  const JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE: u32 = 0x2000;
+
+  #[repr(C)]
+  #[derive(Default)]
+  struct BasicLimitInformation {
+    per_process_user_time_limit: i64,
+    per_job_user_time_limit: i64,
+    limit_flags: u32,
+    minimum_working_set_size: usize,
+    maximum_working_set_size: usize,
+    active_process_limit: u32,
+    affinity: usize,
+    priority_class: u32,
+    scheduling_class: u32,
+  }
+
+  #[repr(C)]
+  #[derive(Default)]
+  struct IoCounters {
+    read_operation_count: u64,
+    write_operation_count: u64,
+    other_operation_count: u64,
+    read_transfer_count: u64,
+    write_transfer_count: u64,
+    other_transfer_count: u64,
+  }
+
+  #[repr(C)]
+  #[derive(Default)]
+  struct ExtendedLimitInformation {
+    basic_limit_information: BasicLimitInformation,
+    io_info: IoCounters,
+    process_memory_limit: usize,
+    job_memory_limit: usize,
+    peak_process_memory_used: usize,
+    peak_job_memory_used: usize,
+  }
+
+  #[link(name = "kernel32")]
+  extern "system" {
+    fn CreateJobObjectW(attributes: *mut c_void, name: *const u16) -> Handle;
+    fn SetInformationJobObject(job: Handle, class: i32, info: *mut c_void, length: u32) -> i32;
+    fn AssignProcessToJobObject(job: Handle, process: Handle) -> i32;
+    fn TerminateJobObject(job: Handle, exit_code: u32) -> i32;
+    fn CloseHandle(handle: Handle) -> i32;
+  }
+
+  pub(super) struct Job(Handle);
+
+  // The handle is owned, and the Job Object functions are thread safe
+  unsafe impl Send for Job {}
+  unsafe impl Sync for Job {}
+
+  impl Job {
+    pub(super) fn new() -> io::Result<Self> {
+      let handle = unsafe { CreateJobObjectW(ptr::null_mut(), ptr::null()) };
+      if handle.is_null() {
+        return Err(io::Error::last_os_error());
+      }
+      let job = Job(handle);
+
+      let mut info = ExtendedLimitInformation::default();
+      info.basic_limit_information.limit_flags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE;
+      let set = unsafe {
+        SetInformationJobObject(
+          job.0,
+          JOB_OBJECT_EXTENDED_LIMIT_INFORMATION,
+          &mut info as *mut ExtendedLimitInformation as *mut c_void,
+          mem::size_of::<ExtendedLimitInformation>() as u32,
+        )
+      };
+      if set == 0 {
+        return Err(io::Error::last_os_error());
+      }
+      Ok(job)
+    }
+
+    pub(super) fn assign(&self, child: &Child) -> io::Result<()> {
+      if unsafe { AssignProcessToJobObject(self.0, child.as_raw_handle()) } == 0 {
+        return Err(io::Error::last_os_error());
+      }
+      Ok(())
+    }
+
+    pub(super) fn terminate(&self) -> io::Result<()> {
+      if unsafe { TerminateJobObject(self.0, 1) } == 0 {
+        return Err(io::Error::last_os_error());
+      }
+      Ok(())
+    }
+  }
+
+  impl Drop for Job {
+    fn drop(&mut self) {
+      unsafe { CloseHandle(self.0) };
+    }
+  }
+}
@@ -0,0 +1,122 @@
+//! Selecting a program of an input with several, like a channel of a DVB or
+//! ATSC capture. See
+//! [`FfmpegCommand::select_program`](crate::command::FfmpegCommand::select_program).
+
+use std::fmt;
+
+use crate::probe::MediaInfo;
+
+/// A program to select, by its number or its `service_name` tag.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Program {
+  /// The program number, as in `-map 0:p:4164`
+  Id(u32),
+  /// The name of the channel, like `BBC ONE HD`, matched in any case.
+  Name(String),
+}
+
+impl Program {
+  /// The number of the program of `media`.
+  ///
+  /// ```rust
+  /// use ffmpeg_sidecar::{probe::MediaInfo, program::Program};
+  ///
+  /// let media = MediaInfo::parse(
+  ///   "[PROGRAM]\nprogram_id=4164\nTAG:service_name=BBC ONE HD\n\
+  ///    [STREAM]\nindex=0\n[/STREAM]\n[/PROGRAM]\n\
+  ///    [PROGRAM]\nprogram_id=4287\nTAG:service_name=BBC TWO HD\n\
+  ///    [STREAM]\nindex=1\n[/STREAM]\n[/PROGRAM]\n\
+  ///    [STREAM]\nindex=0\ncodec_type=video\n[/STREAM]\n\
+  ///    [STREAM]\nindex=1\ncodec_type=video\n[/STREAM]\n",
+  /// );
+  /// assert_eq!(Program::from("bbc two hd").resolve(&media).unwrap(), 4287);
+  /// assert_eq!(Program::from(4164).resolve(&media).unwrap(), 4164);
+  ///
+  /// let error = Program::from("BBC FOUR").resolve(&media).unwrap_err();
+  /// assert_eq!(
+  ///   error.to_string(),
+  ///   "no program BBC FOUR; the input has 4164 (BBC ONE HD), 4287 (BBC TWO HD)"
+  /// );
+  /// ```
+  pub fn resolve(&self, media: &MediaInfo) -> Result<u32, UnknownProgram> {
+    let found = media.programs.iter().find(|program| match self {
+      Program::Id(id) => program.id == *id,
+      Program::Name(name) => program
+        .service_name()
+        .is_some_and(|service_name| service_name.trim().eq_ignore_ascii_case(name.trim())),
+    });
+    match found {
+      Some(program) => Ok(program.id),
+      None => Err(UnknownProgram {
+        program: self.clone(),
+        available: media
+          .programs
+          .iter()
+          .map(|program| (program.id, program.service_name().map(str::to_string)))
+          .collect(),
+      }),
+    }
+  }
+}
+
+impl From<u32> for Program {
+  fn from(id: u32) -> Self {
+    Program::Id(id)
+  }
+}
+
+impl From<&str> for Program {
+  fn from(name: &str) -> Self {
+    Program::Name(name.to_string())
+  }
+}
+
+impl From<String> for Program {
+  fn from(name: String) -> Self {
+    Program::Name(name)
+  }
+}
+
+impl fmt::Display for Program {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Program::Id(id) => write!(f, "{id}"),
+      Program::Name(name) => f.write_str(name),
+    }
+  }
+}
+
+/// Returned (through `io::Error`, with the kind `InvalidInput`) by
+/// [`FfmpegCommand::spawn`](crate::command::FfmpegCommand::spawn) before
+/// spawning, when an input has no program with the name selected with
+/// [`select_program`](crate::command::FfmpegCommand::select_program).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct UnknownProgram {
+  pub program: Program,
+  /// The number and `service_name` of the programs of the input, in order.
+  pub available: Vec<(u32, Option<String>)>,
+}
+
+impl fmt::Display for UnknownProgram {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let available = self
+      .available
+      .iter()
+      .map(|(id, name)| match name {
+        Some(name) => format!("{id} ({name})"),
+        None => id.to_string(),
+      })
+      .collect::<Vec<_>>();
+    write!(
+      f,
+      "no program {}; the input has {}",
+      self.program,
+      match available.is_empty() {
+        true => "none".to_string(),
+        false => available.join(", "),
+      }
+    )
+  }
+}
+
+impl std::error::Error for UnknownProgram {}
@@ -0,0 +1,211 @@
+//! Monitoring a job through the file written by `-progress`, e.g. from a
+//! different process than the one which spawned ffmpeg.
+//!
+//! ```rust,no_run
+//! use ffmpeg_sidecar::{command::FfmpegCommand, progress_file::ProgressFileReader};
+//!
+//! FfmpegCommand::new()
+//!   .testsrc()
+//!   .progress_file("progress.txt")
+//!   .overwrite()
+//!   .output("output.mp4")
+//!   .detach()
+//!   .unwrap();
+//!
+//! // Possibly in another process
+//! for progress in ProgressFileReader::tail("progress.txt") {
+//!   println!("{} frames", progress.frame.unwrap_or(0));
+//! }
+//! ```
+
+use std::{
+  fs::File,
+  io::{self, BufRead, BufReader, Seek, SeekFrom},
+  path::{Path, PathBuf},
+  time::{Duration, Instant},
+};
+
+use crate::{event::FfmpegProgress, timestamp::Timestamp};
+
+/// Follows a progress file as ffmpeg appends to it, yielding a progress update
+/// for every completed block. Iteration blocks while waiting for more data,
+/// and ends after ffmpeg's final `progress=end` block.
+///
+/// The file doesn't need to exist yet, since ffmpeg only creates it after
+/// starting. The file of a job which already finished is read to the end
+/// without waiting.
+pub struct ProgressFileReader {
+  path: PathBuf,
+  reader: Option<BufReader<File>>,
+  /// A line which hasn't been completely written yet
+  partial_line: String,
+  block: Vec<String>,
+  finished: bool,
+  poll_interval: Duration,
+  idle_timeout: Option<Duration>,
+  last_data: Instant,
+}
+
+impl ProgressFileReader {
+  pub fn tail<P: AsRef<Path>>(path: P) -> Self {
+    Self {
+      path: path.as_ref().to_path_buf(),
+      reader: None,
+      partial_line: String::new(),
+      block: Vec::new(),
+      finished: false,
+      poll_interval: Duration::from_millis(100),
+      idle_timeout: None,
+      last_data: Instant::now(),
+    }
+  }
+
+  /// How often to check for new data. Defaults to 100ms; ffmpeg writes a
+  /// block every 500ms by default (see `-stats_period`).
+  pub fn poll_interval(&mut self, interval: Duration) -> &mut Self {
+    self.poll_interval = interval;
+    self
+  }
+
+  /// Stop waiting if the file doesn't grow for this long, e.g. because the
+  /// job was killed before writing its final block. By default, waits forever.
+  pub fn idle_timeout(&mut self, timeout: Duration) -> &mut Self {
+    self.idle_timeout = Some(timeout);
+    self
+  }
+
+  /// Whether the final `progress=end` block was read.
+  pub fn is_finished(&self) -> bool {
+    self.finished
+  }
+
+  /// Read the next complete line, if one is available, without blocking.
+  fn read_line(&mut self) -> io::Result<Option<String>> {
+    if self.reader.is_none() {
+      match File::open(&self.path) {
+        Ok(file) => self.reader = Some(BufReader::new(file)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+      }
+    }
+    let Some(reader) = self.reader.as_mut() else {
+      return Ok(None);
+    };
+
+    // The file was truncated, e.g. because the job was restarted
+    let position = reader.stream_position()?;
+    if reader.get_ref().metadata()?.len() < position {
+      reader.seek(SeekFrom::Start(0))?;
+      self.partial_line.clear();
+      self.block.clear();
+    }
+
+    let mut buf = Vec::new();
+    reader.read_until(b'\n', &mut buf)?;
+    if buf.is_empty() {
+      return Ok(None);
+    }
+    self.last_data = Instant::now();
+    self.partial_line.push_str(&String::from_utf8_lossy(&buf));
+    match self.partial_line.ends_with('\n') {
+      true => Ok(Some(std::mem::take(&mut self.partial_line))),
+      false => Ok(None),
+    }
+  }
+}
+
+impl Iterator for ProgressFileReader {
+  type Item = FfmpegProgress;
+
+  fn next(&mut self) -> Option<Self::Item> {
+    while !self.finished {
+      match self.read_line() {
+        Ok(Some(line)) => {
+          let line = line.trim();
+          self.block.push(line.to_string());
+          if let Some(status) = line.strip_prefix("progress=") {
+            self.finished = status == "end";
+            let block = std::mem::take(&mut self.block).join("\n");
+            if let Some(progress) = parse_progress_block(&block) {
+              return Some(progress);
+            }
+          }
+        }
+        Ok(None) => {
+          if self
+            .idle_timeout
+            .is_some_and(|timeout| self.last_data.elapsed() >= timeout)
+          {
+            return None;
+          }
+          std::thread::sleep(self.poll_interval);
+        }
+        Err(_) => return None,
+      }
+    }
+    None
+  }
+}
+
+/// Parse a single block of `key=value` lines written by `-progress`, ending
+/// with `progress=continue` or `progress=end`.
+///
+/// ```rust
+/// use ffmpeg_sidecar::{progress_file::parse_progress_block, timestamp::Timestamp};
+///
+/// let block = "frame=120\nfps=29.97\nstream_0_0_q=28.0\nbitrate= 524.3kbits/s\ntotal_size=262144\nout_time_us=4000000\nout_time_ms=4000000\nout_time=00:00:04.000000\ndup_frames=1\ndrop_frames=0\nspeed=2.01x\nprogress=continue";
+/// let progress = parse_progress_block(block).unwrap();
+/// assert_eq!(progress.frame, Some(120));
+/// assert_eq!(progress.q, Some(28.0));
+/// assert_eq!(progress.size_kb, Some(256));
+/// assert_eq!(progress.out_time, Some(Timestamp::from_secs_f64(4.0)));
+/// assert_eq!(progress.dup_frames, 1);
+/// assert_eq!(progress.speed, 2.01);
+/// ```
+pub fn parse_progress_block(block: &str) -> Option<FfmpegProgress> {
+  let value = |key: &str| {
+    block
+      .lines()
+      .filter_map(|line| line.trim().split_once('='))
+      .find(|(k, _)| *k == key)
+      .map(|(_, v)| v.trim())
+      .filter(|v| *v != "N/A")
+  };
+
+  let time = value("out_time")?.to_string();
+  let q = block
+    .lines()
+    .filter_map(|line| line.trim().split_once('='))
+    .find(|(k, _)| k.starts_with("stream_") && k.ends_with("_q"))
+    .and_then(|(_, v)| v.trim().parse::<f32>().ok());
+  let out_time = value("out_time_us")
+    .and_then(|us| us.parse::<i64>().ok())
+    .map(|us| Timestamp::new(us < 0, Duration::from_micros(us.unsigned_abs())))
+    .or_else(|| time.parse().ok())
+    .filter(|t| !t.is_negative());
+
+  Some(FfmpegProgress {
+    frame: value("frame").and_then(|s| s.parse().ok()),
+    fps: value("fps").and_then(|s| s.parse().ok()),
+    q,
+    size_kb: value("total_size")
+      .and_then(|s| s.parse::<u64>().ok())
+      .map(|bytes| (bytes / 1024) as u32),
+    time,
+    out_time,
+    bitrate_kbps: value("bitrate")
+      .and_then(|s| s.strip_suffix("kbits/s"))
+      .and_then(|s| s.trim().parse().ok()),
+    dup_frames: value("dup_frames")
+      .and_then(|s| s.parse().ok())
+      .unwrap_or(0),
+    drop_frames: value("drop_frames")
+      .and_then(|s| s.parse().ok())
+      .unwrap_or(0),
+    speed: value("speed")
+      .and_then(|s| s.strip_suffix('x'))
+      .and_then(|s| s.trim().parse().ok())
+      .unwrap_or(0.0),
+    raw_log_message: block.to_string(),
+  })
+}
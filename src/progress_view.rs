@@ -0,0 +1,142 @@
+//! Progress which makes sense whether or not the job has an end, e.g. a
+//! recording from a camera or a live stream, where a percentage can't be
+//! computed.
+//!
+//! ```rust,no_run
+//! use ffmpeg_sidecar::{
+//!   command::FfmpegCommand,
+//!   progress_view::{ProgressTracker, ProgressView},
+//! };
+//! use std::time::Instant;
+//!
+//! let mut command = FfmpegCommand::new();
+//! command.input("rtmp://localhost/live/stream").output("recording.mp4");
+//! let mut tracker = ProgressTracker::for_command(&command);
+//! for event in command.spawn().unwrap().iter().unwrap() {
+//!   match tracker.offer(&event, Instant::now()) {
+//!     Some(ProgressView::Bounded { percent, eta, .. }) => println!("{percent:.0}%, {eta:?} left"),
+//!     Some(ProgressView::Unbounded { elapsed, size_kb, .. }) => {
+//!       println!("recording for {elapsed:?}, {size_kb:?} kB")
+//!     }
+//!     None => {}
+//!   }
+//! }
+//! ```
+
+use std::{
+  ffi::OsStr,
+  time::{Duration, Instant},
+};
+
+use crate::{
+  command::FfmpegCommand, event::FfmpegEvent, integrity::trimmed_duration, timestamp::Timestamp,
+};
+
+/// A progress update, as a percentage if the duration of the output is
+/// known, or else as what was recorded so far.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProgressView {
+  /// The output has a known duration, from its input or from `-t`/`-to`.
+  Bounded {
+    /// From 0 to 100, see
+    /// [`FfmpegProgress::percent`](crate::event::FfmpegProgress::percent).
+    percent: f64,
+    /// See [`FfmpegProgress::eta`](crate::event::FfmpegProgress::eta).
+    eta: Option<Duration>,
+    out_time: Option<Timestamp>,
+    /// The expected duration of the output.
+    total: Timestamp,
+    /// The wall clock time since the job started.
+    elapsed: Duration,
+  },
+  /// The output goes on until ffmpeg is stopped, e.g. recording a live
+  /// input.
+  Unbounded {
+    /// The wall clock time since the job started.
+    elapsed: Duration,
+    out_time: Option<Timestamp>,
+    /// The current bitrate, in kbit/s.
+    bitrate_kbps: Option<f32>,
+    /// The size of the output so far, in kilobytes.
+    size_kb: Option<u32>,
+    /// The video frames written so far.
+    frames: Option<u32>,
+    fps: Option<f32>,
+  },
+}
+
+/// Turns the progress updates of a job into [`ProgressView`]s, keeping
+/// track of the duration of its first input and of when it started.
+///
+/// The output is bounded if the duration of the input is known, or if the
+/// arguments limit it with `-t` or `-to`, like a live capture of a fixed
+/// length. See the [module documentation](self) for an example.
+#[derive(Debug, Clone)]
+pub struct ProgressTracker {
+  args: Vec<String>,
+  input_duration: Option<Timestamp>,
+  start: Instant,
+}
+
+impl ProgressTracker {
+  /// A tracker for a job run with `args`, starting now.
+  pub fn new<S: AsRef<OsStr>>(args: &[S]) -> Self {
+    Self::with_start(args, Instant::now())
+  }
+
+  /// A tracker for a job run with `args`, which started at `start`.
+  pub fn with_start<S: AsRef<OsStr>>(args: &[S], start: Instant) -> Self {
+    Self {
+      args: args
+        .iter()
+        .map(|arg| arg.as_ref().to_string_lossy().into_owned())
+        .collect(),
+      input_duration: None,
+      start,
+    }
+  }
+
+  /// A tracker for `command`, starting now.
+  pub fn for_command(command: &FfmpegCommand) -> Self {
+    Self::new(&command.get_args().collect::<Vec<_>>())
+  }
+
+  /// The expected duration of the output, once the duration of the input
+  /// was parsed or if the arguments limit it, or `None` if it goes on until
+  /// ffmpeg is stopped.
+  ///
+  /// ```rust
+  /// use ffmpeg_sidecar::{progress_view::ProgressTracker, timestamp::Timestamp};
+  ///
+  /// let live = ProgressTracker::new(&["-i", "rtsp://camera/live", "out.mp4"]);
+  /// assert_eq!(live.total_duration(), None);
+  /// let limited = ProgressTracker::new(&["-i", "rtsp://camera/live", "-t", "60", "out.mp4"]);
+  /// assert_eq!(limited.total_duration(), Some(Timestamp::from_secs_f64(60.0)));
+  /// ```
+  pub fn total_duration(&self) -> Option<Timestamp> {
+    let input_duration = self
+      .input_duration
+      .map_or(f64::INFINITY, |duration| duration.as_secs_f64());
+    let total = trimmed_duration(input_duration, &self.args);
+    match total.is_finite() && total > 0.0 {
+      true => Some(Timestamp::from_secs_f64(total)),
+      false => None,
+    }
+  }
+
+  /// Take in `event`, received at `now`, returning its view if it's a
+  /// progress update.
+  pub fn offer(&mut self, event: &FfmpegEvent, now: Instant) -> Option<ProgressView> {
+    match event {
+      FfmpegEvent::ParsedDuration(duration) if duration.input_index == 0 => {
+        self.input_duration = Some(duration.duration);
+        None
+      }
+      FfmpegEvent::Progress(progress) => Some(progress.view(
+        self.total_duration(),
+        now.saturating_duration_since(self.start),
+      )),
+      _ => None,
+    }
+  }
+}
@@ -0,0 +1,381 @@
+//! Informational queries to the ffmpeg binary, like `ffmpeg -protocols` or
+//! `ffmpeg -h filter=scale`, and parsers for their output.
+//!
+//! ```rust,no_run
+//! use ffmpeg_sidecar::query::{ffmpeg_query, filter_help, supported_protocols};
+//!
+//! let layouts = ffmpeg_query(&["-layouts"]).unwrap();
+//! println!("{}", layouts.stdout);
+//!
+//! assert!(supported_protocols().unwrap().supports_input("srt"));
+//! for option in filter_help("scale").unwrap().options {
+//!   println!("{} <{}>: {}", option.name, option.value_type, option.description);
+//! }
+//! ```
+
+#[cfg(feature = "process")]
+use std::{
+  ffi::OsStr,
+  fmt,
+  io::Read,
+  process::{Command, ExitStatus},
+  thread,
+  time::{Duration, Instant},
+};
+
+#[cfg(feature = "process")]
+use crate::{
+  command::force_nocolor,
+  defaults::FfmpegDefaults,
+  paths::ffmpeg_path,
+  spawner::{self, StdioConfig},
+  stdio_policy::StdioPolicy,
+};
+
+/// How long [`ffmpeg_query`] waits for ffmpeg to exit.
+#[cfg(feature = "process")]
+pub const QUERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often a query checks whether ffmpeg exited.
+#[cfg(feature = "process")]
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// What ffmpeg printed for a query, and how it exited.
+#[cfg(feature = "process")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryOutput {
+  pub stdout: String,
+  pub stderr: String,
+  pub status: ExitStatus,
+}
+
+#[cfg(feature = "process")]
+impl QueryOutput {
+  pub fn is_success(&self) -> bool {
+    self.status.success()
+  }
+}
+
+/// Returned (through `anyhow::Error`) by a query which didn't finish within
+/// its timeout. ffmpeg is killed.
+#[cfg(feature = "process")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryTimeout {
+  pub args: Vec<String>,
+  pub timeout: Duration,
+}
+
+#[cfg(feature = "process")]
+impl fmt::Display for QueryTimeout {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "`ffmpeg {}` didn't exit within {:?} and was killed",
+      self.args.join(" "),
+      self.timeout
+    )
+  }
+}
+
+#[cfg(feature = "process")]
+impl std::error::Error for QueryTimeout {}
+
+/// Run ffmpeg with `args` and `-hide_banner`, like `ffmpeg -hide_banner
+/// -layouts`, returning its output. The binary is resolved like
+/// [`FfmpegCommand::new`](crate::command::FfmpegCommand::new).
+///
+/// Nothing is sent on stdin, and ffmpeg is killed after [`QUERY_TIMEOUT`].
+/// A non-zero exit status isn't an error, since some queries exit with one
+/// after printing their output.
+#[cfg(feature = "process")]
+pub fn ffmpeg_query(args: &[&str]) -> anyhow::Result<QueryOutput> {
+  let path = FfmpegDefaults::get()
+    .binary_path
+    .unwrap_or_else(ffmpeg_path);
+  ffmpeg_query_with_path(path, args, QUERY_TIMEOUT)
+}
+
+/// Lower level variant of [`ffmpeg_query`] with a custom path to the ffmpeg
+/// binary and timeout.
+#[cfg(feature = "process")]
+pub fn ffmpeg_query_with_path<S: AsRef<OsStr>>(
+  path: S,
+  args: &[&str],
+  timeout: Duration,
+) -> anyhow::Result<QueryOutput> {
+  let mut command = Command::new(path);
+  command.arg("-hide_banner").args(args);
+  force_nocolor(&mut command);
+  let stdio = StdioConfig {
+    stdin: StdioPolicy::Null,
+    ..StdioConfig::default()
+  };
+  let mut child = spawner::spawn(&mut command, stdio)?;
+
+  // Both pipes are drained while waiting, so ffmpeg never blocks on a full
+  // one
+  let read_all = |pipe: Option<Box<dyn Read + Send>>| {
+    thread::spawn(move || {
+      let mut bytes = Vec::new();
+      if let Some(mut pipe) = pipe {
+        pipe.read_to_end(&mut bytes).ok();
+      }
+      String::from_utf8_lossy(&bytes).into_owned()
+    })
+  };
+  let stdout = read_all(child.take_stdout().map(|pipe| Box::new(pipe) as _));
+  let stderr = read_all(child.take_stderr().map(|pipe| Box::new(pipe) as _));
+
+  let deadline = Instant::now() + timeout;
+  let status = loop {
+    if let Some(status) = child.try_wait()? {
+      break status;
+    }
+    if Instant::now() >= deadline {
+      child.kill().ok();
+      child.wait().ok();
+      return Err(
+        QueryTimeout {
+          args: args.iter().map(|arg| arg.to_string()).collect(),
+          timeout,
+        }
+        .into(),
+      );
+    }
+    thread::sleep(POLL_INTERVAL);
+  };
+  Ok(QueryOutput {
+    stdout: stdout.join().unwrap_or_default(),
+    stderr: stderr.join().unwrap_or_default(),
+    status,
+  })
+}
+
+/// The options of a filter, from `ffmpeg -h filter=<name>`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct FilterHelp {
+  pub name: String,
+  pub description: String,
+  /// Including the options of the components the filter configures, like
+  /// `sws_flags` for `scale`, listed after its own.
+  pub options: Vec<FilterOption>,
+  /// Whether the filter can be turned on and off with the `enable` option.
+  pub timeline: bool,
+}
+
+impl FilterHelp {
+  pub fn option(&self, name: &str) -> Option<&FilterOption> {
+    self.options.iter().find(|option| option.name == name)
+  }
+}
+
+/// An option of a filter, see [`FilterHelp`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct FilterOption {
+  pub name: String,
+  /// Like `int`, `string`, `flags` or `color`.
+  pub value_type: String,
+  /// The letters of the flags column, like `..FV.....T.`, where `T` means it
+  /// can be changed while running.
+  pub flags: String,
+  pub description: String,
+  /// The bounds of `(from X to Y)`.
+  pub range: Option<(String, String)>,
+  /// The value of `(default X)`, quoted for strings, as in `""`.
+  pub default: Option<String>,
+  /// The named values it accepts.
+  pub constants: Vec<OptionConstant>,
+}
+
+/// A named value of a [`FilterOption`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct OptionConstant {
+  pub name: String,
+  /// The number it stands for, missing for `flags` options.
+  pub value: Option<String>,
+  pub description: String,
+}
+
+/// Run `ffmpeg -h filter=<name>` and parse its output, see
+/// [`parse_filter_help`]. Fails if the filter doesn't exist.
+#[cfg(feature = "process")]
+pub fn filter_help(name: &str) -> anyhow::Result<FilterHelp> {
+  let output = ffmpeg_query(&["-h", &format!("filter={name}")])?;
+  parse_filter_help(&output.stdout)
+    .filter(|help| help.name == name)
+    .ok_or_else(|| {
+      let message = format!("{}{}", output.stdout, output.stderr);
+      anyhow::anyhow!("unknown filter {name}: {}", message.trim())
+    })
+}
+
+/// Parse the output of `ffmpeg -h filter=<name>`, or `None` if it's not
+/// the help of a filter.
+///
+/// ```rust
+/// use ffmpeg_sidecar::query::parse_filter_help;
+///
+/// let help = parse_filter_help(
+///   "Filter hflip\n  Horizontally flip the input video.\n    Inputs:\n       #0: default (video)\n\
+///    This filter has support for timeline through the 'enable' option.\n",
+/// )
+/// .unwrap();
+/// assert_eq!(help.name, "hflip");
+/// assert_eq!(help.description, "Horizontally flip the input video.");
+/// assert!(help.options.is_empty());
+/// assert!(help.timeline);
+/// ```
+pub fn parse_filter_help(output: &str) -> Option<FilterHelp> {
+  let mut lines = output.lines();
+  let name = lines.next()?.trim().strip_prefix("Filter ")?.trim();
+  let mut help = FilterHelp {
+    name: name.to_string(),
+    ..Default::default()
+  };
+  let mut in_options = false;
+  for line in lines {
+    let indent = line.len() - line.trim_start().len();
+    let content = line.trim();
+    if content.is_empty() {
+      in_options = false;
+    } else if indent == 0 {
+      in_options = content.ends_with(" AVOptions:");
+      if content.contains("support for timeline") {
+        help.timeline = true;
+      }
+    } else if !in_options {
+      if help.description.is_empty() && indent == 2 {
+        help.description = content.to_string();
+      }
+    } else if indent <= 2 {
+      help.options.extend(parse_option(content));
+    } else if let (Some(option), Some(constant)) =
+      (help.options.last_mut(), parse_constant(content))
+    {
+      option.constants.push(constant);
+    }
+  }
+  Some(help)
+}
+
+/// `name <type> flags description (from X to Y) (default Z)`
+fn parse_option(line: &str) -> Option<FilterOption> {
+  let (name, rest) = next_token(line)?;
+  let (value_type, rest) = next_token(rest)?;
+  let value_type = value_type.strip_prefix('<')?.strip_suffix('>')?;
+  let (flags, rest) = next_token(rest).filter(|(flags, _)| is_flags(flags))?;
+
+  let mut description = rest.trim();
+  let default = strip_parenthesized(&mut description, "default ");
+  let range = strip_parenthesized(&mut description, "from ").and_then(|range| {
+    let (from, to) = range.split_once(" to ")?;
+    Some((from.to_string(), to.to_string()))
+  });
+  Some(FilterOption {
+    name: name.to_string(),
+    value_type: value_type.to_string(),
+    flags: flags.to_string(),
+    description: description.to_string(),
+    range,
+    default,
+    constants: Vec::new(),
+  })
+}
+
+/// `name [value] flags [description]`
+fn parse_constant(line: &str) -> Option<OptionConstant> {
+  let (name, rest) = next_token(line)?;
+  let (value, rest) = match next_token(rest)? {
+    (flags, _) if is_flags(flags) => (None, rest),
+    (value, rest) => (Some(value.to_string()), rest),
+  };
+  let (_, description) = next_token(rest).filter(|(flags, _)| is_flags(flags))?;
+  Some(OptionConstant {
+    name: name.to_string(),
+    value,
+    description: description.trim().to_string(),
+  })
+}
+
+/// The first word of `line`, and what follows it.
+fn next_token(line: &str) -> Option<(&str, &str)> {
+  let line = line.trim_start();
+  let end = line.find(char::is_whitespace).unwrap_or(line.len());
+  match end {
+    0 => None,
+    _ => Some(line.split_at(end)),
+  }
+}
+
+/// The flags column, like `..FV.....T.` or `E..VA......`.
+fn is_flags(token: &str) -> bool {
+  token.len() >= 8
+    && token.contains('.')
+    && token.chars().all(|c| c == '.' || c.is_ascii_uppercase())
+}
+
+/// Remove `(<prefix>...)` from the end of `description`, returning what
+/// follows the prefix.
+fn strip_parenthesized(description: &mut &str, prefix: &str) -> Option<String> {
+  let open = description.rfind(&format!("({prefix}"))?;
+  let value = description[open + 1 + prefix.len()..].strip_suffix(')')?;
+  let value = value.to_string();
+  *description = description[..open].trim_end();
+  Some(value)
+}
+
+/// The protocols of the ffmpeg build, from `ffmpeg -protocols`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Protocols {
+  pub input: Vec<String>,
+  pub output: Vec<String>,
+}
+
+impl Protocols {
+  pub fn supports_input(&self, protocol: &str) -> bool {
+    self.input.iter().any(|name| name == protocol)
+  }
+
+  pub fn supports_output(&self, protocol: &str) -> bool {
+    self.output.iter().any(|name| name == protocol)
+  }
+}
+
+/// Run `ffmpeg -protocols` and parse its output, see [`parse_protocols`].
+#[cfg(feature = "process")]
+pub fn supported_protocols() -> anyhow::Result<Protocols> {
+  let output = ffmpeg_query(&["-protocols"])?;
+  if !output.is_success() {
+    anyhow::bail!("ffmpeg -protocols exited with {}", output.status);
+  }
+  Ok(parse_protocols(&output.stdout))
+}
+
+/// Parse the output of `ffmpeg -protocols`.
+///
+/// ```rust
+/// use ffmpeg_sidecar::query::parse_protocols;
+///
+/// let protocols = parse_protocols("Supported file protocols:\nInput:\n  file\n  srt\nOutput:\n  file\n");
+/// assert_eq!(protocols.input, ["file", "srt"]);
+/// assert!(protocols.supports_input("srt"));
+/// assert!(!protocols.supports_output("srt"));
+/// ```
+pub fn parse_protocols(output: &str) -> Protocols {
+  let mut protocols = Protocols::default();
+  let mut list = None;
+  for line in output.lines() {
+    match line.trim() {
+      "Input:" => list = Some(&mut protocols.input),
+      "Output:" => list = Some(&mut protocols.output),
+      "" => {}
+      name if line.starts_with(char::is_whitespace) => {
+        if let Some(list) = &mut list {
+          list.push(name.to_string());
+        }
+      }
+      _ => list = None,
+    }
+  }
+  protocols
+}
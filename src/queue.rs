@@ -0,0 +1,204 @@
+//! Running many ffmpeg commands with a limit on how many run at once.
+
+use std::{
+  process::ExitStatus,
+  sync::{
+    atomic::{AtomicUsize, Ordering},
+    Mutex,
+  },
+};
+
+use crate::{
+  command::FfmpegCommand,
+  cpu_budget::CpuBudget,
+  event::{FfmpegEvent, LogLevel},
+  summary::FfmpegSummary,
+};
+
+/// Runs ffmpeg commands on a fixed number of worker threads, so that no more
+/// than `max_concurrent` ffmpeg processes are alive at once.
+///
+/// ```rust,no_run
+/// use ffmpeg_sidecar::{command::FfmpegCommand, event::FfmpegEvent, queue::JobQueue};
+///
+/// let commands = ["a.mp4", "b.mp4", "c.mp4"].map(|input| {
+///   let mut command = FfmpegCommand::new();
+///   command.input(input).overwrite().output(input.replace(".mp4", ".mkv"));
+///   command
+/// });
+///
+/// let outcomes = JobQueue::new(2).run_with_events(commands, |job, event| {
+///   if let FfmpegEvent::Progress(progress) = event {
+///     println!("job {job}: {}", progress.time);
+///   }
+/// });
+/// assert!(outcomes.iter().all(|outcome| outcome.is_success()));
+/// ```
+///
+/// With several jobs at once, each ffmpeg should only use its share of the
+/// cores, e.g. 2 threads each for 4 jobs on 8 cores:
+///
+/// ```rust,no_run
+/// use ffmpeg_sidecar::{command::FfmpegCommand, cpu_budget::CpuBudget, queue::JobQueue};
+///
+/// let commands = ["a.mov", "b.mov", "c.mov", "d.mov", "e.mov"].map(|input| {
+///   let mut command = FfmpegCommand::new();
+///   command.input(input).codec_video("libx264").output(input.replace(".mov", ".mp4"));
+///   command
+/// });
+/// // All the cores, shared between the 4 jobs running at once
+/// let outcomes = JobQueue::new(4).cpu_budget(CpuBudget::Fraction(1.0)).run(commands);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct JobQueue {
+  max_concurrent: usize,
+  /// The cores shared between the jobs, or 0 to let ffmpeg pick
+  cpu_budget: Option<usize>,
+}
+
+/// The result of a single command run by a [`JobQueue`].
+#[derive(Debug)]
+pub struct JobOutcome {
+  /// The exit status, or the error which prevented the process from being
+  /// spawned or waited on.
+  pub result: anyhow::Result<ExitStatus>,
+  /// Error messages logged by ffmpeg.
+  pub errors: Vec<String>,
+  /// Statistics and classified errors from the run.
+  pub summary: FfmpegSummary,
+}
+
+impl JobOutcome {
+  /// Whether ffmpeg ran and exited successfully.
+  pub fn is_success(&self) -> bool {
+    matches!(&self.result, Ok(status) if status.success())
+  }
+}
+
+impl JobQueue {
+  /// Creates a queue running at most `max_concurrent` commands at a time (at
+  /// least 1).
+  pub fn new(max_concurrent: usize) -> Self {
+    Self {
+      max_concurrent: max_concurrent.max(1),
+      cpu_budget: None,
+    }
+  }
+
+  pub fn max_concurrent(&self) -> usize {
+    self.max_concurrent
+  }
+
+  /// Share `budget` equally between the jobs running at once, which is
+  /// `max_concurrent`, or the number of commands if there are fewer. Each
+  /// command gets its share as `-threads` for the outputs added with
+  /// [`output`](FfmpegCommand::output), and as `-filter_threads` and
+  /// `-filter_complex_threads`, except for the options it already sets.
+  ///
+  /// The share is computed once when the queue starts, and isn't raised for
+  /// the last jobs when the others are done.
+  pub fn cpu_budget<B: Into<CpuBudget>>(mut self, budget: B) -> Self {
+    self.cpu_budget = Some(budget.into().cores());
+    self
+  }
+
+  /// Run every command to completion, blocking until all have finished.
+  /// Outcomes are returned in the same order as the commands.
+  pub fn run<I: IntoIterator<Item = FfmpegCommand>>(&self, commands: I) -> Vec<JobOutcome> {
+    self.run_with_events(commands, |_, _| {})
+  }
+
+  /// Like [`JobQueue::run`], additionally passing every event to `on_event`
+  /// along with the index of the command it came from. Called from the
+  /// worker threads, possibly concurrently.
+  pub fn run_with_events<I, F>(&self, commands: I, on_event: F) -> Vec<JobOutcome>
+  where
+    I: IntoIterator<Item = FfmpegCommand>,
+    F: Fn(usize, &FfmpegEvent) + Sync,
+  {
+    let jobs = commands
+      .into_iter()
+      .map(|command| Mutex::new(Some(command)))
+      .collect::<Vec<_>>();
+    let outcomes = jobs.iter().map(|_| Mutex::new(None)).collect::<Vec<_>>();
+    let next = AtomicUsize::new(0);
+    let threads = self
+      .cpu_budget
+      .map(|cores| CpuBudget::Cores(cores).threads_per_job(self.max_concurrent.min(jobs.len())));
+
+    std::thread::scope(|scope| {
+      for _ in 0..self.max_concurrent.min(jobs.len()) {
+        scope.spawn(|| loop {
+          let index = next.fetch_add(1, Ordering::SeqCst);
+          let Some(job) = jobs.get(index) else {
+            break;
+          };
+          let command = job.lock().ok().and_then(|mut command| command.take());
+          if let Some(mut command) = command {
+            if let Some(threads) = threads {
+              command.limit_threads(threads);
+            }
+            let outcome = run_job(&mut command, |event| on_event(index, event));
+            if let Ok(mut slot) = outcomes[index].lock() {
+              *slot = Some(outcome);
+            }
+          }
+        });
+      }
+    });
+
+    outcomes
+      .into_iter()
+      .map(|slot| {
+        slot
+          .into_inner()
+          .ok()
+          .flatten()
+          .unwrap_or_else(|| JobOutcome {
+            result: Err(anyhow::anyhow!("job did not run")),
+            errors: Vec::new(),
+            summary: FfmpegSummary::new(),
+          })
+      })
+      .collect()
+  }
+}
+
+pub(crate) fn run_job<F: Fn(&FfmpegEvent)>(command: &mut FfmpegCommand, on_event: F) -> JobOutcome {
+  let mut errors = Vec::new();
+  let mut child = match command.spawn() {
+    Ok(child) => child,
+    Err(e) => {
+      return JobOutcome {
+        result: Err(e.into()),
+        errors,
+        summary: FfmpegSummary::new(),
+      }
+    }
+  };
+
+  // Nothing is sent over stdin, and closing it means ffmpeg exits instead of
+  // waiting forever if it asks for confirmation (e.g. to overwrite a file).
+  drop(child.take_stdin());
+
+  match child.iter() {
+    Ok(iter) => {
+      for event in iter {
+        on_event(&event);
+        match event {
+          FfmpegEvent::Error(e) | FfmpegEvent::Log(LogLevel::Error | LogLevel::Fatal, e) => {
+            errors.push(e)
+          }
+          _ => {}
+        }
+      }
+    }
+    Err(e) => errors.push(e.to_string()),
+  }
+
+  JobOutcome {
+    result: child.wait().map_err(anyhow::Error::from),
+    errors,
+    summary: child.summary(),
+  }
+}
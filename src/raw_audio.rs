@@ -0,0 +1,195 @@
+//! Encoding PCM audio generated in memory, written to ffmpeg's stdin, with
+//! [`FfmpegCommand::input_raw_audio`](crate::command::FfmpegCommand::input_raw_audio)
+//! and a [`StdinAudioWriter`].
+
+use std::{
+  io::{self, BufWriter, Write},
+  process::ChildStdin,
+  time::Duration,
+};
+
+use crate::audio::SampleFormat;
+
+/// The layout of raw PCM audio: interleaved samples of a sample format, in
+/// little-endian byte order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RawAudioFormat {
+  sample_format: SampleFormat,
+  muxer: &'static str,
+  sample_rate: u32,
+  channels: u8,
+}
+
+impl RawAudioFormat {
+  /// Returns an error for planar sample formats, since raw PCM is always
+  /// interleaved.
+  ///
+  /// ```rust
+  /// use ffmpeg_sidecar::{audio::SampleFormat, raw_audio::RawAudioFormat};
+  ///
+  /// let format = RawAudioFormat::new(SampleFormat::F32, 48000, 2).unwrap();
+  /// assert_eq!(format.input_args(), ["-f", "f32le", "-ar", "48000", "-ac", "2"]);
+  /// assert!(RawAudioFormat::new(SampleFormat::F32p, 48000, 2).is_err());
+  /// ```
+  pub fn new(sample_format: SampleFormat, sample_rate: u32, channels: u8) -> anyhow::Result<Self> {
+    let Some(muxer) = sample_format.pcm_muxer() else {
+      anyhow::bail!(
+        "Raw PCM input is interleaved; planar sample format `{sample_format}` is not supported"
+      );
+    };
+    Ok(Self {
+      sample_format,
+      muxer,
+      sample_rate,
+      channels,
+    })
+  }
+
+  pub fn sample_format(&self) -> SampleFormat {
+    self.sample_format
+  }
+
+  pub fn sample_rate(&self) -> u32 {
+    self.sample_rate
+  }
+
+  pub fn channels(&self) -> u8 {
+    self.channels
+  }
+
+  /// The input options reading this layout, before `-i`.
+  pub fn input_args(&self) -> Vec<String> {
+    vec![
+      "-f".to_string(),
+      self.muxer.to_string(),
+      "-ar".to_string(),
+      self.sample_rate.to_string(),
+      "-ac".to_string(),
+      self.channels.to_string(),
+    ]
+  }
+
+  /// The size of a frame, one sample of each channel, in bytes.
+  pub fn bytes_per_frame(&self) -> usize {
+    self.sample_format.bytes_per_sample() as usize * self.channels as usize
+  }
+}
+
+/// Writes PCM audio to ffmpeg's stdin, checking that only whole frames (one
+/// sample of each channel) are written, and counting them. Returned by
+/// [`FfmpegChild::audio_writer`](crate::child::FfmpegChild::audio_writer).
+///
+/// Writes are buffered. Stdin is closed by [`finish`](Self::finish), or
+/// when the writer is dropped, which tells ffmpeg the input ended so it
+/// finalizes the output.
+///
+/// ```rust
+/// use ffmpeg_sidecar::{audio::SampleFormat, raw_audio::{RawAudioFormat, StdinAudioWriter}};
+///
+/// let format = RawAudioFormat::new(SampleFormat::S16, 8000, 2).unwrap();
+/// let mut bytes = Vec::new();
+/// let mut writer = StdinAudioWriter::new(&mut bytes, format);
+/// writer.write_samples(&[0.0, 1.0, -1.0, 0.5]).unwrap();
+/// assert!(writer.write_samples(&[0.0]).is_err());
+/// assert_eq!(writer.finish().unwrap(), 2);
+/// assert_eq!(bytes, [0, 0, 0xff, 0x7f, 0x01, 0x80, 0x00, 0x40]);
+/// ```
+#[derive(Debug)]
+pub struct StdinAudioWriter<W: Write = ChildStdin> {
+  writer: BufWriter<W>,
+  format: RawAudioFormat,
+  frames: u64,
+}
+
+impl<W: Write> StdinAudioWriter<W> {
+  pub fn new(writer: W, format: RawAudioFormat) -> Self {
+    Self {
+      writer: BufWriter::new(writer),
+      format,
+      frames: 0,
+    }
+  }
+
+  pub fn format(&self) -> RawAudioFormat {
+    self.format
+  }
+
+  /// Write interleaved samples between -1.0 and 1.0, converted to the
+  /// sample format. Samples out of that range are clipped in integer
+  /// formats.
+  ///
+  /// Fails with an `InvalidInput` error, writing nothing, unless the number
+  /// of samples is a multiple of the number of channels.
+  pub fn write_samples(&mut self, samples: &[f32]) -> io::Result<()> {
+    let channels = self.format.channels.max(1) as usize;
+    if !samples.len().is_multiple_of(channels) {
+      return Err(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!(
+          "{} samples aren't whole frames of {channels} channels",
+          samples.len()
+        ),
+      ));
+    }
+    let mut bytes =
+      Vec::with_capacity(samples.len() * self.format.sample_format.bytes_per_sample() as usize);
+    for sample in samples {
+      encode_sample(*sample, self.format.sample_format, &mut bytes);
+    }
+    self.writer.write_all(&bytes)?;
+    self.frames += (samples.len() / channels) as u64;
+    Ok(())
+  }
+
+  /// Write samples already in the sample format, interleaved and
+  /// little-endian.
+  ///
+  /// Fails with an `InvalidInput` error, writing nothing, unless the bytes
+  /// are whole frames.
+  pub fn write_bytes(&mut self, bytes: &[u8]) -> io::Result<()> {
+    let frame = self.format.bytes_per_frame().max(1);
+    if !bytes.len().is_multiple_of(frame) {
+      return Err(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("{} bytes aren't whole frames of {frame} bytes", bytes.len()),
+      ));
+    }
+    self.writer.write_all(bytes)?;
+    self.frames += (bytes.len() / frame) as u64;
+    Ok(())
+  }
+
+  /// How many samples of each channel were written.
+  pub fn samples_written(&self) -> u64 {
+    self.frames
+  }
+
+  /// The duration of the audio written, which is the duration of the output
+  /// when nothing trims it.
+  pub fn duration(&self) -> Duration {
+    match self.format.sample_rate {
+      0 => Duration::ZERO,
+      rate => Duration::from_secs_f64(self.frames as f64 / rate as f64),
+    }
+  }
+
+  /// Flush the buffered samples and close stdin, returning how many
+  /// samples of each channel were written.
+  pub fn finish(mut self) -> io::Result<u64> {
+    self.writer.flush()?;
+    Ok(self.frames)
+  }
+}
+
+fn encode_sample(sample: f32, format: SampleFormat, bytes: &mut Vec<u8>) {
+  let clipped = sample.clamp(-1.0, 1.0) as f64;
+  match format {
+    SampleFormat::U8 => bytes.push((clipped * 127.0 + 128.0).round() as u8),
+    SampleFormat::S16 => bytes.extend(((clipped * i16::MAX as f64).round() as i16).to_le_bytes()),
+    SampleFormat::S32 => bytes.extend(((clipped * i32::MAX as f64).round() as i32).to_le_bytes()),
+    SampleFormat::S64 => bytes.extend(((clipped * i64::MAX as f64).round() as i64).to_le_bytes()),
+    SampleFormat::F32 => bytes.extend(sample.to_le_bytes()),
+    // F64, the only other format of raw PCM
+    _ => bytes.extend((sample as f64).to_le_bytes()),
+  }
+}
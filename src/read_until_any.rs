@@ -5,6 +5,19 @@ pub fn read_until_any<R: BufRead + ?Sized>(
   r: &mut R,
   delims: &[u8],
   buf: &mut Vec<u8>,
+) -> Result<usize> {
+  read_until_any_or(r, delims, buf, |_| false)
+}
+
+/// Like [`read_until_any`], also returning without a delimiter once the
+/// bytes read so far satisfy `complete`, instead of blocking for more. Used
+/// for prompts, which are written without a line ending before the writer
+/// waits for an answer.
+pub fn read_until_any_or<R: BufRead + ?Sized>(
+  r: &mut R,
+  delims: &[u8],
+  buf: &mut Vec<u8>,
+  complete: impl Fn(&[u8]) -> bool,
 ) -> Result<usize> {
   let mut read = 0;
   loop {
@@ -16,9 +29,7 @@ pub fn read_until_any<R: BufRead + ?Sized>(
       };
 
       // NB: `memchr` crate would be faster, but it's unstable and not worth the dependency.
-      let first_delim_index = available
-        .iter()
-        .position(|b| delims.iter().any(|d| *d == *b));
+      let first_delim_index = available.iter().position(|b| delims.contains(b));
 
       match first_delim_index {
         Some(i) => {
@@ -33,7 +44,7 @@ pub fn read_until_any<R: BufRead + ?Sized>(
     };
     r.consume(used);
     read += used;
-    if done || used == 0 {
+    if done || used == 0 || complete(buf) {
       return Ok(read);
     }
   }
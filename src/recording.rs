@@ -0,0 +1,251 @@
+//! A flight recorder for ffmpeg sessions: the command line, every event and
+//! the exit status, written as JSON lines to a file which can be attached to
+//! a bug report, and read back with [`SessionReplay`]. See
+//! [`FfmpegChild::record_jsonl`](crate::child::FfmpegChild::record_jsonl).
+//!
+//! Each line is a [`SessionRecord`], like:
+//!
+//! ```json
+//! {"record":"spawn","time":{"wall_ms":1718000000000,"elapsed":0.001},"command_line":["ffmpeg","-i","in.mp4","out.mp4"]}
+//! {"record":"event","time":{"wall_ms":1718000000012,"elapsed":0.013},"event":{"Log":["Info","[info] Press [q] to stop, [?] for help"]}}
+//! {"record":"exit","time":{"wall_ms":1718000002345,"elapsed":2.346},"code":0,"success":true,"runtime":2.346}
+//! ```
+
+use std::{fs, path::Path};
+#[cfg(feature = "process")]
+use std::{
+  fs::File,
+  io::{self, Write},
+  process::ExitStatus,
+  sync::Mutex,
+  time::{Instant, SystemTime, UNIX_EPOCH},
+};
+
+use crate::event::FfmpegEvent;
+#[cfg(feature = "process")]
+use crate::event::OutputVideoFrame;
+
+/// When a record was written.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct RecordTime {
+  /// Milliseconds since the Unix epoch, from the system clock.
+  pub wall_ms: u64,
+  /// Seconds since ffmpeg was spawned, from a monotonic clock, so it can't
+  /// jump when the system clock is adjusted.
+  pub elapsed: f64,
+}
+
+/// A line of a session recording.
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(tag = "record", rename_all = "snake_case")]
+#[allow(clippy::large_enum_variant)] // Almost all the records are events
+pub enum SessionRecord {
+  /// The first record, with the program and its arguments.
+  Spawn {
+    time: RecordTime,
+    command_line: Vec<String>,
+  },
+  /// An event, as returned by the iterator of the child.
+  Event {
+    time: RecordTime,
+    /// The event, without the data of [`FfmpegEvent::OutputFrame`] and
+    /// [`FfmpegEvent::OutputChunk`], which is left empty.
+    event: FfmpegEvent,
+    /// The size of the data left out of the event, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    omitted_bytes: Option<usize>,
+  },
+  /// The last record, written when the child is waited on.
+  Exit {
+    time: RecordTime,
+    /// The exit code, or `None` if ffmpeg was killed by a signal.
+    code: Option<i32>,
+    success: bool,
+    /// Seconds from spawning to the exit.
+    runtime: f64,
+  },
+}
+
+/// A session recording read back from a file, e.g. to inspect the events of
+/// a failed job in a tool.
+///
+/// ```rust,no_run
+/// use ffmpeg_sidecar::{event::FfmpegEvent, recording::SessionReplay};
+///
+/// let replay = SessionReplay::load("session.jsonl").unwrap();
+/// println!("{}", replay.command_line().unwrap_or_default().join(" "));
+/// for event in replay.events() {
+///   if let FfmpegEvent::Log(level, line) = event {
+///     println!("{level:?}: {line}");
+///   }
+/// }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SessionReplay {
+  records: Vec<SessionRecord>,
+}
+
+impl SessionReplay {
+  /// Read a recording. A last line which was cut off, because the program
+  /// crashed while writing it, is skipped.
+  pub fn load<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
+    let path = path.as_ref();
+    let contents = fs::read_to_string(path)?;
+    Self::parse(&contents)
+      .map_err(|e| anyhow::anyhow!("failed to read the recording {}: {e}", path.display()))
+  }
+
+  /// Parse the contents of a recording, see [`load`](Self::load).
+  pub fn parse(contents: &str) -> anyhow::Result<Self> {
+    let lines = contents
+      .lines()
+      .enumerate()
+      .filter(|(_, line)| !line.trim().is_empty())
+      .collect::<Vec<_>>();
+    let mut records = Vec::with_capacity(lines.len());
+    for (position, (number, line)) in lines.iter().enumerate() {
+      match serde_json::from_str(line) {
+        Ok(record) => records.push(record),
+        Err(_) if position + 1 == lines.len() && !contents.ends_with('\n') => break,
+        Err(e) => anyhow::bail!("line {}: {e}", number + 1),
+      }
+    }
+    Ok(Self { records })
+  }
+
+  pub fn records(&self) -> &[SessionRecord] {
+    &self.records
+  }
+
+  /// The program and its arguments.
+  pub fn command_line(&self) -> Option<&[String]> {
+    self.records.iter().find_map(|record| match record {
+      SessionRecord::Spawn { command_line, .. } => Some(command_line.as_slice()),
+      _ => None,
+    })
+  }
+
+  /// The events, in the order they were received.
+  pub fn events(&self) -> impl Iterator<Item = &FfmpegEvent> {
+    self.records.iter().filter_map(|record| match record {
+      SessionRecord::Event { event, .. } => Some(event),
+      _ => None,
+    })
+  }
+
+  /// The exit record, or `None` if the recording stops before ffmpeg
+  /// exited, e.g. because the program crashed.
+  pub fn exit(&self) -> Option<&SessionRecord> {
+    self
+      .records
+      .iter()
+      .rev()
+      .find(|record| matches!(record, SessionRecord::Exit { .. }))
+  }
+}
+
+impl IntoIterator for SessionReplay {
+  type Item = FfmpegEvent;
+  type IntoIter = std::iter::FilterMap<
+    std::vec::IntoIter<SessionRecord>,
+    fn(SessionRecord) -> Option<FfmpegEvent>,
+  >;
+
+  /// The events, like [`SessionReplay::events`].
+  fn into_iter(self) -> Self::IntoIter {
+    self.records.into_iter().filter_map(|record| match record {
+      SessionRecord::Event { event, .. } => Some(event),
+      _ => None,
+    })
+  }
+}
+
+/// Writes the records of a child, each flushed to the file as a whole line
+/// right away.
+#[cfg(feature = "process")]
+pub(crate) struct SessionRecorder {
+  file: Mutex<File>,
+  spawned_at: Instant,
+  /// Set once the exit is recorded, since a child can be waited on again
+  exited: Mutex<bool>,
+}
+
+#[cfg(feature = "process")]
+impl SessionRecorder {
+  /// Create the file, starting with the spawn record.
+  pub(crate) fn create(
+    path: &Path,
+    spawned_at: Instant,
+    command_line: &[String],
+  ) -> io::Result<Self> {
+    let recorder = Self {
+      file: Mutex::new(File::create(path)?),
+      spawned_at,
+      exited: Mutex::new(false),
+    };
+    recorder.write(&SessionRecord::Spawn {
+      time: recorder.now(),
+      command_line: command_line.to_vec(),
+    })?;
+    Ok(recorder)
+  }
+
+  fn now(&self) -> RecordTime {
+    RecordTime {
+      wall_ms: SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |since| since.as_millis() as u64),
+      elapsed: self.spawned_at.elapsed().as_secs_f64(),
+    }
+  }
+
+  fn write(&self, record: &SessionRecord) -> io::Result<()> {
+    let mut line = serde_json::to_string(record)?;
+    line.push('\n');
+    let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+    file.write_all(line.as_bytes())?;
+    file.flush()
+  }
+
+  /// Record an event, leaving out its frame data. Failures to write are
+  /// ignored, so the recording never stops the events.
+  pub(crate) fn record_event(&self, event: &FfmpegEvent) {
+    let (event, omitted_bytes) = match event {
+      FfmpegEvent::OutputFrame(frame) => (
+        FfmpegEvent::OutputFrame(OutputVideoFrame {
+          pix_fmt: frame.pix_fmt.clone(),
+          data: Vec::new(),
+          ..*frame
+        }),
+        Some(frame.data.len()),
+      ),
+      FfmpegEvent::OutputChunk(chunk) => (FfmpegEvent::OutputChunk(Vec::new()), Some(chunk.len())),
+      event => (event.clone(), None),
+    };
+    self
+      .write(&SessionRecord::Event {
+        time: self.now(),
+        event,
+        omitted_bytes,
+      })
+      .ok();
+  }
+
+  /// Record the exit, the first time only.
+  pub(crate) fn record_exit(&self, status: ExitStatus) {
+    let mut exited = self.exited.lock().unwrap_or_else(|e| e.into_inner());
+    if *exited {
+      return;
+    }
+    *exited = true;
+    let time = self.now();
+    self
+      .write(&SessionRecord::Exit {
+        time,
+        code: status.code(),
+        success: status.success(),
+        runtime: time.elapsed,
+      })
+      .ok();
+  }
+}
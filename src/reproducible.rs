@@ -0,0 +1,82 @@
+//! Byte-for-byte reproducible outputs, e.g. for golden-file tests. See
+//! [`FfmpegCommand::reproducible`](crate::command::FfmpegCommand::reproducible).
+//!
+//! By default ffmpeg writes the version of libavformat and of each encoder
+//! into the output, along with the time of the encode in some formats, so
+//! the same encode differs between runs and ffmpeg builds.
+//!
+//! Encoders which split the work between threads may also depend on the
+//! thread count, which defaults to the number of CPU cores. Among others,
+//! libx264 and libx265 (frame threads), libvpx and libaom (tiles and row
+//! multithreading), and libsvtav1 give different results on machines with a
+//! different number of cores, and only match with
+//! [`ReproducibleOptions::strict_single_thread`]. The native encoders
+//! (`mpeg4`, `aac`, `flac`, `pcm_*`, ...) are stable either way. Outputs
+//! are only comparable between identical ffmpeg and encoder versions.
+
+/// The `creation_time` written by default, the Unix epoch.
+pub const DEFAULT_CREATION_TIME: &str = "1970-01-01T00:00:00.000000Z";
+
+/// Options for [`FfmpegCommand::reproducible_with`](crate::command::FfmpegCommand::reproducible_with).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ReproducibleOptions {
+  /// Encode with a single thread (`-threads 1`), so encoders whose output
+  /// depends on the thread count give the same result on every machine.
+  /// Much slower for the encoders which would use several threads.
+  pub strict_single_thread: bool,
+  /// The value of the `creation_time` tag, instead of the time of the
+  /// encode.
+  pub creation_time: String,
+}
+
+impl Default for ReproducibleOptions {
+  fn default() -> Self {
+    Self {
+      strict_single_thread: false,
+      creation_time: DEFAULT_CREATION_TIME.to_string(),
+    }
+  }
+}
+
+impl ReproducibleOptions {
+  /// The output arguments, to be followed by the output path.
+  ///
+  /// ```rust
+  /// use ffmpeg_sidecar::reproducible::ReproducibleOptions;
+  ///
+  /// let options = ReproducibleOptions {
+  ///   strict_single_thread: true,
+  ///   creation_time: "2024-01-01T00:00:00Z".to_string(),
+  /// };
+  /// assert_eq!(
+  ///   options.to_args(),
+  ///   [
+  ///     "-map_metadata", "-1", "-fflags", "+bitexact", "-flags:v", "+bitexact", "-flags:a",
+  ///     "+bitexact", "-metadata", "creation_time=2024-01-01T00:00:00Z", "-threads", "1"
+  ///   ]
+  /// );
+  /// ```
+  pub fn to_args(&self) -> Vec<String> {
+    let mut args = [
+      "-map_metadata",
+      "-1",
+      // No muxer version, or time of the encode
+      "-fflags",
+      "+bitexact",
+      // No encoder versions, and bit-exact algorithms in the native codecs
+      "-flags:v",
+      "+bitexact",
+      "-flags:a",
+      "+bitexact",
+    ]
+    .map(String::from)
+    .to_vec();
+    args.push("-metadata".to_string());
+    args.push(format!("creation_time={}", self.creation_time));
+    if self.strict_single_thread {
+      args.push("-threads".to_string());
+      args.push("1".to_string());
+    }
+    args
+  }
+}
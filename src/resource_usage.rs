@@ -0,0 +1,304 @@
+//! CPU and memory used by a running ffmpeg process. See
+//! [`FfmpegChild::resource_usage`](crate::child::FfmpegChild::resource_usage)
+//! and [`FfmpegChild::sample_interval`](crate::child::FfmpegChild::sample_interval).
+
+use std::time::Duration;
+
+/// A measurement of the resources used by an ffmpeg process.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ResourceUsage {
+  /// CPU time used since the previous measurement (or since the process was
+  /// spawned, for the first one), as a percentage of the wall time between
+  /// them. Like in `top`, this counts a fully used core as 100%, so a
+  /// multithreaded encoder can exceed 100%.
+  pub cpu_percent: f64,
+  /// Physical memory used by the process (its resident set size, or working
+  /// set on Windows).
+  pub rss_bytes: u64,
+  /// Time since the process was spawned.
+  pub elapsed: Duration,
+}
+
+#[cfg(feature = "process")]
+use std::{io, time::Instant};
+
+/// Computes the CPU percentage of a process from the difference between two
+/// readings of its cumulative CPU time.
+#[cfg(feature = "process")]
+#[derive(Debug, Clone)]
+pub(crate) struct ResourceSampler {
+  pid: u32,
+  spawned_at: Instant,
+  /// The time and total CPU time of the previous measurement
+  previous: Option<(Instant, Duration)>,
+}
+
+#[cfg(feature = "process")]
+impl ResourceSampler {
+  pub(crate) fn new(pid: u32, spawned_at: Instant) -> Self {
+    Self {
+      pid,
+      spawned_at,
+      previous: None,
+    }
+  }
+
+  pub(crate) fn sample(&mut self) -> io::Result<ResourceUsage> {
+    let stats = platform::process_stats(self.pid)?;
+    let now = Instant::now();
+    let (since, cpu_before) = self.previous.unwrap_or((self.spawned_at, Duration::ZERO));
+    let wall = now.duration_since(since).as_secs_f64();
+    let cpu = stats.cpu_time.saturating_sub(cpu_before).as_secs_f64();
+    self.previous = Some((now, stats.cpu_time));
+    Ok(ResourceUsage {
+      cpu_percent: if wall > 0.0 { cpu / wall * 100.0 } else { 0.0 },
+      rss_bytes: stats.rss_bytes,
+      elapsed: now.duration_since(self.spawned_at),
+    })
+  }
+}
+
+/// The cumulative readings of the OS, from which [`ResourceUsage`] is
+/// computed.
+#[cfg(feature = "process")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ProcessStats {
+  /// User and system time, across all threads.
+  cpu_time: Duration,
+  rss_bytes: u64,
+}
+
+/// Reads `/proc/<pid>/stat` and `/proc/<pid>/statm`.
+#[cfg(all(feature = "process", target_os = "linux"))]
+mod platform {
+  use std::{ffi::c_long, fs, io, time::Duration};
+
+  use super::ProcessStats;
+
+  const SC_CLK_TCK: i32 = 2;
+  const SC_PAGESIZE: i32 = 30;
+
+  extern "C" {
+    fn sysconf(name: i32) -> c_long;
+  }
+
+  pub(super) fn process_stats(pid: u32) -> io::Result<ProcessStats> {
+    let invalid = || io::Error::new(io::ErrorKind::InvalidData, "unexpected /proc format");
+    let stat = fs::read_to_string(format!("/proc/{pid}/stat"))?;
+    let (utime, stime) = parse_stat_times(&stat).ok_or_else(invalid)?;
+    let statm = fs::read_to_string(format!("/proc/{pid}/statm"))?;
+    let resident_pages = statm
+      .split_whitespace()
+      .nth(1)
+      .and_then(|pages| pages.parse::<u64>().ok())
+      .ok_or_else(invalid)?;
+
+    let (ticks_per_second, page_size) = unsafe { (sysconf(SC_CLK_TCK), sysconf(SC_PAGESIZE)) };
+    if ticks_per_second <= 0 || page_size <= 0 {
+      return Err(io::Error::last_os_error());
+    }
+    Ok(ProcessStats {
+      cpu_time: Duration::from_secs_f64((utime + stime) as f64 / ticks_per_second as f64),
+      rss_bytes: resident_pages * page_size as u64,
+    })
+  }
+
+  /// The `utime` and `stime` fields, in clock ticks. They follow the name
+  /// of the command, in parentheses, which may contain spaces and
+  /// parentheses itself.
+  fn parse_stat_times(stat: &str) -> Option<(u64, u64)> {
+    let (_, fields) = stat.rsplit_once(')')?;
+    let mut fields = fields.split_whitespace().skip(11);
+    let utime = fields.next()?.parse().ok()?;
+    let stime = fields.next()?.parse().ok()?;
+    Some((utime, stime))
+  }
+}
+
+/// Uses `proc_pidinfo`, whose times are in Mach absolute time units.
+#[cfg(all(feature = "process", target_os = "macos"))]
+mod platform {
+  use std::{
+    ffi::{c_int, c_void},
+    io, mem,
+    time::Duration,
+  };
+
+  use super::ProcessStats;
+
+  const PROC_PIDTASKINFO: c_int = 4;
+
+  #[repr(C)]
+  #[derive(Default)]
+  struct ProcTaskInfo {
+    virtual_size: u64,
+    resident_size: u64,
+    total_user: u64,
+    total_system: u64,
+    threads_user: u64,
+    threads_system: u64,
+    policy: i32,
+    faults: i32,
+    pageins: i32,
+    cow_faults: i32,
+    messages_sent: i32,
+    messages_received: i32,
+    syscalls_mach: i32,
+    syscalls_unix: i32,
+    csw: i32,
+    threadnum: i32,
+    numrunning: i32,
+    priority: i32,
+  }
+
+  #[repr(C)]
+  #[derive(Default)]
+  struct MachTimebaseInfo {
+    numer: u32,
+    denom: u32,
+  }
+
+  extern "C" {
+    fn proc_pidinfo(
+      pid: c_int,
+      flavor: c_int,
+      arg: u64,
+      buffer: *mut c_void,
+      buffer_size: c_int,
+    ) -> c_int;
+    fn mach_timebase_info(info: *mut MachTimebaseInfo) -> c_int;
+  }
+
+  pub(super) fn process_stats(pid: u32) -> io::Result<ProcessStats> {
+    let mut info = ProcTaskInfo::default();
+    let size = mem::size_of::<ProcTaskInfo>() as c_int;
+    let written = unsafe {
+      proc_pidinfo(
+        pid as c_int,
+        PROC_PIDTASKINFO,
+        0,
+        &mut info as *mut ProcTaskInfo as *mut c_void,
+        size,
+      )
+    };
+    if written < size {
+      return Err(io::Error::last_os_error());
+    }
+
+    let mut timebase = MachTimebaseInfo::default();
+    if unsafe { mach_timebase_info(&mut timebase) } != 0 || timebase.denom == 0 {
+      return Err(io::Error::other("mach_timebase_info failed"));
+    }
+    let ticks = (info.total_user + info.total_system) as u128;
+    let nanos = ticks * timebase.numer as u128 / timebase.denom as u128;
+    Ok(ProcessStats {
+      cpu_time: Duration::from_nanos(nanos as u64),
+      rss_bytes: info.resident_size,
+    })
+  }
+}
+
+/// Uses `GetProcessTimes` and `GetProcessMemoryInfo`, declared by hand like
+/// the Job Object functions of `process_tree`.
+#[cfg(all(feature = "process", windows))]
+mod platform {
+  use std::{ffi::c_void, io, mem, time::Duration};
+
+  use super::ProcessStats;
+
+  type Handle = *mut c_void;
+
+  const PROCESS_QUERY_LIMITED_INFORMATION: u32 = 0x1000;
+
+  #[repr(C)]
+  #[derive(Default)]
+  struct FileTime {
+    low: u32,
+    high: u32,
+  }
+
+  impl FileTime {
+    /// In units of 100 nanoseconds
+    fn ticks(&self) -> u64 {
+      ((self.high as u64) << 32) | self.low as u64
+    }
+  }
+
+  #[repr(C)]
+  #[derive(Default)]
+  struct ProcessMemoryCounters {
+    cb: u32,
+    page_fault_count: u32,
+    peak_working_set_size: usize,
+    working_set_size: usize,
+    quota_peak_paged_pool_usage: usize,
+    quota_paged_pool_usage: usize,
+    quota_peak_non_paged_pool_usage: usize,
+    quota_non_paged_pool_usage: usize,
+    pagefile_usage: usize,
+    peak_pagefile_usage: usize,
+  }
+
+  #[link(name = "kernel32")]
+  extern "system" {
+    fn OpenProcess(access: u32, inherit: i32, pid: u32) -> Handle;
+    fn GetProcessTimes(
+      process: Handle,
+      creation: *mut FileTime,
+      exit: *mut FileTime,
+      kernel: *mut FileTime,
+      user: *mut FileTime,
+    ) -> i32;
+    fn K32GetProcessMemoryInfo(
+      process: Handle,
+      counters: *mut ProcessMemoryCounters,
+      size: u32,
+    ) -> i32;
+    fn CloseHandle(handle: Handle) -> i32;
+  }
+
+  pub(super) fn process_stats(pid: u32) -> io::Result<ProcessStats> {
+    let process = unsafe { OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid) };
+    if process.is_null() {
+      return Err(io::Error::last_os_error());
+    }
+    let stats = query(process);
+    unsafe { CloseHandle(process) };
+    stats
+  }
+
+  fn query(process: Handle) -> io::Result<ProcessStats> {
+    let (mut creation, mut exit) = (FileTime::default(), FileTime::default());
+    let (mut kernel, mut user) = (FileTime::default(), FileTime::default());
+    if unsafe { GetProcessTimes(process, &mut creation, &mut exit, &mut kernel, &mut user) } == 0 {
+      return Err(io::Error::last_os_error());
+    }
+    let size = mem::size_of::<ProcessMemoryCounters>() as u32;
+    let mut counters = ProcessMemoryCounters {
+      cb: size,
+      ..Default::default()
+    };
+    if unsafe { K32GetProcessMemoryInfo(process, &mut counters, size) } == 0 {
+      return Err(io::Error::last_os_error());
+    }
+    Ok(ProcessStats {
+      cpu_time: Duration::from_nanos((kernel.ticks() + user.ticks()) * 100),
+      rss_bytes: counters.working_set_size as u64,
+    })
+  }
+}
+
+#[cfg(all(
+  feature = "process",
+  not(any(target_os = "linux", target_os = "macos", windows))
+))]
+mod platform {
+  use std::io;
+
+  use super::ProcessStats;
+
+  pub(super) fn process_stats(_pid: u32) -> io::Result<ProcessStats> {
+    Err(io::ErrorKind::Unsupported.into())
+  }
+}
@@ -0,0 +1,45 @@
+//! Handling of rotation metadata, as written by phone cameras which store
+//! video in sensor orientation along with a display rotation.
+
+/// What to do with an input's rotation metadata, used by
+/// [`FfmpegCommand::auto_rotate`](crate::command::FfmpegCommand::auto_rotate).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RotationPolicy {
+  /// Rotate the pixels so the output displays upright without any rotation
+  /// metadata, and strip the rotation tag.
+  Bake,
+  /// Keep the pixels in their stored orientation, and carry the rotation
+  /// metadata over to the output so players rotate it on display.
+  Preserve,
+}
+
+/// Normalize a rotation in degrees to one of `0`, `90`, `180` or `270`,
+/// rounding to the nearest quarter turn.
+///
+/// ```rust
+/// use ffmpeg_sidecar::rotation::normalize_rotation;
+/// assert_eq!(normalize_rotation(-90.0), 270);
+/// assert_eq!(normalize_rotation(450.0), 90);
+/// assert_eq!(normalize_rotation(179.9), 180);
+/// ```
+pub fn normalize_rotation(degrees: f64) -> u32 {
+  let quarter_turns = (degrees / 90.0).round() as i64;
+  (quarter_turns.rem_euclid(4) * 90) as u32
+}
+
+/// The filter chain which rotates video clockwise by `degrees`, which must be
+/// a multiple of 90. Returns `None` when no rotation is needed.
+///
+/// ```rust
+/// use ffmpeg_sidecar::rotation::rotation_filter;
+/// assert_eq!(rotation_filter(90), Some("transpose=clock"));
+/// assert_eq!(rotation_filter(0), None);
+/// ```
+pub fn rotation_filter(degrees: u32) -> Option<&'static str> {
+  match degrees % 360 {
+    90 => Some("transpose=clock"),
+    180 => Some("hflip,vflip"),
+    270 => Some("transpose=cclock"),
+    _ => None,
+  }
+}
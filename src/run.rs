@@ -0,0 +1,301 @@
+//! Transcoding a file in a single blocking call, with a progress callback,
+//! for when the iterator and the child process are more than needed.
+//!
+//! ```rust,no_run
+//! use ffmpeg_sidecar::run::{transcode, RateControl, TranscodeOptions};
+//!
+//! let options = TranscodeOptions {
+//!   video_codec: Some("libx264".into()),
+//!   crf_or_bitrate: Some(RateControl::Crf(23)),
+//!   size: Some((1280, 720)),
+//!   ..Default::default()
+//! };
+//! let report = transcode("in.mov", "out.mp4", options, |progress| {
+//!   if let Some(percent) = progress.percent {
+//!     println!("{percent:.0}%, {:?} left", progress.eta);
+//!   }
+//! })
+//! .unwrap();
+//! println!("{} bytes at {:?}x", report.output_size, report.average_speed);
+//! ```
+
+use std::{
+  fmt, fs,
+  path::{Path, PathBuf},
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    mpsc::{channel, RecvTimeoutError},
+    Arc,
+  },
+  time::{Duration, Instant},
+};
+
+use crate::{
+  command::FfmpegCommand,
+  error::FfmpegErrorKind,
+  event::{FfmpegEvent, FfmpegProgress, LogLevel},
+  timestamp::Timestamp,
+};
+
+/// How often the cancellation token is checked while ffmpeg is quiet.
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// How the video bitrate of a [`transcode`] is chosen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RateControl {
+  /// Constant quality, see [`FfmpegCommand::crf`].
+  Crf(u32),
+  /// Average bitrate, in kbit/s (`-b:v`).
+  Bitrate(u32),
+}
+
+/// The settings of a [`transcode`]. Everything left unset keeps ffmpeg's
+/// default for the output extension.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TranscodeOptions {
+  /// The video encoder, like `libx264`, or `copy`.
+  pub video_codec: Option<String>,
+  /// The audio encoder, like `aac`, or `copy`.
+  pub audio_codec: Option<String>,
+  pub crf_or_bitrate: Option<RateControl>,
+  /// The width and height of the output video, see [`FfmpegCommand::size`].
+  pub size: Option<(u32, u32)>,
+  /// Output options added after the others, like `["-preset", "fast"]`.
+  pub extra_args: Vec<String>,
+}
+
+/// Stops a [`transcode_with_cancel`] from another thread. Clones share the
+/// same state.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  pub fn cancel(&self) {
+    self.0.store(true, Ordering::Relaxed);
+  }
+
+  pub fn is_cancelled(&self) -> bool {
+    self.0.load(Ordering::Relaxed)
+  }
+}
+
+/// A progress update of a [`transcode`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TranscodeProgress {
+  /// The part of the input transcoded, from 0 to 100, or `None` if the
+  /// duration of the input isn't known, e.g. for a live stream.
+  pub percent: Option<f64>,
+  /// The estimated time left, see [`FfmpegProgress::eta`].
+  pub eta: Option<Duration>,
+  /// The update as reported by ffmpeg.
+  pub progress: FfmpegProgress,
+}
+
+/// The result of a successful [`transcode`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TranscodeReport {
+  pub output: PathBuf,
+  /// The size of the output file, in bytes.
+  pub output_size: u64,
+  /// The duration of the output, from the last progress update.
+  pub duration: Option<Timestamp>,
+  /// The wall clock time the transcode took.
+  pub elapsed: Duration,
+  /// The duration of the output divided by the time it took, e.g. `2.0`
+  /// for twice as fast as realtime.
+  pub average_speed: Option<f64>,
+  /// The warnings logged by ffmpeg and the hints of the crate, without
+  /// duplicates, e.g. about a deprecated option or timestamps corrected.
+  pub warnings: Vec<String>,
+}
+
+/// Returned (through `anyhow::Error`) when a [`transcode`] doesn't
+/// complete. Spawn failures are returned as they are, see
+/// [`SpawnError`](crate::error::SpawnError).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TranscodeError {
+  /// The [`CancelToken`] was cancelled. ffmpeg was killed and the partial
+  /// output removed.
+  Cancelled,
+  /// ffmpeg failed.
+  Failed {
+    /// Recognized causes among the logged errors.
+    causes: Vec<FfmpegErrorKind>,
+    /// The error messages logged by ffmpeg, or why waiting for it failed.
+    message: String,
+    /// The last lines of stderr.
+    stderr_tail: Vec<String>,
+  },
+}
+
+impl fmt::Display for TranscodeError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      TranscodeError::Cancelled => f.write_str("transcode cancelled"),
+      TranscodeError::Failed {
+        causes, message, ..
+      } => match causes.first() {
+        Some(cause) => write!(f, "transcode failed ({cause:?}): {message}"),
+        None => write!(f, "transcode failed: {message}"),
+      },
+    }
+  }
+}
+
+impl std::error::Error for TranscodeError {}
+
+/// Transcode `input` to `output`, overwriting it, blocking until ffmpeg
+/// exits, and calling `on_progress` with each progress update. See the
+/// [module documentation](self) for an example.
+pub fn transcode<I: AsRef<str>, O: AsRef<Path>>(
+  input: I,
+  output: O,
+  options: TranscodeOptions,
+  on_progress: impl FnMut(TranscodeProgress),
+) -> anyhow::Result<TranscodeReport> {
+  transcode_with_cancel(input, output, options, &CancelToken::new(), on_progress)
+}
+
+/// Like [`transcode`], returning [`TranscodeError::Cancelled`] soon after
+/// `cancel` is cancelled.
+///
+/// ```rust,no_run
+/// use ffmpeg_sidecar::run::{transcode_with_cancel, CancelToken, TranscodeOptions};
+///
+/// let cancel = CancelToken::new();
+/// let stop = cancel.clone();
+/// std::thread::spawn(move || {
+///   std::thread::sleep(std::time::Duration::from_secs(10));
+///   stop.cancel();
+/// });
+/// let result = transcode_with_cancel("in.mov", "out.mp4", TranscodeOptions::default(), &cancel, |_| {});
+/// ```
+pub fn transcode_with_cancel<I: AsRef<str>, O: AsRef<Path>>(
+  input: I,
+  output: O,
+  options: TranscodeOptions,
+  cancel: &CancelToken,
+  mut on_progress: impl FnMut(TranscodeProgress),
+) -> anyhow::Result<TranscodeReport> {
+  let output = output.as_ref();
+  let started = Instant::now();
+  let mut child = transcode_command(input.as_ref(), output, &options).spawn()?;
+  // Nothing is sent over stdin, and closing it means ffmpeg exits instead of
+  // waiting forever if it asks for confirmation.
+  drop(child.take_stdin());
+
+  // Read on another thread, so the token is checked even while ffmpeg
+  // logs nothing, e.g. waiting for a network input
+  let (tx, rx) = channel();
+  let iter = child.iter()?;
+  std::thread::spawn(move || {
+    for event in iter {
+      if tx.send(event).is_err() {
+        break;
+      }
+    }
+  });
+
+  let mut input_duration = None;
+  let mut duration = None;
+  let mut warnings = Vec::<String>::new();
+  let mut errors = Vec::new();
+  loop {
+    if cancel.is_cancelled() {
+      child.kill()?;
+      // Reaped before removing what it wrote
+      child.wait().ok();
+      fs::remove_file(output).ok();
+      return Err(TranscodeError::Cancelled.into());
+    }
+    let event = match rx.recv_timeout(CANCEL_POLL_INTERVAL) {
+      Ok(event) => event,
+      Err(RecvTimeoutError::Timeout) => continue,
+      Err(RecvTimeoutError::Disconnected) => break,
+    };
+    match event {
+      FfmpegEvent::ParsedDuration(parsed) if parsed.input_index == 0 => {
+        input_duration = Some(parsed.duration)
+      }
+      FfmpegEvent::Progress(progress) => {
+        duration = progress.out_time.or(duration);
+        on_progress(TranscodeProgress {
+          percent: progress.percent(None, input_duration),
+          eta: progress.eta(None, input_duration),
+          progress,
+        });
+      }
+      FfmpegEvent::Log(LogLevel::Warning, warning) | FfmpegEvent::Hint(warning)
+        if !warnings.contains(&warning) =>
+      {
+        warnings.push(warning)
+      }
+      FfmpegEvent::Error(error) | FfmpegEvent::Log(LogLevel::Error | LogLevel::Fatal, error) => {
+        errors.push(error)
+      }
+      _ => {}
+    }
+  }
+
+  let message = match child.wait() {
+    Ok(status) if status.success() => None,
+    Ok(status) if errors.is_empty() => Some(format!("ffmpeg exited with {status}")),
+    Ok(_) => Some(errors.join("\n")),
+    Err(e) => Some(e.to_string()),
+  };
+  if let Some(message) = message {
+    return Err(
+      TranscodeError::Failed {
+        causes: child.summary().errors,
+        message,
+        stderr_tail: child.stderr_tail(),
+      }
+      .into(),
+    );
+  }
+
+  let elapsed = started.elapsed();
+  Ok(TranscodeReport {
+    output: output.to_path_buf(),
+    output_size: fs::metadata(output).map_or(0, |metadata| metadata.len()),
+    duration,
+    elapsed,
+    average_speed: duration
+      .filter(|_| !elapsed.is_zero())
+      .map(|duration| duration.as_secs_f64() / elapsed.as_secs_f64()),
+    warnings,
+  })
+}
+
+/// The ffmpeg command run by [`transcode`].
+fn transcode_command(input: &str, output: &Path, options: &TranscodeOptions) -> FfmpegCommand {
+  let mut command = FfmpegCommand::new();
+  command.hide_banner().input(input);
+  if let Some(codec) = &options.video_codec {
+    command.codec_video(codec);
+  }
+  if let Some(codec) = &options.audio_codec {
+    command.codec_audio(codec);
+  }
+  match options.crf_or_bitrate {
+    Some(RateControl::Crf(crf)) => {
+      command.crf(crf);
+    }
+    Some(RateControl::Bitrate(kbps)) => {
+      command.args(["-b:v".to_string(), format!("{kbps}k")]);
+    }
+    None => {}
+  }
+  if let Some((width, height)) = options.size {
+    command.size(width, height);
+  }
+  command
+    .args(&options.extra_args)
+    .overwrite()
+    .output(output.to_string_lossy());
+  command
+}
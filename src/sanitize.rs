@@ -0,0 +1,158 @@
+//! Passing user-supplied strings, like file names, watermark text or
+//! metadata, to ffmpeg without letting them add options or filters.
+//!
+//! The typed builders pass each value as its own argument, so a value is
+//! never split into several options. Two places still interpret values:
+//! filtergraphs, where values are escaped with [`escape_filter_value`], and
+//! input and output paths, which ffmpeg reads as options when they start
+//! with `-`, or as protocols like `concat:` or `subfile:` which can read any
+//! local file. Paths are checked at spawn with
+//! [`FfmpegCommand::sanitize_inputs`](crate::command::FfmpegCommand::sanitize_inputs).
+
+use std::fmt;
+
+/// The protocols allowed by `sanitize_inputs` without
+/// [`FfmpegCommand::allow_protocol`](crate::command::FfmpegCommand::allow_protocol):
+/// local files, and the stdio pipes.
+pub const SAFE_PROTOCOLS: &[&str] = &["file", "pipe"];
+
+/// Escape a value given to a filter option in a filtergraph, like a file
+/// name, a text or an expression, so that `:` doesn't start the next option
+/// and `,`, `;` or `[` don't start the next filter. This takes two levels:
+/// one for the option value, then one for the filtergraph around it.
+///
+/// ```rust
+/// use ffmpeg_sidecar::sanitize::escape_filter_value;
+///
+/// assert_eq!(escape_filter_value("logo.png"), "logo.png");
+/// assert_eq!(escape_filter_value("a:b"), r"a\\:b");
+/// assert_eq!(escape_filter_value("red,drawbox"), r"red\,drawbox");
+/// assert_eq!(escape_filter_value("it's"), r"it\\\'s");
+/// assert_eq!(escape_filter_value(r"C:\x"), r"C\\:\\\\x");
+/// ```
+pub fn escape_filter_value(value: &str) -> String {
+  let escape = |value: &str, special: &[char]| {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+      if special.contains(&c) {
+        escaped.push('\\');
+      }
+      escaped.push(c);
+    }
+    escaped
+  };
+  let value = escape(value, &['\\', '\'', ':']);
+  escape(&value, &['\\', '\'', '[', ']', ',', ';'])
+}
+
+/// The protocol prefix of an input or output, like `concat` for
+/// `concat:a.ts|b.ts` or `https` for a URL, in lowercase, or `None` for a
+/// plain path. A single letter is a Windows drive rather than a protocol.
+///
+/// ```rust
+/// use ffmpeg_sidecar::sanitize::protocol_of;
+///
+/// assert_eq!(protocol_of("Crypto:secret.bin").as_deref(), Some("crypto"));
+/// assert_eq!(protocol_of("subfile,,start,0,end,0,,:/etc/passwd").as_deref(), Some("subfile"));
+/// assert_eq!(protocol_of(r"C:\videos\in.mp4"), None);
+/// assert_eq!(protocol_of("clips/a:b.mp4"), None);
+/// ```
+pub fn protocol_of(path: &str) -> Option<String> {
+  // Like ffmpeg, which also takes the options of `subfile` after a comma
+  let end = path.find(|c: char| !(c.is_ascii_alphanumeric() || "+-._".contains(c)))?;
+  let separator = path[end..].chars().next();
+  (end > 1 && matches!(separator, Some(':' | ','))).then(|| path[..end].to_ascii_lowercase())
+}
+
+/// Whether a path is an input or an output of the command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PathRole {
+  Input,
+  Output,
+}
+
+impl fmt::Display for PathRole {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(match self {
+      PathRole::Input => "input",
+      PathRole::Output => "output",
+    })
+  }
+}
+
+/// Why a path was rejected, see [`UnsafePath`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum UnsafePathReason {
+  /// The path starts with `-`, so ffmpeg could read it as an option. `-`
+  /// alone, for stdin or stdout, is allowed.
+  LeadingDash,
+  /// The path uses a protocol which wasn't allowed.
+  Protocol(String),
+}
+
+/// Returned (through `io::Error`, with the kind `InvalidInput`) by
+/// [`FfmpegCommand::spawn`](crate::command::FfmpegCommand::spawn) before
+/// spawning, when an input or output fails the checks of
+/// [`sanitize_inputs`](crate::command::FfmpegCommand::sanitize_inputs).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct UnsafePath {
+  pub role: PathRole,
+  pub path: String,
+  pub reason: UnsafePathReason,
+}
+
+impl fmt::Display for UnsafePath {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match &self.reason {
+      UnsafePathReason::LeadingDash => write!(
+        f,
+        "{} `{}` starts with `-` and could be read as an option",
+        self.role, self.path
+      ),
+      UnsafePathReason::Protocol(protocol) => write!(
+        f,
+        "{} `{}` uses the `{protocol}` protocol, which isn't allowed",
+        self.role, self.path
+      ),
+    }
+  }
+}
+
+impl std::error::Error for UnsafePath {}
+
+/// Check a path for the reasons of [`UnsafePathReason`], allowing the
+/// [`SAFE_PROTOCOLS`] and the ones in `allowed_protocols`.
+///
+/// ```rust
+/// use ffmpeg_sidecar::sanitize::{check_path, PathRole, UnsafePathReason};
+///
+/// assert!(check_path(PathRole::Input, "in.mp4", &[]).is_ok());
+/// assert!(check_path(PathRole::Output, "-", &[]).is_ok());
+/// let error = check_path(PathRole::Output, "-y", &[]).unwrap_err();
+/// assert_eq!(error.reason, UnsafePathReason::LeadingDash);
+/// assert!(check_path(PathRole::Input, "https://example.com/a.mp4", &[]).is_err());
+/// assert!(check_path(PathRole::Input, "https://example.com/a.mp4", &["https".to_string()]).is_ok());
+/// ```
+pub fn check_path(
+  role: PathRole,
+  path: &str,
+  allowed_protocols: &[String],
+) -> Result<(), UnsafePath> {
+  let reason = match protocol_of(path) {
+    _ if path.starts_with('-') && path != "-" => UnsafePathReason::LeadingDash,
+    Some(protocol)
+      if !SAFE_PROTOCOLS.contains(&protocol.as_str())
+        && !allowed_protocols
+          .iter()
+          .any(|allowed| allowed.eq_ignore_ascii_case(&protocol)) =>
+    {
+      UnsafePathReason::Protocol(protocol)
+    }
+    _ => return Ok(()),
+  };
+  Err(UnsafePath {
+    role,
+    path: path.to_string(),
+    reason,
+  })
+}
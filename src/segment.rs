@@ -0,0 +1,63 @@
+//! Splitting an output into consecutive files with the `segment` muxer.
+
+use std::time::Duration;
+
+/// Options for [`FfmpegCommand::output_segments`](crate::command::FfmpegCommand::output_segments).
+///
+/// Segments are only split on keyframes, so with video the actual duration
+/// depends on the keyframe interval of the encoder (e.g. `-g` or
+/// `-force_key_frames`).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SegmentOptions {
+  /// Target duration of each segment (`-segment_time`). Zero keeps ffmpeg's
+  /// default of 2 seconds.
+  pub duration: Duration,
+  /// Reuse segment numbers after this many segments, overwriting the oldest
+  /// files, e.g. for a rolling buffer (`-segment_wrap`).
+  pub wrap: Option<u32>,
+  /// Write a list of the finished segments to this file (`-segment_list`).
+  pub list_file: Option<String>,
+  /// Start the timestamps of each segment at zero (`-reset_timestamps 1`).
+  pub reset_timestamps: bool,
+}
+
+impl SegmentOptions {
+  /// The output arguments for these options, to be followed by the filename
+  /// pattern.
+  ///
+  /// ```rust
+  /// use ffmpeg_sidecar::segment::SegmentOptions;
+  /// use std::time::Duration;
+  ///
+  /// let options = SegmentOptions {
+  ///   duration: Duration::from_secs(10),
+  ///   wrap: Some(6),
+  ///   list_file: None,
+  ///   reset_timestamps: true,
+  /// };
+  /// assert_eq!(
+  ///   options.to_args(),
+  ///   ["-f", "segment", "-segment_time", "10", "-segment_wrap", "6", "-reset_timestamps", "1"]
+  /// );
+  /// ```
+  pub fn to_args(&self) -> Vec<String> {
+    let mut args = vec!["-f".to_string(), "segment".to_string()];
+    if !self.duration.is_zero() {
+      args.push("-segment_time".to_string());
+      args.push(self.duration.as_secs_f64().to_string());
+    }
+    if let Some(wrap) = self.wrap {
+      args.push("-segment_wrap".to_string());
+      args.push(wrap.to_string());
+    }
+    if let Some(list_file) = &self.list_file {
+      args.push("-segment_list".to_string());
+      args.push(list_file.clone());
+    }
+    if self.reset_timestamps {
+      args.push("-reset_timestamps".to_string());
+      args.push("1".to_string());
+    }
+    args
+  }
+}
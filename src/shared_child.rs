@@ -0,0 +1,135 @@
+//! The ffmpeg process, shared by the [`FfmpegChild`](crate::child::FfmpegChild)
+//! and its event stream so either one can reap it, whichever gets there
+//! first.
+
+use std::{
+  io,
+  process::ExitStatus,
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    Mutex, MutexGuard, PoisonError,
+  },
+  time::Duration,
+};
+
+use crate::{
+  process_tree::ProcessTree,
+  spawner::{Killer, SpawnedProcess},
+};
+
+/// How often the event stream checks whether the process exited, once its
+/// output is drained.
+const REAP_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// A [`SpawnedProcess`] which can be waited on from several places. `std`
+/// caches the exit status once the process is reaped, so it's never waited on
+/// twice, and never killed once its pid could have been reused.
+pub(crate) struct SharedChild {
+  id: u32,
+  child: Mutex<SpawnedProcess>,
+  killer: Killer,
+  /// Set at spawn with `contain_process_tree`, until the child is reaped
+  process_tree: Mutex<Option<ProcessTree>>,
+  /// Set by `kill`, so the event stream can tell the exit wasn't graceful
+  killed: AtomicBool,
+}
+
+impl SharedChild {
+  pub(crate) fn new(child: SpawnedProcess) -> Self {
+    Self {
+      id: child.id(),
+      killer: child.killer(false),
+      child: Mutex::new(child),
+      process_tree: Mutex::new(None),
+      killed: AtomicBool::new(false),
+    }
+  }
+
+  pub(crate) fn id(&self) -> u32 {
+    self.id
+  }
+
+  /// The child itself. The event stream can't reap it while this is held.
+  pub(crate) fn lock(&self) -> MutexGuard<'_, SpawnedProcess> {
+    self.child.lock().unwrap_or_else(PoisonError::into_inner)
+  }
+
+  /// A way for timers to kill the process without waiting for the lock,
+  /// along with the processes it started if its tree is contained.
+  pub(crate) fn killer(&self) -> Killer {
+    match &self.killer {
+      Killer::Pid { pid, .. } => Killer::Pid {
+        pid: *pid,
+        process_group: self.has_process_tree(),
+      },
+      killer => killer.clone(),
+    }
+  }
+
+  pub(crate) fn set_process_tree(&self, tree: ProcessTree) {
+    *self.tree() = Some(tree);
+  }
+
+  pub(crate) fn has_process_tree(&self) -> bool {
+    self.tree().is_some()
+  }
+
+  /// Kill the process, along with the processes it started if its tree is
+  /// contained.
+  pub(crate) fn kill(&self) -> io::Result<()> {
+    self.killed.store(true, Ordering::SeqCst);
+    if let Some(tree) = &*self.tree() {
+      tree.kill()?;
+    }
+    self.lock().kill()
+  }
+
+  /// Whether [`SharedChild::kill`] was called.
+  pub(crate) fn killed(&self) -> bool {
+    self.killed.load(Ordering::SeqCst)
+  }
+
+  /// The exit status, reaping the process if it just exited.
+  pub(crate) fn try_wait(&self) -> io::Result<Option<ExitStatus>> {
+    let status = self.lock().try_wait()?;
+    if status.is_some() {
+      self.reaped();
+    }
+    Ok(status)
+  }
+
+  /// Block until the process exits, and reap it. Like
+  /// [`Child::wait`](std::process::Child::wait), this closes stdin first.
+  pub(crate) fn wait(&self) -> io::Result<ExitStatus> {
+    let status = self.lock().wait()?;
+    self.reaped();
+    Ok(status)
+  }
+
+  /// Like [`SharedChild::wait`], but without holding the child in the
+  /// meantime, so it can still be killed or sent commands. Stdin is left
+  /// open.
+  pub(crate) fn poll_wait(&self) -> io::Result<ExitStatus> {
+    loop {
+      if let Some(status) = self.try_wait()? {
+        return Ok(status);
+      }
+      std::thread::sleep(REAP_POLL_INTERVAL);
+    }
+  }
+
+  /// Kill what's left of the process tree. Taken, so it's never killed again
+  /// once the group is gone and its id could be reused.
+  fn reaped(&self) {
+    if let Some(tree) = self.tree().take() {
+      tree.kill().ok();
+    }
+  }
+
+  fn tree(&self) -> MutexGuard<'_, Option<ProcessTree>> {
+    self
+      .process_tree
+      .lock()
+      .unwrap_or_else(PoisonError::into_inner)
+  }
+}
@@ -0,0 +1,470 @@
+//! Finding the silences of a recording with the `silencedetect` filter, to
+//! trim them from its ends or split it at them, e.g. for podcasts.
+//!
+//! ```rust,no_run
+//! use ffmpeg_sidecar::silence::{split_on_silence, SplitOptions};
+//! use std::time::Duration;
+//!
+//! let options = SplitOptions {
+//!   min_silence: Duration::from_secs(2),
+//!   ..Default::default()
+//! };
+//! for segment in split_on_silence("episode.wav", "part%03d.wav", options).unwrap() {
+//!   println!("{:?}: {:?} - {:?}", segment.path, segment.start, segment.end);
+//! }
+//! ```
+
+use std::{
+  cell::RefCell,
+  fmt,
+  path::{Path, PathBuf},
+  time::Duration,
+};
+
+use crate::{
+  command::FfmpegCommand,
+  event::{FfmpegEvent, LogLevel},
+  probe::probe_cached,
+  queue::run_job,
+};
+
+/// Silences this close to the start or the end of the input are at its
+/// edges, since the timestamps printed by `silencedetect` are rounded.
+const EDGE_TOLERANCE: Duration = Duration::from_millis(10);
+
+/// A range of the input where every channel stays below the threshold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SilenceInterval {
+  pub start: Duration,
+  pub end: Duration,
+}
+
+impl SilenceInterval {
+  pub fn duration(&self) -> Duration {
+    self.end.saturating_sub(self.start)
+  }
+}
+
+/// Options for [`trim_silence`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrimOptions {
+  /// Sound below this level, in dBFS, is silence.
+  pub threshold_db: f64,
+  /// Silences shorter than this at the start or the end are kept.
+  pub min_silence: Duration,
+}
+
+impl Default for TrimOptions {
+  fn default() -> Self {
+    Self {
+      threshold_db: -50.0,
+      min_silence: Duration::from_millis(500),
+    }
+  }
+}
+
+/// Options for [`split_on_silence`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SplitOptions {
+  /// Sound below this level, in dBFS, is silence.
+  pub threshold_db: f64,
+  /// Only silences at least this long split the input.
+  pub min_silence: Duration,
+  /// Silences which would leave a segment shorter than this are ignored,
+  /// except when the whole input is shorter.
+  pub min_segment: Duration,
+}
+
+impl Default for SplitOptions {
+  fn default() -> Self {
+    Self {
+      threshold_db: -50.0,
+      min_silence: Duration::from_secs(2),
+      min_segment: Duration::from_secs(10),
+    }
+  }
+}
+
+/// A file written by [`split_on_silence`], and the range of the input it
+/// holds.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SilenceSegment {
+  /// Position of the segment, starting at 0.
+  pub index: usize,
+  pub start: Duration,
+  pub end: Duration,
+  pub path: PathBuf,
+}
+
+/// Returned (through `anyhow::Error`) when silences can't be found or cut.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SilenceError {
+  /// The input has no audio stream.
+  NoAudio,
+  /// The duration of the input is unknown, or zero.
+  NoDuration,
+  /// An ffmpeg run failed.
+  Failed {
+    /// `detect`, `trim` or `split`.
+    step: &'static str,
+    /// The spawn error, or the error messages logged by ffmpeg.
+    message: String,
+  },
+}
+
+impl fmt::Display for SilenceError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      SilenceError::NoAudio => write!(f, "the input has no audio stream"),
+      SilenceError::NoDuration => write!(f, "the duration of the input is unknown"),
+      SilenceError::Failed { step, message } => {
+        write!(f, "silence {step} failed: {message}")
+      }
+    }
+  }
+}
+
+impl std::error::Error for SilenceError {}
+
+/// A line printed by `silencedetect`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SilenceMark {
+  /// `silence_start`, in seconds. Can be slightly negative at the start of
+  /// the input.
+  Start(f64),
+  /// `silence_end`, in seconds.
+  End(f64),
+}
+
+/// Parse a line logged by the `silencedetect` filter.
+///
+/// ```rust
+/// use ffmpeg_sidecar::silence::{try_parse_silence, SilenceMark};
+///
+/// let line = "[silencedetect @ 0x600000b3c000] [info] silence_start: 2.00127";
+/// assert_eq!(try_parse_silence(line), Some(SilenceMark::Start(2.00127)));
+///
+/// let line = "[silencedetect @ 0x600000b3c000] [info] silence_end: 5 | silence_duration: 2.99873";
+/// assert_eq!(try_parse_silence(line), Some(SilenceMark::End(5.0)));
+/// ```
+pub fn try_parse_silence(string: &str) -> Option<SilenceMark> {
+  if !string.starts_with("[silencedetect @") {
+    return None;
+  }
+  if let Some((_, rest)) = string.split_once("silence_start: ") {
+    let start = rest.split_whitespace().next()?.parse().ok()?;
+    Some(SilenceMark::Start(start))
+  } else if let Some((_, rest)) = string.split_once("silence_end: ") {
+    let end = rest.split_whitespace().next()?.parse().ok()?;
+    Some(SilenceMark::End(end))
+  } else {
+    None
+  }
+}
+
+/// The `silencedetect` filter finding the silences at least `min_silence`
+/// long, below `threshold_db` dBFS.
+///
+/// ```rust
+/// use ffmpeg_sidecar::silence::silencedetect_filter;
+/// use std::time::Duration;
+///
+/// assert_eq!(
+///   silencedetect_filter(-50.0, Duration::from_millis(1500)),
+///   "silencedetect=noise=-50dB:duration=1.5"
+/// );
+/// ```
+pub fn silencedetect_filter(threshold_db: f64, min_silence: Duration) -> String {
+  format!(
+    "silencedetect=noise={threshold_db}dB:duration={}",
+    min_silence.as_secs_f64()
+  )
+}
+
+/// The silences of the first audio stream of `input` at least `min_silence`
+/// long, below `threshold_db` dBFS, in order. Decodes the whole input.
+pub fn detect_silence<I: AsRef<Path>>(
+  input: I,
+  threshold_db: f64,
+  min_silence: Duration,
+) -> anyhow::Result<Vec<SilenceInterval>> {
+  let input = input.as_ref();
+  Ok(detect(
+    input,
+    audio_duration(input)?,
+    threshold_db,
+    min_silence,
+  )?)
+}
+
+/// Remove the silence at the start and at the end of `input` into `output`,
+/// overwriting it. The silences in the middle are kept.
+///
+/// The silences are found with [`detect_silence`] first, then the leading
+/// one is removed with the `silenceremove` filter, and the output stops
+/// where the trailing one starts. Only the first audio stream is kept, and
+/// it's always re-encoded.
+pub fn trim_silence<I: AsRef<Path>, O: AsRef<Path>>(
+  input: I,
+  output: O,
+  options: TrimOptions,
+) -> anyhow::Result<()> {
+  let (input, output) = (input.as_ref(), output.as_ref());
+  let duration = audio_duration(input)?;
+  let silences = detect(input, duration, options.threshold_db, options.min_silence)?;
+
+  let leading = silences
+    .first()
+    .filter(|silence| silence.start <= EDGE_TOLERANCE);
+  let trailing = silences
+    .last()
+    .filter(|silence| silence.end + EDGE_TOLERANCE >= duration)
+    .filter(|silence| Some(*silence) != leading);
+
+  let mut command = FfmpegCommand::new();
+  command.input(input.to_string_lossy()).map("0:a:0");
+  if leading.is_some() {
+    command.args(["-af", &trim_filter(options.threshold_db)]);
+  }
+  if let Some(trailing) = trailing {
+    let sound_start = leading.map_or(Duration::ZERO, |leading| leading.end);
+    command.duration(trailing.start.saturating_sub(sound_start).as_secs_f64());
+  }
+  command.overwrite().output(output.to_string_lossy());
+  Ok(run_step("trim", &mut command, |_| {})?)
+}
+
+/// The `silenceremove` filter removing the silence at the start, below
+/// `threshold_db` dBFS. Sound stops the trimming as soon as it reaches the
+/// threshold, sample by sample like `silencedetect`.
+///
+/// ```rust
+/// use ffmpeg_sidecar::silence::trim_filter;
+///
+/// assert_eq!(
+///   trim_filter(-50.0),
+///   "silenceremove=start_periods=1:start_duration=0:start_threshold=-50dB:detection=peak"
+/// );
+/// ```
+pub fn trim_filter(threshold_db: f64) -> String {
+  format!(
+    "silenceremove=start_periods=1:start_duration=0:start_threshold={threshold_db}dB:detection=peak"
+  )
+}
+
+/// Split `input` at its silences into numbered files named after
+/// `output_pattern`, like `part%03d.wav`, returning them in order.
+///
+/// Each silence at least `min_silence` long, away from the edges of the
+/// input, is cut in its middle, unless that leaves a segment shorter than
+/// `min_segment`. An input without silences is written as a single
+/// segment.
+///
+/// Only the first audio stream is kept. It's copied when the output has the
+/// same extension as the input and the codec is PCM, which can be cut at
+/// any sample, and re-encoded otherwise.
+pub fn split_on_silence<I: AsRef<Path>, O: AsRef<Path>>(
+  input: I,
+  output_pattern: O,
+  options: SplitOptions,
+) -> anyhow::Result<Vec<SilenceSegment>> {
+  let (input, output_pattern) = (input.as_ref(), output_pattern.as_ref());
+  let info = probe_cached(input)?;
+  let codec = info
+    .streams_of_type("audio")
+    .next()
+    .ok_or(SilenceError::NoAudio)?
+    .codec_name
+    .clone();
+  let duration = info
+    .duration
+    .filter(|duration| *duration > 0.0)
+    .map(Duration::from_secs_f64)
+    .ok_or(SilenceError::NoDuration)?;
+
+  let silences = detect(input, duration, options.threshold_db, options.min_silence)?;
+  let cuts = cut_points(&silences, duration, options.min_segment);
+
+  let mut command = FfmpegCommand::new();
+  command.input(input.to_string_lossy()).map("0:a:0");
+  let same_extension = input.extension().map(|e| e.to_ascii_lowercase())
+    == output_pattern.extension().map(|e| e.to_ascii_lowercase());
+  if same_extension && codec.starts_with("pcm_") {
+    command.codec_audio("copy");
+  }
+  let cut_times = match cuts.is_empty() {
+    // A single segment longer than the input
+    true => vec![
+      "-segment_time".to_string(),
+      (duration.as_secs_f64().ceil() + 1.0).to_string(),
+    ],
+    false => vec![
+      "-segment_times".to_string(),
+      cuts
+        .iter()
+        .map(|cut| cut.as_secs_f64().to_string())
+        .collect::<Vec<_>>()
+        .join(","),
+    ],
+  };
+  command
+    .format("segment")
+    .args(cut_times)
+    .args(["-reset_timestamps", "1"])
+    .overwrite()
+    .output(output_pattern.to_string_lossy());
+
+  let paths = RefCell::new(Vec::new());
+  run_step("split", &mut command, |event| {
+    if let FfmpegEvent::SegmentComplete { path, .. } = event {
+      paths.borrow_mut().push(PathBuf::from(path));
+    }
+  })?;
+
+  let bounds = std::iter::once(Duration::ZERO)
+    .chain(cuts.iter().copied())
+    .zip(cuts.iter().copied().chain(std::iter::once(duration)));
+  let paths = paths.into_inner();
+  if paths.len() != cuts.len() + 1 {
+    let message = format!("wrote {} of {} segments", paths.len(), cuts.len() + 1);
+    return Err(
+      SilenceError::Failed {
+        step: "split",
+        message,
+      }
+      .into(),
+    );
+  }
+  Ok(
+    bounds
+      .zip(paths)
+      .enumerate()
+      .map(|(index, ((start, end), path))| SilenceSegment {
+        index,
+        start,
+        end,
+        path,
+      })
+      .collect(),
+  )
+}
+
+/// Where to split an input of `duration` at its `silences`: in the middle of
+/// each silence away from its edges, skipping those which would leave a
+/// segment shorter than `min_segment`.
+///
+/// ```rust
+/// use ffmpeg_sidecar::silence::{cut_points, SilenceInterval};
+/// use std::time::Duration;
+///
+/// let secs = Duration::from_secs;
+/// let silences = [
+///   SilenceInterval { start: secs(0), end: secs(2) },
+///   SilenceInterval { start: secs(10), end: secs(14) },
+///   SilenceInterval { start: secs(15), end: secs(17) },
+///   SilenceInterval { start: secs(40), end: secs(42) },
+/// ];
+/// assert_eq!(cut_points(&silences, secs(50), secs(2)), [secs(12), secs(16), secs(41)]);
+/// assert_eq!(cut_points(&silences, secs(50), secs(5)), [secs(12), secs(41)]);
+/// // The last segment would be too short
+/// assert_eq!(cut_points(&silences, secs(50), secs(10)), [secs(12)]);
+/// assert_eq!(cut_points(&silences, secs(50), secs(60)), []);
+/// ```
+pub fn cut_points(
+  silences: &[SilenceInterval],
+  duration: Duration,
+  min_segment: Duration,
+) -> Vec<Duration> {
+  let mut cuts = Vec::new();
+  let mut last = Duration::ZERO;
+  for silence in silences {
+    if silence.start <= EDGE_TOLERANCE || silence.end + EDGE_TOLERANCE >= duration {
+      continue;
+    }
+    let cut = silence.start + silence.duration() / 2;
+    if cut.saturating_sub(last) >= min_segment {
+      cuts.push(cut);
+      last = cut;
+    }
+  }
+  // Merge a short last segment into the previous one
+  if duration.saturating_sub(last) < min_segment {
+    cuts.pop();
+  }
+  cuts
+}
+
+fn audio_duration(input: &Path) -> anyhow::Result<Duration> {
+  let info = probe_cached(input)?;
+  if info.streams_of_type("audio").next().is_none() {
+    return Err(SilenceError::NoAudio.into());
+  }
+  info
+    .duration
+    .filter(|duration| *duration > 0.0)
+    .map(Duration::from_secs_f64)
+    .ok_or_else(|| SilenceError::NoDuration.into())
+}
+
+/// The silences of `input`, a silence still going on when it ends lasting
+/// until `duration`.
+fn detect(
+  input: &Path,
+  duration: Duration,
+  threshold_db: f64,
+  min_silence: Duration,
+) -> Result<Vec<SilenceInterval>, SilenceError> {
+  let mut command = FfmpegCommand::new();
+  command
+    .input(input.to_string_lossy())
+    .map("0:a:0")
+    .args(["-af", &silencedetect_filter(threshold_db, min_silence)])
+    .format("null")
+    .output("-");
+
+  let silences = RefCell::new(Vec::new());
+  let start = RefCell::new(None);
+  run_step("detect", &mut command, |event| {
+    let FfmpegEvent::Log(LogLevel::Info, line) = event else {
+      return;
+    };
+    match try_parse_silence(line) {
+      Some(SilenceMark::Start(seconds)) => {
+        *start.borrow_mut() = Some(Duration::from_secs_f64(seconds.max(0.0)));
+      }
+      Some(SilenceMark::End(seconds)) => {
+        if let Some(start) = start.borrow_mut().take() {
+          let end = Duration::from_secs_f64(seconds.max(0.0)).min(duration);
+          silences.borrow_mut().push(SilenceInterval { start, end });
+        }
+      }
+      None => {}
+    }
+  })?;
+
+  let mut silences = silences.into_inner();
+  if let Some(start) = start.into_inner() {
+    silences.push(SilenceInterval {
+      start,
+      end: duration,
+    });
+  }
+  Ok(silences)
+}
+
+fn run_step<F: Fn(&FfmpegEvent)>(
+  step: &'static str,
+  command: &mut FfmpegCommand,
+  on_event: F,
+) -> Result<(), SilenceError> {
+  let outcome = run_job(command, on_event);
+  if outcome.is_success() {
+    return Ok(());
+  }
+  let message = match outcome.result {
+    Err(e) => e.to_string(),
+    Ok(status) if outcome.errors.is_empty() => format!("ffmpeg exited with {status}"),
+    Ok(_) => outcome.errors.join("\n"),
+  };
+  Err(SilenceError::Failed { step, message })
+}
@@ -0,0 +1,730 @@
+//! How the processes of the crate are started: ffmpeg itself, and the
+//! helpers like ffprobe, `ffmpeg -version` or the tools used to download
+//! ffmpeg. By default they're spawned with `std::process`; a
+//! [`ProcessSpawner`] can start them some other way, e.g. inside a sandbox
+//! with a [`WrapperSpawner`], or not at all with a [`MockSpawner`] which
+//! replays a scripted output, to test code using
+//! [`FfmpegCommand`](crate::command::FfmpegCommand) without ffmpeg.
+//!
+//! ```rust
+//! use ffmpeg_sidecar::{command::FfmpegCommand, spawner::MockSpawner};
+//! use std::sync::Arc;
+//!
+//! let mock = MockSpawner::new()
+//!   .stderr("[info] frame=   25 fps=0.0 q=-0.0 size=       0kB time=00:00:01.00 bitrate=N/A speed=10x\n")
+//!   .exit_code(0);
+//! let mut child = FfmpegCommand::new()
+//!   .spawner(Arc::new(mock))
+//!   .testsrc()
+//!   .output("out.mp4")
+//!   .spawn()
+//!   .unwrap();
+//! assert_eq!(child.iter().unwrap().filter_progress().count(), 1);
+//! assert!(child.wait().unwrap().success());
+//! ```
+
+use std::{
+  ffi::{OsStr, OsString},
+  fmt,
+  io::{self, Read, Write},
+  path::PathBuf,
+  process::{Child, ChildStderr, ChildStdin, ChildStdout, Command, ExitStatus, Output},
+  sync::{Arc, Condvar, Mutex, PoisonError, RwLock},
+  thread,
+};
+
+use crate::{stdio_policy::StdioPolicy, timeout::kill_process};
+
+/// Starts a process, given its program, arguments and [`StdioConfig`].
+///
+/// Set for a single ffmpeg command with
+/// [`FfmpegCommand::spawner`](crate::command::FfmpegCommand::spawner), or for
+/// every process of the crate with [`set_default_spawner`].
+pub trait ProcessSpawner: Send + Sync {
+  fn spawn(
+    &self,
+    program: &OsStr,
+    args: &[OsString],
+    stdio: StdioConfig,
+  ) -> io::Result<SpawnedProcess>;
+}
+
+/// How a process is set up, besides its program and arguments.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StdioConfig {
+  pub stdin: StdioPolicy,
+  pub stdout: StdioPolicy,
+  pub stderr: StdioPolicy,
+  /// Variables set, or removed if `None`, on top of the environment of this
+  /// process.
+  pub envs: Vec<(OsString, Option<OsString>)>,
+  pub current_dir: Option<PathBuf>,
+  /// Make the process the leader of a new process group on Unix, see
+  /// [`FfmpegCommand::contain_process_tree`](crate::command::FfmpegCommand::contain_process_tree).
+  pub new_process_group: bool,
+  /// Don't open a console window on Windows, see
+  /// [`FfmpegCommand::create_no_window`](crate::command::FfmpegCommand::create_no_window).
+  pub create_no_window: bool,
+}
+
+impl StdioConfig {
+  /// The environment and working directory of `command`, with every stream
+  /// piped.
+  pub fn from_command(command: &Command) -> Self {
+    Self {
+      envs: command
+        .get_envs()
+        .map(|(key, value)| (key.to_os_string(), value.map(OsStr::to_os_string)))
+        .collect(),
+      current_dir: command.get_current_dir().map(PathBuf::from),
+      ..Self::default()
+    }
+  }
+
+  /// A `std::process::Command` running `program` with `args`, set up
+  /// accordingly, for spawners which end up starting a local process.
+  pub fn command<S: AsRef<OsStr>>(&self, program: impl AsRef<OsStr>, args: &[S]) -> Command {
+    let mut command = Command::new(program);
+    command
+      .args(args)
+      .stdin(self.stdin.to_stdio())
+      .stdout(self.stdout.to_stdio())
+      .stderr(self.stderr.to_stdio());
+    for (key, value) in &self.envs {
+      match value {
+        Some(value) => command.env(key, value),
+        None => command.env_remove(key),
+      };
+    }
+    if let Some(dir) = &self.current_dir {
+      command.current_dir(dir);
+    }
+    #[cfg(unix)]
+    if self.new_process_group {
+      std::os::unix::process::CommandExt::process_group(&mut command, 0);
+    }
+    // CREATE_NO_WINDOW
+    #[cfg(windows)]
+    if self.create_no_window {
+      std::os::windows::process::CommandExt::creation_flags(&mut command, 0x08000000);
+    }
+    command
+  }
+}
+
+/// A process started by a [`ProcessSpawner`]: a [`std::process::Child`], or
+/// the stand-in of a [`MockSpawner`].
+pub struct SpawnedProcess {
+  inner: Process,
+}
+
+enum Process {
+  Os(Child),
+  Mock(MockProcess),
+}
+
+impl From<Child> for SpawnedProcess {
+  fn from(child: Child) -> Self {
+    Self {
+      inner: Process::Os(child),
+    }
+  }
+}
+
+impl fmt::Debug for SpawnedProcess {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match &self.inner {
+      Process::Os(child) => child.fmt(f),
+      Process::Mock(_) => f.write_str("SpawnedProcess(mock)"),
+    }
+  }
+}
+
+impl SpawnedProcess {
+  /// The OS-assigned process identifier, or `0` for a mocked process.
+  pub fn id(&self) -> u32 {
+    match &self.inner {
+      Process::Os(child) => child.id(),
+      Process::Mock(_) => 0,
+    }
+  }
+
+  pub fn take_stdin(&mut self) -> Option<ChildStdin> {
+    self.stdin_mut().take()
+  }
+
+  pub fn take_stdout(&mut self) -> Option<ChildStdout> {
+    match &mut self.inner {
+      Process::Os(child) => child.stdout.take(),
+      Process::Mock(mock) => mock.stdout.take(),
+    }
+  }
+
+  pub fn take_stderr(&mut self) -> Option<ChildStderr> {
+    match &mut self.inner {
+      Process::Os(child) => child.stderr.take(),
+      Process::Mock(mock) => mock.stderr.take(),
+    }
+  }
+
+  /// Like [`Child::try_wait`].
+  pub fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+    match &mut self.inner {
+      Process::Os(child) => child.try_wait(),
+      Process::Mock(mock) => mock.state.exit_status(mock.exit_code),
+    }
+  }
+
+  /// Like [`Child::wait`], closing stdin first.
+  pub fn wait(&mut self) -> io::Result<ExitStatus> {
+    match &mut self.inner {
+      Process::Os(child) => child.wait(),
+      Process::Mock(mock) => {
+        drop(mock.stdin.take());
+        mock.state.wait(mock.exit_code)
+      }
+    }
+  }
+
+  /// Like [`Child::kill`].
+  pub fn kill(&mut self) -> io::Result<()> {
+    match &mut self.inner {
+      Process::Os(child) => child.kill(),
+      Process::Mock(mock) => {
+        mock.state.stop(true);
+        Ok(())
+      }
+    }
+  }
+
+  /// The `Child`, unless the process is mocked.
+  pub fn as_child(&self) -> Option<&Child> {
+    match &self.inner {
+      Process::Os(child) => Some(child),
+      Process::Mock(_) => None,
+    }
+  }
+
+  /// The `Child`, unless the process is mocked.
+  pub fn as_child_mut(&mut self) -> Option<&mut Child> {
+    match &mut self.inner {
+      Process::Os(child) => Some(child),
+      Process::Mock(_) => None,
+    }
+  }
+
+  /// The `Child`, unless the process is mocked.
+  pub fn into_child(self) -> Option<Child> {
+    match self.inner {
+      Process::Os(child) => Some(child),
+      Process::Mock(_) => None,
+    }
+  }
+
+  pub(crate) fn stdin_mut(&mut self) -> &mut Option<ChildStdin> {
+    match &mut self.inner {
+      Process::Os(child) => &mut child.stdin,
+      Process::Mock(mock) => &mut mock.stdin,
+    }
+  }
+
+  /// A way to kill the process from a timer thread, without the
+  /// `SpawnedProcess` itself.
+  pub(crate) fn killer(&self, process_group: bool) -> Killer {
+    match &self.inner {
+      Process::Os(child) => Killer::Pid {
+        pid: child.id(),
+        process_group,
+      },
+      Process::Mock(mock) => Killer::Mock(mock.state.clone()),
+    }
+  }
+}
+
+/// Kills a process by its pid, or stops a mocked one.
+#[derive(Clone)]
+pub(crate) enum Killer {
+  Pid { pid: u32, process_group: bool },
+  Mock(Arc<MockState>),
+}
+
+impl Killer {
+  pub(crate) fn kill(&self) {
+    match self {
+      Killer::Pid { pid, process_group } => kill_process(*pid, *process_group),
+      Killer::Mock(state) => state.stop(true),
+    }
+  }
+}
+
+/// Spawns processes with `std::process`, the default.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StdSpawner;
+
+impl ProcessSpawner for StdSpawner {
+  fn spawn(
+    &self,
+    program: &OsStr,
+    args: &[OsString],
+    stdio: StdioConfig,
+  ) -> io::Result<SpawnedProcess> {
+    stdio
+      .command(program, args)
+      .spawn()
+      .map(SpawnedProcess::from)
+  }
+}
+
+/// Spawns every process through a wrapper program, like a sandbox or a
+/// resource limiter, which gets the program and its arguments after its own
+/// arguments.
+///
+/// ```rust
+/// use ffmpeg_sidecar::spawner::{set_default_spawner, WrapperSpawner};
+/// use std::sync::Arc;
+///
+/// // Runs `firejail --quiet --net=none ffmpeg ...`
+/// set_default_spawner(Arc::new(WrapperSpawner::new(
+///   "firejail",
+///   ["--quiet", "--net=none"],
+/// )));
+/// ```
+#[derive(Debug, Clone)]
+pub struct WrapperSpawner {
+  program: OsString,
+  args: Vec<OsString>,
+}
+
+impl WrapperSpawner {
+  pub fn new<I, S>(program: impl AsRef<OsStr>, args: I) -> Self
+  where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+  {
+    Self {
+      program: program.as_ref().to_os_string(),
+      args: args
+        .into_iter()
+        .map(|arg| arg.as_ref().to_os_string())
+        .collect(),
+    }
+  }
+}
+
+impl ProcessSpawner for WrapperSpawner {
+  fn spawn(
+    &self,
+    program: &OsStr,
+    args: &[OsString],
+    stdio: StdioConfig,
+  ) -> io::Result<SpawnedProcess> {
+    let mut wrapped = self.args.clone();
+    wrapped.push(program.to_os_string());
+    wrapped.extend_from_slice(args);
+    StdSpawner.spawn(&self.program, &wrapped, stdio)
+  }
+}
+
+/// Doesn't start any process, but returns one which writes the scripted
+/// stdout and stderr and exits with the scripted code, to test the handling
+/// of ffmpeg's output hermetically. Stderr should be written the way the
+/// crate makes ffmpeg log, with a `[level]` prefix on each line.
+///
+/// Like ffmpeg, the process stops writing and exits once it reads `q` from
+/// stdin, and reports being killed by a signal on Unix if killed. Each spawn
+/// replays the whole script, and is recorded in [`MockSpawner::spawned`].
+#[derive(Debug, Clone, Default)]
+pub struct MockSpawner {
+  stdout: Vec<u8>,
+  stderr: Vec<u8>,
+  exit_code: i32,
+  spawned: Arc<Mutex<Vec<Vec<OsString>>>>,
+}
+
+impl MockSpawner {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// The bytes written to stdout, e.g. raw output frames.
+  pub fn stdout(mut self, bytes: impl Into<Vec<u8>>) -> Self {
+    self.stdout = bytes.into();
+    self
+  }
+
+  /// The bytes written to stderr, line by line.
+  pub fn stderr(mut self, bytes: impl Into<Vec<u8>>) -> Self {
+    self.stderr = bytes.into();
+    self
+  }
+
+  /// The exit code once everything was written, `0` by default.
+  pub fn exit_code(mut self, code: i32) -> Self {
+    self.exit_code = code;
+    self
+  }
+
+  /// The program and arguments of each process spawned so far, by this
+  /// spawner and its clones.
+  pub fn spawned(&self) -> Vec<Vec<OsString>> {
+    self
+      .spawned
+      .lock()
+      .map(|spawned| spawned.clone())
+      .unwrap_or_default()
+  }
+}
+
+impl ProcessSpawner for MockSpawner {
+  fn spawn(
+    &self,
+    program: &OsStr,
+    args: &[OsString],
+    stdio: StdioConfig,
+  ) -> io::Result<SpawnedProcess> {
+    if let Ok(mut spawned) = self.spawned.lock() {
+      spawned.push(
+        std::iter::once(program.to_os_string())
+          .chain(args.iter().cloned())
+          .collect(),
+      );
+    }
+    #[cfg(any(unix, windows))]
+    return MockProcess::start(self, &stdio).map(|mock| SpawnedProcess {
+      inner: Process::Mock(mock),
+    });
+
+    #[cfg(not(any(unix, windows)))]
+    {
+      let _ = stdio;
+      Err(io::ErrorKind::Unsupported.into())
+    }
+  }
+}
+
+struct MockProcess {
+  stdin: Option<ChildStdin>,
+  stdout: Option<ChildStdout>,
+  stderr: Option<ChildStderr>,
+  exit_code: i32,
+  state: Arc<MockState>,
+}
+
+impl MockProcess {
+  #[cfg(any(unix, windows))]
+  fn start(spawner: &MockSpawner, stdio: &StdioConfig) -> io::Result<Self> {
+    let state = Arc::new(MockState::default());
+    let (stdout, stdout_target) = mock_output(stdio.stdout, io::stdout)?;
+    let (stderr, stderr_target) = mock_output(stdio.stderr, io::stderr)?;
+    let stdin = match stdio.stdin {
+      StdioPolicy::Piped => {
+        let (mut reader, writer) = io::pipe()?;
+        let state = state.clone();
+        thread::spawn(move || {
+          let mut buffer = [0; 64];
+          while let Ok(read @ 1..) = reader.read(&mut buffer) {
+            if buffer[..read].contains(&b'q') {
+              state.stop(false);
+            }
+          }
+        });
+        Some(platform::child_stdin(writer))
+      }
+      StdioPolicy::Inherit | StdioPolicy::Null => None,
+    };
+    for (bytes, target) in [
+      (spawner.stdout.clone(), stdout_target),
+      (spawner.stderr.clone(), stderr_target),
+    ] {
+      state.started_writer();
+      let state = state.clone();
+      thread::spawn(move || state.write_script(&bytes, target));
+    }
+    Ok(Self {
+      stdin,
+      stdout: stdout.map(platform::child_stdout),
+      stderr: stderr.map(platform::child_stderr),
+      exit_code: spawner.exit_code,
+      state,
+    })
+  }
+}
+
+/// Where a mocked process writes a scripted output: a pipe whose reader is
+/// returned, the stream of this process, or nowhere.
+fn mock_output<W: Write + Send + 'static>(
+  policy: StdioPolicy,
+  inherited: fn() -> W,
+) -> io::Result<(Option<io::PipeReader>, Box<dyn Write + Send>)> {
+  Ok(match policy {
+    StdioPolicy::Piped => {
+      let (reader, writer) = io::pipe()?;
+      (Some(reader), Box::new(writer))
+    }
+    StdioPolicy::Inherit => (None, Box::new(inherited())),
+    StdioPolicy::Null => (None, Box::new(io::sink())),
+  })
+}
+
+#[derive(Default)]
+struct MockStatus {
+  writers: usize,
+  stopped: bool,
+  killed: bool,
+}
+
+impl MockStatus {
+  /// Exited once the script is written or stopped, even if a writer is
+  /// still blocked on a full pipe.
+  fn exited(&self, exit_code: i32) -> Option<ExitStatus> {
+    match (self.killed, self.stopped || self.writers == 0) {
+      (true, _) => Some(platform::killed_status()),
+      (false, true) => Some(platform::exit_status(exit_code)),
+      (false, false) => None,
+    }
+  }
+}
+
+/// Shared between a mocked process, the threads writing its output, and
+/// the timers which may kill it.
+#[derive(Default)]
+pub(crate) struct MockState {
+  status: Mutex<MockStatus>,
+  changed: Condvar,
+}
+
+impl MockState {
+  fn status(&self) -> std::sync::MutexGuard<'_, MockStatus> {
+    self.status.lock().unwrap_or_else(PoisonError::into_inner)
+  }
+
+  fn started_writer(&self) {
+    self.status().writers += 1;
+  }
+
+  /// Stop writing the script, as if killed or quit.
+  fn stop(&self, killed: bool) {
+    let mut status = self.status();
+    status.stopped = true;
+    status.killed |= killed;
+    self.changed.notify_all();
+  }
+
+  fn write_script(&self, bytes: &[u8], mut target: Box<dyn Write + Send>) {
+    for line in bytes.split_inclusive(|&byte| byte == b'\n') {
+      if self.status().stopped || target.write_all(line).is_err() {
+        break;
+      }
+    }
+    // Closes the pipe
+    drop(target);
+    self.status().writers -= 1;
+    self.changed.notify_all();
+  }
+
+  fn exit_status(&self, exit_code: i32) -> io::Result<Option<ExitStatus>> {
+    Ok(self.status().exited(exit_code))
+  }
+
+  fn wait(&self, exit_code: i32) -> io::Result<ExitStatus> {
+    let mut status = self.status();
+    loop {
+      if let Some(exited) = status.exited(exit_code) {
+        return Ok(exited);
+      }
+      status = self
+        .changed
+        .wait(status)
+        .unwrap_or_else(PoisonError::into_inner);
+    }
+  }
+}
+
+#[cfg(unix)]
+mod platform {
+  use std::{
+    io::{PipeReader, PipeWriter},
+    os::{fd::OwnedFd, unix::process::ExitStatusExt},
+    process::{ChildStderr, ChildStdin, ChildStdout, ExitStatus},
+  };
+
+  pub(super) fn child_stdin(writer: PipeWriter) -> ChildStdin {
+    OwnedFd::from(writer).into()
+  }
+
+  pub(super) fn child_stdout(reader: PipeReader) -> ChildStdout {
+    OwnedFd::from(reader).into()
+  }
+
+  pub(super) fn child_stderr(reader: PipeReader) -> ChildStderr {
+    OwnedFd::from(reader).into()
+  }
+
+  /// The wait status of a normal exit, with the code in the second byte.
+  pub(super) fn exit_status(code: i32) -> ExitStatus {
+    ExitStatus::from_raw((code & 0xff) << 8)
+  }
+
+  /// Terminated by `SIGKILL`.
+  pub(super) fn killed_status() -> ExitStatus {
+    ExitStatus::from_raw(9)
+  }
+}
+
+#[cfg(windows)]
+mod platform {
+  use std::{
+    io::{PipeReader, PipeWriter},
+    os::windows::{io::OwnedHandle, process::ExitStatusExt},
+    process::{ChildStderr, ChildStdin, ChildStdout, ExitStatus},
+  };
+
+  pub(super) fn child_stdin(writer: PipeWriter) -> ChildStdin {
+    OwnedHandle::from(writer).into()
+  }
+
+  pub(super) fn child_stdout(reader: PipeReader) -> ChildStdout {
+    OwnedHandle::from(reader).into()
+  }
+
+  pub(super) fn child_stderr(reader: PipeReader) -> ChildStderr {
+    OwnedHandle::from(reader).into()
+  }
+
+  pub(super) fn exit_status(code: i32) -> ExitStatus {
+    ExitStatus::from_raw(code as u32)
+  }
+
+  /// `TerminateProcess` exits with code 1.
+  pub(super) fn killed_status() -> ExitStatus {
+    ExitStatus::from_raw(1)
+  }
+}
+
+#[cfg(not(any(unix, windows)))]
+mod platform {
+  use std::process::ExitStatus;
+
+  pub(super) fn exit_status(_code: i32) -> ExitStatus {
+    ExitStatus::default()
+  }
+
+  pub(super) fn killed_status() -> ExitStatus {
+    ExitStatus::default()
+  }
+}
+
+static DEFAULT_SPAWNER: RwLock<Option<Arc<dyn ProcessSpawner>>> = RwLock::new(None);
+
+/// Spawn every process of the crate with `spawner`: ffmpeg, unless its
+/// command has its own [`spawner`](crate::command::FfmpegCommand::spawner),
+/// and the helpers like ffprobe, version checks and download tools.
+///
+/// The OS utilities used to kill processes or list orphans are still run
+/// directly, since they act on processes of this machine.
+///
+/// ```rust
+/// use ffmpeg_sidecar::{
+///   ffprobe::ffprobe_version,
+///   spawner::{reset_default_spawner, set_default_spawner, MockSpawner},
+/// };
+/// use std::sync::Arc;
+///
+/// let mock = MockSpawner::new().stdout("ffprobe version 7.1 Copyright (c) 2007-2024\n");
+/// set_default_spawner(Arc::new(mock.clone()));
+/// assert_eq!(ffprobe_version().unwrap(), "7.1");
+/// assert_eq!(mock.spawned()[0][1], "-version");
+/// reset_default_spawner();
+/// ```
+pub fn set_default_spawner(spawner: Arc<dyn ProcessSpawner>) {
+  *DEFAULT_SPAWNER
+    .write()
+    .unwrap_or_else(PoisonError::into_inner) = Some(spawner);
+}
+
+/// Go back to spawning processes with [`StdSpawner`].
+pub fn reset_default_spawner() {
+  *DEFAULT_SPAWNER
+    .write()
+    .unwrap_or_else(PoisonError::into_inner) = None;
+}
+
+/// The spawner set with [`set_default_spawner`], if any.
+pub(crate) fn custom_default_spawner() -> Option<Arc<dyn ProcessSpawner>> {
+  DEFAULT_SPAWNER
+    .read()
+    .unwrap_or_else(PoisonError::into_inner)
+    .clone()
+}
+
+/// Spawn `command` with the default spawner, and `stdio` instead of the
+/// stdio set on `command`. A custom spawner only gets the program,
+/// arguments, environment and working directory of `command`.
+pub(crate) fn spawn(command: &mut Command, stdio: StdioConfig) -> io::Result<SpawnedProcess> {
+  match custom_default_spawner() {
+    Some(spawner) => {
+      let stdio = StdioConfig {
+        envs: StdioConfig::from_command(command).envs,
+        current_dir: command.get_current_dir().map(PathBuf::from),
+        ..stdio
+      };
+      let args = command
+        .get_args()
+        .map(OsStr::to_os_string)
+        .collect::<Vec<_>>();
+      spawner.spawn(command.get_program(), &args, stdio)
+    }
+    None => command
+      .stdin(stdio.stdin.to_stdio())
+      .stdout(stdio.stdout.to_stdio())
+      .stderr(stdio.stderr.to_stdio())
+      .spawn()
+      .map(SpawnedProcess::from),
+  }
+}
+
+/// Like [`Command::output`], with the default spawner. A custom spawner gets
+/// a null stdin.
+pub(crate) fn output(command: &mut Command) -> io::Result<Output> {
+  if custom_default_spawner().is_none() {
+    return command.output();
+  }
+  let stdio = StdioConfig {
+    stdin: StdioPolicy::Null,
+    ..StdioConfig::default()
+  };
+  let mut process = spawn(command, stdio)?;
+  let read_all = |pipe: Option<Box<dyn Read + Send>>| {
+    thread::spawn(move || {
+      let mut bytes = Vec::new();
+      if let Some(mut pipe) = pipe {
+        pipe.read_to_end(&mut bytes).ok();
+      }
+      bytes
+    })
+  };
+  let stdout = read_all(process.take_stdout().map(|pipe| Box::new(pipe) as _));
+  let stderr = read_all(process.take_stderr().map(|pipe| Box::new(pipe) as _));
+  let status = process.wait()?;
+  Ok(Output {
+    status,
+    stdout: stdout.join().unwrap_or_default(),
+    stderr: stderr.join().unwrap_or_default(),
+  })
+}
+
+/// Like [`Command::status`], with the default spawner. A custom spawner gets
+/// a null stdin and the stdout and stderr of this process.
+#[cfg(feature = "download")]
+pub(crate) fn status(command: &mut Command) -> io::Result<ExitStatus> {
+  if custom_default_spawner().is_none() {
+    return command.status();
+  }
+  let stdio = StdioConfig {
+    stdin: StdioPolicy::Null,
+    stdout: StdioPolicy::Inherit,
+    stderr: StdioPolicy::Inherit,
+    ..StdioConfig::default()
+  };
+  spawn(command, stdio)?.wait()
+}
@@ -0,0 +1,335 @@
+//! Statistics of a long-running job, like a 24/7 restream, aggregated into
+//! buckets of a fixed duration, e.g. hourly, so memory doesn't grow with the
+//! number of progress updates.
+
+use std::{
+  collections::VecDeque,
+  sync::{Arc, Mutex},
+  time::{Duration, Instant},
+};
+
+use crate::event::{FfmpegEvent, FfmpegProgress};
+
+/// The message ffmpeg logs when an HTTP input reconnects, see
+/// [`InputNetworkOptions::reconnect`](crate::network::InputNetworkOptions::reconnect).
+pub const RECONNECT_MESSAGE: &str = "Will reconnect at";
+
+/// The statistics of a bucket, or of several merged, emitted as
+/// [`FfmpegEvent::StatsRollup`] when a bucket ends.
+///
+/// ffmpeg's counters (frames, dropped frames and size) start again from 0
+/// when an output is reopened. A counter going back starts a new epoch,
+/// counted in `counter_resets`, and counts from 0 again, so the totals keep
+/// growing.
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StatsRollup {
+  /// When the bucket starts, since the aggregator was created.
+  pub start: Duration,
+  pub duration: Duration,
+  /// The number of progress updates.
+  pub updates: u64,
+  /// The average of the `fps` of the progress updates.
+  pub average_fps: Option<f64>,
+  /// The average of the `bitrate` of the progress updates, in kilobits per
+  /// second.
+  pub average_bitrate_kbps: Option<f64>,
+  pub frames: u64,
+  pub dropped_frames: u64,
+  pub bytes_out: u64,
+  /// The reconnections of HTTP inputs, from the lines ffmpeg logs
+  /// containing [`RECONNECT_MESSAGE`].
+  pub reconnects: u64,
+  pub counter_resets: u64,
+}
+
+/// The statistics returned by [`StatsAggregator::snapshot`].
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct StatsSnapshot {
+  /// The buckets within the window, oldest first, the last one still in
+  /// progress.
+  pub buckets: Vec<StatsRollup>,
+  /// The buckets within the window, merged.
+  pub window: StatsRollup,
+  /// Everything since the aggregator was created.
+  pub total: StatsRollup,
+}
+
+/// Aggregates the progress updates and reconnections of a job into buckets
+/// of `bucket` duration, keeping the ones within the last `window`.
+///
+/// Events are passed to [`offer`](Self::offer), e.g. from a
+/// [`subscribe`](crate::child::FfmpegChild::subscribe)d thread, or by the
+/// iterator of [`aggregate_stats`](crate::iter::FfmpegIterator::aggregate_stats),
+/// which emits a [`FfmpegEvent::StatsRollup`] as each bucket ends. Clones
+/// share the same statistics, so one can be kept for
+/// [`snapshot`](Self::snapshot).
+///
+/// ```rust
+/// use ffmpeg_sidecar::{event::FfmpegEvent, log_parser::try_parse_progress, stats::StatsAggregator};
+/// use std::time::{Duration, Instant};
+///
+/// let start = Instant::now();
+/// let minute = Duration::from_secs(60);
+/// let stats = StatsAggregator::with_start(start, 10 * minute, minute);
+/// let progress = |frame: u32| {
+///   let line = format!("[info] frame={frame} fps=25 q=28.0 size=1000kB time=00:00:04.00 bitrate=2048.0kbits/s speed=1x");
+///   FfmpegEvent::Progress(try_parse_progress(&line).unwrap())
+/// };
+/// stats.offer(&progress(100), start + Duration::from_secs(10));
+/// let rollups = stats.offer(&progress(250), start + Duration::from_secs(70));
+/// assert_eq!(rollups.len(), 1);
+/// assert_eq!(rollups[0].frames, 100);
+/// assert_eq!(rollups[0].average_fps, Some(25.0));
+/// assert_eq!(stats.snapshot().total.frames, 250);
+/// ```
+#[derive(Debug, Clone)]
+pub struct StatsAggregator {
+  state: Arc<Mutex<StatsState>>,
+}
+
+impl StatsAggregator {
+  /// An aggregator starting now. `bucket` is at least 1ms, and `window` at
+  /// least one bucket.
+  pub fn new(window: Duration, bucket: Duration) -> Self {
+    Self::with_start(Instant::now(), window, bucket)
+  }
+
+  /// An aggregator whose first bucket starts at `start`.
+  pub fn with_start(start: Instant, window: Duration, bucket: Duration) -> Self {
+    let bucket = bucket.max(Duration::from_millis(1));
+    let state = StatsState {
+      start,
+      bucket,
+      max_buckets: (window.as_nanos() / bucket.as_nanos()).max(1) as usize,
+      buckets: VecDeque::from([Bucket::default()]),
+      current: 0,
+      total: Bucket::default(),
+      last: Counters::default(),
+    };
+    Self {
+      state: Arc::new(Mutex::new(state)),
+    }
+  }
+
+  /// Count `event`, received at `now`, returning the rollups of the buckets
+  /// which ended before it, oldest first.
+  pub fn offer(&self, event: &FfmpegEvent, now: Instant) -> Vec<StatsRollup> {
+    let Ok(mut state) = self.state.lock() else {
+      return Vec::new();
+    };
+    let rollups = state.advance(now);
+    match event {
+      FfmpegEvent::Progress(progress) => state.add_progress(progress),
+      FfmpegEvent::Log(_, message) if message.contains(RECONNECT_MESSAGE) => {
+        state.add(|bucket| bucket.reconnects += 1)
+      }
+      _ => {}
+    }
+    rollups
+  }
+
+  /// The rollups of the buckets which ended before `now`, oldest first.
+  pub fn advance(&self, now: Instant) -> Vec<StatsRollup> {
+    match self.state.lock() {
+      Ok(mut state) => state.advance(now),
+      Err(_) => Vec::new(),
+    }
+  }
+
+  /// The rollup of the bucket in progress, e.g. to report the last partial
+  /// hour once the job ends.
+  pub fn current(&self) -> StatsRollup {
+    match self.state.lock() {
+      Ok(state) => state.rollup(state.current, state.buckets.back()),
+      Err(_) => StatsRollup::default(),
+    }
+  }
+
+  /// The statistics as of the last event, or call to
+  /// [`advance`](Self::advance).
+  pub fn snapshot(&self) -> StatsSnapshot {
+    let Ok(state) = self.state.lock() else {
+      return StatsSnapshot::default();
+    };
+    let first = state.current + 1 - state.buckets.len() as u64;
+    let buckets = (first..)
+      .zip(&state.buckets)
+      .map(|(index, bucket)| state.rollup(index, Some(bucket)))
+      .collect::<Vec<_>>();
+    let merged = state.buckets.iter().fold(Bucket::default(), Bucket::merge);
+    let mut window = merged.rollup();
+    window.start = state.offset(first);
+    window.duration = state.offset(state.current + 1) - window.start;
+    // Up to the end of the bucket in progress
+    let mut total = state.total.rollup();
+    total.duration = state.offset(state.current + 1);
+    StatsSnapshot {
+      buckets,
+      window,
+      total,
+    }
+  }
+}
+
+#[derive(Debug)]
+struct StatsState {
+  start: Instant,
+  bucket: Duration,
+  max_buckets: usize,
+  /// The last `max_buckets`, the last one being `current`.
+  buckets: VecDeque<Bucket>,
+  /// The index of the bucket in progress since `start`.
+  current: u64,
+  total: Bucket,
+  /// The counters of the last progress update.
+  last: Counters,
+}
+
+impl StatsState {
+  fn advance(&mut self, now: Instant) -> Vec<StatsRollup> {
+    let elapsed = now.saturating_duration_since(self.start);
+    let index = (elapsed.as_nanos() / self.bucket.as_nanos()) as u64;
+    let mut rollups = Vec::new();
+    while self.current < index {
+      rollups.push(self.rollup(self.current, self.buckets.back()));
+      // After a gap longer than the window, the empty buckets before it are
+      // skipped
+      let next = (self.current + 1).max((index + 1).saturating_sub(self.max_buckets as u64));
+      if next > self.current + 1 {
+        self.buckets.clear();
+      }
+      self.current = next;
+      self.buckets.push_back(Bucket::default());
+      while self.buckets.len() > self.max_buckets {
+        self.buckets.pop_front();
+      }
+    }
+    rollups
+  }
+
+  fn add(&mut self, update: impl Fn(&mut Bucket)) {
+    update(&mut self.total);
+    if let Some(bucket) = self.buckets.back_mut() {
+      update(bucket);
+    }
+  }
+
+  fn add_progress(&mut self, progress: &FfmpegProgress) {
+    let counters = Counters {
+      frames: progress.frame.map(u64::from),
+      dropped_frames: Some(u64::from(progress.drop_frames)),
+      bytes_out: progress.size_kb.map(|size| u64::from(size) * 1024),
+    };
+    let reset = counters.went_back(&self.last);
+    let (frames, dropped_frames, bytes_out) = (
+      delta(counters.frames, self.last.frames, reset),
+      delta(counters.dropped_frames, self.last.dropped_frames, reset),
+      delta(counters.bytes_out, self.last.bytes_out, reset),
+    );
+    self.last = counters;
+    let fps = progress.fps.map(f64::from);
+    let bitrate = progress.bitrate_kbps.map(f64::from);
+    self.add(|bucket| {
+      bucket.updates += 1;
+      bucket.frames += frames;
+      bucket.dropped_frames += dropped_frames;
+      bucket.bytes_out += bytes_out;
+      bucket.counter_resets += u64::from(reset);
+      if let Some(fps) = fps {
+        bucket.fps_sum += fps;
+        bucket.fps_count += 1;
+      }
+      if let Some(bitrate) = bitrate {
+        bucket.bitrate_sum += bitrate;
+        bucket.bitrate_count += 1;
+      }
+    });
+  }
+
+  fn rollup(&self, index: u64, bucket: Option<&Bucket>) -> StatsRollup {
+    let mut rollup = bucket.map(Bucket::rollup).unwrap_or_default();
+    rollup.start = self.offset(index);
+    rollup.duration = self.bucket;
+    rollup
+  }
+
+  /// When the bucket at `index` starts, since `start`.
+  fn offset(&self, index: u64) -> Duration {
+    let nanos = self.bucket.as_nanos() * u128::from(index);
+    Duration::from_nanos(u64::try_from(nanos).unwrap_or(u64::MAX))
+  }
+}
+
+/// The increase of a counter since the last update, or its value in a new
+/// epoch.
+fn delta(value: Option<u64>, last: Option<u64>, reset: bool) -> u64 {
+  match (value, last) {
+    (Some(value), Some(last)) if !reset => value.saturating_sub(last),
+    (Some(value), _) => value,
+    (None, _) => 0,
+  }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Counters {
+  frames: Option<u64>,
+  dropped_frames: Option<u64>,
+  bytes_out: Option<u64>,
+}
+
+impl Counters {
+  fn went_back(&self, last: &Counters) -> bool {
+    let back = |value: Option<u64>, last: Option<u64>| matches!((value, last), (Some(value), Some(last)) if value < last);
+    back(self.frames, last.frames)
+      || back(self.dropped_frames, last.dropped_frames)
+      || back(self.bytes_out, last.bytes_out)
+  }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Bucket {
+  updates: u64,
+  fps_sum: f64,
+  fps_count: u64,
+  bitrate_sum: f64,
+  bitrate_count: u64,
+  frames: u64,
+  dropped_frames: u64,
+  bytes_out: u64,
+  reconnects: u64,
+  counter_resets: u64,
+}
+
+impl Bucket {
+  fn merge(self, other: &Bucket) -> Bucket {
+    Bucket {
+      updates: self.updates + other.updates,
+      fps_sum: self.fps_sum + other.fps_sum,
+      fps_count: self.fps_count + other.fps_count,
+      bitrate_sum: self.bitrate_sum + other.bitrate_sum,
+      bitrate_count: self.bitrate_count + other.bitrate_count,
+      frames: self.frames + other.frames,
+      dropped_frames: self.dropped_frames + other.dropped_frames,
+      bytes_out: self.bytes_out + other.bytes_out,
+      reconnects: self.reconnects + other.reconnects,
+      counter_resets: self.counter_resets + other.counter_resets,
+    }
+  }
+
+  fn rollup(&self) -> StatsRollup {
+    let average = |sum: f64, count: u64| (count > 0).then(|| sum / count as f64);
+    StatsRollup {
+      start: Duration::ZERO,
+      duration: Duration::ZERO,
+      updates: self.updates,
+      average_fps: average(self.fps_sum, self.fps_count),
+      average_bitrate_kbps: average(self.bitrate_sum, self.bitrate_count),
+      frames: self.frames,
+      dropped_frames: self.dropped_frames,
+      bytes_out: self.bytes_out,
+      reconnects: self.reconnects,
+      counter_resets: self.counter_resets,
+    }
+  }
+}
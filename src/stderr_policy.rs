@@ -0,0 +1,158 @@
+//! How much of ffmpeg's stderr is parsed into events. See
+//! [`FfmpegCommand::stderr_policy`](crate::command::FfmpegCommand::stderr_policy).
+
+use std::{
+  borrow::Cow,
+  sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc,
+  },
+};
+
+use crate::{error::FfmpegErrorKind, redact::Redactor};
+
+/// The level of a log line, from the prefix added by `-loglevel level+...`,
+/// ordered from the most to the least severe.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Verbosity {
+  Panic,
+  Fatal,
+  Error,
+  Warning,
+  Info,
+  Verbose,
+  Debug,
+  Trace,
+}
+
+impl Verbosity {
+  /// Parse the name used by ffmpeg's `-loglevel` and log prefixes.
+  pub fn from_name(name: &str) -> Option<Self> {
+    Some(match name {
+      "panic" => Verbosity::Panic,
+      "fatal" => Verbosity::Fatal,
+      "error" => Verbosity::Error,
+      "warning" => Verbosity::Warning,
+      "info" => Verbosity::Info,
+      "verbose" => Verbosity::Verbose,
+      "debug" => Verbosity::Debug,
+      "trace" => Verbosity::Trace,
+      _ => return None,
+    })
+  }
+
+  /// The name used by ffmpeg's `-loglevel`, the reverse of
+  /// [`from_name`](Self::from_name).
+  pub fn name(&self) -> &'static str {
+    match self {
+      Verbosity::Panic => "panic",
+      Verbosity::Fatal => "fatal",
+      Verbosity::Error => "error",
+      Verbosity::Warning => "warning",
+      Verbosity::Info => "info",
+      Verbosity::Verbose => "verbose",
+      Verbosity::Debug => "debug",
+      Verbosity::Trace => "trace",
+    }
+  }
+
+  /// The level of a log line, found in its first or second bracketed prefix,
+  /// as in `[info] ...` or `[libx264 @ 0x7f...] [info] ...`.
+  ///
+  /// ```rust
+  /// use ffmpeg_sidecar::stderr_policy::Verbosity;
+  ///
+  /// assert_eq!(Verbosity::of_line("[trace] read_frame_internal"), Some(Verbosity::Trace));
+  /// assert_eq!(
+  ///   Verbosity::of_line("[h264 @ 0x5581e5c0] [debug] nal_unit_type: 5"),
+  ///   Some(Verbosity::Debug)
+  /// );
+  /// assert_eq!(Verbosity::of_line("no prefix"), None);
+  /// ```
+  pub fn of_line(line: &str) -> Option<Self> {
+    let mut rest = line.trim_start();
+    for _ in 0..2 {
+      let (tag, after) = rest.strip_prefix('[')?.split_once(']')?;
+      if let Some(verbosity) = Self::from_name(tag) {
+        return Some(verbosity);
+      }
+      rest = after.trim_start();
+    }
+    None
+  }
+}
+
+/// Which lines of ffmpeg's stderr are parsed into events by the iterator.
+///
+/// Events are handed to the iterator one at a time, so with
+/// [`ParseAll`](StderrPolicy::ParseAll), a consumer which falls behind makes
+/// ffmpeg block once the stderr pipe is full. The other policies drop lines
+/// as soon as they're read, before parsing them, so a flood of verbose
+/// output from e.g. `-loglevel trace` or the `showinfo` filter never backs
+/// up. Dropped lines are counted by
+/// [`FfmpegChild::dropped_stderr_lines`](crate::child::FfmpegChild::dropped_stderr_lines).
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub enum StderrPolicy {
+  /// Parse every line into an event.
+  #[default]
+  ParseAll,
+  /// Drop lines less severe than `keep_levels`. Lines without a level are
+  /// kept. Metadata and progress are logged at `Info`, so the iterator needs
+  /// at least that to work as usual.
+  ParseDropVerbose { keep_levels: Verbosity },
+  /// Drop every line except the errors recognized by
+  /// [`FfmpegErrorKind::classify`]. Since metadata isn't parsed, output on
+  /// stdout isn't read by the iterator, so this is for outputs written to
+  /// files.
+  RawDiscard,
+}
+
+/// Applies a policy in the threads reading stderr, counting what it drops,
+/// and redacts the credentials of the command's URLs from the lines kept.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct StderrFilter {
+  policy: StderrPolicy,
+  dropped: Arc<AtomicU64>,
+  redactor: Redactor,
+}
+
+impl StderrFilter {
+  #[cfg(feature = "process")]
+  pub(crate) fn new(policy: StderrPolicy) -> Self {
+    Self {
+      policy,
+      dropped: Arc::default(),
+      redactor: Redactor::default(),
+    }
+  }
+
+  #[cfg(feature = "process")]
+  pub(crate) fn set_redactor(&mut self, redactor: Redactor) {
+    self.redactor = redactor;
+  }
+
+  /// The line without the credentials of the command's URLs.
+  pub(crate) fn redact<'a>(&self, line: &'a str) -> Cow<'a, str> {
+    self.redactor.redact(line)
+  }
+
+  /// Whether the line should be parsed, counting it if not.
+  pub(crate) fn keep(&self, line: &str) -> bool {
+    let keep = match &self.policy {
+      StderrPolicy::ParseAll => true,
+      StderrPolicy::ParseDropVerbose { keep_levels } => {
+        Verbosity::of_line(line).is_none_or(|verbosity| verbosity <= *keep_levels)
+      }
+      StderrPolicy::RawDiscard => FfmpegErrorKind::classify(line).is_some(),
+    };
+    if !keep {
+      self.dropped.fetch_add(1, Ordering::Relaxed);
+    }
+    keep
+  }
+
+  #[cfg(feature = "process")]
+  pub(crate) fn dropped(&self) -> u64 {
+    self.dropped.load(Ordering::Relaxed)
+  }
+}
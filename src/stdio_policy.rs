@@ -0,0 +1,40 @@
+//! Where ffmpeg's stdin, stdout and stderr are connected. See
+//! [`FfmpegCommand::stderr_stdio`](crate::command::FfmpegCommand::stderr_stdio).
+
+use std::{fmt, process::Stdio};
+
+/// How one of ffmpeg's standard streams is set up when spawning.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum StdioPolicy {
+  /// A pipe to this process, read or written through the
+  /// [`FfmpegChild`](crate::child::FfmpegChild). Needed for events (stderr),
+  /// output frames (stdout), or feeding input and
+  /// [`quit`](crate::child::FfmpegChild::quit) (stdin).
+  #[default]
+  Piped,
+  /// The same stream as this process, e.g. to see ffmpeg's log in the
+  /// terminal while debugging.
+  Inherit,
+  /// Discarded, or empty for stdin.
+  Null,
+}
+
+impl StdioPolicy {
+  pub fn to_stdio(&self) -> Stdio {
+    match self {
+      StdioPolicy::Piped => Stdio::piped(),
+      StdioPolicy::Inherit => Stdio::inherit(),
+      StdioPolicy::Null => Stdio::null(),
+    }
+  }
+}
+
+impl fmt::Display for StdioPolicy {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(match self {
+      StdioPolicy::Piped => "piped",
+      StdioPolicy::Inherit => "inherited",
+      StdioPolicy::Null => "null",
+    })
+  }
+}
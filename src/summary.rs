@@ -0,0 +1,117 @@
+use crate::{
+  error::{is_decode_error, FfmpegErrorKind, FrameTooLarge, InvalidOption},
+  error_policy::ErrorLimitReached,
+  event::{EncoderStats, FfmpegEncodeSummary, FfmpegEvent, LogLevel, SyncWarning},
+};
+
+/// The most errors kept in [`FfmpegSummary::error_messages`].
+pub const MAX_ERROR_MESSAGES: usize = 100;
+
+/// Statistics accumulated over the course of an ffmpeg run, available from
+/// [`FfmpegChild::summary`](crate::child::FfmpegChild::summary).
+///
+/// The summary is filled in as events are read from the
+/// [`FfmpegIterator`](crate::iter::FfmpegIterator), so it's only complete once
+/// the iterator has been exhausted.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FfmpegSummary {
+  /// Frames duplicated by ffmpeg to maintain the output frame rate, as of the
+  /// last progress update.
+  pub dup_frames: u32,
+  /// Frames dropped by ffmpeg to maintain the output frame rate, as of the
+  /// last progress update.
+  pub drop_frames: u32,
+  /// Frames reported as dropped by the capture device, before they reached
+  /// ffmpeg (e.g. a full dshow real-time buffer).
+  pub device_drop_frames: u32,
+  /// Number of `Non-monotonous DTS` warnings.
+  pub non_monotonic_dts: u32,
+  /// Number of `Past duration ... too large` warnings.
+  pub past_duration: u32,
+  /// Recognized causes of the errors logged so far, without duplicates.
+  pub errors: Vec<FfmpegErrorKind>,
+  /// Number of decoder errors about damaged input, like `corrupt decoded
+  /// frame` or `concealing ... errors`, which ffmpeg skips over unless
+  /// [`fail_on_error`](crate::command::FfmpegCommand::fail_on_error) is set.
+  pub decode_errors: u32,
+  /// Whether ffmpeg confirmed that it reads key presses from stdin, with an
+  /// [`FfmpegEvent::Interactive`] event.
+  pub interactive: bool,
+  /// The sizes of the streams written, from the last
+  /// [`FfmpegEvent::EncodeSummary`] event.
+  pub encode: Option<FfmpegEncodeSummary>,
+  /// The frame statistics of each libx264 or libx265 encoder.
+  pub encoder_stats: Vec<EncoderStats>,
+  /// The first option rejected by ffmpeg, from an
+  /// [`FfmpegEvent::InvalidOption`] event.
+  pub invalid_option: Option<InvalidOption>,
+  /// Number of error lines logged, and of [`FfmpegEvent::Error`] events.
+  pub error_count: u32,
+  /// The first [`MAX_ERROR_MESSAGES`] errors counted in `error_count`.
+  pub error_messages: Vec<String>,
+  /// Set once ffmpeg was killed because of its
+  /// [`error_policy`](crate::child::FfmpegChild::error_policy).
+  pub error_limit_reached: Option<ErrorLimitReached>,
+  /// The output whose frames were too large to read, see
+  /// [`FfmpegChild::max_frame_bytes`](crate::child::FfmpegChild::max_frame_bytes).
+  pub frame_too_large: Option<FrameTooLarge>,
+}
+
+impl FfmpegSummary {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Total number of dropped frames, whether by ffmpeg or by the device.
+  pub fn total_dropped(&self) -> u32 {
+    self.drop_frames + self.device_drop_frames
+  }
+
+  /// Whether any frames were duplicated or dropped, or timestamps had to be
+  /// corrected.
+  pub fn has_sync_issues(&self) -> bool {
+    self.dup_frames > 0
+      || self.total_dropped() > 0
+      || self.non_monotonic_dts > 0
+      || self.past_duration > 0
+  }
+
+  pub fn handle_event(&mut self, event: &FfmpegEvent) {
+    match event {
+      FfmpegEvent::Progress(progress) => {
+        self.dup_frames = progress.dup_frames;
+        self.drop_frames = progress.drop_frames;
+      }
+      FfmpegEvent::SyncWarning(warning) => match warning.kind {
+        SyncWarning::NonMonotonicDts { .. } => self.non_monotonic_dts += 1,
+        SyncWarning::PastDuration { .. } => self.past_duration += 1,
+        SyncWarning::FrameDropped { count } => self.device_drop_frames += count,
+      },
+      FfmpegEvent::Interactive => self.interactive = true,
+      FfmpegEvent::EncodeSummary(summary) => self.encode = Some(summary.clone()),
+      FfmpegEvent::EncoderStats(stats) => self.encoder_stats.push(stats.clone()),
+      FfmpegEvent::InvalidOption { option, message } if self.invalid_option.is_none() => {
+        self.invalid_option = Some(InvalidOption {
+          option: option.clone(),
+          message: message.clone(),
+        });
+      }
+      FfmpegEvent::Error(message)
+      | FfmpegEvent::Log(LogLevel::Error | LogLevel::Fatal, message) => {
+        self.error_count += 1;
+        if self.error_messages.len() < MAX_ERROR_MESSAGES {
+          self.error_messages.push(message.clone());
+        }
+        if is_decode_error(message) {
+          self.decode_errors += 1;
+        }
+        if let Some(kind) = FfmpegErrorKind::classify(message) {
+          if !self.errors.contains(&kind) {
+            self.errors.push(kind);
+          }
+        }
+      }
+      _ => {}
+    }
+  }
+}
@@ -0,0 +1,211 @@
+//! Scratch files for intermediate artifacts, like the parts of a
+//! [`cut`](crate::cut::cut), kept in one place so they don't litter the disk.
+
+use std::{
+  collections::HashSet,
+  fs, io,
+  path::{Path, PathBuf},
+  sync::{
+    atomic::{AtomicU64, Ordering},
+    Arc, Mutex, OnceLock,
+  },
+  time::{Duration, SystemTime},
+};
+
+/// The name of the subdirectory of the system temp directory used by
+/// [`TempRegistry::global`].
+pub const TEMP_DIRNAME: &str = "ffmpeg-sidecar";
+
+/// How old leftovers have to be before the startup sweep removes them.
+pub const DEFAULT_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Creates and tracks scratch files and directories under a root directory.
+///
+/// Each one is removed when its [`TempFile`] is dropped, e.g. with the
+/// [`FfmpegChild`](crate::child::FfmpegChild) owning it, or by
+/// [`TempRegistry::cleanup_all`]. Files left behind by a crashed process are
+/// removed by the sweep when a registry with the same root is created, once
+/// they're older than its `max_age`. Since that's the only way to tell, the
+/// age should be longer than any job using the files.
+///
+/// Names only contain the characters `[A-Za-z0-9._-]`, so they don't need
+/// escaping in filtergraphs, unlike the root itself on Windows (`C:\`).
+///
+/// Clones share the same tracked files.
+///
+/// ```rust,no_run
+/// use ffmpeg_sidecar::temp::TempRegistry;
+///
+/// let list = TempRegistry::global().create_file("concat", "txt").unwrap();
+/// std::fs::write(list.path(), "file 'a.ts'\nfile 'b.ts'\n").unwrap();
+/// // `list` is removed when dropped
+/// ```
+#[derive(Debug, Clone)]
+pub struct TempRegistry {
+  root: Arc<PathBuf>,
+  tracked: Arc<Mutex<HashSet<PathBuf>>>,
+}
+
+impl TempRegistry {
+  /// A registry for the files under `root`, removing the leftovers older
+  /// than `max_age` first. `root` is created with the first file.
+  pub fn new<P: Into<PathBuf>>(root: P, max_age: Duration) -> Self {
+    let registry = Self {
+      root: Arc::new(root.into()),
+      tracked: Arc::default(),
+    };
+    registry.sweep(max_age);
+    registry
+  }
+
+  /// The registry used by the helpers of this crate, under
+  /// [`TEMP_DIRNAME`] in the system temp directory, swept of files older than
+  /// [`DEFAULT_MAX_AGE`] when first used.
+  pub fn global() -> &'static TempRegistry {
+    static GLOBAL: OnceLock<TempRegistry> = OnceLock::new();
+    GLOBAL
+      .get_or_init(|| TempRegistry::new(std::env::temp_dir().join(TEMP_DIRNAME), DEFAULT_MAX_AGE))
+  }
+
+  pub fn root(&self) -> &Path {
+    &self.root
+  }
+
+  /// Create an empty file named after `label`, with the given `extension`
+  /// (which may be empty).
+  pub fn create_file(&self, label: &str, extension: &str) -> io::Result<TempFile> {
+    let path = self.unique_path(label, extension)?;
+    fs::OpenOptions::new()
+      .write(true)
+      .create_new(true)
+      .open(&path)?;
+    Ok(self.track(path))
+  }
+
+  /// Create an empty directory named after `label`. Everything in it is
+  /// removed along with it.
+  pub fn create_dir(&self, label: &str) -> io::Result<TempFile> {
+    let path = self.unique_path(label, "")?;
+    fs::create_dir(&path)?;
+    Ok(self.track(path))
+  }
+
+  /// Remove every file and directory created by this registry (and its
+  /// clones) which hasn't been removed yet, e.g. before exiting after a job
+  /// was aborted. Returns the first error, after trying all of them.
+  pub fn cleanup_all(&self) -> io::Result<()> {
+    let paths = match self.tracked.lock() {
+      Ok(mut tracked) => tracked.drain().collect::<Vec<_>>(),
+      Err(_) => return Ok(()),
+    };
+    let mut result = Ok(());
+    for path in paths {
+      if let Err(e) = remove_path(&path) {
+        if e.kind() != io::ErrorKind::NotFound && result.is_ok() {
+          result = Err(e);
+        }
+      }
+    }
+    result
+  }
+
+  /// Remove the entries of the root directory older than `max_age` which
+  /// aren't tracked, returning how many were removed.
+  pub fn sweep(&self, max_age: Duration) -> usize {
+    let Ok(entries) = fs::read_dir(self.root.as_path()) else {
+      return 0;
+    };
+    let now = SystemTime::now();
+    let mut removed = 0;
+    for entry in entries.flatten() {
+      let path = entry.path();
+      let expired = entry
+        .metadata()
+        .and_then(|metadata| metadata.modified())
+        .is_ok_and(|modified| now.duration_since(modified).unwrap_or_default() > max_age);
+      if expired && !self.is_tracked(&path) && remove_path(&path).is_ok() {
+        removed += 1;
+      }
+    }
+    removed
+  }
+
+  fn unique_path(&self, label: &str, extension: &str) -> io::Result<PathBuf> {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    fs::create_dir_all(self.root.as_path())?;
+    let mut name = format!(
+      "{}-{}-{}",
+      std::process::id(),
+      COUNTER.fetch_add(1, Ordering::Relaxed),
+      sanitize(label)
+    );
+    if !extension.is_empty() {
+      name.push('.');
+      name.push_str(&sanitize(extension));
+    }
+    Ok(self.root.join(name))
+  }
+
+  fn track(&self, path: PathBuf) -> TempFile {
+    if let Ok(mut tracked) = self.tracked.lock() {
+      tracked.insert(path.clone());
+    }
+    TempFile {
+      path,
+      registry: self.clone(),
+    }
+  }
+
+  fn is_tracked(&self, path: &Path) -> bool {
+    self
+      .tracked
+      .lock()
+      .is_ok_and(|tracked| tracked.contains(path))
+  }
+}
+
+/// A scratch file or directory of a [`TempRegistry`], removed when dropped.
+#[derive(Debug)]
+pub struct TempFile {
+  path: PathBuf,
+  registry: TempRegistry,
+}
+
+impl TempFile {
+  pub fn path(&self) -> &Path {
+    &self.path
+  }
+}
+
+impl Drop for TempFile {
+  fn drop(&mut self) {
+    let tracked = match self.registry.tracked.lock() {
+      Ok(mut tracked) => tracked.remove(&self.path),
+      Err(_) => true,
+    };
+    // Already removed by `cleanup_all` otherwise
+    if tracked {
+      remove_path(&self.path).ok();
+    }
+  }
+}
+
+fn remove_path(path: &Path) -> io::Result<()> {
+  if fs::symlink_metadata(path)?.is_dir() {
+    fs::remove_dir_all(path)
+  } else {
+    fs::remove_file(path)
+  }
+}
+
+/// Replace the characters which may need escaping, in a filtergraph or on
+/// some filesystems.
+fn sanitize(name: &str) -> String {
+  name
+    .chars()
+    .map(|c| match c {
+      'a'..='z' | 'A'..='Z' | '0'..='9' | '_' | '-' => c,
+      _ => '_',
+    })
+    .collect()
+}
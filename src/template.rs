@@ -0,0 +1,77 @@
+//! Reusing the same ffmpeg settings for many inputs and outputs.
+
+use std::{ffi::OsStr, fmt};
+
+use crate::command::FfmpegCommand;
+
+/// A command with named placeholders for its input and output paths, which
+/// can be instantiated any number of times.
+///
+/// Only the arguments added with
+/// [`FfmpegCommand::input_placeholder`] and
+/// [`FfmpegCommand::output_placeholder`] are substituted, so other arguments
+/// which happen to contain the placeholder text are left alone.
+///
+/// ```rust
+/// use ffmpeg_sidecar::{command::FfmpegCommand, template::CommandTemplate};
+///
+/// let template = CommandTemplate::from(
+///   FfmpegCommand::new()
+///     .input_placeholder("in")
+///     .codec_video("libx264")
+///     .overwrite()
+///     .output_placeholder("out")
+///     .clone(),
+/// );
+///
+/// let command = template.instantiate(&[("in", "a.mov"), ("out", "a.mp4")]);
+/// let args = command.get_args().filter_map(|arg| arg.to_str()).collect::<Vec<_>>();
+/// assert!(args.ends_with(&["-i", "a.mov", "-c:v", "libx264", "-y", "a.mp4"]));
+/// ```
+#[derive(Clone)]
+pub struct CommandTemplate {
+  command: FfmpegCommand,
+}
+
+impl CommandTemplate {
+  /// A new command with each placeholder replaced by its value in `values`.
+  ///
+  /// ## Panics
+  ///
+  /// Panics if `values` doesn't include every placeholder of the template.
+  pub fn instantiate<S: AsRef<OsStr>>(&self, values: &[(&str, S)]) -> FfmpegCommand {
+    let placeholders = self.command.placeholders();
+    let args = self.command.get_args().enumerate().map(|(index, arg)| {
+      let Some((_, name)) = placeholders.iter().find(|(i, _)| *i == index) else {
+        return arg;
+      };
+      values
+        .iter()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value.as_ref())
+        .unwrap_or_else(|| panic!("missing value for placeholder `{name}`"))
+    });
+    self.command.with_args(args.collect::<Vec<_>>())
+  }
+
+  /// Names of the placeholders, in the order they appear in the arguments.
+  pub fn placeholders(&self) -> impl Iterator<Item = &str> {
+    self
+      .command
+      .placeholders()
+      .iter()
+      .map(|(_, name)| name.as_str())
+  }
+}
+
+impl From<FfmpegCommand> for CommandTemplate {
+  fn from(command: FfmpegCommand) -> Self {
+    Self { command }
+  }
+}
+
+impl fmt::Debug for CommandTemplate {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    self.command.fmt(f)
+  }
+}
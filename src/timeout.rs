@@ -0,0 +1,335 @@
+//! Aborting ffmpeg when it never starts producing output, or waits for an
+//! answer which never comes.
+//!
+//! A wedged hardware decoder, or an input that never delivers any data, can
+//! leave ffmpeg waiting forever before its first frame. See
+//! [`FfmpegCommand::first_output_timeout`](crate::command::FfmpegCommand::first_output_timeout).
+//! A question on stderr, like whether to overwrite an output, blocks it until
+//! the answer is read from stdin. See
+//! [`FfmpegChild::prompt_timeout`](crate::child::FfmpegChild::prompt_timeout).
+
+use std::{
+  fmt,
+  io::BufReader,
+  process::{ChildStderr, Command, Stdio},
+  sync::{mpsc::Sender, Arc, Condvar, Mutex},
+  time::{Duration, Instant},
+};
+
+use crate::{
+  event::FfmpegEvent, log_parser::FfmpegLogParser, pipe::StderrTail, spawner::Killer,
+  stderr_policy::StderrFilter,
+};
+
+/// Returned by [`FfmpegChild::wait`](crate::child::FfmpegChild::wait) (as the
+/// inner error of an `io::Error` of kind `TimedOut`) when the process was
+/// killed for not producing any output within its `first_output_timeout`.
+///
+/// ```rust,no_run
+/// use ffmpeg_sidecar::{command::FfmpegCommand, timeout::NoOutputWithinTimeout};
+/// use std::time::Duration;
+///
+/// let mut child = FfmpegCommand::new()
+///   .input("rtsp://camera.local/stream")
+///   .rawvideo()
+///   .first_output_timeout(Duration::from_secs(10))
+///   .spawn()
+///   .unwrap();
+///
+/// if let Err(e) = child.wait() {
+///   if let Some(timeout) = e.get_ref().and_then(|e| e.downcast_ref::<NoOutputWithinTimeout>()) {
+///     eprintln!("{timeout}");
+///   }
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NoOutputWithinTimeout {
+  pub timeout: Duration,
+  /// The lines ffmpeg logged before it was killed, which usually explain what
+  /// it was waiting for.
+  pub stderr: Vec<String>,
+}
+
+impl fmt::Display for NoOutputWithinTimeout {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "ffmpeg produced no output within {:?} and was killed",
+      self.timeout
+    )?;
+    for line in &self.stderr {
+      write!(f, "\n{line}")?;
+    }
+    Ok(())
+  }
+}
+
+impl std::error::Error for NoOutputWithinTimeout {}
+
+/// The default of [`FfmpegChild::prompt_timeout`](crate::child::FfmpegChild::prompt_timeout).
+pub const DEFAULT_PROMPT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Returned by [`FfmpegChild::wait`](crate::child::FfmpegChild::wait) (as the
+/// inner error of an `io::Error` of kind `TimedOut`) when the process was
+/// killed because a [`FfmpegEvent::Prompt`] wasn't answered with
+/// [`FfmpegChild::reply_to_prompt`](crate::child::FfmpegChild::reply_to_prompt)
+/// within the `prompt_timeout`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnansweredPrompt {
+  /// The question ffmpeg asked.
+  pub text: String,
+  pub timeout: Duration,
+}
+
+impl fmt::Display for UnansweredPrompt {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "ffmpeg asked {:?} and was killed after getting no answer within {:?}",
+      self.text, self.timeout
+    )
+  }
+}
+
+impl std::error::Error for UnansweredPrompt {}
+
+#[derive(Default)]
+struct PromptState {
+  asked: u64,
+  answered: u64,
+  exited: bool,
+  unanswered: Option<UnansweredPrompt>,
+}
+
+/// Shared between the child, which answers the prompts, and its iterator,
+/// which starts a timer thread for each prompt it returns.
+pub(crate) struct PromptWatch {
+  killer: Killer,
+  timeout: Duration,
+  state: Mutex<PromptState>,
+  changed: Condvar,
+}
+
+impl PromptWatch {
+  pub(crate) fn new(killer: Killer, timeout: Duration) -> Arc<Self> {
+    Arc::new(Self {
+      killer,
+      timeout,
+      state: Mutex::new(PromptState::default()),
+      changed: Condvar::new(),
+    })
+  }
+
+  /// Kill the process unless the prompt is answered within the timeout.
+  pub(crate) fn asked(self: &Arc<Self>, text: &str) {
+    let Ok(mut state) = self.state.lock() else {
+      return;
+    };
+    state.asked += 1;
+    let (prompt, text) = (state.asked, text.to_string());
+    let watch = self.clone();
+    std::thread::spawn(move || watch.run_timer(prompt, text));
+  }
+
+  /// Called once an answer was written to stdin.
+  pub(crate) fn answered(&self) {
+    if let Ok(mut state) = self.state.lock() {
+      state.answered = state.asked;
+      self.changed.notify_all();
+    }
+  }
+
+  /// Stop the timers after the process was waited on, so its pid is never
+  /// killed after it could have been reused.
+  pub(crate) fn exited(&self) {
+    if let Ok(mut state) = self.state.lock() {
+      state.exited = true;
+      self.changed.notify_all();
+    }
+  }
+
+  /// The error to report, if the process was killed by a timer.
+  pub(crate) fn error(&self) -> Option<UnansweredPrompt> {
+    self.state.lock().ok()?.unanswered.clone()
+  }
+
+  fn run_timer(&self, prompt: u64, text: String) {
+    let deadline = Instant::now() + self.timeout;
+    let Ok(mut state) = self.state.lock() else {
+      return;
+    };
+    while state.answered < prompt && !state.exited {
+      let now = Instant::now();
+      if now >= deadline {
+        state.unanswered = Some(UnansweredPrompt {
+          text,
+          timeout: self.timeout,
+        });
+        self.killer.kill();
+        return;
+      }
+      state = match self.changed.wait_timeout(state, deadline - now) {
+        Ok((state, _)) => state,
+        Err(_) => return,
+      };
+    }
+  }
+}
+
+#[derive(Default)]
+struct WatchdogState {
+  output_seen: bool,
+  exited: bool,
+  timed_out: bool,
+  stderr: Vec<String>,
+}
+
+/// Shared between the child, its iterator, the thread reading stderr, and the
+/// timer thread which kills the process once the timeout elapses.
+pub(crate) struct OutputWatchdog {
+  timeout: Duration,
+  state: Mutex<WatchdogState>,
+  changed: Condvar,
+}
+
+impl OutputWatchdog {
+  /// Start the timer for the process killed by `killer`, and read its
+  /// stderr on a background thread. Stderr has to be read from the start,
+  /// since progress events are the only sign of output when stdout isn't
+  /// piped. Parsed events are sent to `tx` for the iterator to pick up later.
+  pub(crate) fn spawn(
+    killer: Killer,
+    timeout: Duration,
+    stderr: ChildStderr,
+    tx: Sender<FfmpegEvent>,
+    tail: StderrTail,
+    filter: StderrFilter,
+  ) -> Arc<Self> {
+    let watchdog = Arc::new(Self {
+      timeout,
+      state: Mutex::new(WatchdogState::default()),
+      changed: Condvar::new(),
+    });
+
+    let timer = watchdog.clone();
+    std::thread::spawn(move || timer.run_timer(killer));
+
+    let reader = watchdog.clone();
+    std::thread::spawn(move || {
+      let mut parser = FfmpegLogParser::new(BufReader::new(stderr));
+      parser.set_filter(filter);
+      loop {
+        match parser.parse_next_event() {
+          Ok(FfmpegEvent::LogEOF) => {
+            // Stderr closes as the process exits, which may be reaped
+            // elsewhere, so the timer must not kill its pid after that
+            reader.exited();
+            if let Some(error) = reader.error() {
+              tx.send(FfmpegEvent::Error(error.to_string())).ok();
+            }
+            tx.send(FfmpegEvent::LogEOF).ok();
+            break;
+          }
+          Ok(event) => {
+            if let Some(line) = event.raw_log_message() {
+              tail.push(line);
+            }
+            reader.observe(&event);
+            tx.send(event).ok();
+          }
+          Err(e) => {
+            eprintln!("Error parsing ffmpeg output: {}", e);
+            break;
+          }
+        }
+      }
+    });
+
+    watchdog
+  }
+
+  /// Record a log line, or stop the timer if the event shows output.
+  pub(crate) fn observe(&self, event: &FfmpegEvent) {
+    let Ok(mut state) = self.state.lock() else {
+      return;
+    };
+    if state.output_seen {
+      return;
+    }
+    match event {
+      FfmpegEvent::Progress(_) | FfmpegEvent::OutputFrame(_) | FfmpegEvent::OutputChunk(_) => {
+        state.output_seen = true;
+        state.stderr.clear();
+        self.changed.notify_all();
+      }
+      event => {
+        if let Some(line) = event.raw_log_message() {
+          state.stderr.push(line.to_string());
+        }
+      }
+    }
+  }
+
+  /// Stop the timer after the process was waited on, so its pid is never
+  /// killed after it could have been reused.
+  pub(crate) fn exited(&self) {
+    if let Ok(mut state) = self.state.lock() {
+      state.exited = true;
+      self.changed.notify_all();
+    }
+  }
+
+  /// The error to report, if the process was killed by the timer.
+  pub(crate) fn error(&self) -> Option<NoOutputWithinTimeout> {
+    let state = self.state.lock().ok()?;
+    state.timed_out.then(|| NoOutputWithinTimeout {
+      timeout: self.timeout,
+      stderr: state.stderr.clone(),
+    })
+  }
+
+  fn run_timer(&self, killer: Killer) {
+    let deadline = Instant::now() + self.timeout;
+    let Ok(mut state) = self.state.lock() else {
+      return;
+    };
+    while !state.output_seen && !state.exited {
+      let now = Instant::now();
+      if now >= deadline {
+        state.timed_out = true;
+        killer.kill();
+        return;
+      }
+      state = match self.changed.wait_timeout(state, deadline - now) {
+        Ok((state, _)) => state,
+        Err(_) => return,
+      };
+    }
+  }
+}
+
+/// `std::process::Child::kill` needs the `Child` itself, which is owned by the
+/// caller, so the timer kills the process by its pid instead.
+pub(crate) fn kill_process(pid: u32, process_group: bool) {
+  let mut command = if cfg!(windows) {
+    let mut command = Command::new("taskkill");
+    command.args(["/F", "/T", "/PID", &pid.to_string()]);
+    command
+  } else {
+    let mut command = Command::new("kill");
+    // A negative pid signals the whole process group
+    let target = if process_group {
+      format!("-{pid}")
+    } else {
+      pid.to_string()
+    };
+    command.args(["-KILL", "--", &target]);
+    command
+  };
+  command
+    .stdin(Stdio::null())
+    .stdout(Stdio::null())
+    .stderr(Stdio::null())
+    .status()
+    .ok();
+}
@@ -0,0 +1,342 @@
+//! Positions and durations on a media timeline, as ffmpeg reads and writes
+//! them.
+
+use std::{
+  borrow::Cow,
+  cmp::Ordering,
+  fmt,
+  str::FromStr,
+  time::{Duration, TryFromFloatSecsError},
+};
+
+/// A position or a duration, which unlike [`Duration`] can be negative, as
+/// for `-itsoffset` or `-sseof`.
+///
+/// Parsed from ffmpeg's [time duration
+/// syntax](https://ffmpeg.org/ffmpeg-utils.html#time-duration-syntax):
+/// `[-][HH:]MM:SS[.m...]`, where the hours can go past 24, or
+/// `[-]S+[.m...][s|ms|us]`. A comma is accepted as the decimal separator, as
+/// in the logs of some locales. Displayed as `[-]HH:MM:SS[.m...]`, to the
+/// microsecond like ffmpeg.
+///
+/// ```rust
+/// use std::time::Duration;
+/// use ffmpeg_sidecar::timestamp::Timestamp;
+///
+/// let timestamp = "1:02:03.5".parse::<Timestamp>().unwrap();
+/// assert_eq!(timestamp.as_secs_f64(), 3723.5);
+/// assert_eq!(timestamp.to_string(), "01:02:03.5");
+/// assert_eq!("75".parse::<Timestamp>().unwrap().to_string(), "00:01:15");
+/// assert_eq!("1.5s".parse::<Timestamp>().unwrap(), Timestamp::from_secs_f64(1.5));
+/// assert_eq!("250ms".parse::<Timestamp>().unwrap(), Duration::from_millis(250).into());
+/// assert_eq!("-0.5".parse::<Timestamp>().unwrap().to_string(), "-00:00:00.5");
+/// assert!(Duration::try_from(Timestamp::from_secs_f64(-0.5)).is_err());
+/// assert!(Timestamp::try_from(f64::NAN).is_err());
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(
+  feature = "serde",
+  derive(serde::Serialize, serde::Deserialize),
+  serde(try_from = "f64", into = "f64")
+)]
+pub struct Timestamp {
+  /// Never set for zero, so there's a single zero
+  negative: bool,
+  magnitude: Duration,
+}
+
+impl Timestamp {
+  pub const ZERO: Timestamp = Timestamp {
+    negative: false,
+    magnitude: Duration::ZERO,
+  };
+
+  /// `magnitude` before zero, or after it.
+  pub fn new(negative: bool, magnitude: Duration) -> Self {
+    Self {
+      negative: negative && !magnitude.is_zero(),
+      magnitude,
+    }
+  }
+
+  /// Panics if `seconds` isn't finite, or doesn't fit in a [`Duration`],
+  /// see [`try_from_secs_f64`](Self::try_from_secs_f64).
+  pub fn from_secs_f64(seconds: f64) -> Self {
+    Self::try_from_secs_f64(seconds).unwrap_or_else(|e| panic!("{e}"))
+  }
+
+  /// Fails if `seconds` isn't finite, or doesn't fit in a [`Duration`].
+  pub fn try_from_secs_f64(seconds: f64) -> Result<Self, TryFromFloatSecsError> {
+    let magnitude = Duration::try_from_secs_f64(seconds.abs())?;
+    Ok(Self::new(seconds.is_sign_negative(), magnitude))
+  }
+
+  pub fn as_secs_f64(&self) -> f64 {
+    match self.negative {
+      true => -self.magnitude.as_secs_f64(),
+      false => self.magnitude.as_secs_f64(),
+    }
+  }
+
+  pub fn is_negative(&self) -> bool {
+    self.negative
+  }
+
+  /// The distance from zero, before or after it.
+  pub fn magnitude(&self) -> Duration {
+    self.magnitude
+  }
+
+  fn parse(s: &str) -> Option<Self> {
+    let (negative, s) = match s.strip_prefix('-') {
+      Some(rest) => (true, rest),
+      None => (false, s),
+    };
+    let s = s.replacen(',', ".", 1);
+    let magnitude = match s.contains(':') {
+      true => parse_clock(&s)?,
+      false => parse_seconds(&s)?,
+    };
+    Some(Self::new(negative, magnitude))
+  }
+}
+
+/// `[HH:]MM:SS[.m...]`
+fn parse_clock(s: &str) -> Option<Duration> {
+  let parts = s.split(':').collect::<Vec<_>>();
+  let (hours, minutes, seconds) = match parts[..] {
+    [hours, minutes, seconds] => (parse_digits(hours)?, minutes, seconds),
+    [minutes, seconds] => (0, minutes, seconds),
+    _ => return None,
+  };
+  let minutes = Some(minutes)
+    .filter(|minutes| minutes.len() <= 2)
+    .and_then(parse_digits)
+    .filter(|minutes| *minutes < 60)?;
+  let (whole, nanos) = parse_decimal(seconds)?;
+  if whole >= 60 || seconds.split('.').next()?.len() > 2 {
+    return None;
+  }
+  let seconds = hours.checked_mul(3600)?.checked_add(minutes * 60 + whole)?;
+  Some(Duration::new(seconds, nanos))
+}
+
+/// `S+[.m...][s|ms|us]`
+fn parse_seconds(s: &str) -> Option<Duration> {
+  let (number, divisor) = match s {
+    _ if s.ends_with("ms") => (&s[..s.len() - 2], 1_000),
+    _ if s.ends_with("us") => (&s[..s.len() - 2], 1_000_000),
+    _ => (s.strip_suffix('s').unwrap_or(s), 1),
+  };
+  let (whole, nanos) = parse_decimal(number)?;
+  let total = (u128::from(whole) * 1_000_000_000 + u128::from(nanos)) / divisor;
+  let seconds = u64::try_from(total / 1_000_000_000).ok()?;
+  Some(Duration::new(seconds, (total % 1_000_000_000) as u32))
+}
+
+/// The whole part and the nanoseconds of `S+[.m...]`, ignoring the digits
+/// past the nanoseconds.
+fn parse_decimal(s: &str) -> Option<(u64, u32)> {
+  let (whole, fraction) = s.split_once('.').unwrap_or((s, ""));
+  if !fraction.chars().all(|c| c.is_ascii_digit()) {
+    return None;
+  }
+  let digits = &fraction[..fraction.len().min(9)];
+  let nanos = format!("{digits:0<9}").parse().ok()?;
+  Some((parse_digits(whole)?, nanos))
+}
+
+fn parse_digits(s: &str) -> Option<u64> {
+  match !s.is_empty() && s.chars().all(|c| c.is_ascii_digit()) {
+    true => s.parse().ok(),
+    false => None,
+  }
+}
+
+impl Ord for Timestamp {
+  fn cmp(&self, other: &Self) -> Ordering {
+    match (self.negative, other.negative) {
+      (false, false) => self.magnitude.cmp(&other.magnitude),
+      (true, true) => other.magnitude.cmp(&self.magnitude),
+      (true, false) => Ordering::Less,
+      (false, true) => Ordering::Greater,
+    }
+  }
+}
+
+impl PartialOrd for Timestamp {
+  fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+    Some(self.cmp(other))
+  }
+}
+
+impl fmt::Display for Timestamp {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let seconds = self.magnitude.as_secs();
+    if self.negative {
+      f.write_str("-")?;
+    }
+    write!(
+      f,
+      "{:02}:{:02}:{:02}",
+      seconds / 3600,
+      seconds / 60 % 60,
+      seconds % 60
+    )?;
+    match self.magnitude.subsec_micros() {
+      0 => Ok(()),
+      micros => write!(f, ".{}", format!("{micros:06}").trim_end_matches('0')),
+    }
+  }
+}
+
+impl FromStr for Timestamp {
+  type Err = InvalidTimestamp;
+
+  fn from_str(s: &str) -> Result<Self, Self::Err> {
+    Timestamp::parse(s.trim()).ok_or_else(|| InvalidTimestamp(s.to_string()))
+  }
+}
+
+impl From<Duration> for Timestamp {
+  fn from(duration: Duration) -> Self {
+    Self::new(false, duration)
+  }
+}
+
+/// See [`Timestamp::try_from_secs_f64`].
+impl TryFrom<f64> for Timestamp {
+  type Error = TryFromFloatSecsError;
+
+  fn try_from(seconds: f64) -> Result<Self, Self::Error> {
+    Self::try_from_secs_f64(seconds)
+  }
+}
+
+impl TryFrom<&str> for Timestamp {
+  type Error = InvalidTimestamp;
+
+  fn try_from(s: &str) -> Result<Self, Self::Error> {
+    s.parse()
+  }
+}
+
+impl From<Timestamp> for f64 {
+  fn from(timestamp: Timestamp) -> Self {
+    timestamp.as_secs_f64()
+  }
+}
+
+impl TryFrom<Timestamp> for Duration {
+  type Error = NegativeTimestamp;
+
+  fn try_from(timestamp: Timestamp) -> Result<Self, Self::Error> {
+    match timestamp.negative {
+      true => Err(NegativeTimestamp(timestamp)),
+      false => Ok(timestamp.magnitude),
+    }
+  }
+}
+
+/// The value of a time option like `-ss` or `-t`, taken by the builder
+/// methods such as [`FfmpegCommand::seek`](crate::command::FfmpegCommand::seek).
+///
+/// Text is passed to ffmpeg verbatim rather than parsed, so ffmpeg reports it
+/// if it isn't in the [time duration
+/// syntax](https://ffmpeg.org/ffmpeg-utils.html#time-duration-syntax).
+///
+/// ```rust
+/// use std::time::Duration;
+/// use ffmpeg_sidecar::timestamp::TimeArg;
+///
+/// assert_eq!(TimeArg::from(Duration::from_millis(2500)).to_string(), "00:00:02.5");
+/// assert_eq!(TimeArg::from(-0.5).to_string(), "-00:00:00.5");
+/// assert_eq!(TimeArg::from(f64::NAN).to_string(), "NaN");
+/// assert_eq!(TimeArg::from("1:30").to_string(), "1:30");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TimeArg {
+  Timestamp(Timestamp),
+  Text(String),
+}
+
+impl fmt::Display for TimeArg {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      TimeArg::Timestamp(timestamp) => timestamp.fmt(f),
+      TimeArg::Text(text) => f.write_str(text),
+    }
+  }
+}
+
+impl From<Timestamp> for TimeArg {
+  fn from(timestamp: Timestamp) -> Self {
+    TimeArg::Timestamp(timestamp)
+  }
+}
+
+impl From<Duration> for TimeArg {
+  fn from(duration: Duration) -> Self {
+    TimeArg::Timestamp(duration.into())
+  }
+}
+
+/// Seconds which aren't finite or don't fit in a [`Duration`] are passed to
+/// ffmpeg as text, which it rejects.
+impl From<f64> for TimeArg {
+  fn from(seconds: f64) -> Self {
+    match Timestamp::try_from_secs_f64(seconds) {
+      Ok(timestamp) => TimeArg::Timestamp(timestamp),
+      Err(_) => TimeArg::Text(seconds.to_string()),
+    }
+  }
+}
+
+impl From<&str> for TimeArg {
+  fn from(text: &str) -> Self {
+    TimeArg::Text(text.to_string())
+  }
+}
+
+impl From<String> for TimeArg {
+  fn from(text: String) -> Self {
+    TimeArg::Text(text)
+  }
+}
+
+impl From<&String> for TimeArg {
+  fn from(text: &String) -> Self {
+    TimeArg::Text(text.clone())
+  }
+}
+
+impl From<Cow<'_, str>> for TimeArg {
+  fn from(text: Cow<'_, str>) -> Self {
+    TimeArg::Text(text.into_owned())
+  }
+}
+
+/// Returned when parsing a [`Timestamp`] fails, with the text which couldn't
+/// be parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidTimestamp(pub String);
+
+impl fmt::Display for InvalidTimestamp {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "invalid timestamp: {:?}", self.0)
+  }
+}
+
+impl std::error::Error for InvalidTimestamp {}
+
+/// Returned when converting a negative [`Timestamp`] to a [`Duration`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NegativeTimestamp(pub Timestamp);
+
+impl fmt::Display for NegativeTimestamp {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "negative timestamp {} isn't a duration", self.0)
+  }
+}
+
+impl std::error::Error for NegativeTimestamp {}
@@ -1,26 +1,54 @@
+//! The version of the ffmpeg binary, and the options which need a recent
+//! one.
+
+#[cfg(feature = "process")]
 use anyhow::Context;
 
+#[cfg(feature = "process")]
 use crate::{
   event::FfmpegEvent,
   log_parser::FfmpegLogParser,
   paths::ffmpeg_path,
+  spawner::{self, StdioConfig},
+  stdio_policy::StdioPolicy,
+};
+#[cfg(feature = "process")]
+use std::{
+  collections::HashMap,
+  ffi::OsStr,
+  path::PathBuf,
+  process::Command,
+  sync::{Mutex, OnceLock},
 };
-use std::ffi::OsStr;
-use std::process::{Command, Stdio};
+
+/// Options added by the typed methods of
+/// [`FfmpegCommand`](crate::command::FfmpegCommand) which older ffmpeg
+/// versions don't recognize, with the `major.minor` version adding them.
+const OPTION_VERSIONS: &[(&str, u32, u32)] = &[
+  ("stats_period", 4, 4),
+  ("fps_mode", 5, 1),
+  ("display_rotation", 6, 0),
+];
 
 /// Alias for `ffmpeg -version`, parsing the version number and returning it.
+#[cfg(feature = "process")]
 pub fn ffmpeg_version() -> anyhow::Result<String> {
   ffmpeg_version_with_path(ffmpeg_path())
 }
 
 /// Lower level variant of `ffmpeg_version` that exposes a customized the path
 /// to the ffmpeg binary.
+#[cfg(feature = "process")]
 pub fn ffmpeg_version_with_path<S: AsRef<OsStr>>(path: S) -> anyhow::Result<String> {
-  let mut cmd = Command::new(&path)
-    .arg("-version")
-    .stdout(Stdio::piped()) // not stderr when calling `-version`
-    .spawn()?;
-  let stdout = cmd.stdout.take().context("No standard output channel")?;
+  // Not stderr when calling `-version`
+  let stdio = StdioConfig {
+    stdin: StdioPolicy::Inherit,
+    stdout: StdioPolicy::Piped,
+    stderr: StdioPolicy::Inherit,
+    ..StdioConfig::default()
+  };
+  let mut cmd = spawner::spawn(Command::new(&path).arg("-version"), stdio)?;
+  let stdout = cmd.take_stdout().context("No standard output channel")?;
   let mut parser = FfmpegLogParser::new(stdout);
 
   let mut version: Option<String> = None;
@@ -37,3 +65,84 @@ pub fn ffmpeg_version_with_path<S: AsRef<OsStr>>(path: S) -> anyhow::Result<Stri
   }
   version.context("Failed to parse ffmpeg version")
 }
+
+/// The versions of the ffmpeg binaries, by path, see [`cached_ffmpeg_version`].
+#[cfg(feature = "process")]
+static VERSIONS: OnceLock<Mutex<HashMap<PathBuf, String>>> = OnceLock::new();
+
+/// The version of the ffmpeg binary at `path`, running `-version` only the
+/// first time for each path. Failures aren't cached.
+#[cfg(feature = "process")]
+pub(crate) fn cached_ffmpeg_version<S: AsRef<OsStr>>(path: S) -> Option<String> {
+  let path = PathBuf::from(path.as_ref());
+  let versions = VERSIONS.get_or_init(Default::default);
+  if let Some(version) = versions
+    .lock()
+    .ok()
+    .and_then(|versions| versions.get(&path).cloned())
+  {
+    return Some(version);
+  }
+
+  let version = ffmpeg_version_with_path(&path).ok()?;
+  if let Ok(mut versions) = versions.lock() {
+    versions.insert(path, version.clone());
+  }
+  Some(version)
+}
+
+/// Forget the version of the binary at `path`, which was just replaced.
+#[cfg(feature = "download")]
+pub(crate) fn forget_cached_ffmpeg_version(path: &std::path::Path) {
+  if let Ok(mut versions) = VERSIONS.get_or_init(Default::default).lock() {
+    versions.remove(path);
+  }
+}
+
+/// Whether a version reported by [`ffmpeg_version`] is `major.minor` or
+/// later. Builds from git, named after a date or a commit (`N-113245-…`,
+/// `2024-03-10-git-…`), are assumed to be recent.
+///
+/// ```rust
+/// use ffmpeg_sidecar::version::version_at_least;
+///
+/// assert!(version_at_least("7.0.1-essentials_build-www.gyan.dev", 4, 4));
+/// assert!(version_at_least("n4.4", 4, 4));
+/// assert!(!version_at_least("4.3.1", 4, 4));
+/// assert!(!version_at_least("3.4.11-0ubuntu0.1", 4, 4));
+/// assert!(version_at_least("N-113245-g0cbb7d0c11-20240110", 4, 4));
+/// ```
+pub fn version_at_least(version: &str, major: u32, minor: u32) -> bool {
+  let version = version.strip_prefix('n').unwrap_or(version);
+  let mut numbers = version
+    .split(|c: char| !c.is_ascii_digit())
+    .map(|number| number.parse::<u32>().ok());
+  let Some(Some(found_major)) = numbers.next() else {
+    return true;
+  };
+  let found_minor = numbers.next().flatten().unwrap_or(0);
+  // Dates in git builds, as in 2024-03-10
+  if found_major >= 1000 {
+    return true;
+  }
+  (found_major, found_minor) >= (major, minor)
+}
+
+/// The first `(major, minor)` version of ffmpeg recognizing `option` (with
+/// or without its leading `-`), if it's one of the options added by this
+/// crate which older versions reject as unrecognized.
+///
+/// ```rust
+/// use ffmpeg_sidecar::version::option_min_version;
+///
+/// assert_eq!(option_min_version("-fps_mode"), Some((5, 1)));
+/// assert_eq!(option_min_version("stats_period"), Some((4, 4)));
+/// assert_eq!(option_min_version("crf"), None);
+/// ```
+pub fn option_min_version(option: &str) -> Option<(u32, u32)> {
+  let option = option.strip_prefix('-').unwrap_or(option);
+  OPTION_VERSIONS
+    .iter()
+    .find(|(name, _, _)| *name == option)
+    .map(|(_, major, minor)| (*major, *minor))
+}
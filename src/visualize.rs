@@ -0,0 +1,149 @@
+//! Rendering audio as video or images, e.g. a waveform video for a podcast
+//! episode or a spectrogram picture.
+
+use crate::{geometry::round_up_even, sanitize::escape_filter_value};
+
+/// The label of the waveform video in the filtergraph added by
+/// [`FfmpegCommand::visualize_waveform`](crate::command::FfmpegCommand::visualize_waveform).
+pub const WAVEFORM_OUTPUT_LABEL: &str = "waveform";
+
+/// The label of the picture in the filtergraph added by
+/// [`FfmpegCommand::spectrogram_image`](crate::command::FfmpegCommand::spectrogram_image).
+pub const SPECTROGRAM_OUTPUT_LABEL: &str = "spectrogram";
+
+/// How `showwaves` draws the samples.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum WaveMode {
+  /// A point for each sample.
+  Point,
+  /// A vertical line from the center for each sample.
+  #[default]
+  Line,
+  /// A point for each sample, joined by lines.
+  P2p,
+  /// A vertical line centered on the middle for each sample.
+  Cline,
+}
+
+impl WaveMode {
+  pub fn as_str(&self) -> &'static str {
+    match self {
+      WaveMode::Point => "point",
+      WaveMode::Line => "line",
+      WaveMode::P2p => "p2p",
+      WaveMode::Cline => "cline",
+    }
+  }
+}
+
+/// Options for [`FfmpegCommand::visualize_waveform`](crate::command::FfmpegCommand::visualize_waveform).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VisualOptions {
+  /// Width and height of the video, rounded up to even numbers for
+  /// `yuv420p`.
+  pub size: (u32, u32),
+  pub mode: WaveMode,
+  /// A color for each channel, e.g. `white` or `0x3366ff`. The last one is
+  /// reused for the remaining channels, and ffmpeg's default is used when
+  /// empty.
+  pub colors: Vec<String>,
+  /// Frame rate of the video.
+  pub rate: u32,
+}
+
+impl Default for VisualOptions {
+  fn default() -> Self {
+    Self {
+      size: (1280, 720),
+      mode: WaveMode::default(),
+      colors: vec!["white".to_string()],
+      rate: 25,
+    }
+  }
+}
+
+/// Options for [`FfmpegCommand::spectrogram_image`](crate::command::FfmpegCommand::spectrogram_image).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpectrogramOptions {
+  /// Width and height of the spectrogram. With `legend`, the axes are drawn
+  /// around it, making the picture larger.
+  pub size: (u32, u32),
+  /// Draw the time and frequency axes and the color scale.
+  pub legend: bool,
+  /// The color scheme of `showspectrumpic`, like `intensity`, `rainbow` or
+  /// `viridis`.
+  pub color: String,
+  /// Show the intensity on a logarithmic scale (`log`), or e.g. `lin` or
+  /// `sqrt`.
+  pub scale: String,
+}
+
+impl Default for SpectrogramOptions {
+  fn default() -> Self {
+    Self {
+      size: (1024, 512),
+      legend: false,
+      color: "intensity".to_string(),
+      scale: "log".to_string(),
+    }
+  }
+}
+
+/// The filter chain which draws the audio stream labeled `input` as a
+/// waveform video labeled `output`, converted to `yuv420p` so it plays
+/// everywhere.
+///
+/// ```rust
+/// use ffmpeg_sidecar::visualize::{waveform_filter, VisualOptions, WaveMode};
+///
+/// let options = VisualOptions {
+///   size: (641, 360),
+///   mode: WaveMode::Cline,
+///   colors: vec!["white".to_string(), "0x3366ff".to_string()],
+///   rate: 30,
+/// };
+/// assert_eq!(
+///   waveform_filter("0:a", "out", &options),
+///   "[0:a]showwaves=s=642x360:mode=cline:rate=30:colors=white|0x3366ff,format=yuv420p[out]"
+/// );
+/// ```
+pub fn waveform_filter(input: &str, output: &str, options: &VisualOptions) -> String {
+  let (width, height) = options.size;
+  let mut showwaves = format!(
+    "showwaves=s={}x{}:mode={}:rate={}",
+    round_up_even(width),
+    round_up_even(height),
+    options.mode.as_str(),
+    options.rate
+  );
+  if !options.colors.is_empty() {
+    let colors = options
+      .colors
+      .iter()
+      .map(|color| escape_filter_value(color))
+      .collect::<Vec<_>>();
+    showwaves.push_str(&format!(":colors={}", colors.join("|")));
+  }
+  format!("[{input}]{showwaves},format=yuv420p[{output}]")
+}
+
+/// The filter chain which draws the whole audio stream labeled `input` as a
+/// single spectrogram picture labeled `output`.
+///
+/// ```rust
+/// use ffmpeg_sidecar::visualize::{spectrogram_filter, SpectrogramOptions};
+///
+/// assert_eq!(
+///   spectrogram_filter("1:a", "out", &SpectrogramOptions::default()),
+///   "[1:a]showspectrumpic=s=1024x512:legend=0:color=intensity:scale=log[out]"
+/// );
+/// ```
+pub fn spectrogram_filter(input: &str, output: &str, options: &SpectrogramOptions) -> String {
+  let (width, height) = options.size;
+  format!(
+    "[{input}]showspectrumpic=s={width}x{height}:legend={}:color={}:scale={}[{output}]",
+    u8::from(options.legend),
+    options.color,
+    options.scale
+  )
+}